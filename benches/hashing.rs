@@ -0,0 +1,61 @@
+//! 比较下载时直接计算哈希值与在单独线程中计算哈希值的速度
+//!
+//! 模拟一次本地的大文件传输：数据块依次写入临时文件，同时计算SHA-512。
+//! 运行`cargo bench --bench hashing`。
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use request_tui::app::checksum::{HashAlgorithm, StreamHasher};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::runtime::Runtime;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const TOTAL_SIZE: usize = 64 * 1024 * 1024;
+
+fn chunks() -> Vec<Bytes> {
+    (0..TOTAL_SIZE / CHUNK_SIZE)
+        .map(|i| Bytes::from(vec![i as u8; CHUNK_SIZE]))
+        .collect()
+}
+
+async fn transfer(path: &Path, chunks: &[Bytes], mut hasher: StreamHasher) -> String {
+    let mut file = BufWriter::new(File::create(path).await.unwrap());
+    for chunk in chunks {
+        file.write_all(chunk).await.unwrap();
+        hasher.update(chunk).await;
+    }
+    file.flush().await.unwrap();
+    hasher.finish().await.unwrap()
+}
+
+fn bench_hashing(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let chunks = chunks();
+    let path: PathBuf = env::temp_dir().join("request-tui-hashing-bench.bin");
+
+    let mut group = c.benchmark_group("sha512_transfer");
+    group.throughput(Throughput::Bytes(TOTAL_SIZE as u64));
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::from_parameter("inline"), |b| {
+        b.iter(|| {
+            let hasher = StreamHasher::inline(HashAlgorithm::Sha512);
+            runtime.block_on(transfer(&path, &chunks, hasher))
+        })
+    });
+    group.bench_function(BenchmarkId::from_parameter("pipelined"), |b| {
+        b.iter(|| {
+            let hasher = StreamHasher::pipelined(HashAlgorithm::Sha512, runtime.handle());
+            runtime.block_on(transfer(&path, &chunks, hasher))
+        })
+    });
+    group.finish();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_hashing);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+use std::path::Path;
+use std::process::Command;
+
+/// 在编译时记录当前的git提交，显示在About弹窗中，见`app::about::BuildInfo`
+///
+/// 不在git仓库中（例如从发布的源码包编译）或者没有安装git时记录为`unknown`。
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=REQUEST_TUI_GIT_HASH={}", hash);
+
+    // 切换分支或者提交之后重新运行，监视不存在的文件会导致每次编译都重新运行
+    println!("cargo:rerun-if-changed=build.rs");
+    let head = Path::new(".git/HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        if let Ok(content) = std::fs::read_to_string(head)
+            && let Some(reference) = content.trim().strip_prefix("ref: ")
+            && Path::new(".git").join(reference).exists()
+        {
+            println!("cargo:rerun-if-changed=.git/{}", reference);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! 向正在运行的request-tui发送命令，需要开启`control`特性
+//!
+//! ```text
+//! cargo run --features control --example request-tui-ctl -- add https://example.com/file.zip
+//! cargo run --features control --example request-tui-ctl -- list
+//! ```
+
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+
+use request_tui::app::control::{ControlCommand, ControlServer};
+use request_tui::app::task::TaskId;
+
+const USAGE: &str = "\
+Usage: request-tui-ctl <COMMAND>
+
+Commands:
+  add <URL>    Add a download
+  list         List running and pending tasks
+  stop <ID>    Stop a task
+  status       Show a summary of all tasks";
+
+pub fn parse(args: &[String]) -> anyhow::Result<ControlCommand> {
+    let command = match args {
+        [cmd, url] if cmd == "add" => ControlCommand::Add { url: url.clone() },
+        [cmd] if cmd == "list" => ControlCommand::List,
+        [cmd, id] if cmd == "stop" => ControlCommand::Stop {
+            id: TaskId::parse(id).ok_or(anyhow::anyhow!("Invalid task id: {}", id))?,
+        },
+        [cmd] if cmd == "status" => ControlCommand::Status,
+        _ => return Err(anyhow::anyhow!("{}", USAGE)),
+    };
+    Ok(command)
+}
+
+/// 发送一个命令，返回一行JSON格式的回复
+pub fn request<S: Read + Write>(mut stream: S, command: &ControlCommand) -> anyhow::Result<String> {
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = parse(&args)?;
+
+    let stream = ControlServer::connect()
+        .map_err(|e| anyhow::anyhow!("Failed to connect to request-tui: {}", e))?;
+    print!("{}", request(stream, &command)?);
+    Ok(())
+}
@@ -1,27 +1,83 @@
-use std::io::{self, Stdout};
-use std::time::Duration;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc::Receiver, mpsc::TryRecvError};
+use std::time::{Duration, Instant};
 
-use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::style::palette::tailwind;
 use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
 use ratatui::{Terminal, widgets::Widget};
-use tokio::sync::mpsc;
 
-use crate::app::task::Task;
-use crate::window::app::{DownloadList, FinishList, PageList};
-use crate::window::common::Fill;
+use crate::app::about::BuildInfo;
+use crate::app::config::{Config, LiveConfig};
+use crate::app::input::{EventSource, UnfocusedKeys};
+use crate::app::instance::{InstanceConflict, InstanceLock, LockAttempt};
+use crate::app::listener::TaskListener;
+use crate::app::notice::NoticeBoard;
+use crate::app::permissions::FileModes;
+use crate::app::record::SessionSummary;
+use crate::app::session::{Session, UiState};
+use crate::app::stats::{HostStatsMap, SessionClock};
+use crate::app::task::{ConnectionLimits, ManagerReady, RuntimeStats};
+use crate::app::throttle::Throttle;
+use crate::app::timefmt::TimeConfig;
+use crate::app::title::TerminalProgress;
+use crate::app::undo::{UndoAction, UndoBuffer};
+use crate::app::when_done::{WhenDone, WhenDoneTrigger};
+use crate::window::app::{
+    DownloadActivity, DownloadList, DownloadListRenderState, FinishList, FinishListRenderState,
+    FinishedTask, KeyRoute, PageList, StatsPage, StatsPageRenderState,
+};
+use crate::window::common::{Fill, SymbolSet, Toast};
 use crate::window::{WidgetType, common};
 
+pub mod about;
+pub mod address;
+pub mod bulk;
+pub mod checksum;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod config;
+#[cfg(feature = "control")]
+pub mod control;
+pub mod disk;
+pub mod doctor;
+pub mod export;
+pub mod input;
+pub mod instance;
+pub mod links;
 pub mod listener;
+pub mod migrate;
+pub mod network;
+pub mod notice;
+pub mod opener;
+pub mod peek;
+pub mod pending;
+pub mod permissions;
+pub mod policy;
+pub mod record;
+pub mod saved;
 pub mod sender;
+pub mod session;
+pub mod stats;
 pub mod task;
+pub mod template;
+pub mod throttle;
+pub mod timefmt;
+pub mod timespan;
+pub mod title;
+pub mod undo;
+pub mod when_done;
+
+#[cfg(test)]
+mod snapshot;
 
 /// 目前的设计如下：
 ///
 /// Downloading |
 /// Finished    | <Content>
-/// Config      |
+/// Stats       |
 ///
 /// PageList管理左侧的页面选择部分，而AppData管理右侧内容区。
 /// 在渲染右侧内容时，根据PageList的选择，选择不同的AppData进行渲染。
@@ -34,41 +90,291 @@ pub struct App {
     data: Box<AppData>,
     // 由不同的WidgetType组成的窗口列表，尾部是最上层窗口
     widgets: Vec<WidgetType>,
+    // 运行期间的配置，修改时通知后台线程，见[`App::update_config`]
+    config: LiveConfig,
+    // 版本以及文件的位置，显示在About弹窗中
+    build_info: BuildInfo,
+    // 最近一次破坏性操作的撤销记录
+    undo: UndoBuffer,
+    // 程序主动发出的提示
+    notices: NoticeBoard,
+    // 等待后台运行时启动，启动完成后为None
+    startup: Option<Receiver<ManagerReady>>,
+    // 在终端标题中显示的进度
+    terminal_progress: TerminalProgress,
+    // 每次绘制时递增，用于驱动动画
+    frame: usize,
+    // 连续按下两次Ctrl+C时立即退出
+    ctrl_c: DoublePress,
+    // 还没有进入页面时按下页面中的按键的提示，避免连续按键时不停地刷新提示
+    focus_hint: FocusHint,
+    // 所有下载完成之后执行的操作
+    when_done: WhenDoneTrigger,
+    // 立即退出时跳过退出前的保存等操作
+    hard_exit: bool,
+    // 下载目录的锁，退出时释放，见[`InstanceLock`]
+    instance: Option<InstanceLock>,
+    // 另一个实例正在使用下载目录，此时不添加任务，退出时也不保存配置和会话
+    read_only: Option<InstanceConflict>,
+    // 下一次绘制之前清空终端，重新绘制整个画面
+    force_redraw: bool,
+    // 最近一次收到的终端尺寸，只有尺寸真正变化时才需要重新绘制
+    last_size: Option<(u16, u16)>,
+    // 上一次绘制时整个终端的区域，用于判断弹窗是否只显示了占位提示
+    screen: Rect,
     running: bool,
+    // 接收外部程序的命令，见[`ControlServer`]
+    //
+    // [`ControlServer`]: crate::app::control::ControlServer
+    #[cfg(feature = "control")]
+    control: Option<control::ControlServer>,
+    // 复制URL时提示是否下载，见[`ClipboardWatcher`]
+    //
+    // [`ClipboardWatcher`]: crate::app::clipboard::ClipboardWatcher
+    #[cfg(feature = "clipboard")]
+    clipboard: clipboard::ClipboardWatcher,
 }
 
 impl App {
     // --------------- CONSTRUCT ---------------
 
-    pub fn new(sender: mpsc::Sender<Task>) -> Self {
+    /// `startup`用于接收后台线程启动的运行时，见[`ManagerReady`]
+    pub fn new(
+        startup: Receiver<ManagerReady>,
+        stats: Arc<RuntimeStats>,
+        throttle: Arc<Throttle>,
+        limits: Arc<ConnectionLimits>,
+        config: LiveConfig,
+        session: Session,
+        build_info: BuildInfo,
+    ) -> Self {
+        let current = config.current();
         App {
-            list: PageList::new(),
-            data: Box::new(AppData::new(sender)),
+            list: PageList::new().with_page(session.ui.page, session.ui.entered),
+            data: Box::new(AppData::new(stats, throttle, limits, &current, session)),
             widgets: vec![],
+            terminal_progress: TerminalProgress::from_config(&current),
+            #[cfg(feature = "clipboard")]
+            clipboard: clipboard::ClipboardWatcher::new(
+                current.clipboard_watch,
+                &current.clipboard_extensions,
+            ),
+            config,
+            build_info,
+            undo: UndoBuffer::new(),
+            notices: NoticeBoard::new(),
+            startup: Some(startup),
+            frame: 0,
+            ctrl_c: DoublePress::default(),
+            focus_hint: FocusHint::default(),
+            when_done: WhenDoneTrigger::new(),
+            hard_exit: false,
+            instance: None,
+            read_only: None,
+            force_redraw: false,
+            last_size: None,
+            screen: Rect::default(),
             running: true,
+            #[cfg(feature = "control")]
+            control: None,
         }
     }
 
+    #[cfg(feature = "control")]
+    pub fn with_control(mut self, control: control::ControlServer) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    pub fn with_instance_lock(mut self, lock: InstanceLock) -> Self {
+        self.instance = Some(lock);
+        self
+    }
+
     // ---------------- RUNNING ----------------
 
-    pub fn run(mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    /// 运行直到用户退出，返回本次运行的任务摘要
+    ///
+    /// 终端后端和事件来源都由调用者提供，见[`EventSource`]。
+    pub fn run<B: Backend>(
+        mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+    ) -> io::Result<SessionSummary> {
         while self.running {
             self.handle_async();
+            self.frame = self.frame.wrapping_add(1);
+            if self.take_force_redraw() {
+                terminal.clear()?;
+            }
             terminal.draw(|f| {
                 f.render_widget(&mut self, f.area());
             })?;
-            self.handle_event()?;
+            // 在绘制完成之后写入，避免与界面的输出交错
+            self.update_terminal_progress();
+            self.handle_event(events)?;
+        }
+        if let Err(e) = self.terminal_progress.clear(&mut io::stdout()) {
+            log::warn!(target:"App", "Failed to reset terminal title: {}", e);
+        }
+        if self.hard_exit {
+            log::warn!(target:"App", "Ctrl+C pressed twice, exiting without saving");
+            return Ok(self.data.to_summary());
+        }
+        self.undo.finalize_all();
+        if let Some(conflict) = &self.read_only {
+            log::info!(target:"App", "{}, not saving config and session", conflict);
+            return Ok(self.data.to_summary());
+        }
+        self.save_config();
+        self.save_session();
+        Ok(self.data.to_summary())
+    }
+
+    /// 将需要跨越多次运行保存的数据写入会话文件
+    fn save_session(&mut self) {
+        let mut session = self.data.to_session();
+        session.ui.page = self.list.selected().unwrap_or_default();
+        session.ui.entered = self.list.entered();
+        if let Err(e) = session.save() {
+            log::warn!(target:"App", "Failed to save session: {}", e);
+        }
+    }
+
+    /// 将运行时修改过的设置写回配置文件
+    fn save_config(&mut self) {
+        self.update_config(|_| {});
+    }
+
+    /// 修改配置并且立即写入配置文件
+    ///
+    /// 新的配置通过[`LiveConfig`]交给后台线程，之后接收的任务使用新的设置，
+    /// 速度上限以及同时进行的任务数量对正在进行的任务也立即生效。
+    /// 在界面中切换过的设置（例如公平分配）以当前的状态为准，覆盖`modify`中的修改。
+    /// 配置中的下载目录被修改时换用新的目录，见[`App::apply_download_dir`]。
+    pub(crate) fn update_config(&mut self, modify: impl FnOnce(&mut Config)) {
+        let old_dir = self.config.current().download_dir();
+        let config = self.config.update(|config| {
+            modify(config);
+            self.data.store_config(config);
+        });
+        if let Err(e) = self.config.stored().save() {
+            log::warn!(target:"App", "Failed to save config: {}", e);
+        }
+        if config.download_dir() != old_dir {
+            self.apply_download_dir(config.download_dir());
+        }
+    }
+
+    /// 设置向导完成或者首次运行时跳过时调用，应用并立即保存向导生成的配置
+    pub(crate) fn finish_setup(&mut self, config: Config) {
+        self.update_config(|current| *current = config);
+    }
+
+    /// 之后添加的任务下载到`dir`，同时获取`dir`的锁
+    ///
+    /// 有暂停的任务在原来的目录中时，询问是否移动这些任务已经下载的部分。
+    fn apply_download_dir(&mut self, dir: PathBuf) {
+        let downloading = &mut self.data.downloading;
+        let old_dir = downloading.download_dir().to_path_buf();
+        downloading.set_download_dir(dir);
+        let new_dir = downloading.download_dir().to_path_buf();
+        if old_dir != new_dir && self.read_only.is_none() {
+            self.lock_download_dir(&new_dir);
+        }
+        let downloading = &mut self.data.downloading;
+        if old_dir != new_dir {
+            let (jobs, _) = downloading.partial_moves(&old_dir, &new_dir);
+            if !jobs.is_empty() {
+                self.widgets
+                    .push(WidgetType::new_migrate_dialog(old_dir, new_dir, jobs.len()));
+            }
+        }
+    }
+
+    /// 另一个实例正在使用下载目录时调用，进入只读模式并询问如何处理，见[`InstanceDialog`]
+    ///
+    /// [`InstanceDialog`]: crate::window::dialog::InstanceDialog
+    pub fn enter_read_only(&mut self, conflict: InstanceConflict) {
+        log::warn!(target:"App", "{}, running in read-only mode", conflict);
+        self.data.downloading.set_read_only(true);
+        self.widgets
+            .push(WidgetType::new_instance_dialog(conflict.clone()));
+        self.read_only = Some(conflict);
+    }
+
+    /// 获取`dir`的锁并替换当前持有的锁，另一个实例正在使用`dir`时进入只读模式
+    fn lock_download_dir(&mut self, dir: &Path) {
+        match InstanceLock::acquire(dir) {
+            Ok(LockAttempt::Acquired(lock)) => self.instance = Some(lock),
+            Ok(LockAttempt::Held(conflict)) => {
+                self.instance = None;
+                self.enter_read_only(conflict);
+            }
+            Err(e) => log::warn!(target:"App", "Failed to lock {}: {}", dir.display(), e),
         }
+    }
+
+    /// 只读模式下换用`dir`作为本次运行的下载目录，获取它的锁之后退出只读模式
+    ///
+    /// 不写入配置文件，下一次启动时仍然使用配置中的下载目录。
+    /// `dir`同样被另一个实例使用或者无法获取锁时返回错误信息，保持只读模式。
+    pub(crate) fn switch_download_dir(&mut self, dir: PathBuf) -> Result<(), String> {
+        let lock = match InstanceLock::acquire(&dir) {
+            Ok(LockAttempt::Acquired(lock)) => lock,
+            Ok(LockAttempt::Held(conflict)) => return Err(conflict.to_string()),
+            Err(e) => return Err(format!("Cannot lock {}: {}", dir.display(), e)),
+        };
+        log::info!(target:"App", "Switched download directory to {}", dir.display());
+        self.notices
+            .push(format!("Downloading to {} for this session", dir.display()));
+        self.data.downloading.set_download_dir(dir);
+        self.data.downloading.set_read_only(false);
+        self.instance = Some(lock);
+        self.read_only = None;
         Ok(())
     }
 
     // ------------------ MEMBER_ACCESS --------------------
 
+    /// 另一个实例正在使用下载目录时返回冲突的信息
+    #[inline]
+    pub fn read_only(&self) -> Option<&InstanceConflict> {
+        self.read_only.as_ref()
+    }
+
+    /// 界面中使用的符号，同时决定是否播放动画
+    pub fn symbols(&self) -> SymbolSet {
+        let config = self.config.current();
+        SymbolSet::new(config.ascii_symbols, config.reduced_motion)
+    }
+
+    /// 没有输入时两次重绘之间的间隔，关闭动画时只需要每秒刷新一次进度和倒计时
+    fn frame_interval(&self) -> Duration {
+        if self.symbols().animated {
+            Duration::from_millis(100)
+        } else {
+            Duration::from_secs(1)
+        }
+    }
+
     #[inline]
     pub fn append_widget(&mut self, widget: WidgetType) {
         self.widgets.push(widget);
     }
 
+    /// 在状态栏中显示一条提示
+    pub fn notify(&mut self, message: String) {
+        self.notices.push(message);
+    }
+
+    /// 选择所有下载完成之后执行的操作，见[`WhenDoneTrigger`]
+    pub fn set_when_done(&mut self, action: WhenDone) {
+        self.when_done.arm(action);
+        self.notices
+            .push(format!("When all downloads finish: {}", action));
+    }
+
     #[inline]
     pub fn append_widgets<I>(&mut self, widgets: I)
     where
@@ -77,6 +383,25 @@ impl App {
         self.widgets.extend(widgets);
     }
 
+    /// 是否需要在下一次绘制之前清空终端，调用之后重置
+    ///
+    /// ratatui只绘制与上一帧不同的部分，终端中的内容被其他程序（例如`xdg-open`）
+    /// 修改之后，需要清空终端才能重新绘制整个画面。
+    pub fn take_force_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.force_redraw)
+    }
+
+    /// 当前的配置，界面在每一帧中读取，修改见[`App::update_config`]
+    #[inline]
+    pub fn config(&self) -> Arc<Config> {
+        self.config.current()
+    }
+
+    /// 配置文件中的设置，不包含命令行参数的覆盖，用作设置向导的初始值
+    pub fn stored_config(&self) -> Config {
+        self.config.stored().clone()
+    }
+
     #[inline]
     pub fn download_list(&self) -> &DownloadList {
         self.data.downloading()
@@ -100,11 +425,19 @@ impl App {
     #[inline]
     pub(crate) fn destruct_data(
         &mut self,
-    ) -> (&mut DownloadList, &mut Vec<WidgetType>, &mut FinishList) {
+    ) -> (
+        &mut DownloadList,
+        &mut Vec<WidgetType>,
+        &mut FinishList,
+        &mut UndoBuffer,
+        &mut NoticeBoard,
+    ) {
         (
             &mut self.data.downloading,
             &mut self.widgets,
             &mut self.data.finished,
+            &mut self.undo,
+            &mut self.notices,
         )
     }
 
@@ -153,14 +486,26 @@ impl App {
     fn render_page(&mut self, area: Rect, buf: &mut Buffer, selected: usize) {
         match selected {
             0 => {
-                self.data
-                    .downloading_mut()
-                    .render(area, buf, &mut self.list.entered());
+                let mut state = DownloadListRenderState::new(self.list.entered(), self.symbols());
+                self.data.downloading_mut().render(area, buf, &mut state);
             }
             1 => {
-                self.data
-                    .finished_mut()
-                    .render(area, buf, &mut self.list.entered());
+                let mut state = FinishListRenderState::new(self.list.entered(), self.symbols());
+                self.data.finished_mut().render(area, buf, &mut state);
+            }
+            2 => {
+                let mut state = StatsPageRenderState::new(
+                    self.list.entered(),
+                    self.data.downloading.list().len(),
+                    self.data.finished.list().len(),
+                    &self.data.hosts,
+                    &self.data.clock,
+                )
+                .with_speed(
+                    self.data.downloading.speed_display(),
+                    self.data.downloading.display_speed(),
+                );
+                self.data.stats.render(area, buf, &mut state);
             }
             _ => self.list.set_selected(None),
         }
@@ -169,12 +514,20 @@ impl App {
     // --------------------- HANDLE_EVENT -----------------------
 
     // 我们让页面至少以10FPS的频率进行刷新，而不会因为没有事件而阻塞
-    pub fn handle_event(&mut self) -> io::Result<()> {
-        let timeout = Duration::from_secs_f64(1.0 / 10.0);
-        if event::poll(timeout)? {
-            match event::read()? {
+    pub fn handle_event(&mut self, events: &mut impl EventSource) -> io::Result<()> {
+        if events.poll(self.frame_interval())? {
+            match events.read()? {
                 Event::Key(key) => self.distribute_key_event(key),
                 Event::Mouse(_) => {} // TODO: handle mouse events
+                // 重新连接tmux会话等情况下，终端中的内容可能与上一帧不一致
+                Event::FocusGained => {
+                    self.respond_to_message(AppMessage::ForceRedraw);
+                }
+                Event::Resize(width, height)
+                    if self.last_size.replace((width, height)) != Some((width, height)) =>
+                {
+                    self.respond_to_message(AppMessage::ForceRedraw);
+                }
                 _ => {}
             }
         }
@@ -188,12 +541,53 @@ impl App {
                 self.running = false;
                 None
             }
+            AppMessage::Undo => {
+                self.undo();
+                None
+            }
+            AppMessage::ForceRedraw => {
+                self.force_redraw = true;
+                None
+            }
+            AppMessage::ChooseWhenDone => {
+                let config = self.config();
+                self.append_widget(WidgetType::new_when_done_dialog(
+                    self.when_done.action(),
+                    &config.when_done_command,
+                ));
+                None
+            }
+            AppMessage::OpenSettings => {
+                self.append_widget(WidgetType::new_settings_wizard(self.stored_config()));
+                None
+            }
+            AppMessage::ShowAbout => {
+                // 下载目录可能在运行中被设置向导修改，或者因为另一个实例而换用其他目录
+                let mut info = self.build_info.clone();
+                info.download_dir = self.data.downloading.download_dir().to_path_buf();
+                self.append_widget(WidgetType::new_about_dialog(info));
+                None
+            }
+            #[cfg(feature = "clipboard")]
+            AppMessage::ToggleClipboardWatch => {
+                self.toggle_clipboard_watch();
+                None
+            }
+            #[cfg(feature = "clipboard")]
+            AppMessage::AcceptClipboardUrl => {
+                self.accept_clipboard_url();
+                None
+            }
             // 其他的就交给各个子组件去处理
             AppMessage::Distribute(key) => {
-                if let Some(key) = self.list.handle_key_event(key)
-                    && let Some(i) = self.list.selected()
-                {
-                    self.distribute_to_content(key, i);
+                match self.list.handle_key_event(key) {
+                    KeyRoute::Content(key) => {
+                        if let Some(i) = self.list.selected() {
+                            self.distribute_to_content(key, i);
+                        }
+                    }
+                    KeyRoute::Unfocused(key) => self.handle_unfocused_key(key),
+                    KeyRoute::Handled => {}
                 }
                 None
             }
@@ -208,7 +602,49 @@ impl App {
         }
     }
 
-    // App只处理推出的逻辑，其他的就交给各个子组件去处理
+    /// 还没有进入页面时按下了页面列表不处理的按键，按照配置提示或者直接进入页面，见[`UnfocusedKeys`]
+    fn handle_unfocused_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press || matches!(key.code, KeyCode::Modifier(_)) {
+            return;
+        }
+        let Some(page) = self.list.selected() else {
+            return;
+        };
+        match self.config().unfocused_keys {
+            UnfocusedKeys::Enter => {
+                self.list.enter();
+                self.distribute_to_content(key, page);
+            }
+            UnfocusedKeys::Hint => {
+                if self.focus_hint.due(Instant::now()) {
+                    self.notify(String::from("Press Enter/\u{2192} to focus the page first"));
+                }
+            }
+        }
+    }
+
+    /// 撤销最近一次破坏性操作，记录已经过期时什么都不做
+    fn undo(&mut self) {
+        let Some(action) = self.undo.take() else {
+            log::debug!(target:"App", "Nothing to undo");
+            return;
+        };
+        match action {
+            UndoAction::Abort { id, state, url } => {
+                self.data
+                    .downloading
+                    .undo_abort(id, state, url, &mut self.data.finished);
+            }
+            UndoAction::DeleteFinished { index, task, .. } => {
+                self.data.finished.insert_task(index, *task);
+            }
+            UndoAction::ClearFinished { tasks } => {
+                self.data.finished.restore_tasks(tasks);
+            }
+        }
+    }
+
+    // App只处理推出以及撤销的逻辑，其他的就交给各个子组件去处理
     fn get_key_message(key: KeyEvent) -> Option<AppMessage> {
         if key.kind == KeyEventKind::Press {
             match (key.modifiers, key.code) {
@@ -222,6 +658,26 @@ impl App {
                     KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
                         return Some(AppMessage::Quit);
                     }
+                    KeyCode::Char('u') => {
+                        return Some(AppMessage::Undo);
+                    }
+                    KeyCode::F(1) => {
+                        return Some(AppMessage::ShowAbout);
+                    }
+                    KeyCode::F(2) => {
+                        return Some(AppMessage::OpenSettings);
+                    }
+                    KeyCode::Char('Z') => {
+                        return Some(AppMessage::ChooseWhenDone);
+                    }
+                    #[cfg(feature = "clipboard")]
+                    KeyCode::Char('w') => {
+                        return Some(AppMessage::ToggleClipboardWatch);
+                    }
+                    #[cfg(feature = "clipboard")]
+                    KeyCode::Char('y') => {
+                        return Some(AppMessage::AcceptClipboardUrl);
+                    }
                     _ => {}
                 },
             }
@@ -237,20 +693,64 @@ impl App {
                     key,
                     &mut self.widgets,
                     &mut self.data.finished,
+                    &mut self.undo,
+                    &mut self.notices,
                 );
             }
             1 => {
-                self.data
-                    .finished_mut()
-                    .handle_key_event(key, &mut self.widgets);
+                self.data.finished.handle_key_event(
+                    key,
+                    &mut self.widgets,
+                    &mut self.data.downloading,
+                    &mut self.undo,
+                    &mut self.notices,
+                );
+            }
+            2 => {
+                self.data.stats.handle_key_event(
+                    key,
+                    &mut self.data.hosts,
+                    &mut self.data.downloading,
+                );
             }
             _ => self.list.set_selected(None),
         }
     }
 
     // 我们将KeyEvent分发给最上层的Widget处理，如果没有Widget，则交给App处理
+    //
+    // 连续两次Ctrl+C总是立即退出，不经过弹窗，避免用户被困在某个弹窗中。
+    // Ctrl+L同样不经过弹窗，总是重新绘制整个画面
     fn distribute_key_event(&mut self, key: KeyEvent) {
+        if key.kind == KeyEventKind::Press
+            && key.modifiers == KeyModifiers::CONTROL
+            && matches!(key.code, KeyCode::Char('l') | KeyCode::Char('L'))
+        {
+            self.respond_to_message(AppMessage::ForceRedraw);
+            return;
+        }
+        if key.kind == KeyEventKind::Press
+            && key.modifiers == KeyModifiers::CONTROL
+            && matches!(key.code, KeyCode::Char('c') | KeyCode::Char('C'))
+            && self.ctrl_c.press(Instant::now())
+        {
+            self.hard_exit = true;
+            self.running = false;
+            return;
+        }
+        // 倒计时期间的按键只用于取消，不再做其他处理
+        if key.kind == KeyEventKind::Press && self.when_done.cancel() {
+            self.notices
+                .push(String::from("Cancelled the action after all downloads"));
+            return;
+        }
         match self.widgets.pop() {
+            // 终端太小时弹窗只显示占位提示，Esc直接关闭弹窗
+            Some(widget)
+                if key.code == KeyCode::Esc && widget.desired_area(self.screen).is_none() =>
+            {
+                log::debug!(target:"App", "Closed a popup that does not fit the terminal");
+            }
             Some(widget) => {
                 widget.handle_key_event(key, self);
             }
@@ -262,9 +762,83 @@ impl App {
 
     // ------------------- HANDLE_ASYNC ----------------------
 
+    /// 在侧边栏的下载页面名称后显示活动状态
+    fn update_page_badges(&mut self) {
+        let symbols = self.symbols();
+        let badge = match self.data.downloading.activity() {
+            DownloadActivity::Active => symbols.spinner_frame(self.frame),
+            DownloadActivity::Paused => Some(symbols.paused),
+            DownloadActivity::Idle => None,
+        };
+        self.list.set_badge(0, badge.map(str::to_string));
+    }
+
+    fn update_terminal_progress(&mut self) {
+        let summary = self.data.downloading.progress_summary();
+        if let Err(e) = self.terminal_progress.update(&summary, &mut io::stdout()) {
+            log::warn!(target:"App", "Failed to update terminal title: {}", e);
+        }
+    }
+
+    /// 检查后台运行时是否已经启动
+    fn poll_startup(&mut self) {
+        let Some(startup) = &self.startup else {
+            return;
+        };
+        match startup.try_recv() {
+            Ok(ready) => {
+                log::debug!(target:"App", "Background runtime connected");
+                self.data.downloading.connect(ready.sender, &ready.runtime);
+                #[cfg(feature = "clipboard")]
+                self.clipboard.start(&ready.runtime);
+                self.data.finished.set_runtime(ready.runtime.clone());
+                self.undo.set_runtime(ready.runtime);
+                self.startup = None;
+            }
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => {
+                // 后台线程启动失败，错误由run_app返回
+                log::error!(target:"App", "Background runtime failed to start");
+                self.startup = None;
+                self.running = false;
+            }
+        }
+    }
+
+    /// 下载列表清空之后倒计时结束时执行选择的操作
+    fn poll_when_done(&mut self) {
+        let downloading = &self.data.downloading;
+        let busy = !downloading.list().is_empty() || !downloading.pending().is_empty();
+        match self.when_done.tick(Instant::now(), busy) {
+            Some(WhenDone::RunCommand) => {
+                let config = self.config.current();
+                let command = &config.when_done_command;
+                log::info!(target:"App", "All downloads finished, running {:?}", command);
+                if let Err(e) = opener::spawn_argv(command) {
+                    self.notices
+                        .push(format!("Failed to run {}: {}", command.join(" "), e));
+                }
+            }
+            Some(WhenDone::Exit) => {
+                log::info!(target:"App", "All downloads finished, exiting");
+                self.running = false;
+            }
+            Some(WhenDone::Nothing) | None => {}
+        }
+    }
+
     #[inline]
     pub fn handle_async(&mut self) {
-        self.data.handle_async();
+        self.poll_startup();
+        self.undo.expire();
+        #[cfg(feature = "control")]
+        self.handle_control();
+        #[cfg(feature = "clipboard")]
+        self.handle_clipboard();
+        let finished_visible = self.list.selected() == Some(1);
+        self.data
+            .handle_async(&mut self.widgets, &mut self.notices, finished_visible);
+        self.poll_when_done();
     }
 }
 
@@ -273,7 +847,47 @@ impl Widget for &mut App {
     where
         Self: Sized,
     {
-        let (left, right) = self.render_structure(area, buf);
+        self.screen = area;
+        let (left, mut right) = self.render_structure(area, buf);
+        self.update_page_badges();
+        // 下载目录有问题时，在内容区顶部持续显示警告
+        if let Some(warning) = self.data.downloading.dir_warning() {
+            let [banner, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(right);
+            Paragraph::new(format!(
+                " ! {}  (F2: settings)",
+                common::display_sanitize(&warning)
+            ))
+            .style(Style::new().fg(Color::Black).bg(tailwind::AMBER.c300))
+            .render(banner, buf);
+            right = rest;
+        }
+        // 只读模式下持续显示，避免误以为新添加的任务没有反应
+        if let Some(conflict) = &self.read_only {
+            let [banner, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(right);
+            Paragraph::new(format!(
+                " Read-only: {}",
+                common::display_sanitize(&conflict.to_string())
+            ))
+            .style(Style::new().fg(Color::Black).bg(tailwind::AMBER.c300))
+            .render(banner, buf);
+            right = rest;
+        }
+        // 选择了下载完成之后的操作时持续显示，倒计时期间突出显示
+        if let Some(status) = self.when_done.status(Instant::now()) {
+            let [banner, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(right);
+            let style = if self.when_done.remaining(Instant::now()).is_some() {
+                Style::new().fg(Color::Black).bg(tailwind::AMBER.c300)
+            } else {
+                Style::new().dim()
+            };
+            Paragraph::new(format!(" {}", status))
+                .style(style)
+                .render(banner, buf);
+            right = rest;
+        }
         self.list.render(left, buf);
         match self.list.selected() {
             None => {
@@ -288,6 +902,19 @@ impl Widget for &mut App {
             }
         }
 
+        #[cfg(feature = "clipboard")]
+        let prompt = self.clipboard_prompt();
+        #[cfg(not(feature = "clipboard"))]
+        let prompt: Option<String> = None;
+        // 撤销提示优先于其他提示，其次是是否下载剪贴板中的URL
+        if let Some(record) = self.undo.current() {
+            Toast::new(record.toast()).render(right, buf);
+        } else if let Some(prompt) = prompt {
+            Toast::new(prompt).render(right, buf);
+        } else if let Some(notice) = self.notices.current() {
+            Toast::new(notice.to_string()).render(right, buf);
+        }
+
         for widget in &mut self.widgets {
             widget.render(area, buf);
         }
@@ -296,21 +923,77 @@ impl Widget for &mut App {
 
 pub enum AppMessage {
     Quit,
+    Undo,
+    /// 下一次绘制之前清空终端，见[`App::take_force_redraw`]
+    ForceRedraw,
+    /// 显示版本以及文件的位置，见[`BuildInfo`]
+    ShowAbout,
+    /// 打开设置向导修改下载目录等设置，见[`SetupWizard`]
+    ///
+    /// [`SetupWizard`]: crate::window::dialog::SetupWizard
+    OpenSettings,
+    /// 选择所有下载完成之后执行的操作，见[`WhenDone`]
+    ChooseWhenDone,
+    /// 开启或者关闭剪贴板监视
+    #[cfg(feature = "clipboard")]
+    ToggleClipboardWatch,
+    /// 添加剪贴板中复制的URL
+    #[cfg(feature = "clipboard")]
+    AcceptClipboardUrl,
     Distribute(KeyEvent),
 }
 
 pub struct AppData {
     downloading: DownloadList,
     finished: FinishList,
+    stats: StatsPage,
+    // 按主机统计的下载数据，跨越多次运行累计
+    hosts: HostStatsMap,
+    // 本次运行的开始时间以及传输时间
+    clock: SessionClock,
 }
 
 impl AppData {
     // ------------------ CONSTRUCT --------------------
 
-    pub fn new(sender: mpsc::Sender<Task>) -> Self {
+    pub fn new(
+        stats: Arc<RuntimeStats>,
+        throttle: Arc<Throttle>,
+        limits: Arc<ConnectionLimits>,
+        config: &Config,
+        session: Session,
+    ) -> Self {
+        // 格式无效时已经在启动时提示过
+        let time = TimeConfig::from_config(config).unwrap_or_default();
         AppData {
-            downloading: DownloadList::new(sender),
-            finished: FinishList::new(),
+            downloading: DownloadList::new(
+                stats.clone(),
+                throttle,
+                limits.clone(),
+                config.download_dir(),
+                config.middle_row,
+                config.disk_headroom_mib << 20,
+                session.pending,
+            )
+            .with_templates(session.templates)
+            .with_position(&session.ui.downloading)
+            .with_extract_archives(config.extract_archives)
+            .with_health_thresholds(config.task_health)
+            .with_show_remaining(config.show_remaining)
+            .with_speed_smoothing(
+                config.speed_display,
+                Duration::from_millis(config.speed_half_life_ms),
+            )
+            // 权限无效时已经在启动时提示过
+            .with_dir_mode(FileModes::from_config(config).unwrap_or_default().dir)
+            .with_time_config(time.clone()),
+            finished: FinishList::new()
+                .with_open_with(config.open_with.clone())
+                .with_auto_clear(config.auto_clear_finished_after)
+                .with_time_config(time.clone()),
+            stats: StatsPage::new(stats, limits, config).with_time_config(time),
+            hosts: session.hosts,
+            clock: SessionClock::start(),
         }
     }
 
@@ -336,11 +1019,143 @@ impl AppData {
         &self.finished
     }
 
+    #[inline]
+    pub fn stats(&self) -> &StatsPage {
+        &self.stats
+    }
+
+    #[inline]
+    pub fn hosts(&self) -> &HostStatsMap {
+        &self.hosts
+    }
+
+    // -------------------- FUNCTION ---------------------
+
+    /// 将各个页面中可以在运行时修改的设置同步到配置中
+    pub fn store_config(&self, config: &mut Config) {
+        config.middle_row = self.downloading.middle_row();
+        config.show_remaining = self.downloading.show_remaining();
+        config.speed_display = self.downloading.speed_display();
+        config.admission_policy = self.downloading.admission_policy();
+        config.fair_share = self.downloading.fair_share();
+    }
+
+    /// 本次运行中处理过的所有任务，用于`--summary-json`
+    pub fn to_summary(&self) -> SessionSummary {
+        // 自动清除的任务排在最前面，它们比列表中剩下的任务完成得更早
+        let tasks = self
+            .finished
+            .archived()
+            .iter()
+            .cloned()
+            .chain(self.finished.list().iter().map(FinishedTask::to_record))
+            .chain(self.downloading.list().iter().map(TaskListener::to_record))
+            .collect();
+        SessionSummary {
+            session: self.clock.to_totals(Instant::now()),
+            tasks,
+        }
+    }
+
+    pub fn to_session(&self) -> Session {
+        Session {
+            hosts: self.hosts.clone(),
+            pending: self.downloading.pending().to_vec(),
+            templates: self.downloading.templates().to_vec(),
+            ui: UiState {
+                downloading: self.downloading.position(),
+                ..Default::default()
+            },
+        }
+    }
+
     // ------------------- HANDLE_ASYNC ---------------------
 
     #[inline]
-    pub fn handle_async(&mut self) {
-        self.downloading.handle_async(&mut self.finished);
-        self.finished.handle_async();
+    /// `finished_visible`为完成页面是否正在显示
+    pub fn handle_async(
+        &mut self,
+        widgets: &mut Vec<WidgetType>,
+        notices: &mut NoticeBoard,
+        finished_visible: bool,
+    ) {
+        self.downloading
+            .handle_async(&mut self.finished, widgets, &mut self.hosts, notices);
+        self.finished
+            .handle_async(finished_visible, widgets, notices);
+        self.downloading.update_queue_eta(Instant::now());
+        self.clock.tick(
+            Instant::now(),
+            self.downloading.total_speed() > 0,
+            self.downloading.transferred(),
+        );
+    }
+}
+
+/// 限制[`UnfocusedKeys::Hint`]的提示，[`FocusHint::INTERVAL`]内只提示一次
+#[derive(Debug, Default)]
+struct FocusHint {
+    last: Option<Instant>,
+}
+
+impl FocusHint {
+    // 与提示显示的时间相同，提示消失之前不会再次出现
+    const INTERVAL: Duration = NoticeBoard::DURATION;
+
+    fn due(&mut self, now: Instant) -> bool {
+        if self
+            .last
+            .is_some_and(|last| now.saturating_duration_since(last) < Self::INTERVAL)
+        {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// 检测在[`DoublePress::WINDOW`]内连续按下两次同一个按键
+#[derive(Debug, Default)]
+struct DoublePress {
+    last: Option<Instant>,
+}
+
+impl DoublePress {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// 记录一次按键，与上一次按键的间隔不超过[`DoublePress::WINDOW`]时返回true
+    fn press(&mut self, now: Instant) -> bool {
+        match self.last.take() {
+            Some(last) if now.saturating_duration_since(last) <= Self::WINDOW => true,
+            _ => {
+                self.last = Some(now);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_press_within_window() {
+        let start = Instant::now();
+        let mut ctrl_c = DoublePress::default();
+        assert!(!ctrl_c.press(start));
+        assert!(ctrl_c.press(start + Duration::from_millis(300)));
+        // 触发之后重新计数
+        assert!(!ctrl_c.press(start + Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn slow_second_press_starts_a_new_window() {
+        let start = Instant::now();
+        let mut ctrl_c = DoublePress::default();
+        assert!(!ctrl_c.press(start));
+        let late = start + DoublePress::WINDOW + Duration::from_millis(1);
+        assert!(!ctrl_c.press(late));
+        assert!(ctrl_c.press(late + DoublePress::WINDOW));
     }
 }
@@ -0,0 +1,91 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::app::config::Config;
+use crate::app::session::Session;
+
+/// 版本以及各个文件所在的位置，显示在About弹窗中（按`F1`打开），便于用户报告问题
+///
+/// 在启动时收集一次，交给[`App`]保存。git提交由`build.rs`在编译时记录。
+///
+/// [`App`]: crate::app::App
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    /// 无法确定用户目录时为[`None`]，下同
+    pub config_path: Option<PathBuf>,
+    pub log_path: PathBuf,
+    pub data_dir: Option<PathBuf>,
+    pub download_dir: PathBuf,
+    /// 编译时开启的可选特性
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    // ------------------- CONSTANT -----------------------
+
+    /// 所有可选特性以及是否在编译时开启
+    pub const FEATURES: [(&'static str, bool); 2] = [
+        ("clipboard", cfg!(feature = "clipboard")),
+        ("control", cfg!(feature = "control")),
+    ];
+
+    const LOG_FILENAME: &'static str = "request_tui-debug.log";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn collect(config: &Config) -> Self {
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("REQUEST_TUI_GIT_HASH"),
+            config_path: Config::path(),
+            log_path: Self::log_path(),
+            data_dir: Session::path().and_then(|path| path.parent().map(PathBuf::from)),
+            download_dir: config.download_dir(),
+            features: Self::FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| *name)
+                .collect(),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 调试日志的路径，位于临时目录中
+    pub fn log_path() -> PathBuf {
+        env::temp_dir().join(Self::LOG_FILENAME)
+    }
+
+    /// 每一项的名称以及内容，弹窗和复制的文本使用相同的内容
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map_or(String::from("unknown"), |p| p.display().to_string())
+        };
+        let features = match self.features.is_empty() {
+            true => String::from("none"),
+            false => self.features.join(", "),
+        };
+        vec![
+            ("Version", format!("{} ({})", self.version, self.git_hash)),
+            ("Config", path(&self.config_path)),
+            ("Log", self.log_path.display().to_string()),
+            ("Data", path(&self.data_dir)),
+            ("Downloads", self.download_dir.display().to_string()),
+            ("Features", features),
+        ]
+    }
+
+    /// 复制到剪贴板的纯文本，每项一行
+    pub fn to_text(&self) -> String {
+        let fields = self.fields();
+        let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        let mut text = String::from("request-tui\n");
+        for (name, value) in fields {
+            text.push_str(&format!("{:<width$}  {}\n", name, value, width = width));
+        }
+        text
+    }
+}
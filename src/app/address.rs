@@ -0,0 +1,230 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use url::Url;
+
+/// 规范化用户输入的URL，下载窗口的预览和实际的下载任务使用同一个函数，
+/// 保证预览中显示的就是实际请求的地址。
+///
+/// - 去掉首尾的空白字符，以及从邮件等地方复制时带上的尖括号和引号，例如`<https://…>`
+/// - 没有协议时使用`http://`，例如`example.com/file.iso`
+/// - 只接受`http`和`https`协议
+/// - 路径和查询中的空格、非ASCII字符以及`|`、`^`、`[`等不允许出现的字符使用百分号编码，
+///   已经编码的`%XX`保持不变，见[`encode_invalid`]
+///
+/// 国际化域名（IDN）在解析时被转换为punycode形式（`xn--`开头），见[`is_idn`]。
+/// 对结果再次规范化不会改变结果。
+pub fn normalize_url(input: &str) -> Result<Url, UrlError> {
+    let input = strip_paste_artifacts(input);
+    if input.is_empty() {
+        return Err(UrlError::Empty);
+    }
+    let mut url = parse_http(input)?;
+    let path = encode_invalid(url.path(), "");
+    if path != url.path() {
+        url.set_path(&path);
+    }
+    if let Some(query) = url.query() {
+        let encoded = encode_invalid(query, "?");
+        if encoded != query {
+            url.set_query(Some(&encoded));
+        }
+    }
+    Ok(url)
+}
+
+fn parse_http(input: &str) -> Result<Url, UrlError> {
+    let url = match Url::parse(input) {
+        Ok(url) if is_supported_scheme(url.scheme()) => return Ok(url),
+        // 例如"example.com:8080/file"会被解析为协议为"example.com"的URL
+        Ok(_) if !input.contains("://") => format!("http://{}", input),
+        Ok(url) => return Err(UrlError::UnsupportedScheme(url.scheme().to_string())),
+        Err(url::ParseError::RelativeUrlWithoutBase) => format!("http://{}", input),
+        Err(e) => return Err(UrlError::Invalid(e)),
+    };
+    Url::parse(&url).map_err(UrlError::Invalid)
+}
+
+fn is_supported_scheme(scheme: &str) -> bool {
+    matches!(scheme, "http" | "https")
+}
+
+// 复制URL时可能一起带上的成对符号，由外向内逐层去掉
+const PASTE_ENCLOSURES: [(char, char); 5] =
+    [('<', '>'), ('"', '"'), ('\'', '\''), ('“', '”'), ('‘', '’')];
+
+fn strip_paste_artifacts(input: &str) -> &str {
+    let mut input = input.trim();
+    while let Some(inner) = PASTE_ENCLOSURES
+        .iter()
+        .find_map(|&(open, close)| input.strip_prefix(open)?.strip_suffix(close))
+    {
+        input = inner.trim();
+    }
+    input
+}
+
+/// 对RFC 3986中不允许出现在路径（或者查询）中的字符进行百分号编码，`extra`为额外允许的字符
+///
+/// url库按照WHATWG标准解析，会保留`|`、`^`、`[`等字符以及不完整的`%`，
+/// 有些服务器会拒绝这样的请求。完整的`%XX`被视为已经编码，保持不变，
+/// 不完整的`%`被编码为`%25`。
+fn encode_invalid(source: &str, extra: &str) -> String {
+    let bytes = source.as_bytes();
+    let is_hex = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_hexdigit);
+    let mut encoded = String::with_capacity(source.len());
+    for (i, c) in source.char_indices() {
+        let allowed = c.is_ascii_alphanumeric()
+            || "-._~!$&'()*+,;=:@/".contains(c)
+            || extra.contains(c)
+            || (c == '%' && is_hex(i + 1) && is_hex(i + 2));
+        if allowed {
+            encoded.push(c);
+            continue;
+        }
+        let mut buf = [0; 4];
+        for byte in c.encode_utf8(&mut buf).bytes() {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+/// 主机名是否包含国际化域名，这类域名可能被用来伪装成其他网站
+pub fn is_idn(url: &Url) -> bool {
+    url.host_str()
+        .is_some_and(|host| host.split('.').any(|label| label.starts_with("xn--")))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+    Empty,
+    Invalid(url::ParseError),
+    UnsupportedScheme(String),
+}
+
+impl Display for UrlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlError::Empty => write!(f, "URL is empty"),
+            UrlError::Invalid(e) => write!(f, "Failed to parse URL: {}", e),
+            UrlError::UnsupportedScheme(s) => write!(f, "Unsupported scheme: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(input: &str) -> String {
+        normalize_url(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn missing_scheme_defaults_to_http() {
+        assert_eq!(
+            normalized("example.com/file.iso"),
+            "http://example.com/file.iso"
+        );
+        // 主机名加端口会被url库当作协议
+        assert_eq!(
+            normalized("example.com:8080/file"),
+            "http://example.com:8080/file"
+        );
+        assert_eq!(
+            normalized("https://example.com/file"),
+            "https://example.com/file"
+        );
+    }
+
+    #[test]
+    fn rejects_empty_input_and_other_schemes() {
+        assert_eq!(normalize_url(""), Err(UrlError::Empty));
+        assert_eq!(normalize_url(" \t\n"), Err(UrlError::Empty));
+        assert_eq!(
+            normalize_url("ftp://example.com/file"),
+            Err(UrlError::UnsupportedScheme(String::from("ftp")))
+        );
+        assert!(matches!(
+            normalize_url("http://exa mple.com/"),
+            Err(UrlError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn normalizing_twice_changes_nothing() {
+        for input in [
+            "example.com/a b|c",
+            "https://example.com/%E4%B8%AD?q=[1]",
+            "http://example.com/100%",
+        ] {
+            let once = normalized(input);
+            assert_eq!(normalized(&once), once);
+        }
+    }
+
+    #[test]
+    fn strips_pasted_whitespace_and_quotes() {
+        assert_eq!(
+            strip_paste_artifacts("  https://e.com/a \n"),
+            "https://e.com/a"
+        );
+        assert_eq!(
+            strip_paste_artifacts("<https://e.com/a>"),
+            "https://e.com/a"
+        );
+        assert_eq!(
+            strip_paste_artifacts("\"https://e.com/a\""),
+            "https://e.com/a"
+        );
+        assert_eq!(
+            strip_paste_artifacts("“https://e.com/a”"),
+            "https://e.com/a"
+        );
+        // 多层符号以及符号内侧的空白
+        assert_eq!(
+            strip_paste_artifacts(" '< https://e.com/a >' "),
+            "https://e.com/a"
+        );
+        // 不成对的符号保持不变
+        assert_eq!(
+            strip_paste_artifacts("<https://e.com/a"),
+            "<https://e.com/a"
+        );
+        assert_eq!(strip_paste_artifacts("\"\""), "");
+        assert_eq!(
+            normalized("  <\"example.com/file.iso\">\t"),
+            "http://example.com/file.iso"
+        );
+    }
+
+    #[test]
+    fn encodes_characters_not_allowed_by_rfc_3986() {
+        assert_eq!(encode_invalid("/a b|c^d[e]", ""), "/a%20b%7Cc%5Ed%5Be%5D");
+        assert_eq!(encode_invalid("/中", ""), "/%E4%B8%AD");
+        // 完整的%XX保持不变，不完整的%被编码
+        assert_eq!(encode_invalid("/%41%4g%", ""), "/%41%254g%25");
+        assert_eq!(encode_invalid("a=1?b=/c", "?"), "a=1?b=/c");
+        assert_eq!(encode_invalid("a=1?b", ""), "a=1%3Fb");
+    }
+
+    #[test]
+    fn encodes_invalid_characters_in_path_and_query() {
+        assert_eq!(
+            normalized("https://e.com/a|b^c.iso?q=[1]&r=%zz"),
+            "https://e.com/a%7Cb%5Ec.iso?q=%5B1%5D&r=%25zz"
+        );
+        assert_eq!(
+            normalized("https://e.com/a b.iso"),
+            "https://e.com/a%20b.iso"
+        );
+    }
+
+    #[test]
+    fn detects_internationalized_domains() {
+        assert!(is_idn(&normalize_url("https://exämple.com/").unwrap()));
+        assert!(!is_idn(&normalize_url("https://example.com/").unwrap()));
+    }
+}
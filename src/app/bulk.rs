@@ -0,0 +1,69 @@
+use std::fmt::{self, Display, Formatter};
+
+/// 批量操作的结果，例如`Paused 4, skipped 2 already paused, 1 finishing`
+///
+/// 批量操作在遍历时对每一项调用[`BulkSummary::done`]或者[`BulkSummary::skip`]，
+/// 结束之后显示在状态栏中，让用户知道实际影响了多少项。跳过的原因按照第一次出现的顺序显示。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkSummary {
+    verb: &'static str,
+    done: usize,
+    skipped: Vec<(&'static str, usize)>,
+}
+
+impl BulkSummary {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `verb`为完成的操作，显示在数量之前，例如`Paused`
+    pub fn new(verb: &'static str) -> Self {
+        BulkSummary {
+            verb,
+            done: 0,
+            skipped: Vec::new(),
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn done_count(&self) -> usize {
+        self.done
+    }
+
+    /// 因为`reason`被跳过的数量
+    pub fn skipped(&self, reason: &str) -> usize {
+        self.skipped
+            .iter()
+            .find(|(r, _)| *r == reason)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    pub fn skipped_count(&self) -> usize {
+        self.skipped.iter().map(|(_, count)| count).sum()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn done(&mut self) {
+        self.done += 1;
+    }
+
+    pub fn skip(&mut self, reason: &'static str) {
+        match self.skipped.iter_mut().find(|(r, _)| *r == reason) {
+            Some((_, count)) => *count += 1,
+            None => self.skipped.push((reason, 1)),
+        }
+    }
+}
+
+impl Display for BulkSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.verb, self.done)?;
+        for (i, (reason, count)) in self.skipped.iter().enumerate() {
+            match i {
+                0 => write!(f, ", skipped {} {}", count, reason)?,
+                _ => write!(f, ", {} {}", count, reason)?,
+            }
+        }
+        Ok(())
+    }
+}
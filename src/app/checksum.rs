@@ -0,0 +1,583 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+
+/// 支持的哈希算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    /// 只用于检查传输错误，许多镜像仍然只提供`.md5`文件
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// 自动获取校验和文件时依次尝试的算法
+    pub const REMOTE_ORDER: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Md5,
+    ];
+
+    /// 十六进制摘要的长度
+    pub fn hex_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128,
+            HashAlgorithm::Md5 => 32,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha512" => Some(HashAlgorithm::Sha512),
+            "md5" => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha256 => write!(f, "sha256"),
+            HashAlgorithm::Sha512 => write!(f, "sha512"),
+            HashAlgorithm::Md5 => write!(f, "md5"),
+        }
+    }
+}
+
+/// 用户提供的期望校验和，格式为`<算法>:<十六进制摘要>`，例如`sha256:9f86d0...`
+///
+/// 摘要统一保存为小写，比较时不区分大小写。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Checksum {
+    algorithm: HashAlgorithm,
+    digest: String,
+}
+
+impl Checksum {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(algorithm: HashAlgorithm, digest: &str) -> Result<Self, ChecksumError> {
+        if digest.len() != algorithm.hex_len() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ChecksumError::InvalidDigest(algorithm));
+        }
+        Ok(Checksum {
+            algorithm,
+            digest: digest.to_ascii_lowercase(),
+        })
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn matches(&self, actual: &str) -> bool {
+        self.digest.eq_ignore_ascii_case(actual)
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ChecksumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, digest) = s
+            .trim()
+            .split_once(':')
+            .ok_or(ChecksumError::MissingPrefix)?;
+        let algorithm = HashAlgorithm::from_name(algorithm)
+            .ok_or_else(|| ChecksumError::UnknownAlgorithm(algorithm.to_string()))?;
+        Checksum::new(algorithm, digest)
+    }
+}
+
+impl TryFrom<String> for Checksum {
+    type Error = ChecksumError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Checksum> for String {
+    fn from(value: Checksum) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumError {
+    MissingPrefix,
+    UnknownAlgorithm(String),
+    InvalidDigest(HashAlgorithm),
+}
+
+impl Display for ChecksumError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumError::MissingPrefix => {
+                write!(
+                    f,
+                    "Checksum must look like sha256:<hex>, sha512:<hex> or md5:<hex>"
+                )
+            }
+            ChecksumError::UnknownAlgorithm(s) => write!(f, "Unknown checksum algorithm: {}", s),
+            ChecksumError::InvalidDigest(algorithm) => write!(
+                f,
+                "A {} checksum must be {} hex digits",
+                algorithm,
+                algorithm.hex_len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChecksumError {}
+
+/// 校验和的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumSource {
+    /// 用户在下载窗口中输入
+    User,
+    /// 从URL旁边的校验和文件中读取，见[`parse_checksum_file`]
+    Remote,
+}
+
+/// 下载完成后用于校验的校验和以及它的来源，用于在详情弹窗中显示
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedChecksum {
+    pub checksum: Checksum,
+    pub source: ChecksumSource,
+}
+
+impl Display for ExpectedChecksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.source {
+            ChecksumSource::User => write!(f, "{}", self.checksum),
+            ChecksumSource::Remote => write!(
+                f,
+                "{} (checksum from remote .{})",
+                self.checksum, self.checksum.algorithm
+            ),
+        }
+    }
+}
+
+/// 解析镜像发布的校验和文件，例如`file.iso.sha256`，返回`filename`对应的校验和
+///
+/// 支持两种常见的格式：
+///
+/// ```text
+/// 9f86d08...  file.iso          (sha256sum的输出，文件名前可能带有`*`)
+/// SHA256 (file.iso) = 9f86d08... (BSD风格)
+/// ```
+///
+/// 文件中列出多个文件时只使用文件名匹配的一行；只有一行并且没有文件名时直接使用该行。
+/// 算法与文件名不符、格式无法识别或者找不到匹配的文件时返回[`None`]。
+pub fn parse_checksum_file(
+    content: &str,
+    filename: &str,
+    algorithm: HashAlgorithm,
+) -> Option<Checksum> {
+    let entries: Vec<(Option<&str>, &str)> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| parse_checksum_line(line, algorithm))
+        .collect();
+    let digest = match entries.as_slice() {
+        [(None, digest)] => digest,
+        entries => {
+            entries
+                .iter()
+                .find(|(name, _)| name.is_some_and(|name| same_file_name(name, filename)))?
+                .1
+        }
+    };
+    Checksum::new(algorithm, digest).ok()
+}
+
+/// 解析校验和文件中的一行，返回文件名（如果有）以及摘要
+fn parse_checksum_line(line: &str, algorithm: HashAlgorithm) -> Option<(Option<&str>, &str)> {
+    // BSD风格：`SHA256 (file.iso) = <hex>`
+    if let Some((head, digest)) = line.rsplit_once(" = ")
+        && let Some((name, rest)) = head.split_once(" (")
+        && let Some(file) = rest.strip_suffix(')')
+    {
+        return (HashAlgorithm::from_name(name.trim()) == Some(algorithm))
+            .then_some((Some(file), digest.trim()));
+    }
+    // GNU风格：`<hex>  file.iso`或者`<hex> *file.iso`
+    let (digest, file) = match line.split_once(char::is_whitespace) {
+        Some((digest, file)) => (digest, Some(file.trim_start().trim_start_matches('*'))),
+        None => (line, None),
+    };
+    (digest.len() == algorithm.hex_len() && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .then_some((file.filter(|f| !f.is_empty()), digest))
+}
+
+/// 校验和文件中的文件名可能带有`./`或者目录
+fn same_file_name(listed: &str, filename: &str) -> bool {
+    let listed = listed.rsplit(['/', '\\']).next().unwrap_or(listed);
+    listed == filename
+}
+
+/// 增量计算文件的哈希值，下载时边写入边计算，恢复的任务则在下载完成后重新读取文件计算
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+impl Hasher {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    /// 小写的十六进制摘要
+    pub fn finalize(self) -> String {
+        let digest = match self {
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// 边下载边计算哈希值，网速较快并且使用SHA-512时，计算哈希值可能比网络和磁盘更慢
+///
+/// 默认在单独的阻塞线程中计算，与网络读取和磁盘写入同时进行。数据块通过有界channel传递
+/// （复制[`Bytes`]只增加引用计数），计算跟不上时写入文件的一方等待，不会无限制地占用内存。
+/// 丢弃时channel关闭，后台线程随之退出，因此暂停和取消不需要额外的处理。
+pub enum StreamHasher {
+    /// 在写入文件的循环中直接计算，不在tokio运行时中时使用
+    Inline(Box<Hasher>),
+    Pipelined {
+        sender: mpsc::Sender<Bytes>,
+        digest: oneshot::Receiver<String>,
+    },
+    /// 后台线程提前退出，摘要已经不完整
+    Broken,
+}
+
+impl StreamHasher {
+    // ------------------- CONSTANT -----------------------
+
+    /// channel中最多等待计算的数据块数量，较小的值使计算跟不上时尽快等待，
+    /// 而不是让数据堆积在内存中
+    pub const PIPELINE_CAPACITY: usize = 8;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 在当前的tokio运行时中启动后台线程，无法启动时退回到直接计算
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match Handle::try_current() {
+            Ok(runtime) => StreamHasher::pipelined(algorithm, &runtime),
+            Err(_) => StreamHasher::inline(algorithm),
+        }
+    }
+
+    pub fn inline(algorithm: HashAlgorithm) -> Self {
+        StreamHasher::Inline(Box::new(Hasher::new(algorithm)))
+    }
+
+    pub fn pipelined(algorithm: HashAlgorithm, runtime: &Handle) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Bytes>(Self::PIPELINE_CAPACITY);
+        let (digest_sender, digest) = oneshot::channel();
+        runtime.spawn_blocking(move || {
+            let mut hasher = Hasher::new(algorithm);
+            while let Some(data) = receiver.blocking_recv() {
+                hasher.update(&data);
+            }
+            // 下载被暂停或者取消时接收端已经丢弃，摘要也就不需要了
+            let _ = digest_sender.send(hasher.finalize());
+        });
+        StreamHasher::Pipelined { sender, digest }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 计算落后时等待channel中出现空位
+    pub async fn update(&mut self, data: &Bytes) {
+        match self {
+            StreamHasher::Inline(hasher) => hasher.update(data),
+            StreamHasher::Pipelined { sender, .. } => {
+                if sender.send(data.clone()).await.is_err() {
+                    *self = StreamHasher::Broken;
+                }
+            }
+            StreamHasher::Broken => {}
+        }
+    }
+
+    /// 结束输入并等待摘要，后台线程异常退出时为[`None`]，此时需要重新读取文件计算
+    pub async fn finish(self) -> Option<String> {
+        match self {
+            StreamHasher::Inline(hasher) => Some(hasher.finalize()),
+            StreamHasher::Pipelined { sender, digest } => {
+                drop(sender);
+                digest.await.ok()
+            }
+            StreamHasher::Broken => None,
+        }
+    }
+}
+
+/// RFC 1321中的MD5，sha2没有提供该算法，只为了读取`.md5`文件不值得再引入一个依赖
+pub struct Md5 {
+    state: [u32; 4],
+    buffer: [u8; 64],
+    buffered: usize,
+    length: u64,
+}
+
+impl Md5 {
+    // ------------------- CONSTANT -----------------------
+
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+
+    const CONSTANTS: [u32; 64] = [
+        0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613,
+        0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193,
+        0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d,
+        0x02441453, 0xd8a1e681, 0xe7d3fbc8, 0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed,
+        0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122,
+        0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+        0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665, 0xf4292244,
+        0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+        0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb,
+        0xeb86d391,
+    ];
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; 64],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let n = (64 - self.buffered).min(data.len());
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+            if self.buffered == 64 {
+                let block = self.buffer;
+                self.compress(&block);
+                self.buffered = 0;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        let bits = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.buffered != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_le_bytes());
+        let mut digest = [0; 16];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(Self::CONSTANTS[i])
+                .wrapping_add(words[g])
+                .rotate_left(Self::SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256_ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn md5(data: &[u8]) -> String {
+        let mut hasher = Hasher::new(HashAlgorithm::Md5);
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    #[test]
+    fn md5_matches_reference_digests() {
+        assert_eq!(md5(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        // 超过一个块（64字节）并且跨越填充的边界
+        assert_eq!(
+            md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn md5_updates_in_pieces() {
+        let data = [7u8; 200];
+        let mut hasher = Hasher::new(HashAlgorithm::Md5);
+        for chunk in data.chunks(13) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hasher.finalize(), md5(&data));
+    }
+
+    #[test]
+    fn parse_gnu_and_bsd_lines() {
+        let gnu = format!(
+            "{}  ubuntu.iso\n{} *other.iso\n",
+            SHA256_ABC,
+            "0".repeat(64)
+        );
+        let checksum = parse_checksum_file(&gnu, "ubuntu.iso", HashAlgorithm::Sha256).unwrap();
+        assert_eq!(checksum.digest(), SHA256_ABC);
+        assert_eq!(
+            parse_checksum_file(&gnu, "other.iso", HashAlgorithm::Sha256)
+                .unwrap()
+                .digest(),
+            "0".repeat(64)
+        );
+
+        let bsd = format!("SHA256 (./dist/ubuntu.iso) = {}", SHA256_ABC.to_uppercase());
+        let checksum = parse_checksum_file(&bsd, "ubuntu.iso", HashAlgorithm::Sha256).unwrap();
+        assert_eq!(checksum.digest(), SHA256_ABC);
+    }
+
+    #[test]
+    fn single_bare_digest_is_used_directly() {
+        let content = format!("# generated\n\n{}\n", SHA256_ABC);
+        assert_eq!(
+            parse_checksum_file(&content, "any.iso", HashAlgorithm::Sha256)
+                .unwrap()
+                .digest(),
+            SHA256_ABC
+        );
+    }
+
+    #[test]
+    fn mismatched_files_are_rejected() {
+        let gnu = format!("{}  ubuntu.iso", SHA256_ABC);
+        assert_eq!(
+            parse_checksum_file(&gnu, "debian.iso", HashAlgorithm::Sha256),
+            None
+        );
+        // 摘要长度与算法不符
+        assert_eq!(
+            parse_checksum_file(&gnu, "ubuntu.iso", HashAlgorithm::Md5),
+            None
+        );
+        let bsd = format!("SHA256 (ubuntu.iso) = {}", SHA256_ABC);
+        assert_eq!(
+            parse_checksum_file(&bsd, "ubuntu.iso", HashAlgorithm::Sha512),
+            None
+        );
+        assert_eq!(
+            parse_checksum_file(
+                "<html>Not Found</html>",
+                "ubuntu.iso",
+                HashAlgorithm::Sha256
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_user_checksum() {
+        let checksum: Checksum = format!("SHA256:{}", SHA256_ABC.to_uppercase())
+            .parse()
+            .unwrap();
+        assert_eq!(checksum.algorithm(), HashAlgorithm::Sha256);
+        assert!(checksum.matches(SHA256_ABC));
+        assert_eq!(checksum.to_string(), format!("sha256:{}", SHA256_ABC));
+        assert_eq!(
+            SHA256_ABC.parse::<Checksum>(),
+            Err(ChecksumError::MissingPrefix)
+        );
+        assert_eq!(
+            "md5:abc".parse::<Checksum>(),
+            Err(ChecksumError::InvalidDigest(HashAlgorithm::Md5))
+        );
+    }
+}
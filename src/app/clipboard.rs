@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use url::Url;
+
+use crate::app::App;
+use crate::app::sender::RequestOptions;
+use crate::window::WidgetType;
+use crate::window::app::DownloadList;
+
+/// 监视剪贴板，复制了新的http(s) URL时提示用户是否下载
+///
+/// 后台运行时中每隔[`ClipboardWatcher::INTERVAL`]读取一次剪贴板，发现新的URL时交给UI线程，
+/// UI线程在右下角显示提示，用户在[`ClipboardWatcher::OFFER_TIMEOUT`]内按下`y`才会添加任务，
+/// 不会自动开始下载。
+///
+/// - 配置了`clipboard_extensions`时，只提示路径以其中某个扩展名结尾的URL；
+/// - 最近出现过的内容以及已经在列表中的URL不会重复提示；
+/// - 开启监视时剪贴板中已有的内容，以及弹窗中正在输入文本时复制的内容都不会提示，
+///   避免复制用来粘贴的文本时打扰用户，见[`ClipboardWatcher::set_suspended`]。
+#[derive(Debug)]
+pub struct ClipboardWatcher {
+    shared: Arc<WatchShared>,
+    extensions: Arc<Vec<String>>,
+    // 等待用户确认的URL
+    offer: Option<ClipboardOffer>,
+}
+
+/// 后台运行时与UI线程共享的状态
+#[derive(Debug, Default)]
+struct WatchShared {
+    enabled: AtomicBool,
+    suspended: AtomicBool,
+    // 后台发现的新URL，由UI线程取走
+    found: Mutex<Option<String>>,
+    // 无法访问剪贴板时的错误，由UI线程取走并提示一次
+    error: Mutex<Option<String>>,
+}
+
+/// 等待用户确认的URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardOffer {
+    pub url: String,
+    pub since: Instant,
+}
+
+impl ClipboardWatcher {
+    // ------------------- CONSTANT -----------------------
+
+    pub const INTERVAL: Duration = Duration::from_secs(1);
+    /// 提示显示的时间，超时之后按`y`不再有效
+    pub const OFFER_TIMEOUT: Duration = Duration::from_secs(8);
+    /// 记住的最近的剪贴板内容的数量
+    const RECENT: usize = 32;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `extensions`为空时提示所有http(s) URL，扩展名不区分大小写，可以带有开头的`.`
+    pub fn new(enabled: bool, extensions: &[String]) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        let shared = WatchShared::default();
+        shared.enabled.store(enabled, Ordering::Relaxed);
+        ClipboardWatcher {
+            shared: Arc::new(shared),
+            extensions: Arc::new(extensions),
+            offer: None,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::Relaxed)
+    }
+
+    /// 还没有超时的提示
+    pub fn offer(&self, now: Instant) -> Option<&ClipboardOffer> {
+        self.offer
+            .as_ref()
+            .filter(|offer| now.duration_since(offer.since) < Self::OFFER_TIMEOUT)
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 开启或者关闭监视，返回切换之后的状态，关闭时同时丢弃等待确认的URL
+    pub fn toggle(&mut self) -> bool {
+        let enabled = !self.is_enabled();
+        self.shared.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.offer = None;
+        }
+        log::info!(target:"Clipboard", "Clipboard watching {}", if enabled { "on" } else { "off" });
+        enabled
+    }
+
+    /// 弹窗中正在输入文本时暂停提示，期间复制的内容之后也不会提示
+    pub fn set_suspended(&self, suspended: bool) {
+        self.shared.suspended.store(suspended, Ordering::Relaxed);
+    }
+
+    /// 取出后台发现的新URL作为提示，`is_queued`返回`true`的URL已经在列表中，不再提示
+    ///
+    /// 返回无法访问剪贴板时的错误，每次运行只返回一次。
+    pub fn poll(&mut self, now: Instant, is_queued: impl Fn(&str) -> bool) -> Option<String> {
+        if self.offer(now).is_none() {
+            self.offer = None;
+        }
+        let found = self.shared.found.lock().unwrap().take();
+        if let Some(url) = found
+            && !is_queued(&url)
+        {
+            log::debug!(target:"Clipboard", "Offering copied URL {}", url);
+            self.offer = Some(ClipboardOffer { url, since: now });
+        }
+        self.shared.error.lock().unwrap().take()
+    }
+
+    /// 用户确认下载，返回需要添加的URL，已经超时时返回[`None`]
+    pub fn accept(&mut self, now: Instant) -> Option<String> {
+        self.offer(now)?;
+        self.offer.take().map(|offer| offer.url)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 在后台运行时中开始定期读取剪贴板，运行时关闭时随之结束
+    pub fn start(&self, runtime: &Handle) {
+        let shared = self.shared.clone();
+        let extensions = self.extensions.clone();
+        runtime.spawn(async move {
+            let mut clipboard = None;
+            // 上一次读到的内容，为None时下一次读到的内容只作为基准，不提示
+            let mut last: Option<String> = None;
+            let mut recent = VecDeque::with_capacity(Self::RECENT);
+            loop {
+                tokio::time::sleep(Self::INTERVAL).await;
+                if !shared.enabled.load(Ordering::Relaxed) {
+                    last = None;
+                    continue;
+                }
+                let read = tokio::task::spawn_blocking(move || read_text(clipboard)).await;
+                let text = match read {
+                    Ok(Ok((handle, text))) => {
+                        clipboard = Some(handle);
+                        text
+                    }
+                    Ok(Err(e)) => {
+                        log::warn!(target:"Clipboard", "Clipboard watching stopped: {}", e);
+                        *shared.error.lock().unwrap() = Some(e.to_string());
+                        return;
+                    }
+                    Err(_) => return,
+                };
+                // 剪贴板中不是文本时保持上一次的内容，之后复制回相同的文本也不会提示
+                let Some(text) = text else {
+                    continue;
+                };
+                if last.as_deref() == Some(text.as_str()) {
+                    continue;
+                }
+                let baseline = last.replace(text.clone()).is_none();
+                let Some(url) = candidate_url(&text, &extensions) else {
+                    continue;
+                };
+                let seen = recent.contains(&url);
+                if !seen {
+                    if recent.len() == Self::RECENT {
+                        recent.pop_front();
+                    }
+                    recent.push_back(url.clone());
+                }
+                if !baseline && !seen && !shared.suspended.load(Ordering::Relaxed) {
+                    *shared.found.lock().unwrap() = Some(url);
+                }
+            }
+        });
+    }
+}
+
+/// 读取剪贴板中的文本，第一次读取时连接剪贴板
+///
+/// 剪贴板为空或者不是文本时返回`Ok(None)`，无法连接剪贴板时返回错误。
+fn read_text(
+    clipboard: Option<arboard::Clipboard>,
+) -> Result<(arboard::Clipboard, Option<String>), arboard::Error> {
+    let mut clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => arboard::Clipboard::new()?,
+    };
+    let text = clipboard.get_text().ok();
+    Ok((clipboard, text))
+}
+
+/// 将`text`写入剪贴板，用于复制About弹窗中的内容等
+///
+/// 在X11下，剪贴板的内容由写入的程序持有，这里的连接关闭时交给剪贴板管理器（如果有的话）。
+pub fn write_text(text: &str) -> Result<(), arboard::Error> {
+    arboard::Clipboard::new()?.set_text(text)
+}
+
+/// 剪贴板中的内容是一个http(s) URL，并且（配置了扩展名时）路径以其中某个扩展名结尾
+pub fn candidate_url(text: &str, extensions: &[String]) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = Url::parse(text).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return None;
+    }
+    if !extensions.is_empty() {
+        let path = url.path().to_ascii_lowercase();
+        let (_, ext) = path.rsplit_once('.')?;
+        if !extensions.iter().any(|e| e == ext) {
+            return None;
+        }
+    }
+    Some(text.to_string())
+}
+
+impl App {
+    // ------------------- HANDLE_ASYNC ---------------------
+
+    /// 弹窗中正在输入文本时暂停提示，取出后台发现的URL
+    pub(crate) fn handle_clipboard(&mut self) {
+        let editing = self.widgets.iter().any(WidgetType::is_editing);
+        self.clipboard.set_suspended(editing);
+        let downloading = &self.data.downloading;
+        if let Some(e) = self
+            .clipboard
+            .poll(Instant::now(), |url| downloading.is_queued(url))
+        {
+            self.notices
+                .push(format!("Clipboard watching stopped: {}", e));
+        }
+    }
+
+    // ------------------ HANDLE_MESSAGE --------------------
+
+    pub(crate) fn toggle_clipboard_watch(&mut self) {
+        let message = match self.clipboard.toggle() {
+            true => "Clipboard watching on, copy a URL to download it",
+            false => "Clipboard watching off",
+        };
+        self.notices.push(String::from(message));
+    }
+
+    /// 按下`y`时添加等待确认的URL，没有提示或者已经超时时什么都不做
+    pub(crate) fn accept_clipboard_url(&mut self) {
+        if let Some(url) = self.clipboard.accept(Instant::now()) {
+            if self
+                .data
+                .downloading
+                .append_normal_task(RequestOptions::new(url.clone(), None))
+                .is_none()
+            {
+                self.notices
+                    .push(String::from(DownloadList::READ_ONLY_NOTICE));
+                return;
+            }
+            log::info!(target:"Clipboard", "Queued copied URL {}", url);
+        }
+    }
+
+    /// 右下角的提示，见[`ClipboardWatcher::OFFER_TIMEOUT`]
+    pub(crate) fn clipboard_prompt(&self) -> Option<String> {
+        self.clipboard
+            .offer(Instant::now())
+            .map(|offer| format!("Download copied URL? (y) {}", offer.url))
+    }
+}
@@ -0,0 +1,376 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::Arc, thread};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::app::disk;
+use crate::app::input::UnfocusedKeys;
+use crate::app::network::IpVersion;
+use crate::app::opener::{self, FileCategory};
+use crate::app::policy::FileTypePolicy;
+use crate::app::task::{
+    AdmissionPolicy, BufferSizes, EtaEstimator, HealthThresholds, MiddleRowMode, ObserverKind,
+    OversizedPartial, SpeedDisplay, TaskManager, WarmUp, resolve,
+};
+use crate::app::throttle::{ScheduleEntry, SpeedLimit};
+use crate::app::timefmt::TimeConfig;
+use crate::app::timespan::TimeSpan;
+
+/// 程序的配置，以TOML格式存储在配置目录下的`config.toml`中。
+///
+/// 所有字段都有默认值，因此配置文件中缺失的字段会使用默认值填充，
+/// 配置文件不存在或者解析失败时，使用[`Config::default`]。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 下载目录，为[`None`]时使用[`resolve::default_download_dir`]
+    pub download_dir: Option<PathBuf>,
+    /// 下载完成（并且校验通过）的文件会另外硬链接到该目录，跨文件系统时复制，
+    /// 用于只监视完整文件的其他程序
+    pub completed_dir: Option<PathBuf>,
+    /// 下载页面中每个任务中间一行显示的内容
+    pub middle_row: MiddleRowMode,
+    /// 下载页面中是否在已经下载的大小之后显示剩余的大小，可以使用`r`切换
+    pub show_remaining: bool,
+    /// 后台运行时的工作线程数量，为[`None`]时使用[`Config::DEFAULT_MAX_WORKER_THREADS`]
+    /// 与CPU核心数中较小的值
+    pub worker_threads: Option<usize>,
+    /// 后台运行时工作线程的名称
+    pub thread_name: String,
+    /// 是否在Stats页面显示后台运行时的统计信息
+    pub runtime_stats: bool,
+    /// 所有任务共享的速度上限，不在`speed_schedule`的任何时间段内时使用
+    pub speed_limit: SpeedLimit,
+    /// 按时间段设置的速度上限，例如
+    /// `speed_schedule = [{ from = "08:00", to = "23:00", limit = "2M" }]`
+    pub speed_schedule: Vec<ScheduleEntry>,
+    /// 多个任务同时下载时平分速度上限，避免单个任务占满带宽，可以使用`F`切换
+    pub fair_share: bool,
+    /// 因为磁盘空间不足而暂停的任务，在剩余空间超过所需空间加上该值（MiB）时自动恢复
+    pub disk_headroom_mib: u64,
+    /// 只使用ASCII符号，用于无法显示特殊符号的终端
+    pub ascii_symbols: bool,
+    /// 关闭动画，活动指示器显示为静止的符号，并且降低界面的刷新频率，
+    /// 适用于对动画敏感的用户或者较慢的SSH连接
+    pub reduced_motion: bool,
+    /// 同时进行的任务数量上限，为0时不限制
+    pub max_concurrent_tasks: usize,
+    /// 对同一个主机同时进行的任务数量上限，为0时不限制
+    pub max_tasks_per_host: usize,
+    /// 排队的任务开始的顺序，见[`AdmissionPolicy`]
+    pub admission_policy: AdmissionPolicy,
+    /// 名额即将空出时，排在最前面的任务提前解析域名（`dns`）或者建立连接（`connect`），
+    /// 默认为`off`，见[`WarmUp`]
+    pub warm_up: WarmUp,
+    /// 每个任务写入文件的缓冲区大小（KiB），磁盘较慢并且同时下载的任务较多时，
+    /// 这决定了下载占用的内存，最小为4
+    pub write_buffer_kib: usize,
+    /// 下载窗口中“下载完成后自动解压”的默认值
+    pub extract_archives: bool,
+    /// 连接服务器时使用的IP版本，`auto`、`v4`或者`v6`，
+    /// 适用于某些镜像的IPv6连接不可用的双栈网络
+    pub ip_version: IpVersion,
+    /// 连接时绑定的网络接口，例如`eth0`，只在Linux和macOS等平台上可用
+    pub bind_interface: Option<String>,
+    /// 连接时绑定的本地地址，例如`192.168.1.2`，需要与`ip_version`一致
+    pub bind_address: Option<String>,
+    /// 根据扩展名或者MIME类型提示或者阻止下载，见[`FileTypePolicy`]
+    pub file_type_policy: FileTypePolicy,
+    /// 启用的内置任务观察者，见[`ObserverKind`]
+    pub task_observers: Vec<ObserverKind>,
+    /// 每种类别的文件可以选择用来打开的程序，例如`video = ["mpv", "vlc"]`，
+    /// `other`中的程序可以用于所有类别
+    pub open_with: BTreeMap<FileCategory, Vec<String>>,
+    /// 下载成功的任务在完成页面中保留的时间，例如`"30m"`，之后自动从列表中移除，
+    /// 失败的任务不会被移除。为`"0"`时关闭，见[`TimeSpan`]
+    pub auto_clear_finished_after: TimeSpan,
+    /// `--doctor`检查网络时请求的URL，用于检查DNS、代理以及TLS根证书
+    pub doctor_probe_url: String,
+    /// 在终端标题中显示下载进度，支持的终端中同时在标签页或者任务栏中显示，
+    /// 见[`TerminalProgress`]
+    ///
+    /// [`TerminalProgress`]: crate::app::title::TerminalProgress
+    pub terminal_progress: bool,
+    /// 没有输入校验和时，尝试获取URL旁边的`.sha256`、`.sha512`或者`.md5`文件，
+    /// 找到时下载完成后使用其中的校验和进行校验
+    pub auto_checksum: bool,
+    /// 自动生成的文件名已经存在，并且大小（以及校验和）与要下载的文件相同时，
+    /// 默认直接使用已有的文件，为`true`时总是重新下载并添加后缀
+    pub redownload_existing: bool,
+    /// 恢复下载时，已经下载的部分比记录的进度长时截断到记录的长度（`truncate`）
+    /// 还是从头开始下载（`restart`）。比记录的短时总是从文件的末尾继续
+    pub oversized_partial: OversizedPartial,
+    /// 任务等待用户处理文件冲突或者确认文件类型的时间上限，超时后文件冲突时自动添加后缀，
+    /// 确认文件类型时取消任务。为`"0"`时一直等待，见[`TimeSpan`]
+    pub decision_timeout: TimeSpan,
+    /// 判断下载中的任务是否变慢或者停滞的阈值，见[`HealthThresholds`]
+    pub task_health: HealthThresholds,
+    /// 显示最近一次采样的速度（`instant`）还是平滑后的速度（`smoothed`），
+    /// 可以在Stats页面中使用`s`切换
+    pub speed_display: SpeedDisplay,
+    /// 平滑速度时使用的半衰期（毫秒），越大越平稳，但是对速度变化的反应也越慢，
+    /// 剩余时间的估计使用同样的平滑
+    pub speed_half_life_ms: u64,
+    /// 下载完成的文件的权限，例如`"0644"`，为空时使用系统默认的权限，只在Unix中生效，
+    /// 下载窗口中可以为单个任务指定，见[`FileModes`]
+    ///
+    /// [`FileModes`]: crate::app::permissions::FileModes
+    pub file_mode: Option<String>,
+    /// 程序创建的下载目录（包括命名模板中的子目录）的权限，例如`"0755"`，只在Unix中生效
+    pub dir_mode: Option<String>,
+    /// 启动时是否监视剪贴板，复制了URL时提示是否下载，运行时可以使用`w`切换，
+    /// 需要启用`clipboard`特性
+    pub clipboard_watch: bool,
+    /// 只提示路径以这些扩展名结尾的URL，例如`["iso", "zip"]`，为空时提示所有http(s) URL
+    pub clipboard_extensions: Vec<String>,
+    /// 界面中显示时间的格式，strftime格式，例如`"%d/%m %H:%M"`
+    pub time_format: String,
+    /// 列表中最近的时间是否显示为相对时间，例如`12m ago`，见[`TimeConfig`]
+    ///
+    /// [`TimeConfig`]: crate::app::timefmt::TimeConfig
+    pub relative_times: bool,
+    /// 所有下载完成之后可以运行的命令，例如`["systemctl", "suspend"]`，不经过shell，
+    /// 运行时使用`Z`选择是否运行
+    pub when_done_command: Vec<String>,
+    /// 还没有进入页面时按下页面中的按键：`hint`提示先进入页面，`enter`直接进入页面并处理该按键
+    pub unfocused_keys: UnfocusedKeys,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            download_dir: None,
+            completed_dir: None,
+            middle_row: MiddleRowMode::default(),
+            show_remaining: false,
+            worker_threads: None,
+            thread_name: String::from("request-tui-worker"),
+            runtime_stats: false,
+            speed_limit: SpeedLimit::UNLIMITED,
+            speed_schedule: Vec::new(),
+            fair_share: false,
+            disk_headroom_mib: 64,
+            ascii_symbols: false,
+            reduced_motion: false,
+            max_concurrent_tasks: 4,
+            max_tasks_per_host: 2,
+            admission_policy: AdmissionPolicy::Fifo,
+            warm_up: WarmUp::Off,
+            write_buffer_kib: BufferSizes::DEFAULT_WRITE_KIB,
+            extract_archives: false,
+            ip_version: IpVersion::Auto,
+            bind_interface: None,
+            bind_address: None,
+            file_type_policy: FileTypePolicy::default(),
+            task_observers: vec![ObserverKind::Log],
+            open_with: opener::default_open_with(),
+            auto_clear_finished_after: TimeSpan::OFF,
+            doctor_probe_url: String::from("https://example.com/"),
+            terminal_progress: false,
+            auto_checksum: false,
+            redownload_existing: false,
+            oversized_partial: OversizedPartial::default(),
+            decision_timeout: TimeSpan::from_secs(TaskManager::DEFAULT_DECISION_TIMEOUT.as_secs()),
+            task_health: HealthThresholds::default(),
+            speed_display: SpeedDisplay::default(),
+            speed_half_life_ms: EtaEstimator::DEFAULT_HALF_LIFE.as_millis() as u64,
+            file_mode: None,
+            dir_mode: None,
+            clipboard_watch: false,
+            clipboard_extensions: Vec::new(),
+            time_format: String::from(TimeConfig::DEFAULT_FORMAT),
+            relative_times: true,
+            when_done_command: Vec::new(),
+            unfocused_keys: UnfocusedKeys::default(),
+        }
+    }
+}
+
+impl Config {
+    // ------------------- CONSTANT -----------------------
+
+    pub const FILENAME: &'static str = "config.toml";
+
+    // 下载任务主要是IO密集型的，不需要太多的工作线程
+    pub const DEFAULT_MAX_WORKER_THREADS: usize = 4;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 从配置文件中读取配置，失败时使用默认配置
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Config::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                log::warn!(target:"Config", "Failed to parse {}: {}", path.display(), e);
+                Config::default()
+            }),
+            Err(_) => Config::default(),
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 实际使用的下载目录
+    pub fn download_dir(&self) -> PathBuf {
+        self.download_dir
+            .clone()
+            .unwrap_or_else(resolve::default_download_dir)
+    }
+
+    /// 实际使用的工作线程数量，至少为1
+    pub fn worker_threads(&self) -> usize {
+        self.worker_threads
+            .unwrap_or_else(|| {
+                thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+                    .min(Self::DEFAULT_MAX_WORKER_THREADS)
+            })
+            .max(1)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 配置文件是否存在，不存在时视为首次运行
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|path| path.exists())
+    }
+
+    /// 配置文件的路径，如果无法确定用户目录，则返回[`None`]
+    pub fn path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "request-tui")
+            .map(|dirs| dirs.config_dir().join(Self::FILENAME))
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or(anyhow::anyhow!("Cannot determine config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        disk::write_atomic(&path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 只在本次运行中生效的设置，来自命令行参数，见[`Cli::overrides`]
+///
+/// [`Cli::overrides`]: crate::cli::Cli::overrides
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigOverrides {
+    pub worker_threads: Option<usize>,
+    pub reduced_motion: bool,
+}
+
+impl ConfigOverrides {
+    // -------------------- FUNCTION -----------------------
+
+    /// 使用这些设置覆盖`config`中对应的值
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(threads) = self.worker_threads {
+            config.worker_threads = Some(threads);
+        }
+        if self.reduced_motion {
+            config.reduced_motion = true;
+        }
+    }
+}
+
+/// 运行期间可以修改的配置，由[`App`]持有
+///
+/// 修改时整体替换为新的`Arc<Config>`，已经取得旧配置的一方不会看到修改了一半的值。
+/// 后台长期运行的部分（例如[`TaskManager`]）通过[`LiveConfig::subscribe`]得到接收端，
+/// 在接收任务等合适的时机读取最新的配置；界面在每一帧绘制时读取[`LiveConfig::current`]，不需要通知。
+///
+/// 命令行参数的覆盖单独保存在[`ConfigOverrides`]中，只体现在[`LiveConfig::current`]里，
+/// 修改和保存都针对[`LiveConfig::stored`]，因此不会被写入配置文件。
+///
+/// [`App`]: crate::app::App
+#[derive(Debug)]
+pub struct LiveConfig {
+    sender: watch::Sender<Arc<Config>>,
+    // 配置文件中的设置，不包含命令行参数的覆盖
+    stored: Config,
+    overrides: ConfigOverrides,
+}
+
+impl LiveConfig {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(config: Config) -> Self {
+        LiveConfig {
+            sender: watch::Sender::new(Arc::new(config.clone())),
+            stored: config,
+            overrides: ConfigOverrides::default(),
+        }
+    }
+
+    /// 在配置文件的设置之上使用`overrides`，见[`ConfigOverrides`]
+    pub fn with_overrides(mut self, overrides: ConfigOverrides) -> Self {
+        self.overrides = overrides;
+        self.publish();
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 实际使用的配置，包含命令行参数的覆盖
+    pub fn current(&self) -> Arc<Config> {
+        self.sender.borrow().clone()
+    }
+
+    /// 写入配置文件的设置，不包含命令行参数的覆盖，设置向导以此为初始值
+    pub fn stored(&self) -> &Config {
+        &self.stored
+    }
+
+    /// 之后每次修改都会通知返回的接收端
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Config>> {
+        self.sender.subscribe()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 修改配置文件中的设置，重新应用命令行参数的覆盖之后通知所有接收端，返回新的配置
+    ///
+    /// 没有接收端时同样替换，之后订阅的接收端从新的配置开始。
+    pub fn update(&mut self, modify: impl FnOnce(&mut Config)) -> Arc<Config> {
+        modify(&mut self.stored);
+        self.publish()
+    }
+
+    fn publish(&self) -> Arc<Config> {
+        let mut config = self.stored.clone();
+        self.overrides.apply(&mut config);
+        let config = Arc::new(config);
+        self.sender.send_replace(config.clone());
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_are_not_stored() {
+        let overrides = ConfigOverrides {
+            worker_threads: Some(2),
+            reduced_motion: true,
+        };
+        let mut config = LiveConfig::new(Config::default()).with_overrides(overrides);
+        let receiver = config.subscribe();
+        assert_eq!(config.current().worker_threads, Some(2));
+        assert!(config.current().reduced_motion);
+
+        let updated = config.update(|config| config.max_concurrent_tasks = 7);
+        // 修改之后覆盖仍然有效，但是不会出现在写入配置文件的设置中
+        assert!(updated.reduced_motion);
+        assert_eq!(receiver.borrow().worker_threads, Some(2));
+        assert_eq!(receiver.borrow().max_concurrent_tasks, 7);
+        assert_eq!(config.stored().worker_threads, None);
+        assert!(!config.stored().reduced_motion);
+        assert_eq!(config.stored().max_concurrent_tasks, 7);
+    }
+}
@@ -0,0 +1,407 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::app::listener::{ListenerPhase, PauseOrigin, TaskListener};
+use crate::app::pending::PendingTask;
+use crate::app::sender::RequestOptions;
+use crate::app::task::{TaskId, format_error_brief};
+use crate::window::app::DownloadList;
+
+#[cfg(not(unix))]
+use std::net::{TcpListener as Listener, TcpStream as Stream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+
+/// 外部程序通过控制端口发送的命令，每行一个JSON对象
+///
+/// ```text
+/// {"cmd":"add","url":"https://example.com/file.zip"}
+/// {"cmd":"list"}
+/// {"cmd":"stop","id":3}
+/// {"cmd":"status"}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// 与在下载窗口中输入URL相同，任务先进入等待队列
+    Add {
+        url: String,
+    },
+    /// 列出正在进行以及等待发送的任务
+    List,
+    /// 与在列表中选中任务之后按下停止键相同
+    Stop {
+        id: TaskId,
+    },
+    Status,
+}
+
+/// 对每个命令的回复，同样是一行JSON对象，`result`为`error`时表示命令失败
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum ControlResponse {
+    Added {
+        id: TaskId,
+    },
+    Tasks {
+        tasks: Vec<ControlTask>,
+    },
+    Stopped {
+        id: TaskId,
+    },
+    Status {
+        /// 列表中的任务数量，包括暂停和失败的任务
+        tasks: usize,
+        /// 正在传输数据的任务数量
+        active: usize,
+        pending: usize,
+        finished: usize,
+        /// 所有任务的速度之和（B/s）
+        speed: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// `list`命令返回的单个任务
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlTask {
+    pub id: TaskId,
+    pub url: Option<String>,
+    /// 等待发送的任务还没有确定保存路径
+    pub filepath: Option<PathBuf>,
+    pub downloaded: u64,
+    pub content_length: Option<u64>,
+    pub speed: u64,
+    /// 与列表中左下角显示的状态相同
+    pub status: String,
+}
+
+impl ControlTask {
+    // -------------------- CONSTRUCT ---------------------
+
+    fn from_listener(listener: &TaskListener) -> Self {
+        let state = listener.get_state_handler();
+        let state = state.lock().unwrap();
+        let stopping = listener.stopping_label(Instant::now());
+        let status = match (listener.phase(), listener.task_result()) {
+            _ if let Some(label) = stopping => String::from(label),
+            (ListenerPhase::Paused, _)
+                if listener.pause_origin() == Some(PauseOrigin::BulkPause) =>
+            {
+                String::from("Stopped (all)")
+            }
+            (ListenerPhase::Paused | ListenerPhase::Terminal(_), Some(result)) => {
+                format_error_brief(result, usize::MAX)
+            }
+            _ => String::from(state.status_label()),
+        };
+        ControlTask {
+            id: listener.id(),
+            url: state.url().map(|url| url.to_string()),
+            filepath: Some(state.filepath().to_path_buf()),
+            downloaded: state.downloaded(),
+            content_length: state.content_length(),
+            speed: listener.recent_speed(),
+            status,
+        }
+    }
+
+    fn from_pending(pending: &PendingTask) -> Self {
+        ControlTask {
+            id: pending.id,
+            url: Some(pending.url.clone()),
+            filepath: None,
+            downloaded: 0,
+            content_length: None,
+            speed: 0,
+            status: String::from("Pending"),
+        }
+    }
+}
+
+/// 由连接线程发送给UI线程的命令，UI线程执行之后通过`reply`回复
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: Sender<ControlResponse>,
+}
+
+/// 用于外部程序（例如浏览器扩展或者shell别名）控制正在运行的TUI
+///
+/// Unix中监听数据目录中`control/control.sock`，所在目录的权限为只有当前用户可以访问，
+/// 因此套接字从创建起就不会被其他用户连接；
+/// 其他平台中监听`127.0.0.1`的随机端口，并将地址写入数据目录中的`control/control.addr`。
+/// 这两个文件在退出时删除。
+///
+/// 每个连接在单独的线程中读取命令，命令通过通道交给UI线程，由[`App::handle_async`]
+/// 调用与按键相同的方法执行，因此行为与键盘操作一致。
+pub struct ControlServer {
+    requests: Receiver<ControlRequest>,
+    path: PathBuf,
+}
+
+impl ControlServer {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DIRNAME: &'static str = "control";
+    #[cfg(unix)]
+    pub const FILENAME: &'static str = "control.sock";
+    #[cfg(not(unix))]
+    pub const FILENAME: &'static str = "control.addr";
+
+    /// 等待UI线程回复的最长时间，UI线程每帧都会处理命令，正常情况下不会超时
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 在数据目录中开始监听，已经有其他实例在监听时返回错误
+    pub fn bind() -> io::Result<Self> {
+        Self::bind_in(&Self::data_dir()?)
+    }
+
+    /// 与[`ControlServer::bind`]相同，在`data_dir`中监听
+    pub fn bind_in(data_dir: &Path) -> io::Result<Self> {
+        let path = Self::path_in(data_dir);
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
+        }
+        let listener = Self::listen(&path)?;
+        log::info!(target:"Control", "Listening on {}", path.display());
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let tx = tx.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = serve(stream, tx) {
+                                log::debug!(target:"Control", "Connection closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::warn!(target:"Control", "Failed to accept connection: {}", e),
+                }
+            }
+        });
+        Ok(ControlServer { requests: rx, path })
+    }
+
+    #[cfg(unix)]
+    fn listen(path: &Path) -> io::Result<Listener> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if path.exists() {
+            // 能够连接时说明另一个实例正在运行，否则是上次异常退出留下的文件
+            if Stream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is used by another instance", path.display()),
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+        // 所在目录只有当前用户可以访问，绑定之后再修改权限的间隙中其他用户无法连接
+        let listener = Listener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    #[cfg(not(unix))]
+    fn listen(path: &Path) -> io::Result<Listener> {
+        if let Ok(addr) = fs::read_to_string(path)
+            && Stream::connect(addr.trim()).is_ok()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is used by another instance", addr.trim()),
+            ));
+        }
+        let listener = Listener::bind("127.0.0.1:0")?;
+        fs::write(path, listener.local_addr()?.to_string())?;
+        Ok(listener)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 控制端口文件的路径，如果无法确定用户目录，则返回[`None`]
+    pub fn path() -> Option<PathBuf> {
+        Self::data_dir().ok().map(|dir| Self::path_in(&dir))
+    }
+
+    /// 数据目录为`data_dir`时控制端口文件的路径
+    pub fn path_in(data_dir: &Path) -> PathBuf {
+        data_dir.join(Self::DIRNAME).join(Self::FILENAME)
+    }
+
+    /// 连接正在运行的实例
+    pub fn connect() -> io::Result<Stream> {
+        Self::connect_in(&Self::data_dir()?)
+    }
+
+    /// 与[`ControlServer::connect`]相同，连接在`data_dir`中监听的实例
+    pub fn connect_in(data_dir: &Path) -> io::Result<Stream> {
+        let path = Self::path_in(data_dir);
+        #[cfg(unix)]
+        return Stream::connect(path);
+        #[cfg(not(unix))]
+        return Stream::connect(fs::read_to_string(path)?.trim());
+    }
+
+    fn data_dir() -> io::Result<PathBuf> {
+        directories::ProjectDirs::from("", "", "request-tui")
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .ok_or(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Cannot determine data directory",
+            ))
+    }
+
+    /// 取出一个等待执行的命令，没有时返回[`None`]
+    pub fn try_recv(&self) -> Option<ControlRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!(target:"Control", "Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// 创建只有当前用户可以访问的目录，目录已经存在时同样收紧权限
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
+
+/// 读取一个连接中的命令，直到连接关闭
+fn serve(stream: Stream, requests: Sender<ControlRequest>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => dispatch(command, &requests),
+            Err(e) => ControlResponse::Error {
+                message: format!("Invalid command: {}", e),
+            },
+        };
+        let mut json = serde_json::to_string(&response)?;
+        json.push('\n');
+        writer.write_all(json.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn dispatch(command: ControlCommand, requests: &Sender<ControlRequest>) -> ControlResponse {
+    let (reply, response) = mpsc::channel();
+    if requests.send(ControlRequest { command, reply }).is_err() {
+        return ControlResponse::Error {
+            message: String::from("Application is exiting"),
+        };
+    }
+    match response.recv_timeout(ControlServer::REPLY_TIMEOUT) {
+        Ok(response) => response,
+        Err(RecvTimeoutError::Timeout) => ControlResponse::Error {
+            message: String::from("Timed out waiting for the application"),
+        },
+        Err(RecvTimeoutError::Disconnected) => ControlResponse::Error {
+            message: String::from("Application is exiting"),
+        },
+    }
+}
+
+impl App {
+    // ------------------- HANDLE_ASYNC ---------------------
+
+    /// 执行控制端口收到的命令
+    pub(crate) fn handle_control(&mut self) {
+        while let Some(request) = self.control.as_ref().and_then(ControlServer::try_recv) {
+            let response = self.execute_control(request.command);
+            // 连接已经断开时不需要回复
+            let _ = request.reply.send(response);
+        }
+    }
+
+    fn execute_control(&mut self, command: ControlCommand) -> ControlResponse {
+        let downloading = &mut self.data.downloading;
+        match command {
+            ControlCommand::Add { url } => {
+                let url = url.trim();
+                if url.is_empty() {
+                    return ControlResponse::Error {
+                        message: String::from("Empty URL"),
+                    };
+                }
+                let Some(id) =
+                    downloading.append_normal_task(RequestOptions::new(url.to_string(), None))
+                else {
+                    return ControlResponse::Error {
+                        message: String::from(DownloadList::READ_ONLY_NOTICE),
+                    };
+                };
+                log::info!(target:"Control", "Added {} as {}", url, id);
+                ControlResponse::Added { id }
+            }
+            ControlCommand::List => ControlResponse::Tasks {
+                tasks: downloading
+                    .list()
+                    .iter()
+                    .map(ControlTask::from_listener)
+                    .chain(downloading.pending().iter().map(ControlTask::from_pending))
+                    .collect(),
+            },
+            ControlCommand::Stop { id } => {
+                let Some(index) = downloading.list().iter().position(|l| l.id() == id) else {
+                    return ControlResponse::Error {
+                        message: format!("No running task {}", id),
+                    };
+                };
+                match downloading.stop_task(index) {
+                    Ok(()) => ControlResponse::Stopped { id },
+                    Err(e) => ControlResponse::Error {
+                        message: e.to_string(),
+                    },
+                }
+            }
+            ControlCommand::Status => ControlResponse::Status {
+                tasks: downloading.list().len(),
+                active: downloading
+                    .list()
+                    .iter()
+                    .filter(|l| l.recent_speed() > 0)
+                    .count(),
+                pending: downloading.pending().len(),
+                finished: self.data.finished.list().len(),
+                speed: downloading.total_speed(),
+            },
+        }
+    }
+}
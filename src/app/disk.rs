@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc as std_mpsc};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
+
+use crate::app::permissions::{self, FileMode};
+use crate::window::common;
+
+/// `path`所在文件系统的可用空间（字节）
+///
+/// `path`不存在时使用最近的已经存在的上级目录。
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+    fs4::available_space(existing)
+}
+
+/// 缓存的下载目录可用空间，每隔[`DiskSpace::REFRESH_INTERVAL`]才重新查询一次，
+/// 避免每一帧都进行系统调用。
+#[derive(Debug)]
+pub struct DiskSpace {
+    dir: PathBuf,
+    available: Option<u64>,
+    checked_at: Option<Instant>,
+}
+
+impl DiskSpace {
+    // ------------------- CONSTANT -----------------------
+
+    pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(dir: PathBuf) -> Self {
+        DiskSpace {
+            dir,
+            available: None,
+            checked_at: None,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 最近一次查询到的可用空间，查询失败或者还没有查询过时为[`None`]
+    pub fn available(&self) -> Option<u64> {
+        self.available
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 距离上次查询超过[`DiskSpace::REFRESH_INTERVAL`]时重新查询，返回是否进行了查询
+    pub fn refresh_if_stale(&mut self) -> bool {
+        if self
+            .checked_at
+            .is_some_and(|t| t.elapsed() < Self::REFRESH_INTERVAL)
+        {
+            return false;
+        }
+        self.checked_at = Some(Instant::now());
+        self.available = match available_space(&self.dir) {
+            Ok(available) => Some(available),
+            Err(e) => {
+                log::debug!(target:"App", "Failed to query free space of {}: {}", self.dir.display(), e);
+                None
+            }
+        };
+        true
+    }
+}
+
+/// 下载目录的检查结果，见[`DirHealth::probe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirHealth {
+    Healthy,
+    /// 可用空间（字节）低于[`DirMonitor::LOW_SPACE`]
+    NearlyFull(u64),
+    /// 无法在目录中创建文件
+    ReadOnly(String),
+    /// 目录不存在并且无法创建
+    Unavailable(String),
+}
+
+impl DirHealth {
+    // ------------------- CONSTANT -----------------------
+
+    const PROBE_FILENAME: &'static str = ".request-tui-probe";
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 创建缺失的目录（使用`dir_mode`指定的权限），写入并删除一个临时文件，最后查询可用空间
+    ///
+    /// 会进行阻塞的IO操作，不应该在UI线程中调用。
+    pub fn probe(dir: &Path, low_space: u64, dir_mode: Option<FileMode>) -> Self {
+        if let Err(e) = permissions::create_dir_all(dir, dir_mode) {
+            return DirHealth::Unavailable(e.to_string());
+        }
+        let probe = dir.join(format!("{}-{}", Self::PROBE_FILENAME, std::process::id()));
+        if let Err(e) = fs::write(&probe, b"probe") {
+            return DirHealth::ReadOnly(e.to_string());
+        }
+        let _ = fs::remove_file(&probe);
+        match fs4::available_space(dir) {
+            Ok(available) if available < low_space => DirHealth::NearlyFull(available),
+            _ => DirHealth::Healthy,
+        }
+    }
+
+    /// 需要提示给用户的警告，目录正常时为[`None`]
+    pub fn warning(&self, dir: &Path) -> Option<String> {
+        match self {
+            DirHealth::Healthy => None,
+            DirHealth::NearlyFull(available) => Some(format!(
+                "Download dir {} is nearly full ({} left)",
+                dir.display(),
+                common::get_human_readable_size(*available)
+            )),
+            DirHealth::ReadOnly(e) => Some(format!(
+                "Download dir {} is read-only: {}",
+                dir.display(),
+                e
+            )),
+            DirHealth::Unavailable(e) => Some(format!(
+                "Download dir {} is unavailable: {}",
+                dir.display(),
+                e
+            )),
+        }
+    }
+}
+
+/// 在后台运行时中定期检查下载目录，UI线程只读取缓存的结果
+///
+/// 目录配置错误时，用户往往要等到第一个任务失败才会发现，因此启动时就进行检查，
+/// 之后每隔[`DirMonitor::INTERVAL`]重新检查一次。
+#[derive(Debug, Clone)]
+pub struct DirMonitor {
+    dir: Arc<Mutex<PathBuf>>,
+    health: Arc<Mutex<Option<DirHealth>>>,
+    // 目录改变时立即重新检查
+    recheck: Arc<Notify>,
+    // 目录不存在时创建的目录使用的权限
+    dir_mode: Option<FileMode>,
+}
+
+impl DirMonitor {
+    // ------------------- CONSTANT -----------------------
+
+    pub const INTERVAL: Duration = Duration::from_secs(60);
+    pub const LOW_SPACE: u64 = 1024 * 1024 * 1024;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(dir: PathBuf) -> Self {
+        DirMonitor {
+            dir: Arc::new(Mutex::new(dir)),
+            health: Arc::new(Mutex::new(None)),
+            recheck: Arc::new(Notify::new()),
+            dir_mode: None,
+        }
+    }
+
+    /// 需要在[`DirMonitor::start`]之前调用
+    pub fn with_dir_mode(mut self, dir_mode: Option<FileMode>) -> Self {
+        self.dir_mode = dir_mode;
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 最近一次检查发现的问题，还没有检查过或者目录正常时为[`None`]
+    pub fn warning(&self) -> Option<String> {
+        let health = self.health.lock().unwrap();
+        health.as_ref()?.warning(&self.dir.lock().unwrap())
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 改为检查`dir`，之前的结果被丢弃
+    pub fn set_dir(&self, dir: PathBuf) {
+        *self.dir.lock().unwrap() = dir;
+        *self.health.lock().unwrap() = None;
+        self.recheck.notify_one();
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 在后台运行时中启动定期检查，运行时关闭时随之结束
+    pub fn start(&self, runtime: &Handle) {
+        let monitor = self.clone();
+        runtime.spawn(async move {
+            loop {
+                let dir = monitor.dir.lock().unwrap().clone();
+                let probed = {
+                    let dir = dir.clone();
+                    let dir_mode = monitor.dir_mode;
+                    tokio::task::spawn_blocking(move || {
+                        DirHealth::probe(&dir, Self::LOW_SPACE, dir_mode)
+                    })
+                    .await
+                };
+                // 检查期间目录被修改时，丢弃旧目录的结果
+                if let Ok(health) = probed
+                    && *monitor.dir.lock().unwrap() == dir
+                {
+                    if let Some(warning) = health.warning(&dir) {
+                        log::warn!(target:"App", "{}", warning);
+                    }
+                    *monitor.health.lock().unwrap() = Some(health);
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(Self::INTERVAL) => {}
+                    _ = monitor.recheck.notified() => {}
+                }
+            }
+        });
+    }
+}
+
+/// 定期检查完成列表中下载成功的文件是否仍然存在，文件可能在下载之后被用户移动或者删除
+///
+/// 由UI线程决定何时检查：只有完成页面可见时才会开始新的检查，两次检查至少间隔
+/// [`ExistenceCheck::INTERVAL`]。每次检查在后台运行时中使用一个阻塞任务依次查询所有路径，
+/// UI线程在之后的[`ExistenceCheck::poll`]中取走结果。
+#[derive(Debug, Default)]
+pub struct ExistenceCheck {
+    runtime: Option<Handle>,
+    // 正在进行的检查，完成之后发送每个路径是否存在
+    running: Option<std_mpsc::Receiver<HashMap<PathBuf, bool>>>,
+    checked_at: Option<Instant>,
+}
+
+impl ExistenceCheck {
+    // ------------------- CONSTANT -----------------------
+
+    pub const INTERVAL: Duration = Duration::from_secs(180);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        ExistenceCheck::default()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 后台运行时启动之后才能开始检查
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// 下一次[`ExistenceCheck::start_if_due`]时立即检查，不等待间隔结束
+    pub fn refresh(&mut self) {
+        self.checked_at = None;
+    }
+
+    /// 距离上次检查超过间隔并且没有正在进行的检查时，在后台检查`paths`返回的路径，
+    /// 返回是否开始了检查
+    pub fn start_if_due(&mut self, now: Instant, paths: impl FnOnce() -> Vec<PathBuf>) -> bool {
+        let due = self
+            .checked_at
+            .is_none_or(|t| now.duration_since(t) >= Self::INTERVAL);
+        if !due || self.running.is_some() {
+            return false;
+        }
+        let Some(runtime) = &self.runtime else {
+            return false;
+        };
+        self.checked_at = Some(now);
+        let paths = paths();
+        if paths.is_empty() {
+            return false;
+        }
+        let (sender, receiver) = std_mpsc::channel();
+        runtime.spawn_blocking(move || {
+            let _ = sender.send(exists_all(paths));
+        });
+        self.running = Some(receiver);
+        true
+    }
+
+    /// 取出已经完成的检查的结果，没有完成的检查时返回[`None`]
+    pub fn poll(&mut self) -> Option<HashMap<PathBuf, bool>> {
+        let result = match self.running.as_ref()?.try_recv() {
+            Ok(result) => Some(result),
+            Err(std_mpsc::TryRecvError::Empty) => return None,
+            // 检查任务没有发送结果（例如运行时正在关闭），下次重新检查
+            Err(std_mpsc::TryRecvError::Disconnected) => None,
+        };
+        self.running = None;
+        result
+    }
+}
+
+/// 先写入同一目录中的临时文件，再重命名为`path`
+///
+/// 重命名是原子操作，写入中途退出或者另一个进程同时写入时，`path`总是某一次完整写入的内容，
+/// 不会交错或者截断。`path`已经存在时保留其权限。
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// 依次查询每个路径是否存在，无法确定时（例如没有权限）视为存在
+///
+/// 会进行阻塞的IO操作，不应该在UI线程中调用。
+pub fn exists_all(paths: Vec<PathBuf>) -> HashMap<PathBuf, bool> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let exists = path.try_exists().unwrap_or(true);
+            (path, exists)
+        })
+        .collect()
+}
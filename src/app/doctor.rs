@@ -0,0 +1,485 @@
+use std::env;
+use std::ffi::OsString;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use url::Url;
+
+use crate::app::config::Config;
+use crate::app::disk::{DirHealth, DirMonitor};
+use crate::app::network::NetworkOptions;
+use crate::app::permissions::FileModes;
+use crate::app::task::{self, ObserverKind, error_chain};
+use crate::app::timefmt::TimeConfig;
+use crate::cli::Cli;
+use crate::window::common;
+
+/// 单项检查的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckStatus {
+    Pass,
+    /// 可以继续使用，但是部分功能可能受影响
+    Warn,
+    /// 下载无法正常进行，`--doctor`以非0状态退出
+    Fail,
+}
+
+impl Display for CheckStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "pass"),
+            CheckStatus::Warn => write!(f, "warn"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// 一项检查的结果，`--doctor`输出为表格中的一行，启动时也用于提示配置中的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// 没有通过时如何解决
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    pub fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    pub fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        CheckResult {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// `--doctor`的所有检查结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DoctorReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 依次进行所有检查，网络检查会阻塞直到完成或者超时
+    pub fn run(cli: &Cli) -> Self {
+        let mut results = Vec::new();
+        let (result, mut config) = check_config_file(Config::path().as_deref());
+        results.push(result);
+        cli.apply(&mut config);
+        results.extend(check_config_values(&config));
+        results.push(check_download_dir(&config.download_dir()));
+        results.extend(check_network_blocking(&config));
+        results.extend(check_features(&config, env::var_os("PATH")));
+        results.push(check_terminal(
+            env::var("TERM").ok().as_deref(),
+            env::var("COLORTERM").ok().as_deref(),
+            io::stdout().is_terminal(),
+        ));
+        DoctorReport { results }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn has_failures(&self) -> bool {
+        self.results.iter().any(|r| r.status == CheckStatus::Fail)
+    }
+}
+
+impl Display for DoctorReport {
+    /// 每项一行，提示缩进显示在下一行
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let width = self.results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+        for result in &self.results {
+            writeln!(
+                f,
+                "[{}]  {:<width$}  {}",
+                result.status,
+                result.name,
+                result.detail,
+                width = width
+            )?;
+            if let Some(hint) = &result.hint {
+                writeln!(f, "{:indent$}-> {}", "", hint, indent = width + 10)?;
+            }
+        }
+        let count = |status| self.results.iter().filter(|r| r.status == status).count();
+        writeln!(
+            f,
+            "\n{} passed, {} warning(s), {} failure(s)",
+            count(CheckStatus::Pass),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail)
+        )
+    }
+}
+
+// -------------------- CHECKS -----------------------
+
+/// 读取并解析配置文件，返回检查结果以及实际使用的配置（解析失败时为默认配置）
+pub fn check_config_file(path: Option<&Path>) -> (CheckResult, Config) {
+    const NAME: &str = "config";
+    let Some(path) = path else {
+        return (
+            CheckResult::warn(
+                NAME,
+                "cannot determine the config directory, using defaults",
+                "set HOME (or the platform equivalent) so the config can be found",
+            ),
+            Config::default(),
+        );
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return (
+                CheckResult::pass(
+                    NAME,
+                    format!("{} not found, using defaults", path.display()),
+                ),
+                Config::default(),
+            );
+        }
+        Err(e) => {
+            return (
+                CheckResult::fail(
+                    NAME,
+                    format!("cannot read {}: {}", path.display(), e),
+                    "check the permissions of the config file",
+                ),
+                Config::default(),
+            );
+        }
+    };
+    match toml::from_str(&content) {
+        Ok(config) => (CheckResult::pass(NAME, path.display().to_string()), config),
+        Err(e) => (
+            CheckResult::fail(
+                NAME,
+                format!("failed to parse {}: {}", path.display(), e.message()),
+                "fix the reported field, or delete the file to start over with defaults",
+            ),
+            Config::default(),
+        ),
+    }
+}
+
+/// 配置中无法生效、启动时会被替换为默认值的设置
+///
+/// 启动时同样使用这些检查，在状态栏中提示。
+pub fn check_config_values(config: &Config) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    if let Err(e) = NetworkOptions::from_config(config) {
+        results.push(CheckResult::warn(
+            "network settings",
+            format!("{}, using default network settings", e),
+            "fix ip_version, bind_interface or bind_address in the config",
+        ));
+    }
+    if let Err(e) = FileModes::from_config(config) {
+        results.push(CheckResult::warn(
+            "permissions",
+            format!("{}, using default permissions", e),
+            "use octal modes such as \"644\" for file_mode and dir_mode",
+        ));
+    }
+    if let Err(e) = TimeConfig::from_config(config) {
+        results.push(CheckResult::warn(
+            "time format",
+            format!("{}, using {}", e, TimeConfig::DEFAULT_FORMAT),
+            "use strftime directives such as %Y, %m, %d, %H and %M in time_format",
+        ));
+    }
+    results
+}
+
+/// 下载目录是否可以创建、写入，以及剩余空间，见[`DirHealth::probe`]
+pub fn check_download_dir(dir: &Path) -> CheckResult {
+    const NAME: &str = "download dir";
+    let health = DirHealth::probe(dir, DirMonitor::LOW_SPACE, None);
+    let warning = health.warning(dir).unwrap_or_default();
+    match health {
+        DirHealth::Healthy => {
+            let free = fs4::available_space(dir)
+                .map(common::get_human_readable_size)
+                .unwrap_or_else(|_| String::from("unknown"));
+            CheckResult::pass(NAME, format!("{} ({} free)", dir.display(), free))
+        }
+        DirHealth::NearlyFull(_) => CheckResult::warn(
+            NAME,
+            warning,
+            "free up space or choose another download_dir",
+        ),
+        DirHealth::ReadOnly(_) => CheckResult::fail(
+            NAME,
+            warning,
+            "make the directory writable or set download_dir to another directory",
+        ),
+        DirHealth::Unavailable(_) => CheckResult::fail(
+            NAME,
+            warning,
+            "create the directory or set download_dir to an existing one",
+        ),
+    }
+}
+
+/// reqwest默认从环境变量中读取代理，按照与其相同的顺序查找`url`使用的代理
+pub fn effective_proxy(url: &Url) -> Option<String> {
+    let names: &[&str] = match url.scheme() {
+        "https" => &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+        _ => &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+    };
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// 在新建的运行时中进行[`check_network`]
+fn check_network_blocking(config: &Config) -> Vec<CheckResult> {
+    let url = match Url::parse(&config.doctor_probe_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => url,
+        _ => {
+            return vec![CheckResult::fail(
+                "probe url",
+                format!("invalid doctor_probe_url: {}", config.doctor_probe_url),
+                "set doctor_probe_url to an http(s) URL",
+            )];
+        }
+    };
+    let network = NetworkOptions::from_config(config).unwrap_or_default();
+    let proxy = effective_proxy(&url);
+    match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(check_network(&url, &network, proxy.as_deref())),
+        Err(e) => vec![CheckResult::fail(
+            "network",
+            format!("cannot start the async runtime: {}", e),
+            "check the system limits on threads and file descriptors",
+        )],
+    }
+}
+
+/// 解析域名并且发送HTTPS请求，检查DNS、代理以及TLS根证书是否可用
+///
+/// 使用代理时由代理解析目标的域名，因此解析的是代理的域名。
+pub async fn check_network(
+    url: &Url,
+    network: &NetworkOptions,
+    proxy: Option<&str>,
+) -> Vec<CheckResult> {
+    const TIMEOUT: Duration = Duration::from_secs(10);
+    let mut results = Vec::new();
+
+    let (host, port) = match proxy.and_then(|proxy| Url::parse(proxy).ok()) {
+        Some(proxy) => (
+            proxy.host_str().unwrap_or_default().to_string(),
+            proxy.port_or_known_default().unwrap_or(80),
+        ),
+        None => (
+            url.host_str().unwrap_or_default().to_string(),
+            url.port_or_known_default().unwrap_or(443),
+        ),
+    };
+    let lookup = tokio::time::timeout(TIMEOUT, tokio::net::lookup_host((host.as_str(), port)));
+    match lookup.await {
+        Ok(Ok(addrs)) => results.push(CheckResult::pass(
+            "dns",
+            format!("{} resolved to {} address(es)", host, addrs.count()),
+        )),
+        Ok(Err(e)) => {
+            results.push(CheckResult::fail(
+                "dns",
+                format!("cannot resolve {}: {}", host, e),
+                "check the network connection and the DNS settings",
+            ));
+            return results;
+        }
+        Err(_) => {
+            results.push(CheckResult::fail(
+                "dns",
+                format!("resolving {} timed out", host),
+                "check the network connection and the DNS settings",
+            ));
+            return results;
+        }
+    }
+
+    // 与获取校验和文件一样，只读取很少的内容，允许压缩
+    let builder = task::metadata_client(reqwest::Client::builder()).timeout(TIMEOUT);
+    let builder = match proxy {
+        Some(proxy) => match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => builder.proxy(proxy),
+            Err(e) => {
+                results.push(CheckResult::fail(
+                    "https",
+                    format!("invalid proxy {}: {}", proxy, e),
+                    "fix the HTTPS_PROXY / ALL_PROXY environment variable",
+                ));
+                return results;
+            }
+        },
+        None => builder.no_proxy(),
+    };
+    let via = match proxy {
+        Some(proxy) => format!(" via proxy {}", proxy),
+        None => String::new(),
+    };
+    let client = match network.apply(builder).build() {
+        Ok(client) => client,
+        Err(e) => {
+            results.push(CheckResult::fail(
+                "https",
+                format!("cannot create the HTTP client: {}", error_chain(&e)),
+                "check the TLS setup of the system",
+            ));
+            return results;
+        }
+    };
+    match client.head(url.clone()).send().await {
+        Ok(response) => results.push(CheckResult::pass(
+            "https",
+            format!("{} returned {}{}", url, response.status(), via),
+        )),
+        Err(e) => {
+            let hint = match (proxy, e.is_connect()) {
+                (Some(_), true) => "the proxy is unreachable, check HTTPS_PROXY / ALL_PROXY",
+                _ if e.is_timeout() => "the request timed out, check the firewall or the proxy",
+                _ => "check the system's TLS root certificates, the proxy and the firewall",
+            };
+            results.push(CheckResult::fail(
+                "https",
+                format!("request to {}{} failed: {}", url, via, error_chain(&e)),
+                hint,
+            ));
+        }
+    }
+    results
+}
+
+/// 可选功能是否可用：剪贴板需要编译时启用并且能够连接，桌面通知需要对应的命令
+pub fn check_features(config: &Config, path_var: Option<OsString>) -> Vec<CheckResult> {
+    let mut results = vec![check_clipboard(config)];
+    let notify = config.task_observers.contains(&ObserverKind::Notify);
+    let command = if cfg!(target_os = "macos") {
+        Some("osascript")
+    } else if cfg!(unix) {
+        Some("notify-send")
+    } else {
+        None
+    };
+    results.push(match command {
+        _ if !notify => CheckResult::pass("notifications", "not enabled"),
+        Some(command) if find_in_path(command, path_var).is_some() => {
+            CheckResult::pass("notifications", format!("using {}", command))
+        }
+        Some(command) => CheckResult::warn(
+            "notifications",
+            format!("{} not found in PATH", command),
+            format!(
+                "install {} or remove \"notify\" from task_observers",
+                command
+            ),
+        ),
+        None => CheckResult::warn(
+            "notifications",
+            "desktop notifications are not supported on this platform",
+            "remove \"notify\" from task_observers",
+        ),
+    });
+    results
+}
+
+#[cfg(feature = "clipboard")]
+fn check_clipboard(config: &Config) -> CheckResult {
+    match arboard::Clipboard::new() {
+        Ok(_) => CheckResult::pass("clipboard", "available"),
+        // 没有开启监视时只影响复制About弹窗中的内容
+        Err(e) if config.clipboard_watch => CheckResult::fail(
+            "clipboard",
+            format!("cannot access the clipboard: {}", e),
+            "run inside a graphical session, or set clipboard_watch = false",
+        ),
+        Err(e) => CheckResult::warn(
+            "clipboard",
+            format!("cannot access the clipboard: {}", e),
+            "copying is unavailable outside a graphical session",
+        ),
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn check_clipboard(config: &Config) -> CheckResult {
+    match config.clipboard_watch {
+        true => CheckResult::warn(
+            "clipboard",
+            "clipboard_watch is set but this build has no clipboard support",
+            "rebuild with --features clipboard",
+        ),
+        false => CheckResult::pass("clipboard", "not included in this build"),
+    }
+}
+
+/// 在`PATH`中查找可执行文件
+pub fn find_in_path(command: &str, path_var: Option<OsString>) -> Option<PathBuf> {
+    env::split_paths(&path_var?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
+
+/// 根据`TERM`和`COLORTERM`估计终端支持的颜色
+pub fn check_terminal(term: Option<&str>, colorterm: Option<&str>, is_tty: bool) -> CheckResult {
+    const NAME: &str = "terminal";
+    if !is_tty {
+        return CheckResult::warn(
+            NAME,
+            "stdout is not a terminal",
+            "run request-tui directly in a terminal emulator",
+        );
+    }
+    let term = term.unwrap_or_default();
+    let colors = match (term, colorterm.unwrap_or_default()) {
+        ("" | "dumb", _) => {
+            return CheckResult::warn(
+                NAME,
+                format!("TERM={:?} does not support the interface", term),
+                "set TERM, e.g. TERM=xterm-256color",
+            );
+        }
+        (_, "truecolor" | "24bit") => "24-bit color",
+        (term, _) if term.contains("256color") => "256 colors",
+        _ => "16 colors",
+    };
+    let size = ratatui::crossterm::terminal::size()
+        .map(|(w, h)| format!(", {}x{}", w, h))
+        .unwrap_or_default();
+    CheckResult::pass(NAME, format!("TERM={}, {}{}", term, colors, size))
+}
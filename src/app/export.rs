@@ -0,0 +1,351 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+use std::time::Duration;
+
+use tokio::runtime::Handle;
+
+use crate::app::disk;
+use crate::app::record::TaskRecord;
+use crate::window::common;
+
+/// 导出完成列表时使用的格式，在完成页面中按`X`导出，也用于`--export`
+///
+/// CSV中的数值保持原始的单位（字节、秒、B/s），方便表格软件计算；
+/// Markdown表格用于直接贴进报告，数值换算为易读的单位。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    // ------------------- CONSTANT -----------------------
+
+    const CSV_HEADER: [&'static str; 8] = [
+        "finished_at",
+        "filename",
+        "url",
+        "size_bytes",
+        "duration_secs",
+        "average_speed_bps",
+        "stage",
+        "checksum",
+    ];
+    const MARKDOWN_HEADER: [&'static str; 8] = [
+        "Finished",
+        "Filename",
+        "URL",
+        "Size",
+        "Duration",
+        "Average speed",
+        "Stage",
+        "Checksum",
+    ];
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 根据扩展名选择格式，`.md`以及`.markdown`为Markdown，其他都为CSV
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown") => {
+                ExportFormat::Markdown
+            }
+            _ => ExportFormat::Csv,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn toggle(self) -> Self {
+        match self {
+            ExportFormat::Csv => ExportFormat::Markdown,
+            ExportFormat::Markdown => ExportFormat::Csv,
+        }
+    }
+
+    pub fn render(self, records: &[TaskRecord]) -> String {
+        match self {
+            ExportFormat::Csv => to_csv(records),
+            ExportFormat::Markdown => to_markdown(records),
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "CSV"),
+            ExportFormat::Markdown => write!(f, "Markdown"),
+        }
+    }
+}
+
+/// 每个任务一行的CSV（RFC 4180），第一行为列名，行之间使用CRLF分隔
+pub fn to_csv(records: &[TaskRecord]) -> String {
+    let mut out = String::new();
+    let mut push_row = |cells: &[&str]| {
+        let row: Vec<_> = cells.iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&row.join(","));
+        out.push_str("\r\n");
+    };
+    push_row(&ExportFormat::CSV_HEADER);
+    for record in records {
+        let speed = average_speed(record);
+        push_row(&[
+            record.finished_at.as_deref().unwrap_or_default(),
+            &filename(record),
+            record.url.as_deref().unwrap_or_default(),
+            &record.bytes.to_string(),
+            &format!("{:.3}", record.duration_secs),
+            &speed.map_or(String::new(), |speed| speed.to_string()),
+            &stage_name(record),
+            record.checksum.as_deref().unwrap_or_default(),
+        ]);
+    }
+    out
+}
+
+/// GitHub风格的Markdown表格，每个任务一行
+pub fn to_markdown(records: &[TaskRecord]) -> String {
+    let mut out = String::new();
+    let mut push_row = |cells: &[&str]| {
+        let row: Vec<_> = cells.iter().map(|cell| markdown_cell(cell)).collect();
+        out.push_str(&format!("| {} |\n", row.join(" | ")));
+    };
+    push_row(&ExportFormat::MARKDOWN_HEADER);
+    push_row(&["---"; 8]);
+    for record in records {
+        let duration = Duration::try_from_secs_f64(record.duration_secs).unwrap_or_default();
+        let speed = average_speed(record);
+        push_row(&[
+            record.finished_at.as_deref().unwrap_or_default(),
+            &filename(record),
+            record.url.as_deref().unwrap_or_default(),
+            &common::get_human_readable_size(record.bytes),
+            &common::format_duration(duration),
+            &speed.map_or(String::new(), |speed| {
+                format!("{}/s", common::get_human_readable_size(speed))
+            }),
+            &stage_name(record),
+            record.checksum.as_deref().unwrap_or_default(),
+        ]);
+    }
+    out
+}
+
+/// 含有逗号、引号或者换行时用双引号包围，其中的双引号写两次
+pub fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// 转义表格中的`|`以及反斜杠，换行替换为空格，否则会打断表格的一行
+pub fn markdown_cell(cell: &str) -> String {
+    let mut out = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '|' => out.push_str("\\|"),
+            '\r' | '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn filename(record: &TaskRecord) -> String {
+    record
+        .filepath
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().to_string())
+}
+
+/// 与退出摘要中相同的阶段名称，例如`checksum_mismatch`
+fn stage_name(record: &TaskRecord) -> String {
+    serde_json::to_value(record.stage)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// 整个任务的平均速度（B/s），耗时为0时无法计算
+fn average_speed(record: &TaskRecord) -> Option<u64> {
+    (record.duration_secs > 0.0).then(|| (record.bytes as f64 / record.duration_secs) as u64)
+}
+
+/// 将`records`以`format`写入`path`，写入过程中退出时不会留下不完整的文件
+pub fn write_records(path: &Path, format: ExportFormat, records: &[TaskRecord]) -> io::Result<()> {
+    disk::write_atomic(path, format.render(records))
+}
+
+/// 在后台写入导出的文件，同一时间只有一个导出，完成时在UI线程中提示
+#[derive(Debug, Default)]
+pub struct ExportJob {
+    runtime: Option<Handle>,
+    running: Option<(PathBuf, usize, std_mpsc::Receiver<io::Result<()>>)>,
+}
+
+impl ExportJob {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        ExportJob::default()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// 开始写入，后台运行时还没有启动时返回`false`
+    pub fn start(&mut self, path: PathBuf, format: ExportFormat, records: Vec<TaskRecord>) -> bool {
+        let Some(runtime) = &self.runtime else {
+            return false;
+        };
+        let (sender, receiver) = std_mpsc::channel();
+        let target = path.clone();
+        let count = records.len();
+        runtime.spawn_blocking(move || {
+            let _ = sender.send(write_records(&target, format, &records));
+        });
+        self.running = Some((path, count, receiver));
+        true
+    }
+
+    /// 导出完成时返回提示
+    pub fn poll(&mut self) -> Option<String> {
+        let (_, _, receiver) = self.running.as_ref()?;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std_mpsc::TryRecvError::Empty) => return None,
+            Err(std_mpsc::TryRecvError::Disconnected) => Err(io::Error::other(
+                "background runtime stopped before writing the file",
+            )),
+        };
+        let (path, count, _) = self.running.take()?;
+        Some(match result {
+            Ok(()) => format!("Exported {} tasks to {}", count, path.display()),
+            Err(e) => format!("Failed to export to {}: {}", path.display(), e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::task::StageKind;
+
+    use super::*;
+
+    /// 一个成功的任务，文件名含有中文、逗号以及引号，另一个失败的任务，文件名含有`|`
+    fn records() -> Vec<TaskRecord> {
+        let finished = TaskRecord {
+            finished_at: Some(String::from("2026-01-02T03:04:05+08:00")),
+            ..TaskRecord::new(
+                Some(String::from("https://example.com/a.pdf")),
+                StageKind::Finished,
+                PathBuf::from("/downloads/报告, \"最终\".pdf"),
+                2048,
+                Some(2048),
+                Duration::from_secs(2),
+            )
+            .with_checksum(Some(String::from("sha256:ab")))
+        };
+        let failed = TaskRecord::new(
+            None,
+            StageKind::ChecksumMismatch,
+            PathBuf::from("/downloads/naïve|résumé.txt"),
+            0,
+            None,
+            Duration::ZERO,
+        );
+        vec![finished, failed]
+    }
+
+    #[test]
+    fn csv_quotes_only_when_needed() {
+        assert!(matches!(csv_field("naïve résumé.txt"), Cow::Borrowed(_)));
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("line\r\nbreak"), "\"line\r\nbreak\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn markdown_cells_cannot_break_the_table() {
+        assert_eq!(markdown_cell("a|b"), "a\\|b");
+        assert_eq!(markdown_cell("C:\\dir"), "C:\\\\dir");
+        assert_eq!(markdown_cell("two\r\nlines"), "two  lines");
+        assert_eq!(markdown_cell("报告 ✓"), "报告 ✓");
+    }
+
+    #[test]
+    fn csv_has_one_row_per_task() {
+        assert_eq!(
+            to_csv(&records()),
+            "finished_at,filename,url,size_bytes,duration_secs,average_speed_bps,stage,checksum\r\n\
+             2026-01-02T03:04:05+08:00,\"报告, \"\"最终\"\".pdf\",https://example.com/a.pdf,\
+             2048,2.000,1024,finished,sha256:ab\r\n\
+             ,naïve|résumé.txt,,0,0.000,,checksum_mismatch,\r\n"
+        );
+        assert_eq!(to_csv(&[]).lines().count(), 1);
+    }
+
+    #[test]
+    fn markdown_uses_readable_units() {
+        assert_eq!(
+            to_markdown(&records()),
+            "| Finished | Filename | URL | Size | Duration | Average speed | Stage | Checksum |\n\
+             | --- | --- | --- | --- | --- | --- | --- | --- |\n\
+             | 2026-01-02T03:04:05+08:00 | 报告, \"最终\".pdf | https://example.com/a.pdf \
+             | 2.00 KB | 2s | 1.00 KB/s | finished | sha256:ab |\n\
+             |  | naïve\\|résumé.txt |  | 0 B | 0s |  | checksum_mismatch |  |\n"
+        );
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.MD")),
+            ExportFormat::Markdown
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.markdown")),
+            ExportFormat::Markdown
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.txt")),
+            ExportFormat::Csv
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("a")), ExportFormat::Csv);
+    }
+
+    #[test]
+    fn records_are_written_in_the_chosen_format() {
+        let path =
+            std::env::temp_dir().join(format!("request-tui-export-{}.md", std::process::id()));
+        let records = records();
+        write_records(&path, ExportFormat::Markdown, &records).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            to_markdown(&records)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+/// 左侧的页面列表有焦点（还没有进入页面）时，按下页面列表不处理的按键的行为
+///
+/// 新用户经常在没有进入页面时按下页面中的按键，例如`a`，却看不到任何反应。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnfocusedKeys {
+    /// 提示先按Enter或者→进入页面
+    #[default]
+    Hint,
+    /// 自动进入选中的页面，并将按键交给该页面处理
+    Enter,
+}
+
+/// [`App::run`]读取终端事件的来源
+///
+/// 正常运行时使用[`CrosstermEvents`]，读取真实的终端输入；[`ScriptedEvents`]
+/// 按顺序返回预先给定的事件，与ratatui的`TestBackend`一起用于驱动整个界面。
+///
+/// [`App::run`]: crate::app::App::run
+pub trait EventSource {
+    /// 最多等待`timeout`，有事件可以读取时返回`true`
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// 读取下一个事件，只应该在[`EventSource::poll`]返回`true`之后调用
+    fn read(&mut self) -> io::Result<Event>;
+}
+
+/// 从终端读取事件
+#[derive(Debug, Default)]
+pub struct CrosstermEvents;
+
+impl EventSource for CrosstermEvents {
+    fn poll(&mut self, timeout: Duration) -> io::Result<bool> {
+        event::poll(timeout)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+}
+
+/// 按顺序返回预先给定的事件
+///
+/// 事件用完之后[`EventSource::poll`]返回[`io::ErrorKind::UnexpectedEof`]，
+/// 这样忘记在脚本最后退出程序时，[`App::run`]会返回错误而不是一直运行下去。
+///
+/// [`App::run`]: crate::app::App::run
+#[derive(Debug, Default)]
+pub struct ScriptedEvents {
+    events: VecDeque<Event>,
+}
+
+impl ScriptedEvents {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_event(mut self, event: Event) -> Self {
+        self.events.push_back(event);
+        self
+    }
+
+    pub fn with_key(self, code: KeyCode) -> Self {
+        self.with_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    /// 依次输入`text`中的每个字符
+    pub fn with_text(self, text: &str) -> Self {
+        text.chars()
+            .fold(self, |events, c| events.with_key(KeyCode::Char(c)))
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 还没有被读取的事件数量
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn poll(&mut self, _timeout: Duration) -> io::Result<bool> {
+        if self.events.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "scripted events exhausted",
+            ));
+        }
+        Ok(true)
+    }
+
+    fn read(&mut self) -> io::Result<Event> {
+        self.events
+            .pop_front()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::mpsc as std_mpsc;
+
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::app::App;
+    use crate::app::about::BuildInfo;
+    use crate::app::config::{Config, LiveConfig};
+    use crate::app::sender::DownloadRequest;
+    use crate::app::session::Session;
+    use crate::app::task::{ConnectionLimits, ManagerReady, RuntimeStats, Task};
+    use crate::app::throttle::{SpeedLimit, Throttle};
+
+    /// 连接到`runtime`的App，发送的任务从返回的通道中接收
+    fn app(runtime: &tokio::runtime::Runtime) -> (App, mpsc::Receiver<Task>) {
+        let config = Config {
+            download_dir: Some(std::env::temp_dir()),
+            ..Config::default()
+        };
+        let build_info = BuildInfo::collect(&config);
+        let (startup_tx, startup) = std_mpsc::channel();
+        let (sender, receiver) = mpsc::channel(4);
+        startup_tx
+            .send(ManagerReady {
+                sender,
+                runtime: runtime.handle().clone(),
+            })
+            .unwrap();
+        let app = App::new(
+            startup,
+            Arc::new(RuntimeStats::new()),
+            Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+            Arc::new(ConnectionLimits::unlimited()),
+            LiveConfig::new(config),
+            Session::default(),
+            build_info,
+        );
+        (app, receiver)
+    }
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn scripted_events_run_out_with_an_error() {
+        let mut events = ScriptedEvents::new().with_text("ab");
+        assert_eq!(events.remaining(), 2);
+        assert!(events.poll(Duration::ZERO).unwrap());
+        assert_eq!(
+            events.read().unwrap(),
+            Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE))
+        );
+        events.read().unwrap();
+        let error = events.poll(Duration::ZERO).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn add_a_download_through_the_input_popup() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (app, mut receiver) = app(&runtime);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let url = "https://example.com/file.bin";
+        // 进入下载页面，打开输入窗口（直接处于编辑状态），输入URL之后退出编辑并确认
+        let mut events = ScriptedEvents::new()
+            .with_key(KeyCode::Enter)
+            .with_key(KeyCode::Char('a'))
+            .with_text(url)
+            .with_key(KeyCode::Esc)
+            .with_key(KeyCode::Enter);
+
+        // 脚本中没有退出的按键，事件用完之后返回错误，同时也不会写入配置文件
+        let error = app.run(&mut terminal, &mut events).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(events.remaining(), 0);
+
+        let task = receiver.try_recv().unwrap();
+        match task.request() {
+            DownloadRequest::Normal { url: sent, .. } => assert_eq!(sent, url),
+            request => panic!("a new task was sent as {:?}", request),
+        }
+        assert!(receiver.try_recv().is_err());
+
+        // 任务已经发送但还没有被后台接收，在列表中排队，输入窗口已经关闭
+        let screen = screen(&terminal);
+        assert!(screen.contains("┃#1 "), "{}", screen);
+        assert!(screen.contains("┃Queued"), "{}", screen);
+        assert!(!screen.contains("URL:"), "{}", screen);
+    }
+
+    #[test]
+    fn settings_open_with_f2_and_cancel_with_esc() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let (opened, _receiver) = app(&runtime);
+        let mut events = ScriptedEvents::new().with_key(KeyCode::F(2));
+        opened.run(&mut terminal, &mut events).unwrap_err();
+        let shown = screen(&terminal);
+        assert!(shown.contains("Settings (1/3)"), "{}", shown);
+        assert!(shown.contains("Esc: cancel"), "{}", shown);
+
+        // Esc只关闭向导，不会传给App退出程序
+        let (app, _receiver) = app(&runtime);
+        let mut events = ScriptedEvents::new()
+            .with_key(KeyCode::F(2))
+            .with_key(KeyCode::Esc);
+        let error = app.run(&mut terminal, &mut events).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let screen = screen(&terminal);
+        assert!(!screen.contains("Settings (1/3)"), "{}", screen);
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// 防止两个实例同时使用同一个下载目录
+///
+/// 两个实例同时在同一个目录中检查重名文件、写入会话文件时会互相破坏。锁文件位于数据目录的
+/// `locks`中，文件名由下载目录的绝对路径计算得到，内容为持有者的PID，只用于提示。
+///
+/// 使用操作系统的文件锁（[`File::try_lock`]），持有锁的进程退出或者崩溃时锁由系统释放，
+/// 因此异常退出留下的锁文件不会妨碍下一次启动，其中过期的PID在获得锁时被覆盖。
+/// 正常退出时清空文件中的PID，但不删除文件：删除之后，已经打开该文件的另一个实例
+/// 可能锁住一个已经不在目录中的文件。
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    download_dir: PathBuf,
+}
+
+/// [`InstanceLock::acquire`]的结果
+#[derive(Debug)]
+pub enum LockAttempt {
+    Acquired(InstanceLock),
+    /// 另一个正在运行的实例持有锁
+    Held(InstanceConflict),
+}
+
+impl InstanceLock {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DIRNAME: &'static str = "locks";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 获取`download_dir`的锁，锁文件位于数据目录中，无法确定数据目录时返回错误
+    pub fn acquire(download_dir: &Path) -> io::Result<LockAttempt> {
+        let data_dir = directories::ProjectDirs::from("", "", "request-tui")
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Cannot determine data directory")
+            })?;
+        Self::acquire_in(&data_dir, download_dir)
+    }
+
+    /// 与[`InstanceLock::acquire`]相同，锁文件位于`data_dir`中
+    pub fn acquire_in(data_dir: &Path, download_dir: &Path) -> io::Result<LockAttempt> {
+        let download_dir = absolute_dir(download_dir);
+        let path = Self::lock_path(data_dir, &download_dir);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Ok(LockAttempt::Held(InstanceConflict {
+                    download_dir,
+                    pid: read_pid(&mut file),
+                }));
+            }
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+        if let Some(pid) = read_pid(&mut file) {
+            log::info!(target:"App", "Replaced stale instance lock of process {}", pid);
+        }
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+        log::debug!(target:"App", "Locked download directory {}", download_dir.display());
+        Ok(LockAttempt::Acquired(InstanceLock { file, download_dir }))
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn download_dir(&self) -> &Path {
+        &self.download_dir
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// `download_dir`对应的锁文件，`download_dir`需要是绝对路径
+    pub fn lock_path(data_dir: &Path, download_dir: &Path) -> PathBuf {
+        let digest = Sha256::digest(download_dir.as_os_str().as_encoded_bytes());
+        let name: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        data_dir.join(Self::DIRNAME).join(format!("{}.lock", name))
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.set_len(0) {
+            log::warn!(target:"App", "Failed to clear instance lock: {}", e);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+/// 规范化之后的绝对路径，目录不存在时无法规范化，只转换为绝对路径
+fn absolute_dir(dir: &Path) -> PathBuf {
+    let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    fs::canonicalize(&dir).unwrap_or(dir)
+}
+
+/// 读取锁文件中的PID，Windows上被锁住的文件无法读取，此时为[`None`]
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// 另一个实例正在使用的下载目录，此时以只读模式运行，直到换用其他的下载目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceConflict {
+    pub download_dir: PathBuf,
+    pub pid: Option<u32>,
+}
+
+impl Display for InstanceConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.pid {
+            Some(pid) => write!(
+                f,
+                "Another instance (PID {}) is using {}",
+                pid,
+                self.download_dir.display()
+            ),
+            None => write!(
+                f,
+                "Another instance is using {}",
+                self.download_dir.display()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个测试使用各自的数据目录以及下载目录
+    fn dirs(name: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!(
+            "request-tui-instance-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        let download_dir = root.join("downloads");
+        fs::create_dir_all(&download_dir).unwrap();
+        (root.join("data"), download_dir)
+    }
+
+    fn lock_file(data_dir: &Path, download_dir: &Path) -> PathBuf {
+        InstanceLock::lock_path(data_dir, &absolute_dir(download_dir))
+    }
+
+    #[test]
+    fn stale_lock_file_is_taken_over() {
+        let (data_dir, download_dir) = dirs("stale");
+        // 上次异常退出留下的锁文件，其中的PID已经不存在，文件也没有被锁住
+        let path = lock_file(&data_dir, &download_dir);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "4194304").unwrap();
+
+        let LockAttempt::Acquired(lock) =
+            InstanceLock::acquire_in(&data_dir, &download_dir).unwrap()
+        else {
+            panic!("stale lock was not taken over");
+        };
+        assert_eq!(lock.download_dir(), absolute_dir(&download_dir));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+        // 正常退出时清空PID，保留文件
+        drop(lock);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn lock_held_by_a_running_instance_is_reported() {
+        let (data_dir, download_dir) = dirs("held");
+        let LockAttempt::Acquired(first) =
+            InstanceLock::acquire_in(&data_dir, &download_dir).unwrap()
+        else {
+            panic!("first instance did not get the lock");
+        };
+
+        // 文件锁属于打开的文件，同一进程中再次打开同样无法获得锁
+        let LockAttempt::Held(conflict) =
+            InstanceLock::acquire_in(&data_dir, &download_dir).unwrap()
+        else {
+            panic!("second instance got the lock");
+        };
+        assert_eq!(conflict.download_dir, absolute_dir(&download_dir));
+        #[cfg(unix)]
+        assert_eq!(conflict.pid, Some(std::process::id()));
+        assert!(conflict.to_string().starts_with("Another instance"));
+        // 相对路径与绝对路径对应同一个锁
+        let other = InstanceLock::acquire_in(&data_dir, &download_dir.join("."));
+        assert!(matches!(other, Ok(LockAttempt::Held(_))));
+
+        drop(first);
+        assert!(matches!(
+            InstanceLock::acquire_in(&data_dir, &download_dir),
+            Ok(LockAttempt::Acquired(_))
+        ));
+        fs::remove_dir_all(data_dir.parent().unwrap()).unwrap();
+    }
+}
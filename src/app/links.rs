@@ -0,0 +1,254 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+
+use tokio::runtime::Handle;
+use url::Url;
+
+use crate::app::peek::{self, FileKind};
+
+/// 从HTML页面中找到的链接，已经转换为绝对URL并且去掉了重复的
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScannedLinks {
+    /// 按照在页面中出现的顺序，最多[`LinkScan::MAX_LINKS`]个
+    pub links: Vec<String>,
+    /// 去重之后找到的链接总数，可能多于`links`
+    pub total: usize,
+}
+
+/// 提取HTML中所有`href`以及`src`属性指向的链接
+///
+/// 只是按照标签和属性的语法逐个扫描，不构建文档树，因此也能处理不完整或者不规范的页面。
+/// 注释中的内容被忽略，`<script>`中以字符串形式写出的标签仍然会被扫描到，
+/// 错误页面中真正的下载链接经常出现在这里。
+///
+/// 相对链接以`page`为起点，页面中有`<base href>`时以第一个`<base>`为起点。
+/// 只保留`http`以及`https`链接，去掉`#`之后的部分，`page`自身以及重复的链接只计一次。
+pub fn extract_links(html: &str, page: &Url, limit: usize) -> ScannedLinks {
+    let mut base = Cow::Borrowed(page);
+    let mut base_set = false;
+    let mut seen = HashSet::new();
+    seen.insert(without_fragment(page.clone()).to_string());
+    let mut scanned = ScannedLinks::default();
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let end = tag_end(rest);
+        let (name, attributes) = parse_tag(&rest[..end]);
+        rest = &rest[end..];
+
+        for (attribute, value) in attributes {
+            if !attribute.eq_ignore_ascii_case("href") && !attribute.eq_ignore_ascii_case("src") {
+                continue;
+            }
+            let value = decode_entities(value.trim());
+            if name.eq_ignore_ascii_case("base") {
+                if !base_set && let Ok(url) = page.join(&value) {
+                    base = Cow::Owned(url);
+                    base_set = true;
+                }
+                continue;
+            }
+            let Some(url) = resolve_link(&base, &value) else {
+                continue;
+            };
+            let url = url.to_string();
+            if seen.insert(url.clone()) {
+                scanned.total += 1;
+                if scanned.links.len() < limit {
+                    scanned.links.push(url);
+                }
+            }
+        }
+    }
+    scanned
+}
+
+/// 以`base`为起点解析链接，不是`http`或者`https`链接时返回[`None`]
+fn resolve_link(base: &Url, value: &str) -> Option<Url> {
+    if value.is_empty() || value.starts_with('#') {
+        return None;
+    }
+    let url = base.join(value).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| without_fragment(url))
+}
+
+fn without_fragment(mut url: Url) -> Url {
+    url.set_fragment(None);
+    url
+}
+
+/// `<`之后到标签结尾的`>`的长度，引号中的`>`不算标签的结尾
+fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (index, byte) in tag.bytes().enumerate() {
+        match (quote, byte) {
+            (None, b'>') => return index,
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (Some(q), _) if q == byte => quote = None,
+            _ => {}
+        }
+    }
+    tag.len()
+}
+
+/// 将`<`与`>`之间的内容分为标签名以及属性，没有值的属性的值为空
+fn parse_tag(tag: &str) -> (&str, Vec<(&str, &str)>) {
+    let is_delimiter = |c: char| c.is_ascii_whitespace() || c == '/' || c == '=';
+    let name_end = tag.find(is_delimiter).unwrap_or(tag.len());
+    let (name, mut rest) = tag.split_at(name_end);
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        let end = rest.find(is_delimiter).unwrap_or(rest.len()).max(1);
+        let attribute = &rest[..end];
+        rest = rest[end..].trim_start();
+        let Some(after_equal) = rest.strip_prefix('=') else {
+            attributes.push((attribute, ""));
+            continue;
+        };
+        rest = after_equal.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &rest[1..];
+                let end = value.find(quote).unwrap_or(value.len());
+                rest = value.get(end + 1..).unwrap_or_default();
+                &value[..end]
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            }
+        };
+        attributes.push((attribute, value));
+    }
+    (name, attributes)
+}
+
+/// 解码属性值中的字符引用，例如查询参数之间的`&amp;`，无法识别的保持原样
+fn decode_entities(value: &str) -> Cow<'_, str> {
+    if !value.contains('&') {
+        return Cow::Borrowed(value);
+    }
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[..end])?, end + 1)));
+        match entity {
+            Some((c, len)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => decoded.push('&'),
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let code = match entity {
+        "amp" => return Some('&'),
+        "lt" => return Some('<'),
+        "gt" => return Some('>'),
+        "quot" => return Some('"'),
+        "apos" => return Some('\''),
+        _ => match entity.strip_prefix('#')? {
+            hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok()?,
+            decimal => decimal.parse().ok()?,
+        },
+    };
+    char::from_u32(code)
+}
+
+/// 读取`path`并且提取其中的链接，`sniff`为`true`时先检查文件是不是HTML页面
+pub fn scan_file(path: &Path, page: &Url, sniff: bool) -> io::Result<ScannedLinks> {
+    let bytes = peek::read_head(path, LinkScan::MAX_SCAN_BYTES)?;
+    if sniff && FileKind::detect(&bytes) != Some(FileKind::Html) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file is not an HTML page",
+        ));
+    }
+    Ok(extract_links(
+        &String::from_utf8_lossy(&bytes),
+        page,
+        LinkScan::MAX_LINKS,
+    ))
+}
+
+/// 在后台读取下载到的HTML页面并且提取其中的链接，同一时间只有一个扫描
+#[derive(Debug, Default)]
+pub struct LinkScan {
+    runtime: Option<Handle>,
+    running: Option<(Url, std_mpsc::Receiver<io::Result<ScannedLinks>>)>,
+}
+
+impl LinkScan {
+    // ------------------- CONSTANT -----------------------
+
+    /// 最多读取的字节数，错误页面通常只有几十KB
+    pub const MAX_SCAN_BYTES: usize = 4 * 1024 * 1024;
+    /// 最多列出的链接数量
+    pub const MAX_LINKS: usize = 200;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        LinkScan::default()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// 开始扫描`path`，相对链接以`page`为起点，替换还没有完成的扫描。
+    /// 后台运行时还没有启动时返回`false`
+    pub fn start(&mut self, path: PathBuf, page: Url, sniff: bool) -> bool {
+        let Some(runtime) = &self.runtime else {
+            return false;
+        };
+        let (sender, receiver) = std_mpsc::channel();
+        let base = page.clone();
+        runtime.spawn_blocking(move || {
+            let _ = sender.send(scan_file(&path, &base, sniff));
+        });
+        self.running = Some((page, receiver));
+        true
+    }
+
+    /// 取出已经完成的扫描，返回页面的URL以及找到的链接
+    pub fn poll(&mut self) -> Option<(Url, io::Result<ScannedLinks>)> {
+        let (_, receiver) = self.running.as_ref()?;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std_mpsc::TryRecvError::Empty) => return None,
+            Err(std_mpsc::TryRecvError::Disconnected) => Err(io::Error::other(
+                "background runtime stopped before reading the file",
+            )),
+        };
+        let (page, _) = self.running.take()?;
+        Some((page, result))
+    }
+}
@@ -1,57 +1,139 @@
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use ratatui::widgets::StatefulWidget;
 use ratatui::{prelude::*, widgets::Paragraph};
-use tokio::sync::{mpsc, oneshot};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot,
+};
+use url::Url;
 
+use crate::app::policy::PolicyMatch;
+use crate::app::record::TaskRecord;
 use crate::app::sender::Sender;
-use crate::app::task::{TaskCommand, TaskStateRenderState};
+use crate::app::stats::HostStatsMap;
+use crate::app::task::{
+    self, FileConflict, HealthThresholds, MiddleRowMode, SpeedDisplay, TaskCommand, TaskId,
+    TaskPhase, TaskStateRenderState,
+};
 use crate::{
-    app::task::{TaskFinalStage, TaskResult, TaskState},
+    app::task::{StageKind, TaskResult, TaskState},
     window::app::{FinishState, FinishedTask},
+    window::common::{self, SymbolSet},
 };
 
+mod phase;
+
+pub use phase::*;
+
 pub struct TaskListener {
+    id: TaskId,
     state: Arc<Mutex<TaskState>>,
     channel: ListenerChannel,
 
     // Task的结果，一旦接收后就存储在这里。
     // 如果是None表明Task还没有完成
     task_result: Option<TaskResult>,
-    // 一个标志，表示结果是否已经被处理过
-    processed: bool,
-    stopped: bool,
+    // 只能通过transition修改，见ListenerPhase
+    phase: ListenerPhase,
+    // 是否已经为文件冲突弹出过对话框
+    conflict_prompted: bool,
+    // 是否已经为匹配文件类型规则弹出过确认对话框
+    policy_prompted: bool,
+    // 因为磁盘空间不足而暂停时，继续下载还需要的字节数，长度未知时见UNKNOWN_SIZE_RESERVE
+    waiting_for_space: Option<u64>,
+    // 发出停止指令的原因，恢复时清除
+    pause_origin: Option<PauseOrigin>,
+    // 发出停止或者取消指令的时间，用于判断任务是否迟迟没有响应
+    stop_sent_at: Option<Instant>,
+    // 已经下载的部分正在被移动到新的下载目录，期间不能恢复
+    migrating: bool,
+
+    // 已经计入主机统计的下载量，以及上一次计入的时间
+    reported_bytes: u64,
+    reported_at: Instant,
+    // 最近一次有数据传输时的速度（B/s）以及时间
+    recent_speed: Option<(u64, Instant)>,
+    // 已经计入主机统计的TTFB，同一次请求暂停多次时只计入一次
+    reported_ttfb: Option<Duration>,
+
+    // 任务提交的时间，用于计算任务的耗时
+    created_at: Instant,
 }
 
 impl TaskListener {
     pub const RENDER_HEIGHT: u16 = TaskState::RENDER_HEIGHT;
+    /// 发出停止或者取消指令之后等待任务响应的时间，超过之后显示为未确认
+    pub const STOP_CONFIRM_TIMEOUT: Duration = Duration::from_secs(3);
+    /// 长度未知的任务因为空间不足而暂停时，自动恢复之前至少需要的剩余空间
+    ///
+    /// 这类任务只能在写满磁盘时失败，如果有一点空间就恢复，会反复写满磁盘、再次暂停。
+    pub const UNKNOWN_SIZE_RESERVE: u64 = 1 << 30;
 
     // -------------------- CONSTRUCT -----------------------
 
     pub fn new(
+        id: TaskId,
         state: Arc<Mutex<TaskState>>,
         result_recv: oneshot::Receiver<TaskResult>,
-        command_sender: oneshot::Sender<TaskCommand>,
+        command_sender: mpsc::UnboundedSender<TaskCommand>,
     ) -> Self {
         TaskListener {
+            id,
             state,
             channel: ListenerChannel::new(result_recv, command_sender),
             task_result: None,
-            processed: false,
-            stopped: false,
+            phase: ListenerPhase::Submitted,
+            conflict_prompted: false,
+            policy_prompted: false,
+            waiting_for_space: None,
+            pause_origin: None,
+            stop_sent_at: None,
+            migrating: false,
+            reported_bytes: 0,
+            reported_at: Instant::now(),
+            recent_speed: None,
+            reported_ttfb: None,
+            created_at: Instant::now(),
         }
     }
 
+    /// 测试用的任务，状态已经填好，不连接任何后台任务，阶段按照状态中的[`TaskPhase`]确定
+    #[cfg(test)]
+    pub fn fake(id: TaskId, state: TaskState) -> Self {
+        let (_, result_recv) = oneshot::channel();
+        let (command_sender, _) = mpsc::unbounded_channel();
+        let mut listener =
+            TaskListener::new(id, Arc::new(Mutex::new(state)), result_recv, command_sender);
+        listener.observe_phase();
+        listener
+    }
+
+    /// 测试用的因为磁盘空间不足而暂停的任务，见[`TaskListener::fake`]
+    #[cfg(test)]
+    pub fn fake_waiting_for_space(id: TaskId, state: TaskState) -> Self {
+        let mut listener = Self::fake(id, state);
+        listener.phase = ListenerPhase::Paused;
+        listener.mark_waiting_for_space();
+        listener
+    }
+
     // -------------------- TYPE_CONVERSION -----------------------
 
     pub fn into_finished_task(&mut self) -> FinishedTask {
-        self.processed = true;
+        // 暂停的任务被取消或者恢复失败时也会移动到完成列表中
+        let stage = self
+            .task_result
+            .as_ref()
+            .map_or(StageKind::Interrupted, TaskResult::kind);
+        self.transition(ListenerPhase::Terminal(stage));
         let finish_state = match &self.task_result {
             None => FinishState::Failure,
-            Some(r) => match r.stage() {
-                TaskFinalStage::Finished => FinishState::Success,
-                _ => FinishState::Failure,
-            },
+            Some(r) if r.stage().is_success() => FinishState::Success,
+            Some(_) => FinishState::Failure,
         };
         let cloned_state = {
             let state_lock = self.state.lock().unwrap();
@@ -59,13 +141,10 @@ impl TaskListener {
         };
 
         let content_length = match self.task_result.as_ref() {
-            Some(result) => match result.final_stage {
-                TaskFinalStage::Finished => cloned_state
-                    .content_length()
-                    .or(Some(cloned_state.downloaded())),
-                _ => cloned_state.content_length(),
-            },
-            None => cloned_state.content_length(),
+            Some(result) if result.stage().is_success() => cloned_state
+                .content_length()
+                .or(Some(cloned_state.downloaded())),
+            _ => cloned_state.content_length(),
         };
 
         FinishedTask::new(
@@ -74,7 +153,44 @@ impl TaskListener {
             cloned_state.url().cloned(),
             content_length,
             cloned_state.downloaded(),
+            self.task_result.clone(),
+            self.created_at.elapsed(),
         )
+        .with_options(cloned_state.options().cloned())
+        .with_extracted(cloned_state.extracted().map(Path::to_path_buf))
+        .with_completed(cloned_state.completed().cloned())
+        .with_network(cloned_state.network().cloned())
+        .with_checksum(cloned_state.expected_checksum())
+        .with_verified_checksum(cloned_state.verified_checksum().cloned())
+        .with_peak_speed(cloned_state.peak_speed())
+        .with_ttfb(cloned_state.ttfb())
+        .with_content_type(cloned_state.content_type().map(str::to_string))
+        .with_id(self.id)
+    }
+
+    /// 退出时仍在进行的任务，没有结果时视为被中断
+    ///
+    /// 因为停止指令而中断（或者退出时还在停止中）的任务同时记录停止的原因。
+    pub fn to_record(&self) -> TaskRecord {
+        let state = self.state.lock().unwrap();
+        let stage = self
+            .task_result
+            .as_ref()
+            .map_or(StageKind::Interrupted, TaskResult::kind);
+        let pause_origin = self
+            .pause_origin
+            .filter(|_| stage == StageKind::Interrupted);
+        TaskRecord::new(
+            state.url().map(Url::to_string),
+            stage,
+            state.filepath().to_path_buf(),
+            state.downloaded(),
+            state.content_length(),
+            self.created_at.elapsed(),
+        )
+        .with_peak_speed(state.peak_speed())
+        .with_ttfb(state.ttfb())
+        .with_pause_origin(pause_origin)
     }
 
     // -------------------- FUNCTION -----------------------
@@ -82,8 +198,12 @@ impl TaskListener {
     pub fn resume_task(
         &mut self,
         sender: &mut Sender,
-    ) -> Result<(), Box<mpsc::error::SendError<Arc<Mutex<TaskState>>>>> {
-        if !self.stopped {
+    ) -> Result<(), Box<TrySendError<Arc<Mutex<TaskState>>>>> {
+        if self.phase != ListenerPhase::Paused {
+            return Ok(());
+        }
+        if self.migrating {
+            log::warn!(target:"App", "Task {} is being moved, cannot resume now", self.id);
             return Ok(());
         }
 
@@ -91,56 +211,254 @@ impl TaskListener {
             result_recv,
             command_sender,
         } = sender
-            .send_resume_request(self.state.clone())
-            .map_err(|t| Box::new(mpsc::error::SendError(t.0.release_state())))?;
-
-        self.stopped = false;
+            .send_resume_request(self.id, self.state.clone())
+            .map_err(|e| {
+                Box::new(match *e {
+                    TrySendError::Full(t) => TrySendError::Full(t.release_state()),
+                    TrySendError::Closed(t) => TrySendError::Closed(t.release_state()),
+                })
+            })?;
+
+        self.transition(ListenerPhase::Submitted);
+        self.conflict_prompted = false;
+        self.policy_prompted = false;
+        self.waiting_for_space = None;
+        self.pause_origin = None;
+        self.stop_sent_at = None;
+        // 暂停的这段时间不计入传输时间
+        self.reported_at = Instant::now();
         self.channel.command_sender = command_sender;
         self.channel.result_recv = result_recv;
-        self.processed = false;
         self.task_result = None;
         Ok(())
     }
 
-    pub fn try_receive(&mut self) -> Option<&TaskResult> {
-        if self.task_result.is_some() || self.processed || self.stopped {
-            // 已经接收过结果，直接返回
-            return self.task_result.as_ref();
+    /// 从头重新下载暂停的任务，保留同样的ID、文件以及选项
+    ///
+    /// 已经下载的部分不会在这里删除，而是由恢复的任务截断为0，
+    /// 这样同名的文件不会在这期间被其他任务占用。
+    pub fn restart_task(
+        &mut self,
+        sender: &mut Sender,
+    ) -> Result<(), Box<TrySendError<Arc<Mutex<TaskState>>>>> {
+        if self.phase != ListenerPhase::Paused || self.migrating {
+            return Ok(());
         }
+        self.state.lock().unwrap().reset_progress();
+        self.reported_bytes = 0;
+        self.recent_speed = None;
+        self.resume_task(sender)
+    }
 
-        match self.result_recv_channel().try_recv() {
-            Ok(task_result) => {
-                self.task_result = Some(task_result);
-            }
-            Err(oneshot::error::TryRecvError::Empty) => {}
-            Err(oneshot::error::TryRecvError::Closed) => {
-                self.task_result = Some(TaskResult::new_unknown_error(String::from(
-                    "Task result channel closed unexpectedly",
-                )));
+    /// 接收任务的结果，并根据结果转换到[`ListenerPhase::Paused`]或者[`ListenerPhase::Terminal`]
+    ///
+    /// 只在第一次收到结果时返回结果的阶段，之后以及任务还在运行时返回[`None`]。
+    /// 结果通道意外关闭时视为[`StageKind::UnknownError`]。
+    pub fn receive_result(&mut self) -> Option<StageKind> {
+        if !self.phase.is_active() {
+            return None;
+        }
+
+        let task_result = match self.result_recv_channel().try_recv() {
+            Ok(task_result) => task_result,
+            Err(oneshot::error::TryRecvError::Empty) => return None,
+            Err(oneshot::error::TryRecvError::Closed) => TaskResult::new_unknown_error(
+                String::from("Task result channel closed unexpectedly"),
+            ),
+        };
+        let stage = task_result.kind();
+        self.task_result = Some(task_result);
+        if stage == StageKind::InsufficientDiskSpace {
+            self.mark_waiting_for_space();
+        }
+        self.transition(ListenerPhase::after_result(stage));
+        Some(stage)
+    }
+
+    /// 根据任务当前的[`TaskPhase`]更新所处的阶段，停止中、取消中以及已经有结果的任务不受影响
+    pub fn observe_phase(&mut self) {
+        let next = ListenerPhase::observe(self.state.lock().unwrap().phase());
+        if !self.phase.is_stopping() && self.phase.can_transition_to(next) {
+            self.phase = next;
+        }
+    }
+
+    /// 停止任务，同时记录停止的原因，已经暂停或者结束的任务不受影响
+    ///
+    /// 收到任务的结果之前处于[`ListenerPhase::Pausing`]。
+    pub fn stop(&mut self, origin: PauseOrigin) {
+        if !self.phase.is_active() || self.phase == ListenerPhase::Cancelling {
+            return;
+        }
+        self.pause_origin = Some(origin);
+        self.send_command(TaskCommand::Stop);
+        if self.phase != ListenerPhase::Pausing && self.transition(ListenerPhase::Pausing) {
+            self.stop_sent_at = Some(Instant::now());
+        }
+    }
+
+    /// 取消正在运行的任务，收到任务的结果之前处于[`ListenerPhase::Cancelling`]
+    pub fn cancel(&mut self) {
+        if !self.phase.is_active() {
+            return;
+        }
+        self.send_command(TaskCommand::Abort);
+        if self.phase != ListenerPhase::Cancelling && self.transition(ListenerPhase::Cancelling) {
+            self.stop_sent_at = Some(Instant::now());
+        }
+    }
+
+    /// 设置是否加速该任务，同时记录在状态中，暂停的任务恢复之后仍然加速
+    pub fn set_boost(&self, boost: bool) {
+        let priority = {
+            let mut state = self.state.lock().unwrap();
+            state.boost = boost;
+            state.priority
+        };
+        self.send_command(TaskCommand::SetPriority { priority, boost });
+    }
+
+    /// 可以多次调用，任务暂停或者结束之后的指令会被忽略
+    pub fn send_command(&self, command: TaskCommand) {
+        if self.phase.is_active() {
+            log::debug!("Sending command to task: {:?}", command);
+            let _ = self.channel.command_sender.send(command);
+        }
+    }
+
+    /// 如果任务正在等待用户决定如何处理已经存在的文件，并且还没有弹出过对话框，
+    /// 则返回冲突信息，同时标记为已经弹出过对话框。
+    pub fn take_conflict_prompt(&mut self) -> Option<FileConflict> {
+        if self.conflict_prompted || !self.phase.is_active() {
+            return None;
+        }
+        let conflict = self.state.lock().unwrap().conflict().cloned();
+        if conflict.is_some() {
+            self.conflict_prompted = true;
+        }
+        conflict
+    }
+
+    /// 任务仍在等待用户回应时，允许再次弹出对话框，用于对话框被稍后回答关闭之后从列表中重新打开
+    ///
+    /// 返回`false`表示任务没有在等待用户回应。
+    pub fn reopen_prompt(&mut self) -> bool {
+        if !self.phase.is_active() || !self.state.lock().unwrap().phase().is_awaiting_user() {
+            return false;
+        }
+        self.conflict_prompted = false;
+        self.policy_prompted = false;
+        true
+    }
+
+    /// 如果任务正在等待用户确认是否下载匹配文件类型规则的文件，并且还没有弹出过对话框，
+    /// 则返回文件路径和匹配的规则，同时标记为已经弹出过对话框。
+    pub fn take_policy_prompt(&mut self) -> Option<(PathBuf, PolicyMatch)> {
+        if self.policy_prompted || !self.phase.is_active() {
+            return None;
+        }
+        let prompt = {
+            let state = self.state.lock().unwrap();
+            if state.phase() != TaskPhase::AwaitingConfirm {
+                return None;
             }
+            (
+                state.filepath().to_path_buf(),
+                state.policy_match()?.clone(),
+            )
+        };
+        self.policy_prompted = true;
+        Some(prompt)
+    }
+
+    /// 将上次调用以来新下载的数据计入该任务当前URL所在主机的统计中
+    ///
+    /// 如果任务在这段时间内有数据传输，则这段时间也会计入该主机的传输时间。
+    ///
+    /// 返回距离上次报告新接收的字节数。
+    pub fn report_host_progress(&mut self, hosts: &mut HostStatsMap) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.reported_at);
+        self.reported_at = now;
+
+        let (host, downloaded) = {
+            let state = self.state.lock().unwrap();
+            (state.host().map(str::to_string), state.downloaded())
+        };
+        // 不支持断点续传的任务恢复时会从头开始下载
+        if downloaded < self.reported_bytes {
+            self.reported_bytes = 0;
         }
+        let delta = downloaded - self.reported_bytes;
+        self.reported_bytes = downloaded;
 
-        self.task_result.as_ref()
+        if delta > 0 {
+            let speed = (delta as f64 / elapsed.as_secs_f64().max(f64::EPSILON)) as u64;
+            self.recent_speed = Some((speed, now));
+        }
+        if delta > 0
+            && let Some(host) = host
+        {
+            hosts.record_progress(&host, delta, elapsed);
+        }
+        delta
+    }
+
+    /// 更新任务的下载速度、健康状况以及剩余时间，见[`TaskState::ui_update`]
+    ///
+    /// 每一帧为所有任务调用，不在屏幕中的任务的速度也会被更新。
+    pub fn update_speed(&self, thresholds: &HealthThresholds, half_life: Duration) {
+        self.state
+            .lock()
+            .unwrap()
+            .ui_update(thresholds, half_life, self.phase.is_active());
     }
 
-    pub fn send_command(&mut self, command: TaskCommand) {
-        let sender = self.command_sender_channel().take();
-        if let Some(sender) = sender
-            && !self.stopped
+    /// 将任务的最终结果计入主机统计中，在接收到结果后调用，返回值同[`report_host_progress`]
+    ///
+    /// [`report_host_progress`]: TaskListener::report_host_progress
+    pub fn report_host_result(&mut self, hosts: &mut HostStatsMap) -> u64 {
+        let delta = self.report_host_progress(hosts);
+        let (host, peak_speed, ttfb) = {
+            let state = self.state.lock().unwrap();
+            (
+                state.host().map(str::to_string),
+                state.peak_speed(),
+                state.ttfb(),
+            )
+        };
+        if let Some(host) = &host
+            && let Some(peak_speed) = peak_speed
         {
-            log::debug!("Sending command to task: {:?}", command);
-            let _ = sender.send(command);
+            hosts.record_peak(host, peak_speed);
+        }
+        if let Some(host) = &host
+            && let Some(sample) = ttfb
+            && ttfb != self.reported_ttfb
+        {
+            self.reported_ttfb = ttfb;
+            hosts.record_ttfb(host, sample);
+        }
+        if let Some(host) = host
+            && let Some(result) = &self.task_result
+        {
+            hosts.record_result(&host, result.kind());
         }
+        delta
     }
 
     // -------------------- MEMBER_ACCESS -----------------------
 
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
     pub fn result_recv_channel(&mut self) -> &mut oneshot::Receiver<TaskResult> {
         &mut self.channel.result_recv
     }
 
-    pub fn command_sender_channel(&mut self) -> &mut Option<oneshot::Sender<TaskCommand>> {
-        &mut self.channel.command_sender
+    pub fn command_sender(&self) -> mpsc::UnboundedSender<TaskCommand> {
+        self.channel.command_sender.clone()
     }
 
     pub fn get_state_handler(&self) -> Arc<Mutex<TaskState>> {
@@ -151,22 +469,102 @@ impl TaskListener {
         self.task_result.as_ref()
     }
 
-    pub fn processed(&self) -> bool {
-        self.processed
+    /// 最近一秒内有数据传输时的下载速度（B/s），否则为0
+    pub fn recent_speed(&self) -> u64 {
+        match self.recent_speed {
+            Some((speed, at))
+                if self.phase.is_active() && at.elapsed() < Duration::from_secs(1) =>
+            {
+                speed
+            }
+            _ => 0,
+        }
     }
 
-    pub fn is_stopped(&mut self) -> bool {
-        self.stopped
+    pub fn phase(&self) -> ListenerPhase {
+        self.phase
+    }
+
+    /// 发出停止或者取消指令之后超过[`TaskListener::STOP_CONFIRM_TIMEOUT`]仍然没有收到结果
+    ///
+    /// 此时任务可能卡在网络或者磁盘IO中，收到结果之前仍然不能恢复。
+    pub fn is_stop_unconfirmed(&self, now: Instant) -> bool {
+        self.phase.is_stopping()
+            && self
+                .stop_sent_at
+                .is_some_and(|sent| now.duration_since(sent) >= Self::STOP_CONFIRM_TIMEOUT)
+    }
+
+    /// 停止中以及取消中的任务显示的状态，其他阶段返回[`None`]
+    pub fn stopping_label(&self, now: Instant) -> Option<&'static str> {
+        let unconfirmed = self.is_stop_unconfirmed(now);
+        match self.phase {
+            ListenerPhase::Pausing if self.pause_origin == Some(PauseOrigin::Restart) => {
+                Some("Restarting...")
+            }
+            ListenerPhase::Pausing if unconfirmed => Some("Paused (unconfirmed)"),
+            ListenerPhase::Pausing => Some("Pausing..."),
+            ListenerPhase::Cancelling if unconfirmed => Some("Cancelled (unconfirmed)"),
+            ListenerPhase::Cancelling => Some("Cancelling..."),
+            _ => None,
+        }
+    }
+
+    /// 任务因为停止指令而暂停时，停止的原因
+    pub fn pause_origin(&self) -> Option<PauseOrigin> {
+        match self.task_result.as_ref()?.kind() {
+            StageKind::Interrupted => self.pause_origin,
+            _ => None,
+        }
+    }
+
+    /// 因为磁盘空间不足而暂停时，继续下载还需要的字节数
+    pub fn waiting_for_space(&self) -> Option<u64> {
+        self.waiting_for_space
+    }
+
+    pub fn is_migrating(&self) -> bool {
+        self.migrating
+    }
+
+    pub fn is_boosted(&self) -> bool {
+        self.state.lock().unwrap().boost
     }
 
     // -------------------- MODIFIER -----------------------
 
-    pub fn mark_processed(&mut self) {
-        self.processed = true;
+    /// 转换到`next`阶段，不合法的转换会被忽略并记录警告，返回是否转换成功
+    fn transition(&mut self, next: ListenerPhase) -> bool {
+        if !self.phase.can_transition_to(next) {
+            log::warn!(target:"App", "Task {}: illegal phase transition {:?} -> {:?}", self.id, self.phase, next);
+            return false;
+        }
+        self.phase = next;
+        true
+    }
+
+    /// 只有暂停的任务可以移动，任务正在运行时返回`false`
+    pub fn set_migrating(&mut self, migrating: bool) -> bool {
+        if migrating && !self.phase.is_paused() {
+            return false;
+        }
+        self.migrating = migrating;
+        true
     }
 
-    pub fn mark_stopped(&mut self) {
-        self.stopped = true;
+    /// 标记为等待磁盘空间，收到结果之后同样进入暂停阶段，用户仍然可以手动恢复或者取消
+    ///
+    /// 长度未知时需要的空间为[`TaskListener::UNKNOWN_SIZE_RESERVE`]。
+    fn mark_waiting_for_space(&mut self) {
+        let required = {
+            let state = self.state.lock().unwrap();
+            state
+                .content_length()
+                .map_or(Self::UNKNOWN_SIZE_RESERVE, |len| {
+                    len.saturating_sub(state.downloaded())
+                })
+        };
+        self.waiting_for_space = Some(required);
     }
 }
 
@@ -174,15 +572,38 @@ impl TaskListener {
 pub struct TaskListenerRanderState {
     pub page_focused: bool,
     pub selected: bool,
+    pub middle_row: MiddleRowMode,
+    pub symbols: SymbolSet,
+    pub show_remaining: bool,
+    pub speed_display: SpeedDisplay,
 }
 
 impl TaskListenerRanderState {
-    pub fn new(page_focused: bool, selected: bool) -> Self {
+    pub fn new(
+        page_focused: bool,
+        selected: bool,
+        middle_row: MiddleRowMode,
+        symbols: SymbolSet,
+    ) -> Self {
         TaskListenerRanderState {
             page_focused,
             selected,
+            middle_row,
+            symbols,
+            show_remaining: false,
+            speed_display: SpeedDisplay::default(),
         }
     }
+
+    pub fn with_show_remaining(mut self, show_remaining: bool) -> Self {
+        self.show_remaining = show_remaining;
+        self
+    }
+
+    pub fn with_speed_display(mut self, speed_display: SpeedDisplay) -> Self {
+        self.speed_display = speed_display;
+        self
+    }
 }
 
 impl StatefulWidget for &TaskListener {
@@ -195,47 +616,251 @@ impl StatefulWidget for &TaskListener {
         ])
         .split(area)[0];
 
-        let mut cloned_state = {
-            let mut state_lock = self.state.lock().unwrap();
-            // 首先更新下载速度等状态信息
-            state_lock.ui_update();
-            // 然后克隆一份用于渲染
-            state_lock.clone()
-            // 此处unlock，这样在渲染时不会阻塞其他线程对state的访问
-        };
-
-        cloned_state.render(
-            area,
-            buf,
-            &mut TaskStateRenderState::new(state.page_focused, state.selected),
-        );
-
-        let text_area =
-            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(area)[1];
+        // 下载速度等状态信息已经在update_speed中更新，此处只克隆一份用于渲染，
+        // 这样在渲染时不会阻塞其他线程对state的访问
+        let mut cloned_state = self.state.lock().unwrap().clone();
 
-        let text = match &self.task_result {
-            Some(result) => result.final_stage.to_string(),
-            None => String::from("Downloading..."),
+        let mut render_state = TaskStateRenderState::new(
+            state.page_focused,
+            state.selected,
+            state.middle_row,
+            state.symbols,
+        )
+        .with_id(self.id)
+        .with_show_remaining(state.show_remaining)
+        .with_speed_display(state.speed_display);
+        cloned_state.render(area, buf, &mut render_state);
+
+        // 状态显示在右侧各列之前的空间中
+        let footer = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(area)[1];
+        let [text_area, _] = cloned_state.footer_columns(&render_state).split(footer);
+
+        let text = match (self.phase, &self.task_result) {
+            _ if let Some(label) = self.stopping_label(Instant::now()) => String::from(label),
+            (ListenerPhase::Paused, _) if self.pause_origin() == Some(PauseOrigin::BulkPause) => {
+                String::from("Stopped (all)")
+            }
+            (ListenerPhase::Paused | ListenerPhase::Terminal(_), Some(result)) => {
+                task::format_error_brief(result, text_area.width as usize)
+            }
+            // 下载速度受限于磁盘时在状态之后提示
+            _ if cloned_state.is_disk_bound() => format!(
+                "{} {}",
+                cloned_state.status_label(),
+                state.symbols.disk_bound
+            ),
+            _ => String::from(cloned_state.status_label()),
         };
+        // 按照实际显示的文本测量宽度，放不下时以省略号结尾，而不是在列之前被截断
+        let text = common::truncate_to_width(&text, text_area.width as usize);
         Paragraph::new(text).left_aligned().render(text_area, buf);
     }
 }
 
+/// 任务被停止的原因
+///
+/// 批量停止的任务与用户单独停止的任务需要区分开，这样批量继续时只会恢复批量停止的任务，
+/// 用户之前单独停止的任务保持暂停。停止的原因随[`TaskRecord`]一起写入退出摘要。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseOrigin {
+    /// 用户停止了这个任务
+    User,
+    /// 用户停止了所有任务
+    BulkPause,
+    /// 停止后立即从头重新下载，见[`TaskListener::restart_task`]
+    Restart,
+}
+
 pub struct ListenerChannel {
     pub result_recv: oneshot::Receiver<TaskResult>,
 
     // 用于给Task发送指令
-    pub command_sender: Option<oneshot::Sender<TaskCommand>>,
+    pub command_sender: mpsc::UnboundedSender<TaskCommand>,
 }
 
 impl ListenerChannel {
     pub fn new(
         result_recv: oneshot::Receiver<TaskResult>,
-        command_sender: oneshot::Sender<TaskCommand>,
+        command_sender: mpsc::UnboundedSender<TaskCommand>,
     ) -> Self {
         ListenerChannel {
             result_recv,
-            command_sender: Some(command_sender),
+            command_sender,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::Config;
+    use crate::app::sender::{DownloadRequest, RequestOptions};
+    use crate::app::task::{
+        AdmissionPolicy, ConnectionLimits, RuntimeStats, TaskContext, TaskEvents, resolve,
+    };
+    use crate::app::throttle::{SpeedLimit, Throttle};
+
+    /// 只允许同时进行一个任务的执行环境
+    fn context(limits: Arc<ConnectionLimits>) -> TaskContext {
+        let config = Config {
+            max_concurrent_tasks: 1,
+            ..Config::default()
+        };
+        TaskContext::new(
+            Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+            limits,
+            TaskEvents::default(),
+            &config,
+        )
+    }
+
+    #[test]
+    fn unknown_size_waits_for_a_reserve() {
+        let mut listener = TaskListener::fake(TaskId::new(1), TaskState::new());
+        listener.mark_waiting_for_space();
+        assert_eq!(
+            listener.waiting_for_space(),
+            Some(TaskListener::UNKNOWN_SIZE_RESERVE)
+        );
+
+        let mut state = TaskState::new();
+        state.content_length = Some(1000);
+        state.downloaded = 400;
+        let mut listener = TaskListener::fake(TaskId::new(2), state);
+        listener.mark_waiting_for_space();
+        assert_eq!(listener.waiting_for_space(), Some(600));
+    }
+
+    #[tokio::test]
+    async fn stopped_while_queued_resumes_as_new_request() {
+        // 唯一的名额被占用，新的任务只能排队
+        let limits = Arc::new(ConnectionLimits::new(1, 0, AdmissionPolicy::default()));
+        let blocker = limits.request(None, 0, false, Arc::new(Mutex::new(TaskState::new())));
+        let permit = blocker.wait().await;
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), std::env::temp_dir());
+        sender.connect(tx);
+        let url = String::from("http://127.0.0.1:9/queued.bin");
+        let id = sender.next_id();
+        let mut listener = sender
+            .send_normal_request(id, RequestOptions::new(url.clone(), None))
+            .unwrap();
+
+        let task = rx.recv().await.unwrap();
+        let ctx = context(limits.clone());
+        let queued = tokio::spawn(async move { resolve::handle_task(task, &ctx).await });
+        listener.stop(PauseOrigin::User);
+        queued.await.unwrap();
+
+        assert_eq!(listener.receive_result(), Some(StageKind::Interrupted));
+        assert_eq!(listener.phase(), ListenerPhase::Paused);
+        assert!(!listener.state.lock().unwrap().has_started());
+
+        listener.resume_task(&mut sender).unwrap();
+        let task = rx.recv().await.unwrap();
+        match task.request() {
+            DownloadRequest::Normal { url: sent, .. } => assert_eq!(sent, &url),
+            request => panic!("a task that never started was sent as {:?}", request),
+        }
+
+        // 重新发送的任务正常地运行到底，连接失败而不是崩溃
+        drop(permit);
+        resolve::handle_task(task, &context(limits)).await;
+        let stage = listener.receive_result().unwrap();
+        assert_ne!(stage, StageKind::UnknownError);
+        assert!(listener.state.lock().unwrap().url().is_some());
+    }
+
+    #[tokio::test]
+    async fn resume_and_restart_do_not_count_the_pause_as_transfer_time() {
+        let (tx, _rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), std::env::temp_dir());
+        sender.connect(tx);
+        let paused_for = Duration::from_secs(600);
+
+        for restart in [false, true] {
+            let mut state = TaskState::new();
+            state.url = Some("http://example.com/a".parse().unwrap());
+            state.downloaded = 1000;
+            let mut listener = TaskListener::fake(sender.next_id(), state);
+            listener.phase = ListenerPhase::Paused;
+            listener.reported_bytes = 1000;
+            listener.reported_at = Instant::now() - paused_for;
+
+            if restart {
+                listener.restart_task(&mut sender).unwrap();
+            } else {
+                listener.resume_task(&mut sender).unwrap();
+            }
+            assert_eq!(listener.phase(), ListenerPhase::Submitted);
+            assert!(listener.reported_at.elapsed() < paused_for / 2);
+
+            // 恢复之后的第一次报告只计入恢复之后经过的时间
+            let mut hosts = HostStatsMap::default();
+            listener.state.lock().unwrap().downloaded += 500;
+            listener.report_host_progress(&mut hosts);
+            let host = hosts.get("example.com").unwrap();
+            assert!(host.transfer_secs < paused_for.as_secs_f64() / 2.0);
+        }
+    }
+
+    /// 绘制`listener`，返回最后一行的文本
+    fn footer_row(listener: &TaskListener, width: u16) -> String {
+        let area = Rect::new(0, 0, width, TaskListener::RENDER_HEIGHT);
+        let mut buf = Buffer::empty(area);
+        let mut state =
+            TaskListenerRanderState::new(true, false, MiddleRowMode::default(), SymbolSet::UNICODE);
+        listener.render(area, &mut buf, &mut state);
+        (0..width)
+            .map(|x| buf[(x, TaskListener::RENDER_HEIGHT - 1)].symbol())
+            .collect()
+    }
+
+    #[test]
+    fn long_status_is_shortened_without_moving_the_columns() {
+        let downloading = || {
+            let mut state = TaskState::new();
+            state.phase = TaskPhase::Downloading;
+            state.content_length = Some(4 << 20);
+            state.downloaded = 1 << 20;
+            state
+        };
+        let short = TaskListener::fake(TaskId::new(1), downloading());
+        let mut busy = downloading();
+        busy.file_busy = true;
+        let long = TaskListener::fake(TaskId::new(2), busy);
+
+        let short = footer_row(&short, 56);
+        let long = footer_row(&long, 56);
+        let column = |row: &str| row.chars().position(|c| c == '│');
+        assert!(column(&short).is_some());
+        assert_eq!(column(&long), column(&short));
+        assert!(short.starts_with("Downloading..."), "{}", short);
+        // 放不下的状态以省略号结尾，并且与右侧的列之间仍然隔开一格
+        assert!(long.starts_with("File busy"), "{}", long);
+        let leading = long.split('│').next().unwrap().trim_end();
+        assert!(leading.contains('…'), "{}", long);
+        assert_eq!(short.split('│').nth(1), long.split('│').nth(1));
+    }
+
+    #[tokio::test]
+    async fn resume_without_url_reports_an_error() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), std::env::temp_dir());
+        sender.connect(tx);
+        // 没有选项时无法作为新的任务发送，只能按照恢复处理
+        let state = Arc::new(Mutex::new(TaskState::new()));
+        let id = sender.next_id();
+        let channel = sender.send_resume_request(id, state).unwrap();
+        let task = rx.recv().await.unwrap();
+        assert!(matches!(task.request(), DownloadRequest::Resume));
+
+        let limits = Arc::new(ConnectionLimits::new(1, 0, AdmissionPolicy::default()));
+        resolve::handle_task(task, &context(limits)).await;
+        let mut result_recv = channel.result_recv;
+        let result = result_recv.try_recv().unwrap();
+        assert_eq!(result.kind(), StageKind::UnknownError);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::app::task::{StageKind, TaskPhase};
+
+/// UI线程中[`TaskListener`]所处的阶段
+///
+/// ```text
+///            ┌──────────── resume ────────────┐
+///            ▼                                │
+/// Submitted ─► Running ◄─► AwaitingDecision   │
+///     │          │               │            │
+///     └──────────┴───► Finishing │            │
+///                │        │      │            │
+///                ▼        ▼      ▼            │
+///             Pausing ───────► Paused ────────┘
+///                │                │
+///                ▼                │
+///           Cancelling            │
+///                │                │
+///                ▼                ▼
+///           Terminal(stage) ◄─────┘
+/// ```
+///
+/// - 收到任务结果之前，阶段跟随任务的[`TaskPhase`]在Submitted、Running、AwaitingDecision
+///   和Finishing之间变化，见[`ListenerPhase::observe`]；
+/// - 发出停止指令之后进入Pausing，发出取消指令之后进入Cancelling，直到收到任务的结果。
+///   下载循环在响应指令之前可能还会写入缓冲的数据，因此这期间不显示为已经暂停或者取消；
+/// - 收到结果之后，可以恢复的失败进入Paused，其他结果进入Terminal，见[`ListenerPhase::after_result`]；
+/// - 用户做出决定之后任务重新排队，AwaitingDecision回到Submitted；
+/// - Paused的任务恢复时回到Submitted，被取消或者恢复失败时进入Terminal；
+/// - Terminal的任务随后被移动到完成列表中，不再离开该阶段。
+///
+/// 所有的转换都通过[`ListenerPhase::can_transition_to`]检查，不合法的转换会被忽略。
+///
+/// [`TaskListener`]: crate::app::listener::TaskListener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerPhase {
+    /// 已经提交给后台，还在排队或者刚开始运行
+    Submitted,
+    /// 正在连接或者下载
+    Running,
+    /// 已经发出停止指令，等待任务响应
+    Pausing,
+    /// 已经发出取消指令，等待任务响应
+    Cancelling,
+    /// 任务因为停止指令或者可以恢复的错误而暂停，可以恢复
+    Paused,
+    /// 任务在等待用户决定如何处理已经存在的文件，或者确认是否下载匹配规则的文件
+    AwaitingDecision,
+    /// 下载完成，正在同步、校验或者解压
+    Finishing,
+    /// 任务已经结束，等待移动到完成列表中
+    Terminal(StageKind),
+}
+
+impl ListenerPhase {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 收到任务结果之后所处的阶段，可以恢复的结果为[`ListenerPhase::Paused`]，
+    /// 见[`StageKind::is_resumable`]
+    pub fn after_result(kind: StageKind) -> Self {
+        if kind.is_resumable() {
+            ListenerPhase::Paused
+        } else {
+            ListenerPhase::Terminal(kind)
+        }
+    }
+
+    /// 任务运行时的[`TaskPhase`]对应的阶段
+    pub fn observe(phase: TaskPhase) -> Self {
+        match phase {
+            TaskPhase::Queued => ListenerPhase::Submitted,
+            TaskPhase::WaitingForDecision | TaskPhase::AwaitingConfirm => {
+                ListenerPhase::AwaitingDecision
+            }
+            TaskPhase::Syncing
+            | TaskPhase::Verifying
+            | TaskPhase::Extracting
+            | TaskPhase::Publishing
+            | TaskPhase::Done => ListenerPhase::Finishing,
+            TaskPhase::Connecting | TaskPhase::Downloading => ListenerPhase::Running,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 任务仍在后台运行，还没有返回结果
+    pub fn is_active(self) -> bool {
+        !matches!(self, ListenerPhase::Paused | ListenerPhase::Terminal(_))
+    }
+
+    /// 已经发出停止或者取消指令，还没有收到任务的结果
+    pub fn is_stopping(self) -> bool {
+        matches!(self, ListenerPhase::Pausing | ListenerPhase::Cancelling)
+    }
+
+    pub fn is_paused(self) -> bool {
+        self == ListenerPhase::Paused
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, ListenerPhase::Terminal(_))
+    }
+
+    /// 是否可以从当前阶段转换到`next`，转换到相同的阶段视为合法
+    pub fn can_transition_to(self, next: ListenerPhase) -> bool {
+        use ListenerPhase::*;
+
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (Terminal(_), _) => false,
+            (_, Terminal(_)) => true,
+            (Paused, next) => next == Submitted,
+            (Pausing, next) => matches!(next, Paused | Cancelling),
+            (Cancelling, next) => next == Paused,
+            (Finishing, next) => matches!(next, Pausing | Paused | Cancelling),
+            (AwaitingDecision, _) => true,
+            (Submitted | Running, next) => next != Submitted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ListenerPhase::*;
+
+    const ALL: [ListenerPhase; 8] = [
+        Submitted,
+        Running,
+        Pausing,
+        Cancelling,
+        Paused,
+        AwaitingDecision,
+        Finishing,
+        Terminal(StageKind::Finished),
+    ];
+
+    #[test]
+    fn result_decides_paused_or_terminal() {
+        assert_eq!(ListenerPhase::after_result(StageKind::Interrupted), Paused);
+        assert_eq!(ListenerPhase::after_result(StageKind::Stalled), Paused);
+        assert_eq!(
+            ListenerPhase::after_result(StageKind::Abort),
+            Terminal(StageKind::Abort)
+        );
+        assert_eq!(
+            ListenerPhase::after_result(StageKind::Finished),
+            Terminal(StageKind::Finished)
+        );
+    }
+
+    #[test]
+    fn observe_task_phase() {
+        assert_eq!(ListenerPhase::observe(TaskPhase::Queued), Submitted);
+        assert_eq!(ListenerPhase::observe(TaskPhase::Connecting), Running);
+        assert_eq!(ListenerPhase::observe(TaskPhase::Downloading), Running);
+        assert_eq!(
+            ListenerPhase::observe(TaskPhase::WaitingForDecision),
+            AwaitingDecision
+        );
+        assert_eq!(ListenerPhase::observe(TaskPhase::Verifying), Finishing);
+        assert_eq!(ListenerPhase::observe(TaskPhase::Done), Finishing);
+    }
+
+    #[test]
+    fn terminal_is_final() {
+        let terminal = Terminal(StageKind::Abort);
+        for next in ALL {
+            assert!(!terminal.can_transition_to(next), "Terminal -> {:?}", next);
+        }
+        assert!(terminal.can_transition_to(terminal));
+        // 任何其他阶段都可以结束
+        for phase in ALL.into_iter().filter(|phase| !phase.is_terminal()) {
+            assert!(phase.can_transition_to(terminal), "{:?} -> Terminal", phase);
+        }
+    }
+
+    #[test]
+    fn stopping_waits_for_the_result() {
+        assert!(Running.can_transition_to(Pausing));
+        assert!(Pausing.can_transition_to(Cancelling));
+        assert!(Pausing.can_transition_to(Paused));
+        assert!(!Pausing.can_transition_to(Running));
+        assert!(!Cancelling.can_transition_to(Running));
+        assert!(!Cancelling.can_transition_to(Pausing));
+        assert!(Pausing.is_stopping() && Cancelling.is_stopping());
+        assert!(Pausing.is_active() && !Paused.is_active());
+    }
+
+    #[test]
+    fn only_submitted_leaves_paused() {
+        for next in ALL.into_iter().filter(|phase| !phase.is_terminal()) {
+            assert_eq!(
+                Paused.can_transition_to(next),
+                matches!(next, Submitted | Paused),
+                "Paused -> {:?}",
+                next
+            );
+        }
+        // 做出决定之后重新排队，运行中的任务不会回到排队
+        assert!(AwaitingDecision.can_transition_to(Submitted));
+        assert!(!Running.can_transition_to(Submitted));
+        assert!(Submitted.can_transition_to(Running));
+        assert!(!Finishing.can_transition_to(Running));
+    }
+}
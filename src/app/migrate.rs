@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::app::task::TaskId;
+
+/// 下载目录修改之后，将暂停的任务已经下载的部分移动到新的目录
+///
+/// 移动在后台线程中按顺序进行，每移动完一个文件就通过通道返回一个[`MoveResult`]，
+/// 由UI线程更新对应任务的路径。移动期间任务不能被恢复，见[`TaskListener::is_migrating`]。
+///
+/// [`TaskListener::is_migrating`]: crate::app::listener::TaskListener::is_migrating
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialMove {
+    pub id: TaskId,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl PartialMove {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 保持文件相对于下载目录的路径不变，`from`不在`old_dir`中时返回[`None`]
+    pub fn new(id: TaskId, from: &Path, old_dir: &Path, new_dir: &Path) -> Option<Self> {
+        let relative = from.strip_prefix(old_dir).ok()?;
+        if relative.as_os_str().is_empty() {
+            return None;
+        }
+        Some(PartialMove {
+            id,
+            from: from.to_path_buf(),
+            to: new_dir.join(relative),
+        })
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 移动文件，目标已经存在时失败而不是覆盖
+    pub fn perform(&self) -> io::Result<()> {
+        if self.to.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", self.to.display()),
+            ));
+        }
+        if let Some(parent) = self.to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match fs::rename(&self.from, &self.to) {
+            Ok(()) => Ok(()),
+            // 不在同一个文件系统中时无法重命名，改为复制之后删除
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                if let Err(e) = fs::copy(&self.from, &self.to) {
+                    let _ = fs::remove_file(&self.to);
+                    return Err(e);
+                }
+                fs::remove_file(&self.from)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// 一个文件的移动结果，`index`从1开始，用于显示进度
+#[derive(Debug)]
+pub struct MoveResult {
+    pub job: PartialMove,
+    pub index: usize,
+    pub total: usize,
+    pub result: io::Result<()>,
+}
+
+/// 在后台线程中依次移动`jobs`中的文件
+pub fn spawn_moves(jobs: Vec<PartialMove>) -> mpsc::Receiver<MoveResult> {
+    let (tx, rx) = mpsc::channel();
+    let total = jobs.len();
+    thread::spawn(move || {
+        for (i, job) in jobs.into_iter().enumerate() {
+            let result = job.perform();
+            if let Err(e) = &result {
+                log::warn!(target:"App", "Failed to move {}: {}", job.from.display(), e);
+            }
+            let result = MoveResult {
+                job,
+                index: i + 1,
+                total,
+                result,
+            };
+            if tx.send(result).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
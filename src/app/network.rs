@@ -0,0 +1,204 @@
+use std::fmt::{self, Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::Config;
+
+/// 连接服务器时使用的IP版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpVersion {
+    /// 同时尝试IPv4和IPv6，由reqwest选择先连接成功的地址
+    #[default]
+    Auto,
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    /// `addr`是否属于该IP版本
+    fn accepts(self, addr: IpAddr) -> bool {
+        match self {
+            IpVersion::Auto => true,
+            IpVersion::V4 => addr.is_ipv4(),
+            IpVersion::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl Display for IpVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IpVersion::Auto => write!(f, "IPv4/IPv6"),
+            IpVersion::V4 => write!(f, "IPv4"),
+            IpVersion::V6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NetworkConfigError {
+    /// `bind_address`不是有效的IP地址
+    InvalidBindAddress(String),
+    /// `bind_address`与`ip_version`的IP版本不同
+    VersionMismatch { address: IpAddr, version: IpVersion },
+    /// 当前平台不支持`bind_interface`
+    InterfaceUnsupported(String),
+}
+
+impl Display for NetworkConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkConfigError::InvalidBindAddress(address) => {
+                write!(f, "Invalid bind_address: {}", address)
+            }
+            NetworkConfigError::VersionMismatch { address, version } => {
+                write!(f, "bind_address {} is not an {} address", address, version)
+            }
+            NetworkConfigError::InterfaceUnsupported(interface) => write!(
+                f,
+                "Binding to interface {} is not supported on this platform",
+                interface
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkConfigError {}
+
+/// 所有任务创建HTTP客户端时使用的网络设置，由配置中的`ip_version`、`bind_interface`
+/// 和`bind_address`生成
+///
+/// 只使用一种IP版本时，客户端绑定到对应的未指定地址（`0.0.0.0`或`::`），
+/// 这样解析出的另一种版本的地址都无法连接，相当于只使用该版本。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkOptions {
+    ip_version: IpVersion,
+    bind_address: Option<IpAddr>,
+    bind_interface: Option<String>,
+}
+
+impl NetworkOptions {
+    // ------------------- CONSTANT -----------------------
+
+    /// reqwest只在部分平台上支持绑定网络接口
+    const INTERFACE_SUPPORTED: bool = cfg!(any(
+        target_os = "android",
+        target_os = "fuchsia",
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+    ));
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 检查配置中的网络设置，地址无效或者与IP版本冲突时返回错误
+    pub fn from_config(config: &Config) -> Result<Self, NetworkConfigError> {
+        let bind_address = match config.bind_address.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(address) => Some(
+                address
+                    .parse::<IpAddr>()
+                    .map_err(|_| NetworkConfigError::InvalidBindAddress(address.to_string()))?,
+            ),
+        };
+        if let Some(address) = bind_address
+            && !config.ip_version.accepts(address)
+        {
+            return Err(NetworkConfigError::VersionMismatch {
+                address,
+                version: config.ip_version,
+            });
+        }
+        let bind_interface = config
+            .bind_interface
+            .as_deref()
+            .map(str::trim)
+            .filter(|interface| !interface.is_empty())
+            .map(str::to_string);
+        if let Some(interface) = &bind_interface
+            && !Self::INTERFACE_SUPPORTED
+        {
+            return Err(NetworkConfigError::InterfaceUnsupported(interface.clone()));
+        }
+        Ok(NetworkOptions {
+            ip_version: config.ip_version,
+            bind_address,
+            bind_interface,
+        })
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn ip_version(&self) -> IpVersion {
+        self.ip_version
+    }
+
+    pub fn bind_address(&self) -> Option<IpAddr> {
+        self.bind_address
+    }
+
+    pub fn bind_interface(&self) -> Option<&str> {
+        self.bind_interface.as_deref()
+    }
+
+    /// 客户端绑定的本地地址，指定了`bind_address`时使用该地址，
+    /// 否则只使用一种IP版本时使用对应的未指定地址
+    pub fn local_address(&self) -> Option<IpAddr> {
+        self.bind_address.or(match self.ip_version {
+            IpVersion::Auto => None,
+            IpVersion::V4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpVersion::V6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        })
+    }
+
+    /// 连接时实际会尝试的IP版本，用于错误信息
+    pub fn attempted_version(&self) -> IpVersion {
+        match self.local_address() {
+            Some(IpAddr::V4(_)) => IpVersion::V4,
+            Some(IpAddr::V6(_)) => IpVersion::V6,
+            None => IpVersion::Auto,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        let builder = builder.local_address(self.local_address());
+        #[cfg(any(
+            target_os = "android",
+            target_os = "fuchsia",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "ios",
+        ))]
+        let builder = match &self.bind_interface {
+            Some(interface) => builder.interface(interface),
+            None => builder,
+        };
+        builder
+    }
+}
+
+/// 在详情弹窗中显示的摘要，例如`IPv4, interface eth0`
+impl Display for NetworkOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.ip_version {
+            IpVersion::Auto => write!(f, "auto ({})", self.attempted_version())?,
+            version => write!(f, "{}", version)?,
+        }
+        if let Some(interface) = &self.bind_interface {
+            write!(f, ", interface {}", interface)?;
+        }
+        if let Some(address) = &self.bind_address {
+            write!(f, ", bound to {}", address)?;
+        }
+        Ok(())
+    }
+}
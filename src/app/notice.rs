@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+/// 程序主动发出的简短提示，例如自动恢复了某个任务，显示在内容区的右下角。
+///
+/// 只保留最新的一条，在[`NoticeBoard::DURATION`]之后不再显示。
+#[derive(Debug, Default)]
+pub struct NoticeBoard {
+    notice: Option<(String, Instant)>,
+}
+
+impl NoticeBoard {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DURATION: Duration = Duration::from_secs(4);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 仍然需要显示的提示
+    pub fn current(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < Self::DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn push(&mut self, message: String) {
+        log::info!(target:"App", "{}", message);
+        self.notice = Some((message, Instant::now()));
+    }
+}
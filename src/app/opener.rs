@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+/// 根据扩展名判断的文件类别，用于选择打开文件的程序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileCategory {
+    Video,
+    Audio,
+    Image,
+    Archive,
+    Document,
+    /// 其他类别的文件，该类别的程序也会作为所有类别的备选
+    Other,
+}
+
+impl FileCategory {
+    pub fn from_path(path: &Path) -> Self {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+            return FileCategory::Other;
+        };
+        match extension.to_ascii_lowercase().as_str() {
+            "mp4" | "mkv" | "webm" | "avi" | "mov" | "flv" | "wmv" | "m4v" => FileCategory::Video,
+            "mp3" | "flac" | "ogg" | "opus" | "wav" | "m4a" | "aac" => FileCategory::Audio,
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "svg" => FileCategory::Image,
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" => {
+                FileCategory::Archive
+            }
+            "pdf" | "txt" | "md" | "doc" | "docx" | "odt" | "epub" => FileCategory::Document,
+            _ => FileCategory::Other,
+        }
+    }
+}
+
+/// 配置中`open_with`的默认值
+pub fn default_open_with() -> BTreeMap<FileCategory, Vec<String>> {
+    let fallback = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    BTreeMap::from([
+        (
+            FileCategory::Video,
+            vec![String::from("mpv"), String::from("vlc")],
+        ),
+        (FileCategory::Audio, vec![String::from("mpv")]),
+        (FileCategory::Archive, vec![String::from("file-roller")]),
+        (FileCategory::Other, vec![String::from(fallback)]),
+    ])
+}
+
+/// 可以用来打开`category`类别文件的程序，该类别的程序在前，[`FileCategory::Other`]的在后
+pub fn commands_for(
+    table: &BTreeMap<FileCategory, Vec<String>>,
+    category: FileCategory,
+) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    for command in [category, FileCategory::Other]
+        .iter()
+        .filter_map(|category| table.get(category))
+        .flatten()
+    {
+        if !commands.contains(command) {
+            commands.push(command.clone());
+        }
+    }
+    commands
+}
+
+/// 使用`program`打开`path`，不等待程序结束
+///
+/// 程序的输入输出都被关闭，避免破坏终端界面。程序不存在时返回[`io::ErrorKind::NotFound`]。
+pub fn spawn_detached(program: &str, path: &Path) -> io::Result<()> {
+    let mut command = Command::new(program);
+    command.arg(path);
+    spawn_quiet(command)
+}
+
+/// 运行`argv`指定的命令，第一项为程序，其余为参数，不经过shell，不等待程序结束
+///
+/// `argv`为空时返回[`io::ErrorKind::InvalidInput`]。
+pub fn spawn_argv(argv: &[String]) -> io::Result<()> {
+    let Some((program, args)) = argv.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty command"));
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    spawn_quiet(command)
+}
+
+fn spawn_quiet(mut command: Command) -> io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // 回收子进程，避免留下僵尸进程
+    thread::spawn(move || child.wait());
+    Ok(())
+}
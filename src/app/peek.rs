@@ -0,0 +1,180 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc as std_mpsc;
+
+use tokio::runtime::Handle;
+
+/// 根据文件开头的字节猜测的文件类型，用于快速判断下载到的是不是错误页面
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Zip,
+    Gzip,
+    Pdf,
+    Png,
+    Elf,
+    Html,
+}
+
+impl FileKind {
+    /// 检查开头的魔数，HTML忽略开头的空白以及BOM，不区分大小写
+    pub fn detect(bytes: &[u8]) -> Option<FileKind> {
+        const MAGIC: [(&[u8], FileKind); 5] = [
+            (b"PK\x03\x04", FileKind::Zip),
+            (b"\x1f\x8b", FileKind::Gzip),
+            (b"%PDF", FileKind::Pdf),
+            (b"\x89PNG", FileKind::Png),
+            (b"\x7fELF", FileKind::Elf),
+        ];
+        if let Some((_, kind)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+            return Some(*kind);
+        }
+        let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+        let text = &text[start..];
+        [&b"<!doctype html"[..], b"<html"]
+            .iter()
+            .any(|tag| text.len() >= tag.len() && text[..tag.len()].eq_ignore_ascii_case(tag))
+            .then_some(FileKind::Html)
+    }
+}
+
+impl Display for FileKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FileKind::Zip => "ZIP archive",
+            FileKind::Gzip => "gzip archive",
+            FileKind::Pdf => "PDF document",
+            FileKind::Png => "PNG image",
+            FileKind::Elf => "ELF executable",
+            FileKind::Html => "HTML page",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 十六进制显示，每行`per_line`个字节，例如
+///
+/// ```text
+/// 00000000  50 4b 03 04 14 00 00 00  08 00 21 00 b7 ac ce 34 |PK........!....4|
+/// ```
+///
+/// 最后一行不足时使用空格补齐，右侧的可打印字符保持对齐。
+pub fn hex_dump(bytes: &[u8], per_line: usize) -> Vec<String> {
+    let per_line = per_line.max(1);
+    bytes
+        .chunks(per_line)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut line = format!("{:08x} ", row * per_line);
+            for i in 0..per_line {
+                // 每8个字节之间多空一格
+                if i % 8 == 0 {
+                    line.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(byte) => line.push_str(&format!("{:02x} ", byte)),
+                    None => line.push_str("   "),
+                }
+            }
+            line.push('|');
+            line.extend(chunk.iter().map(|&byte| match byte {
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            }));
+            line.push('|');
+            line
+        })
+        .collect()
+}
+
+/// 按UTF-8解码（无效的部分替换为�），保留换行和制表符，其余控制字符转义为`\xNN`
+pub fn printable_text(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c {
+            '\n' | '\t' => text.push(c),
+            '\r' => text.push_str("\\r"),
+            c if c.is_control() => text.push_str(&format!("\\x{:02x}", c as u32)),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// 以只读方式读取文件开头最多`limit`个字节
+///
+/// 文件可能正在被下载任务写入，比`limit`短时返回已有的部分，不视为错误。
+pub fn read_head(path: &Path, limit: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(limit);
+    File::open(path)?
+        .take(limit as u64)
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// 读取失败时显示的提示
+pub fn error_notice(path: &Path, error: &io::Error) -> String {
+    let name = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    match error.kind() {
+        io::ErrorKind::NotFound => format!("Cannot peek {}: file does not exist", name),
+        _ => format!("Cannot peek {}: {}", name, error),
+    }
+}
+
+/// 在后台读取文件开头的一部分，用于预览弹窗，同一时间只有一个读取
+#[derive(Debug, Default)]
+pub struct FilePeek {
+    runtime: Option<Handle>,
+    running: Option<(PathBuf, std_mpsc::Receiver<io::Result<Vec<u8>>>)>,
+}
+
+impl FilePeek {
+    // ------------------- CONSTANT -----------------------
+
+    /// 读取的字节数，足够判断类型以及看到HTML错误页面的标题
+    pub const PEEK_BYTES: usize = 4096;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        FilePeek::default()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// 开始读取`path`，替换还没有完成的读取。后台运行时还没有启动时返回`false`
+    pub fn start(&mut self, path: PathBuf) -> bool {
+        let Some(runtime) = &self.runtime else {
+            return false;
+        };
+        let (sender, receiver) = std_mpsc::channel();
+        let target = path.clone();
+        runtime.spawn_blocking(move || {
+            let _ = sender.send(read_head(&target, Self::PEEK_BYTES));
+        });
+        self.running = Some((path, receiver));
+        true
+    }
+
+    /// 取出已经完成的读取，返回文件路径以及读取的结果
+    pub fn poll(&mut self) -> Option<(PathBuf, io::Result<Vec<u8>>)> {
+        let (_, receiver) = self.running.as_ref()?;
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(std_mpsc::TryRecvError::Empty) => return None,
+            Err(std_mpsc::TryRecvError::Disconnected) => Err(io::Error::other(
+                "background runtime stopped before reading the file",
+            )),
+        };
+        let (path, _) = self.running.take()?;
+        Some((path, result))
+    }
+}
@@ -0,0 +1,136 @@
+use ratatui::prelude::*;
+use ratatui::style::palette::tailwind;
+use ratatui::widgets::{Paragraph, StatefulWidget, Widget};
+use serde::{Deserialize, Serialize};
+
+use crate::app::checksum::Checksum;
+use crate::app::listener::{TaskListener, TaskListenerRanderState};
+use crate::app::permissions::FileMode;
+use crate::app::sender::{RequestOptions, SaveAs};
+use crate::app::task::{ByteRange, TaskId};
+use crate::window::common::{self, Fill};
+
+/// 还没有成功发送给[`TaskManager`]的任务
+///
+/// 当通道已满或者后台线程暂时不可用时，用户提交的任务不会被丢弃，而是保存在
+/// [`DownloadList`]的等待队列中，每次`handle_async`时按顺序重新尝试发送。
+/// 批量导入的URL也会先进入该队列，再逐渐发送到通道中，避免阻塞UI线程。
+///
+/// 等待队列会保存在会话文件中，下次启动时继续发送。
+///
+/// [`TaskManager`]: crate::app::task::TaskManager
+/// [`DownloadList`]: crate::window::app::DownloadList
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTask {
+    // 编号保存在会话文件中，恢复之后继续使用，旧的会话文件中没有编号时重新生成
+    #[serde(default, skip_serializing_if = "TaskId::is_unassigned")]
+    pub id: TaskId,
+    pub url: String,
+    pub save_as: Option<SaveAs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Checksum>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extract: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_mode: Option<FileMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<ByteRange>,
+}
+
+impl PendingTask {
+    // ------------------- CONSTANT -----------------------
+
+    const FOCUSED_HIGHTLIGHT_COLOR: Color = Color::LightBlue;
+    const UNFOCUSED_HIGHTLIGHT_COLOR: Color = tailwind::GRAY.c500;
+
+    pub const RENDER_HEIGHT: u16 = TaskListener::RENDER_HEIGHT;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn from_options(id: TaskId, options: RequestOptions) -> Self {
+        PendingTask {
+            id,
+            url: options.url,
+            save_as: options.save_as,
+            checksum: options.checksum,
+            headers: options.headers,
+            extract: options.extract,
+            file_mode: options.file_mode,
+            range: options.range,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn to_options(&self) -> RequestOptions {
+        RequestOptions::new(self.url.clone(), self.save_as.clone())
+            .with_checksum(self.checksum.clone())
+            .with_headers(self.headers.clone())
+            .with_extract(self.extract)
+            .with_file_mode(self.file_mode)
+            .with_range(self.range)
+    }
+}
+
+/// 等待发送的任务与正在进行的任务显示在同一个列表中，占据相同的高度：
+///
+/// <url>
+///   -> <save as>
+/// Waiting to submit...
+impl StatefulWidget for &PendingTask {
+    type State = TaskListenerRanderState;
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let highlight_color = if state.page_focused {
+            PendingTask::FOCUSED_HIGHTLIGHT_COLOR
+        } else {
+            PendingTask::UNFOCUSED_HIGHTLIGHT_COLOR
+        };
+
+        let text_style = if state.selected {
+            Style::new().bg(highlight_color).fg(Color::Black)
+        } else {
+            Style::new().fg(Color::Gray)
+        };
+
+        let [area, _] = Layout::vertical([
+            Constraint::Length(PendingTask::RENDER_HEIGHT),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+
+        if state.selected {
+            Fill::new(Style::new().bg(highlight_color)).render(area, buf);
+        }
+
+        let [text, middle, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(1),
+        ])
+        .areas(area);
+
+        Paragraph::new(common::numbered_line(
+            Some(self.id),
+            common::display_sanitize(self.url.as_str()),
+        ))
+        .style(text_style)
+        .left_aligned()
+        .render(text, buf);
+
+        let save_as = match &self.save_as {
+            Some(save_as) => format!("  -> {}", common::display_sanitize(&save_as.to_string())),
+            None => String::from("  -> (auto)"),
+        };
+        Paragraph::new(save_as)
+            .style(text_style)
+            .left_aligned()
+            .render(middle, buf);
+
+        Paragraph::new("Waiting to submit...")
+            .style(text_style)
+            .left_aligned()
+            .render(footer, buf);
+    }
+}
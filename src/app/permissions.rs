@@ -0,0 +1,183 @@
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::config::Config;
+
+/// Unix中的权限位，使用八进制表示，例如`0644`
+///
+/// 在其他平台中没有意义，设置时只记录一条调试日志。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FileMode(u32);
+
+impl FileMode {
+    // ------------------- CONSTANT -----------------------
+
+    /// 包括setuid、setgid以及sticky位
+    pub const MAX: u32 = 0o7777;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(bits: u32) -> Option<Self> {
+        (bits <= Self::MAX).then_some(FileMode(bits))
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl FromStr for FileMode {
+    type Err = InvalidFileMode;
+
+    /// 接受`644`、`0644`或者`0o644`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let digits = s.strip_prefix("0o").unwrap_or(s);
+        if digits.is_empty() || digits.len() > 5 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidFileMode(s.to_string()));
+        }
+        u32::from_str_radix(digits, 8)
+            .ok()
+            .and_then(FileMode::new)
+            .ok_or_else(|| InvalidFileMode(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for FileMode {
+    type Error = InvalidFileMode;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<FileMode> for String {
+    fn from(mode: FileMode) -> Self {
+        mode.to_string()
+    }
+}
+
+impl Display for FileMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04o}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFileMode(String);
+
+impl Display for InvalidFileMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid mode {:?}, expected octal digits such as 0644",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidFileMode {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModeConfigError {
+    /// `file_mode`不是有效的权限
+    InvalidFileMode(InvalidFileMode),
+    /// `dir_mode`不是有效的权限
+    InvalidDirMode(InvalidFileMode),
+}
+
+impl Display for ModeConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ModeConfigError::InvalidFileMode(e) => write!(f, "Invalid file_mode: {}", e),
+            ModeConfigError::InvalidDirMode(e) => write!(f, "Invalid dir_mode: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ModeConfigError {}
+
+/// 下载完成的文件以及程序创建的目录使用的权限，由配置中的`file_mode`和`dir_mode`生成
+///
+/// 为[`None`]时保持系统默认的权限（受umask影响）。指定了权限时直接设置为该值，
+/// 不受umask影响，例如在共享目录中使用`file_mode = "0664"`让同组的用户也可以修改。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileModes {
+    pub file: Option<FileMode>,
+    pub dir: Option<FileMode>,
+}
+
+impl FileModes {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 检查配置中的权限，格式无效时返回错误
+    pub fn from_config(config: &Config) -> Result<Self, ModeConfigError> {
+        Ok(FileModes {
+            file: parse_optional(config.file_mode.as_deref())
+                .map_err(ModeConfigError::InvalidFileMode)?,
+            dir: parse_optional(config.dir_mode.as_deref())
+                .map_err(ModeConfigError::InvalidDirMode)?,
+        })
+    }
+}
+
+/// 空字符串视为没有设置
+fn parse_optional(value: Option<&str>) -> Result<Option<FileMode>, InvalidFileMode> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some),
+    }
+}
+
+/// 与[`fs::create_dir_all`]相同，指定了`mode`时将其中新创建的目录设置为该权限，
+/// 已经存在的目录保持不变
+///
+/// 会进行阻塞的IO操作。
+pub fn create_dir_all(path: &Path, mode: Option<FileMode>) -> io::Result<()> {
+    let Some(mode) = mode else {
+        return fs::create_dir_all(path);
+    };
+    let created: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+    fs::create_dir_all(path)?;
+    // 先设置上层的目录，避免权限不包含执行位时无法访问下层的目录
+    for dir in created.into_iter().rev() {
+        set_dir_mode(dir, mode)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_dir_mode(dir: &Path, mode: FileMode) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode.bits()))
+}
+
+#[cfg(not(unix))]
+fn set_dir_mode(dir: &Path, mode: FileMode) -> io::Result<()> {
+    log::debug!(target:"Task", "Ignoring dir_mode {} for {}: not supported on this platform", mode, dir.display());
+    Ok(())
+}
+
+/// 设置下载完成的文件的权限
+#[cfg(unix)]
+pub async fn set_file_mode(path: &Path, mode: FileMode) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    tokio::fs::set_permissions(path, fs::Permissions::from_mode(mode.bits())).await
+}
+
+/// 设置下载完成的文件的权限
+#[cfg(not(unix))]
+pub async fn set_file_mode(path: &Path, mode: FileMode) -> io::Result<()> {
+    log::debug!(target:"Task", "Ignoring file_mode {} for {}: not supported on this platform", mode, path.display());
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::opener::FileCategory;
+
+/// 下载的文件匹配[`FileTypePolicy`]中的规则时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyAction {
+    /// 暂停任务，由用户确认是否继续下载
+    #[default]
+    Warn,
+    /// 直接使任务失败
+    Block,
+}
+
+impl Display for PolicyAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyAction::Warn => write!(f, "warn"),
+            PolicyAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+/// 根据文件名或者Content-Type限制下载的文件类型，例如在共用的机器上禁止下载可执行文件
+///
+/// 配置示例：
+///
+/// ```toml
+/// [file_type_policy]
+/// action = "block"
+/// extensions = ["exe", "msi", "sh"]
+/// mime_types = ["application/x-msdownload", "application/vnd.microsoft.*"]
+/// ```
+///
+/// 两个列表默认都为空，此时不做任何检查。
+///
+/// 另外，文件名看起来是二进制文件而服务器返回的是HTML页面时（例如登录页面被保存为`ubuntu.iso`）
+/// 总是提示，见[`looks_like_html_page`]，可以使用`html_check = false`关闭。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileTypePolicy {
+    pub action: PolicyAction,
+    /// 不区分大小写的扩展名，可以带有开头的`.`，也可以包含多段，例如`tar.gz`
+    pub extensions: Vec<String>,
+    /// 不区分大小写的MIME类型，以`*`结尾时匹配该前缀，例如`application/*`
+    pub mime_types: Vec<String>,
+    /// 检查服务器是否返回了HTML页面而不是文件
+    pub html_check: bool,
+}
+
+impl Default for FileTypePolicy {
+    fn default() -> Self {
+        FileTypePolicy {
+            action: PolicyAction::default(),
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            html_check: true,
+        }
+    }
+}
+
+/// 匹配到的规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyRule {
+    Extension(String),
+    MimeType(String),
+    /// 服务器返回了HTML页面，而文件名看起来是二进制文件，见[`looks_like_html_page`]
+    HtmlPage,
+}
+
+impl Display for PolicyRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyRule::Extension(extension) => write!(f, "extension .{}", extension),
+            PolicyRule::MimeType(mime) => write!(f, "MIME type {}", mime),
+            PolicyRule::HtmlPage => write!(f, "HTML page instead of a file"),
+        }
+    }
+}
+
+/// [`FileTypePolicy::evaluate`]的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyMatch {
+    pub action: PolicyAction,
+    pub rule: PolicyRule,
+}
+
+impl Display for PolicyMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.rule, self.action)
+    }
+}
+
+impl FileTypePolicy {
+    // -------------------- FUNCTION -----------------------
+
+    /// 检查保存的文件名以及响应的Content-Type，扩展名优先于MIME类型，
+    /// 同一列表中先出现的规则优先
+    ///
+    /// 没有匹配的规则时检查是否为HTML页面，该检查总是只提示，不受`action`影响。
+    pub fn evaluate(
+        &self,
+        filename: &str,
+        content_type: Option<&str>,
+        content_length: Option<u64>,
+    ) -> Option<PolicyMatch> {
+        self.match_rules(filename, content_type).or_else(|| {
+            (self.html_check && looks_like_html_page(filename, content_type, content_length))
+                .then_some(PolicyMatch {
+                    action: PolicyAction::Warn,
+                    rule: PolicyRule::HtmlPage,
+                })
+        })
+    }
+
+    fn match_rules(&self, filename: &str, content_type: Option<&str>) -> Option<PolicyMatch> {
+        let filename = filename.to_ascii_lowercase();
+        let extension = self
+            .extensions
+            .iter()
+            .map(|extension| {
+                extension
+                    .trim()
+                    .trim_start_matches('.')
+                    .to_ascii_lowercase()
+            })
+            .filter(|extension| !extension.is_empty())
+            .find(|extension| {
+                filename
+                    .strip_suffix(extension.as_str())
+                    .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+            })
+            .map(PolicyRule::Extension);
+
+        // Content-Type可能带有参数，例如`text/plain; charset=utf-8`
+        let mime = content_type
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .filter(|value| !value.is_empty());
+        let rule = extension.or_else(|| {
+            let mime = mime?;
+            self.mime_types
+                .iter()
+                .map(|rule| rule.trim().to_ascii_lowercase())
+                .find(|rule| match rule.strip_suffix('*') {
+                    Some(prefix) => !prefix.is_empty() && mime.starts_with(prefix),
+                    None => *rule == mime,
+                })
+                .map(PolicyRule::MimeType)
+        })?;
+        Some(PolicyMatch {
+            action: self.action,
+            rule,
+        })
+    }
+}
+
+/// 不在[`FileCategory`]中，但同样不应该是文本的扩展名
+const BINARY_EXTENSIONS: [&str; 14] = [
+    "iso", "img", "dmg", "exe", "msi", "deb", "rpm", "apk", "appimage", "bin", "jar", "whl", "pkg",
+    "so",
+];
+
+/// 超过该大小的HTML页面不太可能是登录或者错误页面，见[`looks_like_html_page`]
+pub const HTML_PAGE_MAX_SIZE: u64 = 1024 * 1024;
+
+/// `Content-Type`是否为HTML页面，忽略`charset`等参数，不区分大小写
+pub fn is_html_mime(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    mime.eq_ignore_ascii_case("text/html") || mime.eq_ignore_ascii_case("application/xhtml+xml")
+}
+
+/// 文件名的扩展名表明是二进制文件，而响应的Content-Type是HTML，并且长度较小或者未知
+///
+/// 典型的情况是需要登录的下载链接被重定向到登录页面，最终把HTML页面保存为`ubuntu.iso`。
+/// 扩展名使用[`FileCategory`]分类，文本类的文档（`txt`、`md`）以及`svg`不算二进制文件。
+pub fn looks_like_html_page(
+    filename: &str,
+    content_type: Option<&str>,
+    content_length: Option<u64>,
+) -> bool {
+    let is_html = content_type.is_some_and(is_html_mime);
+    if !is_html || content_length.is_some_and(|len| len > HTML_PAGE_MAX_SIZE) {
+        return false;
+    }
+    let path = Path::new(filename);
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match FileCategory::from_path(path) {
+        FileCategory::Video | FileCategory::Audio | FileCategory::Archive => true,
+        FileCategory::Image => extension != "svg",
+        FileCategory::Document => !matches!(extension.as_str(), "txt" | "md"),
+        FileCategory::Other => BINARY_EXTENSIONS.contains(&extension.as_str()),
+    }
+}
@@ -0,0 +1,125 @@
+use std::{path::Path, path::PathBuf, time::Duration, time::SystemTime};
+
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::app::disk;
+use crate::app::listener::PauseOrigin;
+use crate::app::task::StageKind;
+
+/// 单个任务的记录，是导出、历史记录以及退出摘要共用的数据模型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRecord {
+    pub url: Option<String>,
+    /// 不包含附加数据，保持与之前版本相同的格式，例如`"finished"`
+    pub stage: StageKind,
+    pub filepath: PathBuf,
+    /// 实际下载的字节数
+    pub bytes: u64,
+    pub content_length: Option<u64>,
+    /// 从提交任务到结束经过的时间，单位为秒
+    pub duration_secs: f64,
+    /// 文件的校验和，只有计算过时才存在
+    pub checksum: Option<String>,
+    /// 下载期间的最高速度（B/s），没有有效的采样时不存在
+    pub peak_speed: Option<u64>,
+    /// 最近一次请求的TTFB（毫秒），没有收到过数据时不存在
+    pub ttfb_ms: Option<u64>,
+    /// 加入完成列表的时间，RFC 3339格式，退出时仍在进行的任务没有该时间
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    /// 被停止的任务的停止原因，例如批量停止，只有退出时处于暂停状态的任务才有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_origin: Option<PauseOrigin>,
+}
+
+impl TaskRecord {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(
+        url: Option<String>,
+        stage: StageKind,
+        filepath: PathBuf,
+        bytes: u64,
+        content_length: Option<u64>,
+        duration: Duration,
+    ) -> Self {
+        TaskRecord {
+            url,
+            stage,
+            filepath,
+            bytes,
+            content_length,
+            duration_secs: duration.as_secs_f64(),
+            checksum: None,
+            peak_speed: None,
+            ttfb_ms: None,
+            finished_at: None,
+            pause_origin: None,
+        }
+    }
+
+    pub fn with_peak_speed(mut self, peak_speed: Option<u64>) -> Self {
+        self.peak_speed = peak_speed;
+        self
+    }
+
+    pub fn with_ttfb(mut self, ttfb: Option<Duration>) -> Self {
+        self.ttfb_ms = ttfb.map(|ttfb| ttfb.as_millis() as u64);
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: Option<String>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn with_pause_origin(mut self, pause_origin: Option<PauseOrigin>) -> Self {
+        self.pause_origin = pause_origin;
+        self
+    }
+
+    pub fn with_finished_at(mut self, finished_at: SystemTime) -> Self {
+        self.finished_at =
+            Some(DateTime::<Local>::from(finished_at).to_rfc3339_opts(SecondsFormat::Secs, false));
+        self
+    }
+}
+
+/// 退出时写入`--summary-json`指定文件的内容
+///
+/// 包含本次运行中处理过的所有任务：已经完成的任务，以及退出时仍在进行、
+/// 被标记为[`StageKind::Interrupted`]的任务。还没有发送的任务不包含在内，
+/// 它们会保存在会话文件中。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    #[serde(default)]
+    pub session: SessionTotals,
+    pub tasks: Vec<TaskRecord>,
+}
+
+/// 本次运行的时间统计，见[`SessionClock`]
+///
+/// [`SessionClock`]: crate::app::stats::SessionClock
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionTotals {
+    /// 程序启动的时间，RFC 3339格式
+    pub started_at: Option<String>,
+    /// 从启动到退出经过的时间，单位为秒
+    pub duration_secs: f64,
+    /// 至少有一个任务在传输数据的时间，单位为秒
+    pub active_secs: f64,
+    /// 本次运行中接收的字节数
+    pub bytes: u64,
+}
+
+impl SessionSummary {
+    // -------------------- FUNCTION -----------------------
+
+    /// 先写入临时文件再替换，读取摘要的脚本不会看到写了一半的文件
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        disk::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
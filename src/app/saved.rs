@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::app::pending::PendingTask;
+use crate::app::sender::RequestOptions;
+use crate::app::task::TaskId;
+
+/// 保存下来的下载，用于经常重复的下载，例如每天更新的nightly构建
+///
+/// 在下载页面或者完成页面中按`b`保存选中任务的URL和选项，在下载页面中按`T`打开列表，
+/// 选中之后按Enter即可添加任务。URL中的`{date}`在添加任务时替换为当天的日期，
+/// 见[`expand_url`]。
+///
+/// 保存在会话文件中，除了名称之外与等待队列中的任务使用相同的格式。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedDownload {
+    pub name: String,
+    #[serde(flatten)]
+    pub task: PendingTask,
+}
+
+impl SavedDownload {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(name: String, options: RequestOptions) -> Self {
+        SavedDownload {
+            name,
+            task: PendingTask::from_options(TaskId::default(), options),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 保存时的选项，URL中的占位符保持原样，用于修改
+    pub fn options(&self) -> RequestOptions {
+        self.task.to_options()
+    }
+
+    /// 在`date`这一天添加任务时使用的选项
+    pub fn options_on(&self, date: NaiveDate) -> RequestOptions {
+        let mut options = self.options();
+        options.url = expand_url(&options.url, date);
+        options
+    }
+}
+
+/// 将URL中的`{date}`替换为`%Y-%m-%d`格式的日期，`{date:<格式>}`使用指定的
+/// `strftime`格式，例如`{date:%Y%m%d}`
+///
+/// 无效的格式以及其他花括号保持原样，URL中本来就可能出现花括号。
+pub fn expand_url(url: &str, date: NaiveDate) -> String {
+    let mut result = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(start) = rest.find("{date") {
+        result.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let expanded = placeholder.find('}').and_then(|end| {
+            let format = match &placeholder[5..end] {
+                "" => "%Y-%m-%d",
+                spec => spec.strip_prefix(':').filter(|f| !f.is_empty())?,
+            };
+            let mut text = String::new();
+            write!(text, "{}", date.format(format)).ok()?;
+            Some((text, end + 1))
+        });
+        match expanded {
+            Some((text, len)) => {
+                result.push_str(&text);
+                rest = &placeholder[len..];
+            }
+            None => {
+                result.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
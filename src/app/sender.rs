@@ -1,64 +1,294 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot,
+};
 
-use tokio::sync::{mpsc, oneshot};
+use serde::{Deserialize, Serialize};
 
 use crate::app::{
+    checksum::Checksum,
     listener::{ListenerChannel, TaskListener},
-    task::{Task, TaskState},
+    permissions::FileMode,
+    task::{ByteRange, RuntimeStats, Task, TaskId, TaskState},
+    template::NameTemplate,
 };
 
+/// 后台运行时在另一个线程中启动，启动完成之前`sender`为[`None`]，
+/// 见[`Sender::connect`]。
 #[derive(Debug)]
 pub struct Sender {
-    sender: mpsc::Sender<Task>,
+    sender: Option<mpsc::Sender<Task>>,
+    stats: Arc<RuntimeStats>,
+    next_id: u64,
+    // 新任务使用的下载目录，随请求一起发送。保存为绝对路径，
+    // 这样任务记录的文件路径不受工作目录以及之后修改的下载目录影响
+    download_dir: PathBuf,
 }
 
 impl Sender {
+    // -------------------- CONSTANT -----------------------
+
+    /// 与后台运行时之间任务通道的容量，通道已满时新任务留在等待队列中
+    pub const CHANNEL_CAPACITY: usize = 32;
+
     // -------------------- CONSTRUCT -----------------------
 
-    pub fn new(sender: mpsc::Sender<Task>) -> Self {
-        Sender { sender }
+    pub fn new(stats: Arc<RuntimeStats>, download_dir: PathBuf) -> Self {
+        Sender {
+            sender: None,
+            stats,
+            next_id: 1,
+            download_dir: std::path::absolute(&download_dir).unwrap_or(download_dir),
+        }
+    }
+
+    // -------------------- MEMBER_ACCESS -----------------------
+
+    /// 后台运行时是否已经启动
+    pub fn is_connected(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// 已经发送到通道中，但还没有被后台运行时接收的任务数量
+    pub fn in_flight(&self) -> usize {
+        self.stats.queued()
+    }
+
+    pub fn download_dir(&self) -> &PathBuf {
+        &self.download_dir
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 后台运行时启动完成后调用，之后才能真正地发送任务
+    pub fn connect(&mut self, sender: mpsc::Sender<Task>) {
+        self.sender = Some(sender);
+    }
+
+    /// 只影响之后发送的任务，已经发送的任务（包括暂停之后恢复的任务）
+    /// 使用[`TaskState`]中记录的路径
+    ///
+    /// [`TaskState`]: crate::app::task::TaskState
+    pub fn set_download_dir(&mut self, download_dir: PathBuf) {
+        self.download_dir = std::path::absolute(&download_dir).unwrap_or(download_dir);
     }
 
     // -------------------- FUNCTION -----------------------
 
+    /// 生成一个新的任务编号，添加到等待队列时就需要编号，因此与发送分开
+    pub fn next_id(&mut self) -> TaskId {
+        let id = TaskId::new(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    /// 从会话中恢复的任务沿用原来的编号，之后的编号从其中最大的编号之后继续
+    pub fn continue_after(&mut self, id: TaskId) {
+        self.next_id = self.next_id.max(id.value() + 1);
+    }
+
+    /// 发送任务，同时维护[`RuntimeStats`]中的排队计数
+    ///
+    /// 该函数不会阻塞UI线程，通道已满时直接返回[`TrySendError::Full`]，
+    /// 调用者需要自行决定之后是否重试。后台运行时还没有启动时同样视为通道已满。
+    fn send(&self, task: Task) -> Result<(), Box<TrySendError<Task>>> {
+        let Some(sender) = &self.sender else {
+            return Err(Box::new(TrySendError::Full(task)));
+        };
+        self.stats.mark_queued();
+        sender.try_send(task).map_err(|e| {
+            self.stats.cancel_queued();
+            Box::new(e)
+        })
+    }
+
     pub fn send_normal_request(
         &self,
-        url: String,
-    ) -> Result<TaskListener, Box<mpsc::error::SendError<Task>>> {
-        let state = Arc::new(Mutex::new(TaskState::new()));
-        let (res_tx, res_rx) = oneshot::channel();
-        let (cmd_tx, cmd_rx) = oneshot::channel();
-        let task = Task::new(
-            state.clone(),
-            DownloadRequest::new_normal(url),
-            res_tx,
-            cmd_rx,
+        id: TaskId,
+        options: RequestOptions,
+    ) -> Result<TaskListener, Box<TrySendError<Task>>> {
+        let request = DownloadRequest::new_normal(
+            options.url.clone(),
+            options.save_as.clone(),
+            self.download_dir.clone(),
         );
-        self.sender.blocking_send(task)?;
-        let listener = TaskListener::new(state, res_rx, cmd_tx);
+        let mut state = TaskState::new();
+        state.options = Some(Arc::new(options));
+        let state = Arc::new(Mutex::new(state));
+        let (res_tx, res_rx) = oneshot::channel();
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let task = Task::new(id, state.clone(), request, res_tx, cmd_rx);
+        self.send(task)?;
+        let listener = TaskListener::new(id, state, res_rx, cmd_tx);
         Ok(listener)
     }
 
+    /// 重新校验已经下载完成的文件，`state`中需要填好保存路径以及校验和
+    pub fn send_verify_request(
+        &self,
+        id: TaskId,
+        mut state: TaskState,
+    ) -> Result<TaskListener, Box<TrySendError<Task>>> {
+        state.verify_only = true;
+        let state = Arc::new(Mutex::new(state));
+        let (res_tx, res_rx) = oneshot::channel();
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let task = Task::new(id, state.clone(), DownloadRequest::Verify, res_tx, cmd_rx);
+        self.send(task)?;
+        Ok(TaskListener::new(id, state, res_rx, cmd_tx))
+    }
+
+    /// 恢复暂停的任务，沿用原来的状态
+    ///
+    /// 还没有开始下载就被停止的任务（例如在排队期间停止）没有可以继续的文件，
+    /// 按照记录的选项重新作为新的任务发送，使用当前的下载目录。
     pub fn send_resume_request(
         &self,
+        id: TaskId,
         task_state: Arc<Mutex<TaskState>>,
-    ) -> Result<ListenerChannel, Box<mpsc::error::SendError<Task>>> {
+    ) -> Result<ListenerChannel, Box<TrySendError<Task>>> {
+        let request = {
+            let state = task_state.lock().unwrap();
+            match state.options() {
+                _ if state.verify_only => DownloadRequest::Verify,
+                Some(options) if !state.has_started() => DownloadRequest::new_normal(
+                    options.url.clone(),
+                    options.save_as.clone(),
+                    self.download_dir.clone(),
+                ),
+                _ => DownloadRequest::Resume,
+            }
+        };
         let (res_tx, res_rx) = oneshot::channel();
-        let (cmd_tx, cmd_rx) = oneshot::channel();
-        let task = Task::new(task_state, DownloadRequest::Resume, res_tx, cmd_rx);
-        self.sender.blocking_send(task)?;
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let task = Task::new(id, task_state, request, res_tx, cmd_rx);
+        self.send(task)?;
         Ok(ListenerChannel::new(res_rx, cmd_tx))
     }
 }
 
+/// `save_as`为用户指定的保存路径，相对路径基于`download_dir`。为[`None`]时，
+/// 根据URL自动生成文件名。
 #[derive(Debug)]
 pub enum DownloadRequest {
-    Normal { url: String },
+    Normal {
+        url: String,
+        save_as: Option<SaveAs>,
+        download_dir: PathBuf,
+    },
     Resume,
+    /// 重新读取已经存在的文件，与记录的校验和比较，不连接服务器，也不占用名额
+    Verify,
 }
 
 impl DownloadRequest {
-    pub fn new_normal(url: String) -> Self {
-        DownloadRequest::Normal { url }
+    pub fn new_normal(url: String, save_as: Option<SaveAs>, download_dir: PathBuf) -> Self {
+        DownloadRequest::Normal {
+            url,
+            save_as,
+            download_dir,
+        }
+    }
+}
+
+/// 创建任务时用户输入的选项，保存在[`TaskState`]中，用于复制任务
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    pub url: String,
+    pub save_as: Option<SaveAs>,
+    /// 下载完成后需要校验的校验和
+    pub checksum: Option<Checksum>,
+    /// 额外的请求头
+    pub headers: Vec<(String, String)>,
+    /// 下载完成后自动解压，见[`ArchiveKind`]
+    ///
+    /// [`ArchiveKind`]: crate::app::task::ArchiveKind
+    pub extract: bool,
+    /// 已经存在相同的文件时仍然重新下载，见配置中的`redownload_existing`
+    pub redownload: bool,
+    /// 下载完成的文件的权限，优先于配置中的`file_mode`
+    pub file_mode: Option<FileMode>,
+    /// 只下载文件的一部分，见[`ByteRange`]
+    pub range: Option<ByteRange>,
+}
+
+impl RequestOptions {
+    pub fn new(url: String, save_as: Option<SaveAs>) -> Self {
+        RequestOptions {
+            url,
+            save_as,
+            checksum: None,
+            headers: Vec::new(),
+            extract: false,
+            redownload: false,
+            file_mode: None,
+            range: None,
+        }
+    }
+
+    pub fn with_checksum(mut self, checksum: Option<Checksum>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_extract(mut self, extract: bool) -> Self {
+        self.extract = extract;
+        self
+    }
+
+    pub fn with_redownload(mut self, redownload: bool) -> Self {
+        self.redownload = redownload;
+        self
+    }
+
+    pub fn with_file_mode(mut self, file_mode: Option<FileMode>) -> Self {
+        self.file_mode = file_mode;
+        self
+    }
+
+    pub fn with_range(mut self, range: Option<ByteRange>) -> Self {
+        self.range = range;
+        self
+    }
+}
+
+/// 用户在"Save as"中输入的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveAs {
+    /// 明确的保存路径，已经存在文件时需要用户决定如何处理
+    Path(PathBuf),
+    /// 命名模板，以及该任务在同一批任务中的序号（从1开始）。
+    /// 展开后的文件名已经存在时，与自动生成的文件名一样自动添加后缀。
+    Template { template: NameTemplate, n: usize },
+}
+
+impl SaveAs {
+    /// 用户当初在"Save as"中输入的内容
+    pub fn input_text(&self) -> String {
+        match self {
+            SaveAs::Path(path) => path.display().to_string(),
+            SaveAs::Template { template, .. } => template.source().to_string(),
+        }
+    }
+}
+
+impl Display for SaveAs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveAs::Path(path) => write!(f, "{}", path.display()),
+            SaveAs::Template { template, n } => write!(f, "{} (#{})", template.source(), n),
+        }
     }
 }
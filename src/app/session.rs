@@ -0,0 +1,157 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::disk;
+use crate::app::pending::PendingTask;
+use crate::app::saved::SavedDownload;
+use crate::app::stats::HostStatsMap;
+use crate::app::task::TaskId;
+
+/// 需要跨越多次运行保存的数据，以JSON格式存储在数据目录下的`session.json`中。
+///
+/// 与[`Config`]不同，这些数据由程序自动维护，用户不需要手动编辑。
+///
+/// [`Config`]: crate::app::config::Config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// 按主机统计的下载数据
+    pub hosts: HostStatsMap,
+    /// 退出时还没有发送出去的任务
+    pub pending: Vec<PendingTask>,
+    /// 保存下来用于重复添加的下载，见[`SavedDownload`]
+    pub templates: Vec<SavedDownload>,
+    /// 退出时界面的状态，启动时恢复
+    pub ui: UiState,
+}
+
+/// 退出时选中的页面以及下载列表中的选中项，见[`Session::ui`]
+///
+/// 列表的内容在两次运行之间会发生变化，因此选中项使用任务的标识而不是下标保存，
+/// 找不到对应的任务时保持默认的状态。完成列表不会跨越多次运行保存，启动时总是空的，
+/// 因此不记录它的选中项。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    /// 左侧页面列表中选中的页面
+    pub page: usize,
+    /// 是否已经进入该页面
+    pub entered: bool,
+    /// 下载页面中的选中项，使用[`TaskId`]匹配
+    pub downloading: ListPosition<TaskId>,
+}
+
+/// 列表中选中项的标识以及滚动距离
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListPosition<K> {
+    pub selected: Option<K>,
+    pub scroll: usize,
+}
+
+impl<K> Default for ListPosition<K> {
+    fn default() -> Self {
+        ListPosition {
+            selected: None,
+            scroll: 0,
+        }
+    }
+}
+
+impl<K: PartialEq> ListPosition<K> {
+    // -------------------- FUNCTION -----------------------
+
+    /// 在`keys`中查找保存的选中项，返回它的下标，找不到时返回[`None`]
+    pub fn find<'a>(&self, keys: impl IntoIterator<Item = &'a K>) -> Option<usize>
+    where
+        K: 'a,
+    {
+        let selected = self.selected.as_ref()?;
+        keys.into_iter().position(|key| key == selected)
+    }
+}
+
+impl Session {
+    // ------------------- CONSTANT -----------------------
+
+    pub const FILENAME: &'static str = "session.json";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 从会话文件中读取数据，失败时返回空的会话
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Session::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!(target:"Session", "Failed to parse {}: {}", path.display(), e);
+                Session::default()
+            }),
+            Err(_) => Session::default(),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 会话文件的路径，如果无法确定用户目录，则返回[`None`]
+    pub fn path() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "request-tui")
+            .map(|dirs| dirs.data_dir().join(Self::FILENAME))
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path().ok_or(anyhow::anyhow!("Cannot determine data directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        disk::write_atomic(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ui_state_survives_a_round_trip() {
+        let ui = UiState {
+            page: 2,
+            entered: true,
+            downloading: ListPosition {
+                selected: Some(TaskId::new(7)),
+                scroll: 3,
+            },
+        };
+        let json = serde_json::to_string(&Session {
+            ui: ui.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let session: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(session.ui, ui);
+    }
+
+    #[test]
+    fn older_session_with_finished_position_still_loads() {
+        let json = r#"{"ui":{"page":1,"entered":false,
+            "downloading":{"selected":null,"scroll":0},
+            "finished":{"selected":"/tmp/a.bin","scroll":4}}}"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(session.ui.page, 1);
+    }
+
+    #[test]
+    fn position_is_found_by_key() {
+        let position = ListPosition {
+            selected: Some(TaskId::new(2)),
+            scroll: 0,
+        };
+        let ids = [TaskId::new(1), TaskId::new(2), TaskId::new(3)];
+        assert_eq!(position.find(&ids), Some(1));
+        assert_eq!(position.find(&ids[..1]), None);
+        assert_eq!(ListPosition::<TaskId>::default().find(&ids), None);
+    }
+}
@@ -0,0 +1,373 @@
+//! 主界面的渲染快照，防止布局的改动在不知不觉中破坏界面
+//!
+//! 使用[`TestBackend`]绘制整个[`App`]，逐行比较屏幕上的文本，每个场景分别在80×24以及40×12下绘制。
+//! 任务的状态直接填好（见[`TaskListener::fake`]），不启动后台运行时，也不访问网络，
+//! 因此顶部始终显示正在启动后台运行时。界面有意改动时，按照失败信息中输出的实际内容更新快照。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::palette::tailwind;
+use url::Url;
+
+use crate::app::App;
+use crate::app::about::BuildInfo;
+use crate::app::config::{Config, LiveConfig};
+use crate::app::listener::TaskListener;
+use crate::app::session::Session;
+use crate::app::task::{ConnectionLimits, RuntimeStats, TaskId, TaskPhase, TaskResult, TaskState};
+use crate::app::throttle::{SpeedLimit, Throttle};
+use crate::window::WidgetType;
+use crate::window::app::{FinishState, FinishedTask, PageList};
+
+const DOWNLOAD_DIR: &str = "/srv/downloads";
+
+fn app() -> App {
+    let config = Config {
+        download_dir: Some(PathBuf::from(DOWNLOAD_DIR)),
+        ..Config::default()
+    };
+    let build_info = BuildInfo::collect(&config);
+    // 后台运行时永远不会启动
+    let (_, startup) = std::sync::mpsc::channel();
+    App::new(
+        startup,
+        Arc::new(RuntimeStats::new()),
+        Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+        Arc::new(ConnectionLimits::unlimited()),
+        LiveConfig::new(config),
+        Session::default(),
+        build_info,
+    )
+}
+
+fn downloading(id: u64, name: &str, downloaded: u64, length: u64) -> TaskListener {
+    let mut state = TaskState::new();
+    state.url = Some(Url::parse(&format!("https://example.com/{}", name)).unwrap());
+    state.filepath = PathBuf::from(DOWNLOAD_DIR).join(name);
+    state.content_length = Some(length);
+    state.downloaded = downloaded;
+    state.phase = TaskPhase::Downloading;
+    TaskListener::fake(TaskId::new(id), state)
+}
+
+fn two_downloading() -> App {
+    let mut app = app();
+    app.data
+        .downloading
+        .push_fake(downloading(1, "ubuntu.iso", 512 << 20, 2 << 30));
+    app.data
+        .downloading
+        .push_fake(downloading(2, "notes.pdf", 100 << 10, 300 << 10));
+    app.list = PageList::new().with_page(0, true);
+    app.data.downloading.set_selected(Some(1));
+    app
+}
+
+fn finished_failure() -> App {
+    let mut app = app();
+    app.data.finished.push_task(FinishedTask::new(
+        FinishState::Failure,
+        PathBuf::from(DOWNLOAD_DIR).join("missing.zip"),
+        Some(Url::parse("https://example.com/missing.zip").unwrap()),
+        None,
+        0,
+        Some(TaskResult::new_http_status(404)),
+        Duration::from_secs(2),
+    ));
+    app.list = PageList::new().with_page(1, true);
+    app
+}
+
+fn download_input() -> App {
+    let mut app = app();
+    app.list = PageList::new().with_page(0, false);
+    app.widgets
+        .push(WidgetType::new_download_input(None, false));
+    app
+}
+
+fn render(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal
+        .draw(|f| f.render_widget(&mut *app, f.area()))
+        .unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn text_lines(buffer: &Buffer) -> Vec<String> {
+    let area = buffer.area;
+    (area.top()..area.bottom())
+        .map(|y| {
+            (area.left()..area.right())
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        })
+        .collect()
+}
+
+/// 绘制`app`并且逐行比较文本，不一致时输出完整的实际内容，便于更新快照
+fn assert_snapshot(app: &mut App, width: u16, height: u16, expected: &[&str]) {
+    let actual = text_lines(&render(app, width, height));
+    if actual != expected {
+        let rendered: Vec<String> = actual
+            .iter()
+            .map(|line| format!("        r#\"{}\"#,", line))
+            .collect();
+        panic!(
+            "rendered screen differs from the snapshot, actual:\n{}",
+            rendered.join("\n")
+        );
+    }
+}
+
+#[test]
+fn empty_lists_80x24() {
+    assert_snapshot(
+        &mut app(),
+        80,
+        24,
+        &[
+            r#"╭────────────────────────────────── REQUEST ───────────────────────────────────╮"#,
+            r#"│                    ┃ Starting background runtime...                          │"#,
+            r#"│    Downloading     ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│      Finished      ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│       Stats        ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                         NO TASKS                        │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"╰──────────────────────────────────────────────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn two_downloading_tasks_80x24() {
+    assert_snapshot(
+        &mut two_downloading(),
+        80,
+        24,
+        &[
+            r#"╭────────────────────────────────── REQUEST ───────────────────────────────────╮"#,
+            r#"│                    ┃ Starting background runtime...                          │"#,
+            r#"│    Downloading     ┃#1 /srv/downloads/ubuntu.iso                             │"#,
+            r#"│                    ┃ █████████████▊            25%                           │"#,
+            r#"│                    ┃Downloading...        512.00 MB/2.00 GB │          -- B/s│"#,
+            r#"│      Finished      ┃─────────────────────────────────────────────────────────│"#,
+            r#"│                    ┃#2 /srv/downloads/notes.pdf                              │"#,
+            r#"│                    ┃ ██████████████████▏       33%                           │"#,
+            r#"│       Stats        ┃Downloading...      100.00 KB/300.00 KB │          -- B/s│"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"╰──────────────────────────────────────────────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn finished_failure_80x24() {
+    assert_snapshot(
+        &mut finished_failure(),
+        80,
+        24,
+        &[
+            r#"╭────────────────────────────────── REQUEST ───────────────────────────────────╮"#,
+            r#"│                    ┃/srv/downloads/missing.zip                               │"#,
+            r#"│    Downloading     ┃ ██████████████████████████0 B █████████████████████████ │"#,
+            r#"│                    ┃HTTP 404 Not Found               0 B / -- │         0 B/s│"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│      Finished      ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│       Stats        ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"│                    ┃                                                         │"#,
+            r#"╰──────────────────────────────────────────────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn download_input_80x24() {
+    assert_snapshot(
+        &mut download_input(),
+        80,
+        24,
+        &[
+            r#"╭───────────────────╭Download──────────────────────────────╮───────────────────╮"#,
+            r#"│                   │URL:                                  │                   │"#,
+            r#"│    Downloading    │╭input───────────────────────────────╮│                   │"#,
+            r#"│                   ││                                    ││                   │"#,
+            r#"│                   │╰────────────────────────────────────╯│                   │"#,
+            r#"│      Finished     │                                      │                   │"#,
+            r#"│                   │Save as (optional, {n:03} {name} {host│                   │"#,
+            r#"│                   │╭input───────────────────────────────╮│                   │"#,
+            r#"│       Stats       ││                                    ││                   │"#,
+            r#"│                   │╰────────────────────────────────────╯│                   │"#,
+            r#"│                   │Headers (optional, one "Name: value" p│                   │"#,
+            r#"│                   │╭input───────────────────────────────╮│                   │"#,
+            r#"│                   ││                                    ││                   │"#,
+            r#"│                   ││                                    ││                   │"#,
+            r#"│                   │╰────────────────────────────────────╯│                   │"#,
+            r#"│                   │File mode (optional, e.g. 0644, Unix o│                   │"#,
+            r#"│                   │╭input───────────────────────────────╮│                   │"#,
+            r#"│                   ││                                    ││                   │"#,
+            r#"│                   │╰────────────────────────────────────╯│                   │"#,
+            r#"│                   │Byte range (optional, e.g. 0-1048575, │                   │"#,
+            r#"│                   │╭input───────────────────────────────╮│                   │"#,
+            r#"│                   ││                                    ││                   │"#,
+            r#"│                   │╰────────────────────────────────────╯│                   │"#,
+            r#"╰───────────────────╰nload [off]  x: extract archives [off]╯───────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn empty_lists_40x12() {
+    assert_snapshot(
+        &mut app(),
+        40,
+        12,
+        &[
+            r#"╭────────────── REQUEST ───────────────╮"#,
+            r#"│          ┃ Starting background runtim│"#,
+            r#"│Downloadin┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│ Finished ┃                           │"#,
+            r#"│          ┃          NO TASKS         │"#,
+            r#"│          ┃                           │"#,
+            r#"│  Stats   ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"╰──────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn two_downloading_tasks_40x12() {
+    assert_snapshot(
+        &mut two_downloading(),
+        40,
+        12,
+        &[
+            r#"╭────────────── REQUEST ───────────────╮"#,
+            r#"│          ┃ Starting background runtim│"#,
+            r#"│Downloadin┃#1 /srv/downloads/ubuntu.is│"#,
+            r#"│          ┃ ██████▎    25%            │"#,
+            r#"│          ┃Downloading...             │"#,
+            r#"│ Finished ┃───────────────────────────│"#,
+            r#"│          ┃#2 /srv/downloads/notes.pdf│"#,
+            r#"│          ┃ ████████▎  33%            │"#,
+            r#"│  Stats   ┃Downloading...             │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"╰──────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn finished_failure_40x12() {
+    assert_snapshot(
+        &mut finished_failure(),
+        40,
+        12,
+        &[
+            r#"╭────────────── REQUEST ───────────────╮"#,
+            r#"│          ┃/srv/downloads/missing.zip │"#,
+            r#"│Downloadin┃ ███████████0 B ██████████ │"#,
+            r#"│          ┃HTTP 404 Not Found         │"#,
+            r#"│          ┃                           │"#,
+            r#"│ Finished ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│  Stats   ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"╰──────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn download_input_40x12() {
+    assert_snapshot(
+        &mut download_input(),
+        40,
+        12,
+        &[
+            r#"╭────────────── REQUEST ───────────────╮"#,
+            r#"│          ┃ Starting background runtim│"#,
+            r#"│Downloadin┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│ Finished ┃                           │"#,
+            r#"     Window too small, Esc to close     "#,
+            r#"│          ┃                           │"#,
+            r#"│  Stats   ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"│          ┃                           │"#,
+            r#"╰──────────────────────────────────────╯"#,
+        ],
+    );
+}
+
+#[test]
+fn selected_task_and_entered_page_are_highlighted() {
+    let buffer = render(&mut two_downloading(), 80, 24);
+    // 进入页面之后，分隔条的下半部分高亮
+    let bar = 21;
+    assert_ne!(buffer[(bar, 2)].fg, tailwind::AMBER.c300);
+    assert_eq!(buffer[(bar, 20)].fg, tailwind::AMBER.c300);
+    // 选中的第二个任务的标题行以及最后一行整行都有背景色，第一个任务没有
+    let highlighted = buffer[(30, 6)].bg;
+    assert_ne!(buffer[(30, 2)].bg, highlighted);
+    for y in [6, 8] {
+        assert_eq!(buffer[(70, y)].bg, highlighted, "row {}", y);
+    }
+}
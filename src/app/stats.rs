@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{DateTime, Local};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::record::SessionTotals;
+use crate::app::task::StageKind;
+
+/// 单个主机的累计下载统计
+///
+/// `transfer_secs`是该主机上所有任务实际传输数据的时间之和，用于计算平均速度。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostStats {
+    pub files: u64,
+    pub bytes: u64,
+    pub transfer_secs: f64,
+    pub failures: u64,
+    /// 该主机上的任务达到过的最高速度（B/s）
+    pub peak_speed: u64,
+    /// 最近几次请求的TTFB（毫秒），最多保留[`HostStats::TTFB_SAMPLES`]个
+    pub ttfb_ms: Vec<u64>,
+}
+
+impl HostStats {
+    pub const TTFB_SAMPLES: usize = 32;
+
+    /// 最近几次请求的TTFB的中位数，没有记录时返回[`None`]
+    pub fn median_ttfb(&self) -> Option<Duration> {
+        let mut samples = self.ttfb_ms.clone();
+        samples.sort_unstable();
+        let len = samples.len();
+        let median = match len {
+            0 => return None,
+            _ if len % 2 == 1 => samples[len / 2],
+            _ => (samples[len / 2 - 1] + samples[len / 2]) / 2,
+        };
+        Some(Duration::from_millis(median))
+    }
+
+    /// 平均下载速度（B/s），没有传输过数据时返回[`None`]
+    pub fn average_speed(&self) -> Option<u64> {
+        if self.transfer_secs > 0.0 {
+            Some((self.bytes as f64 / self.transfer_secs) as u64)
+        } else {
+            None
+        }
+    }
+}
+
+/// 按主机统计的下载数据，主机名取自任务最终（重定向后）的URL。
+///
+/// 该统计会保存到会话文件中，因此会跨越多次运行累计。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HostStatsMap {
+    hosts: HashMap<String, HostStats>,
+}
+
+impl HostStatsMap {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn get(&self, host: &str) -> Option<&HostStats> {
+        self.hosts.get(host)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// 按下载量从大到小排序的统计
+    pub fn sorted_by_bytes(&self) -> Vec<(&str, &HostStats)> {
+        let mut hosts: Vec<_> = self
+            .hosts
+            .iter()
+            .map(|(host, stats)| (host.as_str(), stats))
+            .collect();
+        hosts.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+        hosts
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 记录某个主机在`elapsed`时间内传输了`bytes`字节
+    pub fn record_progress(&mut self, host: &str, bytes: u64, elapsed: Duration) {
+        let stats = self.hosts.entry(host.to_string()).or_default();
+        stats.bytes += bytes;
+        stats.transfer_secs += elapsed.as_secs_f64();
+    }
+
+    /// 记录该主机上的任务达到的最高速度，只保留最大的值
+    pub fn record_peak(&mut self, host: &str, speed: u64) {
+        let stats = self.hosts.entry(host.to_string()).or_default();
+        stats.peak_speed = stats.peak_speed.max(speed);
+    }
+
+    /// 记录一次请求的TTFB，超出[`HostStats::TTFB_SAMPLES`]时丢弃最早的记录
+    pub fn record_ttfb(&mut self, host: &str, ttfb: Duration) {
+        let stats = self.hosts.entry(host.to_string()).or_default();
+        if stats.ttfb_ms.len() >= HostStats::TTFB_SAMPLES {
+            stats.ttfb_ms.remove(0);
+        }
+        stats.ttfb_ms.push(ttfb.as_millis() as u64);
+    }
+
+    /// 记录任务的最终结果，暂停（包括等待磁盘空间）和取消既不算作完成也不算作失败
+    pub fn record_result(&mut self, host: &str, stage: StageKind) {
+        let stats = self.hosts.entry(host.to_string()).or_default();
+        if stage == StageKind::Finished {
+            stats.files += 1;
+        } else if stage.is_failure() {
+            stats.failures += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.hosts.clear();
+    }
+}
+
+/// 本次运行的时间统计，显示在Stats页面并写入`--summary-json`
+///
+/// `active`是至少有一个任务在传输数据的时间之和，由[`SessionClock::tick`]在每次
+/// `handle_async`时累计：两次调用之间的时间在后一次调用时有数据传输的情况下计入。
+#[derive(Debug, Clone)]
+pub struct SessionClock {
+    started_at: Instant,
+    started: SystemTime,
+    active: Duration,
+    bytes: u64,
+    last_tick: Option<Instant>,
+}
+
+impl SessionClock {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn start() -> Self {
+        SessionClock::new(Instant::now(), SystemTime::now())
+    }
+
+    pub fn new(started_at: Instant, started: SystemTime) -> Self {
+        SessionClock {
+            started_at,
+            started,
+            active: Duration::ZERO,
+            bytes: 0,
+            last_tick: None,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 程序启动的本地时间
+    pub fn since(&self) -> DateTime<Local> {
+        DateTime::from(self.started)
+    }
+
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    pub fn uptime(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started_at)
+    }
+
+    pub fn active(&self) -> Duration {
+        self.active
+    }
+
+    /// 本次运行中接收的字节数
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// 按传输时间计算的平均速度（B/s），还没有传输过数据时返回[`None`]
+    pub fn average_speed(&self) -> Option<u64> {
+        let secs = self.active.as_secs_f64();
+        (secs > 0.0).then(|| (self.bytes as f64 / secs) as u64)
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// `transferring`为此时是否有任务在传输数据，`bytes`为本次运行中接收的总字节数
+    pub fn tick(&mut self, now: Instant, transferring: bool, bytes: u64) {
+        if transferring && let Some(last) = self.last_tick {
+            self.active += now.saturating_duration_since(last);
+        }
+        self.last_tick = Some(now);
+        self.bytes = self.bytes.max(bytes);
+    }
+
+    // ------------------ TYPE_CONVERSION -------------------
+
+    pub fn to_totals(&self, now: Instant) -> SessionTotals {
+        SessionTotals {
+            started_at: Some(self.since().to_rfc3339()),
+            duration_secs: self.uptime(now).as_secs_f64(),
+            active_secs: self.active.as_secs_f64(),
+            bytes: self.bytes,
+        }
+    }
+}
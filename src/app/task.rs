@@ -1,16 +1,62 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use tokio::{fs::File, sync::oneshot};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::File,
+    sync::{mpsc, oneshot, watch},
+};
 
+use crate::app::checksum::StreamHasher;
+use crate::app::config::Config;
+use crate::app::network::NetworkOptions;
+use crate::app::permissions::FileModes;
+use crate::app::policy::FileTypePolicy;
 use crate::app::sender::DownloadRequest;
+use crate::app::throttle::Throttle;
 
+mod busy;
+mod completed;
+mod encoding;
+mod eta;
+mod extract;
+mod file_error;
+mod health;
+mod latency;
+mod limit;
+mod log_limit;
 mod manager;
+#[cfg(test)]
+pub mod mock_server;
+mod naming;
+mod observer;
+mod partial;
+mod pieces;
+mod range;
 pub mod resolve;
 mod result;
+mod security;
 mod state;
 
+pub use busy::*;
+pub use completed::*;
+pub use encoding::*;
+pub use eta::*;
+pub use extract::*;
+pub use file_error::*;
+pub use health::*;
+pub use latency::*;
+pub use limit::*;
+pub use log_limit::*;
 pub use manager::*;
+pub use observer::*;
+pub use partial::*;
+pub use pieces::*;
+pub use range::*;
 pub use result::*;
+pub use security::*;
 pub use state::*;
 
 /// 由于UI线程需要频繁地了解任务的执行状态，并在UI界面显示，因此需要将任务状态
@@ -19,6 +65,7 @@ pub use state::*;
 /// 任何无须与UI线程交互的任务逻辑都不应放置在`TaskState`中。
 #[derive(Debug)]
 pub struct Task {
+    id: TaskId,
     request: DownloadRequest,
     inner: TaskInner,
     handler: SignalHandler,
@@ -28,12 +75,14 @@ impl Task {
     // -------------------- CONSTRUCT -----------------------
 
     pub fn new(
+        id: TaskId,
         state: Arc<Mutex<TaskState>>,
         request: DownloadRequest,
         reporter: oneshot::Sender<TaskResult>,
-        command_recv: oneshot::Receiver<TaskCommand>,
+        command_recv: mpsc::UnboundedReceiver<TaskCommand>,
     ) -> Self {
         Task {
+            id,
             request,
             inner: TaskInner::new(state),
             handler: SignalHandler::new(reporter, command_recv),
@@ -42,6 +91,10 @@ impl Task {
 
     // -------------------- MEMBER_ACCESS -----------------------
 
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
     pub fn request(&self) -> &DownloadRequest {
         &self.request
     }
@@ -55,6 +108,48 @@ impl Task {
     }
 }
 
+/// 任务的唯一编号，由[`Sender`]生成，在本次运行中不会重复
+///
+/// 列表中的任务可能被移动或者删除，因此需要定位某个任务时使用编号，而不是下标。
+/// 恢复任务时沿用原来的编号。编号从1开始，以`#7`的形式显示在列表中，
+/// 用户可以在列表页面中输入`:7`跳转到该任务。
+///
+/// [`Sender`]: crate::app::sender::Sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    pub fn new(id: u64) -> Self {
+        TaskId(id)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// 是否为还没有分配的编号，例如旧的会话文件中没有保存编号的任务
+    pub fn is_unassigned(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// 解析用户输入的编号，可以带有开头的`#`
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let number = input.strip_prefix('#').unwrap_or(input).trim();
+        match number.parse::<u64>() {
+            Ok(0) | Err(_) => None,
+            Ok(n) => Some(TaskId(n)),
+        }
+    }
+}
+
+impl Display for TaskId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 struct TaskInner {
     pub state: Arc<Mutex<TaskState>>,
@@ -78,20 +173,221 @@ impl TaskInner {
 #[derive(Debug)]
 struct SignalHandler {
     pub reporter: oneshot::Sender<TaskResult>,
-    pub receiver: oneshot::Receiver<TaskCommand>,
+    pub receiver: mpsc::UnboundedReceiver<TaskCommand>,
 }
 
 impl SignalHandler {
     pub fn new(
         reporter: oneshot::Sender<TaskResult>,
-        receiver: oneshot::Receiver<TaskCommand>,
+        receiver: mpsc::UnboundedReceiver<TaskCommand>,
     ) -> Self {
         SignalHandler { reporter, receiver }
     }
 }
 
+/// 所有任务共享的执行环境，由[`TaskManager`]创建
+///
+/// 每个任务在被接收时复制一份，得到名额时再按照当时的配置更新，见[`TaskContext::admitted`]，
+/// 之后修改配置不再影响该任务。例外是`throttle`和`limits`这类共享的部分，
+/// 见[`TaskContext::configure`]。
+#[derive(Debug, Clone)]
+pub struct TaskContext {
+    pub throttle: Arc<Throttle>,
+    pub limits: Arc<ConnectionLimits>,
+    pub network: Arc<NetworkOptions>,
+    pub policy: Arc<FileTypePolicy>,
+    /// 是否自动获取URL旁边的校验和文件，见配置中的`auto_checksum`
+    pub auto_checksum: bool,
+    /// 为`false`时，大小（以及校验和）相同的已有文件不会被重新下载，见配置中的`redownload_existing`
+    pub redownload_existing: bool,
+    /// 下载完成的文件以及创建的目录的权限，见配置中的`file_mode`和`dir_mode`
+    pub modes: FileModes,
+    /// 排队的任务是否提前准备连接，见配置中的`warm_up`
+    pub warm_up: WarmUp,
+    pub buffers: BufferSizes,
+    /// 下载完成的文件另外链接或者复制到的目录，见配置中的`completed_dir`
+    pub completed_dir: Option<PathBuf>,
+    /// 恢复下载时已经下载的部分比记录的长时的处理方式，见配置中的`oversized_partial`
+    pub oversized_partial: OversizedPartial,
+    /// 等待用户做出决定的时间上限，之后按照安全的默认选择继续，见配置中的`decision_timeout`
+    pub decision_timeout: Option<Duration>,
+    pub events: TaskEvents,
+    // 运行期间的配置，任务得到名额时从中读取最新的设置
+    config: Option<watch::Receiver<Arc<Config>>>,
+}
+
+impl TaskContext {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(
+        throttle: Arc<Throttle>,
+        limits: Arc<ConnectionLimits>,
+        events: TaskEvents,
+        config: &Config,
+    ) -> Self {
+        let mut context = TaskContext {
+            throttle,
+            limits,
+            network: Arc::new(NetworkOptions::new()),
+            policy: Arc::new(FileTypePolicy::default()),
+            auto_checksum: false,
+            redownload_existing: false,
+            modes: FileModes::default(),
+            warm_up: WarmUp::Off,
+            buffers: BufferSizes::default(),
+            completed_dir: None,
+            oversized_partial: OversizedPartial::default(),
+            decision_timeout: None,
+            events,
+            config: None,
+        };
+        context.configure(config);
+        context
+    }
+
+    /// 任务得到名额时从`config`中读取最新的配置，见[`TaskContext::admitted`]
+    pub fn with_live_config(mut self, config: watch::Receiver<Arc<Config>>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 任务得到名额时调用，返回按照此时的配置更新之后的执行环境
+    ///
+    /// 排队期间配置可能已经被修改，之后得到名额的任务使用新的设置，已经开始的任务不受影响。
+    pub fn admitted(&self) -> TaskContext {
+        let mut context = self.clone();
+        if let Some(config) = &mut context.config
+            && config.has_changed().unwrap_or(false)
+        {
+            let current = config.borrow_and_update().clone();
+            context.apply(&current);
+        }
+        context
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 使用`config`中的设置，运行期间配置被修改时由[`TaskManager`]调用
+    ///
+    /// 复制到各个任务中的设置只对之后接收的任务生效，HTTP客户端也在任务开始时按照新的网络设置创建。
+    /// 速度上限以及同时进行的任务数量保存在共享的`throttle`和`limits`中，正在进行的任务也立即使用新的值。
+    /// 网络设置或者权限无效时保留原来的值。
+    pub fn configure(&mut self, config: &Config) {
+        self.apply(config);
+        self.limits
+            .set_max_tasks(config.max_concurrent_tasks, config.max_tasks_per_host);
+        self.throttle
+            .set_defaults(config.speed_limit, config.speed_schedule.clone());
+        // 之后复制的执行环境已经是最新的设置，得到名额时不需要再次应用
+        if let Some(config) = &mut self.config {
+            config.mark_unchanged();
+        }
+    }
+
+    /// 只更新复制到各个任务中的设置
+    fn apply(&mut self, config: &Config) {
+        match NetworkOptions::from_config(config) {
+            Ok(network) => self.network = Arc::new(network),
+            Err(e) => log::warn!(target:"Config", "{}, keeping the current network settings", e),
+        }
+        match FileModes::from_config(config) {
+            Ok(modes) => self.modes = modes,
+            Err(e) => log::warn!(target:"Config", "{}, keeping the current permissions", e),
+        }
+        self.policy = Arc::new(config.file_type_policy.clone());
+        self.auto_checksum = config.auto_checksum;
+        self.redownload_existing = config.redownload_existing;
+        self.warm_up = config.warm_up;
+        self.buffers = BufferSizes::from_kib(config.write_buffer_kib);
+        self.completed_dir = config.completed_dir.clone();
+        self.oversized_partial = config.oversized_partial;
+        self.decision_timeout = config.decision_timeout.duration();
+    }
+}
+
+/// 每个任务在内存中缓冲的数据的上限，写入文件的缓冲区大小见配置中的`write_buffer_kib`
+///
+/// 磁盘比网络慢时，缓冲区和计算哈希值的队列都满了之后任务不再读取响应，
+/// 背压会传递到TCP的接收窗口，而不是让数据堆积在内存中。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSizes {
+    /// 写入文件的缓冲区（字节）
+    pub write: usize,
+    /// 等待计算哈希值的数据块数量，见[`StreamHasher`]
+    pub hash_queue: usize,
+}
+
+impl BufferSizes {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DEFAULT_WRITE_KIB: usize = 256;
+    /// 更小的缓冲区会让每一块数据都直接写入文件
+    const MIN_WRITE_KIB: usize = 4;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn from_kib(write_kib: usize) -> Self {
+        BufferSizes {
+            write: write_kib.max(Self::MIN_WRITE_KIB) * 1024,
+            hash_queue: StreamHasher::PIPELINE_CAPACITY,
+        }
+    }
+}
+
+impl Default for BufferSizes {
+    fn default() -> Self {
+        Self::from_kib(Self::DEFAULT_WRITE_KIB)
+    }
+}
+
+/// 在详情弹窗中显示，例如`write 256 KiB, hash queue 8 chunks`
+impl Display for BufferSizes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "write {} KiB, hash queue {} chunks",
+            self.write / 1024,
+            self.hash_queue
+        )
+    }
+}
+
+/// UI线程发送给任务的指令
+///
+/// 指令通道可以多次发送，例如任务在等待[`TaskCommand::ResolveConflict`]时，
+/// 用户仍然可以停止或者取消任务。
 #[derive(Debug, Clone, Copy)]
 pub enum TaskCommand {
     Stop,
     Abort,
+    ResolveConflict(ConflictResolution),
+    /// 文件类型匹配[`FileTypePolicy`]的提示规则时，用户是否继续下载
+    ConfirmDownload(bool),
+    /// 设置该任务自己的速度上限（B/s），为[`None`]时只受全局上限的限制，
+    /// 从下一块数据开始生效
+    SetSpeedLimit(Option<u64>),
+    /// 设置排队时的优先级，越大越先开始，对已经开始下载的任务没有影响
+    ///
+    /// `boost`为是否加速该任务：排队时排在所有未加速的任务之前，
+    /// 下载时在全局限速器中使用更大的份额，见[`Throttle`]。
+    ///
+    /// [`Throttle`]: crate::app::throttle::Throttle
+    SetPriority {
+        priority: u8,
+        boost: bool,
+    },
+}
+
+/// 用户指定的保存路径已经存在文件时，用户做出的选择
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// 覆盖已有的文件
+    Overwrite,
+    /// 使用自动添加后缀的文件名
+    Rename,
+    /// 取消该任务
+    #[default]
+    Cancel,
 }
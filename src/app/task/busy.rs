@@ -0,0 +1,159 @@
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::time::{self, Instant};
+
+use crate::app::task::TaskState;
+
+/// Windows中文件被其他进程以不允许共享的方式打开
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+/// Windows中要访问的区域被其他进程锁定
+#[cfg(windows)]
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// 文件暂时被其他程序占用，稍后重试可能成功
+///
+/// Windows中杀毒软件或者索引服务经常在文件刚创建时短暂地独占打开它，此时写入会失败并返回
+/// `ERROR_SHARING_VIOLATION`或者`ERROR_LOCK_VIOLATION`。其他平台中对应的是`EBUSY`和`ETXTBSY`。
+pub fn is_file_busy(e: &io::Error) -> bool {
+    #[cfg(windows)]
+    if matches!(
+        e.raw_os_error(),
+        Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION)
+    ) {
+        return true;
+    }
+    matches!(
+        e.kind(),
+        io::ErrorKind::ResourceBusy | io::ErrorKind::ExecutableFileBusy
+    )
+}
+
+/// 文件被占用时的重试策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusyRetry {
+    /// 写入时最多重试的次数
+    pub write_attempts: u32,
+    /// 打开文件时最多等待的时间
+    pub open_timeout: Duration,
+    /// 第一次重试前等待的时间，之后每次翻倍，不超过`max_backoff`
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for BusyRetry {
+    fn default() -> Self {
+        BusyRetry {
+            write_attempts: 5,
+            open_timeout: Duration::from_secs(2),
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(800),
+        }
+    }
+}
+
+impl BusyRetry {
+    /// 第`attempt`次（从0开始）重试前等待的时间
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_backoff)
+    }
+
+    /// 打开文件，文件被占用时在`open_timeout`内不断重试
+    ///
+    /// 重试期间`state`中的[`TaskState::file_busy`]为`true`，用于在状态栏中显示提示。
+    pub async fn open(
+        &self,
+        options: &OpenOptions,
+        path: &Path,
+        state: &Mutex<TaskState>,
+    ) -> io::Result<File> {
+        let deadline = Instant::now() + self.open_timeout;
+        let mut attempt = 0;
+        loop {
+            match options.open(path).await {
+                Err(e) if is_file_busy(&e) && Instant::now() < deadline => {
+                    log::info!(target:"Task", "{} is busy, retrying: {}", path.display(), e);
+                    set_busy(state, true);
+                    time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => {
+                    set_busy(state, false);
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// 与[`AsyncWriteExt::write_all`]相同，文件被占用时最多重试`write_attempts`次
+    ///
+    /// 每次只重试还没有写入的部分，因此不会重复写入数据。
+    pub async fn write_all(
+        &self,
+        writer: &mut (impl AsyncWrite + Unpin),
+        mut data: &[u8],
+        state: &Mutex<TaskState>,
+    ) -> io::Result<()> {
+        let mut attempt = 0;
+        while !data.is_empty() {
+            match writer.write(data).await {
+                Ok(0) => {
+                    set_busy(state, false);
+                    return Err(io::Error::from(io::ErrorKind::WriteZero));
+                }
+                Ok(n) => {
+                    data = &data[n..];
+                    if attempt > 0 {
+                        set_busy(state, false);
+                        attempt = 0;
+                    }
+                }
+                Err(e) if is_file_busy(&e) && attempt < self.write_attempts => {
+                    log::info!(target:"Task", "File is busy, retrying write: {}", e);
+                    set_busy(state, true);
+                    time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    set_busy(state, false);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 与[`AsyncWriteExt::flush`]相同，文件被占用时最多重试`write_attempts`次
+    pub async fn flush(
+        &self,
+        writer: &mut (impl AsyncWrite + Unpin),
+        state: &Mutex<TaskState>,
+    ) -> io::Result<()> {
+        let mut attempt = 0;
+        loop {
+            match writer.flush().await {
+                Err(e) if is_file_busy(&e) && attempt < self.write_attempts => {
+                    log::info!(target:"Task", "File is busy, retrying flush: {}", e);
+                    set_busy(state, true);
+                    time::sleep(self.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => {
+                    set_busy(state, false);
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+fn set_busy(state: &Mutex<TaskState>, busy: bool) {
+    state.lock().unwrap().file_busy = busy;
+}
@@ -0,0 +1,98 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::app::task::naming;
+
+/// 下载完成的文件在`completed_dir`中的副本，见配置中的`completed_dir`
+///
+/// 其他程序可以只监视该目录，不会看到还没有下载完成的文件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletedCopy {
+    /// 与下载的文件在同一个文件系统中，创建了硬链接
+    Linked(PathBuf),
+    /// 无法创建硬链接（例如跨文件系统），复制了整个文件
+    Copied(PathBuf),
+    /// 链接和复制都失败，下载本身仍然视为成功
+    Failed(String),
+}
+
+impl CompletedCopy {
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 副本的路径，失败时为[`None`]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            CompletedCopy::Linked(path) | CompletedCopy::Copied(path) => Some(path),
+            CompletedCopy::Failed(_) => None,
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self, CompletedCopy::Failed(_))
+    }
+}
+
+/// 复制时每次读写的大小，同时也是更新进度的间隔
+const COPY_CHUNK: usize = 1024 * 1024;
+
+/// 将`source`硬链接到`dir`中，无法链接时复制，期间使用0.0~1.0的进度调用`progress`
+///
+/// 该函数会阻塞，需要在`spawn_blocking`中调用。`dir`不存在时自动创建，
+/// 文件名重复时与下载时一样添加`(1)`等后缀。复制失败时删除不完整的文件。
+pub fn publish(source: &Path, dir: &Path, progress: impl FnMut(f64)) -> CompletedCopy {
+    match link_or_copy(source, dir, progress) {
+        Ok(copy) => copy,
+        Err(e) => CompletedCopy::Failed(e.to_string()),
+    }
+}
+
+fn link_or_copy(source: &Path, dir: &Path, progress: impl FnMut(f64)) -> io::Result<CompletedCopy> {
+    let filename = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "source has no file name"))?;
+    fs::create_dir_all(dir)?;
+    let dest = dir.join(naming::get_filename_no_duplicate(dir, &filename));
+    // 跨文件系统、文件系统不支持硬链接或者没有权限时都尝试复制
+    match fs::hard_link(source, &dest) {
+        Ok(()) => return Ok(CompletedCopy::Linked(dest)),
+        Err(e) => {
+            log::debug!(target:"Task", "Cannot link {} into {}: {}, copying", source.display(), dir.display(), e);
+        }
+    }
+    match copy_with_progress(source, &dest, progress) {
+        Ok(()) => Ok(CompletedCopy::Copied(dest)),
+        Err(e) => {
+            let _ = fs::remove_file(&dest);
+            Err(e)
+        }
+    }
+}
+
+fn copy_with_progress(source: &Path, dest: &Path, mut progress: impl FnMut(f64)) -> io::Result<()> {
+    let mut reader = File::open(source)?;
+    let metadata = reader.metadata()?;
+    let total = metadata.len();
+    // 不覆盖在检查文件名之后出现的同名文件
+    let mut writer = OpenOptions::new().write(true).create_new(true).open(dest)?;
+    let mut buf = vec![0; COPY_CHUNK];
+    let mut copied = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+        progress(if total == 0 {
+            1.0
+        } else {
+            copied as f64 / total as f64
+        });
+    }
+    writer.sync_all()?;
+    fs::set_permissions(dest, metadata.permissions())
+}
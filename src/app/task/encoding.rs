@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+use reqwest::header::{self, HeaderValue};
+
+/// 获取校验和文件等元数据时的超时时间，这些请求很小，慢的时候宁可放弃
+pub const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 下载文件本身使用的客户端设置，总是请求未经压缩（`identity`）的内容
+///
+/// 写入文件的字节、`Content-Length`以及断点续传的`Range`都以原始文件为准，
+/// 因此不能让服务器压缩，也不能让reqwest解压。读取文件大小的HEAD请求同样使用该设置，
+/// 否则得到的可能是压缩之后的大小。
+pub fn transfer_client(builder: ClientBuilder) -> ClientBuilder {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        header::ACCEPT_ENCODING,
+        HeaderValue::from_static("identity"),
+    );
+    builder
+        .default_headers(headers)
+        .no_gzip()
+        .no_brotli()
+        .no_zstd()
+        .no_deflate()
+}
+
+/// 获取校验和文件等很小的元数据时使用的客户端设置
+///
+/// 请求`br, zstd, gzip`压缩并由reqwest解压，同时使用较短的超时时间，见[`METADATA_TIMEOUT`]。
+/// 这些响应只用于读取内容，不关心原始的长度。
+pub fn metadata_client(builder: ClientBuilder) -> ClientBuilder {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        header::ACCEPT_ENCODING,
+        HeaderValue::from_static("br, zstd, gzip"),
+    );
+    builder
+        .default_headers(headers)
+        .brotli(true)
+        .zstd(true)
+        .gzip(true)
+        .timeout(METADATA_TIMEOUT)
+}
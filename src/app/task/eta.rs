@@ -0,0 +1,385 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::window::common;
+
+/// 根据速度的采样估计剩余时间，由[`TaskState::ui_update`]在每次更新速度时调用
+///
+/// 速度使用指数移动平均（EMA）平滑，同时以相同的权重估计速度的方差，
+/// 每次采样的权重由采样间隔和配置中的半衰期决定，见[`EtaEstimator::weight`]。
+/// 速度波动较大（变异系数较高）时，给出的剩余时间是一个范围或者近似值，
+/// 而不是一个看起来很精确但是不停跳动的值，见[`Eta`]。
+///
+/// [`TaskState::ui_update`]: crate::app::task::TaskState::ui_update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EtaEstimator {
+    mean: f64,
+    variance: f64,
+    samples: u32,
+}
+
+impl EtaEstimator {
+    // ------------------- CONSTANT -----------------------
+
+    /// 默认的半衰期，采样间隔为500ms时新的采样的权重约为0.3
+    pub const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(1);
+    /// 采样太少时不给出估计
+    pub const MIN_SAMPLES: u32 = 3;
+    /// 变异系数低于该值时显示精确的剩余时间
+    pub const STABLE_CV: f64 = 0.25;
+    /// 变异系数低于该值时显示近似值，否则显示范围
+    pub const ROUGH_CV: f64 = 0.5;
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 平滑后的速度（B/s）
+    pub fn speed(&self) -> f64 {
+        self.mean
+    }
+
+    /// 平滑后的速度，还没有采样时为[`None`]
+    pub fn smoothed_speed(&self) -> Option<u64> {
+        (self.samples > 0).then_some(self.mean as u64)
+    }
+
+    /// 速度的变异系数（标准差/平均值），平均速度为0时为[`None`]
+    pub fn variation(&self) -> Option<f64> {
+        (self.mean > 0.0).then(|| self.variance.sqrt() / self.mean)
+    }
+
+    /// 下载剩余的`remaining`字节需要的时间
+    pub fn estimate(&self, remaining: u64) -> Option<Eta> {
+        if self.samples < Self::MIN_SAMPLES {
+            return None;
+        }
+        let cv = self.variation()?;
+        // 速度接近0时时间会超出Duration的范围，此时不给出估计
+        let time_at = |speed: f64| Duration::try_from_secs_f64(remaining as f64 / speed).ok();
+        let eta = if cv < Self::STABLE_CV {
+            Eta::Precise(time_at(self.mean)?)
+        } else if cv < Self::ROUGH_CV {
+            Eta::Approx(time_at(self.mean)?)
+        } else {
+            // 速度下限不大于0时，无法给出上限
+            let deviation = self.variance.sqrt();
+            let low = self.mean - deviation;
+            match time_at(low).filter(|_| low > 0.0) {
+                Some(high) => Eta::Range(time_at(self.mean + deviation)?, high),
+                None => Eta::Approx(time_at(self.mean)?),
+            }
+        };
+        Some(eta)
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 任务暂停或者离开下载阶段时清空记录
+    pub fn reset(&mut self) {
+        *self = EtaEstimator::default();
+    }
+
+    /// 记录一次速度采样（B/s），`elapsed`为距离上一次采样的时间
+    pub fn update(&mut self, speed: u64, elapsed: Duration, half_life: Duration) {
+        let speed = speed as f64;
+        if self.samples == 0 {
+            self.mean = speed;
+            self.variance = 0.0;
+        } else {
+            // 指数加权的方差，见Finch, "Incremental calculation of weighted mean and variance"
+            let alpha = Self::weight(elapsed, half_life);
+            let diff = speed - self.mean;
+            let incr = alpha * diff;
+            self.mean += incr;
+            self.variance = (1.0 - alpha) * (self.variance + diff * incr);
+        }
+        self.samples = self.samples.saturating_add(1);
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 间隔`elapsed`的新采样的权重，经过一个半衰期之前的采样的权重减半，
+    /// 半衰期为0时只使用最新的采样
+    pub fn weight(elapsed: Duration, half_life: Duration) -> f64 {
+        if half_life.is_zero() {
+            return 1.0;
+        }
+        1.0 - 0.5f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64())
+    }
+}
+
+/// 任务以及下载页面第一行显示的速度，在Stats页面中使用`s`切换
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedDisplay {
+    /// 最近一次采样（约500ms）的速度，适合观察限速是否生效
+    #[default]
+    Instant,
+    /// 与剩余时间使用相同的平滑后的速度，显示时带有`~`前缀
+    Smoothed,
+}
+
+impl SpeedDisplay {
+    pub fn toggle(self) -> Self {
+        match self {
+            SpeedDisplay::Instant => SpeedDisplay::Smoothed,
+            SpeedDisplay::Smoothed => SpeedDisplay::Instant,
+        }
+    }
+}
+
+impl Display for SpeedDisplay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SpeedDisplay::Instant => write!(f, "instant"),
+            SpeedDisplay::Smoothed => write!(f, "smoothed"),
+        }
+    }
+}
+
+/// 剩余时间的估计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eta {
+    /// 速度稳定，例如`3m 04s`
+    Precise(Duration),
+    /// 速度有一定波动，例如`~4 min`
+    Approx(Duration),
+    /// 速度波动较大，给出最短和最长的时间，例如`3–7 min`
+    Range(Duration, Duration),
+}
+
+impl Eta {
+    // ------------------- CONSTANT -----------------------
+
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 3600;
+}
+
+impl Display for Eta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Eta::Precise(eta) => write!(f, "{}", common::format_duration(eta)),
+            Eta::Approx(eta) => write!(f, "~{}", rough(eta)),
+            Eta::Range(low, high) => {
+                let (low, high) = (low.as_secs(), high.as_secs());
+                let minutes = |secs: u64| (secs + Eta::MINUTE / 2) / Eta::MINUTE;
+                if (Eta::MINUTE..Eta::HOUR).contains(&low)
+                    && (Eta::MINUTE..Eta::HOUR).contains(&high)
+                    && minutes(low) != minutes(high)
+                {
+                    write!(f, "{}\u{2013}{} min", minutes(low), minutes(high))
+                } else {
+                    write!(
+                        f,
+                        "{}\u{2013}{}",
+                        rough(Duration::from_secs(low)),
+                        rough(Duration::from_secs(high))
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// 整个队列中的一个任务所处的状态，见[`QueueEta::estimate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueEntry {
+    /// 已经占用名额，正在连接或者下载
+    Running,
+    /// 在等待空闲的名额
+    Queued,
+    /// 已经暂停，不会自己继续，不计入剩余时间
+    Paused,
+}
+
+/// 下载完整个队列（正在进行以及排队中的任务）需要的时间，显示在下载页面的第一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueEta {
+    pub remaining: Duration,
+    /// 有任务的大小未知，实际需要的时间只会更长
+    pub lower_bound: bool,
+}
+
+impl QueueEta {
+    // -------------------- FUNCTION -----------------------
+
+    /// 根据每个任务剩余的字节数（未知时为[`None`]）以及平滑后的总速度`speed`（B/s）估计剩余时间
+    ///
+    /// 基本的估计为剩余字节数之和除以总速度。排队的任务多于同时进行的上限`max_tasks`时，
+    /// 队列分批开始，最后一批任务结束时只剩下少数任务在下载，总速度也随之下降。此时假设每个名额
+    /// 分到相同的速度，按顺序将排队的任务交给最先空闲的名额，以最后一个名额空闲的时间作为估计。
+    ///
+    /// 没有未暂停的任务、总速度为0或者已知的剩余字节数为0时返回[`None`]。
+    pub fn estimate(
+        tasks: &[(Option<u64>, QueueEntry)],
+        speed: f64,
+        max_tasks: usize,
+    ) -> Option<Self> {
+        if speed <= 0.0 {
+            return None;
+        }
+        let pending: Vec<_> = tasks
+            .iter()
+            .filter(|(_, entry)| *entry != QueueEntry::Paused)
+            .collect();
+        let known: u64 = pending.iter().filter_map(|(remaining, _)| *remaining).sum();
+        if known == 0 {
+            return None;
+        }
+        let lower_bound = pending.iter().any(|(remaining, _)| remaining.is_none());
+        let queued = pending
+            .iter()
+            .filter(|(_, entry)| *entry == QueueEntry::Queued)
+            .count();
+
+        let secs = if max_tasks > 0 && queued > max_tasks {
+            // 正在进行的任务先占用名额，排队的任务按顺序交给最先空闲的名额
+            let mut slots = vec![0u64; max_tasks];
+            let running = pending.iter().filter(|(_, e)| *e == QueueEntry::Running);
+            let waiting = pending.iter().filter(|(_, e)| *e == QueueEntry::Queued);
+            for (remaining, _) in running.chain(waiting) {
+                let slot = slots.iter_mut().min().unwrap();
+                *slot += remaining.unwrap_or(0);
+            }
+            let busiest = slots.into_iter().max().unwrap_or(0);
+            busiest as f64 / (speed / max_tasks as f64)
+        } else {
+            known as f64 / speed
+        };
+        Duration::try_from_secs_f64(secs)
+            .ok()
+            .map(|remaining| QueueEta {
+                remaining,
+                lower_bound,
+            })
+    }
+}
+
+impl Display for QueueEta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let prefix = if self.lower_bound { ">" } else { "~" };
+        write!(f, "{}{}", prefix, rough(self.remaining))
+    }
+}
+
+/// 不显示秒数的时长，例如`45s`、`4 min`、`1h 05m`
+fn rough(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < Eta::MINUTE {
+        format!("{}s", secs)
+    } else if secs < Eta::HOUR {
+        format!("{} min", (secs + Eta::MINUTE / 2) / Eta::MINUTE)
+    } else {
+        format!(
+            "{}h {:02}m",
+            secs / Eta::HOUR,
+            secs % Eta::HOUR / Eta::MINUTE
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn estimator(speeds: &[u64]) -> EtaEstimator {
+        let mut estimator = EtaEstimator::default();
+        for &speed in speeds {
+            estimator.update(speed, secs(1), EtaEstimator::DEFAULT_HALF_LIFE);
+        }
+        estimator
+    }
+
+    #[test]
+    fn weight_halves_every_half_life() {
+        assert_eq!(EtaEstimator::weight(secs(1), Duration::ZERO), 1.0);
+        assert_eq!(EtaEstimator::weight(Duration::ZERO, secs(1)), 0.0);
+        assert!((EtaEstimator::weight(secs(1), secs(1)) - 0.5).abs() < 1e-9);
+        assert!((EtaEstimator::weight(secs(2), secs(1)) - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stable_speed_gives_a_precise_estimate() {
+        assert_eq!(estimator(&[1000, 1000]).estimate(10_000), None);
+        let stable = estimator(&[1000; 5]);
+        assert_eq!(stable.smoothed_speed(), Some(1000));
+        assert_eq!(stable.estimate(10_000), Some(Eta::Precise(secs(10))));
+        assert_eq!(estimator(&[0; 5]).estimate(10_000), None);
+    }
+
+    #[test]
+    fn unstable_speed_gives_a_range() {
+        let unstable = estimator(&[100, 1900, 100, 1900]);
+        assert!(unstable.variation().unwrap() > EtaEstimator::ROUGH_CV);
+        match unstable.estimate(100_000) {
+            Some(Eta::Range(low, high)) => assert!(low < high),
+            eta => panic!("expected a range, got {:?}", eta),
+        }
+        // 速度下限不大于0时只给出近似值
+        let falling = estimator(&[100, 1900, 100]);
+        assert!(matches!(falling.estimate(100_000), Some(Eta::Approx(_))));
+
+        let mut reset = unstable;
+        reset.reset();
+        assert_eq!(reset.smoothed_speed(), None);
+    }
+
+    #[test]
+    fn display_rounds_to_minutes() {
+        assert_eq!(Eta::Approx(secs(45)).to_string(), "~45s");
+        assert_eq!(Eta::Approx(secs(230)).to_string(), "~4 min");
+        assert_eq!(Eta::Approx(secs(3900)).to_string(), "~1h 05m");
+        assert_eq!(
+            Eta::Range(secs(180), secs(420)).to_string(),
+            "3\u{2013}7 min"
+        );
+        assert_eq!(
+            Eta::Range(secs(30), secs(90)).to_string(),
+            "30s\u{2013}2 min"
+        );
+    }
+
+    #[test]
+    fn queue_eta_skips_paused_tasks() {
+        let tasks = [
+            (Some(1000), QueueEntry::Running),
+            (Some(1000), QueueEntry::Queued),
+            (None, QueueEntry::Paused),
+        ];
+        let eta = QueueEta::estimate(&tasks, 100.0, 4).unwrap();
+        assert_eq!(eta.remaining, secs(20));
+        assert!(!eta.lower_bound);
+        assert_eq!(eta.to_string(), "~20s");
+
+        let unknown = [
+            (Some(1000), QueueEntry::Running),
+            (None, QueueEntry::Queued),
+        ];
+        let eta = QueueEta::estimate(&unknown, 100.0, 4).unwrap();
+        assert!(eta.lower_bound);
+        assert_eq!(eta.to_string(), ">10s");
+
+        assert_eq!(QueueEta::estimate(&tasks, 0.0, 4), None);
+        assert_eq!(
+            QueueEta::estimate(&[(None, QueueEntry::Running)], 100.0, 4),
+            None
+        );
+    }
+
+    #[test]
+    fn queue_eta_accounts_for_the_last_batch() {
+        let mut tasks = vec![(Some(1000), QueueEntry::Running); 2];
+        tasks.extend([(Some(1000), QueueEntry::Queued); 3]);
+        // 最后一批只有一个任务，只能使用一个名额的速度
+        let eta = QueueEta::estimate(&tasks, 100.0, 2).unwrap();
+        assert_eq!(eta.remaining, secs(60));
+        // 不限制并发时所有任务同时进行
+        let eta = QueueEta::estimate(&tasks, 100.0, 0).unwrap();
+        assert_eq!(eta.remaining, secs(50));
+    }
+}
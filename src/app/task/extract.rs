@@ -0,0 +1,448 @@
+use std::cell::Cell;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use flate2::read::GzDecoder;
+
+/// 支持解压的压缩包格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// 根据扩展名判断压缩包格式，不支持的格式返回[`None`]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+
+    fn extension_len(self, name: &str) -> usize {
+        let lower = name.to_ascii_lowercase();
+        match self {
+            ArchiveKind::Zip => ".zip".len(),
+            ArchiveKind::Tar => ".tar".len(),
+            ArchiveKind::TarGz if lower.ends_with(".tgz") => ".tgz".len(),
+            ArchiveKind::TarGz => ".tar.gz".len(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(io::Error),
+    /// 压缩包本身损坏或者格式不正确
+    Malformed(String),
+    /// 压缩包中的路径会写到目标目录之外，例如`../etc/passwd`
+    UnsafePath(String),
+}
+
+impl Display for ExtractError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Io(e) => write!(f, "{}", e),
+            ExtractError::Malformed(e) => write!(f, "Malformed archive: {}", e),
+            ExtractError::UnsafePath(path) => write!(f, "Unsafe path in archive: {}", path),
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+impl From<io::Error> for ExtractError {
+    fn from(e: io::Error) -> Self {
+        ExtractError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ExtractError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => ExtractError::Io(e),
+            e => ExtractError::Malformed(e.to_string()),
+        }
+    }
+}
+
+/// 解压的目标目录：与压缩包同级，以去掉扩展名的压缩包名称命名，已经存在时添加数字后缀
+pub fn extraction_dir(archive: &Path, kind: ArchiveKind) -> PathBuf {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = &name[..name.len().saturating_sub(kind.extension_len(&name))];
+    let stem = if stem.is_empty() { "extracted" } else { stem };
+    let parent = archive.parent().unwrap_or(Path::new("."));
+    let mut dir = parent.join(stem);
+    let mut n = 1;
+    while dir.exists() {
+        dir = parent.join(format!("{} ({})", stem, n));
+        n += 1;
+    }
+    dir
+}
+
+/// 将`archive`解压到`dest`，期间使用0.0~1.0的进度调用`progress`
+///
+/// 该函数会阻塞，需要在`spawn_blocking`中调用。任何条目的路径包含`..`或者为绝对路径时，
+/// 整个解压失败。失败时删除已经解压的内容。
+pub fn extract_archive(
+    archive: &Path,
+    dest: &Path,
+    kind: ArchiveKind,
+    progress: impl FnMut(f64),
+) -> Result<(), ExtractError> {
+    fs::create_dir_all(dest)?;
+    let result = match kind {
+        ArchiveKind::Zip => extract_zip(archive, dest, progress),
+        ArchiveKind::Tar => extract_tar(archive, dest, false, progress),
+        ArchiveKind::TarGz => extract_tar(archive, dest, true, progress),
+    };
+    if result.is_err() {
+        let _ = fs::remove_dir_all(dest);
+    }
+    result
+}
+
+fn extract_zip(
+    archive: &Path,
+    dest: &Path,
+    mut progress: impl FnMut(f64),
+) -> Result<(), ExtractError> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    let total = zip.len();
+    // 先检查所有条目，避免写入一部分之后才发现不安全的路径
+    for i in 0..total {
+        let entry = zip.by_index_raw(i)?;
+        if entry.enclosed_name().is_none() {
+            return Err(ExtractError::UnsafePath(entry.name().to_string()));
+        }
+    }
+    for i in 0..total {
+        let mut entry = zip.by_index(i)?;
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(ExtractError::UnsafePath(entry.name().to_string()));
+        };
+        let path = dest.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+        }
+        progress((i + 1) as f64 / total as f64);
+    }
+    Ok(())
+}
+
+fn extract_tar(
+    archive: &Path,
+    dest: &Path,
+    gzip: bool,
+    mut progress: impl FnMut(f64),
+) -> Result<(), ExtractError> {
+    let file = File::open(archive)?;
+    let total = file.metadata()?.len();
+    let (reader, read) = CountingReader::new(BufReader::new(file));
+    let reader: Box<dyn Read> = if gzip {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !is_safe_relative(&path) || path.components().any(|c| c == Component::ParentDir) {
+            return Err(ExtractError::UnsafePath(path.display().to_string()));
+        }
+        // 链接的目标同样可能指向目标目录之外，硬链接的目标相对于压缩包的根目录，
+        // 符号链接的目标相对于链接所在的目录
+        let base = if entry.header().entry_type().is_hard_link() {
+            Path::new("")
+        } else {
+            path.parent().unwrap_or(Path::new(""))
+        };
+        if let Some(target) = entry.link_name()?
+            && !is_safe_relative(&base.join(&target))
+        {
+            return Err(ExtractError::UnsafePath(format!(
+                "{} -> {}",
+                path.display(),
+                target.display()
+            )));
+        }
+        entry.unpack_in(dest)?;
+        if total > 0 {
+            progress((read.get() as f64 / total as f64).min(1.0));
+        }
+    }
+    progress(1.0);
+    Ok(())
+}
+
+/// 只包含普通路径段的相对路径，`..`不会超出起点
+fn is_safe_relative(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+/// 记录已经读取的字节数，用于根据压缩包的读取位置估计进度
+struct CountingReader<R> {
+    inner: R,
+    read: Rc<Cell<u64>>,
+}
+
+impl<R> CountingReader<R> {
+    fn new(inner: R) -> (Self, Rc<Cell<u64>>) {
+        let read = Rc::new(Cell::new(0));
+        (
+            CountingReader {
+                inner,
+                read: read.clone(),
+            },
+            read,
+        )
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.set(self.read.get() + n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    use super::*;
+
+    /// 每个测试使用各自的目录，压缩包在`dir`中，解压到`dir/out`
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "request-tui-extract-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_zip(path: &Path, build: impl FnOnce(&mut ZipWriter<File>)) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        build(&mut zip);
+        zip.finish().unwrap();
+    }
+
+    fn zip_file(zip: &mut ZipWriter<File>, name: &str, content: &[u8]) {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content).unwrap();
+    }
+
+    /// 直接写入头部中的路径，`tar::Builder`会拒绝包含`..`的路径
+    fn tar_entry(
+        name: &[u8],
+        kind: tar::EntryType,
+        link: Option<&str>,
+        content: &[u8],
+    ) -> (tar::Header, Vec<u8>) {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_entry_type(kind);
+        header.set_mode(0o644);
+        header.set_size(content.len() as u64);
+        if let Some(link) = link {
+            header.set_link_name(link).unwrap();
+        }
+        header.set_cksum();
+        (header, content.to_vec())
+    }
+
+    fn write_tar(path: &Path, entries: Vec<(tar::Header, Vec<u8>)>) {
+        let mut tar = tar::Builder::new(File::create(path).unwrap());
+        for (header, content) in entries {
+            tar.append(&header, content.as_slice()).unwrap();
+        }
+        tar.finish().unwrap();
+    }
+
+    fn assert_rejected(dir: &Path, archive: &Path, kind: ArchiveKind) {
+        let dest = dir.join("out");
+        let result = extract_archive(archive, &dest, kind, |_| {});
+        assert!(
+            matches!(result, Err(ExtractError::UnsafePath(_))),
+            "{:?}",
+            result
+        );
+        // 失败时删除已经解压的内容，目标目录之外也没有写入任何文件
+        assert!(!dest.exists());
+        assert!(!dir.join("evil.txt").exists());
+    }
+
+    #[test]
+    fn safe_archives_are_extracted() {
+        let dir = test_dir("safe");
+        let archive = dir.join("safe.zip");
+        write_zip(&archive, |zip| {
+            zip_file(zip, "a.txt", b"a");
+            zip_file(zip, "sub/b.txt", b"b");
+        });
+        let dest = dir.join("out");
+        let mut last = 0.0;
+        extract_archive(&archive, &dest, ArchiveKind::Zip, |p| last = p).unwrap();
+        assert_eq!(fs::read(dest.join("sub/b.txt")).unwrap(), b"b");
+        assert_eq!(last, 1.0);
+
+        let archive = dir.join("safe.tar");
+        write_tar(
+            &archive,
+            vec![
+                tar_entry(b"a.txt", tar::EntryType::Regular, None, b"a"),
+                tar_entry(b"sub/link", tar::EntryType::Symlink, Some("../a.txt"), b""),
+            ],
+        );
+        let dest = dir.join("out-tar");
+        extract_archive(&archive, &dest, ArchiveKind::Tar, |_| {}).unwrap();
+        assert_eq!(fs::read(dest.join("sub/link")).unwrap(), b"a");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zip_entries_outside_the_target_are_rejected() {
+        let dir = test_dir("zip");
+        for (name, entry) in [("parent", "../evil.txt"), ("absolute", "/tmp/evil.txt")] {
+            let archive = dir.join(format!("{}.zip", name));
+            // 不安全的条目排在后面，也不会先写入前面的条目
+            write_zip(&archive, |zip| {
+                zip_file(zip, "ok.txt", b"ok");
+                zip_file(zip, entry, b"evil");
+            });
+            assert_rejected(&dir, &archive, ArchiveKind::Zip);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zip_symlinks_are_extracted_as_plain_files() {
+        let dir = test_dir("zip-symlink");
+        let archive = dir.join("link.zip");
+        write_zip(&archive, |zip| {
+            zip.add_symlink("link", "../../evil.txt", SimpleFileOptions::default())
+                .unwrap();
+        });
+        let dest = dir.join("out");
+        extract_archive(&archive, &dest, ArchiveKind::Zip, |_| {}).unwrap();
+        let metadata = fs::symlink_metadata(dest.join("link")).unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(fs::read(dest.join("link")).unwrap(), b"../../evil.txt");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tar_entries_outside_the_target_are_rejected() {
+        let dir = test_dir("tar");
+        let ok = || tar_entry(b"ok.txt", tar::EntryType::Regular, None, b"ok");
+        let cases = [
+            (
+                "parent",
+                tar_entry(b"../evil.txt", tar::EntryType::Regular, None, b"evil"),
+            ),
+            (
+                "nested",
+                tar_entry(b"a/../../evil.txt", tar::EntryType::Regular, None, b"evil"),
+            ),
+            (
+                "absolute",
+                tar_entry(b"/tmp/evil.txt", tar::EntryType::Regular, None, b"evil"),
+            ),
+            (
+                "symlink",
+                tar_entry(
+                    b"sub/link",
+                    tar::EntryType::Symlink,
+                    Some("../../evil.txt"),
+                    b"",
+                ),
+            ),
+            (
+                "absolute-symlink",
+                tar_entry(b"link", tar::EntryType::Symlink, Some("/etc/passwd"), b""),
+            ),
+            (
+                "hardlink",
+                tar_entry(b"sub/link", tar::EntryType::Link, Some("../evil.txt"), b""),
+            ),
+        ];
+        for (name, entry) in cases {
+            let archive = dir.join(format!("{}.tar", name));
+            write_tar(&archive, vec![ok(), entry]);
+            assert_rejected(&dir, &archive, ArchiveKind::Tar);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gzipped_tar_is_checked_as_well() {
+        let dir = test_dir("tar-gz");
+        let tar = dir.join("evil.tar");
+        write_tar(
+            &tar,
+            vec![tar_entry(
+                b"../evil.txt",
+                tar::EntryType::Regular,
+                None,
+                b"evil",
+            )],
+        );
+        let archive = dir.join("evil.tar.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&archive).unwrap(), Default::default());
+        encoder.write_all(&fs::read(&tar).unwrap()).unwrap();
+        encoder.finish().unwrap();
+        assert_rejected(&dir, &archive, ArchiveKind::TarGz);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_archives_are_malformed() {
+        let dir = test_dir("corrupt");
+        let archive = dir.join("corrupt.zip");
+        fs::write(&archive, b"not a zip file").unwrap();
+        let result = extract_archive(&archive, &dir.join("out"), ArchiveKind::Zip, |_| {});
+        assert!(
+            matches!(result, Err(ExtractError::Malformed(_))),
+            "{:?}",
+            result
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::app::task::StageKind;
+
+/// 文件操作失败的常见原因，从[`io::Error`]中分类得到，用于给出具体的解决建议
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileErrorKind {
+    PermissionDenied,
+    /// 创建文件时为所在目录不存在，打开已有文件时为文件不存在
+    NotFound,
+    NameTooLong,
+    ReadOnlyFilesystem,
+    /// 磁盘已满或者超出配额
+    DiskFull,
+}
+
+impl FileErrorKind {
+    // ------------------- CONSTANT -----------------------
+
+    /// 大多数文件系统中文件名的最大字节数
+    pub const MAX_NAME_BYTES: usize = 255;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 优先使用[`ErrorKind`]，为`Other`等无法分类的类型时再检查操作系统的错误码
+    pub fn classify(error: &io::Error) -> Option<Self> {
+        match error.kind() {
+            ErrorKind::PermissionDenied => Some(FileErrorKind::PermissionDenied),
+            ErrorKind::NotFound => Some(FileErrorKind::NotFound),
+            ErrorKind::InvalidFilename => Some(FileErrorKind::NameTooLong),
+            ErrorKind::ReadOnlyFilesystem => Some(FileErrorKind::ReadOnlyFilesystem),
+            ErrorKind::StorageFull | ErrorKind::QuotaExceeded => Some(FileErrorKind::DiskFull),
+            _ => Self::from_os_error(error.raw_os_error()?),
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_os_error(code: i32) -> Option<Self> {
+        // EPERM、ENOENT、EACCES、EROFS、ENOSPC在各个Unix平台上相同，其余的因平台而异
+        const ENAMETOOLONG: i32 = if cfg!(target_os = "linux") { 36 } else { 63 };
+        const EDQUOT: i32 = if cfg!(target_os = "linux") { 122 } else { 69 };
+        match code {
+            1 | 13 => Some(FileErrorKind::PermissionDenied),
+            2 => Some(FileErrorKind::NotFound),
+            30 => Some(FileErrorKind::ReadOnlyFilesystem),
+            28 | EDQUOT => Some(FileErrorKind::DiskFull),
+            ENAMETOOLONG => Some(FileErrorKind::NameTooLong),
+            _ => None,
+        }
+    }
+
+    #[cfg(windows)]
+    fn from_os_error(code: i32) -> Option<Self> {
+        match code {
+            5 => Some(FileErrorKind::PermissionDenied),
+            2 | 3 => Some(FileErrorKind::NotFound),
+            19 => Some(FileErrorKind::ReadOnlyFilesystem),
+            39 | 112 => Some(FileErrorKind::DiskFull),
+            206 => Some(FileErrorKind::NameTooLong),
+            _ => None,
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn from_os_error(_code: i32) -> Option<Self> {
+        None
+    }
+}
+
+/// 文件操作失败的原因以及对应的文件，随[`TaskResult`]发送给UI线程
+///
+/// [`TaskResult`]: crate::app::task::TaskResult
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileError {
+    pub kind: FileErrorKind,
+    pub path: PathBuf,
+}
+
+impl FileError {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 无法分类的错误返回[`None`]，此时只显示原始的错误信息
+    pub fn new(error: &io::Error, path: &Path) -> Option<Self> {
+        Some(FileError {
+            kind: FileErrorKind::classify(error)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// 从`anyhow`包装的错误中找到[`io::Error`]
+    pub fn from_anyhow(error: &anyhow::Error, path: &Path) -> Option<Self> {
+        let error = error.chain().find_map(|e| e.downcast_ref::<io::Error>())?;
+        Self::new(error, path)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 给用户的建议，见[`file_error_hint`]
+    pub fn hint(&self, stage: StageKind) -> String {
+        file_error_hint(self.kind, stage, &self.path)
+    }
+}
+
+/// 根据失败的原因和阶段生成一句解决建议，例如`check permissions on /mnt/usb`
+///
+/// `stage`用于区分[`FileErrorKind::NotFound`]：创建文件时是目录不存在，
+/// 恢复下载时是已经下载的部分被移动或者删除了。
+pub fn file_error_hint(kind: FileErrorKind, stage: StageKind, path: &Path) -> String {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    match kind {
+        FileErrorKind::PermissionDenied => {
+            format!("check permissions on {}", dir.display())
+        }
+        FileErrorKind::NotFound if stage == StageKind::FailToCreateFile => format!(
+            "{} does not exist, create it or choose another folder",
+            dir.display()
+        ),
+        FileErrorKind::NotFound => {
+            format!("{} was moved or deleted, download it again", path.display())
+        }
+        FileErrorKind::NameTooLong => {
+            let name_len = path.file_name().map_or(0, |name| name.len());
+            if name_len > FileErrorKind::MAX_NAME_BYTES {
+                format!(
+                    "filename is {} bytes, exceeding {} \u{2014} use Save as to pick a shorter name",
+                    name_len,
+                    FileErrorKind::MAX_NAME_BYTES
+                )
+            } else {
+                String::from("the path is too long \u{2014} use Save as or a shorter folder")
+            }
+        }
+        FileErrorKind::ReadOnlyFilesystem => format!(
+            "{} is on a read-only filesystem, choose another folder",
+            dir.display()
+        ),
+        FileErrorKind::DiskFull => format!(
+            "free up space on {} or choose another folder",
+            dir.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_by_error_kind() {
+        let kind = |kind: ErrorKind| FileErrorKind::classify(&io::Error::from(kind));
+        assert_eq!(
+            kind(ErrorKind::PermissionDenied),
+            Some(FileErrorKind::PermissionDenied)
+        );
+        assert_eq!(kind(ErrorKind::NotFound), Some(FileErrorKind::NotFound));
+        assert_eq!(kind(ErrorKind::StorageFull), Some(FileErrorKind::DiskFull));
+        assert_eq!(
+            kind(ErrorKind::QuotaExceeded),
+            Some(FileErrorKind::DiskFull)
+        );
+        assert_eq!(kind(ErrorKind::Interrupted), None);
+        assert_eq!(FileErrorKind::classify(&io::Error::other("busy")), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn classify_by_os_error_code() {
+        let code = |code: i32| FileErrorKind::classify(&io::Error::from_raw_os_error(code));
+        assert_eq!(code(13), Some(FileErrorKind::PermissionDenied));
+        assert_eq!(code(28), Some(FileErrorKind::DiskFull));
+        assert_eq!(code(30), Some(FileErrorKind::ReadOnlyFilesystem));
+        assert_eq!(code(36), Some(FileErrorKind::NameTooLong));
+        assert_eq!(code(122), Some(FileErrorKind::DiskFull));
+        // EBADF
+        assert_eq!(code(9), None);
+    }
+
+    #[test]
+    fn find_io_error_inside_anyhow() {
+        let path = Path::new("/mnt/usb/a.iso");
+        let error = anyhow::Error::new(io::Error::from(ErrorKind::PermissionDenied))
+            .context("Failed to open the file");
+        assert_eq!(
+            FileError::from_anyhow(&error, path),
+            Some(FileError {
+                kind: FileErrorKind::PermissionDenied,
+                path: path.to_path_buf(),
+            })
+        );
+        assert_eq!(
+            FileError::from_anyhow(&anyhow::anyhow!("no io error"), path),
+            None
+        );
+    }
+
+    #[test]
+    fn hints_name_the_directory_or_file() {
+        let path = Path::new("/mnt/usb/a.iso");
+        let hint = |kind| file_error_hint(kind, StageKind::FailToResumeFile, path);
+        assert_eq!(
+            hint(FileErrorKind::PermissionDenied),
+            "check permissions on /mnt/usb"
+        );
+        assert_eq!(
+            hint(FileErrorKind::NotFound),
+            "/mnt/usb/a.iso was moved or deleted, download it again"
+        );
+        assert_eq!(
+            file_error_hint(FileErrorKind::NotFound, StageKind::FailToCreateFile, path),
+            "/mnt/usb does not exist, create it or choose another folder"
+        );
+        assert_eq!(
+            file_error_hint(
+                FileErrorKind::DiskFull,
+                StageKind::FailToDownload,
+                Path::new("a.iso")
+            ),
+            "free up space on . or choose another folder"
+        );
+    }
+
+    #[test]
+    fn name_too_long_hint_reports_the_length() {
+        let long = PathBuf::from("/tmp").join("a".repeat(300));
+        assert_eq!(
+            file_error_hint(
+                FileErrorKind::NameTooLong,
+                StageKind::FailToCreateFile,
+                &long
+            ),
+            "filename is 300 bytes, exceeding 255 \u{2014} use Save as to pick a shorter name"
+        );
+        assert_eq!(
+            file_error_hint(
+                FileErrorKind::NameTooLong,
+                StageKind::FailToCreateFile,
+                Path::new("/tmp/a")
+            ),
+            "the path is too long \u{2014} use Save as or a shorter folder"
+        );
+    }
+}
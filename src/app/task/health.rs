@@ -0,0 +1,268 @@
+use std::time::{Duration, Instant};
+
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// 正在下载的任务的健康状况，显示为速度之前的彩色圆点
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskHealth {
+    #[default]
+    Healthy,
+    /// 速度持续低于该任务最近的峰值速度的一定比例
+    Slow,
+    /// 一段时间内没有收到任何数据
+    Stalled,
+}
+
+impl TaskHealth {
+    pub fn color(self) -> Color {
+        match self {
+            TaskHealth::Healthy => Color::Green,
+            TaskHealth::Slow => Color::Yellow,
+            TaskHealth::Stalled => Color::Red,
+        }
+    }
+}
+
+/// 判断任务健康状况的阈值
+///
+/// 配置示例：
+///
+/// ```toml
+/// [task_health]
+/// slow_percent = 20
+/// slow_after_secs = 30
+/// stall_after_secs = 10
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthThresholds {
+    /// 速度低于最近峰值的百分比时视为慢速
+    pub slow_percent: u8,
+    /// 慢速持续超过该时间（秒）后才显示为慢速，避免速度短暂波动时闪烁
+    pub slow_after_secs: u64,
+    /// 超过该时间（秒）没有收到数据时视为停滞
+    pub stall_after_secs: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            slow_percent: 20,
+            slow_after_secs: 30,
+            stall_after_secs: 10,
+        }
+    }
+}
+
+/// 根据速度的采样判断任务的健康状况，由[`TaskState::ui_update`]在每次更新速度时调用
+///
+/// 峰值速度只保留[`HealthTracker::PEAK_WINDOW`]，之后使用当前的速度重新计算，
+/// 这样服务器整体变慢之后不会一直显示为慢速。
+///
+/// [`TaskState::ui_update`]: crate::app::task::TaskState::ui_update
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HealthTracker {
+    // 最近的峰值速度以及记录的时间
+    peak: Option<(u64, Instant)>,
+    // 最近一次收到数据的时间
+    last_progress: Option<Instant>,
+    // 速度开始低于阈值的时间
+    slow_since: Option<Instant>,
+    health: Option<TaskHealth>,
+}
+
+impl HealthTracker {
+    // ------------------- CONSTANT -----------------------
+
+    pub const PEAK_WINDOW: Duration = Duration::from_secs(120);
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 当前的健康状况，任务没有在接收数据时为[`None`]
+    pub fn health(&self) -> Option<TaskHealth> {
+        self.health
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 任务暂停或者离开下载阶段时清空记录，恢复之后重新开始计算
+    pub fn reset(&mut self) {
+        *self = HealthTracker::default();
+    }
+
+    /// 记录一次速度采样，`received`为距离上次采样收到的字节数，`speed`为对应的速度（B/s）
+    pub fn update(
+        &mut self,
+        now: Instant,
+        received: u64,
+        speed: u64,
+        thresholds: &HealthThresholds,
+    ) -> TaskHealth {
+        let last_progress = *self.last_progress.get_or_insert(now);
+        if received > 0 {
+            self.last_progress = Some(now);
+        }
+
+        let peak = match self.peak {
+            Some((peak, at)) if peak >= speed && now.duration_since(at) < Self::PEAK_WINDOW => peak,
+            _ => {
+                self.peak = Some((speed, now));
+                speed
+            }
+        };
+        let slow = speed.saturating_mul(100) < peak.saturating_mul(thresholds.slow_percent as u64);
+        self.slow_since = if slow {
+            Some(self.slow_since.unwrap_or(now))
+        } else {
+            None
+        };
+
+        let health = classify(
+            received > 0,
+            now.duration_since(last_progress),
+            self.slow_since.map(|since| now.duration_since(since)),
+            thresholds,
+        );
+        self.health = Some(health);
+        health
+    }
+}
+
+/// 根据距离上次收到数据的时间以及慢速持续的时间判断健康状况
+pub fn classify(
+    received: bool,
+    since_progress: Duration,
+    slow_for: Option<Duration>,
+    thresholds: &HealthThresholds,
+) -> TaskHealth {
+    if !received && since_progress > Duration::from_secs(thresholds.stall_after_secs) {
+        TaskHealth::Stalled
+    } else if slow_for.is_some_and(|d| d > Duration::from_secs(thresholds.slow_after_secs)) {
+        TaskHealth::Slow
+    } else {
+        TaskHealth::Healthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn classify_prefers_stalled_over_slow() {
+        let thresholds = HealthThresholds::default();
+        assert_eq!(
+            classify(true, secs(0), None, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            classify(false, secs(10), None, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            classify(false, secs(11), None, &thresholds),
+            TaskHealth::Stalled
+        );
+        // 收到了数据就不算停滞
+        assert_eq!(
+            classify(true, secs(60), None, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            classify(true, secs(0), Some(secs(30)), &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            classify(true, secs(0), Some(secs(31)), &thresholds),
+            TaskHealth::Slow
+        );
+        assert_eq!(
+            classify(false, secs(11), Some(secs(31)), &thresholds),
+            TaskHealth::Stalled
+        );
+    }
+
+    #[test]
+    fn tracker_reports_stall_and_recovery() {
+        let thresholds = HealthThresholds::default();
+        let start = Instant::now();
+        let mut tracker = HealthTracker::default();
+        assert_eq!(tracker.health(), None);
+        assert_eq!(
+            tracker.update(start, 1000, 1000, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            tracker.update(start + secs(10), 0, 0, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            tracker.update(start + secs(11), 0, 0, &thresholds),
+            TaskHealth::Stalled
+        );
+        assert_eq!(
+            tracker.update(start + secs(12), 1000, 1000, &thresholds),
+            TaskHealth::Healthy
+        );
+
+        tracker.reset();
+        assert_eq!(tracker.health(), None);
+    }
+
+    #[test]
+    fn tracker_reports_slow_only_after_it_persists() {
+        let thresholds = HealthThresholds::default();
+        let start = Instant::now();
+        let mut tracker = HealthTracker::default();
+        tracker.update(start, 1000, 1000, &thresholds);
+        // 峰值的20%以下
+        for t in 1..=31 {
+            assert_eq!(
+                tracker.update(start + secs(t), 100, 100, &thresholds),
+                TaskHealth::Healthy
+            );
+        }
+        assert_eq!(
+            tracker.update(start + secs(32), 100, 100, &thresholds),
+            TaskHealth::Slow
+        );
+        // 恢复到阈值以上之后重新计时
+        assert_eq!(
+            tracker.update(start + secs(33), 300, 300, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            tracker.update(start + secs(34), 100, 100, &thresholds),
+            TaskHealth::Healthy
+        );
+    }
+
+    #[test]
+    fn old_peak_expires() {
+        let thresholds = HealthThresholds {
+            slow_after_secs: 0,
+            ..HealthThresholds::default()
+        };
+        let start = Instant::now();
+        let mut tracker = HealthTracker::default();
+        tracker.update(start, 1000, 1000, &thresholds);
+        assert_eq!(
+            tracker.update(start + secs(1), 100, 100, &thresholds),
+            TaskHealth::Healthy
+        );
+        assert_eq!(
+            tracker.update(start + secs(2), 100, 100, &thresholds),
+            TaskHealth::Slow
+        );
+        let expired = start + HealthTracker::PEAK_WINDOW;
+        assert_eq!(
+            tracker.update(expired, 100, 100, &thresholds),
+            TaskHealth::Healthy
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// 写入文件的耗时统计，用于判断下载速度是否受限于磁盘
+///
+/// 下载目录位于网络文件系统等较慢的设备上时，即使网络正常，下载也会因为写入阻塞而停顿。
+/// 平均值基于所有写入计算，百分位数只基于最近[`WriteLatency::WINDOW`]次写入，
+/// 最近的写入中超过一半都慢于[`WriteLatency::SLOW_WRITE`]时视为受限于磁盘。
+#[derive(Debug, Clone, Default)]
+pub struct WriteLatency {
+    recent: VecDeque<Duration>,
+    // recent中慢于SLOW_WRITE的写入次数
+    slow: usize,
+    count: u64,
+    total: Duration,
+}
+
+impl WriteLatency {
+    // ------------------- CONSTANT -----------------------
+
+    pub const WINDOW: usize = 128;
+    pub const SLOW_WRITE: Duration = Duration::from_millis(200);
+    /// 写入次数太少时不做判断，避免刚开始下载时的偶然停顿
+    pub const MIN_SAMPLES: usize = 8;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 所有写入的平均耗时，还没有写入时为[`None`]
+    pub fn average(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.total.div_f64(self.count as f64))
+    }
+
+    /// 最近的写入中第`p`百分位（0.0~100.0）的耗时，使用最近秩法
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    /// 最近的写入是否持续较慢
+    pub fn is_disk_bound(&self) -> bool {
+        self.recent.len() >= Self::MIN_SAMPLES && self.slow * 2 > self.recent.len()
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn record(&mut self, elapsed: Duration) {
+        if self.recent.len() == Self::WINDOW
+            && let Some(oldest) = self.recent.pop_front()
+            && oldest >= Self::SLOW_WRITE
+        {
+            self.slow -= 1;
+        }
+        if elapsed >= Self::SLOW_WRITE {
+            self.slow += 1;
+        }
+        self.recent.push_back(elapsed);
+        self.count += 1;
+        self.total += elapsed;
+    }
+}
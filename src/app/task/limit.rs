@@ -0,0 +1,905 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::app::task::{TaskPhase, TaskState};
+
+/// 有空闲的名额时，优先级相同的排队任务之间的顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdmissionPolicy {
+    /// 先排队的任务先开始
+    #[default]
+    Fifo,
+    /// 文件小的任务先开始
+    SmallestFirst,
+    /// 文件大的任务先开始
+    LargestFirst,
+}
+
+impl AdmissionPolicy {
+    /// Fifo -> SmallestFirst -> LargestFirst -> Fifo ...
+    pub fn next(self) -> Self {
+        match self {
+            AdmissionPolicy::Fifo => AdmissionPolicy::SmallestFirst,
+            AdmissionPolicy::SmallestFirst => AdmissionPolicy::LargestFirst,
+            AdmissionPolicy::LargestFirst => AdmissionPolicy::Fifo,
+        }
+    }
+
+    /// 排序用的键，越小越先开始，大小未知的任务排在大小已知的任务之后
+    fn sort_key(self, size: Option<u64>) -> u64 {
+        match (self, size) {
+            (AdmissionPolicy::Fifo, _) | (_, None) => u64::MAX,
+            (AdmissionPolicy::SmallestFirst, Some(size)) => size.min(u64::MAX - 1),
+            (AdmissionPolicy::LargestFirst, Some(size)) => u64::MAX - 1 - size.min(u64::MAX - 1),
+        }
+    }
+}
+
+impl Display for AdmissionPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AdmissionPolicy::Fifo => write!(f, "FIFO"),
+            AdmissionPolicy::SmallestFirst => write!(f, "smallest first"),
+            AdmissionPolicy::LargestFirst => write!(f, "largest first"),
+        }
+    }
+}
+
+/// 排队中的任务在名额即将空出时提前做的准备，见配置中的`warm_up`
+///
+/// 有任务即将完成时（见[`SlotRequest::warm_up_due`]），排在队列最前面的几个任务提前解析域名，
+/// 或者使用之后下载时相同的客户端发送HEAD请求建立连接，开始下载时可以直接复用该连接，
+/// 省去DNS查询以及TLS握手的时间。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmUp {
+    #[default]
+    Off,
+    /// 只解析域名
+    Dns,
+    /// 解析域名并且建立连接
+    Connect,
+}
+
+/// 任务在并发名额中的位置，显示在文件名的右侧
+///
+/// 名额编号在任务开始时分配最小的空闲编号，任务结束之前不变。队列中的位置与开始的顺序一致，
+/// 即同样考虑优先级以及[`AdmissionPolicy`]，队列变化时随之更新。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotBadge {
+    /// 占用的名额编号，从1开始
+    Running(usize),
+    /// 在队列中的位置，从1开始
+    Queued(usize),
+}
+
+impl Display for SlotBadge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SlotBadge::Running(slot) => write!(f, "[{}]", slot),
+            SlotBadge::Queued(position) => write!(f, "[queued #{}]", position),
+        }
+    }
+}
+
+/// 名额的占用情况，显示在下载列表的第一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotOccupancy {
+    pub busy: usize,
+    /// 为0时不限制
+    pub max: usize,
+    pub queued: usize,
+}
+
+impl Display for SlotOccupancy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.max {
+            0 => write!(f, "{} running, {} queued", self.busy, self.queued),
+            max => write!(
+                f,
+                "{}/{} slots busy, {} queued",
+                self.busy, max, self.queued
+            ),
+        }
+    }
+}
+
+/// 限制同时进行的任务数量，包括全局的上限以及每个主机的上限
+///
+/// 对同一个服务器同时发起太多连接容易被限流，而不同主机之间的任务可以同时进行，
+/// 因此在全局上限之外，每个主机还有各自的上限。
+///
+/// 主机根据任务提交时的URL确定，重定向到其他主机的任务仍然计入原来的主机。
+/// 无法确定主机的任务只受全局上限的限制。上限为0时表示不限制。
+///
+/// 有空闲的名额时，优先级最高的任务先开始，优先级相同时按照[`AdmissionPolicy`]排序，
+/// 仍然相同时先排队的任务先开始。所在主机已满的任务不会阻挡其他主机的任务。
+///
+/// 该结构体由UI线程和[`TaskManager`]共享，UI线程可以随时修改排序方式，
+/// 只影响还在排队的任务。
+///
+/// [`TaskManager`]: crate::app::task::TaskManager
+#[derive(Debug)]
+pub struct ConnectionLimits {
+    inner: Arc<Mutex<LimitsInner>>,
+    notify: Arc<Notify>,
+}
+
+#[derive(Debug)]
+struct LimitsInner {
+    max_tasks: usize,
+    max_per_host: usize,
+    policy: AdmissionPolicy,
+    running: usize,
+    hosts: HashMap<String, usize>,
+    waiting: Vec<Waiter>,
+    next_seq: u64,
+    // 正在进行的任务的状态，用于判断是否有名额即将空出
+    states: HashMap<u64, Arc<Mutex<TaskState>>>,
+    // 正在进行的任务占用的名额编号，见[`SlotBadge`]
+    slots: HashMap<u64, usize>,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    seq: u64,
+    host: Option<String>,
+    priority: u8,
+    boosted: bool,
+    // 文件（剩余部分）的大小，排队期间通过HEAD请求得到
+    size: Option<u64>,
+    state: Arc<Mutex<TaskState>>,
+    // 已经提前建立了连接（或者解析了域名），占用该主机的一个名额
+    warmed: bool,
+    // 重新排队时仍然占用所在主机的名额，见[`SlotPermit::park`]
+    host_held: bool,
+}
+
+impl LimitsInner {
+    fn has_room(&self, host: Option<&str>) -> bool {
+        let global = self.max_tasks == 0 || self.running < self.max_tasks;
+        let host = match host {
+            Some(host) if self.max_per_host > 0 => {
+                self.hosts.get(host).copied().unwrap_or(0) < self.max_per_host
+            }
+            _ => true,
+        };
+        global && host
+    }
+
+    /// 排队的顺序，越小越先开始，加速的任务排在最前面
+    fn admission_key(
+        &self,
+        waiter: &Waiter,
+    ) -> (std::cmp::Reverse<bool>, std::cmp::Reverse<u8>, u64, u64) {
+        (
+            std::cmp::Reverse(waiter.boosted),
+            std::cmp::Reverse(waiter.priority),
+            self.policy.sort_key(waiter.size),
+            waiter.seq,
+        )
+    }
+
+    /// 当前可以开始的任务中排在最前面的一个，仍然占用主机名额的任务只需要全局的名额
+    fn next_admitted(&self) -> Option<u64> {
+        self.waiting
+            .iter()
+            .filter(|waiter| {
+                let host = waiter.host.as_deref().filter(|_| !waiter.host_held);
+                self.has_room(host)
+            })
+            .min_by_key(|waiter| self.admission_key(waiter))
+            .map(|waiter| waiter.seq)
+    }
+
+    /// 全局的名额空出之后最先开始的几个任务，所在主机的名额需要能容纳提前建立的连接
+    fn warm_up_candidates(&self) -> Vec<u64> {
+        let mut order: Vec<&Waiter> = self.waiting.iter().collect();
+        order.sort_by_key(|waiter| self.admission_key(waiter));
+        // 已经提前建立的连接同样计入主机的名额
+        let mut hosts = self.hosts.clone();
+        for waiter in self.waiting.iter().filter(|w| w.warmed) {
+            if let Some(host) = &waiter.host {
+                *hosts.entry(host.clone()).or_default() += 1;
+            }
+        }
+        let mut candidates = Vec::new();
+        for waiter in order {
+            if candidates.len() == ConnectionLimits::WARM_UP_AHEAD {
+                break;
+            }
+            match &waiter.host {
+                _ if waiter.warmed || waiter.host_held => {}
+                Some(host) if self.max_per_host > 0 => {
+                    let count = hosts.entry(host.clone()).or_default();
+                    if *count >= self.max_per_host {
+                        continue;
+                    }
+                    *count += 1;
+                }
+                _ => {}
+            }
+            candidates.push(waiter.seq);
+        }
+        candidates
+    }
+
+    fn remove_waiter(&mut self, seq: u64) -> Option<Waiter> {
+        let index = self.waiting.iter().position(|waiter| waiter.seq == seq)?;
+        Some(self.waiting.remove(index))
+    }
+
+    /// 归还全局的名额，返回该任务的状态
+    fn release_slot(&mut self, seq: u64) -> Option<Arc<Mutex<TaskState>>> {
+        self.running -= 1;
+        self.slots.remove(&seq);
+        self.states.remove(&seq)
+    }
+
+    fn release_host(&mut self, host: &str) {
+        if let Some(count) = self.hosts.get_mut(host) {
+            *count -= 1;
+            if *count == 0 {
+                self.hosts.remove(host);
+            }
+        }
+    }
+
+    /// 最小的没有被占用的名额编号
+    fn free_slot(&self) -> usize {
+        (1..)
+            .find(|slot| !self.slots.values().any(|used| used == slot))
+            .unwrap_or(1)
+    }
+
+    /// 所有任务当前的位置
+    fn badges(&self) -> Vec<(Arc<Mutex<TaskState>>, Option<SlotBadge>)> {
+        let mut order: Vec<&Waiter> = self.waiting.iter().collect();
+        order.sort_by_key(|waiter| self.admission_key(waiter));
+        let running = self.states.iter().map(|(seq, state)| {
+            let badge = self.slots.get(seq).copied().map(SlotBadge::Running);
+            (state.clone(), badge)
+        });
+        let queued = order
+            .into_iter()
+            .enumerate()
+            .map(|(index, waiter)| (waiter.state.clone(), Some(SlotBadge::Queued(index + 1))));
+        running.chain(queued).collect()
+    }
+}
+
+/// 释放锁之后将所有任务的位置写入各自的状态，`left`为刚刚离开队列或者结束的任务
+///
+/// 渲染时UI线程会锁住任务的状态，这里不在持有`inner`的同时锁住任务的状态。
+fn publish_badges(inner: MutexGuard<'_, LimitsInner>, left: Option<Arc<Mutex<TaskState>>>) {
+    let mut badges = inner.badges();
+    drop(inner);
+    badges.extend(left.map(|state| (state, None)));
+    for (state, badge) in badges {
+        state.lock().unwrap().slot = badge;
+    }
+}
+
+/// 正在排队的任务，drop时离开队列
+#[derive(Debug)]
+pub struct SlotRequest {
+    seq: u64,
+    inner: Arc<Mutex<LimitsInner>>,
+    notify: Arc<Notify>,
+}
+
+/// 任务执行期间持有的许可，drop时归还
+///
+/// 任务等待用户做出决定时使用[`SlotPermit::release`]或者[`SlotPermit::park`]提前归还，
+/// 让排队的任务先开始，之后通过[`SlotPermit::requeue`]重新排队。
+#[derive(Debug)]
+pub struct SlotPermit {
+    seq: u64,
+    host: Option<String>,
+    state: Arc<Mutex<TaskState>>,
+    // 已经归还全局的名额时为false，drop时不再重复归还
+    held: bool,
+    // 仍然占用所在主机的名额，见[`SlotPermit::park`]
+    host_held: bool,
+    inner: Arc<Mutex<LimitsInner>>,
+    notify: Arc<Notify>,
+}
+
+impl ConnectionLimits {
+    // ------------------- CONSTANT -----------------------
+
+    /// 最多提前准备的排队任务数量
+    pub const WARM_UP_AHEAD: usize = 2;
+    /// 正在进行的任务完成了这个比例之后，认为名额即将空出
+    pub const NEARLY_DONE: f64 = 0.95;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(max_tasks: usize, max_per_host: usize, policy: AdmissionPolicy) -> Self {
+        ConnectionLimits {
+            inner: Arc::new(Mutex::new(LimitsInner {
+                max_tasks,
+                max_per_host,
+                policy,
+                running: 0,
+                hosts: HashMap::new(),
+                waiting: Vec::new(),
+                next_seq: 0,
+                states: HashMap::new(),
+                slots: HashMap::new(),
+            })),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(0, 0, AdmissionPolicy::Fifo)
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn policy(&self) -> AdmissionPolicy {
+        self.inner.lock().unwrap().policy
+    }
+
+    pub fn occupancy(&self) -> SlotOccupancy {
+        let inner = self.inner.lock().unwrap();
+        SlotOccupancy {
+            busy: inner.running,
+            max: inner.max_tasks,
+            queued: inner.waiting.len(),
+        }
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 同时进行的任务数量上限以及同一主机的上限，为0时不限制，已经开始的任务不受影响
+    pub fn set_max_tasks(&self, max_tasks: usize, max_per_host: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        if (inner.max_tasks, inner.max_per_host) == (max_tasks, max_per_host) {
+            return;
+        }
+        log::info!(
+            target:"Task",
+            "Task limits changed to {} in total, {} per host",
+            max_tasks,
+            max_per_host
+        );
+        inner.max_tasks = max_tasks;
+        inner.max_per_host = max_per_host;
+        self.notify.notify_waiters();
+    }
+
+    pub fn set_policy(&self, policy: AdmissionPolicy) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.policy = policy;
+        log::info!(target:"Task", "Queue admission policy changed to {}", policy);
+        self.notify.notify_waiters();
+        publish_badges(inner, None);
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 以`priority`的优先级加入队列，使用[`SlotRequest::wait`]等待空闲的名额，
+    /// `boosted`的任务排在其他任务之前
+    ///
+    /// `state`为任务的状态，开始之后用于判断任务是否即将完成。
+    pub fn request(
+        &self,
+        host: Option<&str>,
+        priority: u8,
+        boosted: bool,
+        state: Arc<Mutex<TaskState>>,
+    ) -> SlotRequest {
+        let mut inner = self.inner.lock().unwrap();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        inner.waiting.push(Waiter {
+            seq,
+            host: host.map(|host| host.to_ascii_lowercase()),
+            priority,
+            boosted,
+            size: None,
+            state,
+            warmed: false,
+            host_held: false,
+        });
+        publish_badges(inner, None);
+        SlotRequest {
+            seq,
+            inner: self.inner.clone(),
+            notify: self.notify.clone(),
+        }
+    }
+}
+
+impl SlotRequest {
+    // -------------------- MODIFIER -----------------------
+
+    /// 修改排队中的任务的优先级以及是否加速，已经开始的任务不受影响
+    pub fn set_priority(&self, priority: u8, boosted: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(waiter) = inner.waiting.iter_mut().find(|w| w.seq == self.seq) {
+            waiter.priority = priority;
+            waiter.boosted = boosted;
+            self.notify.notify_waiters();
+            publish_badges(inner, None);
+        }
+    }
+
+    /// 设置文件的大小，用于[`AdmissionPolicy`]排序
+    pub fn set_size(&self, size: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(waiter) = inner.waiting.iter_mut().find(|w| w.seq == self.seq) {
+            waiter.size = Some(size);
+            self.notify.notify_waiters();
+            publish_badges(inner, None);
+        }
+    }
+
+    /// 记录已经提前准备过，之后不会再次准备
+    pub fn mark_warmed(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(waiter) = inner.waiting.iter_mut().find(|w| w.seq == self.seq) {
+            waiter.warmed = true;
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 是否应该提前准备连接：全局的名额已满，有正在下载的任务完成了[`ConnectionLimits::NEARLY_DONE`]，
+    /// 并且该任务是名额空出之后最先开始的[`ConnectionLimits::WARM_UP_AHEAD`]个任务之一
+    ///
+    /// 全局名额未满时任务只是在等待所在主机的名额，此时提前建立连接会超出主机的上限。
+    pub fn warm_up_due(&self) -> bool {
+        let states: Vec<_> = {
+            let inner = self.inner.lock().unwrap();
+            let global_full = inner.max_tasks > 0 && inner.running >= inner.max_tasks;
+            if !global_full || !inner.warm_up_candidates().contains(&self.seq) {
+                return false;
+            }
+            inner.states.values().cloned().collect()
+        };
+        states.iter().any(|state| {
+            let state = state.lock().unwrap();
+            state.phase() == TaskPhase::Downloading
+                && state.content_length().is_some_and(|len| {
+                    len > 0
+                        && state.downloaded() as f64 >= len as f64 * ConnectionLimits::NEARLY_DONE
+                })
+        })
+    }
+
+    /// 等待直到该主机以及全局都有空闲的名额，并且没有可以开始的、排在更前面的任务
+    pub async fn wait(&self) -> SlotPermit {
+        loop {
+            // 先注册再检查，避免错过检查之后、等待之前发出的通知
+            let notified = self.notify.notified();
+            let mut notified = std::pin::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if inner.next_admitted() == Some(self.seq)
+                    && let Some(waiter) = inner.remove_waiter(self.seq)
+                {
+                    inner.running += 1;
+                    if let Some(host) = &waiter.host
+                        && !waiter.host_held
+                    {
+                        *inner.hosts.entry(host.clone()).or_default() += 1;
+                    }
+                    inner.states.insert(self.seq, waiter.state.clone());
+                    let slot = inner.free_slot();
+                    inner.slots.insert(self.seq, slot);
+                    // 其他主机的任务可能也可以开始了
+                    self.notify.notify_waiters();
+                    publish_badges(inner, None);
+                    return SlotPermit {
+                        seq: self.seq,
+                        host_held: waiter.host.is_some(),
+                        host: waiter.host,
+                        state: waiter.state,
+                        held: true,
+                        inner: self.inner.clone(),
+                        notify: self.notify.clone(),
+                    };
+                }
+            } // MutexGuard drop here
+            notified.await;
+        }
+    }
+}
+
+impl Drop for SlotRequest {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(waiter) = inner.remove_waiter(self.seq) {
+            if let Some(host) = waiter.host.as_deref().filter(|_| waiter.host_held) {
+                inner.release_host(host);
+            }
+            // 排在后面的任务可能因此可以开始
+            self.notify.notify_waiters();
+            publish_badges(inner, Some(waiter.state));
+        }
+    }
+}
+
+impl SlotPermit {
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 任务所在的主机（小写），无法确定主机时为[`None`]
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 归还全局以及所在主机的名额，已经归还过时不做任何事
+    pub fn release(&mut self) {
+        let held = std::mem::replace(&mut self.held, false);
+        let host_held = std::mem::replace(&mut self.host_held, false);
+        if !held && !host_held {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let state = if held {
+            inner.release_slot(self.seq)
+        } else {
+            None
+        };
+        if let Some(host) = self.host.as_deref().filter(|_| host_held) {
+            inner.release_host(host);
+        }
+        self.notify.notify_waiters();
+        publish_badges(inner, state);
+    }
+
+    /// 只归还全局的名额，所在主机的名额保留到[`SlotPermit::release`]或者重新开始之后结束
+    ///
+    /// 用于保持响应打开等待用户做出决定的任务：与服务器的连接仍然存在，继续计入主机的上限，
+    /// 其他主机的任务可以先开始。
+    pub fn park(&mut self) {
+        if !std::mem::replace(&mut self.held, false) {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let state = inner.release_slot(self.seq);
+        self.notify.notify_waiters();
+        publish_badges(inner, state);
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 归还全局的名额（如果还没有归还）之后以原来的顺序重新排队，使用[`SlotRequest::wait`]等待新的许可
+    ///
+    /// 优先级相同时，排在之后才加入队列的任务前面，用户做出决定之后不需要从队尾重新开始等待。
+    /// 通过[`SlotPermit::park`]保留的主机名额转交给新的请求，重新开始时只需要全局的名额。
+    pub fn requeue(&mut self, priority: u8, boosted: bool) -> SlotRequest {
+        self.park();
+        let host_held = std::mem::replace(&mut self.host_held, false);
+        let mut inner = self.inner.lock().unwrap();
+        inner.waiting.push(Waiter {
+            seq: self.seq,
+            host: self.host.clone(),
+            priority,
+            boosted,
+            size: None,
+            state: self.state.clone(),
+            warmed: false,
+            host_held,
+        });
+        publish_badges(inner, None);
+        SlotRequest {
+            seq: self.seq,
+            inner: self.inner.clone(),
+            notify: self.notify.clone(),
+        }
+    }
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::FutureExt;
+
+    use super::*;
+
+    fn state() -> Arc<Mutex<TaskState>> {
+        Arc::new(Mutex::new(TaskState::new()))
+    }
+
+    fn queue(limits: &ConnectionLimits, host: Option<&str>, priority: u8) -> SlotRequest {
+        limits.request(host, priority, false, state())
+    }
+
+    /// 已经可以开始时立即返回许可，否则返回[`None`]
+    fn try_admit(request: &SlotRequest) -> Option<SlotPermit> {
+        request.wait().now_or_never()
+    }
+
+    /// 全局只有一个名额时排队的任务依次开始的顺序，每次只能有一个任务可以开始
+    fn admission_order<'a>(requests: &[(&'a str, &SlotRequest)]) -> Vec<&'a str> {
+        let mut order = Vec::new();
+        while order.len() < requests.len() {
+            let admitted: Vec<_> = requests
+                .iter()
+                .filter(|(name, _)| !order.contains(name))
+                .filter_map(|(name, request)| Some((*name, try_admit(request)?)))
+                .collect();
+            assert_eq!(admitted.len(), 1, "admitted {:?} at once", admitted);
+            order.push(admitted[0].0);
+        }
+        order
+    }
+
+    #[test]
+    fn global_limit_is_respected() {
+        let limits = ConnectionLimits::new(2, 0, AdmissionPolicy::Fifo);
+        let (a, b, c) = (
+            queue(&limits, None, 0),
+            queue(&limits, Some("a.com"), 0),
+            queue(&limits, Some("b.com"), 0),
+        );
+        let first = try_admit(&a).unwrap();
+        let _second = try_admit(&b).unwrap();
+        assert!(try_admit(&c).is_none());
+        assert_eq!(
+            limits.occupancy(),
+            SlotOccupancy {
+                busy: 2,
+                max: 2,
+                queued: 1
+            }
+        );
+
+        drop(first);
+        assert!(try_admit(&c).is_some());
+    }
+
+    #[test]
+    fn per_host_limit_does_not_block_other_hosts() {
+        let limits = ConnectionLimits::new(0, 1, AdmissionPolicy::Fifo);
+        let a1 = queue(&limits, Some("a.com"), 0);
+        // 主机名不区分大小写
+        let a2 = queue(&limits, Some("A.COM"), 0);
+        let b = queue(&limits, Some("b.com"), 0);
+        let unknown = queue(&limits, None, 0);
+
+        let mut permit = try_admit(&a1).unwrap();
+        assert_eq!(permit.host(), Some("a.com"));
+        assert!(try_admit(&a2).is_none());
+        assert!(try_admit(&b).is_some());
+        assert!(try_admit(&unknown).is_some());
+
+        permit.release();
+        assert!(!permit.is_held());
+        assert!(try_admit(&a2).is_some());
+    }
+
+    #[test]
+    fn boosted_then_higher_priority_then_fifo() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let blocker = try_admit(&queue(&limits, None, 0)).unwrap();
+        let low = queue(&limits, None, 0);
+        let low_later = queue(&limits, None, 0);
+        let high = queue(&limits, None, 5);
+        let boosted = limits.request(None, 0, true, state());
+        assert!(try_admit(&boosted).is_none());
+
+        drop(blocker);
+        assert_eq!(
+            admission_order(&[
+                ("low_later", &low_later),
+                ("low", &low),
+                ("high", &high),
+                ("boosted", &boosted),
+            ]),
+            ["boosted", "high", "low", "low_later"]
+        );
+    }
+
+    #[test]
+    fn priority_changes_and_policy_reorder_the_queue() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::SmallestFirst);
+        let blocker = try_admit(&queue(&limits, None, 0)).unwrap();
+        let (unknown, large, small, raised) = (
+            queue(&limits, None, 0),
+            queue(&limits, None, 0),
+            queue(&limits, None, 0),
+            queue(&limits, None, 0),
+        );
+        large.set_size(1 << 30);
+        small.set_size(1 << 10);
+        raised.set_priority(1, false);
+
+        limits.set_policy(AdmissionPolicy::LargestFirst);
+        drop(blocker);
+        assert_eq!(
+            admission_order(&[
+                ("unknown", &unknown),
+                ("large", &large),
+                ("small", &small),
+                ("raised", &raised),
+            ]),
+            // 大小未知的任务排在大小已知的任务之后
+            ["raised", "large", "small", "unknown"]
+        );
+    }
+
+    #[test]
+    fn queued_badges_follow_the_admission_order() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let blocker_state = state();
+        let blocker = limits.request(None, 0, false, blocker_state.clone());
+        let _permit = try_admit(&blocker).unwrap();
+        let (first, second) = (state(), state());
+        let _first = limits.request(None, 0, false, first.clone());
+        let _second = limits.request(None, 3, false, second.clone());
+
+        assert_eq!(
+            blocker_state.lock().unwrap().slot,
+            Some(SlotBadge::Running(1))
+        );
+        assert_eq!(second.lock().unwrap().slot, Some(SlotBadge::Queued(1)));
+        assert_eq!(first.lock().unwrap().slot, Some(SlotBadge::Queued(2)));
+    }
+
+    #[test]
+    fn requeue_keeps_the_original_place() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let mut permit = try_admit(&queue(&limits, None, 0)).unwrap();
+        let later = queue(&limits, None, 0);
+
+        let requeued = permit.requeue(0, false);
+        assert!(!permit.is_held());
+        assert_eq!(limits.occupancy().busy, 0);
+        // 排在之后才加入队列的任务前面
+        let again = try_admit(&requeued).unwrap();
+        assert!(try_admit(&later).is_none());
+
+        // 已经归还的许可drop时不会再次归还
+        drop(permit);
+        assert_eq!(limits.occupancy().busy, 1);
+        drop(again);
+        assert!(try_admit(&later).is_some());
+    }
+
+    #[test]
+    fn parked_permit_keeps_its_host_slot() {
+        let limits = ConnectionLimits::new(1, 1, AdmissionPolicy::Fifo);
+        let mut parked = try_admit(&queue(&limits, Some("a.com"), 0)).unwrap();
+        let same_host = queue(&limits, Some("a.com"), 0);
+        let other_host = queue(&limits, Some("b.com"), 0);
+
+        // 等待决定期间全局的名额可以给其他主机的任务使用，响应仍然打开，主机的名额不变
+        parked.park();
+        assert_eq!(limits.occupancy().busy, 0);
+        assert!(try_admit(&same_host).is_none());
+        let other = try_admit(&other_host).unwrap();
+
+        // 重新排队时只等待全局的名额，排在同一主机的任务前面
+        let requeued = parked.requeue(0, false);
+        drop(parked);
+        assert!(try_admit(&requeued).is_none());
+        drop(other);
+        let resumed = try_admit(&requeued).unwrap();
+        assert!(try_admit(&same_host).is_none());
+        drop(resumed);
+        assert!(try_admit(&same_host).is_some());
+    }
+
+    #[test]
+    fn dropping_a_requeued_request_returns_the_host_slot() {
+        let limits = ConnectionLimits::new(0, 1, AdmissionPolicy::Fifo);
+        let mut permit = try_admit(&queue(&limits, Some("a.com"), 0)).unwrap();
+        let waiting = queue(&limits, Some("a.com"), 0);
+        permit.park();
+        let requeued = permit.requeue(0, false);
+        assert!(try_admit(&waiting).is_none());
+
+        // 任务在重新排队期间被停止
+        drop(requeued);
+        drop(permit);
+        assert!(try_admit(&waiting).is_some());
+    }
+
+    /// 开始下载并且已经完成了`done`比例的任务
+    fn downloading(limits: &ConnectionLimits, host: &str, done: f64) -> SlotPermit {
+        let permit = try_admit(&queue(limits, Some(host), 0)).unwrap();
+        let mut state = permit.state.lock().unwrap();
+        state.phase = TaskPhase::Downloading;
+        state.content_length = Some(1000);
+        state.downloaded = (1000.0 * done) as u64;
+        drop(state);
+        permit
+    }
+
+    #[test]
+    fn warm_up_candidates_count_warmed_waiters_against_the_host() {
+        let limits = ConnectionLimits::new(1, 1, AdmissionPolicy::Fifo);
+        let _running = downloading(&limits, "c.com", 0.99);
+        let warmed = queue(&limits, Some("a.com"), 0);
+        let same_host = queue(&limits, Some("a.com"), 0);
+        let other_host = queue(&limits, Some("b.com"), 0);
+        warmed.mark_warmed();
+
+        // 已经提前建立的连接占用了a.com唯一的名额
+        let candidates = limits.inner.lock().unwrap().warm_up_candidates();
+        assert_eq!(candidates, [warmed.seq, other_host.seq]);
+        assert!(!same_host.warm_up_due());
+        assert!(other_host.warm_up_due());
+    }
+
+    #[test]
+    fn warm_up_waits_until_a_running_task_is_nearly_done() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let running = downloading(&limits, "a.com", 0.5);
+        let waiting = queue(&limits, Some("b.com"), 0);
+        assert!(!waiting.warm_up_due());
+        running.state.lock().unwrap().downloaded = 960;
+        assert!(waiting.warm_up_due());
+    }
+
+    #[test]
+    fn warm_up_is_not_due_when_only_the_host_is_full() {
+        let limits = ConnectionLimits::new(3, 1, AdmissionPolicy::Fifo);
+        let _running = downloading(&limits, "a.com", 0.99);
+        let waiting = queue(&limits, Some("a.com"), 0);
+        // 全局还有名额，任务只是在等待a.com，提前建立连接会超出主机的上限
+        assert!(try_admit(&waiting).is_none());
+        assert!(!waiting.warm_up_due());
+
+        // 全局的名额已满时，所在主机已满的任务同样不会提前准备
+        limits.set_max_tasks(1, 1);
+        assert!(!waiting.warm_up_due());
+    }
+
+    #[tokio::test]
+    async fn waiting_task_starts_when_a_slot_is_returned() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let permit = try_admit(&queue(&limits, Some("a.com"), 0)).unwrap();
+        let waiting = queue(&limits, Some("b.com"), 0);
+        let handle = tokio::spawn(async move { waiting.wait().await.host().map(str::to_string) });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!handle.is_finished());
+        drop(permit);
+        let host = tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("queued task was not admitted")
+            .unwrap();
+        assert_eq!(host.as_deref(), Some("b.com"));
+    }
+
+    #[tokio::test]
+    async fn raising_the_limit_wakes_queued_tasks() {
+        let limits = ConnectionLimits::new(1, 0, AdmissionPolicy::Fifo);
+        let _permit = try_admit(&queue(&limits, None, 0)).unwrap();
+        let waiting = queue(&limits, None, 0);
+        let handle = tokio::spawn(async move { waiting.wait().await });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        limits.set_max_tasks(2, 0);
+        let _admitted = tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("queued task was not admitted")
+            .unwrap();
+        assert_eq!(limits.occupancy().busy, 2);
+    }
+}
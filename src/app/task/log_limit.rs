@@ -0,0 +1,51 @@
+use std::time::{Duration, Instant};
+
+/// 限制高频日志的输出频率，用于下载循环中每块数据都可能触发的日志
+///
+/// 开启Trace级别的日志时，逐块输出的日志每秒可能有上万条，因此这类日志需要先调用
+/// [`LogLimiter::check`]，距离上次输出不足`interval`时跳过。任务的开始、阶段变化、
+/// 结束以及错误等生命周期日志不应该经过限制。
+#[derive(Debug)]
+pub struct LogLimiter {
+    interval: Duration,
+    last: Option<Instant>,
+    suppressed: u64,
+}
+
+impl LogLimiter {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(interval: Duration) -> Self {
+        LogLimiter {
+            interval,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 可以输出时返回自上次输出以来跳过的条数，否则返回[`None`]
+    pub fn check(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            self.suppressed += 1;
+            return None;
+        }
+        self.last = Some(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+impl Default for LogLimiter {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_INTERVAL)
+    }
+}
@@ -1,6 +1,18 @@
-use tokio::{runtime::Runtime, sync::mpsc};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
 
-use crate::app::task::{Task, resolve};
+use tokio::{
+    runtime::{Handle, Runtime},
+    sync::{mpsc, watch},
+    task::JoinSet,
+};
+
+use crate::app::config::Config;
+use crate::app::task::{ConnectionLimits, Task, TaskContext, TaskEvents, TaskObserver, resolve};
+use crate::app::throttle::Throttle;
 
 /// 用于在另一个线程中管理异步任务的执行
 ///
@@ -9,28 +21,160 @@ use crate::app::task::{Task, resolve};
 ///
 /// 该线程的主要任务就是轮询mpsc通道，接收来自用户端的任务请求，由于这些任务的操作
 /// 暂时是由UI线程承担的，所以这些任务需要从UI线程发送。
-#[derive(Debug)]
+///
+/// 任务使用的设置来自`config`，UI线程修改配置之后，之后得到名额的任务使用新的设置，
+/// 见[`TaskContext::admitted`]。
 pub struct TaskManager {
     runtime: Runtime,
     receiver: mpsc::Receiver<Task>,
+    stats: Arc<RuntimeStats>,
+    throttle: Arc<Throttle>,
+    limits: Arc<ConnectionLimits>,
+    config: watch::Receiver<Arc<Config>>,
+    observers: Vec<Arc<dyn TaskObserver>>,
 }
 
 impl TaskManager {
+    // ------------------- CONSTANT -----------------------
+
+    /// 默认等待用户做出决定的时间上限，见[`TaskContext::decision_timeout`]
+    pub const DEFAULT_DECISION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
     // -------------------- CONSTRUCT ---------------------
 
-    pub fn new(runtime: Runtime, receiver: mpsc::Receiver<Task>) -> Self {
-        TaskManager { runtime, receiver }
+    pub fn new(
+        runtime: Runtime,
+        receiver: mpsc::Receiver<Task>,
+        stats: Arc<RuntimeStats>,
+        throttle: Arc<Throttle>,
+        limits: Arc<ConnectionLimits>,
+        config: watch::Receiver<Arc<Config>>,
+    ) -> Self {
+        stats
+            .workers
+            .store(runtime.metrics().num_workers(), Ordering::Relaxed);
+        TaskManager {
+            runtime,
+            receiver,
+            stats,
+            throttle,
+            limits,
+            config,
+            observers: Vec::new(),
+        }
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 注册一个观察者，需要在[`TaskManager::run`]之前调用
+    pub fn register_observer(&mut self, observer: Arc<dyn TaskObserver>) {
+        self.observers.push(observer);
     }
 
     // -------------------- RUNNING -----------------------
 
     pub fn run(&mut self) {
-        self.runtime.block_on(async {
-            while let Some(task) = self.receiver.recv().await {
-                tokio::spawn(async move {
-                    resolve::handle_task(task).await;
-                });
+        let stats = self.stats.clone();
+        let throttle = self.throttle.clone();
+        let limits = self.limits.clone();
+        let observers = std::mem::take(&mut self.observers);
+        let receiver = &mut self.receiver;
+        let config = &mut self.config;
+        self.runtime.block_on(async move {
+            let schedule = tokio::spawn({
+                let throttle = throttle.clone();
+                async move { throttle.run_schedule().await }
+            });
+            let current = config.borrow_and_update().clone();
+            let mut context =
+                TaskContext::new(throttle, limits, TaskEvents::spawn(observers), &current)
+                    .with_live_config(config.clone());
+            // UI线程退出时发送端被丢弃，之后不再等待配置的变化
+            let mut watching = true;
+            let mut tasks = JoinSet::new();
+            loop {
+                tokio::select! {
+                    task = receiver.recv() => {
+                        let Some(task) = task else {
+                            break;
+                        };
+                        stats.queued.fetch_sub(1, Ordering::Relaxed);
+                        let context = context.clone();
+                        tasks.spawn(async move {
+                            resolve::handle_task(task, &context).await;
+                        });
+                    }
+                    changed = config.changed(), if watching => match changed {
+                        Ok(()) => {
+                            log::info!(target:"Config", "Config changed, applying to newly admitted tasks");
+                            let current = config.borrow_and_update().clone();
+                            context.configure(&current);
+                        }
+                        Err(_) => watching = false,
+                    },
+                    // 回收已经完成的任务，JoinSet为空时该分支会被禁用
+                    Some(_) = tasks.join_next(), if !tasks.is_empty() => {}
+                }
+                stats.active.store(tasks.len(), Ordering::Relaxed);
             }
+            // UI线程已经退出，不再等待剩余的任务
+            tasks.detach_all();
+            schedule.abort();
         })
     }
 }
+
+/// 后台线程启动运行时之后交给UI线程的内容
+///
+/// 运行时在后台线程中创建，UI线程不需要等待运行时启动就可以绘制第一帧，
+/// 在收到该结构体之前，用户添加的任务都暂时保存在等待队列中。
+#[derive(Debug)]
+pub struct ManagerReady {
+    pub sender: mpsc::Sender<Task>,
+    pub runtime: Handle,
+}
+
+/// 后台运行时的统计信息，由UI线程和[`TaskManager`]共享，在Stats页面中显示。
+///
+/// - `workers`：运行时的工作线程数量
+/// - `active`：正在执行的任务数量
+/// - `queued`：已经发送到通道中，但还没有被[`TaskManager`]接收的任务数量
+#[derive(Debug, Default)]
+pub struct RuntimeStats {
+    workers: AtomicUsize,
+    active: AtomicUsize,
+    queued: AtomicUsize,
+}
+
+impl RuntimeStats {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn workers(&self) -> usize {
+        self.workers.load(Ordering::Relaxed)
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 在任务发送到通道之前调用，发送失败时需要调用[`RuntimeStats::cancel_queued`]
+    pub fn mark_queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cancel_queued(&self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,190 @@
+//! 测试用的HTTP服务器，只监听本机的地址，按照请求的路径返回预先给定的内容
+//!
+//! 每个连接只处理一个请求，响应之后关闭连接。收到的请求按顺序记录下来，
+//! 测试可以检查任务发送了哪些请求以及请求的范围。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 服务器上的一个文件
+#[derive(Debug, Clone)]
+pub struct MockFile {
+    body: Vec<u8>,
+    ranges: bool,
+    content_type: Option<String>,
+    delay: Option<Duration>,
+}
+
+impl MockFile {
+    /// 内容为`body`，默认支持Range请求
+    pub fn new(body: impl Into<Vec<u8>>) -> Self {
+        MockFile {
+            body: body.into(),
+            ranges: true,
+            content_type: None,
+            delay: None,
+        }
+    }
+
+    /// 忽略请求中的Range，总是返回200以及整个文件
+    pub fn without_ranges(mut self) -> Self {
+        self.ranges = false;
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// 发送响应头之后等待`delay`再发送响应体，让任务在下载阶段停留一段时间
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+/// 服务器收到的请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub range: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    /// 在当前的运行时中启动服务器，`files`为路径（例如`/a.bin`）以及对应的文件
+    pub async fn start(files: Vec<(&str, MockFile)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let files: Arc<HashMap<String, MockFile>> = Arc::new(
+            files
+                .into_iter()
+                .map(|(path, file)| (path.to_string(), file))
+                .collect(),
+        );
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (files, recorded) = (files.clone(), recorded.clone());
+                tokio::spawn(async move {
+                    let _ = serve(stream, &files, &recorded).await;
+                });
+            }
+        });
+        MockServer { addr, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 到目前为止收到的请求
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// 到目前为止收到的GET请求
+    pub fn gets(&self) -> Vec<MockRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.method == "GET")
+            .collect()
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    files: &HashMap<String, MockFile>,
+    requests: &Mutex<Vec<MockRequest>>,
+) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.ends_with(b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let range = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("range"))
+        .map(|(_, value)| value.trim().to_string());
+    requests.lock().unwrap().push(MockRequest {
+        method: method.clone(),
+        path: path.clone(),
+        range: range.clone(),
+    });
+
+    let Some(file) = files.get(&path) else {
+        let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        return stream.write_all(response.as_bytes()).await;
+    };
+    let len = file.body.len();
+    let requested = range
+        .as_deref()
+        .filter(|_| file.ranges)
+        .and_then(|range| parse_range(range, len));
+    let (status, body, content_range) = match requested {
+        Some((start, end)) => (
+            "206 Partial Content",
+            &file.body[start..=end],
+            Some(format!("bytes {}-{}/{}", start, end, len)),
+        ),
+        None => ("200 OK", &file.body[..], None),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    if file.ranges {
+        response.push_str("Accept-Ranges: bytes\r\n");
+    }
+    if let Some(content_range) = content_range {
+        response.push_str(&format!("Content-Range: {}\r\n", content_range));
+    }
+    if let Some(content_type) = &file.content_type {
+        response.push_str(&format!("Content-Type: {}\r\n", content_type));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes()).await?;
+    if method != "HEAD" {
+        if let Some(delay) = file.delay {
+            stream.flush().await?;
+            tokio::time::sleep(delay).await;
+        }
+        stream.write_all(body).await?;
+    }
+    stream.shutdown().await
+}
+
+/// 解析`bytes=a-b`、`bytes=a-`以及`bytes=-n`，返回包含两端的范围，无法满足时返回[`None`]
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.parse::<usize>().ok(), end.parse::<usize>().ok()) {
+        (Some(start), Some(end)) => (start, end.min(len.checked_sub(1)?)),
+        (Some(start), None) => (start, len.checked_sub(1)?),
+        (None, Some(last)) => (len.saturating_sub(last), len.checked_sub(1)?),
+        (None, None) => return None,
+    };
+    (start <= end).then_some((start, end))
+}
@@ -0,0 +1,231 @@
+use std::path::Path;
+
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// 无法从响应中得到文件名时使用的名称
+pub const FALLBACK_FILENAME: &str = "tmp.bin";
+
+/// 根据响应确定保存时使用的文件名，依次尝试：
+///
+/// 1. `Content-Disposition`中的文件名
+/// 2. 重定向之后最终URL路径的最后一段
+/// 3. 原始URL路径的最后一段
+/// 4. [`FALLBACK_FILENAME`]
+///
+/// 类似`https://example.com/download?id=123`的地址通常会重定向到包含真实文件名的CDN地址，
+/// 因此最终URL优先于原始URL。看起来像是随机令牌的路径段（见[`is_opaque_token`]）会被跳过。
+pub fn choose_filename(original: &Url, final_url: &Url, disposition: Option<&str>) -> String {
+    disposition
+        .and_then(disposition_filename)
+        .or_else(|| url_filename(final_url))
+        .or_else(|| url_filename(original))
+        .unwrap_or_else(|| FALLBACK_FILENAME.to_string())
+}
+
+/// FILENAME 不是 PATH !!!
+///
+/// 规则为filename.ext -> filename(1).ext -> filename(2).ext ...
+pub fn get_filename_no_duplicate(dir: &Path, filename: &str) -> String {
+    let mut path = dir.join(filename);
+    if !path.exists() {
+        return filename.to_string();
+    }
+
+    let mut count: u64 = 1;
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("file")
+        .to_string();
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+
+    loop {
+        let new_filename = if let Some(ext) = &extension {
+            format!("{}({}).{}", stem, count, ext)
+        } else {
+            format!("{}({})", stem, count)
+        };
+        path = dir.join(&new_filename);
+        if !path.exists() {
+            return new_filename;
+        }
+        count += 1;
+    }
+}
+
+/// URL路径的最后一段（已解码），为空或者像是随机令牌时返回[`None`]
+///
+/// 也用于展开命名模板中的`{name}`，见[`NameTemplate`]。
+///
+/// [`NameTemplate`]: crate::app::template::NameTemplate
+pub fn url_filename(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    let name = percent_decode_str(segment).decode_utf8_lossy();
+    safe_filename(&name).filter(|name| !is_opaque_token(name))
+}
+
+/// 解析`Content-Disposition`中的文件名，`filename*`（RFC 5987）优先于`filename`
+fn disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'percent-encoded
+                let encoded = value.splitn(3, '\'').nth(2)?;
+                extended = Some(percent_decode_str(encoded).decode_utf8_lossy().to_string());
+            }
+            "filename" => {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+                plain = Some(value.replace("\\\"", "\""));
+            }
+            _ => {}
+        }
+    }
+    extended
+        .and_then(|name| safe_filename(&name))
+        .or_else(|| plain.and_then(|name| safe_filename(&name)))
+}
+
+/// 只保留最后一个路径分隔符之后的部分，并去掉控制字符，防止服务器指定的文件名
+/// 写到下载目录之外
+fn safe_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// 没有扩展名、超过40个字符并且全部为十六进制数字的路径段，通常是下载令牌而不是文件名
+fn is_opaque_token(segment: &str) -> bool {
+    segment.len() > 40 && segment.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn url_filename_is_decoded() {
+        assert_eq!(
+            url_filename(&url("https://example.com/files/a%20b.zip")).as_deref(),
+            Some("a b.zip")
+        );
+        assert_eq!(
+            url_filename(&url("https://example.com/%E6%96%87%E4%BB%B6.txt")).as_deref(),
+            Some("文件.txt")
+        );
+    }
+
+    #[test]
+    fn url_filename_cannot_escape_the_directory() {
+        // 编码后的分隔符在解码之后被去掉，只保留最后一部分
+        assert_eq!(
+            url_filename(&url("https://example.com/..%2F..%2Fetc%2Fpasswd")).as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            url_filename(&url("https://example.com/a%5Cb%0A.txt")).as_deref(),
+            Some("b.txt")
+        );
+        assert_eq!(url_filename(&url("https://example.com/%2E%2E")), None);
+        assert_eq!(url_filename(&url("https://example.com/dir/")), None);
+    }
+
+    #[test]
+    fn opaque_tokens_are_skipped() {
+        let token = "0123456789abcdef".repeat(3);
+        let original = url(&format!("https://example.com/dl/{}", token));
+        assert_eq!(url_filename(&original), None);
+        assert_eq!(
+            choose_filename(&original, &url("https://cdn.example.com/real.iso"), None),
+            "real.iso"
+        );
+        assert_eq!(
+            choose_filename(&original, &original, None),
+            FALLBACK_FILENAME
+        );
+    }
+
+    #[test]
+    fn final_url_takes_priority_over_original() {
+        let original = url("https://example.com/download.php?id=123");
+        assert_eq!(
+            choose_filename(
+                &original,
+                &url("https://cdn.example.com/v2/setup.exe?t=1"),
+                None
+            ),
+            "setup.exe"
+        );
+        // 最终URL没有文件名时使用原始URL的
+        assert_eq!(
+            choose_filename(
+                &url("https://example.com/get/tool.tar.gz"),
+                &url("https://cdn.example.com/"),
+                None
+            ),
+            "tool.tar.gz"
+        );
+        assert_eq!(
+            choose_filename(
+                &url("https://example.com/"),
+                &url("https://example.com/"),
+                None
+            ),
+            FALLBACK_FILENAME
+        );
+    }
+
+    #[test]
+    fn duplicate_names_get_a_counter() {
+        let dir = std::env::temp_dir().join(format!("request-tui-naming-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(get_filename_no_duplicate(&dir, "a.txt"), "a.txt");
+        std::fs::write(dir.join("a.txt"), b"").unwrap();
+        std::fs::write(dir.join("a(1).txt"), b"").unwrap();
+        std::fs::write(dir.join("README"), b"").unwrap();
+        assert_eq!(get_filename_no_duplicate(&dir, "a.txt"), "a(2).txt");
+        assert_eq!(get_filename_no_duplicate(&dir, "README"), "README(1)");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disposition_takes_priority() {
+        let original = url("https://example.com/download?id=1");
+        assert_eq!(
+            choose_filename(
+                &original,
+                &original,
+                Some(r#"attachment; filename="plain.txt"; filename*=UTF-8''%E2%82%AC%20rates.txt"#)
+            ),
+            "€ rates.txt"
+        );
+        assert_eq!(
+            choose_filename(
+                &original,
+                &original,
+                Some(r#"attachment; filename="../x.txt""#)
+            ),
+            "x.txt"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use url::Url;
+
+use crate::app::task::{StageKind, TaskPhase, TaskResult, TaskState};
+
+/// 观察任务生命周期的回调，使用[`TaskManager::register_observer`]注册。
+///
+/// 回调在后台运行时中的一个单独的任务里按事件发生的顺序依次调用，不会阻塞下载，
+/// 但是耗时的回调会推迟之后的事件，因此耗时的操作应当自行另开线程。
+/// TUI本身不依赖这些回调，它们主要用于将下载引擎嵌入到其他程序中。
+///
+/// [`TaskManager::register_observer`]: crate::app::task::TaskManager::register_observer
+pub trait TaskObserver: Send + Sync {
+    /// 任务开始执行，包括恢复之前暂停的任务
+    fn on_started(&self, _task: &TaskInfo) {}
+
+    /// 下载进度，每个任务最多每隔[`TaskEvents::PROGRESS_INTERVAL`]调用一次
+    fn on_progress(&self, _task: &TaskInfo, _downloaded: u64, _content_length: Option<u64>) {}
+
+    /// 任务进入了新的阶段
+    fn on_state_change(&self, _task: &TaskInfo, _phase: TaskPhase) {}
+
+    /// 任务结束，包括失败、暂停和取消
+    fn on_finished(&self, _task: &TaskInfo, _result: &TaskResult) {}
+}
+
+/// 事件发生时任务的基本信息
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub url: Option<Url>,
+    pub filepath: PathBuf,
+}
+
+impl TaskInfo {
+    pub fn from_state(state: &Mutex<TaskState>) -> Self {
+        let state = state.lock().unwrap();
+        TaskInfo {
+            url: state.url().cloned(),
+            filepath: state.filepath().to_path_buf(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TaskEvent {
+    Started(TaskInfo),
+    Progress {
+        task: TaskInfo,
+        downloaded: u64,
+        content_length: Option<u64>,
+    },
+    StateChange(TaskInfo, TaskPhase),
+    Finished(TaskInfo, TaskResult),
+}
+
+/// 任务用于发出事件的句柄，没有注册任何观察者时什么都不做
+#[derive(Debug, Clone, Default)]
+pub struct TaskEvents {
+    sender: Option<mpsc::UnboundedSender<TaskEvent>>,
+}
+
+impl TaskEvents {
+    // ------------------- CONSTANT -----------------------
+
+    pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 在当前的运行时中启动分发事件的任务，没有观察者时返回空的句柄
+    pub fn spawn(observers: Vec<Arc<dyn TaskObserver>>) -> Self {
+        if observers.is_empty() {
+            return TaskEvents::default();
+        }
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                for observer in &observers {
+                    dispatch(observer.as_ref(), &event);
+                }
+            }
+        });
+        TaskEvents { sender: Some(tx) }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn emit(&self, event: impl FnOnce() -> TaskEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event());
+        }
+    }
+
+    pub fn started(&self, state: &Mutex<TaskState>) {
+        self.emit(|| TaskEvent::Started(TaskInfo::from_state(state)));
+    }
+
+    pub fn state_change(&self, state: &Mutex<TaskState>, phase: TaskPhase) {
+        self.emit(|| TaskEvent::StateChange(TaskInfo::from_state(state), phase));
+    }
+
+    pub fn finished(&self, state: &Mutex<TaskState>, result: &TaskResult) {
+        self.emit(|| TaskEvent::Finished(TaskInfo::from_state(state), result.clone()));
+    }
+
+    /// 报告下载进度，距离`last`不足[`TaskEvents::PROGRESS_INTERVAL`]时忽略
+    pub fn progress(&self, state: &Mutex<TaskState>, last: &mut Option<Instant>) {
+        if self.sender.is_none() || last.is_some_and(|t| t.elapsed() < Self::PROGRESS_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+        self.emit(|| {
+            let (downloaded, content_length) = {
+                let state = state.lock().unwrap();
+                (state.downloaded(), state.content_length())
+            };
+            TaskEvent::Progress {
+                task: TaskInfo::from_state(state),
+                downloaded,
+                content_length,
+            }
+        });
+    }
+}
+
+fn dispatch(observer: &dyn TaskObserver, event: &TaskEvent) {
+    match event {
+        TaskEvent::Started(task) => observer.on_started(task),
+        TaskEvent::Progress {
+            task,
+            downloaded,
+            content_length,
+        } => observer.on_progress(task, *downloaded, *content_length),
+        TaskEvent::StateChange(task, phase) => observer.on_state_change(task, *phase),
+        TaskEvent::Finished(task, result) => observer.on_finished(task, result),
+    }
+}
+
+/// 可以在配置中启用的内置观察者
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObserverKind {
+    /// 将任务的生命周期写入日志
+    Log,
+    /// 任务完成或者失败时发送桌面通知
+    Notify,
+}
+
+impl ObserverKind {
+    pub fn build(self) -> Arc<dyn TaskObserver> {
+        match self {
+            ObserverKind::Log => Arc::new(LogObserver),
+            ObserverKind::Notify => Arc::new(NotifyObserver),
+        }
+    }
+}
+
+/// 将任务的生命周期写入日志
+#[derive(Debug, Default)]
+pub struct LogObserver;
+
+impl LogObserver {
+    fn name(task: &TaskInfo) -> String {
+        match &task.url {
+            Some(url) => url.to_string(),
+            None => task.filepath.display().to_string(),
+        }
+    }
+}
+
+impl TaskObserver for LogObserver {
+    fn on_started(&self, task: &TaskInfo) {
+        log::debug!(target:"Task", "Started {}", Self::name(task));
+    }
+
+    fn on_progress(&self, task: &TaskInfo, downloaded: u64, content_length: Option<u64>) {
+        log::trace!(target:"Task", "{}: {}/{:?} bytes", Self::name(task), downloaded, content_length);
+    }
+
+    fn on_state_change(&self, task: &TaskInfo, phase: TaskPhase) {
+        log::debug!(target:"Task", "{}: {:?}", Self::name(task), phase);
+    }
+
+    fn on_finished(&self, task: &TaskInfo, result: &TaskResult) {
+        log::debug!(target:"Task", "{}: {}", Self::name(task), result.detail());
+    }
+}
+
+/// 任务完成或者失败时发送桌面通知，暂停和取消不通知
+///
+/// Linux下使用`notify-send`，macOS下使用`osascript`，其他平台不做任何事。
+#[derive(Debug, Default)]
+pub struct NotifyObserver;
+
+impl NotifyObserver {
+    fn notify(title: &str, body: &str) {
+        let mut command = if cfg!(target_os = "macos") {
+            let script = format!("display notification {:?} with title {:?}", body, title);
+            let mut command = Command::new("osascript");
+            command.arg("-e").arg(script);
+            command
+        } else if cfg!(unix) {
+            let mut command = Command::new("notify-send");
+            command.arg("--app-name=request-tui").arg(title).arg(body);
+            command
+        } else {
+            return;
+        };
+        // 通知只是附加功能，失败时只记录日志
+        match command.spawn() {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(e) => log::debug!(target:"Task", "Failed to send desktop notification: {}", e),
+        }
+    }
+}
+
+impl TaskObserver for NotifyObserver {
+    fn on_finished(&self, task: &TaskInfo, result: &TaskResult) {
+        let name = task
+            .filepath
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match result.kind() {
+            StageKind::Finished => Self::notify("Download finished", &name),
+            StageKind::AlreadyExists => Self::notify("Already downloaded", &name),
+            kind if kind.is_user_stop() => {}
+            _ => Self::notify("Download failed", &format!("{}\n{}", name, result.detail())),
+        }
+    }
+}
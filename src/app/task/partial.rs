@@ -0,0 +1,162 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// 恢复下载时，已经下载的部分比会话中记录的长时的处理方式，见[`reconcile_partial`]
+///
+/// 多出来的部分来自上一次运行中写入了文件、却没有来得及记录进度的数据，通常是完整的，
+/// 但也可能是其他程序写入的内容。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedPartial {
+    /// 截断到记录的长度，从记录的位置继续
+    #[default]
+    Truncate,
+    /// 丢弃整个文件，从头开始下载
+    Restart,
+}
+
+/// 恢复下载之前对比记录的进度与文件实际长度之后的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumePlan {
+    /// 长度一致，从记录的位置继续
+    Continue(u64),
+    /// 文件比记录的短（例如写入缓冲区之前程序崩溃，或者文件被截断），以文件为准，从文件末尾继续
+    Rewind { recorded: u64, on_disk: u64 },
+    /// 文件比记录的长，截断到记录的长度
+    Truncate { recorded: u64, on_disk: u64 },
+    /// 文件比记录的长，并且配置为从头开始下载
+    Restart { recorded: u64, on_disk: u64 },
+    /// 已经下载的部分不存在
+    Missing,
+}
+
+impl ResumePlan {
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 继续下载的位置，也是文件需要截断到的长度，文件不存在时为[`None`]
+    pub fn offset(&self) -> Option<u64> {
+        match *self {
+            ResumePlan::Continue(offset) => Some(offset),
+            ResumePlan::Rewind { on_disk, .. } => Some(on_disk),
+            ResumePlan::Truncate { recorded, .. } => Some(recorded),
+            ResumePlan::Restart { .. } => Some(0),
+            ResumePlan::Missing => None,
+        }
+    }
+}
+
+impl Display for ResumePlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumePlan::Continue(offset) => write!(f, "resuming at {} bytes", offset),
+            ResumePlan::Rewind { recorded, on_disk } => write!(
+                f,
+                "partial file has {} of {} recorded bytes, resuming from the file",
+                on_disk, recorded
+            ),
+            ResumePlan::Truncate { recorded, on_disk } => write!(
+                f,
+                "partial file has {} bytes, more than the recorded {}, truncating",
+                on_disk, recorded
+            ),
+            ResumePlan::Restart { recorded, on_disk } => write!(
+                f,
+                "partial file has {} bytes, more than the recorded {}, restarting",
+                on_disk, recorded
+            ),
+            ResumePlan::Missing => write!(f, "partial file is missing"),
+        }
+    }
+}
+
+/// 对比记录的已下载字节数`recorded`与已经下载的文件的长度`on_disk`（文件不存在时为[`None`]），
+/// 决定从哪里继续下载
+///
+/// 直接使用记录的位置发送`Range`请求时，文件比记录的短会在文件中留下空洞，
+/// 比记录的长则会让新的数据接在错误的位置之后，两种情况都会得到损坏的文件。
+pub fn reconcile_partial(
+    recorded: u64,
+    on_disk: Option<u64>,
+    oversized: OversizedPartial,
+) -> ResumePlan {
+    let Some(on_disk) = on_disk else {
+        return ResumePlan::Missing;
+    };
+    if on_disk == recorded {
+        ResumePlan::Continue(recorded)
+    } else if on_disk < recorded {
+        ResumePlan::Rewind { recorded, on_disk }
+    } else {
+        match oversized {
+            OversizedPartial::Truncate => ResumePlan::Truncate { recorded, on_disk },
+            OversizedPartial::Restart => ResumePlan::Restart { recorded, on_disk },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_length_continues_at_the_recorded_offset() {
+        let plan = reconcile_partial(4096, Some(4096), OversizedPartial::Truncate);
+        assert_eq!(plan, ResumePlan::Continue(4096));
+        assert_eq!(plan.offset(), Some(4096));
+        assert_eq!(
+            reconcile_partial(0, Some(0), OversizedPartial::Restart).offset(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn shorter_file_rewinds_to_its_end() {
+        let plan = reconcile_partial(4096, Some(1000), OversizedPartial::Truncate);
+        assert_eq!(
+            plan,
+            ResumePlan::Rewind {
+                recorded: 4096,
+                on_disk: 1000
+            }
+        );
+        assert_eq!(plan.offset(), Some(1000));
+        // 设置只影响文件更长的情况
+        assert_eq!(
+            reconcile_partial(4096, Some(1000), OversizedPartial::Restart).offset(),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn longer_file_follows_the_oversized_setting() {
+        let plan = reconcile_partial(1000, Some(4096), OversizedPartial::Truncate);
+        assert_eq!(
+            plan,
+            ResumePlan::Truncate {
+                recorded: 1000,
+                on_disk: 4096
+            }
+        );
+        assert_eq!(plan.offset(), Some(1000));
+
+        let plan = reconcile_partial(1000, Some(4096), OversizedPartial::Restart);
+        assert_eq!(
+            plan,
+            ResumePlan::Restart {
+                recorded: 1000,
+                on_disk: 4096
+            }
+        );
+        assert_eq!(plan.offset(), Some(0));
+    }
+
+    #[test]
+    fn missing_file_cannot_be_resumed() {
+        for oversized in [OversizedPartial::Truncate, OversizedPartial::Restart] {
+            let plan = reconcile_partial(4096, None, oversized);
+            assert_eq!(plan, ResumePlan::Missing);
+            assert_eq!(plan.offset(), None);
+        }
+    }
+}
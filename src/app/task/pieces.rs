@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+/// 文件中已经下载完成的部分，将文件均分为最多[`PieceMap::MAX_CELLS`]块，
+/// 每块只记录完成的字节数，因此无论文件多大，记录的开销都是固定的。
+///
+/// 目前的下载总是从头开始连续写入，分段下载加入之后每一段写入各自的范围即可。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceMap {
+    total: u64,
+    cell_size: u64,
+    cells: Vec<u64>,
+    completed: u64,
+}
+
+impl PieceMap {
+    // ------------------- CONSTANT -----------------------
+
+    pub const MAX_CELLS: u64 = 200;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(total: u64) -> Self {
+        let cell_size = total.div_ceil(Self::MAX_CELLS).max(1);
+        let count = total.div_ceil(cell_size).max(1);
+        PieceMap {
+            total,
+            cell_size,
+            cells: vec![0; count as usize],
+            completed: 0,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// 已经完成的字节数
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 记录`[offset, offset + len)`已经写入，超出文件长度的部分被忽略
+    pub fn record(&mut self, offset: u64, len: u64) {
+        let mut start = offset.min(self.total);
+        let end = offset.saturating_add(len).min(self.total);
+        while start < end {
+            let index = (start / self.cell_size) as usize;
+            let cell_end = ((index as u64 + 1) * self.cell_size).min(end);
+            let capacity = self.cell_len(index);
+            let added = (cell_end - start).min(capacity - self.cells[index]);
+            self.cells[index] += added;
+            self.completed += added;
+            start = cell_end;
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn cell_len(&self, index: usize) -> u64 {
+        let start = index as u64 * self.cell_size;
+        (start + self.cell_size).min(self.total) - start.min(self.total)
+    }
+
+    /// 将文件分为`width`列，每列的完成比例（0.0~1.0），用于在一行中显示
+    pub fn columns(&self, width: usize) -> Vec<f64> {
+        let count = self.cells.len();
+        (0..width)
+            .map(|column| {
+                let from = column * count / width;
+                let to = ((column + 1) * count / width).max(from + 1).min(count);
+                let (done, len) = (from..to).fold((0, 0), |(done, len), i| {
+                    (done + self.cells[i], len + self.cell_len(i))
+                });
+                if len == 0 {
+                    1.0
+                } else {
+                    done as f64 / len as f64
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_cover_the_whole_file() {
+        let map = PieceMap::new(1000);
+        assert_eq!(map.cells.len(), 200);
+        assert_eq!(map.cell_size, 5);
+
+        // 不能整除时最后一块较短
+        let map = PieceMap::new(1001);
+        assert_eq!(map.cell_size, 6);
+        assert_eq!(map.cells.len(), 167);
+        assert_eq!(map.cell_len(166), 1001 - 166 * 6);
+
+        // 小文件每字节一块，空文件也保留一块
+        assert_eq!(PieceMap::new(10).cells.len(), 10);
+        assert_eq!(PieceMap::new(0).cells.len(), 1);
+    }
+
+    #[test]
+    fn record_spans_cells_and_ignores_overlap() {
+        let mut map = PieceMap::new(1000);
+        map.record(3, 10);
+        assert_eq!(map.completed(), 10);
+        assert_eq!(&map.cells[..3], &[2, 5, 3]);
+
+        // 每块最多计入自身的长度，重复写入已经完成的块不会重复计数
+        map.record(0, 15);
+        map.record(0, 15);
+        assert_eq!(map.completed(), 15);
+        assert_eq!(&map.cells[..4], &[5, 5, 5, 0]);
+    }
+
+    #[test]
+    fn record_clamps_to_the_file_length() {
+        let mut map = PieceMap::new(100);
+        map.record(90, 50);
+        assert_eq!(map.completed(), 10);
+        map.record(200, 10);
+        map.record(u64::MAX - 1, 10);
+        assert_eq!(map.completed(), 10);
+        map.record(0, 100);
+        assert_eq!(map.completed(), map.total());
+    }
+
+    #[test]
+    fn columns_report_completion_per_column() {
+        let mut map = PieceMap::new(1000);
+        map.record(0, 500);
+        let columns = map.columns(4);
+        assert_eq!(columns, vec![1.0, 1.0, 0.0, 0.0]);
+
+        map.record(500, 125);
+        assert_eq!(map.columns(4)[2], 0.5);
+        assert_eq!(map.columns(1), vec![0.625]);
+    }
+
+    #[test]
+    fn columns_wider_than_the_cells_repeat_cells() {
+        let mut map = PieceMap::new(2);
+        map.record(0, 1);
+        assert_eq!(map.columns(4), vec![1.0, 1.0, 0.0, 0.0]);
+        // 空文件视为完成
+        assert_eq!(PieceMap::new(0).columns(3), vec![1.0; 3]);
+    }
+}
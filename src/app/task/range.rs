@@ -0,0 +1,327 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+/// 只下载文件的一部分（部分下载），与HTTP的`Range`使用相同的写法，两端都包含在内
+///
+/// - `0-10485759`：开头的10 MiB
+/// - `1048576-`：从1 MiB处到文件末尾
+/// - `-1048576`：最后的1 MiB
+///
+/// 保存的文件名添加`.part-<范围>`后缀，进度以范围的长度为准，收到整个范围时任务即完成。
+/// 服务器忽略`Range`返回整个文件时，丢弃范围之前的部分并且在范围结束时停止读取，见[`limit_stream`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ByteRange {
+    /// 从`start`开始，`end`为[`None`]时直到文件末尾
+    From { start: u64, end: Option<u64> },
+    /// 文件最后的若干字节
+    Last(u64),
+}
+
+impl ByteRange {
+    // -------------------- FUNCTION -----------------------
+
+    /// 请求头`Range`的值
+    pub fn header_value(&self) -> String {
+        format!("bytes={}", self)
+    }
+
+    /// 添加到自动生成的文件名之后的后缀
+    pub fn filename_suffix(&self) -> String {
+        format!(".part-{}", self)
+    }
+
+    /// 整个文件的长度为`total`时，范围的起点以及长度
+    ///
+    /// 范围超出文件末尾的部分被截掉。起点超出文件末尾时长度为0；
+    /// 长度未知时，到文件末尾的范围长度也未知，最后若干字节的范围无法确定起点，返回[`None`]。
+    pub fn resolve(&self, total: Option<u64>) -> Option<(u64, Option<u64>)> {
+        match *self {
+            ByteRange::From { start, end } => {
+                // 范围之后的第一个字节的位置
+                let stop = match (end.map(|end| end.saturating_add(1)), total) {
+                    (Some(stop), Some(total)) => Some(stop.min(total)),
+                    (stop, total) => stop.or(total),
+                };
+                Some((start, stop.map(|stop| stop.saturating_sub(start))))
+            }
+            ByteRange::Last(len) => {
+                let total = total?;
+                let len = len.min(total);
+                Some((total - len, Some(len)))
+            }
+        }
+    }
+}
+
+impl FromStr for ByteRange {
+    type Err = InvalidByteRange;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || InvalidByteRange(s.to_string());
+        let digits = |part: &str| -> Result<u64, InvalidByteRange> {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            part.parse().map_err(|_| invalid())
+        };
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        match (start, end) {
+            ("", len) => match digits(len)? {
+                0 => Err(invalid()),
+                len => Ok(ByteRange::Last(len)),
+            },
+            (start, "") => Ok(ByteRange::From {
+                start: digits(start)?,
+                end: None,
+            }),
+            (start, end) => {
+                let (start, end) = (digits(start)?, digits(end)?);
+                if end < start {
+                    return Err(invalid());
+                }
+                Ok(ByteRange::From {
+                    start,
+                    end: Some(end),
+                })
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for ByteRange {
+    type Error = InvalidByteRange;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ByteRange> for String {
+    fn from(range: ByteRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl Display for ByteRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteRange::From { start, end: None } => write!(f, "{}-", start),
+            ByteRange::From {
+                start,
+                end: Some(end),
+            } => write!(f, "{}-{}", start, end),
+            ByteRange::Last(len) => write!(f, "-{}", len),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidByteRange(String);
+
+impl Display for InvalidByteRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid range {:?}, expected start-end, start- or -length in bytes",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidByteRange {}
+
+/// 解析响应头`Content-Range`，例如`bytes 0-1023/4096`，返回范围的两端（包含在内）
+pub fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (range, _total) = range.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+    (start <= end).then_some((start, end))
+}
+
+/// 丢弃流开头的`skip`字节，之后最多保留`take`字节，达到`take`之后不再读取底层的流
+///
+/// 用于服务器忽略`Range`返回整个文件的情况，见[`ByteRange`]。
+pub fn limit_stream<S>(
+    stream: S,
+    skip: u64,
+    take: Option<u64>,
+) -> impl Stream<Item = reqwest::Result<Bytes>>
+where
+    S: Stream<Item = reqwest::Result<Bytes>>,
+{
+    futures::stream::unfold(
+        (Box::pin(stream), skip, take),
+        |(mut stream, mut skip, mut take)| async move {
+            loop {
+                if take == Some(0) {
+                    return None;
+                }
+                let mut chunk = match stream.next().await? {
+                    Ok(chunk) => chunk,
+                    Err(e) => return Some((Err(e), (stream, skip, take))),
+                };
+                let skipped = skip.min(chunk.len() as u64);
+                skip -= skipped;
+                chunk = chunk.slice(skipped as usize..);
+                if let Some(remaining) = take.as_mut() {
+                    let kept = (*remaining).min(chunk.len() as u64);
+                    *remaining -= kept;
+                    chunk.truncate(kept as usize);
+                }
+                if !chunk.is_empty() {
+                    return Some((Ok(chunk), (stream, skip, take)));
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from(start: u64, end: Option<u64>) -> ByteRange {
+        ByteRange::From { start, end }
+    }
+
+    #[test]
+    fn parse_the_three_range_forms() {
+        assert_eq!("0-1023".parse(), Ok(from(0, Some(1023))));
+        assert_eq!(" 1024- ".parse(), Ok(from(1024, None)));
+        assert_eq!("-500".parse(), Ok(ByteRange::Last(500)));
+        assert_eq!("7-7".parse(), Ok(from(7, Some(7))));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_ranges() {
+        for source in [
+            "", "-", "5-3", "-0", "a-b", "1-2-3", "+1-2", "1 - 2", "1024",
+        ] {
+            assert!(
+                source.parse::<ByteRange>().is_err(),
+                "{:?} should be rejected",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn formats_header_and_filename_suffix() {
+        let range = from(0, Some(1023));
+        assert_eq!(range.header_value(), "bytes=0-1023");
+        assert_eq!(range.filename_suffix(), ".part-0-1023");
+        assert_eq!(from(1024, None).header_value(), "bytes=1024-");
+        assert_eq!(ByteRange::Last(500).filename_suffix(), ".part--500");
+        assert_eq!(
+            serde_json::to_string(&ByteRange::Last(500)).unwrap(),
+            "\"-500\""
+        );
+        assert_eq!(
+            serde_json::from_str::<ByteRange>("\"1024-\"").unwrap(),
+            from(1024, None)
+        );
+    }
+
+    #[test]
+    fn resolve_against_the_reported_length() {
+        assert_eq!(
+            from(0, Some(1023)).resolve(Some(4096)),
+            Some((0, Some(1024)))
+        );
+        assert_eq!(
+            from(1024, None).resolve(Some(4096)),
+            Some((1024, Some(3072)))
+        );
+        assert_eq!(
+            ByteRange::Last(100).resolve(Some(4096)),
+            Some((3996, Some(100)))
+        );
+        // 服务器报告的文件比范围短时截掉超出的部分
+        assert_eq!(from(0, Some(1023)).resolve(Some(500)), Some((0, Some(500))));
+        assert_eq!(from(5000, None).resolve(Some(4096)), Some((5000, Some(0))));
+        assert_eq!(ByteRange::Last(100).resolve(Some(50)), Some((0, Some(50))));
+    }
+
+    #[test]
+    fn resolve_without_the_reported_length() {
+        assert_eq!(from(0, Some(99)).resolve(None), Some((0, Some(100))));
+        assert_eq!(from(10, None).resolve(None), Some((10, None)));
+        assert_eq!(ByteRange::Last(100).resolve(None), None);
+    }
+
+    #[test]
+    fn parse_content_range_bounds() {
+        assert_eq!(parse_content_range("bytes 0-1023/4096"), Some((0, 1023)));
+        assert_eq!(
+            parse_content_range(" bytes 1024-4095/*"),
+            Some((1024, 4095))
+        );
+        // 无法满足的范围只有总长度
+        assert_eq!(parse_content_range("bytes */4096"), None);
+    }
+
+    #[test]
+    fn parse_content_range_rejects_malformed_headers() {
+        for value in [
+            "",
+            "bytes",
+            "0-1023/4096",
+            "bytes 0-1023",
+            "bytes 1023-0/4096",
+            "bytes a-b/4096",
+            "bytes -1023/4096",
+            "items 0-1023/4096",
+        ] {
+            assert_eq!(parse_content_range(value), None, "{:?}", value);
+        }
+    }
+
+    async fn collect(chunks: &[&'static [u8]], skip: u64, take: Option<u64>) -> Vec<Vec<u8>> {
+        let chunks: Vec<reqwest::Result<Bytes>> = chunks
+            .iter()
+            .map(|&chunk| Ok(Bytes::from_static(chunk)))
+            .collect();
+        limit_stream(futures::stream::iter(chunks), skip, take)
+            .map(|chunk| chunk.unwrap().to_vec())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn limit_stream_skips_and_takes_across_chunks() {
+        let chunks: [&[u8]; 3] = [b"0123", b"4567", b"89"];
+        assert_eq!(
+            collect(&chunks, 0, None).await,
+            [b"0123".to_vec(), b"4567".to_vec(), b"89".to_vec()]
+        );
+        assert_eq!(
+            collect(&chunks, 5, Some(4)).await,
+            [b"567".to_vec(), b"8".to_vec()]
+        );
+        // 跳过的部分正好是整块
+        assert_eq!(collect(&chunks, 4, Some(4)).await, [b"4567".to_vec()]);
+        assert!(collect(&chunks, 20, None).await.is_empty());
+        assert!(collect(&chunks, 0, Some(0)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn limit_stream_stops_reading_at_the_range_end() {
+        let chunks = futures::stream::iter([Ok(Bytes::from_static(b"0123"))]).chain(
+            futures::stream::poll_fn(|_| -> std::task::Poll<Option<reqwest::Result<Bytes>>> {
+                panic!("read past the end of the range")
+            }),
+        );
+        let taken: Vec<_> = limit_stream(chunks, 1, Some(3))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(taken, [Bytes::from_static(b"123")]);
+    }
+}
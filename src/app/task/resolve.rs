@@ -1,37 +1,472 @@
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     pin::{Pin, pin},
-    time::Instant,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use bytes::Bytes;
+use chrono::Local;
 use futures::{Stream, StreamExt};
-use reqwest::{ClientBuilder, header};
+use percent_encoding::percent_decode_str;
+use reqwest::{ClientBuilder, StatusCode, header};
 use tokio::{
     fs::{File, OpenOptions},
-    io::{AsyncWriteExt, BufWriter},
-    sync::oneshot,
+    io::{AsyncReadExt, BufWriter},
+    sync::{mpsc, oneshot},
+    time::error::Elapsed,
 };
 use url::Url;
 
 use crate::app::{
-    sender::DownloadRequest,
-    task::{SignalHandler, Task, TaskCommand, TaskInner, TaskResult},
+    address,
+    checksum::{self, Checksum, HashAlgorithm, Hasher, StreamHasher},
+    disk,
+    network::NetworkOptions,
+    permissions,
+    policy::{FileTypePolicy, PolicyAction},
+    sender::{DownloadRequest, SaveAs},
+    task::{
+        ArchiveKind, BufferSizes, BusyRetry, ByteRange, CompletedCopy, ConflictResolution,
+        ConnectionSecurity, FileConflict, FileError, LogLimiter, SignalHandler, SlotPermit,
+        SlotRequest, Task, TaskCommand, TaskContext, TaskId, TaskInner, TaskPhase, TaskResult,
+        WarmUp, completed, error_chain, extract, limit_stream, metadata_client, naming,
+        parse_content_range, reconcile_partial, transfer_client,
+    },
+    template::TemplateContext,
+    throttle::TaskThrottle,
 };
+use crate::window::common;
 
-pub async fn handle_task(task: Task) {
-    match task.request {
-        DownloadRequest::Normal { url } => {
-            handle_normal_download(task.inner, url, task.handler).await;
+pub async fn handle_task(task: Task, ctx: &TaskContext) {
+    let Task {
+        id,
+        request,
+        inner,
+        handler,
+    } = task;
+    let state = inner.state.clone();
+    ctx.events.started(&state);
+
+    // 截获发送给UI线程的结果，先通知观察者，再转发给UI线程
+    let SignalHandler { reporter, receiver } = handler;
+    let (result_tx, mut result_rx) = oneshot::channel();
+    let handler = SignalHandler::new(result_tx, receiver);
+
+    let url = match &request {
+        DownloadRequest::Normal { url, .. } => address::normalize_url(url).ok(),
+        DownloadRequest::Resume | DownloadRequest::Verify => None,
+    };
+    let host = match &url {
+        Some(url) => url.host_str().map(str::to_string),
+        None => state.lock().unwrap().host().map(str::to_string),
+    };
+    match request {
+        // 重新校验只读取本地的文件，不需要排队
+        DownloadRequest::Verify => verify_existing_file(&inner, handler, ctx).await,
+        request => download_when_admitted(id, inner, request, host, url, handler, ctx).await,
+    }
+
+    let result = result_rx.try_recv().unwrap_or_else(|_| {
+        TaskResult::new_unknown_error(String::from("Task ended without a result"))
+    });
+    ctx.events.finished(&state, &result);
+    let _ = reporter.send(result);
+}
+
+/// 等待空闲的名额之后开始下载，许可在任务结束时归还，见[`acquire_slot`]
+///
+/// 得到名额之后按照此时的配置下载，见[`TaskContext::admitted`]。
+async fn download_when_admitted(
+    id: TaskId,
+    inner: TaskInner,
+    request: DownloadRequest,
+    host: Option<String>,
+    url: Option<Url>,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) {
+    let state = inner.state.clone();
+    if let Some((mut admission, handler)) =
+        acquire_slot(&inner, host.as_deref(), url, handler, ctx).await
+    {
+        log::debug!(target:"Task", "Task {} admitted", id);
+        let ctx = &ctx.admitted();
+        // 排队期间用户可能修改了任务的选项，见TaskState::is_editable
+        let options = state.lock().unwrap().options().cloned();
+        let request = match (request, options) {
+            (DownloadRequest::Normal { download_dir, .. }, Some(options)) => {
+                DownloadRequest::new_normal(
+                    options.url.clone(),
+                    options.save_as.clone(),
+                    download_dir,
+                )
+            }
+            (request, _) => request,
+        };
+        match request {
+            DownloadRequest::Normal {
+                url,
+                save_as,
+                download_dir,
+            } => {
+                handle_normal_download(
+                    inner,
+                    url,
+                    save_as,
+                    download_dir,
+                    handler,
+                    ctx,
+                    &mut admission,
+                )
+                .await;
+            }
+            DownloadRequest::Resume => {
+                handle_resume_download(inner, handler, ctx, admission.client).await;
+            }
+            DownloadRequest::Verify => verify_existing_file(&inner, handler, ctx).await,
         }
-        DownloadRequest::Resume => {
-            handle_resume_download(task.inner, task.handler).await;
+    }
+}
+
+/// 等待[`ConnectionLimits`]中的空闲名额，等待期间仍然响应停止、取消以及修改设置的指令
+///
+/// 需要等待时，对新的任务发送HEAD请求获取文件大小，用于[`AdmissionPolicy`]排序，
+/// 恢复的任务直接使用剩余的大小。开启了[`WarmUp`]时，名额即将空出时提前准备连接，
+/// 提前建立了连接的客户端随许可一起返回，下载时继续使用。
+///
+/// 返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+///
+/// [`ConnectionLimits`]: crate::app::task::ConnectionLimits
+/// [`AdmissionPolicy`]: crate::app::task::AdmissionPolicy
+async fn acquire_slot(
+    task: &TaskInner,
+    host: Option<&str>,
+    url: Option<Url>,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) -> Option<(Admission, SignalHandler)> {
+    enter_phase(task, ctx, TaskPhase::Queued);
+    let (priority, boost) = {
+        let state = task.state.lock().unwrap();
+        (state.priority, state.boost)
+    };
+    let request = ctx
+        .limits
+        .request(host, priority, boost, task.state.clone());
+    wait_for_slot(task, request, url, ctx.warm_up, handler, ctx).await
+}
+
+/// 等待用户做出决定期间归还了名额的任务，在做出决定之后重新排队，见[`SlotPermit::requeue`]
+///
+/// 等待期间与第一次排队时一样响应各种指令，返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+async fn reacquire_slot(
+    task: &TaskInner,
+    permit: &mut SlotPermit,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) -> Option<SignalHandler> {
+    if permit.is_held() {
+        return Some(handler);
+    }
+    enter_phase(task, ctx, TaskPhase::Queued);
+    let (priority, boost) = {
+        let state = task.state.lock().unwrap();
+        (state.priority, state.boost)
+    };
+    let request = permit.requeue(priority, boost);
+    // 已经有了客户端，不需要提前准备连接
+    let (admission, handler) =
+        wait_for_slot(task, request, None, WarmUp::Off, handler, ctx).await?;
+    *permit = admission.permit;
+    Some(handler)
+}
+
+/// [`acquire_slot`]加入队列之后的等待过程，`url`用于排队时获取文件的大小，
+/// `warm`为名额即将空出时是否以及如何提前准备连接
+async fn wait_for_slot(
+    task: &TaskInner,
+    request: SlotRequest,
+    url: Option<Url>,
+    warm: WarmUp,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) -> Option<(Admission, SignalHandler)> {
+    let SignalHandler {
+        reporter,
+        mut receiver,
+    } = handler;
+    let remaining = {
+        let state = task.state.lock().unwrap();
+        state
+            .content_length()
+            .map(|len| len.saturating_sub(state.downloaded()))
+    };
+    if let Some(remaining) = remaining {
+        request.set_size(remaining);
+    }
+    // 恢复的任务使用之前记录的URL
+    let target = url
+        .clone()
+        .or_else(|| task.state.lock().unwrap().url().cloned());
+    let acquire = request.wait();
+    let mut acquire = pin!(acquire);
+    let preflight = preflight_size(task, &ctx.network, url.filter(|_| remaining.is_none()));
+    let mut preflight = pin!(preflight);
+    let mut preflight_done = false;
+    let mut warm_check = tokio::time::interval(WARM_UP_CHECK_INTERVAL);
+    warm_check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut warm_started = warm == WarmUp::Off || target.is_none();
+    let mut warming: Option<Pin<Box<dyn Future<Output = Option<WarmClient>> + Send + '_>>> = None;
+    let mut warmed = None;
+    loop {
+        tokio::select! {
+            // 有空闲的名额时直接开始，不发送HEAD请求
+            biased;
+            permit = &mut acquire => {
+                // 与UI线程修改选项时持有同一个锁，离开排队状态之后选项不会再被修改
+                task.state.lock().unwrap().phase = TaskPhase::Connecting;
+                // 排队期间修改了请求头时，提前建立的客户端不再适用
+                let headers = request_headers(task);
+                let client = warmed
+                    .filter(|warmed: &WarmClient| warmed.headers == headers)
+                    .map(|warmed| warmed.client);
+                let admission = Admission { permit, client };
+                return Some((admission, SignalHandler::new(reporter, receiver)));
+            }
+            command = receiver.recv() => match command {
+                Some(TaskCommand::Stop) => {
+                    reporter.send(TaskResult::new_interrupted()).unwrap();
+                    return None;
+                }
+                Some(TaskCommand::Abort) => {
+                    reporter.send(TaskResult::new_abort()).unwrap();
+                    return None;
+                }
+                Some(command @ TaskCommand::SetPriority { priority, boost }) => {
+                    record_setting(task, command);
+                    request.set_priority(priority, boost);
+                }
+                Some(command @ TaskCommand::SetSpeedLimit(_)) => record_setting(task, command),
+                Some(TaskCommand::ResolveConflict(_)) | Some(TaskCommand::ConfirmDownload(_)) => {}
+                None => {
+                    let _ = reporter.send(TaskResult::new_unknown_error(String::from(
+                        "Command channel closed unexpectedly",
+                    )));
+                    return None;
+                }
+            },
+            size = &mut preflight, if !preflight_done => {
+                preflight_done = true;
+                if let Some(size) = size {
+                    request.set_size(size);
+                    let mut state = task.state.lock().unwrap();
+                    state.preflight_size = match state.options().and_then(|options| options.range) {
+                        Some(range) => range.resolve(Some(size)).and_then(|(_, len)| len),
+                        None => Some(size),
+                    };
+                }
+            }
+            _ = warm_check.tick(), if !warm_started => {
+                if request.warm_up_due()
+                    && let Some(target) = target.clone()
+                {
+                    warm_started = true;
+                    request.mark_warmed();
+                    warming = Some(Box::pin(warm_up(task, &ctx.network, target, warm)));
+                }
+            }
+            client = async { warming.as_mut().unwrap().await }, if warming.is_some() => {
+                warming = None;
+                warmed = client;
+            }
         }
     }
 }
 
-async fn handle_normal_download(task: TaskInner, url_str: String, handler: SignalHandler) {
-    let url = match get_proper_url(&url_str) {
+/// 排队结束时得到的许可，以及排队时提前建立了连接的客户端
+struct Admission {
+    /// 任务结束时归还，等待用户做出决定期间暂时归还，见[`reacquire_slot`]
+    permit: SlotPermit,
+    client: Option<reqwest::Client>,
+}
+
+/// 排队时检查是否需要提前准备连接的间隔
+const WARM_UP_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 提前建立了连接的客户端，以及建立时使用的请求头
+struct WarmClient {
+    client: reqwest::Client,
+    headers: Vec<(String, String)>,
+}
+
+/// 任务选项中的自定义请求头，见[`build_client`]
+fn request_headers(task: &TaskInner) -> Vec<(String, String)> {
+    let state = task.state.lock().unwrap();
+    state
+        .options()
+        .map(|options| options.headers.clone())
+        .unwrap_or_default()
+}
+
+/// 按照`mode`提前解析域名或者建立连接，返回建立了连接的客户端
+///
+/// 建立连接时发送的HEAD请求的响应本身并不重要，只需要连接留在客户端的连接池中。
+async fn warm_up(
+    task: &TaskInner,
+    network: &Arc<NetworkOptions>,
+    url: Url,
+    mode: WarmUp,
+) -> Option<WarmClient> {
+    let started = Instant::now();
+    match mode {
+        WarmUp::Off => None,
+        WarmUp::Dns => {
+            // IP地址不需要解析
+            let Some(url::Host::Domain(domain)) = url.host() else {
+                return None;
+            };
+            let port = url.port_or_known_default()?;
+            match tokio::net::lookup_host((domain, port)).await {
+                Ok(addrs) => log::debug!(
+                    target:"Task",
+                    "Pre-resolved {} to {} address(es) in {:?}",
+                    domain,
+                    addrs.count(),
+                    started.elapsed()
+                ),
+                Err(e) => log::debug!(target:"Task", "Failed to pre-resolve {}: {}", domain, e),
+            }
+            None
+        }
+        WarmUp::Connect => {
+            let headers = request_headers(task);
+            let client = build_client(task, network).ok()?;
+            match client.head(url.clone()).send().await {
+                Ok(_) => {
+                    log::debug!(
+                        target:"Task",
+                        "Warmed up connection to {} in {:?}",
+                        url.host_str().unwrap_or_default(),
+                        started.elapsed()
+                    );
+                    Some(WarmClient { client, headers })
+                }
+                Err(e) => {
+                    log::debug!(target:"Task", "Failed to warm up connection: {}", error_chain(&e));
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// 使用HEAD请求获取文件的大小，失败时返回[`None`]
+async fn preflight_size(
+    task: &TaskInner,
+    network: &Arc<NetworkOptions>,
+    url: Option<Url>,
+) -> Option<u64> {
+    let url = url?;
+    let client = build_client(task, network).ok()?;
+    let response = client.head(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok())
+}
+
+/// 任务选项中的部分下载范围，见[`ByteRange`]
+fn requested_range(task: &TaskInner) -> Option<ByteRange> {
+    let state = task.state.lock().unwrap();
+    state.options().and_then(|options| options.range)
+}
+
+/// 根据响应头设置文件的长度以及是否可以断点续传，返回还需要从响应体开头丢弃的字节数
+/// 以及之后最多保留的字节数，见[`limit_stream`]
+///
+/// 部分下载时，服务器支持范围请求（206）则响应体就是请求的范围，记录`Content-Range`中的位置
+/// 用于恢复；服务器忽略`Range`返回整个文件（200）时根据文件的长度截取范围，恢复时只能重新开始。
+fn apply_range(
+    task: &TaskInner,
+    range: Option<ByteRange>,
+    response: &reqwest::Response,
+) -> anyhow::Result<(u64, Option<u64>)> {
+    let head = response.headers();
+    let content_length = head
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok());
+    let mut state = task.state.lock().unwrap();
+    let Some(range) = range else {
+        state.content_length = content_length;
+        state.accept_ranges = head
+            .get(header::ACCEPT_RANGES)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|s| s.eq_ignore_ascii_case("bytes"));
+        return Ok((0, None));
+    };
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        let window = head
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_range);
+        state.content_length = window
+            .map(|(start, end)| end - start + 1)
+            .or(content_length);
+        state.accept_ranges = window.is_some();
+        state.fetched_range = window;
+        return Ok((0, state.content_length));
+    }
+    let (skip, take) = range.resolve(content_length).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Server ignored the range {} and did not send the file size",
+            range
+        )
+    })?;
+    log::info!(
+        target:"Task",
+        "Server ignored the range {}, skipping {} bytes of the response",
+        range,
+        skip
+    );
+    state.content_length = take;
+    state.accept_ranges = false;
+    state.fetched_range = None;
+    Ok((skip, take))
+}
+
+/// 记录修改任务设置的指令，下载开始之前收到的设置在下载开始时生效
+fn record_setting(task: &TaskInner, command: TaskCommand) {
+    let mut state = task.state.lock().unwrap();
+    match command {
+        TaskCommand::SetSpeedLimit(limit) => state.speed_limit = limit,
+        TaskCommand::SetPriority { priority, boost } => {
+            state.priority = priority;
+            state.boost = boost;
+        }
+        TaskCommand::Stop
+        | TaskCommand::Abort
+        | TaskCommand::ResolveConflict(_)
+        | TaskCommand::ConfirmDownload(_) => {}
+    }
+}
+
+async fn handle_normal_download(
+    task: TaskInner,
+    url_str: String,
+    save_as: Option<SaveAs>,
+    mut download_dir: PathBuf,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+    admission: &mut Admission,
+) {
+    let permit = &mut admission.permit;
+    let url = match address::normalize_url(&url_str) {
         Ok(u) => u,
         Err(e) => {
             // 解析失败时，发送一个未知URL的结果
@@ -43,172 +478,680 @@ async fn handle_normal_download(task: TaskInner, url_str: String, handler: Signa
         }
     };
 
-    // FIXME: 使用配置的路径
-    let base_dirs = directories::BaseDirs::new().unwrap(); // 临时先用一个路径
-    let download_dir = base_dirs.home_dir().join("Downloads");
-    let _ = std::fs::DirBuilder::new().create(&download_dir);
+    let _ = permissions::create_dir_all(&download_dir, ctx.modes.dir);
+
+    // 在得到响应之前先记录请求的URL，这样连接失败时也能知道是哪个主机
+    task.state.lock().unwrap().url = Some(url.clone());
+
+    // 用户指定了保存路径时，如果该路径是一个目录，则在该目录下自动生成文件名，
+    // 否则直接使用该路径，已经存在文件时需要用户决定如何处理。
+    // 使用命名模板时，展开后的文件名与自动生成的文件名一样，自动添加后缀避免重复。
+    let (dest, handler) = match save_as {
+        Some(SaveAs::Path(path)) if download_dir.join(&path).is_dir() => {
+            download_dir = download_dir.join(path);
+            (None, handler)
+        }
+        Some(SaveAs::Path(path)) => {
+            match resolve_user_destination(&task, download_dir.join(path), handler, ctx, permit)
+                .await
+            {
+                Some((path, handler)) => (Some(path), handler),
+                None => return,
+            }
+        }
+        Some(SaveAs::Template { template, n }) => {
+            // 与自动生成的文件名一样解码并且去掉路径分隔符，`a%20b.zip`展开为`a b.zip`
+            let name = naming::url_filename(&url);
+            let context = TemplateContext {
+                n,
+                name: name.as_deref().unwrap_or(naming::FALLBACK_FILENAME),
+                host: url.host_str().unwrap_or("unknown"),
+                date: Local::now().date_naive(),
+            };
+            let path = download_dir.join(template.expand(&context));
+            let dir = path.parent().unwrap_or(&download_dir).to_path_buf();
+            let _ = permissions::create_dir_all(&dir, ctx.modes.dir);
+            let fname = path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or(String::from("tmp.bin"));
+            (
+                Some(dir.join(naming::get_filename_no_duplicate(&dir, &fname))),
+                handler,
+            )
+        }
+        None => (None, handler),
+    };
+
+    let reuse_existing = !ctx.redownload_existing && {
+        let state = task.state.lock().unwrap();
+        !state.options().is_some_and(|o| o.redownload)
+    };
 
-    let client = match ClientBuilder::new().build() {
+    // 排队时提前建立了连接的客户端，见[`acquire_slot`]
+    let client = match admission
+        .client
+        .take()
+        .map_or_else(|| build_client(&task, &ctx.network), Ok)
+    {
         Ok(c) => c,
         Err(e) => {
             handler
                 .reporter
-                .send(TaskResult::new_failed_to_connection(e.to_string()))
+                .send(TaskResult::new_failed_to_connection(error_chain(&e)))
                 .unwrap();
             return;
         }
     };
 
-    let stream = match get_download_head(&task, url, &client, &download_dir).await {
+    enter_phase(&task, ctx, TaskPhase::Connecting);
+    let (stream, existing) = match get_download_head(
+        &task,
+        url,
+        &client,
+        &download_dir,
+        dest,
+        &ctx.policy,
+        reuse_existing,
+    )
+    .await
+    {
         Ok(s) => s,
         Err(e) => {
-            handler
-                .reporter
-                .send(TaskResult::new_failed_to_connection(e.to_string()))
-                .unwrap();
+            let result = match e.downcast_ref::<HttpStatusError>() {
+                Some(HttpStatusError(status)) => TaskResult::new_http_status(status.as_u16()),
+                None => TaskResult::new_failed_to_connection(connection_error(&e, &ctx.network)),
+            };
+            handler.reporter.send(result).unwrap();
             return;
         }
     };
     let stream = pin!(stream);
 
+    // 在创建文件之前处理文件类型策略，被阻止或者取消时不会留下空文件
+    let Some(handler) = enforce_file_type_policy(&task, handler, ctx, permit).await else {
+        return;
+    };
+
+    // 校验和文件对应的是整个文件，部分下载时不需要
+    if ctx.auto_checksum && requested_range(&task).is_none() {
+        fetch_remote_checksum(&task, &ctx.network).await;
+    }
+
+    let handler = if existing {
+        match reuse_existing_file(&task, handler, ctx).await {
+            Some(handler) => handler,
+            None => return,
+        }
+    } else {
+        handler
+    };
+
     let filepath = { task.state.lock().unwrap().filepath.clone() };
-    let mut file = match create_download_file(&filepath).await {
+    let mut file = match create_download_file(&filepath, &task, ctx.buffers).await {
         Ok(f) => f,
         Err(e) => {
             handler
                 .reporter
-                .send(TaskResult::new_failed_to_create_file(e.to_string()))
+                .send(
+                    TaskResult::new_failed_to_create_file(e.to_string())
+                        .with_file_error(FileError::from_anyhow(&e, &filepath)),
+                )
                 .unwrap();
             return;
         }
     };
 
-    if let Some(handler) = download_stream_to_file(&task, stream, &mut file, handler).await {
-        handler.reporter.send(TaskResult::new_finished()).unwrap();
+    // 文件已经创建，空间不足时任务会暂停，之后可以从头恢复
+    if let Err(result) = check_disk_space(&task) {
+        handler.reporter.send(result).unwrap();
+        return;
+    }
+
+    let mut hasher = new_hasher(&task);
+    if let Some(handler) =
+        download_stream_to_file(&task, stream, &mut file, handler, ctx, &mut hasher).await
+    {
+        finish_download(&task, file, handler, ctx, hasher).await;
     }
 }
 
-fn get_proper_url(url_str: &str) -> anyhow::Result<Url> {
-    match Url::parse(url_str) {
-        Ok(url) => Ok(url),
-        Err(e) => {
-            if e == url::ParseError::RelativeUrlWithoutBase {
-                let fixed_url = format!("http://{}", url_str);
-                match Url::parse(&fixed_url) {
-                    Ok(url) => Ok(url),
-                    Err(e) => Err(anyhow::anyhow!("Failed to parse URL: {}", e)),
+/// 创建下载文件使用的HTTP客户端，见[`transfer_client`]
+///
+/// 开启`tls_info`，从响应中读取服务器的证书，见[`ConnectionSecurity`]。
+fn build_client(
+    task: &TaskInner,
+    network: &Arc<NetworkOptions>,
+) -> reqwest::Result<reqwest::Client> {
+    transfer_client(client_builder(task, network))
+        .tls_info(true)
+        .build()
+}
+
+/// 创建获取校验和文件等元数据使用的HTTP客户端，见[`metadata_client`]
+fn build_metadata_client(
+    task: &TaskInner,
+    network: &Arc<NetworkOptions>,
+) -> reqwest::Result<reqwest::Client> {
+    metadata_client(client_builder(task, network)).build()
+}
+
+/// 任务选项中的请求头作为每个请求的默认请求头，同时记录使用的网络设置，
+/// 用于在详情弹窗中显示
+fn client_builder(task: &TaskInner, network: &Arc<NetworkOptions>) -> ClientBuilder {
+    let mut headers = header::HeaderMap::new();
+    {
+        let mut state = task.state.lock().unwrap();
+        state.network = Some(network.clone());
+        if let Some(options) = state.options() {
+            for (name, value) in &options.headers {
+                // 输入时已经检查过，这里直接忽略无效的请求头
+                if let (Ok(name), Ok(value)) = (
+                    header::HeaderName::from_bytes(name.as_bytes()),
+                    header::HeaderValue::from_str(value),
+                ) {
+                    headers.append(name, value);
                 }
-            } else {
-                Err(anyhow::anyhow!("Failed to parse URL: {}", e))
             }
         }
     }
+    network.apply(ClientBuilder::new().default_headers(headers))
 }
 
-/// FILENAME 不是 PATH !!!
+/// 服务器返回的状态码不表示成功，任务以[`TaskFinalStage::HttpStatus`]结束
 ///
-/// 规则为filename.ext -> filename(1).ext -> filename(2).ext ...
-fn get_filename_no_duplicate(dir: &Path, filename: &str) -> String {
-    let mut path = dir.join(filename);
-    if !path.exists() {
-        return filename.to_string();
-    }
-
-    let mut count: u64 = 1;
-    let stem = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("file")
-        .to_string();
-    let extension = path
-        .extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_string());
+/// [`TaskFinalStage::HttpStatus`]: crate::app::task::TaskFinalStage::HttpStatus
+#[derive(Debug)]
+struct HttpStatusError(reqwest::StatusCode);
 
-    loop {
-        let new_filename = if let Some(ext) = &extension {
-            format!("{}({}).{}", stem, count, ext)
-        } else {
-            format!("{}({})", stem, count)
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server returned {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// 连接失败时说明尝试的IP版本，便于排查某一种IP版本不可用的问题
+fn connection_error(e: &anyhow::Error, network: &NetworkOptions) -> String {
+    let message = error_chain(&**e);
+    match e.downcast_ref::<reqwest::Error>() {
+        Some(e) if e.is_connect() || e.is_timeout() => {
+            format!("{} (attempted {})", message, network.attempted_version())
+        }
+        _ => message,
+    }
+}
+
+/// 进入新的阶段，同时通知观察者
+fn enter_phase(task: &TaskInner, ctx: &TaskContext, phase: TaskPhase) {
+    {
+        let mut state = task.state.lock().unwrap();
+        state.phase = phase;
+        state.phase_progress = None;
+    }
+    ctx.events.state_change(&task.state, phase);
+}
+
+/// 指定了校验和时，创建用于边下载边计算哈希值的[`StreamHasher`]
+fn new_hasher(task: &TaskInner) -> Option<StreamHasher> {
+    let state = task.state.lock().unwrap();
+    let expected = state.expected_checksum()?;
+    Some(StreamHasher::new(expected.checksum.algorithm()))
+}
+
+// 校验和文件（解压之后）的最大长度
+const REMOTE_CHECKSUM_MAX_LEN: u64 = 64 * 1024;
+
+/// 依次尝试获取`<url>.sha256`、`<url>.sha512`和`<url>.md5`，找到时记录在任务状态中
+///
+/// 用户已经输入了校验和时不会获取。校验和文件不存在、超时或者无法解析时不做校验，
+/// 不影响下载本身。这些请求允许压缩，见[`build_metadata_client`]。
+async fn fetch_remote_checksum(task: &TaskInner, network: &Arc<NetworkOptions>) {
+    let url = {
+        let state = task.state.lock().unwrap();
+        if state.options().is_some_and(|o| o.checksum.is_some()) {
+            return;
+        }
+        match state.url.clone() {
+            Some(url) => url,
+            None => return,
+        }
+    };
+    let filename = match url.path_segments().and_then(|mut s| s.next_back()) {
+        Some(name) if !name.is_empty() => percent_decode_str(name).decode_utf8_lossy().to_string(),
+        _ => return,
+    };
+    let client = match build_metadata_client(task, network) {
+        Ok(client) => client,
+        Err(e) => {
+            log::debug!(target:"Task", "Cannot build client for checksum files: {}", e);
+            return;
+        }
+    };
+    for algorithm in HashAlgorithm::REMOTE_ORDER {
+        let mut checksum_url = url.clone();
+        checksum_url.set_query(None);
+        checksum_url.set_fragment(None);
+        checksum_url.set_path(&format!("{}.{}", url.path(), algorithm));
+        let content = match fetch_checksum_file(&client, checksum_url.clone()).await {
+            Ok(content) => content,
+            Err(e) => {
+                log::debug!(target:"Task", "No checksum at {}: {}", checksum_url, e);
+                continue;
+            }
+        };
+        match checksum::parse_checksum_file(&content, &filename, algorithm) {
+            Some(checksum) => {
+                log::info!(target:"Task", "Using checksum from {}", checksum_url);
+                task.state.lock().unwrap().remote_checksum = Some(checksum);
+                return;
+            }
+            None => {
+                log::debug!(target:"Task", "No checksum for {} in {}", filename, checksum_url);
+            }
+        }
+    }
+}
+
+async fn fetch_checksum_file(client: &reqwest::Client, url: Url) -> anyhow::Result<String> {
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    // 校验和文件一般只有几行，过大的响应不可能是校验和文件。
+    // 压缩的响应没有原始长度，因此边读边检查解压之后的长度
+    if response
+        .content_length()
+        .is_some_and(|len| len > REMOTE_CHECKSUM_MAX_LEN)
+    {
+        anyhow::bail!("response too large");
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() as u64 > REMOTE_CHECKSUM_MAX_LEN {
+            anyhow::bail!("response too large");
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// 配置中没有指定下载目录时使用的目录
+pub fn default_download_dir() -> PathBuf {
+    let base_dirs = directories::BaseDirs::new().unwrap();
+    base_dirs.home_dir().join("Downloads")
+}
+
+/// 自动生成的文件名在`dir`中已经存在，并且大小与`content_length`相同时，返回该文件的路径
+///
+/// 长度未知时无法判断是否为同一个文件，总是返回[`None`]。
+fn existing_copy(dir: &Path, filename: &str, content_length: Option<u64>) -> Option<PathBuf> {
+    let path = dir.join(filename);
+    let metadata = std::fs::metadata(&path).ok()?;
+    (metadata.is_file() && Some(metadata.len()) == content_length).then_some(path)
+}
+
+/// [`get_download_head`]找到了大小相同的已有文件时，判断是否可以直接使用该文件
+///
+/// 有校验和时重新读取文件进行校验，校验失败时与普通的同名文件一样添加后缀，继续下载。
+/// 没有校验和时只比较大小。直接使用已有文件时发送[`TaskResult::new_already_exists`]。
+///
+/// 返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+async fn reuse_existing_file(
+    task: &TaskInner,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) -> Option<SignalHandler> {
+    let checksum = {
+        let state = task.state.lock().unwrap();
+        state.expected_checksum().map(|expected| expected.checksum)
+    };
+    let handler = match checksum {
+        None => handler,
+        Some(checksum) => {
+            let (actual, handler) = verify_file(task, checksum.algorithm(), handler, ctx).await?;
+            if !checksum.matches(&actual) {
+                let mut state = task.state.lock().unwrap();
+                log::info!(
+                    target:"Task",
+                    "Existing {} does not match {}, downloading again",
+                    state.filepath.display(),
+                    checksum
+                );
+                let dir = state
+                    .filepath
+                    .parent()
+                    .unwrap_or(Path::new("."))
+                    .to_path_buf();
+                let fname = state
+                    .filepath
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or(String::from("tmp.bin"));
+                state.filepath = dir.join(naming::get_filename_no_duplicate(&dir, &fname));
+                state.phase_progress = None;
+                return Some(handler);
+            }
+            handler
+        }
+    };
+
+    {
+        let mut state = task.state.lock().unwrap();
+        log::info!(target:"Task", "{} already exists, skip downloading", state.filepath.display());
+        state.downloaded = state.content_length.unwrap_or(state.downloaded);
+    }
+    enter_phase(task, ctx, TaskPhase::Done);
+    handler
+        .reporter
+        .send(TaskResult::new_already_exists())
+        .unwrap();
+    None
+}
+
+/// 用户明确指定的保存路径上已经存在文件时，不应该像自动生成的文件名那样静默地添加后缀，
+/// 也不应该直接覆盖，而是暂停任务，等待用户通过[`TaskCommand::ResolveConflict`]做出选择。
+///
+/// 等待期间归还`permit`，做出选择之后重新排队。超过配置中的`decision_timeout`仍然没有回应时，
+/// 与自动生成的文件名一样添加后缀，不会覆盖已有的文件。
+///
+/// 返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+async fn resolve_user_destination(
+    task: &TaskInner,
+    path: PathBuf,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+    permit: &mut SlotPermit,
+) -> Option<(PathBuf, SignalHandler)> {
+    let metadata = match tokio::fs::metadata(&path).await {
+        Ok(m) => m,
+        Err(_) => return Some((path, handler)),
+    };
+
+    {
+        let mut state = task.state.lock().unwrap();
+        state.filepath = path.clone();
+        state.conflict = Some(FileConflict::new(
+            path.clone(),
+            metadata.len(),
+            metadata.modified().ok(),
+        ));
+    }
+    enter_phase(task, ctx, TaskPhase::WaitingForDecision);
+    permit.release();
+
+    let SignalHandler {
+        reporter,
+        mut receiver,
+    } = handler;
+    let deadline = decision_deadline(ctx);
+    let resolution = loop {
+        let Ok(command) = recv_before(&mut receiver, deadline).await else {
+            log::info!(
+                target:"Task",
+                "No answer for existing {} within {}, renaming",
+                path.display(),
+                ctx.decision_timeout.map(common::format_duration).unwrap_or_default()
+            );
+            break ConflictResolution::Rename;
         };
-        path = dir.join(&new_filename);
-        if !path.exists() {
-            return new_filename;
+        match command {
+            Some(TaskCommand::ResolveConflict(resolution)) => break resolution,
+            // 等待期间停止或者取消任务时，都视为取消，因为此时还没有开始下载
+            Some(TaskCommand::Stop) | Some(TaskCommand::Abort) => break ConflictResolution::Cancel,
+            Some(command) => record_setting(task, command),
+            None => {
+                let _ = reporter.send(TaskResult::new_unknown_error(String::from(
+                    "Command channel closed unexpectedly",
+                )));
+                return None;
+            }
+        }
+    };
+    task.state.lock().unwrap().conflict = None;
+
+    let path = match resolution {
+        ConflictResolution::Overwrite => path,
+        ConflictResolution::Rename => {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let fname = path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or(String::from("tmp.bin"));
+            dir.join(naming::get_filename_no_duplicate(dir, &fname))
+        }
+        ConflictResolution::Cancel => {
+            reporter.send(TaskResult::new_abort()).unwrap();
+            return None;
         }
-        count += 1;
+    };
+    let handler = reacquire_slot(task, permit, SignalHandler::new(reporter, receiver), ctx).await?;
+    Some((path, handler))
+}
+
+/// 等待用户做出决定的截止时间，关闭了`decision_timeout`时为[`None`]
+fn decision_deadline(ctx: &TaskContext) -> Option<tokio::time::Instant> {
+    ctx.decision_timeout
+        .map(|timeout| tokio::time::Instant::now() + timeout)
+}
+
+/// 接收下一个指令，超过`deadline`时返回[`Elapsed`]
+async fn recv_before(
+    receiver: &mut mpsc::UnboundedReceiver<TaskCommand>,
+    deadline: Option<tokio::time::Instant>,
+) -> Result<Option<TaskCommand>, Elapsed> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, receiver.recv()).await,
+        None => Ok(receiver.recv().await),
     }
 }
 
+/// `dest`为用户指定（并且已经处理过冲突）的保存路径，为[`None`]时根据URL自动生成
+///
+/// 确定文件名之后，根据文件名和Content-Type检查`policy`，匹配的规则记录在任务状态中，
+/// 由[`enforce_file_type_policy`]处理。
+///
+/// 自动生成文件名并且`reuse_existing`时，如果同名文件的大小与Content-Length相同，
+/// 保存路径指向该文件而不是添加后缀，返回值中的`bool`为`true`，
+/// 由[`reuse_existing_file`]决定是否需要重新下载。
+///
+/// 部分下载时发送`Range`请求头，自动生成的文件名添加范围的后缀，见[`ByteRange`]。
 async fn get_download_head(
     task: &TaskInner,
     url: Url,
     client: &reqwest::Client,
     download_dir: &Path,
-) -> anyhow::Result<impl Stream<Item = reqwest::Result<Bytes>>> {
-    let response = client.get(url.clone()).send().await?;
+    dest: Option<PathBuf>,
+    policy: &FileTypePolicy,
+    reuse_existing: bool,
+) -> anyhow::Result<(impl Stream<Item = reqwest::Result<Bytes>>, bool)> {
+    let range = requested_range(task);
+    let mut request = client.get(url.clone());
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range.header_value());
+    }
+    let sent = Instant::now();
+    let response = request.send().await?;
+    // 服务器返回的错误页面不应该被保存为文件
+    if !response.status().is_success() {
+        return Err(HttpStatusError(response.status()).into());
+    }
 
+    let (skip, take) = apply_range(task, range, &response)?;
+    let content_length = task.state.lock().unwrap().content_length;
     let head = response.headers();
-    let content_length = head
-        .get(header::CONTENT_LENGTH)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.parse::<u64>().ok());
-    let accept_ranges = head
-        .get(header::ACCEPT_RANGES)
-        .and_then(|v| v.to_str().ok())
-        .is_some_and(|s| s.eq_ignore_ascii_case("bytes"));
-    let dest = {
-        let opt_fname = url
-            .path_segments()
-            .and_then(|mut segments| segments.next_back())
-            .and_then(|name| if name.is_empty() { None } else { Some(name) });
-        let fname = opt_fname
-            .or(response
-                .url()
-                .path_segments()
-                .and_then(|mut segments| segments.next_back())
-                .and_then(|name| if name.is_empty() { None } else { Some(name) }))
-            .unwrap_or("tmp.bin");
-
-        // FIXME:
-        // 由于当前会首先搜索目录下是否有同名文件，然后创建文件，存在这样一种情况，
-        // 同时下载两个同名文件时，两者同时检测到没有同名文件，然后创建了同名文件，导致冲突。
-        download_dir.join(get_filename_no_duplicate(download_dir, fname))
-    };
-
-    // TODO: handle Content-Disposition
+    let (dest, existing) = match dest {
+        Some(dest) => (dest, false),
+        None => {
+            let disposition = head
+                .get(header::CONTENT_DISPOSITION)
+                .and_then(|v| v.to_str().ok());
+            let mut fname = naming::choose_filename(&url, response.url(), disposition);
+            if let Some(range) = range {
+                fname.push_str(&range.filename_suffix());
+            }
+
+            match existing_copy(download_dir, &fname, content_length).filter(|_| reuse_existing) {
+                Some(path) => (path, true),
+                // FIXME:
+                // 由于当前会首先搜索目录下是否有同名文件，然后创建文件，存在这样一种情况，
+                // 同时下载两个同名文件时，两者同时检测到没有同名文件，然后创建了同名文件，导致冲突。
+                None => (
+                    download_dir.join(naming::get_filename_no_duplicate(download_dir, &fname)),
+                    false,
+                ),
+            }
+        }
+    };
+
+    let content_type = head.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    let filename = dest
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let policy_match = policy.evaluate(&filename, content_type, content_length);
 
     {
         let mut state = task.state.lock().unwrap();
-        state.content_length = content_length;
-        state.accept_ranges = accept_ranges;
         state.filepath = dest;
         state.url = Some(response.url().clone());
+        state.security = Some(ConnectionSecurity::from_response(&response));
+        state.policy_match = policy_match;
+        state.content_type = content_type.map(str::to_string);
+    }
+
+    let stream = first_byte_stream(task, sent, response).await;
+
+    Ok((limit_stream(stream, skip, take), existing))
+}
+
+/// 处理[`get_download_head`]中匹配到的文件类型规则
+///
+/// 阻止时直接使任务失败；提示时暂停任务，等待用户通过[`TaskCommand::ConfirmDownload`]确认，
+/// 等待期间停止或者取消任务都视为不继续下载。
+///
+/// 等待期间响应保持打开，只归还全局的名额，所在主机的名额仍然占用，见[`SlotPermit::park`]，
+/// 确认之后重新排队。超过配置中的`decision_timeout`仍然没有回应时取消任务。
+///
+/// 返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+async fn enforce_file_type_policy(
+    task: &TaskInner,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+    permit: &mut SlotPermit,
+) -> Option<SignalHandler> {
+    let (policy_match, filepath) = {
+        let state = task.state.lock().unwrap();
+        (state.policy_match.clone(), state.filepath.clone())
+    };
+    let Some(policy_match) = policy_match else {
+        return Some(handler);
+    };
+    if policy_match.action == PolicyAction::Block {
+        log::info!(target:"Task", "Blocked {}: {}", filepath.display(), policy_match.rule);
+        handler
+            .reporter
+            .send(TaskResult::new_blocked_by_policy(format!(
+                "{} matches {}",
+                filepath.display(),
+                policy_match.rule
+            )))
+            .unwrap();
+        return None;
     }
 
-    let stream = response.bytes_stream();
+    enter_phase(task, ctx, TaskPhase::AwaitingConfirm);
+    permit.park();
+    let SignalHandler {
+        reporter,
+        mut receiver,
+    } = handler;
+    let deadline = decision_deadline(ctx);
+    let confirmed = loop {
+        let Ok(command) = recv_before(&mut receiver, deadline).await else {
+            log::info!(
+                target:"Task",
+                "No confirmation for {} within {}, canceling",
+                filepath.display(),
+                ctx.decision_timeout.map(common::format_duration).unwrap_or_default()
+            );
+            break false;
+        };
+        match command {
+            Some(TaskCommand::ConfirmDownload(confirmed)) => break confirmed,
+            Some(TaskCommand::Stop) | Some(TaskCommand::Abort) => break false,
+            Some(command) => record_setting(task, command),
+            None => {
+                let _ = reporter.send(TaskResult::new_unknown_error(String::from(
+                    "Command channel closed unexpectedly",
+                )));
+                return None;
+            }
+        }
+    };
+    if !confirmed {
+        reporter.send(TaskResult::new_abort()).unwrap();
+        return None;
+    }
+    log::info!(target:"Task", "Download of {} confirmed: {}", filepath.display(), policy_match.rule);
+    let handler = reacquire_slot(task, permit, SignalHandler::new(reporter, receiver), ctx).await?;
+    enter_phase(task, ctx, TaskPhase::Connecting);
+    Some(handler)
+}
 
-    Ok(stream)
+/// 下载前检查剩余的数据能否放进下载目录所在的文件系统，内容长度未知时不做检查
+fn check_disk_space(task: &TaskInner) -> Result<(), TaskResult> {
+    let (filepath, required) = {
+        let state = task.state.lock().unwrap();
+        let Some(content_length) = state.content_length() else {
+            return Ok(());
+        };
+        (
+            state.filepath().to_path_buf(),
+            content_length.saturating_sub(state.downloaded()),
+        )
+    };
+    // 查询失败时不阻止下载，真正写满时仍然会报告空间不足
+    let Ok(available) = disk::available_space(&filepath) else {
+        return Ok(());
+    };
+    if available < required {
+        return Err(TaskResult::new_insufficient_disk_space(format!(
+            "{} bytes required but only {} bytes available",
+            required, available
+        )));
+    }
+    Ok(())
 }
 
-async fn create_download_file(filepath: &Path) -> anyhow::Result<BufWriter<File>> {
-    let file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(filepath)
+async fn create_download_file(
+    filepath: &Path,
+    task: &TaskInner,
+    buffers: BufferSizes,
+) -> anyhow::Result<BufWriter<File>> {
+    let mut options = OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    let file = BusyRetry::default()
+        .open(&options, filepath, &task.state)
         .await?;
-    Ok(BufWriter::new(file))
+    Ok(buffered(task, file, buffers))
+}
+
+/// 使用配置的大小创建写入文件的缓冲区，同时记录到任务的状态中
+fn buffered(task: &TaskInner, file: File, buffers: BufferSizes) -> BufWriter<File> {
+    task.state.lock().unwrap().buffers = Some(buffers);
+    BufWriter::with_capacity(buffers.write, file)
 }
 
 async fn flush_file_buffer(
+    task: &TaskInner,
     file: &mut BufWriter<File>,
     reporter: oneshot::Sender<TaskResult>,
 ) -> Option<oneshot::Sender<TaskResult>> {
-    match file.flush().await {
+    match BusyRetry::default().flush(file, &task.state).await {
         Ok(_) => Some(reporter),
         Err(e) => {
+            let file_error = FileError::new(&e, &task.state.lock().unwrap().filepath);
             reporter
-                .send(TaskResult::new_failed_to_write(e.to_string()))
+                .send(TaskResult::new_failed_to_write(e.to_string()).with_file_error(file_error))
                 .unwrap();
             None
         }
@@ -220,47 +1163,116 @@ async fn download_stream_to_file(
     mut stream: Pin<&mut impl Stream<Item = reqwest::Result<Bytes>>>,
     file: &mut BufWriter<File>,
     handler: SignalHandler,
+    ctx: &TaskContext,
+    hasher: &mut Option<StreamHasher>,
 ) -> Option<SignalHandler> {
     let reporter = handler.reporter;
     let mut cmd_recv = handler.receiver;
+    let mut last_progress = None;
+    let mut chunk_log = LogLimiter::default();
+    let mut task_throttle = TaskThrottle::new(task.state.lock().unwrap().speed_limit);
+    // 离开该函数（暂停、出错或者下载完成）时退出公平模式下的份额
+    let share = ctx.throttle.join();
+    share.set_boost(task.state.lock().unwrap().boost);
+    let mut disk_bound = false;
+    enter_phase(task, ctx, TaskPhase::Downloading);
     while let Some(chunk) = stream.next().await {
         let data = match chunk {
             Ok(d) => d,
             Err(e) => {
                 reporter
-                    .send(TaskResult::new_failed_to_download(e.to_string()))
+                    .send(TaskResult::new_failed_to_download(error_chain(&e)))
                     .unwrap();
                 return None;
             }
         };
 
-        if let Err(e) = file.write_all(&data).await {
-            reporter
-                .send(TaskResult::new_failed_to_write(e.to_string()))
-                .unwrap();
+        let write_started = Instant::now();
+        let written = BusyRetry::default()
+            .write_all(file, &data, &task.state)
+            .await;
+        let write_elapsed = write_started.elapsed();
+        if let Err(e) = written {
+            let file_error = FileError::new(&e, &task.state.lock().unwrap().filepath);
+            let result = if e.kind() == std::io::ErrorKind::StorageFull {
+                // 缓冲区中的数据没有写入文件，以文件的实际长度为准，这样恢复时不会被视为文件损坏
+                if let Ok(metadata) = file.get_ref().metadata().await {
+                    task.state.lock().unwrap().downloaded = metadata.len();
+                }
+                TaskResult::new_insufficient_disk_space(e.to_string())
+            } else {
+                TaskResult::new_failed_to_write(e.to_string()).with_file_error(file_error)
+            };
+            reporter.send(result).unwrap();
             return None;
         }
 
-        {
+        if let Some(hasher) = hasher {
+            hasher.update(&data).await;
+        }
+
+        let now_disk_bound = {
             let mut state = task.state.lock().unwrap();
-            state.downloaded += data.len() as u64;
-        } // MutexGuard drop here
+            state.record_received(data.len() as u64);
+            state.record_write(write_elapsed);
+            state.write_latency().is_disk_bound()
+        }; // MutexGuard drop here
+        if now_disk_bound != disk_bound {
+            disk_bound = now_disk_bound;
+            let path = task.state.lock().unwrap().filepath().to_path_buf();
+            if disk_bound {
+                log::warn!(
+                    target:"Task",
+                    "Writes to {} are slow, the disk is the bottleneck",
+                    path.display()
+                );
+            } else {
+                log::info!(target:"Task", "Writes to {} are fast again", path.display());
+            }
+        }
+
+        ctx.events.progress(&task.state, &mut last_progress);
+        if let Some(suppressed) = chunk_log.check() {
+            let state = task.state.lock().unwrap();
+            log::trace!(
+                target:"Task",
+                "{}: received {} bytes, {}/{:?} in total ({} similar messages suppressed)",
+                state.filepath().display(),
+                data.len(),
+                state.downloaded(),
+                state.content_length(),
+                suppressed
+            );
+        }
+        share.consume(data.len()).await;
+        task_throttle.consume(data.len()).await;
 
         // 监听指令（非异步）
         match cmd_recv.try_recv() {
             Ok(signal) => match signal {
                 TaskCommand::Stop => {
-                    let reporter = flush_file_buffer(file, reporter).await?;
+                    let reporter = flush_file_buffer(task, file, reporter).await?;
                     reporter.send(TaskResult::new_interrupted()).unwrap();
                     return None;
                 }
                 TaskCommand::Abort => {
-                    let reporter = flush_file_buffer(file, reporter).await?;
+                    let reporter = flush_file_buffer(task, file, reporter).await?;
                     reporter.send(TaskResult::new_abort()).unwrap();
                     return None;
                 }
+                TaskCommand::SetSpeedLimit(limit) => {
+                    record_setting(task, signal);
+                    task_throttle.set_limit(limit);
+                }
+                // 已经开始下载，优先级只影响之后的排队，加速立即生效
+                TaskCommand::SetPriority { boost, .. } => {
+                    record_setting(task, signal);
+                    share.set_boost(boost);
+                }
+                // 冲突和文件类型已经在下载开始前处理过了
+                TaskCommand::ResolveConflict(_) | TaskCommand::ConfirmDownload(_) => {}
             },
-            Err(oneshot::error::TryRecvError::Closed) => {
+            Err(mpsc::error::TryRecvError::Disconnected) => {
                 let _ = reporter.send(TaskResult::new_unknown_error(String::from(
                     "Command channel closed unexpectedly",
                 )));
@@ -268,42 +1280,336 @@ async fn download_stream_to_file(
                 // 因此我们发送失败后直接忽略
                 return None;
             }
-            Err(oneshot::error::TryRecvError::Empty) => {}
+            Err(mpsc::error::TryRecvError::Empty) => {}
         }
     }
 
-    let reporter = flush_file_buffer(file, reporter).await?;
+    let reporter = flush_file_buffer(task, file, reporter).await?;
 
     Some(SignalHandler::new(reporter, cmd_recv))
 }
 
+/// 数据全部写入之后，将文件同步到磁盘，指定了校验和时进行校验，最后发送结果
+///
+/// `hasher`为下载时计算的哈希值，为[`None`]时（例如从中途恢复的任务）重新读取文件计算，
+/// 后台计算的线程异常退出时同样如此。
+async fn finish_download(
+    task: &TaskInner,
+    file: BufWriter<File>,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+    hasher: Option<StreamHasher>,
+) {
+    enter_phase(task, ctx, TaskPhase::Syncing);
+    if let Err(e) = file.get_ref().sync_all().await {
+        let file_error = FileError::new(&e, &task.state.lock().unwrap().filepath);
+        handler
+            .reporter
+            .send(TaskResult::new_failed_to_write(e.to_string()).with_file_error(file_error))
+            .unwrap();
+        return;
+    }
+    drop(file);
+
+    let checksum = {
+        let state = task.state.lock().unwrap();
+        state.expected_checksum().map(|expected| expected.checksum)
+    };
+    let handler = match checksum {
+        None => handler,
+        Some(checksum) => {
+            let digest = match hasher {
+                Some(hasher) => hasher.finish().await,
+                None => None,
+            };
+            let (actual, handler) = match digest {
+                Some(actual) => (actual, handler),
+                None => match verify_file(task, checksum.algorithm(), handler, ctx).await {
+                    Some(verified) => verified,
+                    None => return,
+                },
+            };
+            if !checksum.matches(&actual) {
+                handler
+                    .reporter
+                    .send(TaskResult::new_checksum_mismatch(
+                        checksum.to_string(),
+                        format!("{}:{}", checksum.algorithm(), actual),
+                    ))
+                    .unwrap();
+                return;
+            }
+            task.state.lock().unwrap().verified_checksum =
+                Checksum::new(checksum.algorithm(), &actual).ok();
+            handler
+        }
+    };
+
+    // 文件已经完整，设置权限失败时任务仍然视为成功
+    let (extract, file_mode, filepath) = {
+        let state = task.state.lock().unwrap();
+        let options = state.options();
+        (
+            options.is_some_and(|o| o.extract),
+            options.and_then(|o| o.file_mode).or(ctx.modes.file),
+            state.filepath.clone(),
+        )
+    };
+    let mode_note = match file_mode {
+        Some(mode) => match permissions::set_file_mode(&filepath, mode).await {
+            Ok(()) => None,
+            Err(e) => {
+                log::warn!(target:"Task", "Failed to set mode {} on {}: {}", mode, filepath.display(), e);
+                Some(format!("Failed to set mode {}, {}", mode, e))
+            }
+        },
+        None => None,
+    };
+    let mut result = if extract {
+        extract_download(task, ctx).await
+    } else {
+        TaskResult::new_finished()
+    };
+    if result.message.is_none() {
+        result.message = mode_note;
+    }
+    if let Some(dir) = &ctx.completed_dir {
+        publish_completed(task, ctx, dir).await;
+    }
+
+    enter_phase(task, ctx, TaskPhase::Done);
+    handler.reporter.send(result).unwrap();
+}
+
+/// 将下载完成的文件链接或者复制到`dir`中，见[`CompletedCopy`]
+///
+/// 失败时任务仍然视为成功，只在完成列表中标注并且记录日志。期间不响应停止和取消的指令。
+async fn publish_completed(task: &TaskInner, ctx: &TaskContext, dir: &Path) {
+    enter_phase(task, ctx, TaskPhase::Publishing);
+    let source = task.state.lock().unwrap().filepath.clone();
+    let copy = {
+        let state = task.state.clone();
+        let source = source.clone();
+        let dir = dir.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            completed::publish(&source, &dir, |progress| {
+                state.lock().unwrap().phase_progress = Some(progress);
+            })
+        })
+        .await
+        .unwrap_or_else(|e| CompletedCopy::Failed(e.to_string()))
+    };
+    match &copy {
+        CompletedCopy::Linked(path) => {
+            log::info!(target:"Task", "Linked {} to {}", source.display(), path.display());
+        }
+        CompletedCopy::Copied(path) => {
+            log::info!(target:"Task", "Copied {} to {}", source.display(), path.display());
+        }
+        CompletedCopy::Failed(e) => {
+            log::warn!(target:"Task", "Failed to copy {} to {}: {}", source.display(), dir.display(), e);
+        }
+    }
+    task.state.lock().unwrap().completed = Some(copy);
+}
+
+/// 将下载完成的压缩包解压到同级的目录中
+///
+/// 此时文件已经完整，因此解压失败或者格式不支持时任务仍然视为成功，只在结果中附带说明。
+/// 解压期间不响应停止和取消的指令。
+async fn extract_download(task: &TaskInner, ctx: &TaskContext) -> TaskResult {
+    let archive = task.state.lock().unwrap().filepath.clone();
+    let Some(kind) = ArchiveKind::from_path(&archive) else {
+        log::info!(target:"Task", "Skip extracting {}: unsupported format", archive.display());
+        return TaskResult::new_finished_with_note(String::from(
+            "Extraction skipped, unsupported format",
+        ));
+    };
+
+    enter_phase(task, ctx, TaskPhase::Extracting);
+    let dest = extract::extraction_dir(&archive, kind);
+    let extracted = {
+        let state = task.state.clone();
+        let archive = archive.clone();
+        let dest = dest.clone();
+        tokio::task::spawn_blocking(move || {
+            extract::extract_archive(&archive, &dest, kind, |progress| {
+                state.lock().unwrap().phase_progress = Some(progress);
+            })
+        })
+        .await
+    };
+    let error = match extracted {
+        Ok(Ok(())) => {
+            log::info!(target:"Task", "Extracted {} to {}", archive.display(), dest.display());
+            task.state.lock().unwrap().extracted = Some(dest);
+            return TaskResult::new_finished();
+        }
+        Ok(Err(e)) => e.to_string(),
+        Err(e) => e.to_string(),
+    };
+    log::warn!(target:"Task", "Failed to extract {}: {}", archive.display(), error);
+    TaskResult::new_finished_with_note(format!("Extraction failed, {}", error))
+}
+
+/// 重新读取整个文件计算哈希值，期间更新校验的进度，并且响应停止和取消的指令
+///
+/// 返回[`None`]表示任务已经结束，结果已经发送给UI线程。
+async fn verify_file(
+    task: &TaskInner,
+    algorithm: HashAlgorithm,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+) -> Option<(String, SignalHandler)> {
+    const CHUNK_SIZE: usize = 1024 * 1024;
+
+    enter_phase(task, ctx, TaskPhase::Verifying);
+    let SignalHandler {
+        reporter,
+        mut receiver,
+    } = handler;
+    let filepath = task.state.lock().unwrap().filepath.clone();
+    let mut file = match File::open(&filepath).await {
+        Ok(f) => f,
+        Err(e) => {
+            reporter
+                .send(
+                    TaskResult::new_failed_to_resume_file(e.to_string())
+                        .with_file_error(FileError::new(&e, &filepath)),
+                )
+                .unwrap();
+            return None;
+        }
+    };
+    let total = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+
+    let mut hasher = Hasher::new(algorithm);
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut read = 0;
+    loop {
+        let n = match file.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                reporter
+                    .send(
+                        TaskResult::new_failed_to_resume_file(e.to_string())
+                            .with_file_error(FileError::new(&e, &filepath)),
+                    )
+                    .unwrap();
+                return None;
+            }
+        };
+        hasher.update(&buf[..n]);
+        read += n as u64;
+        task.state.lock().unwrap().phase_progress = Some(if total == 0 {
+            1.0
+        } else {
+            read as f64 / total as f64
+        });
+
+        match receiver.try_recv() {
+            // 文件已经完整，恢复时会直接重新校验
+            Ok(TaskCommand::Stop) => {
+                reporter.send(TaskResult::new_interrupted()).unwrap();
+                return None;
+            }
+            Ok(TaskCommand::Abort) => {
+                reporter.send(TaskResult::new_abort()).unwrap();
+                return None;
+            }
+            Ok(TaskCommand::ResolveConflict(_)) | Err(mpsc::error::TryRecvError::Empty) => {}
+            Ok(command) => record_setting(task, command),
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                let _ = reporter.send(TaskResult::new_unknown_error(String::from(
+                    "Command channel closed unexpectedly",
+                )));
+                return None;
+            }
+        }
+    }
+
+    Some((hasher.finalize(), SignalHandler::new(reporter, receiver)))
+}
+
+/// 重新校验已经下载完成的文件，见[`DownloadRequest::Verify`]
+///
+/// 校验期间同样响应停止和取消的指令，停止之后恢复时重新开始校验。
+async fn verify_existing_file(task: &TaskInner, handler: SignalHandler, ctx: &TaskContext) {
+    let checksum = task
+        .state
+        .lock()
+        .unwrap()
+        .expected_checksum()
+        .map(|expected| expected.checksum);
+    let Some(checksum) = checksum else {
+        handler
+            .reporter
+            .send(TaskResult::new_unknown_error(String::from(
+                "No checksum to verify against",
+            )))
+            .unwrap();
+        return;
+    };
+    let Some((actual, handler)) = verify_file(task, checksum.algorithm(), handler, ctx).await
+    else {
+        return;
+    };
+    if !checksum.matches(&actual) {
+        handler
+            .reporter
+            .send(TaskResult::new_checksum_mismatch(
+                checksum.to_string(),
+                format!("{}:{}", checksum.algorithm(), actual),
+            ))
+            .unwrap();
+        return;
+    }
+    {
+        let mut state = task.state.lock().unwrap();
+        log::info!(target:"Task", "{} matches {}", state.filepath.display(), checksum);
+        state.verified_checksum = Checksum::new(checksum.algorithm(), &actual).ok();
+    }
+    enter_phase(task, ctx, TaskPhase::Done);
+    handler
+        .reporter
+        .send(TaskResult::new_finished_with_note(String::from(
+            "Checksum verified",
+        )))
+        .unwrap();
+}
+
 async fn resume_file(
+    task: &TaskInner,
     filepath: &Path,
     downloaded: u64,
     accept_range: bool,
+    buffers: BufferSizes,
 ) -> Result<BufWriter<File>, TaskResult> {
-    if !accept_range {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(filepath)
-            .await
-            .map_err(|e| TaskResult::new_failed_to_resume_file(e.to_string()))?;
-        return Ok(BufWriter::new(file));
+    let mut options = OpenOptions::new();
+    if accept_range {
+        options.create(false).append(true);
+    } else {
+        options.create(true).write(true).truncate(true);
     }
-
-    let file = OpenOptions::new()
-        .create(false)
-        .append(true)
-        .open(filepath)
+    let file = BusyRetry::default()
+        .open(&options, filepath, &task.state)
         .await
-        .map_err(|e| TaskResult::new_failed_to_resume_file(e.to_string()))?;
+        .map_err(|e| {
+            TaskResult::new_failed_to_resume_file(e.to_string())
+                .with_file_error(FileError::new(&e, filepath))
+        })?;
+    if !accept_range {
+        return Ok(buffered(task, file, buffers));
+    }
 
     if file
         .metadata()
         .await
-        .map_err(|e| TaskResult::new_failed_to_resume_file(e.to_string()))?
+        .map_err(|e| {
+            TaskResult::new_failed_to_resume_file(e.to_string())
+                .with_file_error(FileError::new(&e, filepath))
+        })?
         .len()
         < downloaded
     {
@@ -312,17 +1618,71 @@ async fn resume_file(
         ));
     }
 
-    file.set_len(downloaded)
-        .await
-        .map_err(|e| TaskResult::new_failed_to_resume_file(e.to_string()))?;
+    file.set_len(downloaded).await.map_err(|e| {
+        TaskResult::new_failed_to_resume_file(e.to_string())
+            .with_file_error(FileError::new(&e, filepath))
+    })?;
 
-    Ok(BufWriter::new(file))
+    Ok(buffered(task, file, buffers))
+}
+
+/// 对比记录的进度与已经下载的文件的实际长度，返回继续下载的位置，见[`reconcile_partial`]
+///
+/// 位置与记录的不同时同时修改任务的进度。文件不存在或者无法读取时返回错误。
+async fn reconcile_resume_offset(
+    task: &TaskInner,
+    filepath: &Path,
+    recorded: u64,
+    ctx: &TaskContext,
+) -> Result<u64, TaskResult> {
+    let on_disk = match tokio::fs::metadata(filepath).await {
+        Ok(metadata) => Some(metadata.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(TaskResult::new_failed_to_resume_file(e.to_string())
+                .with_file_error(FileError::new(&e, filepath)));
+        }
+    };
+    let plan = reconcile_partial(recorded, on_disk, ctx.oversized_partial);
+    let Some(offset) = plan.offset() else {
+        log::warn!(target:"Task", "Cannot resume {}: {}", filepath.display(), plan);
+        let e = std::io::Error::from(std::io::ErrorKind::NotFound);
+        return Err(TaskResult::new_failed_to_resume_file(String::from(
+            "Partially downloaded file is missing",
+        ))
+        .with_file_error(FileError::new(&e, filepath)));
+    };
+    if offset == recorded {
+        log::debug!(target:"Task", "Resuming {}: {}", filepath.display(), plan);
+        return Ok(offset);
+    }
+    log::warn!(target:"Task", "Resuming {}: {}", filepath.display(), plan);
+    let mut state = task.state.lock().unwrap();
+    state.downloaded = offset;
+    state.last_downloaded = offset;
+    // 进度图按照新的位置重建，见[`TaskState::record_received`]
+    state.pieces = None;
+    Ok(offset)
 }
 
-async fn handle_resume_download(task: TaskInner, handler: SignalHandler) {
+async fn handle_resume_download(
+    task: TaskInner,
+    handler: SignalHandler,
+    ctx: &TaskContext,
+    client: Option<reqwest::Client>,
+) {
     let (url, filepath, accept_range, mut downloaded) = {
         let mut state_guard = task.state.lock().unwrap();
-        let url = state_guard.url.clone().unwrap();
+        // 没有开始下载的任务由Sender作为新的任务发送，这里只是防止状态不完整时崩溃
+        let Some(url) = state_guard.url.clone() else {
+            drop(state_guard);
+            let _ = handler
+                .reporter
+                .send(TaskResult::new_unknown_error(String::from(
+                    "Task has no recorded URL to resume from",
+                )));
+            return;
+        };
         let filepath = state_guard.filepath.clone();
         let accept_ranges = state_guard.accept_ranges;
         let mut downloaded = state_guard.downloaded;
@@ -335,6 +1695,8 @@ async fn handle_resume_download(task: TaskInner, handler: SignalHandler) {
         state_guard.last_updated = Instant::now();
         state_guard.last_downloaded = downloaded;
         state_guard.last_speed = None;
+        state_guard.health.reset();
+        state_guard.eta.reset();
 
         (url, filepath, accept_ranges, downloaded)
     }; // MutexGuard unlock here
@@ -342,9 +1704,23 @@ async fn handle_resume_download(task: TaskInner, handler: SignalHandler) {
     if !accept_range {
         downloaded = 0;
         task.state.lock().unwrap().downloaded = 0;
+    } else {
+        match reconcile_resume_offset(&task, &filepath, downloaded, ctx).await {
+            Ok(offset) => downloaded = offset,
+            Err(tr) => {
+                handler.reporter.send(tr).unwrap();
+                return;
+            }
+        }
     }
 
-    let mut file = match resume_file(&filepath, downloaded, accept_range).await {
+    // 在同步或者校验时被停止的任务，数据已经全部下载
+    let complete = accept_range
+        && downloaded > 0
+        && task.state.lock().unwrap().content_length == Some(downloaded);
+
+    let mut file = match resume_file(&task, &filepath, downloaded, accept_range, ctx.buffers).await
+    {
         Ok(f) => f,
         Err(tr) => {
             handler.reporter.send(tr).unwrap();
@@ -352,32 +1728,68 @@ async fn handle_resume_download(task: TaskInner, handler: SignalHandler) {
         }
     };
 
-    let client = match ClientBuilder::new().build() {
+    if complete {
+        finish_download(&task, file, handler, ctx, None).await;
+        return;
+    }
+
+    if let Err(result) = check_disk_space(&task) {
+        handler.reporter.send(result).unwrap();
+        return;
+    }
+
+    let client = match client.map_or_else(|| build_client(&task, &ctx.network), Ok) {
         Ok(c) => c,
         Err(e) => {
             handler
                 .reporter
-                .send(TaskResult::new_failed_to_resume_connection(e.to_string()))
+                .send(TaskResult::new_failed_to_resume_connection(error_chain(&e)))
                 .unwrap();
             return;
         }
     };
 
-    let stream =
+    enter_phase(&task, ctx, TaskPhase::Connecting);
+    let (stream, offset) =
         match get_resume_download_stream(&task, url, &client, downloaded, accept_range).await {
             Ok(s) => s,
             Err(e) => {
                 handler
                     .reporter
-                    .send(TaskResult::new_failed_to_resume_connection(e.to_string()))
+                    .send(TaskResult::new_failed_to_resume_connection(
+                        connection_error(&e, &ctx.network),
+                    ))
                     .unwrap();
                 return;
             }
         };
     let stream = pin!(stream);
 
-    if let Some(handler) = download_stream_to_file(&task, stream, &mut file, handler).await {
-        handler.reporter.send(TaskResult::new_finished()).unwrap();
+    // 服务器没有从继续的位置返回数据，丢弃已经下载的部分从头写入
+    if offset != downloaded {
+        downloaded = offset;
+        if let Err(e) = file.get_ref().set_len(0).await {
+            handler
+                .reporter
+                .send(
+                    TaskResult::new_failed_to_resume_file(e.to_string())
+                        .with_file_error(FileError::new(&e, &filepath)),
+                )
+                .unwrap();
+            return;
+        }
+    }
+
+    // 从头开始下载时可以边下载边计算哈希值，否则下载完成后重新读取文件校验
+    let mut hasher = if downloaded == 0 {
+        new_hasher(&task)
+    } else {
+        None
+    };
+    if let Some(handler) =
+        download_stream_to_file(&task, stream, &mut file, handler, ctx, &mut hasher).await
+    {
+        finish_download(&task, file, handler, ctx, hasher).await;
     }
 }
 
@@ -387,53 +1799,510 @@ async fn get_resume_download_stream(
     client: &reqwest::Client,
     downloaded: u64,
     accept_range: bool,
-) -> anyhow::Result<impl Stream<Item = reqwest::Result<Bytes>>> {
+) -> anyhow::Result<(impl Stream<Item = reqwest::Result<Bytes>>, u64)> {
     if accept_range {
+        let sent = Instant::now();
+        // 部分下载时从范围内已经下载的位置继续，直到范围的末尾
+        let fetched_range = task.state.lock().unwrap().fetched_range;
+        let start = fetched_range.map_or(0, |(start, _)| start) + downloaded;
+        let (range, take) = match fetched_range {
+            Some((_, end)) => (
+                format!("bytes={}-{}", start, end),
+                Some((end + 1).saturating_sub(start)),
+            ),
+            None => (format!("bytes={}-", start), None),
+        };
         let response = client
-            .get(url)
-            .header(
-                header::RANGE,
-                header::HeaderValue::from_str(&format!("bytes={}-", downloaded))?,
-            )
+            .get(url.clone())
+            .header(header::RANGE, header::HeaderValue::from_str(&range)?)
             .send()
             .await?;
 
         let head = response.headers();
-        let content_length = head
-            .get(header::CONTENT_LENGTH)
-            .and_then(|v| v.to_str().ok())
-            .and_then(|s| s.parse::<u64>().ok());
+        let content_range = head
+            .get(header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok());
+        if resumes_at(response.status(), content_range, start) {
+            let content_length = head
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| s.parse::<u64>().ok());
 
-        {
-            let mut state = task.state.lock().unwrap();
-            if let Some(len) = content_length {
-                state.content_length = Some(len + downloaded);
+            {
+                let mut state = task.state.lock().unwrap();
+                if let Some(len) = content_length
+                    && fetched_range.is_none()
+                {
+                    state.content_length = Some(len + downloaded);
+                }
+                state.security = Some(ConnectionSecurity::from_response(&response));
             }
+
+            let stream = first_byte_stream(task, sent, response).await;
+            return Ok((limit_stream(stream, 0, take), downloaded));
         }
 
-        let stream = response.bytes_stream();
-        return Ok(stream);
+        log::warn!(
+            target:"Task",
+            "Server did not resume at byte {} (status {}, Content-Range {:?}), restarting",
+            start,
+            response.status(),
+            content_range
+        );
+        let mut state = task.state.lock().unwrap();
+        state.downloaded = 0;
+        state.last_downloaded = 0;
+        state.pieces = None;
     }
 
-    // vvv !ACCEPT_RANGES
+    // vvv !ACCEPT_RANGES，或者服务器没有从继续的位置返回数据
 
-    let response = client.get(url).send().await?;
-    let head = response.headers();
-    let content_length = head
-        .get(header::CONTENT_LENGTH)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.parse::<u64>().ok());
-    let accept_ranges = head
-        .get(header::ACCEPT_RANGES)
-        .and_then(|v| v.to_str().ok())
-        .is_some_and(|s| s.eq_ignore_ascii_case("bytes"));
+    let sent = Instant::now();
+    let range = requested_range(task);
+    let mut request = client.get(url);
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range.header_value());
+    }
+    let response = request.send().await?;
+    let (skip, take) = apply_range(task, range, &response)?;
+    task.state.lock().unwrap().security = Some(ConnectionSecurity::from_response(&response));
 
-    {
-        let mut state = task.state.lock().unwrap();
-        state.content_length = content_length;
-        state.accept_ranges = accept_ranges;
+    let stream = first_byte_stream(task, sent, response).await;
+    Ok((limit_stream(stream, skip, take), 0))
+}
+
+/// 恢复下载的响应是否从`start`处继续：状态码为`206`，并且`Content-Range`从`start`开始
+///
+/// 服务器忽略`Range`返回`200`以及整个文件，或者返回的范围与请求的不同时，
+/// 把响应接在已经下载的部分之后会得到损坏的文件。
+fn resumes_at(status: StatusCode, content_range: Option<&str>, start: u64) -> bool {
+    status == StatusCode::PARTIAL_CONTENT
+        && content_range
+            .and_then(parse_content_range)
+            .is_some_and(|(from, _)| from == start)
+}
+
+/// 等待响应体的第一块数据，记录从`sent`开始到收到这块数据的时间，见[`TaskState::ttfb`]，
+/// 之后将这块数据放回流的开头
+///
+/// 在这里而不是在写入文件时读取第一块数据，这样等待用户确认文件类型、
+/// 获取校验和文件等耗时不会被计入。
+///
+/// [`TaskState::ttfb`]: crate::app::task::TaskState::ttfb
+async fn first_byte_stream(
+    task: &TaskInner,
+    sent: Instant,
+    mut response: reqwest::Response,
+) -> impl Stream<Item = reqwest::Result<Bytes>> + use<> {
+    let first = match response.chunk().await {
+        Ok(Some(chunk)) => {
+            task.state.lock().unwrap().ttfb = Some(sent.elapsed());
+            Some(Ok(chunk))
+        }
+        Ok(None) => None,
+        Err(e) => Some(Err(e)),
+    };
+    futures::stream::iter(first).chain(response.bytes_stream())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::app::config::Config;
+    use crate::app::listener::{ListenerPhase, TaskListener};
+    use crate::app::sender::{RequestOptions, Sender};
+    use crate::app::task::mock_server::{MockFile, MockServer};
+    use crate::app::task::{
+        AdmissionPolicy, ConnectionLimits, RuntimeStats, StageKind, TaskEvents, TaskState,
+    };
+    use crate::app::throttle::{SpeedLimit, Throttle};
+
+    /// 每个测试使用单独的下载目录
+    fn download_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "request-tui-resolve-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn context(config: &Config) -> TaskContext {
+        TaskContext::new(
+            Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+            Arc::new(ConnectionLimits::new(
+                config.max_concurrent_tasks,
+                config.max_tasks_per_host,
+                AdmissionPolicy::default(),
+            )),
+            TaskEvents::default(),
+            config,
+        )
+    }
+
+    /// 在`dir`中下载`options`，运行到任务结束，返回对应的监听器
+    async fn download(dir: &Path, options: RequestOptions) -> TaskListener {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.to_path_buf());
+        sender.connect(tx);
+        let id = sender.next_id();
+        let mut listener = sender.send_normal_request(id, options).unwrap();
+        let task = rx.recv().await.unwrap();
+        handle_task(task, &context(&Config::default())).await;
+        listener.receive_result();
+        listener
+    }
+
+    /// 重新校验`state`中的文件，运行到任务结束，返回对应的监听器
+    async fn verify(dir: &Path, state: TaskState) -> TaskListener {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.to_path_buf());
+        sender.connect(tx);
+        let id = sender.next_id();
+        let mut listener = sender.send_verify_request(id, state).unwrap();
+        let task = rx.recv().await.unwrap();
+        handle_task(task, &context(&Config::default())).await;
+        listener.receive_result();
+        listener
+    }
+
+    fn sha256(data: &[u8]) -> Checksum {
+        let mut hasher = Hasher::new(HashAlgorithm::Sha256);
+        hasher.update(data);
+        Checksum::new(HashAlgorithm::Sha256, &hasher.finalize()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn existing_file_is_verified_without_connecting() {
+        let body = b"verify me again".repeat(100);
+        let server = MockServer::start(vec![("/a.bin", MockFile::new(body.clone()))]).await;
+        let dir = download_dir("verify-existing");
+        let checksum = sha256(&body);
+        let options =
+            RequestOptions::new(server.url("/a.bin"), None).with_checksum(Some(checksum.clone()));
+        let finished = download(&dir, options).await.into_finished_task();
+        let state = finished.verify_state().unwrap();
+
+        let mut listener = verify(&dir, state.clone()).await;
+        assert_eq!(
+            listener.phase(),
+            ListenerPhase::Terminal(StageKind::Finished)
+        );
+        let handle = listener.get_state_handler();
+        assert_eq!(handle.lock().unwrap().verified_checksum(), Some(&checksum));
+        assert_eq!(server.gets().len(), 1);
+        assert_eq!(
+            listener.into_finished_task().to_record().checksum,
+            Some(checksum.to_string())
+        );
+
+        // 文件被修改之后校验失败
+        std::fs::write(finished.filepath(), b"tampered").unwrap();
+        let listener = verify(&dir, state.clone()).await;
+        assert_eq!(
+            listener.task_result().map(TaskResult::kind),
+            Some(StageKind::ChecksumMismatch)
+        );
+
+        // 校验期间被停止的任务恢复时仍然只校验
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.clone());
+        sender.connect(tx);
+        let mut state = state;
+        state.verify_only = true;
+        let id = sender.next_id();
+        sender
+            .send_resume_request(id, Arc::new(Mutex::new(state)))
+            .unwrap();
+        let task = rx.recv().await.unwrap();
+        assert!(matches!(task.request(), DownloadRequest::Verify));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 每个字节都不同（在251字节之内），截取错位时内容不同
+    fn numbered_body(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn ignored_range_is_cut_from_the_full_response() {
+        let body = numbered_body(1000);
+        let server = MockServer::start(vec![(
+            "/a.bin",
+            MockFile::new(body.clone()).without_ranges(),
+        )])
+        .await;
+        let dir = download_dir("range-ignored");
+        for (range, expected) in [
+            (
+                ByteRange::From {
+                    start: 100,
+                    end: Some(299),
+                },
+                &body[100..300],
+            ),
+            (
+                ByteRange::From {
+                    start: 900,
+                    end: None,
+                },
+                &body[900..],
+            ),
+            (ByteRange::Last(50), &body[950..]),
+        ] {
+            let options = RequestOptions::new(server.url("/a.bin"), None).with_range(Some(range));
+            let listener = download(&dir, options).await;
+            assert_eq!(
+                listener.phase(),
+                ListenerPhase::Terminal(StageKind::Finished)
+            );
+            let handle = listener.get_state_handler();
+            let state = handle.lock().unwrap();
+            assert_eq!(std::fs::read(state.filepath()).unwrap(), expected);
+            assert_eq!(state.content_length(), Some(expected.len() as u64));
+            // 无法从中途恢复，只能重新开始
+            assert_eq!(state.fetched_range, None);
+            assert!(!state.accept_ranges);
+            let requested = server.gets().last().unwrap().range.clone();
+            assert_eq!(requested, Some(range.header_value()));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ranged_task_resumes_inside_its_range() {
+        let body = numbered_body(1000);
+        let server = MockServer::start(vec![("/a.bin", MockFile::new(body.clone()))]).await;
+        let dir = download_dir("range-resume");
+        let range = ByteRange::From {
+            start: 100,
+            end: Some(299),
+        };
+        let options = RequestOptions::new(server.url("/a.bin"), None).with_range(Some(range));
+        let listener = download(&dir, options).await;
+        let mut state = listener.get_state_handler().lock().unwrap().clone();
+        assert_eq!(state.fetched_range, Some((100, 299)));
+
+        // 模拟在范围中下载了50字节之后被停止
+        std::fs::write(state.filepath(), &body[100..150]).unwrap();
+        state.downloaded = 50;
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.clone());
+        sender.connect(tx);
+        let id = sender.next_id();
+        let state = Arc::new(Mutex::new(state));
+        let channel = sender.send_resume_request(id, state.clone()).unwrap();
+        let task = rx.recv().await.unwrap();
+        assert!(matches!(task.request(), DownloadRequest::Resume));
+        handle_task(task, &context(&Config::default())).await;
+
+        let result = channel.result_recv.await.unwrap();
+        assert_eq!(result.kind(), StageKind::Finished);
+        // 从范围中已经下载的位置继续，到范围的末尾为止
+        assert_eq!(
+            server.gets().last().unwrap().range.as_deref(),
+            Some("bytes=150-299")
+        );
+        let state = state.lock().unwrap();
+        assert_eq!(std::fs::read(state.filepath()).unwrap(), &body[100..300]);
+        assert_eq!(state.content_length(), Some(200));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn task_awaiting_confirmation_lets_queued_tasks_start() {
+        let body = b"policy".repeat(100);
+        let server = MockServer::start(vec![
+            ("/a.exe", MockFile::new(body.clone())),
+            ("/b.bin", MockFile::new(body.clone())),
+        ])
+        .await;
+        let dir = download_dir("policy-parked");
+        let config = Config {
+            max_concurrent_tasks: 1,
+            max_tasks_per_host: 2,
+            file_type_policy: FileTypePolicy {
+                extensions: vec![String::from("exe")],
+                ..FileTypePolicy::default()
+            },
+            ..Config::default()
+        };
+        let ctx = context(&config);
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.clone());
+        sender.connect(tx);
+        let mut spawn = |path: &str| {
+            let id = sender.next_id();
+            let options = RequestOptions::new(server.url(path), None);
+            let listener = sender.send_normal_request(id, options).unwrap();
+            let task = rx.try_recv().unwrap();
+            let ctx = ctx.clone();
+            (
+                listener,
+                tokio::spawn(async move { handle_task(task, &ctx).await }),
+            )
+        };
+        let (mut parked, parked_task) = spawn("/a.exe");
+        let state = parked.get_state_handler();
+        while state.lock().unwrap().phase() != TaskPhase::AwaitingConfirm {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // 等待确认的任务不占用全局的名额
+        let (mut queued, queued_task) = spawn("/b.bin");
+        tokio::time::timeout(Duration::from_secs(5), queued_task)
+            .await
+            .expect("queued task was not admitted")
+            .unwrap();
+        queued.receive_result();
+        assert_eq!(queued.phase(), ListenerPhase::Terminal(StageKind::Finished));
+
+        parked.send_command(TaskCommand::ConfirmDownload(true));
+        parked_task.await.unwrap();
+        parked.receive_result();
+        assert_eq!(parked.phase(), ListenerPhase::Terminal(StageKind::Finished));
+        assert_eq!(std::fs::read(dir.join("a.exe")).unwrap(), body);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn config_changes_apply_to_tasks_admitted_later() {
+        let body = b"live config".repeat(100);
+        let server = MockServer::start(vec![
+            (
+                "/a.bin",
+                MockFile::new(body.clone()).with_delay(Duration::from_millis(300)),
+            ),
+            ("/b.bin", MockFile::new(body.clone())),
+        ])
+        .await;
+        let dir = download_dir("live-config");
+        let config = Config {
+            max_concurrent_tasks: 1,
+            write_buffer_kib: 64,
+            ..Config::default()
+        };
+        let (config_tx, config_rx) = watch::channel(Arc::new(config.clone()));
+        let ctx = context(&config).with_live_config(config_rx);
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut sender = Sender::new(Arc::new(RuntimeStats::new()), dir.clone());
+        sender.connect(tx);
+        let mut listeners = Vec::new();
+        let mut running = Vec::new();
+        for path in ["/a.bin", "/b.bin"] {
+            let id = sender.next_id();
+            let options = RequestOptions::new(server.url(path), None);
+            listeners.push(sender.send_normal_request(id, options).unwrap());
+            // 与TaskManager一样，在接收任务时复制执行环境
+            let (task, ctx) = (rx.recv().await.unwrap(), ctx.clone());
+            running.push(tokio::spawn(async move { handle_task(task, &ctx).await }));
+        }
+        let states: Vec<_> = listeners.iter().map(|l| l.get_state_handler()).collect();
+
+        // A已经得到名额，B在A之后排队时修改配置
+        while states[0].lock().unwrap().phase() == TaskPhase::Queued
+            || states[1].lock().unwrap().phase() != TaskPhase::Queued
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        config_tx
+            .send(Arc::new(Config {
+                write_buffer_kib: 128,
+                ..config
+            }))
+            .unwrap();
+        for task in running {
+            task.await.unwrap();
+        }
+
+        let written: Vec<_> = states
+            .iter()
+            .map(|state| state.lock().unwrap().buffers.unwrap().write)
+            .collect();
+        assert_eq!(written, [64 * 1024, 128 * 1024]);
+        for listener in &mut listeners {
+            listener.receive_result();
+            assert_eq!(
+                listener.phase(),
+                ListenerPhase::Terminal(StageKind::Finished)
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn verified_checksum_is_recorded() {
+        let body = b"verified content".repeat(100);
+        let server = MockServer::start(vec![
+            ("/a.bin", MockFile::new(body.clone())),
+            ("/b.bin", MockFile::new(body.clone())),
+        ])
+        .await;
+        let dir = download_dir("verified");
+        let checksum = sha256(&body);
+
+        let options =
+            RequestOptions::new(server.url("/a.bin"), None).with_checksum(Some(checksum.clone()));
+        let mut listener = download(&dir, options).await;
+        assert_eq!(
+            listener.phase(),
+            ListenerPhase::Terminal(StageKind::Finished)
+        );
+        let state = listener.get_state_handler();
+        assert_eq!(state.lock().unwrap().verified_checksum(), Some(&checksum));
+        let record = listener.into_finished_task().to_record();
+        assert_eq!(record.checksum, Some(checksum.to_string()));
+
+        // 没有校验的任务不记录校验和
+        let mut listener = download(&dir, RequestOptions::new(server.url("/b.bin"), None)).await;
+        let record = listener.into_finished_task().to_record();
+        assert_eq!(record.stage, StageKind::Finished);
+        assert_eq!(record.checksum, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resume_requires_partial_content_at_the_offset() {
+        let range = Some("bytes 1024-4095/4096");
+        assert!(resumes_at(StatusCode::PARTIAL_CONTENT, range, 1024));
+        // 范围从其他位置开始
+        assert!(!resumes_at(StatusCode::PARTIAL_CONTENT, range, 2048));
+        assert!(!resumes_at(
+            StatusCode::PARTIAL_CONTENT,
+            Some("bytes 0-4095/4096"),
+            1024
+        ));
+        // 忽略Range返回整个文件
+        assert!(!resumes_at(StatusCode::OK, range, 1024));
+        assert!(!resumes_at(StatusCode::OK, None, 0));
     }
 
-    let stream = response.bytes_stream();
-    Ok(stream)
+    #[test]
+    fn resume_rejects_missing_or_malformed_content_range() {
+        assert!(!resumes_at(StatusCode::PARTIAL_CONTENT, None, 1024));
+        assert!(!resumes_at(
+            StatusCode::PARTIAL_CONTENT,
+            Some("bytes */4096"),
+            1024
+        ));
+        assert!(!resumes_at(
+            StatusCode::PARTIAL_CONTENT,
+            Some("1024-4095/4096"),
+            1024
+        ));
+        assert!(!resumes_at(
+            StatusCode::PARTIAL_CONTENT,
+            Some("bytes 4095-1024/4096"),
+            4095
+        ));
+    }
 }
@@ -1,10 +1,18 @@
+use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 
+use serde::{Deserialize, Serialize};
+
+use crate::app::task::FileError;
+use crate::window::common;
+
 /// 通过channel发送给UI线程的内容，用于显示错误信息或者设置任务最终状态。
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TaskResult {
     pub final_stage: TaskFinalStage,
     pub message: Option<String>,
+    /// 文件操作失败时的具体原因，用于在详情弹窗中给出解决建议
+    pub file_error: Option<FileError>,
 }
 
 impl TaskResult {
@@ -14,9 +22,16 @@ impl TaskResult {
         TaskResult {
             final_stage,
             message,
+            file_error: None,
         }
     }
 
+    /// 附加文件操作失败的原因，见[`FileError::new`]
+    pub fn with_file_error(mut self, file_error: Option<FileError>) -> Self {
+        self.file_error = file_error;
+        self
+    }
+
     pub fn new_unknown_url(message: String) -> Self {
         TaskResult::new(TaskFinalStage::UnknownUrl, Some(message))
     }
@@ -49,6 +64,34 @@ impl TaskResult {
         TaskResult::new(TaskFinalStage::FailToResumeConnection, Some(message))
     }
 
+    pub fn new_insufficient_disk_space(message: String) -> Self {
+        TaskResult::new(TaskFinalStage::InsufficientDiskSpace, Some(message))
+    }
+
+    /// 服务器返回了表示失败的状态码，例如404
+    pub fn new_http_status(status: u16) -> Self {
+        TaskResult::new(TaskFinalStage::HttpStatus(status), None)
+    }
+
+    /// `expected`和`actual`都带有算法名称，例如`sha256:…`
+    pub fn new_checksum_mismatch(expected: String, actual: String) -> Self {
+        TaskResult::new(TaskFinalStage::ChecksumMismatch { expected, actual }, None)
+    }
+
+    /// 文件的大小`actual`超过了上限`limit`（字节）
+    pub fn new_too_large(limit: u64, actual: u64) -> Self {
+        TaskResult::new(TaskFinalStage::TooLarge { limit, actual }, None)
+    }
+
+    /// 超过`seconds`秒没有收到任何数据
+    pub fn new_stalled(seconds: u64) -> Self {
+        TaskResult::new(TaskFinalStage::Stalled { seconds }, None)
+    }
+
+    pub fn new_blocked_by_policy(message: String) -> Self {
+        TaskResult::new(TaskFinalStage::BlockedByPolicy, Some(message))
+    }
+
     pub fn new_interrupted() -> Self {
         TaskResult::new(TaskFinalStage::Interrupted, None)
     }
@@ -61,19 +104,95 @@ impl TaskResult {
         TaskResult::new(TaskFinalStage::Finished, None)
     }
 
+    /// 下载成功，但是之后的步骤（例如自动解压）失败或者被跳过，`note`说明原因
+    pub fn new_finished_with_note(note: String) -> Self {
+        TaskResult::new(TaskFinalStage::Finished, Some(note))
+    }
+
+    /// 下载目录中已经有相同的文件，没有重新下载
+    pub fn new_already_exists() -> Self {
+        TaskResult::new(TaskFinalStage::AlreadyExists, None)
+    }
+
     pub fn new_unknown_error(message: String) -> Self {
         TaskResult::new(TaskFinalStage::UnknownError, Some(message))
     }
 
     // -------------------- MEMBER_ACCESS -----------------------
 
-    pub fn stage(&self) -> TaskFinalStage {
-        self.final_stage
+    pub fn stage(&self) -> &TaskFinalStage {
+        &self.final_stage
+    }
+
+    /// 不带附加数据的阶段，用于分类和比较
+    pub fn kind(&self) -> StageKind {
+        self.final_stage.kind()
     }
 
     pub fn message(&self) -> Option<&str> {
         self.message.as_deref()
     }
+
+    /// 文件操作失败时的解决建议，例如`check permissions on /mnt/usb`
+    pub fn hint(&self) -> Option<String> {
+        self.file_error
+            .as_ref()
+            .map(|error| error.hint(self.kind()))
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 完整的结果描述，用于在详情弹窗中显示，不做任何截断
+    pub fn detail(&self) -> String {
+        match self.message() {
+            Some(message) if !message.trim().is_empty() => {
+                format!("{}: {}", self.final_stage, message.trim())
+            }
+            _ => self.final_stage.to_string(),
+        }
+    }
+}
+
+/// 将错误及其所有source用" → "连接起来。
+///
+/// reqwest的错误的Display只包含最外层的描述，例如"error sending request for url (...)"，
+/// 真正的原因（例如"dns error: failed to lookup address information"）藏在source中。
+pub fn error_chain(err: &dyn Error) -> String {
+    let mut chain = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        let message = err.to_string();
+        // 有些错误会在自己的描述中包含source的描述，此时不再重复
+        if !chain.ends_with(&message) {
+            chain.push_str(" → ");
+            chain.push_str(&message);
+        }
+        source = err.source();
+    }
+    chain
+}
+
+/// 生成一行的结果摘要，用于在列表中显示。
+///
+/// 摘要由阶段和消息的第一句组成，超出`width`（按显示宽度计）时在末尾使用`…`省略。
+pub fn format_error_brief(result: &TaskResult, width: usize) -> String {
+    let stage = result.final_stage.to_string();
+    let first_sentence = result.message().and_then(|message| {
+        let line = message
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())?;
+        Some(match line.find(". ") {
+            Some(end) => &line[..=end],
+            None => line,
+        })
+    });
+    let brief = match first_sentence {
+        Some(sentence) => format!("{}: {}", stage, common::display_sanitize(sentence)),
+        None => stage,
+    };
+
+    common::truncate_to_width(&brief, width)
 }
 
 /// 我们希望这些错误能够通过channel发送给UI线程，以便UI线程能够显示错误信息。
@@ -83,38 +202,253 @@ impl TaskResult {
 /// 对于FailToConnection，可以直接将任务标记为失败，并以失败状态放置到完成列表。
 /// 对于FailToCreateFile，同样可以将任务标记为失败，并以失败状态放置到完成列表。
 /// 对于Interrupted，则需要将任务标记为暂停状态，用户仍然有机会重新开始该任务。
+/// 对于InsufficientDiskSpace，同样标记为暂停状态，并在空间足够时自动恢复。
 /// 对于Finished，则将任务标记为成功，放置到完成列表。
-#[derive(Debug, Clone, Copy)]
+/// 对于AlreadyExists，下载目录中已经有相同的文件，同样视为成功，任务指向已有的文件。
+///
+/// 部分阶段带有附加的数据，只需要判断阶段时使用[`TaskFinalStage::kind`]。
+/// 序列化时没有附加数据的阶段为字符串，例如`"finished"`，带有附加数据的阶段为
+/// 以阶段名称为键的对象，例如`{"http_status":404}`。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TaskFinalStage {
     UnknownUrl,
     FailToConnection,
+    /// 服务器返回的状态码不表示成功
+    HttpStatus(u16),
     FailToCreateFile,
     FailToDownload,
     FailToWrite,
     FailToResumeFile,
     FileCorrupted,
     FailToResumeConnection,
+    InsufficientDiskSpace,
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// 文件超过了允许的大小（字节）
+    TooLarge {
+        limit: u64,
+        actual: u64,
+    },
+    /// 长时间没有收到数据
+    Stalled {
+        seconds: u64,
+    },
+    BlockedByPolicy,
     Interrupted,
     Abort,
     Finished,
+    AlreadyExists,
     UnknownError,
 }
 
+impl TaskFinalStage {
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn kind(&self) -> StageKind {
+        match self {
+            TaskFinalStage::UnknownUrl => StageKind::UnknownUrl,
+            TaskFinalStage::FailToConnection => StageKind::FailToConnection,
+            TaskFinalStage::HttpStatus(_) => StageKind::HttpStatus,
+            TaskFinalStage::FailToCreateFile => StageKind::FailToCreateFile,
+            TaskFinalStage::FailToDownload => StageKind::FailToDownload,
+            TaskFinalStage::FailToWrite => StageKind::FailToWrite,
+            TaskFinalStage::FailToResumeFile => StageKind::FailToResumeFile,
+            TaskFinalStage::FileCorrupted => StageKind::FileCorrupted,
+            TaskFinalStage::FailToResumeConnection => StageKind::FailToResumeConnection,
+            TaskFinalStage::InsufficientDiskSpace => StageKind::InsufficientDiskSpace,
+            TaskFinalStage::ChecksumMismatch { .. } => StageKind::ChecksumMismatch,
+            TaskFinalStage::TooLarge { .. } => StageKind::TooLarge,
+            TaskFinalStage::Stalled { .. } => StageKind::Stalled,
+            TaskFinalStage::BlockedByPolicy => StageKind::BlockedByPolicy,
+            TaskFinalStage::Interrupted => StageKind::Interrupted,
+            TaskFinalStage::Abort => StageKind::Abort,
+            TaskFinalStage::Finished => StageKind::Finished,
+            TaskFinalStage::AlreadyExists => StageKind::AlreadyExists,
+            TaskFinalStage::UnknownError => StageKind::UnknownError,
+        }
+    }
+
+    /// 任务是否成功完成，包括直接使用已有文件的任务
+    pub fn is_success(&self) -> bool {
+        self.kind().is_success()
+    }
+}
+
 impl Display for TaskFinalStage {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             TaskFinalStage::UnknownUrl => write!(f, "Unknown URL"),
             TaskFinalStage::FailToConnection => write!(f, "Connection Failed"),
+            TaskFinalStage::HttpStatus(status) => {
+                let reason = reqwest::StatusCode::from_u16(*status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason());
+                match reason {
+                    Some(reason) => write!(f, "HTTP {} {}", status, reason),
+                    None => write!(f, "HTTP {}", status),
+                }
+            }
             TaskFinalStage::FailToCreateFile => write!(f, "Failed to create file"),
             TaskFinalStage::FailToDownload => write!(f, "Failed to download"),
             TaskFinalStage::FailToWrite => write!(f, "Failed to write to file"),
             TaskFinalStage::FailToResumeFile => write!(f, "Cannot open file"),
             TaskFinalStage::FileCorrupted => write!(f, "File corrupted"),
             TaskFinalStage::FailToResumeConnection => write!(f, "Connection failed"),
+            TaskFinalStage::InsufficientDiskSpace => write!(f, "Waiting for disk space"),
+            TaskFinalStage::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Checksum mismatch (expected {}, got {})",
+                    expected, actual
+                )
+            }
+            TaskFinalStage::TooLarge { limit, actual } => write!(
+                f,
+                "Too large ({} > {})",
+                common::get_human_readable_size(*actual),
+                common::get_human_readable_size(*limit)
+            ),
+            TaskFinalStage::Stalled { seconds } => {
+                write!(f, "Stalled (no data for {}s)", seconds)
+            }
+            TaskFinalStage::BlockedByPolicy => write!(f, "Blocked by policy"),
             TaskFinalStage::Interrupted => write!(f, "Stopped"),
             TaskFinalStage::Abort => write!(f, "Abort"),
             TaskFinalStage::Finished => write!(f, "Finished"),
+            TaskFinalStage::AlreadyExists => write!(f, "Already downloaded"),
             TaskFinalStage::UnknownError => write!(f, "Unknown error"),
         }
     }
 }
+
+/// [`TaskFinalStage`]去掉附加数据之后的阶段，可以复制和比较，用于分类任务的结果
+///
+/// 退出摘要等记录中使用这个类型，序列化的结果与阶段的名称相同，例如`"checksum_mismatch"`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageKind {
+    UnknownUrl,
+    FailToConnection,
+    HttpStatus,
+    FailToCreateFile,
+    FailToDownload,
+    FailToWrite,
+    FailToResumeFile,
+    FileCorrupted,
+    FailToResumeConnection,
+    InsufficientDiskSpace,
+    ChecksumMismatch,
+    TooLarge,
+    Stalled,
+    BlockedByPolicy,
+    Interrupted,
+    Abort,
+    Finished,
+    AlreadyExists,
+    UnknownError,
+}
+
+impl StageKind {
+    /// 任务是否成功完成，包括直接使用已有文件的任务
+    pub fn is_success(self) -> bool {
+        matches!(self, StageKind::Finished | StageKind::AlreadyExists)
+    }
+
+    /// 任务因为用户的停止或者取消指令而结束
+    pub fn is_user_stop(self) -> bool {
+        matches!(self, StageKind::Interrupted | StageKind::Abort)
+    }
+
+    /// 任务暂停并且可以恢复，已经下载的部分会被保留，其他失败的任务会被移动到完成列表中
+    pub fn is_resumable(self) -> bool {
+        matches!(
+            self,
+            StageKind::FailToDownload
+                | StageKind::FailToWrite
+                | StageKind::FailToResumeFile
+                | StageKind::FailToResumeConnection
+                | StageKind::InsufficientDiskSpace
+                | StageKind::ChecksumMismatch
+                | StageKind::Stalled
+                | StageKind::Interrupted
+        )
+    }
+
+    /// 计入失败统计的结果，等待磁盘空间以及用户停止的任务不算作失败
+    pub fn is_failure(self) -> bool {
+        !self.is_success() && !self.is_user_stop() && self != StageKind::InsufficientDiskSpace
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn hint_uses_the_stage_of_the_result() {
+        let error = io::Error::from(io::ErrorKind::NotFound);
+        let path = Path::new("/srv/new/a.iso");
+        let created = TaskResult::new_failed_to_create_file(error.to_string())
+            .with_file_error(FileError::new(&error, path));
+        assert_eq!(
+            created.hint().as_deref(),
+            Some("/srv/new does not exist, create it or choose another folder")
+        );
+        let resumed = TaskResult::new_failed_to_resume_file(error.to_string())
+            .with_file_error(FileError::new(&error, path));
+        assert_eq!(
+            resumed.hint().as_deref(),
+            Some("/srv/new/a.iso was moved or deleted, download it again")
+        );
+        assert_eq!(
+            TaskResult::new_unknown_error(String::from("?")).hint(),
+            None
+        );
+    }
+
+    #[test]
+    fn brief_keeps_the_first_sentence() {
+        let result = TaskResult::new_failed_to_create_file(String::from(
+            "\n  Permission denied. Check the folder.\nos error 13",
+        ));
+        assert_eq!(
+            format_error_brief(&result, 80),
+            "Failed to create file: Permission denied."
+        );
+        assert_eq!(
+            format_error_brief(&TaskResult::new_http_status(404), 80),
+            "HTTP 404 Not Found"
+        );
+    }
+
+    #[test]
+    fn brief_is_truncated_to_width() {
+        let result = TaskResult::new_unknown_error(String::from("x"));
+        let full = format_error_brief(&result, 80);
+        let width = full.chars().count();
+        assert_eq!(format_error_brief(&result, width), full);
+        let truncated = format_error_brief(&result, width - 1);
+        assert_eq!(truncated.chars().count(), width - 1);
+        assert!(truncated.ends_with('…'));
+        assert_eq!(format_error_brief(&result, 1), "…");
+        assert_eq!(format_error_brief(&result, 0), "");
+    }
+
+    #[test]
+    fn brief_is_measured_by_display_width() {
+        use unicode_width::UnicodeWidthStr;
+
+        let result = TaskResult::new_unknown_error(String::from("磁盘已满，无法继续写入"));
+        for width in [10, 21, 30] {
+            let brief = format_error_brief(&result, width);
+            assert!(brief.width() <= width, "{:?} wider than {}", brief, width);
+            assert!(brief.ends_with('…'));
+        }
+    }
+}
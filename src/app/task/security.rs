@@ -0,0 +1,373 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use url::Url;
+
+/// 下载使用的连接是否加密，以及服务器出示的证书的摘要，显示在详情弹窗的Security部分
+///
+/// 证书来自下载请求的响应本身（reqwest的`tls_info`），不会为了获取证书额外建立连接。
+/// 证书已经由TLS后端正常校验过，这里只记录其中便于人工核对的字段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionSecurity {
+    /// 普通的HTTP连接
+    Unencrypted,
+    /// TLS后端没有提供证书，或者证书无法解析
+    Unavailable,
+    Certificate(CertificateSummary),
+}
+
+/// 证书中用于核对的字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    /// subjectAltName中的域名以及IP地址
+    pub names: Vec<String>,
+    /// 请求的主机名是否在`names`中，通配符只匹配最左边的一级
+    pub host_matches: bool,
+}
+
+impl ConnectionSecurity {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 根据响应的最终URL以及TLS信息生成，需要创建客户端时开启`tls_info`
+    pub fn from_response(response: &reqwest::Response) -> Self {
+        Self::from_parts(
+            response.url(),
+            response
+                .extensions()
+                .get::<reqwest::tls::TlsInfo>()
+                .and_then(|info| info.peer_certificate()),
+        )
+    }
+
+    pub fn from_parts(url: &Url, certificate: Option<&[u8]>) -> Self {
+        if url.scheme() != "https" {
+            return ConnectionSecurity::Unencrypted;
+        }
+        let host = url.host_str().unwrap_or_default();
+        certificate
+            .and_then(|der| CertificateSummary::parse(der, host))
+            .map_or(
+                ConnectionSecurity::Unavailable,
+                ConnectionSecurity::Certificate,
+            )
+    }
+}
+
+impl Display for ConnectionSecurity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionSecurity::Unencrypted => write!(f, "unencrypted"),
+            ConnectionSecurity::Unavailable => write!(f, "unavailable"),
+            ConnectionSecurity::Certificate(cert) => {
+                write!(f, "{} (issued by {})", cert.subject, cert.issuer)
+            }
+        }
+    }
+}
+
+impl CertificateSummary {
+    // ------------------- CONSTANT -----------------------
+
+    const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+    const ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0A];
+    const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 解析DER编码的X.509证书，格式不符合预期时返回[`None`]
+    ///
+    /// 只读取需要显示的字段，不检查签名，也不支持证书中少见的编码方式。
+    pub fn parse(der: &[u8], host: &str) -> Option<Self> {
+        let (_, cert, _) = der::read(der, der::SEQUENCE)?;
+        let (_, tbs, _) = der::read(cert, der::SEQUENCE)?;
+        // version为可选的[0]，之后依次为serialNumber、signature
+        let mut rest = match der::peek(tbs)? {
+            der::VERSION => der::skip(tbs)?,
+            _ => tbs,
+        };
+        rest = der::skip(rest)?;
+        rest = der::skip(rest)?;
+        let (_, issuer, rest) = der::read(rest, der::SEQUENCE)?;
+        let (_, validity, rest) = der::read(rest, der::SEQUENCE)?;
+        let (_, subject, mut rest) = der::read(rest, der::SEQUENCE)?;
+        rest = der::skip(rest)?;
+
+        let (tag, not_before, validity) = der::read_any(validity)?;
+        let not_before = parse_time(tag, not_before);
+        let (tag, not_after, _) = der::read_any(validity)?;
+        let not_after = parse_time(tag, not_after);
+
+        let mut names = Vec::new();
+        while !rest.is_empty() {
+            let (tag, content, next) = der::read_any(rest)?;
+            if tag == der::EXTENSIONS {
+                names = subject_alt_names(content).unwrap_or_default();
+            }
+            rest = next;
+        }
+
+        Some(CertificateSummary {
+            subject: name_summary(subject).unwrap_or_else(|| String::from("unknown")),
+            issuer: name_summary(issuer).unwrap_or_else(|| String::from("unknown")),
+            not_before,
+            not_after,
+            host_matches: names.iter().any(|name| host_matches(name, host)),
+            names,
+        })
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// `now`不在证书的有效期内，正常校验时不会出现，只在跳过校验时有意义
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.not_after.is_some_and(|t| now > t) || self.not_before.is_some_and(|t| now < t)
+    }
+}
+
+/// Name中的CN，没有CN时使用O
+fn name_summary(name: &[u8]) -> Option<String> {
+    let mut common_name = None;
+    let mut organization = None;
+    let mut rest = name;
+    while !rest.is_empty() {
+        let (_, set, next) = der::read(rest, der::SET)?;
+        rest = next;
+        let (_, attribute, _) = der::read(set, der::SEQUENCE)?;
+        let (_, oid, value) = der::read(attribute, der::OID)?;
+        let (_, value, _) = der::read_any(value)?;
+        let value = String::from_utf8_lossy(value).into_owned();
+        match oid {
+            CertificateSummary::COMMON_NAME => common_name = Some(value),
+            CertificateSummary::ORGANIZATION => organization = Some(value),
+            _ => {}
+        }
+    }
+    common_name.or(organization)
+}
+
+/// 扩展中subjectAltName的dNSName和iPAddress
+fn subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let (_, mut rest, _) = der::read(extensions, der::SEQUENCE)?;
+    while !rest.is_empty() {
+        let (_, extension, next) = der::read(rest, der::SEQUENCE)?;
+        rest = next;
+        let (_, oid, extension) = der::read(extension, der::OID)?;
+        if oid != CertificateSummary::SUBJECT_ALT_NAME {
+            continue;
+        }
+        // critical为可选的BOOLEAN
+        let extension = match der::peek(extension)? {
+            der::BOOLEAN => der::skip(extension)?,
+            _ => extension,
+        };
+        let (_, value, _) = der::read(extension, der::OCTET_STRING)?;
+        let (_, mut general_names, _) = der::read(value, der::SEQUENCE)?;
+        let mut names = Vec::new();
+        while !general_names.is_empty() {
+            let (tag, name, next) = der::read_any(general_names)?;
+            general_names = next;
+            match (tag, name.len()) {
+                (der::DNS_NAME, _) => names.push(String::from_utf8_lossy(name).into_owned()),
+                (der::IP_ADDRESS, 4) => {
+                    let octets: [u8; 4] = name.try_into().ok()?;
+                    names.push(std::net::Ipv4Addr::from(octets).to_string());
+                }
+                (der::IP_ADDRESS, 16) => {
+                    let octets: [u8; 16] = name.try_into().ok()?;
+                    names.push(std::net::Ipv6Addr::from(octets).to_string());
+                }
+                _ => {}
+            }
+        }
+        return Some(names);
+    }
+    Some(Vec::new())
+}
+
+/// UTCTime（两位年份）或者GeneralizedTime，只支持以`Z`结尾的格式
+fn parse_time(tag: u8, content: &[u8]) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(content).ok()?;
+    let text = match tag {
+        der::UTC_TIME => {
+            let year: u32 = text.get(..2)?.parse().ok()?;
+            let century = if year >= 50 { "19" } else { "20" };
+            format!("{}{}", century, text)
+        }
+        der::GENERALIZED_TIME => text.to_string(),
+        _ => return None,
+    };
+    NaiveDateTime::parse_from_str(&text, "%Y%m%d%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// 证书中的名称是否匹配主机名，不区分大小写，`*.example.com`只匹配一级子域名
+fn host_matches(name: &str, host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if name.eq_ignore_ascii_case(host) {
+        return true;
+    }
+    match (name.strip_prefix("*."), host.split_once('.')) {
+        (Some(domain), Some((label, rest))) => {
+            !label.is_empty() && domain.eq_ignore_ascii_case(rest)
+        }
+        _ => false,
+    }
+}
+
+/// 读取DER编码的最小实现，只处理证书中用到的确定长度的编码
+mod der {
+    pub const BOOLEAN: u8 = 0x01;
+    pub const OCTET_STRING: u8 = 0x04;
+    pub const OID: u8 = 0x06;
+    pub const UTC_TIME: u8 = 0x17;
+    pub const GENERALIZED_TIME: u8 = 0x18;
+    pub const SEQUENCE: u8 = 0x30;
+    pub const SET: u8 = 0x31;
+    pub const VERSION: u8 = 0xA0;
+    pub const EXTENSIONS: u8 = 0xA3;
+    pub const DNS_NAME: u8 = 0x82;
+    pub const IP_ADDRESS: u8 = 0x87;
+
+    /// 读取一个元素，返回标签、内容以及剩余的部分
+    pub fn read_any(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+        let (&tag, input) = input.split_first()?;
+        let (&first, input) = input.split_first()?;
+        let (len, input) = if first < 0x80 {
+            (first as usize, input)
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 || input.len() < count {
+                return None;
+            }
+            let (bytes, input) = input.split_at(count);
+            let len = bytes.iter().fold(0usize, |len, &b| (len << 8) | b as usize);
+            (len, input)
+        };
+        if input.len() < len {
+            return None;
+        }
+        let (content, rest) = input.split_at(len);
+        Some((tag, content, rest))
+    }
+
+    /// 读取一个标签为`tag`的元素
+    pub fn read(input: &[u8], tag: u8) -> Option<(u8, &[u8], &[u8])> {
+        read_any(input).filter(|(t, _, _)| *t == tag)
+    }
+
+    pub fn peek(input: &[u8]) -> Option<u8> {
+        input.first().copied()
+    }
+
+    pub fn skip(input: &[u8]) -> Option<&[u8]> {
+        read_any(input).map(|(_, _, rest)| rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// `openssl req -x509`生成的自签名证书，O=Request TUI Test, CN=localhost，
+    /// 有效期2025-01-01到2060-01-01（分别为UTCTime和GeneralizedTime），
+    /// subjectAltName为`localhost`、`*.example.com`、`127.0.0.1`以及`::1`
+    const SELF_SIGNED: &[u8] = include_bytes!("testdata/self_signed.der");
+
+    fn date(year: i32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn self_signed_certificate_is_summarized() {
+        let cert = CertificateSummary::parse(SELF_SIGNED, "localhost").unwrap();
+        assert_eq!(cert.subject, "localhost");
+        assert_eq!(cert.issuer, "localhost");
+        assert_eq!(cert.not_before, Some(date(2025)));
+        assert_eq!(cert.not_after, Some(date(2060)));
+        assert_eq!(
+            cert.names,
+            ["localhost", "*.example.com", "127.0.0.1", "::1"]
+        );
+        assert!(cert.host_matches);
+        assert!(!cert.is_expired(date(2030)));
+        assert!(cert.is_expired(date(2061)));
+        assert!(cert.is_expired(date(2024)));
+    }
+
+    #[test]
+    fn host_is_matched_against_the_alt_names() {
+        let matches = |host| {
+            CertificateSummary::parse(SELF_SIGNED, host)
+                .unwrap()
+                .host_matches
+        };
+        assert!(matches("LOCALHOST"));
+        assert!(matches("cdn.example.com"));
+        assert!(matches("127.0.0.1"));
+        assert!(matches("[::1]"));
+        // 通配符只匹配一级子域名
+        assert!(!matches("example.com"));
+        assert!(!matches("a.cdn.example.com"));
+        assert!(!matches("127.0.0.2"));
+    }
+
+    #[test]
+    fn truncated_certificates_are_rejected() {
+        for len in 0..SELF_SIGNED.len() {
+            assert_eq!(
+                CertificateSummary::parse(&SELF_SIGNED[..len], "localhost"),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn corrupted_certificates_do_not_panic() {
+        // 每次修改一个字节，结果可能仍然可以解析，只要求不会panic
+        for index in 0..SELF_SIGNED.len() {
+            for value in [0x00, 0x80, 0x84, 0xFF] {
+                let mut der = SELF_SIGNED.to_vec();
+                der[index] = value;
+                let _ = CertificateSummary::parse(&der, "localhost");
+            }
+        }
+        let invalid: [&[u8]; 5] = [
+            &[],
+            &[0x30],
+            // 不定长度以及超过4个字节的长度
+            &[0x30, 0x80, 0x00, 0x00],
+            &[0x30, 0x85, 0x01, 0x00, 0x00, 0x00, 0x00],
+            &[0x30, 0x84, 0xFF, 0xFF, 0xFF, 0xFF],
+        ];
+        for der in invalid {
+            assert_eq!(CertificateSummary::parse(der, "localhost"), None);
+        }
+    }
+
+    #[test]
+    fn security_depends_on_scheme_and_certificate() {
+        let https = Url::parse("https://localhost/file.bin").unwrap();
+        let http = Url::parse("http://localhost/file.bin").unwrap();
+        assert_eq!(
+            ConnectionSecurity::from_parts(&http, Some(SELF_SIGNED)),
+            ConnectionSecurity::Unencrypted
+        );
+        assert_eq!(
+            ConnectionSecurity::from_parts(&https, None),
+            ConnectionSecurity::Unavailable
+        );
+        assert_eq!(
+            ConnectionSecurity::from_parts(&https, Some(&SELF_SIGNED[..100])),
+            ConnectionSecurity::Unavailable
+        );
+        let security = ConnectionSecurity::from_parts(&https, Some(SELF_SIGNED));
+        assert_eq!(security.to_string(), "localhost (issued by localhost)");
+    }
+}
@@ -1,13 +1,24 @@
 use std::{
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
 use ratatui::widgets::{Paragraph, Widget};
 use ratatui::{prelude::*, style::palette::tailwind, widgets::Gauge};
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
 use url::Url;
 
-use crate::window::common::{self, Fill};
+use crate::app::checksum::{Checksum, ChecksumSource, ExpectedChecksum};
+use crate::app::network::NetworkOptions;
+use crate::app::policy::PolicyMatch;
+use crate::app::sender::RequestOptions;
+use crate::app::task::{
+    BufferSizes, CompletedCopy, ConnectionSecurity, Eta, EtaEstimator, HealthThresholds,
+    HealthTracker, PieceMap, SlotBadge, SpeedDisplay, TaskId, WriteLatency,
+};
+use crate::window::common::{self, Fill, FooterColumns, SymbolSet};
 
 /// 用于表示单个下载任务的状态
 ///
@@ -20,11 +31,74 @@ pub struct TaskState {
     pub accept_ranges: bool,
     pub content_length: Option<u64>,
     pub downloaded: u64,
+    // 用户指定的保存路径已经存在文件，等待用户决定时为Some
+    pub conflict: Option<FileConflict>,
+    // 创建任务时的选项，该结构体会被频繁复制，因此使用Arc
+    pub options: Option<Arc<RequestOptions>>,
+    // 任务当前所处的阶段，只由下载任务修改，UI线程只负责显示
+    pub phase: TaskPhase,
+    // 当前阶段的进度（0.0~1.0），只有校验等阶段有自己的进度
+    pub phase_progress: Option<f64>,
+    // 该任务自己的速度上限（B/s），与全局的上限同时生效
+    pub speed_limit: Option<u64>,
+    // 排队时的优先级，越大越先开始
+    pub priority: u8,
+    // 用户正在等待该任务，排队时优先开始，下载时分到更多的带宽，同时只有一个任务加速
+    pub boost: bool,
+    // 文件中已经下载的部分，长度未知时为None，与options一样使用Arc避免频繁复制
+    pub pieces: Option<Arc<PieceMap>>,
+    // 写入文件的耗时，用于提示下载速度受限于磁盘
+    pub write_latency: Arc<WriteLatency>,
+    // 自动解压成功时解压到的目录
+    pub extracted: Option<PathBuf>,
+    // 链接或者复制到`completed_dir`的结果，没有设置该目录时为None
+    pub completed: Option<CompletedCopy>,
+    // 创建HTTP客户端时使用的网络设置，开始连接之前为None
+    pub network: Option<Arc<NetworkOptions>>,
+    // 最近一次请求的连接是否加密以及服务器的证书，收到响应之前为None
+    pub security: Option<ConnectionSecurity>,
+    // 文件类型匹配到的规则，见[`FileTypePolicy`]
+    //
+    // [`FileTypePolicy`]: crate::app::policy::FileTypePolicy
+    pub policy_match: Option<PolicyMatch>,
+    // 开启自动获取校验和时，从URL旁边的校验和文件中读取的校验和
+    pub remote_checksum: Option<Checksum>,
+    // 下载完成后校验通过的文件的实际校验和
+    pub verified_checksum: Option<Checksum>,
+    // 只重新校验已经下载完成的文件，恢复时同样只校验，见[`DownloadRequest::Verify`]
+    //
+    // [`DownloadRequest::Verify`]: crate::app::sender::DownloadRequest::Verify
+    pub verify_only: bool,
+    // 文件被其他程序占用，正在等待重试，见[`BusyRetry`]
+    //
+    // [`BusyRetry`]: crate::app::task::BusyRetry
+    pub file_busy: bool,
+    // 占用的名额编号或者在队列中的位置，由[`ConnectionLimits`]设置
+    //
+    // [`ConnectionLimits`]: crate::app::task::ConnectionLimits
+    pub slot: Option<SlotBadge>,
+    // 打开文件之后使用的缓冲区大小，用于在详情弹窗中显示
+    pub buffers: Option<BufferSizes>,
 
     // 用于UI显示侧修改的数据
     pub last_updated: Instant,
     pub last_downloaded: u64,
     pub last_speed: Option<u64>,
+    pub health: HealthTracker,
+    pub eta: EtaEstimator,
+    // 下载期间采样到的最高速度（B/s），用于比较不同镜像
+    pub peak_speed: u64,
+    // 开始或者恢复下载之后的第一次采样可能包含暂停之前的数据，不计入峰值
+    pub peak_ready: bool,
+    // 最近一次请求从发送到收到响应体第一块数据的时间（TTFB），用于比较镜像的延迟
+    pub ttfb: Option<Duration>,
+    // 响应的Content-Type，用于判断下载到的是不是HTML页面
+    pub content_type: Option<String>,
+    // 部分下载时服务器返回的范围在整个文件中的位置（包含两端），用于从中途恢复，
+    // 服务器忽略范围请求时为None
+    pub fetched_range: Option<(u64, u64)>,
+    // 排队期间HEAD请求得到的大小（部分下载时为范围的长度），收到响应之前用于估计整个队列的剩余时间
+    pub preflight_size: Option<u64>,
 }
 
 impl Default for TaskState {
@@ -41,10 +115,13 @@ impl TaskState {
     const BAR_STYLE_NO_TOTAL: Style = Style::new()
         .fg(tailwind::YELLOW.c600)
         .bg(tailwind::GRAY.c500);
+    const BAR_STYLE_VERIFYING: Style = Style::new()
+        .fg(tailwind::EMERALD.c500)
+        .bg(tailwind::GRAY.c500);
     const BAR_TEXT_STYLE: Style = Style::new().fg(Color::White);
 
     // 我们希望每隔500毫秒刷新一次下载速度显示
-    const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+    pub const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 
     const FOCUSED_HIGHTLIGHT_COLOR: Color = Color::LightBlue;
     const UNFOCUSED_HIGHTLIGHT_COLOR: Color = tailwind::GRAY.c500;
@@ -60,9 +137,37 @@ impl TaskState {
             accept_ranges: false,
             content_length: None,
             downloaded: 0,
+            conflict: None,
+            options: None,
+            phase: TaskPhase::Queued,
+            phase_progress: None,
+            speed_limit: None,
+            priority: 0,
+            boost: false,
+            pieces: None,
+            write_latency: Arc::new(WriteLatency::new()),
+            extracted: None,
+            completed: None,
+            network: None,
+            security: None,
+            policy_match: None,
+            remote_checksum: None,
+            verified_checksum: None,
+            verify_only: false,
+            file_busy: false,
+            slot: None,
+            buffers: None,
             last_updated: Instant::now(),
             last_downloaded: 0,
             last_speed: None,
+            health: HealthTracker::default(),
+            eta: EtaEstimator::default(),
+            peak_speed: 0,
+            peak_ready: false,
+            ttfb: None,
+            content_type: None,
+            fetched_range: None,
+            preflight_size: None,
         }
     }
 
@@ -76,6 +181,19 @@ impl TaskState {
         self.url.as_ref()
     }
 
+    /// 是否已经确定了URL以及保存路径，只有这样的任务才能断点续传
+    ///
+    /// 在排队期间被停止的任务两者都没有，恢复时需要重新作为新的任务发送，见[`Sender::send_resume_request`]。
+    ///
+    /// [`Sender::send_resume_request`]: crate::app::sender::Sender::send_resume_request
+    pub fn has_started(&self) -> bool {
+        self.url.is_some() && !self.filepath.as_os_str().is_empty()
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.url.as_ref().and_then(Url::host_str)
+    }
+
     pub fn accept_ranges(&self) -> bool {
         self.accept_ranges
     }
@@ -88,42 +206,448 @@ impl TaskState {
         self.downloaded
     }
 
-    fn get_speed_string(&self) -> String {
-        match self.last_speed {
+    pub fn options(&self) -> Option<&Arc<RequestOptions>> {
+        self.options.as_ref()
+    }
+
+    pub fn conflict(&self) -> Option<&FileConflict> {
+        self.conflict.as_ref()
+    }
+
+    pub fn phase(&self) -> TaskPhase {
+        self.phase
+    }
+
+    pub fn phase_progress(&self) -> Option<f64> {
+        self.phase_progress
+    }
+
+    pub fn pieces(&self) -> Option<&PieceMap> {
+        self.pieces.as_deref()
+    }
+
+    /// 校验通过时文件实际的校验和，没有校验过时为[`None`]
+    pub fn verified_checksum(&self) -> Option<&Checksum> {
+        self.verified_checksum.as_ref()
+    }
+
+    pub fn extracted(&self) -> Option<&Path> {
+        self.extracted.as_deref()
+    }
+
+    pub fn completed(&self) -> Option<&CompletedCopy> {
+        self.completed.as_ref()
+    }
+
+    pub fn network(&self) -> Option<&Arc<NetworkOptions>> {
+        self.network.as_ref()
+    }
+
+    pub fn buffers(&self) -> Option<BufferSizes> {
+        self.buffers
+    }
+
+    /// 下载期间的最高速度（B/s），还没有有效的采样时为[`None`]
+    pub fn peak_speed(&self) -> Option<u64> {
+        (self.peak_speed > 0).then_some(self.peak_speed)
+    }
+
+    /// 最近一次收到数据的请求的TTFB，重试或者恢复之后为新请求的值
+    pub fn ttfb(&self) -> Option<Duration> {
+        self.ttfb
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn security(&self) -> Option<&ConnectionSecurity> {
+        self.security.as_ref()
+    }
+
+    pub fn policy_match(&self) -> Option<&PolicyMatch> {
+        self.policy_match.as_ref()
+    }
+
+    /// 下载完成后用于校验的校验和，用户输入的校验和优先于自动获取的校验和
+    pub fn expected_checksum(&self) -> Option<ExpectedChecksum> {
+        let user = self
+            .options()
+            .and_then(|o| o.checksum.clone())
+            .map(|checksum| ExpectedChecksum {
+                checksum,
+                source: ChecksumSource::User,
+            });
+        user.or_else(|| {
+            self.remote_checksum
+                .clone()
+                .map(|checksum| ExpectedChecksum {
+                    checksum,
+                    source: ChecksumSource::Remote,
+                })
+        })
+    }
+
+    pub fn write_latency(&self) -> &WriteLatency {
+        &self.write_latency
+    }
+
+    /// 正在下载，并且最近的写入持续较慢，见[`WriteLatency::is_disk_bound`]
+    pub fn is_disk_bound(&self) -> bool {
+        self.phase == TaskPhase::Downloading && self.write_latency.is_disk_bound()
+    }
+
+    /// 显示在任务左下角的状态，文件被占用时提示正在重试
+    pub fn status_label(&self) -> &'static str {
+        if self.file_busy {
+            "File busy, retrying..."
+        } else {
+            self.phase.label()
+        }
+    }
+
+    /// 任务还在排队，并且从来没有连接过服务器，此时可以修改任务的选项
+    pub fn is_editable(&self) -> bool {
+        self.phase == TaskPhase::Queued && self.url.is_none()
+    }
+
+    /// 正在下载并且长度已知时，剩余时间的估计
+    pub fn eta(&self) -> Option<Eta> {
+        let total = self.content_length?;
+        self.eta.estimate(total.saturating_sub(self.downloaded))
+    }
+
+    /// 还需要下载的字节数，还没有收到响应时使用排队期间得到的大小，都未知时为[`None`]
+    pub fn remaining(&self) -> Option<u64> {
+        let total = self.content_length.or(self.preflight_size)?;
+        Some(total.saturating_sub(self.downloaded))
+    }
+
+    /// 按照`display`选择的速度（B/s），还没有平滑后的速度时使用最近一次采样的速度
+    pub fn speed(&self, display: SpeedDisplay) -> Option<u64> {
+        match display {
+            SpeedDisplay::Instant => self.last_speed,
+            SpeedDisplay::Smoothed => self.eta.smoothed_speed().or(self.last_speed),
+        }
+    }
+
+    fn get_speed_string(&self, display: SpeedDisplay) -> String {
+        let prefix = match display {
+            SpeedDisplay::Smoothed if self.eta.smoothed_speed().is_some() => "~",
+            _ => "",
+        };
+        match self.speed(display) {
             None => String::from("-- B/s"),
             Some(speed) => {
-                format!("{}/s", common::get_human_readable_size(speed))
+                format!("{}{}/s", prefix, common::get_human_readable_size(speed))
             }
         }
     }
 
-    fn get_downloaded_string(&self) -> String {
-        if let Some(total) = self.content_length {
-            format!(
+    /// 最后一行右侧的各列：已经下载的大小、剩余的大小（开启时）、速度以及剩余时间，
+    /// 见[`FooterColumns`]
+    ///
+    /// 没有选中时速度和剩余时间使用不同的颜色，健康状况的圆点总是带有颜色。
+    pub fn footer_columns(&self, state: &TaskStateRenderState) -> FooterColumns<'static> {
+        let colored = |span: Span<'static>, color: Color| {
+            if state.selected { span } else { span.fg(color) }
+        };
+        let size = match self.content_length {
+            Some(total) => format!(
                 "{}/{}",
                 common::get_human_readable_size(self.downloaded),
                 common::get_human_readable_size(total)
-            )
-        } else {
-            format!("{} / --", common::get_human_readable_size(self.downloaded))
+            ),
+            None => format!("{} / --", common::get_human_readable_size(self.downloaded)),
+        };
+        let mut columns = FooterColumns::new(state.symbols.separator)
+            .with_column(size, FooterColumns::SIZE_WIDTH);
+        if state.show_remaining {
+            let remaining = self.content_length.map_or_else(String::new, |total| {
+                format!(
+                    "{} left",
+                    common::get_human_readable_size(total.saturating_sub(self.downloaded))
+                )
+            });
+            columns = columns.with_column(remaining, FooterColumns::REMAINING_WIDTH);
         }
+        // 健康状况的圆点放在速度之前，没有时留出同样的宽度
+        let dot = match self.health.health() {
+            Some(health) => Span::from(state.symbols.health).fg(health.color()),
+            None => Span::from(" ".repeat(state.symbols.health.width())),
+        };
+        let speed = colored(
+            Span::from(self.get_speed_string(state.speed_display)),
+            Color::LightCyan,
+        );
+        let eta = colored(
+            Span::from(self.eta().map(|eta| eta.to_string()).unwrap_or_default()),
+            Color::LightGreen,
+        );
+        columns
+            .with_column(
+                vec![dot, Span::raw(" "), speed],
+                state.symbols.health.width() as u16 + 1 + FooterColumns::SPEED_WIDTH,
+            )
+            .with_column(eta, FooterColumns::ETA_WIDTH)
     }
 
     // ---------------------- FUNCTION ------------------------
 
-    /// 更新下载速度信息
-    pub fn ui_update(&mut self) {
-        let now = Instant::now();
+    /// 清除已经下载的进度，准备从头重新下载
+    ///
+    /// 文件路径、URL以及任务的选项保持不变，已经写入的数据由恢复的任务截断。
+    pub fn reset_progress(&mut self) {
+        self.downloaded = 0;
+        self.pieces = None;
+        self.phase = TaskPhase::Queued;
+        self.phase_progress = None;
+        self.conflict = None;
+        self.file_busy = false;
+        self.slot = None;
+        self.extracted = None;
+        self.completed = None;
+        self.verified_checksum = None;
+        self.security = None;
+        self.write_latency = Arc::new(WriteLatency::new());
+        self.last_updated = Instant::now();
+        self.last_downloaded = 0;
+        self.last_speed = None;
+        self.health.reset();
+        self.eta.reset();
+        self.peak_ready = false;
+        self.ttfb = None;
+        self.fetched_range = None;
+    }
+
+    /// 在已经下载的数据之后又写入了`len`字节
+    ///
+    /// 文件长度变化或者重新从头下载时，根据已经下载的字节数重建[`PieceMap`]。
+    pub fn record_received(&mut self, len: u64) {
+        let offset = self.downloaded;
+        self.downloaded += len;
+        let Some(total) = self.content_length else {
+            self.pieces = None;
+            return;
+        };
+        let pieces = self
+            .pieces
+            .get_or_insert_with(|| Arc::new(PieceMap::new(total)));
+        if pieces.total() != total || pieces.completed() != offset {
+            let mut rebuilt = PieceMap::new(total);
+            rebuilt.record(0, offset);
+            *pieces = Arc::new(rebuilt);
+        }
+        Arc::make_mut(pieces).record(offset, len);
+    }
+
+    /// 记录一次写入文件的耗时
+    pub fn record_write(&mut self, elapsed: Duration) {
+        Arc::make_mut(&mut self.write_latency).record(elapsed);
+    }
+
+    /// 更新下载速度，`running`为`true`并且正在接收数据时同时更新健康状况以及剩余时间，
+    /// `half_life`为平滑速度时使用的半衰期，见[`EtaEstimator::weight`]
+    pub fn ui_update(&mut self, thresholds: &HealthThresholds, half_life: Duration, running: bool) {
+        self.ui_update_at(Instant::now(), thresholds, half_life, running);
+    }
+
+    /// 与[`TaskState::ui_update`]相同，使用`now`作为当前时间
+    pub fn ui_update_at(
+        &mut self,
+        now: Instant,
+        thresholds: &HealthThresholds,
+        half_life: Duration,
+        running: bool,
+    ) {
+        if !running || self.phase != TaskPhase::Downloading {
+            self.health.reset();
+            self.eta.reset();
+            self.peak_ready = false;
+        }
+
         let elapsed = now.duration_since(self.last_updated);
 
         // 我们已经限制了刷新间隔，因此只有当距离上次刷新时间超过该间隔时，才更新速度信息
         if elapsed >= TaskState::REFRESH_INTERVAL {
-            let downloaded_since_last = self.downloaded - self.last_downloaded;
-            let speed = downloaded_since_last as f64 / elapsed.as_secs_f64();
+            let downloaded_since_last = self.downloaded.saturating_sub(self.last_downloaded);
+            let speed = (downloaded_since_last as f64 / elapsed.as_secs_f64()) as u64;
 
-            self.last_speed = Some(speed as u64);
+            self.last_speed = Some(speed);
             self.last_updated = now;
             self.last_downloaded = self.downloaded;
+            if running && self.phase == TaskPhase::Downloading {
+                self.health
+                    .update(now, downloaded_since_last, speed, thresholds);
+                self.eta.update(speed, elapsed, half_life);
+                if self.peak_ready {
+                    self.peak_speed = self.peak_speed.max(speed);
+                }
+                self.peak_ready = true;
+            }
+        }
+    }
+
+    fn render_gauge(&self, area: Rect, buf: &mut Buffer) {
+        // 校验和解压时使用同一个进度条显示该阶段的进度
+        let phase_label = match self.phase {
+            TaskPhase::Verifying => Some("verifying"),
+            TaskPhase::Extracting => Some("extracting"),
+            TaskPhase::Publishing => Some("copying"),
+            _ => None,
+        };
+        if let Some(label) = phase_label {
+            let ratio = self.phase_progress.unwrap_or(0.0).clamp(0.0, 1.0);
+            Gauge::default()
+                .label(
+                    Span::from(format!("{} {}%", label, (ratio * 100.0) as u16))
+                        .style(TaskState::BAR_TEXT_STYLE),
+                )
+                .gauge_style(TaskState::BAR_STYLE_VERIFYING)
+                .style(TaskState::BAR_TEXT_STYLE)
+                .ratio(ratio)
+                .use_unicode(true)
+                .render(area, buf);
+            return;
+        }
+        match self.content_length {
+            Some(total) => {
+                let percentage = (if total == 0 {
+                    0.0
+                } else {
+                    self.downloaded as f64 / total as f64 * 100.0
+                } as u64)
+                    .clamp(0, 100) as u16;
+
+                Gauge::default()
+                    .label(Span::from(format!("{}%", percentage)).style(TaskState::BAR_TEXT_STYLE))
+                    .gauge_style(TaskState::BAR_STYLE_WITH_TOTAL)
+                    .style(TaskState::BAR_TEXT_STYLE)
+                    .percent(percentage)
+                    .use_unicode(true)
+                    .render(area, buf);
+            }
+            None => {
+                Gauge::default()
+                    .label(
+                        Span::from(common::get_human_readable_size(self.downloaded))
+                            .style(TaskState::BAR_TEXT_STYLE),
+                    )
+                    .gauge_style(TaskState::BAR_STYLE_NO_TOTAL)
+                    .style(TaskState::BAR_TEXT_STYLE)
+                    .ratio(1.0)
+                    .use_unicode(true)
+                    .render(area, buf);
+            }
+        }
+    }
+}
+
+/// 任务执行过程中的阶段，按照以下顺序推进：
+///
+/// Queued -> Connecting -> (WaitingForDecision) -> Connecting -> Downloading -> Syncing
+/// -> (Verifying) -> (Extracting) -> Done
+///
+/// 等待用户做出决定（WaitingForDecision或者AwaitingConfirm）期间任务不占用名额，
+/// 做出决定之后回到Queued重新排队。
+///
+/// 只有指定了校验和，并且没有在下载时计算出哈希值（例如从中途恢复的任务）时，
+/// 才需要重新读取文件进入Verifying阶段。只有开启了自动解压的任务才会进入Extracting阶段。
+/// 重新校验已有文件的任务不连接服务器，从Queued直接进入Verifying阶段。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskPhase {
+    /// 等待空闲的名额，见[`ConnectionLimits`]
+    ///
+    /// [`ConnectionLimits`]: crate::app::task::ConnectionLimits
+    Queued,
+    /// 正在连接服务器
+    Connecting,
+    /// 保存路径已经存在文件，等待用户决定
+    WaitingForDecision,
+    /// 文件类型匹配提示规则，等待用户确认是否继续下载
+    AwaitingConfirm,
+    /// 正在接收数据
+    Downloading,
+    /// 数据已经全部接收，正在将文件同步到磁盘
+    Syncing,
+    /// 正在读取文件校验，进度见[`TaskState::phase_progress`]
+    Verifying,
+    /// 正在解压下载完成的压缩包，进度见[`TaskState::phase_progress`]
+    Extracting,
+    /// 正在将文件链接或者复制到`completed_dir`，复制的进度见[`TaskState::phase_progress`]
+    Publishing,
+    /// 任务成功完成
+    Done,
+}
+
+impl TaskPhase {
+    /// 是否在等待用户回应对话框
+    pub fn is_awaiting_user(self) -> bool {
+        matches!(
+            self,
+            TaskPhase::WaitingForDecision | TaskPhase::AwaitingConfirm
+        )
+    }
+
+    /// 显示在任务左下角的简短说明
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskPhase::Queued => "Queued, waiting for a free slot...",
+            TaskPhase::Connecting => "Connecting...",
+            TaskPhase::WaitingForDecision => {
+                "File exists, waiting for input \u{2014} press Enter to answer"
+            }
+            TaskPhase::AwaitingConfirm => {
+                "Flagged file type, waiting for input \u{2014} press Enter to answer"
+            }
+            TaskPhase::Downloading => "Downloading...",
+            TaskPhase::Syncing => "Syncing to disk...",
+            TaskPhase::Verifying => "Verifying checksum...",
+            TaskPhase::Extracting => "Extracting...",
+            TaskPhase::Publishing => "Copying to completed folder...",
+            TaskPhase::Done => "Done",
+        }
+    }
+}
+
+/// 用户指定的保存路径上已经存在的文件的信息，用于在对话框中展示给用户
+#[derive(Debug, Clone)]
+pub struct FileConflict {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileConflict {
+    pub fn new(path: PathBuf, size: u64, modified: Option<SystemTime>) -> Self {
+        FileConflict {
+            path,
+            size,
+            modified,
+        }
+    }
+}
+
+/// 任务中间一行显示的内容，在下载页面中使用`i`键切换
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MiddleRowMode {
+    /// 进度条
+    #[default]
+    Progress,
+    /// 完整的URL
+    Url,
+    /// 文件所在的目录
+    Directory,
+}
+
+impl MiddleRowMode {
+    /// Progress -> Url -> Directory -> Progress ...
+    pub fn next(self) -> Self {
+        match self {
+            MiddleRowMode::Progress => MiddleRowMode::Url,
+            MiddleRowMode::Url => MiddleRowMode::Directory,
+            MiddleRowMode::Directory => MiddleRowMode::Progress,
         }
     }
 }
@@ -132,28 +656,61 @@ impl TaskState {
 pub struct TaskStateRenderState {
     pub page_focused: bool,
     pub selected: bool,
+    pub middle_row: MiddleRowMode,
+    pub symbols: SymbolSet,
+    pub id: Option<TaskId>,
+    pub show_remaining: bool,
+    pub speed_display: SpeedDisplay,
 }
 
 impl TaskStateRenderState {
-    pub fn new(page_focused: bool, selected: bool) -> Self {
+    pub fn new(
+        page_focused: bool,
+        selected: bool,
+        middle_row: MiddleRowMode,
+        symbols: SymbolSet,
+    ) -> Self {
         TaskStateRenderState {
             page_focused,
             selected,
+            middle_row,
+            symbols,
+            id: None,
+            show_remaining: false,
+            speed_display: SpeedDisplay::default(),
         }
     }
+
+    /// 在文件名之前显示任务编号
+    pub fn with_id(mut self, id: TaskId) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// 在已经下载的大小之后增加一列剩余的大小，见[`TaskState::footer_columns`]
+    pub fn with_show_remaining(mut self, show_remaining: bool) -> Self {
+        self.show_remaining = show_remaining;
+        self
+    }
+
+    pub fn with_speed_display(mut self, speed_display: SpeedDisplay) -> Self {
+        self.speed_display = speed_display;
+        self
+    }
 }
 
 /// 给TaskState实现[`StatefulWidget`] trait，以便在UI线程中渲染任务状态。
 /// 具体的渲染的样子可以见[`DownloadList`]的文档。
 ///
 /// 请注意，下载速度信息的更新并不在这里进行，你需要调用[`TaskState::ui_update`]
-/// 方法来更新速度信息。
+/// 方法来更新速度信息，下载列表在[`DownloadList::handle_async`]中为所有任务更新。
 ///
 /// 由于TaskState渲染的内容较多，不建议使用[`Mutex::lock`]的方式在UI线程中直接调用，
 /// 推荐将TaskState进行复制，然后在UI线程中渲染复制的内容。这不会带来太大的性能损失，
 /// 原因是UI线程的渲染频率并不高。
 ///
 /// [`DownloadList`]: crate::window::app::DownloadList
+/// [`DownloadList::handle_async`]: crate::window::app::DownloadList::handle_async
 impl StatefulWidget for &mut TaskState {
     type State = TaskStateRenderState;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State)
@@ -198,52 +755,65 @@ impl StatefulWidget for &mut TaskState {
         ])
         .split(bar)[1];
 
-        // 文件名
-        Paragraph::new(self.filepath.to_string_lossy())
-            .style(text_style)
-            .left_aligned()
-            .render(text, buf);
-
-        // 进度条
-        match self.content_length {
-            Some(total) => {
-                let percentage = (if total == 0 {
-                    0.0
-                } else {
-                    self.downloaded as f64 / total as f64 * 100.0
-                } as u64)
-                    .clamp(0, 100) as u16;
-
-                Gauge::default()
-                    .label(Span::from(format!("{}%", percentage)).style(TaskState::BAR_TEXT_STYLE))
-                    .gauge_style(TaskState::BAR_STYLE_WITH_TOTAL)
-                    .style(TaskState::BAR_TEXT_STYLE)
-                    .percent(percentage)
-                    .use_unicode(true)
+        // 文件名，右侧显示加速的标记，以及占用的名额或者排队的位置
+        let boost = if self.boost {
+            format!(" {}", state.symbols.boost)
+        } else {
+            String::new()
+        };
+        let badge = self
+            .slot
+            .map(|slot| format!(" {}", slot))
+            .unwrap_or_default();
+        let [text, boost_area, badge_area] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(boost.width() as u16),
+            Constraint::Length(badge.width() as u16),
+        ])
+        .areas(text);
+        Paragraph::new(boost)
+            .style(text_style.add_modifier(Modifier::BOLD))
+            .render(boost_area, buf);
+        Paragraph::new(badge)
+            .style(text_style.add_modifier(Modifier::DIM))
+            .render(badge_area, buf);
+        let filename = self.filepath.to_string_lossy();
+        Paragraph::new(common::numbered_line(
+            state.id,
+            common::display_sanitize(&filename),
+        ))
+        .style(text_style)
+        .left_aligned()
+        .render(text, buf);
+
+        // 进度条，或者根据设置显示URL、目录
+        match state.middle_row {
+            MiddleRowMode::Progress => self.render_gauge(bar, buf),
+            MiddleRowMode::Url => {
+                let url = self.url.as_ref().map(Url::as_str).unwrap_or("--");
+                Paragraph::new(common::display_sanitize(url))
+                    .style(text_style)
+                    .left_aligned()
                     .render(bar, buf);
             }
-            None => {
-                Gauge::default()
-                    .label(
-                        Span::from(common::get_human_readable_size(self.downloaded))
-                            .style(TaskState::BAR_TEXT_STYLE),
-                    )
-                    .gauge_style(TaskState::BAR_STYLE_NO_TOTAL)
-                    .style(TaskState::BAR_TEXT_STYLE)
-                    .ratio(1.0)
-                    .use_unicode(true)
+            MiddleRowMode::Directory => {
+                let dir = self
+                    .filepath
+                    .parent()
+                    .map(|p| p.to_string_lossy())
+                    .unwrap_or_default();
+                Paragraph::new(common::display_sanitize(&dir))
+                    .style(text_style)
+                    .left_aligned()
                     .render(bar, buf);
             }
         }
 
-        // 其他信息
-        Paragraph::new(format!(
-            "{} | {}",
-            self.get_downloaded_string(),
-            self.get_speed_string()
-        ))
-        .style(text_style)
-        .right_aligned()
-        .render(footer, buf);
+        // 其他信息按列对齐，左侧的状态由TaskListener绘制在同一行
+        let columns = self.footer_columns(state);
+        let [_, columns_area] = columns.split(footer);
+        Paragraph::new(columns.into_line(columns_area.width))
+            .style(text_style)
+            .render(columns_area, buf);
     }
 }
@@ -0,0 +1,252 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// 批量下载时使用的文件命名模板，在"Save as"中输入。
+///
+/// 支持的占位符：
+///
+/// - `{n}`：从1开始递增的序号，`{n:03}`表示使用0补齐到3位
+/// - `{name}`：根据URL得到的原始文件名
+/// - `{host}`：URL中的主机名
+/// - `{date}`：下载开始时的日期，格式为`%Y-%m-%d`
+///
+/// 使用`{{`和`}}`表示字面量的花括号。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NameTemplate {
+    source: String,
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    Counter { width: usize },
+    Name,
+    Host,
+    Date,
+}
+
+/// 展开模板时需要的信息
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateContext<'a> {
+    /// 在同一批任务中的序号，从1开始
+    pub n: usize,
+    pub name: &'a str,
+    pub host: &'a str,
+    pub date: NaiveDate,
+}
+
+impl NameTemplate {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '}' => return Err(TemplateError::UnmatchedBrace),
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(TemplateError::UnclosedPlaceholder),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Self::parse_placeholder(&placeholder)?);
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+
+        Ok(NameTemplate {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    fn parse_placeholder(placeholder: &str) -> Result<TemplatePart, TemplateError> {
+        match placeholder {
+            "n" => Ok(TemplatePart::Counter { width: 0 }),
+            "name" => Ok(TemplatePart::Name),
+            "host" => Ok(TemplatePart::Host),
+            "date" => Ok(TemplatePart::Date),
+            _ => match placeholder.strip_prefix("n:") {
+                Some(width) => width
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&w| w <= 20)
+                    .map(|width| TemplatePart::Counter { width })
+                    .ok_or(TemplateError::InvalidWidth(width.to_string())),
+                None => Err(TemplateError::UnknownPlaceholder(placeholder.to_string())),
+            },
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 输入的内容是否应该被视为模板
+    pub fn is_template(source: &str) -> bool {
+        source.contains('{') || source.contains('}')
+    }
+
+    pub fn expand(&self, context: &TemplateContext) -> PathBuf {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Literal(s) => result.push_str(s),
+                TemplatePart::Counter { width } => {
+                    result.push_str(&format!("{:0width$}", context.n, width = *width))
+                }
+                TemplatePart::Name => result.push_str(context.name),
+                TemplatePart::Host => result.push_str(context.host),
+                TemplatePart::Date => result.push_str(&context.date.format("%Y-%m-%d").to_string()),
+            }
+        }
+        PathBuf::from(result)
+    }
+}
+
+impl TryFrom<String> for NameTemplate {
+    type Error = TemplateError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        NameTemplate::parse(&value)
+    }
+}
+
+impl From<NameTemplate> for String {
+    fn from(value: NameTemplate) -> Self {
+        value.source
+    }
+}
+
+/// 模板中的错误，在提交之前显示给用户
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    UnclosedPlaceholder,
+    UnmatchedBrace,
+    UnknownPlaceholder(String),
+    InvalidWidth(String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnclosedPlaceholder => write!(f, "Missing '}}' in template"),
+            TemplateError::UnmatchedBrace => write!(f, "Unmatched '}}' in template, use '}}}}'"),
+            TemplateError::UnknownPlaceholder(p) => write!(f, "Unknown placeholder {{{}}}", p),
+            TemplateError::InvalidWidth(w) => write!(f, "Invalid counter width: {}", w),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(n: usize, name: &str) -> TemplateContext<'_> {
+        TemplateContext {
+            n,
+            name,
+            host: "example.com",
+            date: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+        }
+    }
+
+    fn expand(template: &str, context: &TemplateContext) -> String {
+        let path = NameTemplate::parse(template).unwrap().expand(context);
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn expands_all_placeholders() {
+        let context = context(7, "a b.zip");
+        assert_eq!(expand("{n}-{name}", &context), "7-a b.zip");
+        assert_eq!(expand("{n:03}_{name}", &context), "007_a b.zip");
+        assert_eq!(
+            expand("{host}/{date}/{name}", &context),
+            "example.com/2026-10-16/a b.zip"
+        );
+        assert_eq!(expand("plain.bin", &context), "plain.bin");
+    }
+
+    #[test]
+    fn counter_wider_than_width_is_not_truncated() {
+        assert_eq!(expand("{n:2}", &context(123, "x")), "123");
+        assert_eq!(expand("{n:0}", &context(5, "x")), "5");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let context = context(1, "x");
+        assert_eq!(expand("{{n}}-{n}", &context), "{n}-1");
+        assert_eq!(expand("}}{{", &context), "}{");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert_eq!(
+            NameTemplate::parse("{name"),
+            Err(TemplateError::UnclosedPlaceholder)
+        );
+        assert_eq!(
+            NameTemplate::parse("name}"),
+            Err(TemplateError::UnmatchedBrace)
+        );
+        assert_eq!(
+            NameTemplate::parse("{ext}"),
+            Err(TemplateError::UnknownPlaceholder(String::from("ext")))
+        );
+        assert_eq!(
+            NameTemplate::parse("{n:abc}"),
+            Err(TemplateError::InvalidWidth(String::from("abc")))
+        );
+        assert_eq!(
+            NameTemplate::parse("{n:21}"),
+            Err(TemplateError::InvalidWidth(String::from("21")))
+        );
+    }
+
+    #[test]
+    fn detects_templates_and_keeps_source() {
+        assert!(NameTemplate::is_template("{n}.bin"));
+        assert!(NameTemplate::is_template("odd}"));
+        assert!(!NameTemplate::is_template("dir/file.bin"));
+        let template = NameTemplate::parse("{n:03}-{name}").unwrap();
+        assert_eq!(template.source(), "{n:03}-{name}");
+        assert_eq!(String::from(template.clone()), "{n:03}-{name}");
+        assert_eq!(
+            NameTemplate::try_from(String::from("{n:03}-{name}")),
+            Ok(template)
+        );
+    }
+}
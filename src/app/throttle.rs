@@ -0,0 +1,847 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::app::task::LogLimiter;
+
+/// 下载速度上限（B/s），`0`表示不限速。
+///
+/// 在配置文件中使用字符串表示，例如`"512K"`、`"2M"`、`"1.5M"`，单位为1024进制，
+/// `"0"`、`"off"`和`"unlimited"`表示不限速。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SpeedLimit(u64);
+
+impl SpeedLimit {
+    // ------------------- CONSTANT -----------------------
+
+    pub const UNLIMITED: SpeedLimit = SpeedLimit(0);
+
+    /// 使用`[`和`]`手动调整速度上限时依次经过的档位
+    const STEPS: [u64; 10] = [
+        64 << 10,
+        128 << 10,
+        256 << 10,
+        512 << 10,
+        1 << 20,
+        2 << 20,
+        4 << 20,
+        8 << 20,
+        16 << 20,
+        32 << 20,
+    ];
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(bytes_per_sec: u64) -> Self {
+        SpeedLimit(bytes_per_sec)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ThrottleError> {
+        let source = source.trim();
+        if matches!(
+            source.to_ascii_lowercase().as_str(),
+            "0" | "off" | "unlimited"
+        ) {
+            return Ok(SpeedLimit::UNLIMITED);
+        }
+
+        let number = source.strip_suffix(['B', 'b']).unwrap_or(source).trim_end();
+        let (number, unit) = match number.chars().last() {
+            Some('K' | 'k') => (&number[..number.len() - 1], 1u64 << 10),
+            Some('M' | 'm') => (&number[..number.len() - 1], 1u64 << 20),
+            Some('G' | 'g') => (&number[..number.len() - 1], 1u64 << 30),
+            _ => (number, 1),
+        };
+        let value: f64 = number
+            .trim()
+            .parse()
+            .map_err(|_| ThrottleError::InvalidLimit(source.to_string()))?;
+        if !value.is_finite() || value < 0.0 {
+            return Err(ThrottleError::InvalidLimit(source.to_string()));
+        }
+        Ok(SpeedLimit((value * unit as f64) as u64))
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.0
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.0 == 0
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 比当前上限更低的下一个档位，不限速时为最高的档位，已经低于最低的档位时保持不变
+    pub fn step_down(self) -> Self {
+        if self.is_unlimited() {
+            return SpeedLimit(Self::STEPS[Self::STEPS.len() - 1]);
+        }
+        Self::STEPS
+            .iter()
+            .rev()
+            .find(|&&step| step < self.0)
+            .map_or(self, |&step| SpeedLimit(step))
+    }
+
+    /// 比当前上限更高的下一个档位，超过最高的档位之后为不限速
+    pub fn step_up(self) -> Self {
+        if self.is_unlimited() {
+            return self;
+        }
+        Self::STEPS
+            .iter()
+            .find(|&&step| step > self.0)
+            .map_or(SpeedLimit::UNLIMITED, |&step| SpeedLimit(step))
+    }
+}
+
+impl Display for SpeedLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "unlimited"),
+            n if n < 1 << 10 => write!(f, "{} B/s", n),
+            n if n < 1 << 20 => write!(f, "{:.0} KB/s", n as f64 / 1024.0),
+            n => write!(f, "{:.1} MB/s", n as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+impl TryFrom<String> for SpeedLimit {
+    type Error = ThrottleError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        SpeedLimit::parse(&value)
+    }
+}
+
+impl From<SpeedLimit> for String {
+    fn from(value: SpeedLimit) -> Self {
+        match value.0 {
+            0 => String::from("0"),
+            n if n % (1 << 30) == 0 => format!("{}G", n >> 30),
+            n if n % (1 << 20) == 0 => format!("{}M", n >> 20),
+            n if n % (1 << 10) == 0 => format!("{}K", n >> 10),
+            n => n.to_string(),
+        }
+    }
+}
+
+/// 一天中的时刻，在配置文件中以`"HH:MM"`表示
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClockTime(NaiveTime);
+
+impl ClockTime {
+    pub fn parse(source: &str) -> Result<Self, ThrottleError> {
+        NaiveTime::parse_from_str(source.trim(), "%H:%M")
+            .map(ClockTime)
+            .map_err(|_| ThrottleError::InvalidTime(source.to_string()))
+    }
+
+    pub fn time(&self) -> NaiveTime {
+        self.0
+    }
+}
+
+impl Display for ClockTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+impl TryFrom<String> for ClockTime {
+    type Error = ThrottleError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ClockTime::parse(&value)
+    }
+}
+
+impl From<ClockTime> for String {
+    fn from(value: ClockTime) -> Self {
+        value.to_string()
+    }
+}
+
+/// 速度计划中的一项，在`[from, to)`（本地时间）内使用`limit`作为速度上限。
+///
+/// `from`晚于`to`时表示跨越午夜，例如`23:00`到`07:00`；`from`等于`to`时表示全天。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub from: ClockTime,
+    pub to: ClockTime,
+    pub limit: SpeedLimit,
+}
+
+impl ScheduleEntry {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (from, to) = (self.from.time(), self.to.time());
+        match from.cmp(&to) {
+            std::cmp::Ordering::Less => from <= time && time < to,
+            std::cmp::Ordering::Greater => time >= from || time < to,
+            std::cmp::Ordering::Equal => true,
+        }
+    }
+}
+
+/// 在`time`时生效的计划项，多个计划项重叠时使用最靠前的一项
+pub fn active_entry(schedule: &[ScheduleEntry], time: NaiveTime) -> Option<&ScheduleEntry> {
+    schedule.iter().find(|entry| entry.contains(time))
+}
+
+/// 当前速度上限的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitSource {
+    /// 配置中的`speed_limit`
+    Default,
+    /// 速度计划中的一项
+    Schedule(ScheduleEntry),
+    /// 使用`[`和`]`手动设置，直到手动清除之前一直有效
+    Manual,
+}
+
+/// 当前生效的速度上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveLimit {
+    pub limit: SpeedLimit,
+    pub source: LimitSource,
+}
+
+impl Display for ActiveLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.source {
+            LimitSource::Default => write!(f, "{}", self.limit),
+            LimitSource::Schedule(entry) => {
+                write!(f, "{} (schedule {}-{})", self.limit, entry.from, entry.to)
+            }
+            LimitSource::Manual => write!(f, "{} (manual)", self.limit),
+        }
+    }
+}
+
+/// 所有下载任务共享的限速器，由UI线程和[`TaskManager`]共享。
+///
+/// 所有任务共用同一个速度上限，任务开始接收数据时通过[`Throttle::join`]加入，
+/// 每收到一块数据都需要调用[`ThrottleShare::consume`]，限速器根据上限计算该数据块
+/// 可以被接收的时间并等待。
+///
+/// 开启公平模式（配置中的`fair_share`，在下载页面中按`F`切换）并且设置了上限时，
+/// 每个正在接收数据的任务最多使用上限的`1/n`，超出的部分最多为[`Throttle::FAIR_BURST`]
+/// 时间内的份额，避免服务器推送更快的任务占满带宽。任务暂停或者结束时退出，
+/// 份额随之重新计算。没有上限时无法知道可用的带宽，公平模式不起作用。
+///
+/// 加速的任务（在下载页面中按`!`）的权重为[`Throttle::BOOST_WEIGHT`]，其他任务为1。
+/// 公平模式下每个任务按权重分配上限；没有开启公平模式时，只要有加速的任务，
+/// 其他任务就按权重限制在各自的份额之内，剩余的带宽都留给加速的任务。
+///
+/// 速度计划由[`Throttle::run_schedule`]每分钟检查一次，因此正在进行的下载
+/// 不需要重新启动就会使用新的上限。
+///
+/// [`TaskManager`]: crate::app::task::TaskManager
+#[derive(Debug)]
+pub struct Throttle {
+    inner: Mutex<ThrottleInner>,
+}
+
+#[derive(Debug)]
+struct ThrottleInner {
+    default: SpeedLimit,
+    schedule: Vec<ScheduleEntry>,
+    manual: Option<SpeedLimit>,
+    active: ActiveLimit,
+    // 下一块数据最早可以被接收的时间
+    next_slot: Instant,
+    fair: bool,
+    // 正在接收数据的任务
+    shares: HashMap<u64, Share>,
+    next_share: u64,
+    // 每块数据都可能需要等待，限制相关日志的频率
+    wait_log: LogLimiter,
+}
+
+#[derive(Debug)]
+struct Share {
+    // 下一块数据最早可以被接收的时间
+    next: Instant,
+    boosted: bool,
+}
+
+impl Share {
+    fn weight(&self) -> u32 {
+        if self.boosted {
+            Throttle::BOOST_WEIGHT
+        } else {
+            1
+        }
+    }
+}
+
+impl ThrottleInner {
+    fn total_weight(&self) -> u32 {
+        self.shares.values().map(Share::weight).sum()
+    }
+
+    fn refresh(&mut self, time: NaiveTime) {
+        let active = match (self.manual, active_entry(&self.schedule, time)) {
+            (Some(limit), _) => ActiveLimit {
+                limit,
+                source: LimitSource::Manual,
+            },
+            (None, Some(entry)) => ActiveLimit {
+                limit: entry.limit,
+                source: LimitSource::Schedule(*entry),
+            },
+            (None, None) => ActiveLimit {
+                limit: self.default,
+                source: LimitSource::Default,
+            },
+        };
+        if active != self.active {
+            log::info!(target:"Throttle", "Speed limit changed to {}", active);
+            // 之前的上限预留的时间不再有意义
+            if active.limit != self.active.limit {
+                self.next_slot = Instant::now();
+            }
+            self.active = active;
+        }
+    }
+
+    /// `share`在`now`收到了`bytes`字节，返回可以继续接收的时间
+    fn reserve(&mut self, now: Instant, share: u64, bytes: usize) -> Instant {
+        let limit = self.active.limit;
+        if limit.is_unlimited() {
+            self.next_slot = now;
+            return now;
+        }
+        let cost = Duration::from_secs_f64(bytes as f64 / limit.bytes_per_sec() as f64);
+        self.next_slot = self.next_slot.max(now) + cost;
+        let count = self.shares.len();
+        let boosting = self.shares.values().any(|share| share.boosted);
+        let total = self.total_weight();
+        match self.shares.get_mut(&share) {
+            Some(slot) if count > 1 && (self.fair || (boosting && !slot.boosted)) => {
+                // 空闲的任务最多积累FAIR_BURST的份额，之后按照权重/总权重的速度接收
+                let earliest = now.checked_sub(Throttle::FAIR_BURST).unwrap_or(now);
+                let cost = cost.mul_f64(total as f64 / slot.weight() as f64);
+                slot.next = slot.next.max(earliest) + cost;
+                self.next_slot.max(slot.next)
+            }
+            _ => self.next_slot,
+        }
+    }
+}
+
+impl Throttle {
+    // ------------------- CONSTANT -----------------------
+
+    pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+    /// 公平模式下，单个任务可以超出份额的部分，以按份额接收的时间计算
+    pub const FAIR_BURST: Duration = Duration::from_millis(250);
+    /// 加速的任务相对于普通任务的权重
+    pub const BOOST_WEIGHT: u32 = 3;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(default: SpeedLimit, schedule: Vec<ScheduleEntry>) -> Self {
+        let mut inner = ThrottleInner {
+            default,
+            schedule,
+            manual: None,
+            active: ActiveLimit {
+                limit: default,
+                source: LimitSource::Default,
+            },
+            next_slot: Instant::now(),
+            fair: false,
+            shares: HashMap::new(),
+            next_share: 0,
+            wait_log: LogLimiter::default(),
+        };
+        inner.refresh(Local::now().time());
+        Throttle {
+            inner: Mutex::new(inner),
+        }
+    }
+
+    pub fn with_fair_share(self, fair: bool) -> Self {
+        self.set_fair_share(fair);
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn active(&self) -> ActiveLimit {
+        self.inner.lock().unwrap().active
+    }
+
+    pub fn is_fair(&self) -> bool {
+        self.inner.lock().unwrap().fair
+    }
+
+    /// 公平模式下的份额，没有开启公平模式时为[`None`]
+    pub fn fair_status(&self) -> Option<FairStatus> {
+        let inner = self.inner.lock().unwrap();
+        inner.fair.then(|| FairStatus {
+            limit: inner.active.limit,
+            tasks: inner.shares.len(),
+            boosted: inner.shares.values().filter(|share| share.boosted).count(),
+        })
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 根据当前的本地时间重新计算速度上限
+    pub fn refresh(&self) {
+        self.inner.lock().unwrap().refresh(Local::now().time());
+    }
+
+    /// 设置手动的速度上限，为[`None`]时清除手动设置，恢复使用配置中的上限
+    pub fn set_manual(&self, limit: Option<SpeedLimit>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.manual = limit;
+        inner.refresh(Local::now().time());
+    }
+
+    /// 替换配置中的默认上限以及速度计划，手动设置的上限仍然优先
+    pub fn set_defaults(&self, default: SpeedLimit, schedule: Vec<ScheduleEntry>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.default = default;
+        inner.schedule = schedule;
+        inner.refresh(Local::now().time());
+    }
+
+    pub fn set_fair_share(&self, fair: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.fair != fair {
+            log::info!(target:"Throttle", "Fair share {}", if fair { "on" } else { "off" });
+            inner.fair = fair;
+        }
+    }
+
+    pub fn toggle_fair_share(&self) {
+        self.set_fair_share(!self.is_fair());
+    }
+
+    pub fn step_down(&self) {
+        let limit = self.active().limit.step_down();
+        self.set_manual(Some(limit));
+    }
+
+    pub fn step_up(&self) {
+        let limit = self.active().limit.step_up();
+        self.set_manual(Some(limit));
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 任务开始接收数据时调用，返回的[`ThrottleShare`]被丢弃时退出
+    pub fn join(self: &Arc<Self>) -> ThrottleShare {
+        let now = Instant::now();
+        let id = {
+            let mut inner = self.inner.lock().unwrap();
+            let id = inner.next_share;
+            inner.next_share += 1;
+            inner.shares.insert(
+                id,
+                Share {
+                    next: now,
+                    boosted: false,
+                },
+            );
+            id
+        };
+        ThrottleShare {
+            throttle: self.clone(),
+            id,
+        }
+    }
+
+    /// 与[`ThrottleShare::consume`]相同，但是由调用者提供当前的时间，不等待，
+    /// 返回可以继续接收的时间
+    pub fn reserve(&self, share: &ThrottleShare, now: Instant, bytes: usize) -> Instant {
+        let mut inner = self.inner.lock().unwrap();
+        let deadline = inner.reserve(now, share.id, bytes);
+        if deadline > now
+            && let Some(suppressed) = inner.wait_log.check()
+        {
+            log::trace!(
+                target:"Throttle",
+                "Waiting {:?} for {} bytes at {} ({} similar messages suppressed)",
+                deadline - now,
+                bytes,
+                inner.active.limit,
+                suppressed
+            );
+        }
+        deadline
+    }
+
+    /// 每隔[`Throttle::CHECK_INTERVAL`]检查一次速度计划，需要在后台运行时中执行
+    pub async fn run_schedule(&self) {
+        let mut interval = tokio::time::interval(Self::CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.refresh();
+        }
+    }
+}
+
+/// 加入[`Throttle`]的任务，丢弃时退出，公平模式下的份额随之重新计算
+#[derive(Debug)]
+pub struct ThrottleShare {
+    throttle: Arc<Throttle>,
+    id: u64,
+}
+
+impl ThrottleShare {
+    /// 设置该任务是否加速，从下一块数据开始按新的权重计算
+    pub fn set_boost(&self, boosted: bool) {
+        let mut inner = self.throttle.inner.lock().unwrap();
+        if let Some(share) = inner.shares.get_mut(&self.id) {
+            share.boosted = boosted;
+        }
+    }
+
+    /// 接收了`bytes`字节之后调用，在超过速度上限或者公平模式下的份额时等待
+    pub async fn consume(&self, bytes: usize) {
+        let deadline = self.throttle.reserve(self, Instant::now(), bytes);
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
+
+impl Drop for ThrottleShare {
+    fn drop(&mut self) {
+        self.throttle.inner.lock().unwrap().shares.remove(&self.id);
+    }
+}
+
+/// 公平模式下的份额，显示在下载页面的第一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FairStatus {
+    pub limit: SpeedLimit,
+    /// 正在接收数据的任务数量
+    pub tasks: usize,
+    /// 其中加速的任务数量
+    pub boosted: usize,
+}
+
+impl FairStatus {
+    /// 普通任务的份额，没有上限时为[`None`]，加速的任务为其[`Throttle::BOOST_WEIGHT`]倍
+    pub fn share(&self) -> Option<SpeedLimit> {
+        if self.limit.is_unlimited() {
+            return None;
+        }
+        let weight = self.tasks.max(1) + self.boosted * (Throttle::BOOST_WEIGHT as usize - 1);
+        Some(SpeedLimit::new(
+            (self.limit.bytes_per_sec() / weight as u64).max(1),
+        ))
+    }
+}
+
+impl Display for FairStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.share() {
+            None => write!(f, "fair (no limit)"),
+            Some(share) if self.boosted > 0 => write!(
+                f,
+                "fair, {} x {}, boosted {}",
+                self.tasks.max(1),
+                share,
+                SpeedLimit::new(share.bytes_per_sec() * Throttle::BOOST_WEIGHT as u64)
+            ),
+            Some(share) => write!(f, "fair, {} x {}", self.tasks.max(1), share),
+        }
+    }
+}
+
+/// 单个任务的速度上限，在全局的[`Throttle`]之后生效，只由任务自己使用
+#[derive(Debug)]
+pub struct TaskThrottle {
+    limit: SpeedLimit,
+    next_slot: Instant,
+}
+
+impl TaskThrottle {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(limit: Option<u64>) -> Self {
+        TaskThrottle {
+            limit: SpeedLimit::new(limit.unwrap_or(0)),
+            next_slot: Instant::now(),
+        }
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = SpeedLimit::new(limit.unwrap_or(0));
+        self.next_slot = Instant::now();
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 与[`ThrottleShare::consume`]相同，但是只计算该任务接收的数据
+    pub async fn consume(&mut self, bytes: usize) {
+        let now = Instant::now();
+        if self.limit.is_unlimited() {
+            self.next_slot = now;
+            return;
+        }
+        let cost = Duration::from_secs_f64(bytes as f64 / self.limit.bytes_per_sec() as f64);
+        self.next_slot = self.next_slot.max(now) + cost;
+        tokio::time::sleep_until(self.next_slot.into()).await;
+    }
+}
+
+/// 速度上限配置中的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThrottleError {
+    InvalidLimit(String),
+    InvalidTime(String),
+}
+
+impl Display for ThrottleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ThrottleError::InvalidLimit(s) => write!(f, "Invalid speed limit: {}", s),
+            ThrottleError::InvalidTime(s) => write!(f, "Invalid time, expected HH:MM: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ThrottleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Option<u64> {
+        SpeedLimit::parse(source)
+            .ok()
+            .map(|limit| limit.bytes_per_sec())
+    }
+
+    #[test]
+    fn parse_unlimited_keywords() {
+        for source in ["0", "off", "OFF", "unlimited", " Unlimited "] {
+            assert_eq!(SpeedLimit::parse(source).ok(), Some(SpeedLimit::UNLIMITED));
+        }
+    }
+
+    #[test]
+    fn parse_units_with_optional_byte_suffix() {
+        assert_eq!(parse("512"), Some(512));
+        assert_eq!(parse("512B"), Some(512));
+        assert_eq!(parse("2K"), Some(2 << 10));
+        assert_eq!(parse("2kb"), Some(2 << 10));
+        assert_eq!(parse("3M"), Some(3 << 20));
+        assert_eq!(parse("3 MB"), Some(3 << 20));
+        assert_eq!(parse("1G"), Some(1 << 30));
+        assert_eq!(parse("1gB"), Some(1 << 30));
+    }
+
+    #[test]
+    fn parse_fractional_values() {
+        assert_eq!(parse("1.5K"), Some(1536));
+        assert_eq!(parse("0.5M"), Some(512 << 10));
+        // 不足1字节的部分舍去
+        assert_eq!(parse("10.9"), Some(10));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        for source in [
+            "", "  ", "B", "K", "KB", "-1", "-1K", "NaN", "nanK", "inf", "fast",
+        ] {
+            assert!(
+                matches!(
+                    SpeedLimit::parse(source),
+                    Err(ThrottleError::InvalidLimit(_))
+                ),
+                "{:?} should be rejected",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn parse_saturates_on_overflow() {
+        assert_eq!(parse("1e30G"), Some(u64::MAX));
+        assert_eq!(parse("99999999999999999999"), Some(u64::MAX));
+    }
+
+    fn time(source: &str) -> NaiveTime {
+        ClockTime::parse(source).unwrap().time()
+    }
+
+    fn entry(from: &str, to: &str, limit: u64) -> ScheduleEntry {
+        ScheduleEntry {
+            from: ClockTime::parse(from).unwrap(),
+            to: ClockTime::parse(to).unwrap(),
+            limit: SpeedLimit::new(limit),
+        }
+    }
+
+    #[test]
+    fn step_through_the_presets() {
+        let k = |n: u64| SpeedLimit::new(n << 10);
+        assert_eq!(SpeedLimit::UNLIMITED.step_down(), k(32 << 10));
+        assert_eq!(k(32 << 10).step_up(), SpeedLimit::UNLIMITED);
+        assert_eq!(SpeedLimit::UNLIMITED.step_up(), SpeedLimit::UNLIMITED);
+        assert_eq!(k(512).step_down(), k(256));
+        assert_eq!(k(512).step_up(), k(1024));
+        // 不在档位上的值移动到相邻的档位
+        assert_eq!(k(300).step_down(), k(256));
+        assert_eq!(k(300).step_up(), k(512));
+        assert_eq!(k(64).step_down(), k(64));
+    }
+
+    #[test]
+    fn step_down_below_the_lowest_preset_keeps_the_limit() {
+        let low = SpeedLimit::new(10 << 10);
+        assert_eq!(low.step_down(), low);
+        assert_eq!(low.step_up(), SpeedLimit::new(64 << 10));
+    }
+
+    #[test]
+    fn parse_clock_times() {
+        assert_eq!(time(" 07:30 "), NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(ClockTime::parse("23:05").unwrap().to_string(), "23:05");
+        for source in ["", "24:00", "7", "07:60", "7pm"] {
+            assert!(
+                matches!(ClockTime::parse(source), Err(ThrottleError::InvalidTime(_))),
+                "{:?} should be rejected",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn schedule_entry_within_a_day() {
+        let entry = entry("09:00", "17:00", 1);
+        assert!(!entry.contains(time("08:59")));
+        assert!(entry.contains(time("09:00")));
+        assert!(entry.contains(time("16:59")));
+        assert!(!entry.contains(time("17:00")));
+    }
+
+    #[test]
+    fn schedule_entry_wraps_past_midnight() {
+        let entry = entry("23:00", "07:00", 1);
+        assert!(entry.contains(time("23:00")));
+        assert!(entry.contains(time("00:00")));
+        assert!(entry.contains(time("06:59")));
+        assert!(!entry.contains(time("07:00")));
+        assert!(!entry.contains(time("12:00")));
+        assert!(!entry.contains(time("22:59")));
+    }
+
+    #[test]
+    fn schedule_entry_with_equal_bounds_covers_the_day() {
+        let entry = entry("08:00", "08:00", 1);
+        for source in ["00:00", "07:59", "08:00", "23:59"] {
+            assert!(entry.contains(time(source)));
+        }
+    }
+
+    #[test]
+    fn first_matching_entry_wins() {
+        let schedule = [entry("22:00", "06:00", 1), entry("00:00", "12:00", 2)];
+        assert_eq!(
+            active_entry(&schedule, time("01:00"))
+                .unwrap()
+                .limit
+                .bytes_per_sec(),
+            1
+        );
+        assert_eq!(
+            active_entry(&schedule, time("08:00"))
+                .unwrap()
+                .limit
+                .bytes_per_sec(),
+            2
+        );
+        assert!(active_entry(&schedule, time("13:00")).is_none());
+    }
+
+    #[test]
+    fn manual_limit_overrides_schedule_and_default() {
+        let night = entry("23:00", "07:00", 100);
+        let throttle = Throttle::new(SpeedLimit::new(500), vec![night]);
+        let mut inner = throttle.inner.lock().unwrap();
+
+        inner.refresh(time("12:00"));
+        assert_eq!(inner.active.limit.bytes_per_sec(), 500);
+        assert_eq!(inner.active.source, LimitSource::Default);
+
+        inner.refresh(time("01:00"));
+        assert_eq!(inner.active.limit.bytes_per_sec(), 100);
+        assert_eq!(inner.active.source, LimitSource::Schedule(night));
+
+        inner.manual = Some(SpeedLimit::new(42));
+        inner.refresh(time("01:00"));
+        assert_eq!(inner.active.limit.bytes_per_sec(), 42);
+        assert_eq!(inner.active.source, LimitSource::Manual);
+
+        inner.manual = None;
+        inner.refresh(time("07:00"));
+        assert_eq!(inner.active.source, LimitSource::Default);
+    }
+
+    fn throttle(bytes_per_sec: u64, fair: bool) -> Arc<Throttle> {
+        Arc::new(Throttle::new(SpeedLimit::new(bytes_per_sec), Vec::new()).with_fair_share(fair))
+    }
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn unlimited_throttle_never_waits() {
+        let throttle = throttle(0, true);
+        let (a, _b) = (throttle.join(), throttle.join());
+        let now = Instant::now() + Duration::from_secs(10);
+        assert_eq!(throttle.reserve(&a, now, 1 << 20), now);
+        assert_eq!(throttle.fair_status().unwrap().share(), None);
+    }
+
+    #[test]
+    fn global_limit_is_shared_by_all_tasks() {
+        let throttle = throttle(1000, false);
+        let (a, b) = (throttle.join(), throttle.join());
+        let now = Instant::now() + Duration::from_secs(10);
+        assert_eq!(throttle.reserve(&a, now, 500), now + millis(500));
+        assert_eq!(throttle.reserve(&b, now, 500), now + millis(1000));
+        assert_eq!(throttle.fair_status(), None);
+    }
+
+    #[test]
+    fn fair_share_holds_back_the_greedy_task() {
+        let throttle = throttle(1000, true);
+        let (greedy, quiet) = (throttle.join(), throttle.join());
+        let now = Instant::now() + Duration::from_secs(10);
+        let mut deadline = now;
+        for _ in 0..10 {
+            deadline = throttle.reserve(&greedy, now, 100);
+        }
+        // 全局上限只需要等到1s，两个任务时每个任务只能使用一半，减去积累的FAIR_BURST
+        assert_eq!(deadline, now + millis(2000) - Throttle::FAIR_BURST);
+        // 另一个任务只受全局上限的限制
+        assert_eq!(throttle.reserve(&quiet, now, 100), now + millis(1100));
+
+        let status = throttle.fair_status().unwrap();
+        assert_eq!(status.tasks, 2);
+        assert_eq!(status.share(), Some(SpeedLimit::new(500)));
+
+        // 只剩一个任务时不再限制份额
+        drop(quiet);
+        assert_eq!(throttle.reserve(&greedy, now, 100), now + millis(1200));
+        assert_eq!(throttle.fair_status().unwrap().tasks, 1);
+    }
+}
@@ -0,0 +1,210 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::SystemTime;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Days, Local, TimeZone};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::config::Config;
+
+/// 界面中显示时间的方式，由配置中的`time_format`和`relative_times`生成
+///
+/// `relative`为true时，最近的时间显示为`just now`、`12m ago`、`3h ago`，
+/// 昨天的时间显示为`yesterday 23:50`，更早或者在未来的时间使用`format`。
+/// 以小时计的相对时间不会跨过午夜，跨过午夜之后显示为`yesterday`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeConfig {
+    format: String,
+    relative: bool,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        TimeConfig {
+            format: String::from(TimeConfig::DEFAULT_FORMAT),
+            relative: true,
+        }
+    }
+}
+
+impl TimeConfig {
+    // ------------------- CONSTANT -----------------------
+
+    pub const DEFAULT_FORMAT: &'static str = "%Y-%m-%d %H:%M";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 检查格式中的每一个指令，有无法识别的指令时返回错误
+    pub fn new(format: &str, relative: bool) -> Result<Self, TimeFormatError> {
+        validate(format)?;
+        Ok(TimeConfig {
+            format: format.to_string(),
+            relative,
+        })
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, TimeFormatError> {
+        TimeConfig::new(&config.time_format, config.relative_times)
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn is_relative(&self) -> bool {
+        self.relative
+    }
+
+    /// 以该方式显示的时间的最大宽度，用于按列对齐
+    pub fn max_width(&self) -> usize {
+        // 名称最长的月份和星期几：2000年9月27日是星期三
+        let widest = Local
+            .with_ymd_and_hms(2000, 9, 27, 23, 59, 59)
+            .earliest()
+            .unwrap_or_else(Local::now);
+        let absolute = widest.format(&self.format).to_string().width();
+        if self.relative {
+            absolute.max("yesterday 23:59".width())
+        } else {
+            absolute
+        }
+    }
+}
+
+/// 按照`config`显示`time`，见[`TimeConfig`]
+pub fn format_time(time: SystemTime, config: &TimeConfig) -> String {
+    format_time_at(DateTime::from(time), Local::now(), config)
+}
+
+/// 与[`format_time`]相同，使用`now`作为当前时间
+pub fn format_time_at(time: DateTime<Local>, now: DateTime<Local>, config: &TimeConfig) -> String {
+    if config.relative
+        && let Some(relative) = relative(time, now)
+    {
+        return relative;
+    }
+    time.format(&config.format).to_string()
+}
+
+fn relative(time: DateTime<Local>, now: DateTime<Local>) -> Option<String> {
+    let elapsed = now.signed_duration_since(time);
+    if elapsed.num_seconds() < 0 {
+        return None;
+    }
+    if elapsed.num_minutes() < 1 {
+        return Some(String::from("just now"));
+    }
+    if elapsed.num_hours() < 1 {
+        return Some(format!("{}m ago", elapsed.num_minutes()));
+    }
+    let today = now.date_naive();
+    let day = time.date_naive();
+    if day == today {
+        return Some(format!("{}h ago", elapsed.num_hours()));
+    }
+    if today.checked_sub_days(Days::new(1)) == Some(day) {
+        return Some(format!("yesterday {}", time.format("%H:%M")));
+    }
+    None
+}
+
+/// 逐个检查`format`中以`%`开头的指令，包括其中的标志和宽度，例如`%-d`、`%.3f`、`%:z`
+fn validate(format: &str) -> Result<(), TimeFormatError> {
+    let mut chars = format.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        let mut end = format.len();
+        for (i, c) in chars.by_ref() {
+            if !matches!(c, '-' | '_' | '0'..='9' | '.' | ':' | '#') {
+                end = i + c.len_utf8();
+                break;
+            }
+        }
+        let directive = &format[start..end];
+        if StrftimeItems::new(directive).any(|item| matches!(item, Item::Error)) {
+            return Err(TimeFormatError(directive.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// `time_format`中无法识别的指令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeFormatError(String);
+
+impl TimeFormatError {
+    pub fn directive(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for TimeFormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid time_format: unknown directive {}", self.0)
+    }
+}
+
+impl std::error::Error for TimeFormatError {}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, min: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 6, day, hour, min, 0)
+            .single()
+            .unwrap()
+    }
+
+    fn format(time: DateTime<Local>, now: DateTime<Local>) -> String {
+        format_time_at(time, now, &TimeConfig::default())
+    }
+
+    #[test]
+    fn recent_times_are_relative() {
+        let now = at(10, 15, 0);
+        assert_eq!(format(now - Duration::seconds(30), now), "just now");
+        assert_eq!(format(now - Duration::minutes(12), now), "12m ago");
+        assert_eq!(format(at(10, 11, 30), now), "3h ago");
+        assert_eq!(format(at(9, 23, 50), now), "yesterday 23:50");
+        assert_eq!(format(at(8, 23, 50), now), "2026-06-08 23:50");
+        // 未来的时间使用绝对时间
+        assert_eq!(format(at(10, 16, 0), now), "2026-06-10 16:00");
+    }
+
+    #[test]
+    fn hours_do_not_cross_midnight() {
+        let now = at(10, 2, 0);
+        assert_eq!(format(at(9, 23, 0), now), "yesterday 23:00");
+        // 不足一小时时仍然显示分钟
+        assert_eq!(format(at(9, 23, 30), at(10, 0, 10)), "40m ago");
+    }
+
+    #[test]
+    fn absolute_times_use_the_format() {
+        let config = TimeConfig::new("%d/%m %H:%M", false).unwrap();
+        let now = at(10, 15, 0);
+        assert!(!config.is_relative());
+        assert_eq!(format_time_at(now, now, &config), "10/06 15:00");
+        assert_eq!(config.max_width(), "27/09 23:59".len());
+        assert_eq!(TimeConfig::default().max_width(), "2000-09-27 23:59".len());
+        let short = TimeConfig::new("%H:%M", true).unwrap();
+        assert_eq!(short.max_width(), "yesterday 23:59".len());
+    }
+
+    #[test]
+    fn unknown_directives_are_rejected() {
+        assert!(TimeConfig::new("%-d %b %Y, %H:%M:%S%.3f %:z", true).is_ok());
+        assert_eq!(
+            TimeConfig::new("%Y %K", true).unwrap_err().directive(),
+            "%K"
+        );
+        assert_eq!(
+            TimeConfig::new("%H:%M %-Q", true).unwrap_err().directive(),
+            "%-Q"
+        );
+    }
+}
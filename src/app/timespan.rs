@@ -0,0 +1,125 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// 配置文件中以字符串表示的一段时间，精确到秒，为0时表示关闭对应的功能
+///
+/// 由数字加单位`s`、`m`、`h`、`d`组成，可以组合，例如`"90s"`、`"30m"`、`"1h30m"`，
+/// 不带单位的数字视为分钟。`"0"`和`"off"`表示关闭。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeSpan(u64);
+
+impl TimeSpan {
+    // ------------------- CONSTANT -----------------------
+
+    pub const OFF: TimeSpan = TimeSpan(0);
+
+    const UNITS: [(char, u64); 4] = [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)];
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn from_secs(secs: u64) -> Self {
+        TimeSpan(secs)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, TimeSpanError> {
+        let source = source.trim();
+        let invalid = || TimeSpanError(source.to_string());
+        let lower = source.to_ascii_lowercase();
+        if matches!(lower.as_str(), "0" | "off") {
+            return Ok(TimeSpan::OFF);
+        }
+        if lower.is_empty() {
+            return Err(invalid());
+        }
+        if lower.bytes().all(|b| b.is_ascii_digit()) {
+            let minutes: u64 = lower.parse().map_err(|_| invalid())?;
+            return minutes.checked_mul(60).map(TimeSpan).ok_or_else(invalid);
+        }
+
+        let mut secs = 0u64;
+        let mut number = String::new();
+        for c in lower.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let (_, unit) = Self::UNITS
+                .iter()
+                .find(|(name, _)| *name == c)
+                .ok_or_else(invalid)?;
+            let value: u64 = number.parse().map_err(|_| invalid())?;
+            secs = value
+                .checked_mul(*unit)
+                .and_then(|value| secs.checked_add(value))
+                .ok_or_else(invalid)?;
+            number.clear();
+        }
+        // 最后一个数字缺少单位，例如"1h30"
+        if !number.is_empty() {
+            return Err(invalid());
+        }
+        Ok(TimeSpan(secs))
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn is_off(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// 关闭时为[`None`]
+    pub fn duration(&self) -> Option<Duration> {
+        (!self.is_off()).then(|| Duration::from_secs(self.0))
+    }
+}
+
+impl Display for TimeSpan {
+    /// 按照从大到小的单位显示，例如`1h30m`，关闭时为`off`
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_off() {
+            return write!(f, "off");
+        }
+        let mut rest = self.0;
+        for (name, unit) in Self::UNITS {
+            if rest >= unit {
+                write!(f, "{}{}", rest / unit, name)?;
+                rest %= unit;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for TimeSpan {
+    type Error = TimeSpanError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TimeSpan::parse(&value)
+    }
+}
+
+impl From<TimeSpan> for String {
+    fn from(value: TimeSpan) -> Self {
+        match value.0 {
+            0 => String::from("0"),
+            _ => value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeSpanError(String);
+
+impl Display for TimeSpanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid duration, expected e.g. 30m or 1h30m: {}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for TimeSpanError {}
@@ -0,0 +1,208 @@
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::terminal::SetTitle;
+use ratatui::crossterm::{Command, execute};
+
+use crate::app::config::Config;
+
+/// 下载列表的整体进度，用于更新终端的标题以及进度指示
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProgressSummary {
+    /// 正在进行（没有暂停或者结束）的任务数量
+    pub active: usize,
+    /// 长度已知的任务已经下载的字节数以及总字节数
+    pub downloaded: u64,
+    pub total: u64,
+    /// 有长度未知的任务正在进行
+    pub indeterminate: bool,
+    /// 最近有任务失败
+    pub failed: bool,
+}
+
+impl ProgressSummary {
+    /// 长度已知的任务的整体进度（0~100）
+    pub fn percent(&self) -> Option<u8> {
+        (self.total > 0).then(|| (self.downloaded.min(self.total) * 100 / self.total) as u8)
+    }
+
+    /// 终端标题，例如`request-tui — 3 active, 47%`
+    pub fn title(&self) -> String {
+        match (self.active, self.percent()) {
+            (0, _) => String::from(TerminalProgress::TITLE),
+            (active, Some(percent)) => format!(
+                "{} \u{2014} {} active, {}%",
+                TerminalProgress::TITLE,
+                active,
+                percent
+            ),
+            (active, None) => format!("{} \u{2014} {} active", TerminalProgress::TITLE, active),
+        }
+    }
+
+    fn progress_state(&self) -> ProgressState {
+        match (self.active, self.failed, self.percent()) {
+            (_, true, percent) => ProgressState::Error(percent.unwrap_or(100)),
+            (0, false, _) => ProgressState::Hidden,
+            (_, false, _) if self.indeterminate => ProgressState::Indeterminate,
+            (_, false, Some(percent)) => ProgressState::Normal(percent),
+            (_, false, None) => ProgressState::Indeterminate,
+        }
+    }
+}
+
+/// ConEmu和Windows Terminal等终端支持的`OSC 9;4`进度状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProgressState {
+    Hidden,
+    Normal(u8),
+    Error(u8),
+    Indeterminate,
+}
+
+impl Command for ProgressState {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let (state, percent) = match *self {
+            ProgressState::Hidden => (0, 0),
+            ProgressState::Normal(percent) => (1, percent),
+            ProgressState::Error(percent) => (2, percent),
+            ProgressState::Indeterminate => (3, 0),
+        };
+        write!(f, "\x1b]9;4;{};{}\x07", state, percent)
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        true
+    }
+}
+
+/// 保存或者恢复终端原来的标题（XTWINOPS 22/23），退出时标题不会停留在最后的进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TitleStack {
+    Push,
+    Pop,
+}
+
+impl Command for TitleStack {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match self {
+            TitleStack::Push => write!(f, "\x1b[22;0t"),
+            TitleStack::Pop => write!(f, "\x1b[23;0t"),
+        }
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        true
+    }
+}
+
+/// 在终端标题以及支持的终端的标签页或者任务栏中显示下载进度
+///
+/// 由配置中的`terminal_progress`开启，`TERM`为空或者为`dumb`时自动关闭。
+/// 转义序列在每次[`Terminal::draw`]返回之后写入，因此不会与界面的输出交错，
+/// 并且最多每[`TerminalProgress::INTERVAL`]写入一次。
+///
+/// `OSC 9`在iTerm2等终端中表示发送通知，因此只在确认支持`OSC 9;4`的终端中发送进度状态，
+/// 其他终端只更新标题。
+///
+/// [`Terminal::draw`]: ratatui::Terminal::draw
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    enabled: bool,
+    osc_progress: bool,
+    last_emit: Option<Instant>,
+    last: Option<(String, ProgressState)>,
+}
+
+impl TerminalProgress {
+    // ------------------- CONSTANT -----------------------
+
+    pub const INTERVAL: Duration = Duration::from_secs(1);
+    const TITLE: &'static str = "request-tui";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn from_config(config: &Config) -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let enabled = config.terminal_progress && !term_is_dumb(&term);
+        if config.terminal_progress && !enabled {
+            log::info!(target:"App", "Terminal progress disabled for TERM={:?}", term);
+        }
+        TerminalProgress {
+            enabled,
+            osc_progress: enabled && supports_osc_progress(),
+            last_emit: None,
+            last: None,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 距离上次写入超过[`TerminalProgress::INTERVAL`]并且内容有变化时，更新标题和进度状态
+    pub fn update(&mut self, summary: &ProgressSummary, out: &mut impl Write) -> io::Result<()> {
+        if !self.enabled || self.last_emit.is_some_and(|t| t.elapsed() < Self::INTERVAL) {
+            return Ok(());
+        }
+        self.last_emit = Some(Instant::now());
+
+        let title = summary.title();
+        let state = summary.progress_state();
+        if self
+            .last
+            .as_ref()
+            .is_some_and(|(t, s)| *t == title && *s == state)
+        {
+            return Ok(());
+        }
+        if self.last.is_none() {
+            execute!(out, TitleStack::Push)?;
+        }
+        execute!(out, SetTitle(&title))?;
+        if self.osc_progress {
+            execute!(out, state)?;
+        }
+        self.last = Some((title, state));
+        Ok(())
+    }
+
+    /// 退出前调用，清除进度状态并恢复原来的标题
+    pub fn clear(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.last.take().is_none() {
+            return Ok(());
+        }
+        if self.osc_progress {
+            execute!(out, ProgressState::Hidden)?;
+        }
+        // 不支持恢复标题的终端中，至少不显示最后的进度
+        execute!(out, SetTitle(Self::TITLE), TitleStack::Pop)
+    }
+}
+
+fn term_is_dumb(term: &str) -> bool {
+    let term = term.trim();
+    term.is_empty() || term == "dumb"
+}
+
+/// 已知支持`OSC 9;4`的终端
+fn supports_osc_progress() -> bool {
+    env::var_os("WT_SESSION").is_some()
+        || env::var_os("ConEmuPID").is_some()
+        || matches!(
+            env::var("TERM_PROGRAM").as_deref(),
+            Ok("WezTerm") | Ok("ghostty")
+        )
+}
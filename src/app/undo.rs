@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::runtime::Handle;
+use tokio::task::AbortHandle;
+
+use crate::app::task::{TaskId, TaskState};
+use crate::window::app::FinishedTask;
+
+/// 可以撤销的破坏性操作
+///
+/// 操作本身会立即生效（任务被取消、条目从列表中移除），但删除文件这样无法恢复的部分
+/// 会推迟到撤销窗口结束之后才执行。
+pub enum UndoAction {
+    /// 取消了一个正在进行的任务，`id`为任务原本的编号，`url`为任务原本的URL
+    Abort {
+        id: TaskId,
+        state: Arc<Mutex<TaskState>>,
+        url: String,
+    },
+    /// 删除了完成列表中的一项以及对应的文件，`job`为延迟删除文件的任务，
+    /// 后台运行时还没有启动时为[`None`]，此时在记录过期时同步删除文件
+    DeleteFinished {
+        index: usize,
+        task: Box<FinishedTask>,
+        job: Option<AbortHandle>,
+    },
+    /// 清空了完成列表
+    ClearFinished { tasks: Vec<FinishedTask> },
+}
+
+impl UndoAction {
+    /// 撤销窗口结束后执行推迟的部分
+    ///
+    /// 正常情况下文件由后台的延迟任务删除，这里只在程序退出时使用，
+    /// 此时后台运行时即将关闭，延迟任务已经没有机会执行。
+    fn finalize_now(self) {
+        if let UndoAction::DeleteFinished { task, job, .. } = self {
+            if let Some(job) = job {
+                job.abort();
+            }
+            remove_file(task.filepath().to_path_buf());
+        }
+    }
+
+    /// 记录过期或者被新的记录替换时调用，没有后台任务负责的推迟操作在这里立即执行
+    fn retire(self) {
+        if let UndoAction::DeleteFinished { job: None, .. } = &self {
+            self.finalize_now();
+        }
+    }
+}
+
+fn remove_file(path: PathBuf) {
+    match std::fs::remove_file(&path) {
+        Ok(()) => log::debug!(target:"App", "Deleted {}", path.display()),
+        Err(e) => log::warn!(target:"App", "Failed to delete {}: {}", path.display(), e),
+    }
+}
+
+pub struct UndoRecord {
+    action: UndoAction,
+    message: String,
+    created_at: Instant,
+}
+
+impl UndoRecord {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(action: UndoAction, message: String) -> Self {
+        UndoRecord {
+            action,
+            message,
+            created_at: Instant::now(),
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn action(&self) -> &UndoAction {
+        &self.action
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    pub fn is_expired(&self) -> bool {
+        self.created_at.elapsed() >= UndoBuffer::WINDOW
+    }
+
+    /// 剩余可以撤销的秒数，向上取整
+    pub fn remaining_secs(&self) -> u64 {
+        let remaining = UndoBuffer::WINDOW.saturating_sub(self.created_at.elapsed());
+        remaining.as_millis().div_ceil(1000) as u64
+    }
+
+    /// 在提示中显示的内容，例如"Aborted foo.iso — press u to undo (5s)"
+    pub fn toast(&self) -> String {
+        format!(
+            "{} — press u to undo ({}s)",
+            self.message,
+            self.remaining_secs()
+        )
+    }
+}
+
+/// 保存最近一次破坏性操作的撤销记录
+///
+/// 只保存一条记录，新的操作会使上一条记录立即过期。记录在[`UndoBuffer::WINDOW`]
+/// 之后过期，过期后无法撤销。
+#[derive(Default)]
+pub struct UndoBuffer {
+    record: Option<UndoRecord>,
+    runtime: Option<Handle>,
+}
+
+impl UndoBuffer {
+    // ------------------- CONSTANT -----------------------
+
+    pub const WINDOW: Duration = Duration::from_secs(5);
+
+    // 延迟删除文件的任务比撤销窗口稍晚执行，避免在窗口结束前的一瞬间撤销时文件已经被删除
+    const DELETE_GRACE: Duration = Duration::from_secs(1);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 当前仍然可以撤销的记录
+    pub fn current(&self) -> Option<&UndoRecord> {
+        self.record.as_ref().filter(|r| !r.is_expired())
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 后台运行时启动完成后调用，之后删除文件的操作由后台运行时完成
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.runtime = Some(runtime);
+    }
+
+    /// 保存新的记录，上一条记录立即过期
+    pub fn push(&mut self, record: UndoRecord) {
+        log::debug!(target:"App", "{}", record.message());
+        if let Some(previous) = self.record.replace(record) {
+            previous.action.retire();
+        }
+    }
+
+    pub fn push_abort(&mut self, id: TaskId, state: Arc<Mutex<TaskState>>, url: String) {
+        let name = {
+            let state = state.lock().unwrap();
+            file_name_of(state.filepath()).unwrap_or(url.clone())
+        };
+        self.push(UndoRecord::new(
+            UndoAction::Abort { id, state, url },
+            format!("Aborted {}", name),
+        ));
+    }
+
+    /// 删除完成列表中的一项，文件会在撤销窗口结束后由后台运行时删除
+    pub fn push_delete_finished(&mut self, index: usize, task: FinishedTask) {
+        let path = task.filepath().to_path_buf();
+        let name = file_name_of(&path).unwrap_or_default();
+        let job = self.runtime.as_ref().map(|runtime| {
+            runtime
+                .spawn(async move {
+                    tokio::time::sleep(UndoBuffer::WINDOW + UndoBuffer::DELETE_GRACE).await;
+                    if let Err(e) = tokio::fs::remove_file(&path).await {
+                        log::warn!(target:"App", "Failed to delete {}: {}", path.display(), e);
+                    }
+                })
+                .abort_handle()
+        });
+        self.push(UndoRecord::new(
+            UndoAction::DeleteFinished {
+                index,
+                task: Box::new(task),
+                job,
+            },
+            format!("Deleted {}", name),
+        ));
+    }
+
+    pub fn push_clear_finished(&mut self, tasks: Vec<FinishedTask>) {
+        let message = format!("Cleared {} finished task(s)", tasks.len());
+        self.push(UndoRecord::new(
+            UndoAction::ClearFinished { tasks },
+            message,
+        ));
+    }
+
+    /// 取出可以撤销的记录，已经过期时返回[`None`]
+    ///
+    /// 对于删除文件的操作，取出时会同时取消延迟删除的任务。
+    pub fn take(&mut self) -> Option<UndoAction> {
+        let record = self.record.take()?;
+        if record.is_expired() {
+            record.action.retire();
+            return None;
+        }
+        if let UndoAction::DeleteFinished { job: Some(job), .. } = &record.action {
+            job.abort();
+        }
+        Some(record.action)
+    }
+
+    /// 清除已经过期的记录，过期的操作一般已经由后台任务完成
+    pub fn expire(&mut self) {
+        if self.record.as_ref().is_some_and(UndoRecord::is_expired) {
+            self.record.take().unwrap().action.retire();
+        }
+    }
+
+    /// 程序退出时调用，立即完成所有被推迟的操作
+    pub fn finalize_all(&mut self) {
+        if let Some(record) = self.record.take() {
+            record.action.finalize_now();
+        }
+    }
+}
+
+fn file_name_of(path: &std::path::Path) -> Option<String> {
+    path.file_name().map(|s| s.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::app::FinishState;
+
+    /// 临时目录中的一个已经存在的文件，以及对应的完成列表中的项
+    fn finished_file(name: &str) -> (PathBuf, FinishedTask) {
+        let dir = std::env::temp_dir().join(format!("request-tui-undo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, b"data").unwrap();
+        let task = FinishedTask::new(
+            FinishState::Success,
+            path.clone(),
+            None,
+            Some(4),
+            4,
+            None,
+            Duration::from_secs(1),
+        );
+        (path, task)
+    }
+
+    fn expire_now(buffer: &mut UndoBuffer) {
+        let record = buffer.record.as_mut().unwrap();
+        record.created_at = Instant::now().checked_sub(UndoBuffer::WINDOW).unwrap();
+    }
+
+    #[test]
+    fn undo_keeps_the_file() {
+        let (path, task) = finished_file("undo.bin");
+        let mut buffer = UndoBuffer::new();
+        buffer.push_delete_finished(3, task);
+        assert_eq!(buffer.current().unwrap().message(), "Deleted undo.bin");
+        assert!(matches!(
+            buffer.take(),
+            Some(UndoAction::DeleteFinished { index: 3, .. })
+        ));
+        buffer.finalize_all();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn replaced_delete_runs_without_runtime() {
+        let (path, task) = finished_file("replaced.bin");
+        let mut buffer = UndoBuffer::new();
+        buffer.push_delete_finished(0, task);
+        assert!(path.exists());
+        buffer.push_clear_finished(Vec::new());
+        assert!(!path.exists());
+        assert_eq!(
+            buffer.current().unwrap().message(),
+            "Cleared 0 finished task(s)"
+        );
+    }
+
+    #[test]
+    fn expired_delete_runs_without_runtime() {
+        let (expired, task) = finished_file("expired.bin");
+        let mut buffer = UndoBuffer::new();
+        buffer.push_delete_finished(0, task);
+        expire_now(&mut buffer);
+        assert!(buffer.current().is_none());
+        buffer.expire();
+        assert!(!expired.exists());
+
+        // 过期之后尝试撤销同样删除文件
+        let (taken, task) = finished_file("taken.bin");
+        buffer.push_delete_finished(0, task);
+        expire_now(&mut buffer);
+        assert!(buffer.take().is_none());
+        assert!(!taken.exists());
+    }
+
+    #[test]
+    fn exit_finalizes_pending_delete() {
+        let (path, task) = finished_file("exit.bin");
+        let mut buffer = UndoBuffer::new();
+        buffer.push_delete_finished(0, task);
+        buffer.finalize_all();
+        assert!(!path.exists());
+        assert!(buffer.current().is_none());
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// 所有下载完成之后执行的操作，运行时使用`Z`选择
+///
+/// 挂起或者关闭系统不是内置的操作，需要时在配置的`when_done_command`中填写对应的命令，
+/// 例如`["systemctl", "suspend"]`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenDone {
+    #[default]
+    Nothing,
+    /// 运行配置中的`when_done_command`
+    RunCommand,
+    /// 正常退出程序，与按下`q`相同
+    Exit,
+}
+
+impl WhenDone {
+    pub const ALL: [WhenDone; 3] = [WhenDone::Nothing, WhenDone::RunCommand, WhenDone::Exit];
+}
+
+impl fmt::Display for WhenDone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            WhenDone::Nothing => "do nothing",
+            WhenDone::RunCommand => "run command",
+            WhenDone::Exit => "exit",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// 下载列表清空时触发[`WhenDone`]
+///
+/// 只有列表从有任务变为没有任务时才会开始倒计时，因此在空列表上选择操作不会立即触发。
+/// 倒计时期间按下任意键，或者又有新的任务时取消倒计时，操作保持选中，
+/// 下一次列表清空时再次倒计时。触发之后恢复为[`WhenDone::Nothing`]。
+#[derive(Debug, Default)]
+pub struct WhenDoneTrigger {
+    action: WhenDone,
+    // 选择操作或者上一次倒计时之后，下载列表中是否有过任务
+    seen_busy: bool,
+    // 倒计时结束的时间
+    deadline: Option<Instant>,
+}
+
+impl WhenDoneTrigger {
+    // ------------------- CONSTANT -----------------------
+
+    pub const COUNTDOWN: Duration = Duration::from_secs(30);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn action(&self) -> WhenDone {
+        self.action
+    }
+
+    /// 倒计时剩余的时间，没有在倒计时时为[`None`]
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(now))
+    }
+
+    /// 显示在内容区顶部的状态，没有选择操作时为[`None`]
+    pub fn status(&self, now: Instant) -> Option<String> {
+        match (self.action, self.remaining(now)) {
+            (WhenDone::Nothing, _) => None,
+            (action, Some(remaining)) => Some(format!(
+                "All downloads done: {} in {}s, press any key to cancel",
+                action,
+                remaining.as_secs_f64().ceil() as u64
+            )),
+            (action, None) => Some(format!("When all downloads finish: {}", action)),
+        }
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 选择操作，同时取消正在进行的倒计时
+    pub fn arm(&mut self, action: WhenDone) {
+        self.action = action;
+        self.seen_busy = false;
+        self.deadline = None;
+    }
+
+    /// 取消正在进行的倒计时，返回是否真的取消了
+    pub fn cancel(&mut self) -> bool {
+        self.deadline.take().is_some()
+    }
+
+    /// `busy`为下载列表中是否还有任务（包括暂停以及等待发送的任务），
+    /// 倒计时结束时返回需要执行的操作
+    pub fn tick(&mut self, now: Instant, busy: bool) -> Option<WhenDone> {
+        if self.action == WhenDone::Nothing {
+            return None;
+        }
+        if busy {
+            self.seen_busy = true;
+            self.deadline = None;
+            return None;
+        }
+        match self.deadline {
+            Some(deadline) if now >= deadline => {
+                self.deadline = None;
+                Some(std::mem::take(&mut self.action))
+            }
+            Some(_) => None,
+            None => {
+                if std::mem::take(&mut self.seen_busy) {
+                    self.deadline = Some(now + Self::COUNTDOWN);
+                }
+                None
+            }
+        }
+    }
+}
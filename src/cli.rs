@@ -0,0 +1,110 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::app::config::{Config, ConfigOverrides};
+
+/// 命令行参数
+///
+/// 命令行参数的优先级高于配置文件，通过[`Cli::overrides`]覆盖配置中对应的值，
+/// 这些覆盖不会被写回配置文件，见[`LiveConfig`]。
+///
+/// [`LiveConfig`]: crate::app::config::LiveConfig
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub worker_threads: Option<usize>,
+    /// 退出时将本次运行的任务摘要以JSON格式写入该文件
+    pub summary_json: Option<PathBuf>,
+    /// 退出时将本次运行中完成的任务导出到该文件，格式由扩展名决定，见[`ExportFormat`]
+    ///
+    /// [`ExportFormat`]: crate::app::export::ExportFormat
+    pub export: Option<PathBuf>,
+    /// 关闭动画，见[`Config::reduced_motion`]
+    pub reduced_motion: bool,
+    /// 检查运行环境并输出结果，不进入TUI，见[`DoctorReport`]
+    ///
+    /// [`DoctorReport`]: crate::app::doctor::DoctorReport
+    pub doctor: bool,
+}
+
+impl Cli {
+    // ------------------- CONSTANT -----------------------
+
+    pub const USAGE: &'static str = "\
+Usage: request-tui [OPTIONS]
+
+Options:
+      --worker-threads <N>    Number of worker threads of the background runtime
+      --summary-json <PATH>   Write a JSON summary of all tasks to PATH on exit
+      --export <PATH>         Write finished tasks to PATH on exit (.md for Markdown, else CSV)
+      --reduced-motion        Disable animations and redraw less often
+      --doctor                Check the environment, print a report and exit
+  -h, --help                  Print help";
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn parse() -> anyhow::Result<Self> {
+        Self::parse_from(env::args().skip(1))
+    }
+
+    pub fn parse_from<I>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    std::process::exit(0);
+                }
+                "--worker-threads" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    let threads = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or(anyhow::anyhow!("Invalid value for {}: {}", arg, value))?;
+                    cli.worker_threads = Some(threads);
+                }
+                "--summary-json" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    cli.summary_json = Some(PathBuf::from(value));
+                }
+                "--export" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    cli.export = Some(PathBuf::from(value));
+                }
+                "--reduced-motion" => cli.reduced_motion = true,
+                "--doctor" => cli.doctor = true,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown argument: {}\n\n{}",
+                        arg,
+                        Self::USAGE
+                    ));
+                }
+            }
+        }
+        Ok(cli)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn value_of(arg: &str, value: Option<String>) -> anyhow::Result<String> {
+        value.ok_or(anyhow::anyhow!("Missing value for {}", arg))
+    }
+
+    /// 命令行参数中覆盖配置的部分
+    pub fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            worker_threads: self.worker_threads,
+            reduced_motion: self.reduced_motion,
+        }
+    }
+
+    /// 使用命令行参数覆盖配置
+    pub fn apply(&self, config: &mut Config) {
+        self.overrides().apply(config);
+    }
+}
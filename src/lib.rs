@@ -1,22 +1,130 @@
-use std::{io::Stdout, thread};
+use std::{sync::Arc, thread, time::Instant};
 
-use ratatui::{Terminal, prelude::CrosstermBackend};
+use ratatui::{Terminal, prelude::Backend};
 use tokio::{runtime, sync::mpsc};
 
-use crate::app::{App, task::TaskManager};
+use crate::app::{
+    App,
+    about::BuildInfo,
+    config::{Config, LiveConfig},
+    doctor::{self, CheckStatus},
+    input::CrosstermEvents,
+    instance::{InstanceLock, LockAttempt},
+    record::SessionSummary,
+    sender::Sender,
+    session::Session,
+    task::{ConnectionLimits, ManagerReady, RuntimeStats, TaskManager},
+    throttle::Throttle,
+};
+use crate::cli::Cli;
+use crate::window::WidgetType;
+use crate::window::common::Splash;
 
 pub mod app;
+pub mod cli;
 pub mod window;
 
-pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
-    let runtime = runtime::Builder::new_multi_thread().enable_all().build()?;
-    let (tx, rx) = mpsc::channel(32);
-    let background = thread::spawn(move || {
-        let mut manager = TaskManager::new(runtime, rx);
-        manager.run();
-    });
-    let app = App::new(tx);
-    app.run(terminal)?;
-    background.join().unwrap();
-    Ok(())
+/// 运行TUI，直到用户退出并且后台线程结束，返回本次运行的任务摘要
+///
+/// 为了尽快显示第一帧，读取配置和会话以及启动后台运行时都在绘制启动画面之后进行，
+/// 其中运行时在后台线程中创建，通过[`ManagerReady`]交给UI线程。
+pub fn run_app<B: Backend>(terminal: &mut Terminal<B>, cli: Cli) -> anyhow::Result<SessionSummary> {
+    let started = Instant::now();
+    terminal.draw(|f| f.render_widget(Splash, f.area()))?;
+    log::info!(target:"App", "First frame drawn in {:?}", started.elapsed());
+
+    // 配置文件不存在时先显示设置向导，向导结束时写入配置文件
+    let first_run = !Config::exists();
+    // 后台线程通过接收端读取配置，运行期间修改之后的任务使用新的配置，
+    // 命令行参数只覆盖本次运行使用的配置，不会被写入配置文件
+    let config = LiveConfig::new(Config::load()).with_overrides(cli.overrides());
+    let current = config.current();
+
+    let stats = Arc::new(RuntimeStats::new());
+    let throttle = Arc::new(
+        Throttle::new(current.speed_limit, current.speed_schedule.clone())
+            .with_fair_share(current.fair_share),
+    );
+    let limits = Arc::new(ConnectionLimits::new(
+        current.max_concurrent_tasks,
+        current.max_tasks_per_host,
+        current.admission_policy,
+    ));
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let background = {
+        let watch = config.subscribe();
+        let stats = stats.clone();
+        let throttle = throttle.clone();
+        let limits = limits.clone();
+        thread::spawn(move || -> std::io::Result<()> {
+            let config = watch.borrow().clone();
+            let runtime = build_runtime(&config)?;
+            let (tx, rx) = mpsc::channel(Sender::CHANNEL_CAPACITY);
+            let ready = ManagerReady {
+                sender: tx,
+                runtime: runtime.handle().clone(),
+            };
+            // UI线程已经退出时没有必要继续启动
+            if ready_tx.send(ready).is_err() {
+                return Ok(());
+            }
+            log::info!(target:"App", "Background runtime ready in {:?}", started.elapsed());
+            let mut manager = TaskManager::new(runtime, rx, stats, throttle, limits, watch);
+            for kind in &config.task_observers {
+                manager.register_observer(kind.build());
+            }
+            manager.run();
+            Ok(())
+        })
+    };
+
+    // 另一个实例正在使用同一个下载目录时以只读模式运行，不接手会话中等待发送的任务
+    let lock = InstanceLock::acquire(&current.download_dir());
+    let mut session = Session::load();
+    if let Ok(LockAttempt::Held(_)) = &lock {
+        session.pending.clear();
+    }
+    let build_info = BuildInfo::collect(&current);
+    let mut app = App::new(
+        ready_rx, stats, throttle, limits, config, session, build_info,
+    );
+    match lock {
+        Ok(LockAttempt::Acquired(lock)) => app = app.with_instance_lock(lock),
+        Ok(LockAttempt::Held(conflict)) => app.enter_read_only(conflict),
+        Err(e) => {
+            log::warn!(target:"App", "Failed to lock download directory: {}", e);
+            app.notify(format!("Cannot detect other instances: {}", e));
+        }
+    }
+    #[cfg(feature = "control")]
+    match app::control::ControlServer::bind() {
+        Ok(control) => app = app.with_control(control),
+        Err(e) => {
+            log::warn!(target:"Control", "Failed to start control socket: {}", e);
+            app.notify(format!("Control socket disabled: {}", e));
+        }
+    }
+    if first_run {
+        log::info!(target:"App", "No config file found, starting setup wizard");
+        app.append_widget(WidgetType::new_setup_wizard(app.stored_config()));
+    }
+    // 与`--doctor`使用相同的检查
+    for result in doctor::check_config_values(&current) {
+        if result.status != CheckStatus::Pass {
+            app.notify(result.detail);
+        }
+    }
+    let summary = app.run(terminal, &mut CrosstermEvents);
+    background.join().unwrap()?;
+    Ok(summary?)
+}
+
+fn build_runtime(config: &Config) -> std::io::Result<runtime::Runtime> {
+    let workers = config.worker_threads();
+    log::debug!(target:"App", "Starting runtime with {} worker threads", workers);
+    runtime::Builder::new_multi_thread()
+        .worker_threads(workers)
+        .thread_name(config.thread_name.clone())
+        .enable_all()
+        .build()
 }
@@ -1,13 +1,26 @@
-use std::env;
+use std::io;
 
+use ratatui::crossterm::event::{DisableFocusChange, EnableFocusChange};
+use ratatui::crossterm::execute;
+
+use request_tui::app::about::BuildInfo;
+use request_tui::app::doctor::DoctorReport;
+use request_tui::app::export::{self, ExportFormat};
+use request_tui::cli::Cli;
 use tui_logger::{LevelFilter, TuiLoggerFile, TuiLoggerLevelOutput};
 
 fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse()?;
+    if cli.doctor {
+        let report = DoctorReport::run(&cli);
+        print!("{}", report);
+        std::process::exit(if report.has_failures() { 1 } else { 0 });
+    }
+
     // initialize logging
     tui_logger::init_logger(LevelFilter::Trace)?;
     tui_logger::set_default_level(LevelFilter::Trace);
-    let mut dir = env::temp_dir();
-    dir.push("request_tui-debug.log");
+    let dir = BuildInfo::log_path();
     let file_options = TuiLoggerFile::new(dir.to_str().unwrap())
         .output_level(Some(TuiLoggerLevelOutput::Abbreviated))
         .output_file(false)
@@ -17,8 +30,33 @@ fn main() -> anyhow::Result<()> {
     log::debug!(target:"App", "Logging initialized");
 
     // 使用Crossterm后端初始化终端
+    let summary_json = cli.summary_json.clone();
+    let export_path = cli.export.clone();
     let mut terminal = ratatui::init();
-    request_tui::run_app(&mut terminal)?;
+    // 重新获得焦点时（例如重新连接tmux会话）重新绘制整个画面
+    if let Err(e) = execute!(io::stdout(), EnableFocusChange) {
+        log::warn!(target:"App", "Failed to enable focus events: {}", e);
+    }
+    let summary = request_tui::run_app(&mut terminal, cli);
+    let _ = execute!(io::stdout(), DisableFocusChange);
     ratatui::restore();
+
+    // 终端恢复之后再写入摘要，这样写入失败时可以正常地输出错误信息
+    let summary = summary?;
+    if let Some(path) = summary_json {
+        summary
+            .write(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to write summary to {}: {}", path.display(), e))?;
+    }
+    if let Some(path) = export_path {
+        // 摘要中还包含退出时仍在进行的任务，它们没有完成时间
+        let finished: Vec<_> = summary
+            .tasks
+            .into_iter()
+            .filter(|record| record.finished_at.is_some())
+            .collect();
+        export::write_records(&path, ExportFormat::from_path(&path), &finished)
+            .map_err(|e| anyhow::anyhow!("Failed to export to {}: {}", path.display(), e))?;
+    }
     Ok(())
 }
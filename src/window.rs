@@ -2,11 +2,33 @@ use ratatui::crossterm::event::KeyEvent;
 use ratatui::prelude::*;
 use ratatui::widgets::Widget;
 
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+use url::Url;
+
 use crate::app::App;
-use crate::window::download::DownloadInput;
+use crate::app::about::BuildInfo;
+use crate::app::config::Config;
+use crate::app::instance::InstanceConflict;
+use crate::app::policy::PolicyMatch;
+use crate::app::saved::SavedDownload;
+use crate::app::sender::RequestOptions;
+use crate::app::task::{FileConflict, TaskCommand, TaskId, TaskResult, TaskState};
+use crate::app::timefmt::TimeConfig;
+use crate::app::when_done::WhenDone;
+use crate::window::common::InputMode;
+use crate::window::dialog::{
+    AboutDialog, ConflictDialog, DetailDialog, ExportDialog, InstanceDialog, JumpInput, JumpTarget,
+    MigrateDialog, OpenWithDialog, PeekDialog, PolicyDialog, SetupStep, SetupWizard,
+    TemplateDialog, TemplateNameInput, WhenDoneDialog,
+};
+use crate::window::download::{BatchReview, DownloadInput};
 
 pub mod app;
 pub mod common;
+pub mod dialog;
 pub mod download;
 
 /// 代表所有可能的窗口类型
@@ -16,6 +38,21 @@ pub mod download;
 /// 必须都实现Widget trait。
 pub enum WidgetType {
     DownloadInput(Box<DownloadInput>),
+    BatchReview(Box<BatchReview>),
+    ConflictDialog(Box<ConflictDialog>),
+    DetailDialog(Box<DetailDialog>),
+    JumpInput(Box<JumpInput>),
+    MigrateDialog(Box<MigrateDialog>),
+    OpenWithDialog(Box<OpenWithDialog>),
+    PolicyDialog(Box<PolicyDialog>),
+    SetupWizard(Box<SetupWizard>),
+    TemplateNameInput(Box<TemplateNameInput>),
+    TemplateDialog(Box<TemplateDialog>),
+    AboutDialog(Box<AboutDialog>),
+    PeekDialog(Box<PeekDialog>),
+    WhenDoneDialog(Box<WhenDoneDialog>),
+    InstanceDialog(Box<InstanceDialog>),
+    ExportDialog(Box<ExportDialog>),
 }
 
 impl Widget for &mut WidgetType {
@@ -23,23 +60,289 @@ impl Widget for &mut WidgetType {
     where
         Self: Sized,
     {
+        // 终端太小时只显示一行提示，而不是挤在一起或者不可见的组件
+        let Some(area) = self.desired_area(area) else {
+            common::render_too_small(area, buf);
+            return;
+        };
         match self {
-            WidgetType::DownloadInput(w) => {
-                let area = common::centered_rect(50, 50, area);
-                w.render(area, buf);
-            }
+            WidgetType::DownloadInput(w) => w.render(area, buf),
+            WidgetType::BatchReview(w) => w.render(area, buf),
+            WidgetType::ConflictDialog(w) => w.render(area, buf),
+            WidgetType::DetailDialog(w) => w.render(area, buf),
+            WidgetType::JumpInput(w) => w.render(area, buf),
+            WidgetType::MigrateDialog(w) => w.render(area, buf),
+            WidgetType::OpenWithDialog(w) => w.render(area, buf),
+            WidgetType::PolicyDialog(w) => w.render(area, buf),
+            WidgetType::SetupWizard(w) => w.render(area, buf),
+            WidgetType::TemplateNameInput(w) => w.render(area, buf),
+            WidgetType::TemplateDialog(w) => w.render(area, buf),
+            WidgetType::AboutDialog(w) => w.render(area, buf),
+            WidgetType::PeekDialog(w) => w.render(area, buf),
+            WidgetType::WhenDoneDialog(w) => w.render(area, buf),
+            WidgetType::InstanceDialog(w) => w.render(area, buf),
+            WidgetType::ExportDialog(w) => w.render(area, buf),
         }
     }
 }
 
 impl WidgetType {
-    pub fn new_download_input() -> Self {
-        WidgetType::DownloadInput(Box::default())
+    // ------------------ MEMBER_ACCESS --------------------
+
+    /// 弹窗在整个终端`area`中占据的区域，不小于[`WidgetType::min_size`]，
+    /// 终端比最小区域还小时返回[`None`]
+    pub fn desired_area(&self, area: Rect) -> Option<Rect> {
+        let popup = match self {
+            // URL输入框占据多余的空间，窗口至少为屏幕的一半高
+            WidgetType::DownloadInput(w) => {
+                common::popup_rect(50, w.height().max(area.height / 2), area)
+            }
+            WidgetType::BatchReview(_) => common::centered_rect(70, 70, area),
+            WidgetType::ConflictDialog(_) => common::centered_rect(60, 30, area),
+            WidgetType::DetailDialog(_) => common::centered_rect(70, 50, area),
+            WidgetType::JumpInput(_) => {
+                // 与其他弹窗不同，跳转输入框显示在底部，不遮挡列表
+                let [_, area] = Layout::vertical([
+                    Constraint::Min(0),
+                    Constraint::Length(JumpInput::RENDER_HEIGHT),
+                ])
+                .areas(area);
+                let [area, _] = Layout::horizontal([
+                    Constraint::Length(JumpInput::RENDER_WIDTH),
+                    Constraint::Min(0),
+                ])
+                .areas(area);
+                area
+            }
+            WidgetType::MigrateDialog(_) => common::centered_rect(60, 40, area),
+            WidgetType::OpenWithDialog(_) => common::centered_rect(40, 30, area),
+            WidgetType::PolicyDialog(_) => common::centered_rect(60, 30, area),
+            WidgetType::SetupWizard(_) => common::centered_rect(60, 40, area),
+            WidgetType::TemplateNameInput(_) => {
+                common::popup_rect(40, TemplateNameInput::RENDER_HEIGHT, area)
+            }
+            WidgetType::TemplateDialog(_) => common::centered_rect(70, 40, area),
+            WidgetType::AboutDialog(_) => common::centered_rect(70, 40, area),
+            WidgetType::PeekDialog(_) => common::centered_rect(80, 70, area),
+            WidgetType::WhenDoneDialog(_) => common::centered_rect(50, 30, area),
+            WidgetType::InstanceDialog(_) => common::centered_rect(60, 40, area),
+            WidgetType::ExportDialog(_) => {
+                common::popup_rect(60, ExportDialog::RENDER_HEIGHT, area)
+            }
+        };
+        let (width, height) = self.min_size();
+        common::fit_popup(popup, width, height, area)
+    }
+
+    /// 弹窗中是否正在输入文本，此时复制的内容不会触发剪贴板提示
+    pub fn is_editing(&self) -> bool {
+        match self {
+            WidgetType::DownloadInput(w) => matches!(w.mode(), InputMode::Editing),
+            WidgetType::JumpInput(_)
+            | WidgetType::TemplateNameInput(_)
+            | WidgetType::ExportDialog(_) => true,
+            WidgetType::SetupWizard(w) => w.step() != SetupStep::Appearance,
+            _ => false,
+        }
+    }
+
+    /// 弹窗正常显示需要的最小宽度和高度，包括边框
+    pub fn min_size(&self) -> (u16, u16) {
+        match self {
+            WidgetType::DownloadInput(w) => (DownloadInput::MIN_WIDTH, w.height()),
+            WidgetType::BatchReview(_) => (BatchReview::MIN_WIDTH, BatchReview::MIN_HEIGHT),
+            WidgetType::ConflictDialog(_) => {
+                (ConflictDialog::MIN_WIDTH, ConflictDialog::MIN_HEIGHT)
+            }
+            WidgetType::DetailDialog(_) => (DetailDialog::MIN_WIDTH, DetailDialog::MIN_HEIGHT),
+            WidgetType::JumpInput(_) => (JumpInput::RENDER_WIDTH, JumpInput::RENDER_HEIGHT),
+            WidgetType::MigrateDialog(_) => (MigrateDialog::MIN_WIDTH, MigrateDialog::MIN_HEIGHT),
+            WidgetType::OpenWithDialog(_) => {
+                (OpenWithDialog::MIN_WIDTH, OpenWithDialog::MIN_HEIGHT)
+            }
+            WidgetType::PolicyDialog(_) => (PolicyDialog::MIN_WIDTH, PolicyDialog::MIN_HEIGHT),
+            WidgetType::SetupWizard(_) => (SetupWizard::MIN_WIDTH, SetupWizard::MIN_HEIGHT),
+            WidgetType::TemplateNameInput(_) => (
+                TemplateNameInput::MIN_WIDTH,
+                TemplateNameInput::RENDER_HEIGHT,
+            ),
+            WidgetType::TemplateDialog(_) => {
+                (TemplateDialog::MIN_WIDTH, TemplateDialog::MIN_HEIGHT)
+            }
+            WidgetType::AboutDialog(_) => (AboutDialog::MIN_WIDTH, AboutDialog::MIN_HEIGHT),
+            WidgetType::PeekDialog(_) => (PeekDialog::MIN_WIDTH, PeekDialog::MIN_HEIGHT),
+            WidgetType::WhenDoneDialog(_) => {
+                (WhenDoneDialog::MIN_WIDTH, WhenDoneDialog::MIN_HEIGHT)
+            }
+            WidgetType::InstanceDialog(_) => {
+                (InstanceDialog::MIN_WIDTH, InstanceDialog::MIN_HEIGHT)
+            }
+            WidgetType::ExportDialog(_) => (ExportDialog::MIN_WIDTH, ExportDialog::RENDER_HEIGHT),
+        }
+    }
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `warning`为下载目录存在的问题，只作为提示，不阻止提交；
+    /// `extract`为是否默认开启自动解压
+    pub fn new_download_input(warning: Option<String>, extract: bool) -> Self {
+        WidgetType::DownloadInput(Box::new(
+            DownloadInput::new()
+                .with_warning(warning)
+                .with_extract(extract),
+        ))
+    }
+
+    /// 复制一个已有的任务，下载窗口中预先填入该任务的选项
+    pub fn new_clone_input(options: &RequestOptions) -> Self {
+        WidgetType::DownloadInput(Box::new(DownloadInput::from_options(options)))
+    }
+
+    /// 修改还没有开始的任务的选项
+    pub fn new_edit_input(id: TaskId, options: &RequestOptions) -> Self {
+        WidgetType::DownloadInput(Box::new(
+            DownloadInput::from_options(options).editing(id, options),
+        ))
+    }
+
+    /// `time`决定已有文件的修改时间如何显示
+    pub fn new_conflict_dialog(
+        conflict: FileConflict,
+        command_sender: mpsc::UnboundedSender<TaskCommand>,
+        time: &TimeConfig,
+    ) -> Self {
+        WidgetType::ConflictDialog(Box::new(
+            ConflictDialog::new(conflict, command_sender).with_time_config(time.clone()),
+        ))
+    }
+
+    pub fn new_policy_dialog(
+        filepath: PathBuf,
+        policy_match: PolicyMatch,
+        command_sender: mpsc::UnboundedSender<TaskCommand>,
+    ) -> Self {
+        WidgetType::PolicyDialog(Box::new(PolicyDialog::new(
+            filepath,
+            policy_match,
+            command_sender,
+        )))
+    }
+
+    /// 正在进行的任务的详情，实时显示已经下载的部分
+    pub fn new_task_detail_dialog(
+        state: Arc<Mutex<TaskState>>,
+        result: Option<TaskResult>,
+    ) -> Self {
+        let (filepath, url) = {
+            let state = state.lock().unwrap();
+            (state.filepath().to_path_buf(), state.url().cloned())
+        };
+        let dialog = DetailDialog::new(filepath, url, result).with_state(state);
+        WidgetType::DetailDialog(Box::new(dialog))
+    }
+
+    pub fn new_jump_input(target: JumpTarget) -> Self {
+        WidgetType::JumpInput(Box::new(JumpInput::new(target)))
+    }
+
+    /// `count`为已经下载的部分保存在`old_dir`中的暂停任务数量
+    pub fn new_migrate_dialog(old_dir: PathBuf, new_dir: PathBuf, count: usize) -> Self {
+        WidgetType::MigrateDialog(Box::new(MigrateDialog::new(old_dir, new_dir, count)))
+    }
+
+    /// `last_choice`为本次运行中同类别的文件最近一次使用的程序
+    pub fn new_open_with_dialog(
+        filepath: PathBuf,
+        commands: Vec<String>,
+        last_choice: Option<&str>,
+    ) -> Self {
+        WidgetType::OpenWithDialog(Box::new(OpenWithDialog::new(
+            filepath,
+            commands,
+            last_choice,
+        )))
+    }
+
+    /// 输入名称，保存任务的选项，见[`SavedDownload`]
+    ///
+    /// [`SavedDownload`]: crate::app::saved::SavedDownload
+    pub fn new_template_name_input(options: RequestOptions) -> Self {
+        WidgetType::TemplateNameInput(Box::new(TemplateNameInput::new(options)))
+    }
+
+    /// `templates`为下载页面中保存的下载的副本
+    pub fn new_template_dialog(templates: Vec<SavedDownload>) -> Self {
+        WidgetType::TemplateDialog(Box::new(TemplateDialog::new(templates)))
+    }
+
+    pub fn new_about_dialog(info: BuildInfo) -> Self {
+        WidgetType::AboutDialog(Box::new(AboutDialog::new(info)))
+    }
+
+    /// `count`为完成列表中将要导出的任务数量
+    pub fn new_export_dialog(count: usize) -> Self {
+        WidgetType::ExportDialog(Box::new(ExportDialog::new(count)))
+    }
+
+    /// 选择在`page`中找到的`links`，选中的链接与在下载窗口中输入的一样添加，
+    /// 同时以`page`作为`Referer`，有的服务器只允许从页面中点击下载。默认都不选中，
+    /// 页面中通常还有样式表、图片等不需要的链接
+    pub fn new_link_review(page: &Url, links: &[String]) -> Self {
+        let referer = (String::from("Referer"), page.to_string());
+        let input = DownloadInput::new()
+            .with_urls(links)
+            .with_headers(&[referer]);
+        let review = BatchReview::new(Box::new(input), links)
+            .with_title("Links in page")
+            .with_checked(false);
+        WidgetType::BatchReview(Box::new(review))
+    }
+
+    /// `bytes`为文件开头的一部分，见[`FilePeek`]
+    ///
+    /// [`FilePeek`]: crate::app::peek::FilePeek
+    pub fn new_peek_dialog(filepath: PathBuf, bytes: Vec<u8>) -> Self {
+        WidgetType::PeekDialog(Box::new(PeekDialog::new(filepath, bytes)))
+    }
+
+    /// `command`为配置中的`when_done_command`
+    pub fn new_when_done_dialog(current: WhenDone, command: &[String]) -> Self {
+        WidgetType::WhenDoneDialog(Box::new(WhenDoneDialog::new(current, command)))
+    }
+
+    /// 另一个实例正在使用下载目录时，询问以只读模式继续、换用其他目录还是退出
+    pub fn new_instance_dialog(conflict: InstanceConflict) -> Self {
+        WidgetType::InstanceDialog(Box::new(InstanceDialog::new(conflict)))
+    }
+
+    /// 首次运行时的设置向导，`config`为各项的默认值
+    pub fn new_setup_wizard(config: Config) -> Self {
+        WidgetType::SetupWizard(Box::new(SetupWizard::new(config)))
+    }
+
+    /// 运行期间修改设置，使用同一个向导，`config`为当前的配置
+    pub fn new_settings_wizard(config: Config) -> Self {
+        WidgetType::SetupWizard(Box::new(SetupWizard::new(config).with_first_run(false)))
     }
 
     pub fn handle_key_event(self, key: KeyEvent, app: &mut App) {
         let vec: Vec<WidgetType> = match self {
             WidgetType::DownloadInput(w) => w.handle_key_event(key, app),
+            WidgetType::BatchReview(w) => w.handle_key_event(key, app),
+            WidgetType::ConflictDialog(w) => w.handle_key_event(key, app),
+            WidgetType::DetailDialog(w) => w.handle_key_event(key, app),
+            WidgetType::JumpInput(w) => w.handle_key_event(key, app),
+            WidgetType::MigrateDialog(w) => w.handle_key_event(key, app),
+            WidgetType::OpenWithDialog(w) => w.handle_key_event(key, app),
+            WidgetType::PolicyDialog(w) => w.handle_key_event(key, app),
+            WidgetType::SetupWizard(w) => w.handle_key_event(key, app),
+            WidgetType::TemplateNameInput(w) => w.handle_key_event(key, app),
+            WidgetType::TemplateDialog(w) => w.handle_key_event(key, app),
+            WidgetType::AboutDialog(w) => w.handle_key_event(key, app),
+            WidgetType::PeekDialog(w) => w.handle_key_event(key, app),
+            WidgetType::WhenDoneDialog(w) => w.handle_key_event(key, app),
+            WidgetType::InstanceDialog(w) => w.handle_key_event(key, app),
+            WidgetType::ExportDialog(w) => w.handle_key_event(key, app),
         };
         app.append_widgets(vec);
     }
@@ -1,7 +1,9 @@
 mod download;
 mod finish;
 mod page;
+mod stats;
 
 pub use download::*;
 pub use finish::*;
 pub use page::*;
+pub use stats::*;
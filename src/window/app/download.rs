@@ -1,21 +1,48 @@
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc as std_mpsc};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::widgets::Paragraph;
 use ratatui::widgets::Widget;
-use tokio::sync::mpsc;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use url::Url;
 
 use crate::app::App;
-use crate::app::listener::{TaskListener, TaskListenerRanderState};
-use crate::app::sender;
-use crate::app::task::{Task, TaskCommand, TaskFinalStage};
+use crate::app::bulk::BulkSummary;
+use crate::app::disk::{self, DirMonitor, DiskSpace};
+use crate::app::listener::{ListenerPhase, PauseOrigin, TaskListener, TaskListenerRanderState};
+use crate::app::migrate::{self, MoveResult, PartialMove};
+use crate::app::notice::NoticeBoard;
+use crate::app::peek::{self, FilePeek};
+use crate::app::pending::PendingTask;
+use crate::app::permissions::FileMode;
+use crate::app::saved::SavedDownload;
+use crate::app::sender::{self, RequestOptions};
+use crate::app::session::ListPosition;
+use crate::app::stats::HostStatsMap;
+use crate::app::task::{
+    AdmissionPolicy, ConnectionLimits, EtaEstimator, HealthThresholds, MiddleRowMode, QueueEntry,
+    QueueEta, RuntimeStats, SpeedDisplay, StageKind, Task, TaskId, TaskPhase, TaskState,
+};
+use crate::app::throttle::Throttle;
+use crate::app::timefmt::TimeConfig;
+use crate::app::title::ProgressSummary;
+use crate::app::undo::UndoBuffer;
 use crate::window::WidgetType;
 use crate::window::app::FinishList;
-use crate::window::common::{self, VerticalList, VerticalListItem};
+use crate::window::common::{self, ItemList, SymbolSet};
+use crate::window::dialog::JumpTarget;
 
+/// 列表中的行由两部分组成：排在最前面的等待发送的任务，以及已经发送的任务。
+/// `selected`是在这两部分合并后的行号，使用[`DownloadListInner::row`]转换。
 pub struct DownloadListInner {
+    pending: Vec<PendingTask>,
     list: Vec<TaskListener>,
-    selected: Option<usize>,
-    scroll: usize,
+    view: ItemList,
 }
 
 impl Default for DownloadListInner {
@@ -31,9 +58,9 @@ impl DownloadListInner {
 
     pub fn new() -> Self {
         DownloadListInner {
+            pending: Vec::new(),
             list: Vec::new(),
-            selected: None,
-            scroll: 0,
+            view: ItemList::new(Self::RENDER_ITEM_HEIGHT),
         }
     }
 
@@ -41,12 +68,12 @@ impl DownloadListInner {
 
     #[inline]
     pub fn selected(&self) -> Option<usize> {
-        self.selected
+        self.view.selected()
     }
 
     #[inline]
     pub fn scroll(&self) -> usize {
-        self.scroll
+        self.view.scroll()
     }
 
     #[inline]
@@ -54,6 +81,38 @@ impl DownloadListInner {
         &self.list
     }
 
+    #[inline]
+    pub fn pending(&self) -> &Vec<PendingTask> {
+        &self.pending
+    }
+
+    #[inline]
+    pub fn pending_mut(&mut self) -> &mut Vec<PendingTask> {
+        &mut self.pending
+    }
+
+    /// 列表的总行数，包括等待发送的任务
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.list.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 将合并后的行号转换为对应的任务
+    pub fn row(&self, row: usize) -> Option<DownloadRowIndex> {
+        if row < self.pending.len() {
+            Some(DownloadRowIndex::Pending(row))
+        } else if row < self.len() {
+            Some(DownloadRowIndex::Task(row - self.pending.len()))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub fn get_item(&self, index: usize) -> Option<&TaskListener> {
         self.list.get(index)
@@ -64,27 +123,74 @@ impl DownloadListInner {
         self.list.get_mut(index)
     }
 
+    pub fn find_task_mut(&mut self, id: TaskId) -> Option<&mut TaskListener> {
+        self.list.iter_mut().find(|l| l.id() == id)
+    }
+
     // -------------------- MODIFIER -----------------------
 
     #[inline]
     pub fn set_selected(&mut self, selected: Option<usize>) {
-        self.selected = selected;
+        self.view.set_selected(selected);
     }
 
     #[inline]
     pub fn scroll_to(&mut self, scroll: usize) {
-        self.scroll = scroll;
+        self.view.scroll_to(scroll);
     }
 
     // -------------------- FUNCTION -----------------------
 
-    fn fit_to_screen(&mut self, area_height: u16) {
-        let total_height =
-            (self.list.len() * (Self::RENDER_ITEM_HEIGHT as usize + 1)).saturating_sub(1);
-        self.scroll_to(
-            self.scroll()
-                .min(total_height.saturating_sub(area_height as usize)),
-        );
+    #[inline]
+    pub fn select_next(&mut self) {
+        self.view.select_next(self.len());
+    }
+
+    #[inline]
+    pub fn select_previous(&mut self) {
+        self.view.select_previous(self.len());
+    }
+
+    #[inline]
+    pub fn select_page_down(&mut self) {
+        self.view.select_page_down(self.len());
+    }
+
+    #[inline]
+    pub fn select_page_up(&mut self) {
+        self.view.select_page_up(self.len());
+    }
+
+    #[inline]
+    pub fn select_first(&mut self) {
+        self.view.select_first(self.len());
+    }
+
+    #[inline]
+    pub fn select_last(&mut self) {
+        self.view.select_last(self.len());
+    }
+
+    #[inline]
+    pub fn select_first_if_none(&mut self) {
+        self.view.select_first_if_none(self.len());
+    }
+
+    /// 选中编号为`id`的任务，等待发送的任务排在前面，找不到时返回`false`
+    pub fn select_task(&mut self, id: TaskId) -> bool {
+        let row = self.pending.iter().position(|p| p.id == id).or_else(|| {
+            self.list
+                .iter()
+                .position(|l| l.id() == id)
+                .map(|idx| self.pending.len() + idx)
+        });
+        match row {
+            Some(row) => {
+                self.view.jump_to(row, self.len());
+                true
+            }
+            None => false,
+        }
     }
 
     #[inline]
@@ -92,44 +198,122 @@ impl DownloadListInner {
         self.list.push(listener);
     }
 
-    #[inline]
     pub fn remove_task(&mut self, index: usize) {
         self.list.remove(index);
+        self.view.on_removed(self.pending.len() + index, self.len());
     }
-}
 
-impl StatefulWidget for &mut DownloadListInner {
-    type State = bool; // focused
-    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        self.fit_to_screen(area.height);
+    #[inline]
+    pub fn push_pending(&mut self, pending: PendingTask) {
+        self.pending.push(pending);
+    }
+
+    pub fn remove_pending(&mut self, index: usize) -> PendingTask {
+        let pending = self.pending.remove(index);
+        self.view.on_removed(index, self.len());
+        pending
+    }
+
+    /// 将第一个等待发送的任务替换为已经发送的任务，该任务会移动到列表的末尾
+    pub fn submit_first_pending(&mut self, listener: TaskListener) {
+        self.pending.remove(0);
+        self.list.push(listener);
+        // 第一行被移动到了列表的末尾，选中的行随之移动，其他行都向上移动了一行
         match self.selected() {
+            Some(0) => self.view.jump_to(self.len() - 1, self.len()),
+            Some(selected) => self.set_selected(Some(selected - 1)),
             None => {}
-            Some(idx) => {
-                let focused_distance = idx * (DownloadListInner::RENDER_ITEM_HEIGHT + 1) as usize;
-                if focused_distance < self.scroll() {
-                    self.scroll_to(focused_distance);
-                }
-                if focused_distance + DownloadListInner::RENDER_ITEM_HEIGHT as usize
-                    > self.scroll() + area.height as usize
-                {
-                    self.scroll_to(
-                        focused_distance + DownloadListInner::RENDER_ITEM_HEIGHT as usize
-                            - area.height as usize,
-                    );
-                }
-            }
         }
+    }
+}
+
+/// 合并后的行号对应的任务，内部的值为在各自列表中的下标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadRowIndex {
+    Pending(usize),
+    Task(usize),
+}
 
-        let items: Vec<_> = self
-            .list
+/// 列表中的一行，用于将两种任务放在同一个[`VerticalList`]中渲染
+#[derive(Clone, Copy)]
+enum DownloadRow<'a> {
+    Pending(&'a PendingTask),
+    Task(&'a TaskListener),
+}
+
+impl StatefulWidget for DownloadRow<'_> {
+    type State = TaskListenerRanderState;
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        match self {
+            DownloadRow::Pending(pending) => pending.render(area, buf, state),
+            DownloadRow::Task(listener) => listener.render(area, buf, state),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadListInnerRenderState {
+    pub page_focused: bool,
+    pub middle_row: MiddleRowMode,
+    pub symbols: SymbolSet,
+    pub show_remaining: bool,
+    pub speed_display: SpeedDisplay,
+}
+
+impl DownloadListInnerRenderState {
+    pub fn new(
+        page_focused: bool,
+        middle_row: MiddleRowMode,
+        symbols: SymbolSet,
+        show_remaining: bool,
+        speed_display: SpeedDisplay,
+    ) -> Self {
+        DownloadListInnerRenderState {
+            page_focused,
+            middle_row,
+            symbols,
+            show_remaining,
+            speed_display,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadListRenderState {
+    pub focused: bool,
+    pub symbols: SymbolSet,
+}
+
+impl DownloadListRenderState {
+    pub fn new(focused: bool, symbols: SymbolSet) -> Self {
+        DownloadListRenderState { focused, symbols }
+    }
+}
+
+impl StatefulWidget for &mut DownloadListInner {
+    type State = DownloadListInnerRenderState;
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let items = self
+            .pending
             .iter()
-            .map(|item| VerticalListItem::new(DownloadListInner::RENDER_ITEM_HEIGHT, item))
-            .collect();
-        VerticalList::new(items, TaskListenerRanderState::new(*state, false))
-            .with_selected_state(TaskListenerRanderState::new(*state, true))
-            .with_selected(self.selected())
-            .with_scroll(self.scroll())
-            .render(area, buf);
+            .map(DownloadRow::Pending)
+            .chain(self.list.iter().map(DownloadRow::Task));
+        self.view.render(
+            items,
+            TaskListenerRanderState::new(
+                state.page_focused,
+                false,
+                state.middle_row,
+                state.symbols,
+            )
+            .with_show_remaining(state.show_remaining)
+            .with_speed_display(state.speed_display),
+            TaskListenerRanderState::new(state.page_focused, true, state.middle_row, state.symbols)
+                .with_show_remaining(state.show_remaining)
+                .with_speed_display(state.speed_display),
+            area,
+            buf,
+        );
     }
 }
 
@@ -141,9 +325,51 @@ impl StatefulWidget for &mut DownloadListInner {
 /// <process bar> <percentage>%
 ///               <speed> <eta>
 /// --------------------------- (分隔线，如果不是最后一项并且有空间渲染时)
+///
+/// 中间一行可以使用`i`键在进度条、URL和目录之间切换，见[`MiddleRowMode`]。
+///
+/// 还没有成功发送的任务显示在列表的最前面，见[`PendingTask`]。
 pub struct DownloadList {
     inner: DownloadListInner,
     sender: sender::Sender,
+    throttle: Arc<Throttle>,
+    // 与后台运行时共享，用于修改排队任务的排序方式
+    limits: Arc<ConnectionLimits>,
+    middle_row: MiddleRowMode,
+    // 是否在已经下载的大小之后显示剩余的大小
+    show_remaining: bool,
+    // 弹窗中显示时间的方式
+    time: TimeConfig,
+    // 默认下载目录的剩余空间，用于在标题行显示，每次刷新时检查等待空间的任务能否恢复
+    disk: DiskSpace,
+    // 在后台检查下载目录是否可写
+    dir_monitor: DirMonitor,
+    // 自动恢复时需要额外保留的空间（字节）
+    disk_headroom: u64,
+    // 下载窗口中是否默认开启自动解压
+    extract_archives: bool,
+    // 正在后台移动的已下载部分，见[`DownloadList::migrate_partials`]
+    migration: Option<std_mpsc::Receiver<MoveResult>>,
+    // 最近一次有任务失败的时间，用于终端的进度状态
+    last_failure: Option<Instant>,
+    // 判断任务健康状况的阈值
+    health: HealthThresholds,
+    // 显示瞬时速度还是平滑后的速度，以及平滑使用的半衰期
+    speed_display: SpeedDisplay,
+    speed_half_life: Duration,
+    // 本次运行中接收的字节数
+    transferred: u64,
+    // 整个队列的剩余时间，与速度采样使用相同的间隔重新计算，见[`DownloadList::update_queue_eta`]
+    queue_eta: Option<QueueEta>,
+    queue_eta_updated: Option<Instant>,
+    // 保存下来用于重复添加的下载，见[`SavedDownload`]
+    templates: Vec<SavedDownload>,
+    // 在后台读取选中任务的文件开头，用于预览
+    peek: FilePeek,
+    // 另一个实例正在使用下载目录，不能添加新的任务，见[`InstanceLock`]
+    //
+    // [`InstanceLock`]: crate::app::instance::InstanceLock
+    read_only: bool,
 }
 
 impl DownloadList {
@@ -151,15 +377,112 @@ impl DownloadList {
 
     pub const NOT_ENOUGH_SPACE_BG: Style = Style::new().bg(Color::DarkGray);
 
+    /// 只读模式下尝试添加任务时的提示
+    pub const READ_ONLY_NOTICE: &'static str =
+        "Read-only: another instance is using the download directory";
+    pub const HEADER_STYLE: Style = Style::new().fg(Color::Gray);
+    pub const CHANNEL_BUSY_STYLE: Style = Style::new().fg(Color::Yellow);
+    pub const CHANNEL_FULL_STYLE: Style = Style::new().fg(Color::Red);
+
+    /// 任务失败之后，终端的进度状态保持为错误的时间
+    pub const RECENT_FAILURE: Duration = Duration::from_secs(10);
+
     // -------------------- CONSTRUCT -----------------------
 
-    pub fn new(sender: mpsc::Sender<Task>) -> Self {
+    pub fn new(
+        stats: Arc<RuntimeStats>,
+        throttle: Arc<Throttle>,
+        limits: Arc<ConnectionLimits>,
+        download_dir: PathBuf,
+        middle_row: MiddleRowMode,
+        disk_headroom: u64,
+        pending: Vec<PendingTask>,
+    ) -> Self {
+        let mut sender = sender::Sender::new(stats, download_dir.clone());
+        let mut inner = DownloadListInner::new();
+        if let Some(max) = pending.iter().map(|p| p.id).max_by_key(|id| id.value()) {
+            sender.continue_after(max);
+        }
+        for mut pending in pending {
+            if pending.id.is_unassigned() {
+                pending.id = sender.next_id();
+            }
+            inner.push_pending(pending);
+        }
         DownloadList {
-            inner: DownloadListInner::new(),
-            sender: sender::Sender::new(sender),
+            inner,
+            sender,
+            throttle,
+            limits,
+            middle_row,
+            show_remaining: false,
+            time: TimeConfig::default(),
+            disk: DiskSpace::new(download_dir.clone()),
+            dir_monitor: DirMonitor::new(download_dir),
+            disk_headroom,
+            extract_archives: false,
+            migration: None,
+            last_failure: None,
+            health: HealthThresholds::default(),
+            speed_display: SpeedDisplay::default(),
+            speed_half_life: EtaEstimator::DEFAULT_HALF_LIFE,
+            transferred: 0,
+            queue_eta: None,
+            queue_eta_updated: None,
+            templates: Vec::new(),
+            peek: FilePeek::new(),
+            read_only: false,
         }
     }
 
+    pub fn with_templates(mut self, templates: Vec<SavedDownload>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn with_extract_archives(mut self, extract_archives: bool) -> Self {
+        self.extract_archives = extract_archives;
+        self
+    }
+
+    pub fn with_health_thresholds(mut self, health: HealthThresholds) -> Self {
+        self.health = health;
+        self
+    }
+
+    pub fn with_show_remaining(mut self, show_remaining: bool) -> Self {
+        self.show_remaining = show_remaining;
+        self
+    }
+
+    pub fn with_time_config(mut self, time: TimeConfig) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// 显示的速度以及计算平滑速度（同时也用于估计剩余时间）时使用的半衰期
+    pub fn with_speed_smoothing(mut self, display: SpeedDisplay, half_life: Duration) -> Self {
+        self.speed_display = display;
+        self.speed_half_life = half_life;
+        self
+    }
+
+    /// 下载目录不存在时，创建的目录使用的权限
+    pub fn with_dir_mode(mut self, dir_mode: Option<FileMode>) -> Self {
+        self.dir_monitor = self.dir_monitor.with_dir_mode(dir_mode);
+        self
+    }
+
+    /// 恢复上次退出时的选中项，只有上次退出时还在等待队列中的任务会保留相同的[`TaskId`]
+    pub fn with_position(mut self, position: &ListPosition<TaskId>) -> Self {
+        let ids: Vec<TaskId> = self.row_ids().collect();
+        if let Some(row) = position.find(&ids) {
+            self.inner.set_selected(Some(row));
+            self.inner.scroll_to(position.scroll);
+        }
+        self
+    }
+
     // -------------------- MEMBER_ACCESS -----------------------
 
     #[inline]
@@ -172,6 +495,71 @@ impl DownloadList {
         self.inner.list()
     }
 
+    #[inline]
+    pub fn pending(&self) -> &Vec<PendingTask> {
+        self.inner.pending()
+    }
+
+    /// `url`是否已经在等待队列或者任务列表中
+    pub fn is_queued(&self, url: &str) -> bool {
+        self.pending().iter().any(|pending| pending.url == url)
+            || self.list().iter().any(|listener| {
+                let state = listener.get_state_handler();
+                let state = state.lock().unwrap();
+                state.options().is_some_and(|options| options.url == url)
+            })
+    }
+
+    pub fn templates(&self) -> &[SavedDownload] {
+        &self.templates
+    }
+
+    /// 新任务使用的下载目录，为绝对路径
+    pub fn download_dir(&self) -> &Path {
+        self.sender.download_dir()
+    }
+
+    /// 下载目录存在的问题，见[`DirMonitor`]
+    pub fn dir_warning(&self) -> Option<String> {
+        self.dir_monitor.warning()
+    }
+
+    #[inline]
+    pub fn middle_row(&self) -> MiddleRowMode {
+        self.middle_row
+    }
+
+    pub fn show_remaining(&self) -> bool {
+        self.show_remaining
+    }
+
+    pub fn speed_display(&self) -> SpeedDisplay {
+        self.speed_display
+    }
+
+    /// 正在运行的任务按照[`SpeedDisplay`]选择的速度之和（B/s），用于第一行以及Stats页面
+    pub fn display_speed(&self) -> u64 {
+        self.list()
+            .iter()
+            .filter(|listener| listener.phase().is_active())
+            .filter_map(|listener| {
+                listener
+                    .get_state_handler()
+                    .lock()
+                    .unwrap()
+                    .speed(self.speed_display)
+            })
+            .sum()
+    }
+
+    pub fn admission_policy(&self) -> AdmissionPolicy {
+        self.limits.policy()
+    }
+
+    pub fn fair_share(&self) -> bool {
+        self.throttle.is_fair()
+    }
+
     // -------------------- MODIFIER -----------------------
 
     #[inline]
@@ -179,50 +567,394 @@ impl DownloadList {
         self.inner.set_selected(index);
     }
 
-    // -------------------- FUNCTION -----------------------
+    /// 直接加入已经填好状态的任务，见[`TaskListener::fake`]
+    #[cfg(test)]
+    pub fn push_fake(&mut self, listener: TaskListener) {
+        self.inner.push_task(listener);
+    }
 
-    pub fn select_next(&mut self) {
-        if self.list().is_empty() {
-            self.set_selected(None);
+    #[inline]
+    pub fn set_middle_row(&mut self, middle_row: MiddleRowMode) {
+        self.middle_row = middle_row;
+    }
+
+    /// 在瞬时速度和平滑后的速度之间切换，返回切换之后的显示方式
+    pub fn toggle_speed_display(&mut self) -> SpeedDisplay {
+        self.speed_display = self.speed_display.toggle();
+        log::debug!(target:"App", "Showing {} speed", self.speed_display);
+        self.speed_display
+    }
+
+    /// 修改之后发送的任务使用的下载目录，已经发送的任务不受影响，
+    /// 暂停的任务恢复时仍然使用原来的路径，可以使用[`DownloadList::migrate_partials`]移动
+    pub fn set_download_dir(&mut self, download_dir: PathBuf) {
+        self.disk = DiskSpace::new(download_dir.clone());
+        self.dir_monitor.set_dir(download_dir.clone());
+        self.sender.set_download_dir(download_dir);
+    }
+
+    /// 只读模式下不添加任何任务，另一个实例正在使用下载目录时开启，换用其他的下载目录之后关闭
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// 距离上一次计算超过速度的采样间隔时，重新估计整个队列的剩余时间
+    ///
+    /// 速度使用所有任务平滑后的速度之和，排队中的任务使用HEAD请求得到的大小。
+    pub fn update_queue_eta(&mut self, now: Instant) {
+        if self
+            .queue_eta_updated
+            .is_some_and(|t| now.duration_since(t) < TaskState::REFRESH_INTERVAL)
+        {
             return;
         }
+        self.queue_eta_updated = Some(now);
+        let mut speed = 0;
+        let mut tasks = Vec::with_capacity(self.list().len());
+        for listener in self.list() {
+            let phase = listener.phase();
+            // 取消中的任务不会再继续
+            if phase.is_terminal() || phase == ListenerPhase::Cancelling {
+                continue;
+            }
+            let state = listener.get_state_handler();
+            let state = state.lock().unwrap();
+            let entry = match phase {
+                ListenerPhase::Paused | ListenerPhase::Pausing => QueueEntry::Paused,
+                ListenerPhase::Submitted => QueueEntry::Queued,
+                _ if state.phase == TaskPhase::Queued => QueueEntry::Queued,
+                _ => QueueEntry::Running,
+            };
+            if entry == QueueEntry::Running {
+                speed += state.speed(SpeedDisplay::Smoothed).unwrap_or(0);
+            }
+            tasks.push((state.remaining(), entry));
+        }
+        self.queue_eta = QueueEta::estimate(&tasks, speed as f64, self.limits.occupancy().max);
+    }
 
-        match self.selected() {
-            Some(i) => {
-                self.set_selected(Some((i + 1) % self.list().len()));
+    /// 本次运行中所有任务接收的字节数
+    pub fn transferred(&self) -> u64 {
+        self.transferred
+    }
+
+    /// 下载完所有未暂停的任务需要的时间，见[`QueueEta`]
+    pub fn queue_eta(&self) -> Option<QueueEta> {
+        self.queue_eta
+    }
+
+    /// 所有任务最近的下载速度之和（B/s）
+    pub fn total_speed(&self) -> u64 {
+        self.list().iter().map(TaskListener::recent_speed).sum()
+    }
+
+    /// 用于在侧边栏显示的整体状态
+    pub fn activity(&self) -> DownloadActivity {
+        if self.total_speed() > 0 {
+            DownloadActivity::Active
+        } else if !self.list().is_empty() && self.list().iter().all(|l| l.phase().is_paused()) {
+            DownloadActivity::Paused
+        } else {
+            DownloadActivity::Idle
+        }
+    }
+
+    /// 正在进行的任务的整体进度，见[`TerminalProgress`]
+    ///
+    /// [`TerminalProgress`]: crate::app::title::TerminalProgress
+    pub fn progress_summary(&self) -> ProgressSummary {
+        let mut summary = ProgressSummary {
+            failed: self
+                .last_failure
+                .is_some_and(|t| t.elapsed() < Self::RECENT_FAILURE),
+            ..Default::default()
+        };
+        for listener in self.list() {
+            if !listener.phase().is_active() {
+                continue;
             }
-            None => {
-                self.set_selected(Some(0));
+            summary.active += 1;
+            let state = listener.get_state_handler();
+            let state = state.lock().unwrap();
+            match state.content_length() {
+                Some(total) => {
+                    summary.downloaded += state.downloaded();
+                    summary.total += total;
+                }
+                None => summary.indeterminate = true,
             }
         }
+        summary
+    }
+
+    /// 当前选中的行对应的任务
+    #[inline]
+    pub fn selected_row(&self) -> Option<DownloadRowIndex> {
+        self.selected().and_then(|row| self.inner.row(row))
+    }
+
+    /// 选中的任务创建时的选项，用于复制任务或者保存为模板
+    fn selected_options(&self) -> Option<RequestOptions> {
+        match self.selected_row()? {
+            DownloadRowIndex::Task(idx) => self.list()[idx]
+                .get_state_handler()
+                .lock()
+                .unwrap()
+                .options()
+                .map(|options| options.as_ref().clone()),
+            DownloadRowIndex::Pending(idx) => Some(self.pending()[idx].to_options()),
+        }
+    }
+
+    /// 选中的任务以及滚动距离，退出时保存到会话文件中
+    pub fn position(&self) -> ListPosition<TaskId> {
+        ListPosition {
+            selected: self.selected().and_then(|row| self.row_ids().nth(row)),
+            scroll: self.inner.scroll(),
+        }
+    }
+
+    /// 按照显示的顺序排列的每一行的任务，等待队列中的任务在前
+    fn row_ids(&self) -> impl Iterator<Item = TaskId> + '_ {
+        self.pending()
+            .iter()
+            .map(|pending| pending.id)
+            .chain(self.list().iter().map(TaskListener::id))
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 通道中等待接收的任务超过容量的一半时显示为黄色，通道已满时显示为红色，
+    /// 此时新任务只能留在等待队列中
+    pub fn channel_style(in_flight: usize) -> Style {
+        let capacity = sender::Sender::CHANNEL_CAPACITY;
+        if in_flight >= capacity {
+            DownloadList::CHANNEL_FULL_STYLE
+        } else if in_flight * 2 > capacity {
+            DownloadList::CHANNEL_BUSY_STYLE
+        } else {
+            Style::new()
+        }
     }
 
+    #[inline]
+    pub fn select_next(&mut self) {
+        self.inner.select_next();
+    }
+
+    #[inline]
     pub fn select_previous(&mut self) {
-        if self.list().is_empty() {
-            self.set_selected(None);
+        self.inner.select_previous();
+    }
+
+    #[inline]
+    pub fn select_page_down(&mut self) {
+        self.inner.select_page_down();
+    }
+
+    #[inline]
+    pub fn select_page_up(&mut self) {
+        self.inner.select_page_up();
+    }
+
+    #[inline]
+    pub fn select_first(&mut self) {
+        self.inner.select_first();
+    }
+
+    #[inline]
+    pub fn select_last(&mut self) {
+        self.inner.select_last();
+    }
+
+    pub fn select_task(&mut self, id: TaskId) -> bool {
+        self.inner.select_task(id)
+    }
+
+    /// 已经下载的部分保存在`old_dir`中的任务，返回可以移动的任务以及正在运行而不能移动的任务数量
+    pub fn partial_moves(&self, old_dir: &Path, new_dir: &Path) -> (Vec<PartialMove>, usize) {
+        let mut jobs = Vec::new();
+        let mut running = 0;
+        for listener in self.list() {
+            let filepath = listener
+                .get_state_handler()
+                .lock()
+                .unwrap()
+                .filepath
+                .clone();
+            let Some(job) = PartialMove::new(listener.id(), &filepath, old_dir, new_dir) else {
+                continue;
+            };
+            if !filepath.is_file() || listener.is_migrating() {
+                continue;
+            }
+            if listener.phase().is_paused() {
+                jobs.push(job);
+            } else {
+                running += 1;
+            }
+        }
+        (jobs, running)
+    }
+
+    /// 将暂停的任务已经下载的部分从`old_dir`移动到`new_dir`，正在运行的任务会被跳过
+    ///
+    /// 移动在后台线程中进行，每个文件完成时在[`DownloadList::handle_async`]中更新任务的路径。
+    pub fn migrate_partials(&mut self, old_dir: &Path, new_dir: &Path, notices: &mut NoticeBoard) {
+        if self.migration.is_some() {
+            notices.push(String::from("Partial files are already being moved"));
+            return;
+        }
+        let (candidates, running) = self.partial_moves(old_dir, new_dir);
+        // 在开始移动之前标记，之后任务不能被恢复，直到移动完成
+        let jobs: Vec<PartialMove> = candidates
+            .into_iter()
+            .filter(|job| {
+                self.inner
+                    .find_task_mut(job.id)
+                    .is_some_and(|l| l.set_migrating(true))
+            })
+            .collect();
+        if running > 0 {
+            notices.push(format!("Skipped {} running task(s)", running));
+        }
+        if jobs.is_empty() {
             return;
         }
+        log::info!(target:"App", "Moving {} partial file(s) to {}", jobs.len(), new_dir.display());
+        self.migration = Some(migrate::spawn_moves(jobs));
+    }
 
-        let len = self.list().len();
+    /// 后台运行时启动完成后调用，发送在此之前添加的任务，并开始检查下载目录
+    pub fn connect(&mut self, sender: mpsc::Sender<Task>, runtime: &Handle) {
+        self.dir_monitor.start(runtime);
+        self.peek.set_runtime(runtime.clone());
+        self.sender.connect(sender);
+        self.submit_pending();
+    }
 
-        match self.selected() {
-            Some(i) => {
-                if i == 0 || i >= len {
-                    self.set_selected(Some(len - 1));
-                } else {
-                    self.set_selected(Some(i - 1));
+    /// 新的任务总是先进入等待队列，再按顺序发送，保证任务的顺序与提交的顺序一致
+    ///
+    /// 返回分配给该任务的编号，只读模式下不添加任务，返回[`None`]。
+    pub fn append_normal_task(&mut self, options: RequestOptions) -> Option<TaskId> {
+        let id = self.sender.next_id();
+        self.append_task_with_id(id, options)
+    }
+
+    /// 与[`DownloadList::append_normal_task`]相同，但是沿用已有的编号，用于撤销取消的任务
+    fn append_task_with_id(&mut self, id: TaskId, options: RequestOptions) -> Option<TaskId> {
+        if self.read_only {
+            log::warn!(target:"App", "Read-only mode, not adding {}", options.url);
+            return None;
+        }
+        self.inner
+            .push_pending(PendingTask::from_options(id, options));
+        self.submit_pending();
+        Some(id)
+    }
+
+    /// 重新校验已经下载完成的文件，校验的进度与普通任务一样显示在列表中，
+    /// 见[`Sender::send_verify_request`]
+    ///
+    /// [`Sender::send_verify_request`]: sender::Sender::send_verify_request
+    pub fn verify_existing(&mut self, state: TaskState, notices: &mut NoticeBoard) {
+        let id = self.sender.next_id();
+        match self.sender.send_verify_request(id, state) {
+            Ok(listener) => self.inner.push_task(listener),
+            Err(_) => notices.push(String::from("Background runtime is not ready")),
+        }
+    }
+
+    /// 按顺序发送等待队列中的任务，直到队列为空或者发送失败
+    pub fn submit_pending(&mut self) {
+        while let Some(pending) = self.inner.pending().first() {
+            match self
+                .sender
+                .send_normal_request(pending.id, pending.to_options())
+            {
+                Ok(listener) => self.inner.submit_first_pending(listener),
+                Err(e) => {
+                    if let TrySendError::Closed(_) = *e {
+                        log::warn!(target:"App", "Task channel closed, {} task(s) pending", self.pending().len());
+                    }
+                    break;
                 }
             }
+        }
+    }
+
+    /// 修改还没有开始的任务的选项，任务已经开始时返回false
+    pub fn update_task_options(&mut self, id: TaskId, options: RequestOptions) -> bool {
+        if let Some(pending) = self.inner.pending_mut().iter_mut().find(|p| p.id == id) {
+            *pending = PendingTask::from_options(id, options);
+            return true;
+        }
+        let Some(listener) = self.list().iter().find(|l| l.id() == id) else {
+            return false;
+        };
+        // 任务离开排队状态时也会持有该锁，因此不会在检查之后才开始
+        let state = listener.get_state_handler();
+        let mut state = state.lock().unwrap();
+        if !state.is_editable() {
+            return false;
+        }
+        state.options = Some(Arc::new(options));
+        true
+    }
+
+    /// 保存一个下载，已经存在同名的下载时替换它，返回是否替换了已有的下载
+    pub fn save_template(&mut self, template: SavedDownload) -> bool {
+        log::info!(target:"App", "Saved download {}: {}", template.name, template.task.url);
+        match self.templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => {
+                *existing = template;
+                true
+            }
             None => {
-                self.set_selected(Some(0));
+                self.templates.push(template);
+                false
             }
         }
     }
 
-    pub fn append_normal_task(&mut self, url: String) -> anyhow::Result<()> {
-        let listener = self.sender.send_normal_request(url)?;
-        self.inner.push_task(listener);
-        Ok(())
+    /// 修改名称为`name`的下载的选项，找不到时（例如已经被删除）返回false
+    pub fn update_template(&mut self, name: &str, options: RequestOptions) -> bool {
+        match self.templates.iter_mut().find(|t| t.name == name) {
+            Some(template) => {
+                *template = SavedDownload::new(template.name.clone(), options);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_template(&mut self, name: &str) {
+        self.templates.retain(|t| t.name != name);
+    }
+
+    /// 使用保存的下载添加一个新的任务，URL中的日期为今天，找不到时返回[`None`]
+    pub fn queue_template(&mut self, name: &str) -> Option<TaskId> {
+        let today = chrono::Local::now().date_naive();
+        let options = self
+            .templates
+            .iter()
+            .find(|t| t.name == name)?
+            .options_on(today);
+        log::info!(target:"App", "Queued saved download {}: {}", name, options.url);
+        self.append_normal_task(options)
+    }
+
+    pub fn remove_pending(&mut self, index: usize) {
+        if index >= self.pending().len() {
+            return;
+        }
+        let pending = self.inner.remove_pending(index);
+        log::debug!(target:"App", "Remove pending task: {}", pending.url);
     }
 
     pub fn stop_task(&mut self, index: usize) -> anyhow::Result<()> {
@@ -230,27 +962,117 @@ impl DownloadList {
             return Err(anyhow::anyhow!("Index out of bounds"));
         }
 
-        let listener = self.inner.get_item_mut(index).unwrap();
-        if listener.is_stopped() {
-            return Ok(());
+        self.inner
+            .get_item_mut(index)
+            .unwrap()
+            .stop(PauseOrigin::User);
+        Ok(())
+    }
+
+    /// 停止所有正在进行的任务，已经停止的任务保持原来的停止原因，返回实际停止了多少任务
+    ///
+    /// 正在同步或者校验的任务已经下载完成，不会被批量停止，需要时可以单独停止。
+    pub fn stop_all(&mut self) -> BulkSummary {
+        let mut summary = BulkSummary::new("Paused");
+        for idx in 0..self.list().len() {
+            let listener = self.inner.get_item_mut(idx).unwrap();
+            let skipped = match listener.phase() {
+                ListenerPhase::Paused => Some("already paused"),
+                ListenerPhase::Pausing => Some("already stopping"),
+                ListenerPhase::Cancelling => Some("cancelling"),
+                ListenerPhase::Finishing => Some("finishing"),
+                ListenerPhase::Terminal(_) => Some("finished"),
+                _ => None,
+            };
+            match skipped {
+                Some(reason) => {
+                    log::debug!(target:"App", "Stop all: skipped {} ({})", listener.id(), reason);
+                    summary.skip(reason);
+                }
+                None => {
+                    log::debug!(target:"App", "Stop all: stopping {}", listener.id());
+                    listener.stop(PauseOrigin::BulkPause);
+                    summary.done();
+                }
+            }
         }
+        summary
+    }
 
-        listener.send_command(TaskCommand::Stop);
-        Ok(())
+    /// 恢复被[`DownloadList::stop_all`]停止的任务，`everything`为true时恢复所有停止的任务，
+    /// 包括单独停止的、失败的以及等待磁盘空间的任务，返回实际恢复了多少任务
+    ///
+    /// 在排队期间被停止的任务还没有开始下载，按照原来的选项重新发送，见[`Sender::send_resume_request`]。
+    ///
+    /// [`Sender::send_resume_request`]: crate::app::sender::Sender::send_resume_request
+    pub fn continue_all(&mut self, everything: bool, finish_list: &mut FinishList) -> BulkSummary {
+        let mut summary = BulkSummary::new("Resumed");
+        let mut idx = 0;
+        while idx < self.list().len() {
+            let listener = &self.list()[idx];
+            let id = listener.id();
+            let len = self.list().len();
+            let origin = listener.pause_origin();
+            let waiting_for_space = listener.waiting_for_space().is_some();
+            let skipped = match listener.phase() {
+                ListenerPhase::Paused if listener.is_migrating() => Some("being moved"),
+                ListenerPhase::Paused if everything || origin == Some(PauseOrigin::BulkPause) => {
+                    self.try_resume(idx, finish_list).err()
+                }
+                ListenerPhase::Paused if origin == Some(PauseOrigin::User) => {
+                    Some("paused individually")
+                }
+                ListenerPhase::Paused if waiting_for_space => Some("waiting for space"),
+                ListenerPhase::Paused => Some("failed"),
+                ListenerPhase::Pausing => Some("still stopping"),
+                ListenerPhase::Cancelling => Some("cancelling"),
+                ListenerPhase::Terminal(_) => Some("finished"),
+                _ => Some("already running"),
+            };
+            match skipped {
+                Some(reason) => {
+                    log::debug!(target:"App", "Continue all: skipped {} ({})", id, reason);
+                    summary.skip(reason);
+                }
+                None => {
+                    log::debug!(target:"App", "Continue all: resumed {}", id);
+                    summary.done();
+                }
+            }
+            // 恢复失败的任务会被移动到完成列表中
+            if self.list().len() == len {
+                idx += 1;
+            }
+        }
+        summary
     }
 
-    pub fn abort_task(&mut self, index: usize, finish_list: &mut FinishList) -> anyhow::Result<()> {
+    pub fn abort_task(
+        &mut self,
+        index: usize,
+        finish_list: &mut FinishList,
+        undo: &mut UndoBuffer,
+    ) -> anyhow::Result<()> {
         if index >= self.list().len() {
             return Err(anyhow::anyhow!("Index out of bounds"));
         }
 
+        let id = self.inner.get_item(index).unwrap().id();
+        let state = self.inner.get_item(index).unwrap().get_state_handler();
+        let url = state
+            .lock()
+            .unwrap()
+            .url()
+            .map(Url::to_string)
+            .unwrap_or_default();
+        undo.push_abort(id, state, url);
+
         let listener = self.inner.get_item_mut(index).unwrap();
-        if listener.is_stopped() {
-            self.move_to_finish_list(index, finish_list);
-            return Ok(());
+        match listener.phase() {
+            ListenerPhase::Paused => self.move_to_finish_list(index, finish_list),
+            ListenerPhase::Terminal(_) => {}
+            _ => listener.cancel(),
         }
-
-        listener.send_command(TaskCommand::Abort);
         Ok(())
     }
 
@@ -263,16 +1085,155 @@ impl DownloadList {
             return Err(anyhow::anyhow!("Index out of bounds"));
         }
 
-        if self
+        let _ = self.try_resume(index, finish_list);
+        Ok(())
+    }
+
+    /// 从头重新下载任务，列表中的位置、ID以及文件名保持不变
+    ///
+    /// 暂停的任务立即重新提交；正在进行的任务先停止，收到停止的结果之后
+    /// 才在[`DownloadList::handle_async`]中重新提交，避免两个任务同时写入同一个文件。
+    pub fn restart_task(
+        &mut self,
+        index: usize,
+        finish_list: &mut FinishList,
+    ) -> anyhow::Result<()> {
+        if index >= self.list().len() {
+            return Err(anyhow::anyhow!("Index out of bounds"));
+        }
+
+        let listener = self.inner.get_item_mut(index).unwrap();
+        match listener.phase() {
+            ListenerPhase::Paused => {
+                let _ = self.try_restart(index, finish_list);
+            }
+            ListenerPhase::Terminal(_) => {}
+            _ => listener.stop(PauseOrigin::Restart),
+        }
+        Ok(())
+    }
+
+    /// 切换任务是否加速，同时只有一个任务加速，加速一个任务时取消其他任务的加速
+    pub fn toggle_boost(&mut self, index: usize) -> anyhow::Result<()> {
+        let Some(listener) = self.list().get(index) else {
+            return Err(anyhow::anyhow!("Index out of bounds"));
+        };
+        if matches!(listener.phase(), ListenerPhase::Terminal(_)) {
+            return Ok(());
+        }
+        let boost = !listener.is_boosted();
+        if boost {
+            for other in self.list().iter().filter(|other| other.is_boosted()) {
+                other.set_boost(false);
+            }
+        }
+        self.list()[index].set_boost(boost);
+        Ok(())
+    }
+
+    /// 恢复暂停的任务，失败时返回原因，`index`需要在范围内
+    fn try_resume(
+        &mut self,
+        index: usize,
+        finish_list: &mut FinishList,
+    ) -> Result<(), &'static str> {
+        let result = self
             .inner
             .get_item_mut(index)
             .unwrap()
-            .resume_task(&mut self.sender)
-            .is_err()
+            .resume_task(&mut self.sender);
+        self.check_resubmitted(index, finish_list, result)
+    }
+
+    /// 清除暂停的任务的进度并重新提交，失败时返回原因，`index`需要在范围内
+    fn try_restart(
+        &mut self,
+        index: usize,
+        finish_list: &mut FinishList,
+    ) -> Result<(), &'static str> {
+        let result = self
+            .inner
+            .get_item_mut(index)
+            .unwrap()
+            .restart_task(&mut self.sender);
+        self.check_resubmitted(index, finish_list, result)
+    }
+
+    fn check_resubmitted(
+        &mut self,
+        index: usize,
+        finish_list: &mut FinishList,
+        result: Result<(), Box<TrySendError<Arc<Mutex<TaskState>>>>>,
+    ) -> Result<(), &'static str> {
+        match result {
+            Ok(()) => Ok(()),
+            // 通道已满时保持暂停状态，用户可以稍后再试
+            Err(e) if matches!(*e, TrySendError::Full(_)) => {
+                log::warn!(target:"App", "Task channel is full, cannot resume task now");
+                Err("queue full")
+            }
+            Err(_) => {
+                self.move_to_finish_list(index, finish_list);
+                Err("failed to resume")
+            }
+        }
+    }
+
+    /// 撤销取消任务的操作
+    ///
+    /// 取消任务时已经下载的部分会被保留，如果服务器支持断点续传，则继续下载，
+    /// 否则重新添加一个相同URL的任务。两种情况下任务都沿用原来的编号。
+    pub fn undo_abort(
+        &mut self,
+        id: TaskId,
+        state: Arc<Mutex<TaskState>>,
+        url: String,
+        finish_list: &mut FinishList,
+    ) {
+        // 任务仍然在列表中，说明任务还没有响应取消的指令
+        if self
+            .list()
+            .iter()
+            .any(|l| Arc::ptr_eq(&l.get_state_handler(), &state))
         {
-            self.move_to_finish_list(index, finish_list);
+            log::warn!(target:"App", "Task is still being aborted, cannot undo");
+            return;
+        }
+
+        let (filepath, partial_kept, options) = {
+            let state = state.lock().unwrap();
+            let filepath = state.filepath().to_path_buf();
+            let partial_kept =
+                state.accept_ranges() && state.downloaded() > 0 && filepath.is_file();
+            (
+                filepath,
+                partial_kept,
+                state.options().map(|o| o.as_ref().clone()),
+            )
+        };
+        finish_list.remove_task_by_path(&filepath);
+
+        if partial_kept {
+            match self.sender.send_resume_request(id, state.clone()) {
+                Ok(channel) => {
+                    self.inner.push_task(TaskListener::new(
+                        id,
+                        state,
+                        channel.result_recv,
+                        channel.command_sender,
+                    ));
+                    return;
+                }
+                Err(_) => {
+                    log::warn!(target:"App", "Failed to resume {}, adding it again", filepath.display());
+                }
+            }
+        }
+
+        let options = options.or_else(|| (!url.is_empty()).then(|| RequestOptions::new(url, None)));
+        if let Some(options) = options {
+            self.append_task_with_id(id, options);
         }
-        Ok(())
     }
 
     fn push_to_finish_list(listener: &mut TaskListener, finish_list: &mut FinishList) {
@@ -282,13 +1243,6 @@ impl DownloadList {
     fn move_to_finish_list(&mut self, index: usize, finish_list: &mut FinishList) {
         Self::push_to_finish_list(self.inner.get_item_mut(index).unwrap(), finish_list);
         self.inner.remove_task(index);
-        if let Some(selected) = self.selected() {
-            if selected >= index && selected > 0 {
-                self.set_selected(Some(selected - 1));
-            } else if self.list().is_empty() {
-                self.set_selected(None);
-            }
-        }
     }
 
     // ------------------- HANDLE_MESSAGE ----------------------
@@ -298,8 +1252,8 @@ impl DownloadList {
         app: &mut App,
         message: DownloadListMessage,
     ) -> Option<DownloadListMessage> {
-        let (this_widget, widgets, finish_list) = app.destruct_data();
-        this_widget.respond_to_message_inner(message, widgets, finish_list)
+        let (this_widget, widgets, finish_list, undo, notices) = app.destruct_data();
+        this_widget.respond_to_message_inner(message, widgets, finish_list, undo, notices)
     }
 
     fn respond_to_message_inner(
@@ -307,6 +1261,8 @@ impl DownloadList {
         message: DownloadListMessage,
         widgets: &mut Vec<WidgetType>,
         finish_list: &mut FinishList,
+        undo: &mut UndoBuffer,
+        notices: &mut NoticeBoard,
     ) -> Option<DownloadListMessage> {
         match message {
             DownloadListMessage::GoUp => {
@@ -317,42 +1273,227 @@ impl DownloadList {
                 self.select_next();
                 None
             }
+            DownloadListMessage::PageUp => {
+                self.select_page_up();
+                None
+            }
+            DownloadListMessage::PageDown => {
+                self.select_page_down();
+                None
+            }
+            DownloadListMessage::GoTop => {
+                self.select_first();
+                None
+            }
+            DownloadListMessage::GoBottom => {
+                self.select_last();
+                None
+            }
             DownloadListMessage::AppendTaskInput => {
-                widgets.push(WidgetType::new_download_input());
+                if self.read_only {
+                    notices.push(String::from(Self::READ_ONLY_NOTICE));
+                    return None;
+                }
+                widgets.push(WidgetType::new_download_input(
+                    self.dir_warning(),
+                    self.extract_archives,
+                ));
+                None
+            }
+            DownloadListMessage::JumpInput => {
+                widgets.push(WidgetType::new_jump_input(JumpTarget::Downloading));
+                None
+            }
+            DownloadListMessage::AppendNewTask(options) => {
+                self.append_normal_task(options);
+                None
+            }
+            DownloadListMessage::ShowDetail => {
+                // 等待用户回应的任务重新打开对话框，而不是显示详细信息
+                if let Some(DownloadRowIndex::Task(idx)) = self.selected_row()
+                    && let Some(listener) = self.inner.get_item_mut(idx)
+                    && listener.reopen_prompt()
+                {
+                    push_prompt_dialogs(listener, widgets, &self.time);
+                } else if let Some(DownloadRowIndex::Task(idx)) = self.selected_row()
+                    && let Some(listener) = self.list().get(idx)
+                {
+                    widgets.push(WidgetType::new_task_detail_dialog(
+                        listener.get_state_handler(),
+                        listener.task_result().cloned(),
+                    ));
+                }
+                None
+            }
+            DownloadListMessage::PeekFile => {
+                if let Some(DownloadRowIndex::Task(idx)) = self.selected_row()
+                    && let Some(listener) = self.list().get(idx)
+                {
+                    let filepath = listener
+                        .get_state_handler()
+                        .lock()
+                        .unwrap()
+                        .filepath()
+                        .to_path_buf();
+                    // 还在排队的任务可能还没有确定文件名
+                    if filepath.as_os_str().is_empty() {
+                        notices.push(format!("{} has no file yet", listener.id()));
+                    } else if !self.peek.start(filepath) {
+                        notices.push(String::from("Background runtime is not ready"));
+                    }
+                }
+                None
+            }
+            DownloadListMessage::LowerSpeedLimit => {
+                self.throttle.step_down();
+                None
+            }
+            DownloadListMessage::RaiseSpeedLimit => {
+                self.throttle.step_up();
+                None
+            }
+            DownloadListMessage::ClearSpeedLimit => {
+                self.throttle.set_manual(None);
                 None
             }
-            DownloadListMessage::AppendNewTask(request) => {
-                // FIXME: 应该之后会专门制作一个弹窗
-                self.append_normal_task(request); // TODO: handle error
+            DownloadListMessage::CycleAdmissionPolicy => {
+                self.limits.set_policy(self.admission_policy().next());
+                None
+            }
+            DownloadListMessage::ToggleFairShare => {
+                self.throttle.toggle_fair_share();
+                None
+            }
+            DownloadListMessage::EditTask => {
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Pending(idx)) => {
+                        let pending = &self.pending()[idx];
+                        widgets.push(WidgetType::new_edit_input(
+                            pending.id,
+                            &pending.to_options(),
+                        ));
+                    }
+                    Some(DownloadRowIndex::Task(idx)) => {
+                        let listener = &self.list()[idx];
+                        let options = {
+                            let state = listener.get_state_handler();
+                            let state = state.lock().unwrap();
+                            state
+                                .options()
+                                .filter(|_| state.is_editable())
+                                .map(|options| options.as_ref().clone())
+                        };
+                        match options {
+                            Some(options) => {
+                                widgets.push(WidgetType::new_edit_input(listener.id(), &options))
+                            }
+                            None => notices.push(format!(
+                                "Task {} has already started and cannot be edited",
+                                listener.id()
+                            )),
+                        }
+                    }
+                    None => {}
+                }
+                None
+            }
+            DownloadListMessage::CloneTask => {
+                if let Some(options) = self.selected_options() {
+                    widgets.push(WidgetType::new_clone_input(&options));
+                }
+                None
+            }
+            DownloadListMessage::SaveTemplate => {
+                if let Some(options) = self.selected_options() {
+                    widgets.push(WidgetType::new_template_name_input(options));
+                }
+                None
+            }
+            DownloadListMessage::OpenTemplates => {
+                widgets.push(WidgetType::new_template_dialog(self.templates.clone()));
+                None
+            }
+            DownloadListMessage::ToggleMiddleRow => {
+                self.set_middle_row(self.middle_row().next());
+                None
+            }
+            DownloadListMessage::ToggleRemaining => {
+                self.show_remaining = !self.show_remaining;
                 None
             }
             DownloadListMessage::StopTask => {
-                if let Some(index) = self.selected() {
-                    if index >= self.list().len() {
-                        self.set_selected(None);
-                        return None;
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Task(index)) => self.stop_task(index).unwrap(),
+                    Some(DownloadRowIndex::Pending(_)) => {}
+                    None => self.set_selected(None),
+                }
+                None
+            }
+            DownloadListMessage::RestartTask => {
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Task(index))
+                        if self
+                            .list()
+                            .get(index)
+                            .is_some_and(TaskListener::is_migrating) =>
+                    {
+                        notices.push(format!(
+                            "{} is being moved, try again later",
+                            self.list()[index].id()
+                        ));
                     }
-                    self.stop_task(index).unwrap();
+                    Some(DownloadRowIndex::Task(index)) => {
+                        self.restart_task(index, finish_list).unwrap()
+                    }
+                    Some(DownloadRowIndex::Pending(_)) => {}
+                    None => self.set_selected(None),
+                }
+                None
+            }
+            DownloadListMessage::ToggleBoost => {
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Task(index)) => self.toggle_boost(index).unwrap(),
+                    Some(DownloadRowIndex::Pending(_)) => {}
+                    None => self.set_selected(None),
                 }
                 None
             }
             DownloadListMessage::CancelTask => {
-                if let Some(index) = self.selected() {
-                    if index >= self.list().len() {
-                        self.set_selected(None);
-                        return None;
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Task(index)) => {
+                        self.abort_task(index, finish_list, undo).unwrap()
                     }
-                    self.abort_task(index, finish_list).unwrap();
+                    Some(DownloadRowIndex::Pending(index)) => self.remove_pending(index),
+                    None => self.set_selected(None),
                 }
                 None
             }
+            DownloadListMessage::StopAll => {
+                notices.push(self.stop_all().to_string());
+                None
+            }
+            DownloadListMessage::ContinueAll { everything } => {
+                notices.push(self.continue_all(everything, finish_list).to_string());
+                None
+            }
             DownloadListMessage::ContinueTask => {
-                if let Some(index) = self.selected() {
-                    if index >= self.list().len() {
-                        self.set_selected(None);
-                        return None;
+                match self.selected_row() {
+                    Some(DownloadRowIndex::Task(index))
+                        if self
+                            .list()
+                            .get(index)
+                            .is_some_and(TaskListener::is_migrating) =>
+                    {
+                        notices.push(format!(
+                            "{} is being moved, try again later",
+                            self.list()[index].id()
+                        ));
                     }
-                    self.resume_task(index, finish_list).unwrap();
+                    Some(DownloadRowIndex::Task(index)) => {
+                        self.resume_task(index, finish_list).unwrap()
+                    }
+                    Some(DownloadRowIndex::Pending(_)) => self.submit_pending(),
+                    None => self.set_selected(None),
                 }
                 None
             }
@@ -363,10 +1504,34 @@ impl DownloadList {
         match key.code {
             KeyCode::Up | KeyCode::Char('k') => Some(DownloadListMessage::GoUp),
             KeyCode::Down | KeyCode::Char('j') => Some(DownloadListMessage::GoDown),
+            KeyCode::PageUp => Some(DownloadListMessage::PageUp),
+            KeyCode::PageDown => Some(DownloadListMessage::PageDown),
+            KeyCode::Home | KeyCode::Char('g') => Some(DownloadListMessage::GoTop),
+            KeyCode::End | KeyCode::Char('G') => Some(DownloadListMessage::GoBottom),
             KeyCode::Char('a') => Some(DownloadListMessage::AppendTaskInput),
             KeyCode::Char('s') => Some(DownloadListMessage::StopTask),
             KeyCode::Char('c') => Some(DownloadListMessage::ContinueTask),
+            KeyCode::Char('S') => Some(DownloadListMessage::StopAll),
+            KeyCode::Char('C') => Some(DownloadListMessage::ContinueAll {
+                everything: key.modifiers.contains(KeyModifiers::ALT),
+            }),
             KeyCode::Char('x') => Some(DownloadListMessage::CancelTask),
+            KeyCode::Char('R') => Some(DownloadListMessage::RestartTask),
+            KeyCode::Char('!') => Some(DownloadListMessage::ToggleBoost),
+            KeyCode::Char('i') => Some(DownloadListMessage::ToggleMiddleRow),
+            KeyCode::Char('r') => Some(DownloadListMessage::ToggleRemaining),
+            KeyCode::Char('e') => Some(DownloadListMessage::EditTask),
+            KeyCode::Enter => Some(DownloadListMessage::ShowDetail),
+            KeyCode::Char('P') => Some(DownloadListMessage::PeekFile),
+            KeyCode::Char('A') | KeyCode::Char('d') => Some(DownloadListMessage::CloneTask),
+            KeyCode::Char('[') => Some(DownloadListMessage::LowerSpeedLimit),
+            KeyCode::Char(']') => Some(DownloadListMessage::RaiseSpeedLimit),
+            KeyCode::Char('=') => Some(DownloadListMessage::ClearSpeedLimit),
+            KeyCode::Char('o') => Some(DownloadListMessage::CycleAdmissionPolicy),
+            KeyCode::Char('F') => Some(DownloadListMessage::ToggleFairShare),
+            KeyCode::Char(':') => Some(DownloadListMessage::JumpInput),
+            KeyCode::Char('b') => Some(DownloadListMessage::SaveTemplate),
+            KeyCode::Char('T') => Some(DownloadListMessage::OpenTemplates),
             _ => None,
         }
     }
@@ -376,19 +1541,28 @@ impl DownloadList {
         key: KeyEvent,
         widgets: &mut Vec<WidgetType>,
         finish_list: &mut FinishList,
+        undo: &mut UndoBuffer,
+        notices: &mut NoticeBoard,
     ) {
         let mut opt_message = self.get_key_message(key);
         while let Some(message) = opt_message {
-            opt_message = self.respond_to_message_inner(message, widgets, finish_list);
+            opt_message =
+                self.respond_to_message_inner(message, widgets, finish_list, undo, notices);
         }
     }
 
     // ------------------- HANDLE_ASYNC ----------------------
 
-    pub fn handle_async(&mut self, finish_list: &mut FinishList) {
-        if self.selected().is_none() && !self.list().is_empty() {
-            self.set_selected(Some(0));
-        }
+    pub fn handle_async(
+        &mut self,
+        finish_list: &mut FinishList,
+        widgets: &mut Vec<WidgetType>,
+        hosts: &mut HostStatsMap,
+        notices: &mut NoticeBoard,
+    ) {
+        self.inner.select_first_if_none();
+
+        self.submit_pending();
 
         let mut idx = 0;
         while idx < self.list().len() {
@@ -398,63 +1572,260 @@ impl DownloadList {
             // 导致panic。
 
             let listener = self.inner.get_item_mut(idx).unwrap();
+            listener.update_speed(&self.health, self.speed_half_life);
 
-            if listener.processed() {
+            if !listener.phase().is_active() {
                 idx += 1;
                 continue;
             }
+            listener.observe_phase();
+            push_prompt_dialogs(listener, widgets, &self.time);
+
+            self.transferred += listener.report_host_progress(hosts);
+
+            let Some(stage) = listener.receive_result() else {
+                idx += 1;
+                continue;
+            };
+            self.transferred += listener.report_host_result(hosts);
 
-            let (remove_hint, mark_processed, mark_stopped) =
-                if let Some(task_result) = listener.try_receive() {
-                    (
-                        matches!(
-                            task_result.final_stage,
-                            TaskFinalStage::UnknownUrl
-                                | TaskFinalStage::FailToConnection
-                                | TaskFinalStage::FailToCreateFile
-                                | TaskFinalStage::FileCorrupted
-                                | TaskFinalStage::Abort
-                                | TaskFinalStage::Finished
-                                | TaskFinalStage::UnknownError
-                        ),
-                        true,
-                        !matches!(task_result.final_stage, TaskFinalStage::Finished),
-                    )
-                } else {
-                    (false, false, false)
-                };
-
-            if mark_processed {
-                listener.mark_processed();
-            }
-            if mark_stopped {
-                listener.mark_stopped();
-            }
-            if remove_hint {
+            match (stage, listener.phase()) {
+                (StageKind::Finished, _) => {
+                    notices.push(format!("{} finished", listener.id()));
+                }
+                (StageKind::AlreadyExists, _) => {
+                    notices.push(format!("{} already downloaded", listener.id()));
+                }
+                (StageKind::Abort, _) | (_, ListenerPhase::Paused) => {}
+                _ => self.last_failure = Some(Instant::now()),
+            }
+            if listener.phase().is_terminal() {
                 self.move_to_finish_list(idx, finish_list);
                 // 直接continue，因为当前idx已经被移除，下一项已经移动到当前位置
                 continue;
             }
+            // 任务已经停止，不会再写入文件，此时才可以从头重新下载
+            if listener.phase() == ListenerPhase::Paused
+                && listener.pause_origin() == Some(PauseOrigin::Restart)
+            {
+                let id = listener.id();
+                let len = self.list().len();
+                if let Err(reason) = self.try_restart(idx, finish_list) {
+                    notices.push(format!("Cannot restart {}: {}", id, reason));
+                }
+                if self.list().len() < len {
+                    continue;
+                }
+            }
 
             idx += 1;
         }
+
+        self.poll_migration(notices);
+
+        if let Some((filepath, result)) = self.peek.poll() {
+            match result {
+                Ok(bytes) => widgets.push(WidgetType::new_peek_dialog(filepath, bytes)),
+                Err(e) => notices.push(peek::error_notice(&filepath, &e)),
+            }
+        }
+
+        if self.disk.refresh_if_stale() {
+            self.resume_waiting_for_space(notices);
+        }
+    }
+
+    /// 更新已经移动的任务的路径，同时显示进度
+    ///
+    /// 更新路径和清除移动标记都在UI线程中进行，因此恢复任务时不会看到移动到一半的状态。
+    fn poll_migration(&mut self, notices: &mut NoticeBoard) {
+        let Some(migration) = &self.migration else {
+            return;
+        };
+        loop {
+            let MoveResult {
+                job,
+                index,
+                total,
+                result,
+            } = match migration.try_recv() {
+                Ok(result) => result,
+                Err(std_mpsc::TryRecvError::Empty) => return,
+                Err(std_mpsc::TryRecvError::Disconnected) => break,
+            };
+            let name = job
+                .to
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if let Some(listener) = self.inner.find_task_mut(job.id) {
+                if result.is_ok() {
+                    listener.get_state_handler().lock().unwrap().filepath = job.to.clone();
+                }
+                listener.set_migrating(false);
+            }
+            match result {
+                Ok(()) => notices.push(format!("Moved {} {} ({}/{})", job.id, name, index, total)),
+                Err(e) => notices.push(format!(
+                    "Failed to move {} {}: {} ({}/{})",
+                    job.id, name, e, index, total
+                )),
+            }
+        }
+        self.migration = None;
+    }
+
+    /// 剩余空间足够时自动恢复因为空间不足而暂停的任务
+    ///
+    /// 每恢复一个任务就从剩余空间中减去它需要的空间，避免同时恢复太多任务再次写满磁盘。
+    /// 长度未知的任务需要[`TaskListener::UNKNOWN_SIZE_RESERVE`]的空间。
+    fn resume_waiting_for_space(&mut self, notices: &mut NoticeBoard) {
+        self.resume_waiting_for_space_with(notices, |dir| disk::available_space(dir).ok());
+    }
+
+    /// `available`返回目录所在文件系统的剩余空间
+    ///
+    /// 每个任务检查自己的保存目录，而不是默认的下载目录，每个目录只查询一次，
+    /// 恢复的任务需要的空间从所在目录的剩余空间中扣除。
+    fn resume_waiting_for_space_with(
+        &mut self,
+        notices: &mut NoticeBoard,
+        mut available: impl FnMut(&Path) -> Option<u64>,
+    ) {
+        let mut remaining: HashMap<PathBuf, Option<u64>> = HashMap::new();
+        for idx in 0..self.list().len() {
+            let listener = self.inner.get_item_mut(idx).unwrap();
+            let Some(required) = listener.waiting_for_space() else {
+                continue;
+            };
+            if listener.is_migrating() {
+                continue;
+            }
+            let dir = listener
+                .get_state_handler()
+                .lock()
+                .unwrap()
+                .filepath()
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .map_or_else(|| self.sender.download_dir().clone(), Path::to_path_buf);
+            let Some(space) = remaining
+                .entry(dir)
+                .or_insert_with_key(|dir| available(dir))
+            else {
+                continue;
+            };
+            if *space < required.saturating_add(self.disk_headroom) {
+                continue;
+            }
+            if listener.resume_task(&mut self.sender).is_err() {
+                // 通道已满或者已经关闭，下次检查时再试
+                break;
+            }
+            *space -= required;
+            let name = listener
+                .get_state_handler()
+                .lock()
+                .unwrap()
+                .filepath()
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            notices.push(format!(
+                "Disk space available, resumed {} {}",
+                listener.id(),
+                name
+            ));
+        }
+    }
+}
+
+/// 任务在等待用户决定如何处理已经存在的文件，或者确认是否下载匹配文件类型规则的文件时，
+/// 弹出对应的对话框，每次等待只弹出一次，见[`TaskListener::reopen_prompt`]
+fn push_prompt_dialogs(
+    listener: &mut TaskListener,
+    widgets: &mut Vec<WidgetType>,
+    time: &TimeConfig,
+) {
+    if let Some(conflict) = listener.take_conflict_prompt() {
+        widgets.push(WidgetType::new_conflict_dialog(
+            conflict,
+            listener.command_sender(),
+            time,
+        ));
+    }
+    if let Some((filepath, policy_match)) = listener.take_policy_prompt() {
+        widgets.push(WidgetType::new_policy_dialog(
+            filepath,
+            policy_match,
+            listener.command_sender(),
+        ));
     }
 }
 
 impl StatefulWidget for &mut DownloadList {
-    type State = bool; // focused
+    type State = DownloadListRenderState;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State)
     where
         Self: Sized,
     {
-        let empty_text_style = if *state {
+        // 第一行显示所有任务的速度以及当前的速度上限
+        let [header, area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        let header_line = if self.sender.is_connected() {
+            let free = self
+                .disk
+                .available()
+                .map_or(String::from("?"), common::get_human_readable_size);
+            let in_flight = self.sender.in_flight();
+            let speed = common::get_human_readable_size(self.display_speed());
+            let prefix = match self.speed_display {
+                SpeedDisplay::Instant => "",
+                SpeedDisplay::Smoothed => "~",
+            };
+            let fair = self
+                .throttle
+                .fair_status()
+                .map_or(String::new(), |status| format!(" [{}]", status));
+            let queue_eta = self
+                .queue_eta
+                .map_or(String::new(), |eta| format!(" | All done in {}", eta));
+            Line::from(vec![
+                Span::raw(format!(
+                    " Speed: {}{}/s | Speed limit: {}{} | Free: {} | {} ({}){} | ",
+                    prefix,
+                    speed,
+                    self.throttle.active(),
+                    fair,
+                    free,
+                    self.limits.occupancy(),
+                    self.admission_policy(),
+                    queue_eta
+                )),
+                Span::styled(
+                    format!(
+                        "Submitted: {}/{}",
+                        in_flight,
+                        sender::Sender::CHANNEL_CAPACITY
+                    ),
+                    DownloadList::channel_style(in_flight),
+                ),
+            ])
+        } else {
+            Line::from(" Starting background runtime...")
+        };
+        header_line
+            .style(DownloadList::HEADER_STYLE)
+            .render(header, buf);
+
+        let empty_text_style = if state.focused {
             Style::new().bg(Color::Gray).fg(Color::Black)
         } else {
             Style::new().fg(Color::White)
         };
 
         // 没有下载任务时，显示EMPTY
-        if self.list().is_empty() {
+        if self.inner.is_empty() {
             let text = "NO TASKS";
             let text_area = common::centered_text(text, area, 0, 0);
             Paragraph::new(text)
@@ -464,16 +1835,305 @@ impl StatefulWidget for &mut DownloadList {
             return;
         }
 
-        self.inner.render(area, buf, state);
+        self.inner.render(
+            area,
+            buf,
+            &mut DownloadListInnerRenderState::new(
+                state.focused,
+                self.middle_row,
+                state.symbols,
+                self.show_remaining,
+                self.speed_display,
+            ),
+        );
     }
 }
 
+/// 下载列表的整体状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadActivity {
+    /// 没有任务，或者任务还在连接中
+    Idle,
+    /// 至少有一个任务在最近一秒内接收到了数据
+    Active,
+    /// 所有任务都已暂停
+    Paused,
+}
+
 pub enum DownloadListMessage {
     GoUp,
     GoDown,
+    /// 翻页和跳到首尾时不会循环，停在第一项或者最后一项
+    PageUp,
+    PageDown,
+    GoTop,
+    GoBottom,
     AppendTaskInput,
-    AppendNewTask(String),
+    /// 输入任务编号，跳转到该任务
+    JumpInput,
+    AppendNewTask(RequestOptions),
+    EditTask,
     StopTask,
     ContinueTask,
+    /// 停止所有任务
+    StopAll,
+    /// 继续被批量停止的任务，按住Alt时继续所有停止的任务
+    ContinueAll {
+        everything: bool,
+    },
     CancelTask,
+    /// 丢弃已经下载的部分，从头重新下载选中的任务
+    RestartTask,
+    /// 加速选中的任务，或者取消加速
+    ToggleBoost,
+    ToggleMiddleRow,
+    /// 切换是否显示剩余的大小
+    ToggleRemaining,
+    /// 显示选中任务的详细信息，任务在等待用户回应时重新打开对应的对话框
+    ShowDetail,
+    /// 预览选中任务的文件开头的一部分，见[`FilePeek`]
+    PeekFile,
+    /// 使用相同的选项打开下载窗口，作为一个新的任务，原来的任务不受影响
+    CloneTask,
+    /// 输入名称，保存选中任务的URL和选项，见[`SavedDownload`]
+    SaveTemplate,
+    /// 打开保存的下载的列表
+    OpenTemplates,
+    /// 手动设置的速度上限，直到使用`=`清除之前优先于配置中的上限
+    LowerSpeedLimit,
+    RaiseSpeedLimit,
+    ClearSpeedLimit,
+    /// 开启或者关闭公平模式，见[`Throttle`]
+    ToggleFairShare,
+    /// 切换排队任务开始的顺序，见[`AdmissionPolicy`]
+    CycleAdmissionPolicy,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::config::Config;
+    use crate::app::sender::DownloadRequest;
+    use crate::app::task::{SlotPermit, TaskContext, TaskEvents, resolve};
+    use crate::app::throttle::SpeedLimit;
+    use crate::app::undo::UndoAction;
+
+    /// 所有任务都在排队的下载列表，唯一的名额被测试占用，返回发送任务的通道的接收端以及执行环境
+    async fn queued_list(
+        urls: &[&str],
+    ) -> (DownloadList, mpsc::Receiver<Task>, TaskContext, SlotPermit) {
+        let throttle = Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new()));
+        let limits = Arc::new(ConnectionLimits::new(1, 0, AdmissionPolicy::default()));
+        let config = Config {
+            max_concurrent_tasks: 1,
+            ..Config::default()
+        };
+        let ctx = TaskContext::new(
+            throttle.clone(),
+            limits.clone(),
+            TaskEvents::default(),
+            &config,
+        );
+        let blocker = limits.request(None, 0, false, Arc::new(Mutex::new(TaskState::new())));
+        let permit = blocker.wait().await;
+
+        let mut list = DownloadList::new(
+            Arc::new(RuntimeStats::new()),
+            throttle,
+            limits,
+            std::env::temp_dir(),
+            MiddleRowMode::default(),
+            0,
+            Vec::new(),
+        );
+        let (tx, rx) = mpsc::channel(16);
+        list.connect(tx, &Handle::current());
+        for url in urls {
+            list.append_normal_task(RequestOptions::new(url.to_string(), None));
+        }
+        (list, rx, ctx, permit)
+    }
+
+    /// 运行通道中所有排队的任务，直到它们因为停止指令结束，然后接收结果
+    async fn settle(list: &mut DownloadList, rx: &mut mpsc::Receiver<Task>, ctx: &TaskContext) {
+        let mut running = Vec::new();
+        while let Ok(task) = rx.try_recv() {
+            let ctx = ctx.clone();
+            running.push(tokio::spawn(async move {
+                resolve::handle_task(task, &ctx).await
+            }));
+        }
+        for task in running {
+            task.await.unwrap();
+        }
+        for idx in 0..list.list().len() {
+            list.inner.get_item_mut(idx).unwrap().receive_result();
+        }
+    }
+
+    fn origins(list: &DownloadList) -> Vec<(ListenerPhase, Option<PauseOrigin>)> {
+        list.list()
+            .iter()
+            .map(|listener| (listener.phase(), listener.pause_origin()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn continue_all_resumes_only_bulk_paused_tasks() {
+        let urls = [
+            "http://127.0.0.1:9/a.bin",
+            "http://127.0.0.1:9/b.bin",
+            "http://127.0.0.1:9/c.bin",
+        ];
+        let (mut list, mut rx, ctx, _permit) = queued_list(&urls).await;
+        let mut finish_list = FinishList::new();
+        // 第二个任务先被单独停止
+        list.stop_task(1).unwrap();
+        let stopped = list.stop_all();
+        assert_eq!(stopped.done_count(), 2);
+        assert_eq!(stopped.skipped("already stopping"), 1);
+        settle(&mut list, &mut rx, &ctx).await;
+        assert_eq!(
+            origins(&list),
+            vec![
+                (ListenerPhase::Paused, Some(PauseOrigin::BulkPause)),
+                (ListenerPhase::Paused, Some(PauseOrigin::User)),
+                (ListenerPhase::Paused, Some(PauseOrigin::BulkPause)),
+            ]
+        );
+
+        let resumed = list.continue_all(false, &mut finish_list);
+        assert_eq!(resumed.done_count(), 2);
+        assert_eq!(resumed.skipped("paused individually"), 1);
+        assert_eq!(list.list()[1].phase(), ListenerPhase::Paused);
+        // 排队期间被停止的任务还没有开始，作为新的任务重新发送
+        let mut sent = Vec::new();
+        while let Ok(task) = rx.try_recv() {
+            match task.request() {
+                DownloadRequest::Normal { url, .. } => sent.push(url.clone()),
+                request => panic!("task that never started was sent as {:?}", request),
+            }
+        }
+        assert_eq!(sent, vec![urls[0].to_string(), urls[2].to_string()]);
+
+        let resumed = list.continue_all(true, &mut finish_list);
+        assert_eq!(resumed.done_count(), 1);
+        assert_eq!(resumed.skipped("already running"), 2);
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn stop_all_keeps_individual_origin_and_records_it() {
+        let urls = ["http://127.0.0.1:9/a.bin", "http://127.0.0.1:9/b.bin"];
+        let (mut list, mut rx, ctx, _permit) = queued_list(&urls).await;
+        list.stop_task(0).unwrap();
+        let stopped = list.stop_all();
+        assert_eq!(stopped.done_count(), 1);
+        assert_eq!(stopped.skipped("already stopping"), 1);
+        settle(&mut list, &mut rx, &ctx).await;
+        // 再次批量停止时，已经暂停的任务保持原来的原因
+        assert_eq!(list.stop_all().skipped("already paused"), 2);
+
+        // 停止的原因写入退出摘要中的任务记录
+        let records: Vec<_> = list.list().iter().map(TaskListener::to_record).collect();
+        assert_eq!(records[0].pause_origin, Some(PauseOrigin::User));
+        assert_eq!(records[1].pause_origin, Some(PauseOrigin::BulkPause));
+        let json = serde_json::to_string(&records[1]).unwrap();
+        assert!(json.contains(r#""pause_origin":"bulk_pause""#), "{}", json);
+        let parsed: crate::app::record::TaskRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.pause_origin, Some(PauseOrigin::BulkPause));
+    }
+
+    /// 选中的行对应的任务编号
+    fn selected_id(list: &DownloadList) -> Option<TaskId> {
+        match list.selected_row()? {
+            DownloadRowIndex::Pending(index) => Some(list.pending()[index].id),
+            DownloadRowIndex::Task(index) => Some(list.list()[index].id()),
+        }
+    }
+
+    #[tokio::test]
+    async fn selection_follows_submitted_tasks() {
+        for selected in 0..3 {
+            let mut list = DownloadList::new(
+                Arc::new(RuntimeStats::new()),
+                Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+                Arc::new(ConnectionLimits::unlimited()),
+                std::env::temp_dir(),
+                MiddleRowMode::default(),
+                0,
+                Vec::new(),
+            );
+            // 后台运行时还没有启动，任务都在等待队列中
+            for name in ["a", "b", "c"] {
+                let url = format!("http://127.0.0.1:9/{}.bin", name);
+                list.append_normal_task(RequestOptions::new(url, None));
+            }
+            assert_eq!(list.pending().len(), 3);
+            list.set_selected(Some(selected));
+            let id = selected_id(&list);
+
+            let (tx, _rx) = mpsc::channel(16);
+            list.connect(tx, &Handle::current());
+            assert!(list.pending().is_empty());
+            assert_eq!(selected_id(&list), id);
+        }
+    }
+
+    #[tokio::test]
+    async fn undo_abort_keeps_the_task_id() {
+        let urls = ["http://127.0.0.1:9/a.bin", "http://127.0.0.1:9/b.bin"];
+        let (mut list, mut rx, ctx, _permit) = queued_list(&urls).await;
+        let mut finish_list = FinishList::new();
+        let mut undo = UndoBuffer::new();
+        list.stop_all();
+        settle(&mut list, &mut rx, &ctx).await;
+        let id = list.list()[0].id();
+
+        list.abort_task(0, &mut finish_list, &mut undo).unwrap();
+        assert!(list.list().iter().all(|listener| listener.id() != id));
+        let Some(UndoAction::Abort {
+            id: undone,
+            state,
+            url,
+        }) = undo.take()
+        else {
+            panic!("abort was not recorded");
+        };
+        assert_eq!(undone, id);
+
+        list.undo_abort(undone, state, url, &mut finish_list);
+        assert_eq!(list.list().last().unwrap().id(), id);
+        let task = rx.try_recv().unwrap();
+        assert_eq!(task.id(), id);
+    }
+
+    #[tokio::test]
+    async fn waiting_tasks_check_their_own_directory() {
+        let (mut list, mut rx, _ctx, _permit) = queued_list(&[]).await;
+        let full = PathBuf::from("/mnt/full");
+        let roomy = PathBuf::from("/mnt/roomy");
+        for (id, dir) in [(1, &full), (2, &roomy), (3, &roomy)] {
+            let mut state = TaskState::new();
+            state.filepath = dir.join(format!("{}.bin", id));
+            state.content_length = Some(600);
+            list.push_fake(TaskListener::fake_waiting_for_space(TaskId::new(id), state));
+        }
+
+        // 默认下载目录的空间与这些任务无关，第二个任务恢复之后剩余的空间不够第三个任务使用
+        let mut queried = Vec::new();
+        list.resume_waiting_for_space_with(&mut NoticeBoard::new(), |dir| {
+            queried.push(dir.to_path_buf());
+            Some(if dir == full { 100 } else { 1000 })
+        });
+        assert_eq!(queried, [full, roomy]);
+        let waiting: Vec<_> = list
+            .list()
+            .iter()
+            .map(|listener| listener.waiting_for_space())
+            .collect();
+        assert_eq!(waiting, [Some(600), None, Some(600)]);
+        assert_eq!(rx.try_recv().unwrap().id(), TaskId::new(2));
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -1,27 +1,105 @@
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use ratatui::crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::*;
 use ratatui::style::palette::tailwind;
 use ratatui::widgets::{Gauge, Paragraph, Widget};
+use tokio::runtime::Handle;
 use url::Url;
 
 use crate::app::App;
+use crate::app::checksum::{Checksum, ExpectedChecksum};
+use crate::app::disk::ExistenceCheck;
+use crate::app::export::{ExportFormat, ExportJob};
+use crate::app::links::LinkScan;
+use crate::app::network::NetworkOptions;
+use crate::app::notice::NoticeBoard;
+use crate::app::opener::{self, FileCategory};
+use crate::app::peek::{self, FilePeek};
+use crate::app::policy;
+use crate::app::record::TaskRecord;
+use crate::app::sender::RequestOptions;
+use crate::app::task::{self, CompletedCopy, StageKind, TaskId, TaskResult, TaskState};
+use crate::app::timefmt::{self, TimeConfig};
+use crate::app::timespan::TimeSpan;
+use crate::app::undo::UndoBuffer;
 use crate::window::WidgetType;
-use crate::window::common::{self, Fill, VerticalList, VerticalListItem};
+use crate::window::app::download::DownloadList;
+use crate::window::common::{self, Fill, FooterColumns, ItemList, SymbolSet};
+use crate::window::dialog::{DetailDialog, JumpTarget};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FinishState {
     Success,
     Failure,
 }
 
+/// 添加到完成列表的任务与保存到同一路径的已有任务之间的合并方式，见[`merge_policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// 两者无关，直接添加到列表末尾
+    Append,
+    /// 新任务是失败任务的重试，替换原来的项并记录重试次数
+    Replace,
+    /// 同一个文件被重新下载，保留两者，将原来的项标记为已被取代
+    Supersede,
+}
+
+/// 根据两个保存到同一路径的任务的状态以及请求的URL是否相同，决定如何合并
+///
+/// 同一URL先失败后成功时视为重试，替换原来失败的项；两者都成功时视为重新下载。
+/// 其他情况下两项都保留。
+pub fn merge_policy(old: FinishState, new: FinishState, same_url: bool) -> MergePolicy {
+    match (old, new) {
+        (FinishState::Failure, FinishState::Success) if same_url => MergePolicy::Replace,
+        (FinishState::Success, FinishState::Success) => MergePolicy::Supersede,
+        _ => MergePolicy::Append,
+    }
+}
+
 pub struct FinishedTask {
     state: FinishState,
     filepath: PathBuf,
     url: Option<Url>,
     content_length: Option<u64>,
     downloaded: u64,
+    result: Option<TaskResult>,
+    duration: Duration,
+    // 创建任务时的选项，用于复制任务
+    options: Option<Arc<RequestOptions>>,
+    // 自动解压成功时解压到的目录
+    extracted: Option<PathBuf>,
+    // 链接或者复制到`completed_dir`的结果
+    completed: Option<CompletedCopy>,
+    // 创建HTTP客户端时使用的网络设置
+    network: Option<Arc<NetworkOptions>>,
+    // 替换掉的失败任务的数量，见[`MergePolicy::Replace`]
+    retries: u32,
+    // 之后同一文件被重新下载
+    superseded: bool,
+    // 本次运行中的任务编号，从上次会话恢复的记录没有编号
+    id: Option<TaskId>,
+    // 下载完成后用于校验的校验和
+    checksum: Option<ExpectedChecksum>,
+    // 校验通过时文件实际的校验和
+    verified_checksum: Option<Checksum>,
+    // 下载期间的最高速度（B/s）
+    peak_speed: Option<u64>,
+    // 最近一次请求的TTFB
+    ttfb: Option<Duration>,
+    // 响应的Content-Type，HTML页面可以从中查找真正的下载链接
+    content_type: Option<String>,
+    // 最近一次检查时文件已经不存在，见[`ExistenceCheck`]
+    //
+    // [`ExistenceCheck`]: crate::app::disk::ExistenceCheck
+    missing: bool,
+    // 加入完成列表的时间，用于自动清除下载成功的任务
+    finished_at: Instant,
+    // 加入完成列表时的系统时间，用于显示
+    finished_time: SystemTime,
 }
 
 impl FinishedTask {
@@ -47,6 +125,8 @@ impl FinishedTask {
         url: Option<Url>,
         content_length: Option<u64>,
         downloaded: u64,
+        result: Option<TaskResult>,
+        duration: Duration,
     ) -> Self {
         FinishedTask {
             state,
@@ -54,21 +134,234 @@ impl FinishedTask {
             url,
             content_length,
             downloaded,
+            result,
+            duration,
+            options: None,
+            extracted: None,
+            completed: None,
+            network: None,
+            retries: 0,
+            superseded: false,
+            id: None,
+            checksum: None,
+            verified_checksum: None,
+            peak_speed: None,
+            ttfb: None,
+            content_type: None,
+            missing: false,
+            finished_at: Instant::now(),
+            finished_time: SystemTime::now(),
+        }
+    }
+
+    pub fn with_options(mut self, options: Option<Arc<RequestOptions>>) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_extracted(mut self, extracted: Option<PathBuf>) -> Self {
+        self.extracted = extracted;
+        self
+    }
+
+    pub fn with_completed(mut self, completed: Option<CompletedCopy>) -> Self {
+        self.completed = completed;
+        self
+    }
+
+    pub fn with_network(mut self, network: Option<Arc<NetworkOptions>>) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn with_id(mut self, id: TaskId) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: Option<ExpectedChecksum>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn with_verified_checksum(mut self, checksum: Option<Checksum>) -> Self {
+        self.verified_checksum = checksum;
+        self
+    }
+
+    pub fn with_peak_speed(mut self, peak_speed: Option<u64>) -> Self {
+        self.peak_speed = peak_speed;
+        self
+    }
+
+    pub fn with_ttfb(mut self, ttfb: Option<Duration>) -> Self {
+        self.ttfb = ttfb;
+        self
+    }
+
+    pub fn with_content_type(mut self, content_type: Option<String>) -> Self {
+        self.content_type = content_type;
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn state(&self) -> FinishState {
+        self.state
+    }
+
+    pub fn filepath(&self) -> &Path {
+        &self.filepath
+    }
+
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    pub fn result(&self) -> Option<&TaskResult> {
+        self.result.as_ref()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn extracted(&self) -> Option<&Path> {
+        self.extracted.as_deref()
+    }
+
+    pub fn completed(&self) -> Option<&CompletedCopy> {
+        self.completed.as_ref()
+    }
+
+    pub fn peak_speed(&self) -> Option<u64> {
+        self.peak_speed
+    }
+
+    pub fn ttfb(&self) -> Option<Duration> {
+        self.ttfb
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn finished_time(&self) -> SystemTime {
+        self.finished_time
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn is_superseded(&self) -> bool {
+        self.superseded
+    }
+
+    /// 下载目录中已经有相同的文件，任务直接使用了该文件
+    pub fn is_existing(&self) -> bool {
+        self.result
+            .as_ref()
+            .is_some_and(|result| result.kind() == StageKind::AlreadyExists)
+    }
+
+    pub fn id(&self) -> Option<TaskId> {
+        self.id
+    }
+
+    /// 下载成功的文件在之后被移动或者删除
+    pub fn is_missing(&self) -> bool {
+        self.missing
+    }
+
+    /// 用户请求的URL，与重定向之后的最终URL可能不同
+    fn requested_url(&self) -> Option<&str> {
+        self.options
+            .as_deref()
+            .map(|options| options.url.as_str())
+            .or_else(|| self.url.as_ref().map(Url::as_str))
+    }
+
+    /// 复制该任务时使用的选项，没有记录选项时只使用最终的URL
+    pub fn request_options(&self) -> Option<RequestOptions> {
+        self.options
+            .as_deref()
+            .cloned()
+            .or_else(|| Some(RequestOptions::new(self.url.as_ref()?.to_string(), None)))
+    }
+
+    /// 重新校验文件时使用的任务状态，只有下载成功并且有校验和的任务可以重新校验
+    pub fn verify_state(&self) -> Option<TaskState> {
+        if self.state != FinishState::Success {
+            return None;
+        }
+        let expected = self.checksum.as_ref()?;
+        let mut state = TaskState::new();
+        state.url = self.url.clone();
+        state.filepath = self.filepath.clone();
+        state.content_length = self.content_length.or(Some(self.downloaded));
+        state.downloaded = self.downloaded;
+        state.options = self.options.clone();
+        state.network = self.network.clone();
+        // 校验和来自远程的校验和文件，或者从上次会话恢复的记录没有创建任务时的选项
+        if state.expected_checksum().as_ref() != Some(expected) {
+            state.remote_checksum = Some(expected.checksum.clone());
         }
+        Some(state)
+    }
+
+    // ------------------ TYPE_CONVERSION -------------------
+
+    pub fn to_record(&self) -> TaskRecord {
+        let stage = match (&self.result, self.state) {
+            (Some(result), _) => result.kind(),
+            (None, FinishState::Success) => StageKind::Finished,
+            (None, FinishState::Failure) => StageKind::UnknownError,
+        };
+        TaskRecord::new(
+            self.url.as_ref().map(Url::to_string),
+            stage,
+            self.filepath.clone(),
+            self.downloaded,
+            self.content_length,
+            self.duration,
+        )
+        .with_peak_speed(self.peak_speed)
+        .with_ttfb(self.ttfb)
+        // 只记录校验通过的文件实际的校验和，校验失败时期望的校验和与文件不符
+        .with_checksum(self.verified_checksum.as_ref().map(Checksum::to_string))
+        .with_finished_at(self.finished_time)
     }
 }
 
 #[derive(Debug, Clone, Copy)]
-pub struct FinishedTaskRenderState {
+pub struct FinishedTaskRenderState<'a> {
     pub page_focused: bool,
     pub selected: bool,
+    pub time: &'a TimeConfig,
+    pub symbols: SymbolSet,
 }
 
-impl FinishedTaskRenderState {
-    pub fn new(page_focused: bool, selected: bool) -> Self {
+impl<'a> FinishedTaskRenderState<'a> {
+    pub fn new(
+        page_focused: bool,
+        selected: bool,
+        time: &'a TimeConfig,
+        symbols: SymbolSet,
+    ) -> Self {
         FinishedTaskRenderState {
             page_focused,
             selected,
+            time,
+            symbols,
         }
     }
 }
@@ -76,11 +369,13 @@ impl FinishedTaskRenderState {
 /// 对于完成的任务，渲染的内容也大致与正在进行的任务类似，见[`TaskState`]：
 /// <filename>
 /// <process bar> <percentage>%
-///       <downloaded> / <size>
+/// <error brief>   <downloaded>/<size> │ <average speed> │ <finished time>
+///
+/// 最后一行右侧的各列与下载列表一样对齐，见[`FooterColumns`]。
 ///
 /// [`TaskState`]: crate::app::task::TaskState
-impl StatefulWidget for &FinishedTask {
-    type State = FinishedTaskRenderState;
+impl<'a> StatefulWidget for &'a FinishedTask {
+    type State = FinishedTaskRenderState<'a>;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         let highlight_color = if state.page_focused {
             FinishedTask::FOCUSED_HIGHTLIGHT_COLOR
@@ -115,14 +410,41 @@ impl StatefulWidget for &FinishedTask {
         ])
         .split(bar)[1];
 
-        // 文件名
-        Paragraph::new(self.filepath.to_string_lossy())
-            .style(text_style)
+        // 文件名，以及合并重复项时的标注
+        let filename = self.filepath.to_string_lossy();
+        let mut name = common::numbered_line(self.id, common::display_sanitize(&filename)).spans;
+        if self.retries > 0 {
+            name.push(Span::raw(format!(" (retried {}x)", self.retries)));
+        }
+        if self.superseded {
+            name.push(Span::raw(" (superseded)"));
+        }
+        if self.is_existing() {
+            name.push(Span::raw(" (existing)").fg(Color::Cyan));
+        }
+        if let Some(range) = self.options.as_deref().and_then(|options| options.range) {
+            name.push(Span::raw(format!(" (bytes {})", range)).fg(Color::Cyan));
+        }
+        if self
+            .completed
+            .as_ref()
+            .is_some_and(CompletedCopy::is_failed)
+        {
+            name.push(Span::raw(" (completed copy failed)").fg(Color::Yellow));
+        }
+        let name_style = if self.missing {
+            name.push(Span::raw(" (missing)"));
+            text_style.add_modifier(Modifier::DIM)
+        } else {
+            text_style
+        };
+        Paragraph::new(Line::from(name))
+            .style(name_style)
             .left_aligned()
             .render(text, buf);
 
         // 进度条和其他信息
-        match self.content_length {
+        let size_text = match self.content_length {
             Some(total) => {
                 let percentage = (if total == 0 {
                     100.0
@@ -141,14 +463,11 @@ impl StatefulWidget for &FinishedTask {
                     .use_unicode(true)
                     .render(bar, buf);
 
-                Paragraph::new(format!(
-                    "{} / {}",
+                format!(
+                    "{}/{}",
                     common::get_human_readable_size(self.downloaded),
                     common::get_human_readable_size(total)
-                ))
-                .style(text_style)
-                .right_aligned()
-                .render(footer, buf);
+                )
             }
             None => {
                 Gauge::default()
@@ -162,16 +481,58 @@ impl StatefulWidget for &FinishedTask {
                     .use_unicode(true)
                     .render(bar, buf);
 
-                Paragraph::new(format!(
-                    "{} / {}",
-                    common::get_human_readable_size(self.downloaded),
-                    "Unknown"
-                ))
-                .style(text_style)
-                .right_aligned()
-                .render(footer, buf);
+                format!("{} / --", common::get_human_readable_size(self.downloaded))
             }
+        };
+
+        // 整个任务的平均速度，与正在下载的任务的速度在同一列
+        let speed = match self.duration.as_secs_f64() {
+            secs if secs > 0.0 => format!(
+                "{}/s",
+                common::get_human_readable_size((self.downloaded as f64 / secs) as u64)
+            ),
+            _ => String::from("-- B/s"),
+        };
+        let speed = if state.selected {
+            Span::from(speed)
+        } else {
+            Span::from(speed).fg(Color::LightCyan)
+        };
+        let columns = FooterColumns::new(state.symbols.separator)
+            .with_column(size_text, FooterColumns::SIZE_WIDTH)
+            .with_column(speed, FooterColumns::SPEED_WIDTH)
+            .with_column(
+                timefmt::format_time(self.finished_time, state.time),
+                state.time.max_width() as u16,
+            );
+        let [brief_area, size_area] = columns.split(footer);
+
+        // 失败的任务在左侧显示错误摘要，完整的信息在详情弹窗中查看。
+        // 成功的任务也可能附带说明，例如自动解压失败
+        match (self.state, &self.result) {
+            (FinishState::Failure, Some(result)) => {
+                Paragraph::new(task::format_error_brief(result, brief_area.width as usize))
+                    .style(text_style)
+                    .left_aligned()
+                    .render(brief_area, buf);
+            }
+            (FinishState::Success, Some(result)) if result.message().is_some() => {
+                let style = if state.selected {
+                    text_style
+                } else {
+                    text_style.fg(Color::Yellow)
+                };
+                Paragraph::new(task::format_error_brief(result, brief_area.width as usize))
+                    .style(style)
+                    .left_aligned()
+                    .render(brief_area, buf);
+            }
+            _ => {}
         }
+
+        Paragraph::new(columns.into_line(size_area.width))
+            .style(text_style)
+            .render(size_area, buf);
     }
 }
 
@@ -180,11 +541,27 @@ impl StatefulWidget for &FinishedTask {
 /// <process bar> <percentage>%
 ///       <downloaded> / <size>
 /// ---------------------------
-/// [`DownloadList`]: crate::window::app::download::DownloadList
 pub struct FinishList {
     list: Vec<FinishedTask>,
-    selected: Option<usize>,
-    scroll: usize,
+    view: ItemList,
+    // 配置中每种类别的文件可以选择的程序
+    open_with: BTreeMap<FileCategory, Vec<String>>,
+    // 本次运行中每种类别最近一次使用的程序
+    last_open_with: HashMap<FileCategory, String>,
+    // 检查下载成功的文件是否仍然存在
+    existence: ExistenceCheck,
+    // 在后台读取选中任务的文件开头，用于预览
+    peek: FilePeek,
+    // 下载成功的任务保留的时间，见配置中的`auto_clear_finished_after`
+    auto_clear: TimeSpan,
+    // 显示完成时间的方式
+    time: TimeConfig,
+    // 自动清除的任务的记录，退出时仍然包含在摘要中
+    archived: Vec<TaskRecord>,
+    // 在后台将列表导出为CSV或者Markdown表格
+    export: ExportJob,
+    // 在后台从下载到的HTML页面中查找链接
+    links: LinkScan,
 }
 
 impl Default for FinishList {
@@ -197,6 +574,7 @@ impl FinishList {
     // ------------------- CONSTANT -----------------------
 
     pub const NOT_ENOUGH_SPACE_BG: Style = Style::new().bg(Color::DarkGray);
+    pub const HEADER_STYLE: Style = Style::new().fg(Color::Gray);
     pub const RENDER_ITEM_HEIGHT: u16 = FinishedTask::RENDER_HEIGHT;
 
     // -------------------- CONSTRUCT ----------------------
@@ -204,82 +582,229 @@ impl FinishList {
     pub fn new() -> Self {
         FinishList {
             list: Vec::new(),
-            selected: None,
-            scroll: 0,
+            view: ItemList::new(Self::RENDER_ITEM_HEIGHT),
+            open_with: BTreeMap::new(),
+            last_open_with: HashMap::new(),
+            existence: ExistenceCheck::new(),
+            peek: FilePeek::new(),
+            auto_clear: TimeSpan::OFF,
+            time: TimeConfig::default(),
+            archived: Vec::new(),
+            export: ExportJob::new(),
+            links: LinkScan::new(),
         }
     }
 
+    pub fn with_open_with(mut self, open_with: BTreeMap<FileCategory, Vec<String>>) -> Self {
+        self.open_with = open_with;
+        self
+    }
+
+    pub fn with_auto_clear(mut self, auto_clear: TimeSpan) -> Self {
+        self.auto_clear = auto_clear;
+        self
+    }
+
+    pub fn with_time_config(mut self, time: TimeConfig) -> Self {
+        self.time = time;
+        self
+    }
+
     // ------------------ MEMBER_ACCESS --------------------
 
+    pub fn list(&self) -> &Vec<FinishedTask> {
+        &self.list
+    }
+
     pub fn selected(&self) -> Option<usize> {
-        self.selected
+        self.view.selected()
+    }
+
+    /// 本次运行中自动清除的任务，按照清除的顺序
+    pub fn archived(&self) -> &[TaskRecord] {
+        &self.archived
     }
 
     pub fn scroll(&self) -> usize {
-        self.scroll
+        self.view.scroll()
     }
 
     // -------------------- MODIFIER -----------------------
 
     pub fn set_selected(&mut self, index: Option<usize>) {
-        self.selected = index;
+        self.view.set_selected(index);
     }
 
     pub fn scroll_to(&mut self, scroll: usize) {
-        self.scroll = scroll;
+        self.view.scroll_to(scroll);
     }
 
-    // --------------------- FUNCTION ----------------------
+    pub fn remember_open_with(&mut self, category: FileCategory, command: String) {
+        self.last_open_with.insert(category, command);
+    }
 
-    pub fn select_next(&mut self) {
-        if self.list.is_empty() {
-            self.selected = None;
-            return;
-        }
+    /// 后台运行时启动之后开始检查文件是否存在，以及可以预览文件
+    pub fn set_runtime(&mut self, runtime: Handle) {
+        self.existence.set_runtime(runtime.clone());
+        self.peek.set_runtime(runtime.clone());
+        self.export.set_runtime(runtime.clone());
+        self.links.set_runtime(runtime);
+    }
+
+    /// 在后台将列表中的所有任务以`format`写入`path`，后台运行时还没有启动时返回`false`
+    pub fn start_export(&mut self, path: PathBuf, format: ExportFormat) -> bool {
+        let records = self.list.iter().map(FinishedTask::to_record).collect();
+        self.export.start(path, format, records)
+    }
 
-        match self.selected {
-            Some(i) => {
-                self.selected = Some((i + 1) % self.list.len());
+    /// 根据检查的结果更新下载成功的任务的文件是否存在，返回新发现不存在的文件数量
+    ///
+    /// 不在`results`中的任务（例如检查开始之后才完成的任务）保持不变。
+    pub fn apply_existence(&mut self, results: &HashMap<PathBuf, bool>) -> usize {
+        let mut newly_missing = 0;
+        for task in &mut self.list {
+            if task.state != FinishState::Success {
+                continue;
             }
-            None => {
-                self.selected = Some(0);
+            if let Some(&exists) = results.get(&task.filepath) {
+                if !exists && !task.missing {
+                    log::info!(target:"App", "Finished file is missing: {}", task.filepath.display());
+                    newly_missing += 1;
+                }
+                task.missing = !exists;
             }
         }
+        newly_missing
+    }
+
+    // --------------------- FUNCTION ----------------------
+
+    pub fn select_next(&mut self) {
+        self.view.select_next(self.list.len());
     }
 
     pub fn select_previous(&mut self) {
-        if self.list.is_empty() {
-            self.selected = None;
-            return;
-        }
+        self.view.select_previous(self.list.len());
+    }
 
-        let len = self.list.len();
+    pub fn select_page_down(&mut self) {
+        self.view.select_page_down(self.list.len());
+    }
 
-        match self.selected {
-            Some(i) => {
-                if i == 0 || i >= len {
-                    self.selected = Some(len - 1);
-                } else {
-                    self.selected = Some(i - 1);
+    pub fn select_page_up(&mut self) {
+        self.view.select_page_up(self.list.len());
+    }
+
+    pub fn select_first(&mut self) {
+        self.view.select_first(self.list.len());
+    }
+
+    pub fn select_last(&mut self) {
+        self.view.select_last(self.list.len());
+    }
+
+    /// 选中编号为`id`的任务，找不到时返回`false`
+    pub fn select_task(&mut self, id: TaskId) -> bool {
+        match self.list.iter().position(|t| t.id == Some(id)) {
+            Some(idx) => {
+                self.view.jump_to(idx, self.list.len());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 添加任务，已有保存到同一路径的任务时根据[`merge_policy`]合并
+    ///
+    /// 替换时新任务占据原来的位置，因此选中的项仍然有效。
+    pub fn push_task(&mut self, mut task: FinishedTask) {
+        if let Some(old) = self
+            .list
+            .iter_mut()
+            .rfind(|t| !t.superseded && t.filepath == task.filepath)
+        {
+            let same_url = old.requested_url() == task.requested_url();
+            match merge_policy(old.state, task.state, same_url) {
+                MergePolicy::Replace => {
+                    task.retries = old.retries + 1;
+                    *old = task;
+                    return;
                 }
+                MergePolicy::Supersede => old.superseded = true,
+                MergePolicy::Append => {}
             }
-            None => {
-                self.selected = Some(0);
+        }
+        self.list.push(task);
+    }
+
+    pub fn insert_task(&mut self, index: usize, task: FinishedTask) {
+        let index = index.min(self.list.len());
+        self.list.insert(index, task);
+        self.view.set_selected(Some(index));
+    }
+
+    pub fn remove_task(&mut self, index: usize) -> FinishedTask {
+        let task = self.list.remove(index);
+        self.view.on_removed(index, self.list.len());
+        task
+    }
+
+    /// 删除最近一个保存到`path`的任务
+    pub fn remove_task_by_path(&mut self, path: &Path) -> Option<FinishedTask> {
+        let index = self.list.iter().rposition(|t| t.filepath == path)?;
+        Some(self.remove_task(index))
+    }
+
+    /// 移除加入列表超过`auto_clear`的下载成功的任务，记录保存在`archived`中，
+    /// 返回移除的数量。失败的任务需要用户处理，不会被移除
+    pub fn clear_expired(&mut self, now: Instant) -> usize {
+        let Some(keep) = self.auto_clear.duration() else {
+            return 0;
+        };
+        let mut cleared = 0;
+        let mut idx = 0;
+        while idx < self.list.len() {
+            let task = &self.list[idx];
+            if task.state != FinishState::Success
+                || now.saturating_duration_since(task.finished_at) < keep
+            {
+                idx += 1;
+                continue;
             }
+            // remove_task同时调整选中项和滚动距离
+            let task = self.remove_task(idx);
+            log::debug!(target:"App", "Auto-cleared finished task: {}", task.filepath.display());
+            self.archived.push(task.to_record());
+            cleared += 1;
         }
+        cleared
     }
 
-    pub fn push_task(&mut self, task: FinishedTask) {
-        self.list.push(task);
+    /// 需要检查是否存在的文件，即下载成功的任务的保存路径
+    fn existence_paths(list: &[FinishedTask]) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = list
+            .iter()
+            .filter(|t| t.state == FinishState::Success)
+            .map(|t| t.filepath.clone())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
     }
 
-    fn fit_to_screen(&mut self, area_height: u16) {
-        let total_height =
-            (self.list.len() * (Self::RENDER_ITEM_HEIGHT as usize + 1)).saturating_sub(1);
-        self.scroll_to(
-            self.scroll()
-                .min(total_height.saturating_sub(area_height as usize)),
-        );
+    pub fn take_all(&mut self) -> Vec<FinishedTask> {
+        self.view.set_selected(None);
+        self.view.scroll_to(0);
+        std::mem::take(&mut self.list)
+    }
+
+    /// 将之前清空的任务放回列表的前面
+    pub fn restore_tasks(&mut self, tasks: Vec<FinishedTask>) {
+        let count = tasks.len();
+        self.list.splice(0..0, tasks);
+        if let Some(selected) = self.view.selected() {
+            self.view.set_selected(Some(selected + count));
+        }
     }
 
     // ------------------- HANDLE_MESSAGE ----------------------
@@ -288,16 +813,164 @@ impl FinishList {
         app: &mut App,
         message: FinishListMessage,
     ) -> Option<FinishListMessage> {
-        let (_, widgets, this_widget) = app.destruct_data();
-        this_widget.respond_to_message_inner(message, widgets)
+        let (downloading, widgets, this_widget, undo, notices) = app.destruct_data();
+        this_widget.respond_to_message_inner(message, widgets, downloading, undo, notices)
     }
 
     fn respond_to_message_inner(
         &mut self,
         message: FinishListMessage,
-        _widgets: &mut Vec<WidgetType>,
+        widgets: &mut Vec<WidgetType>,
+        downloading: &mut DownloadList,
+        undo: &mut UndoBuffer,
+        notices: &mut NoticeBoard,
     ) -> Option<FinishListMessage> {
         match message {
+            FinishListMessage::DeleteFile => {
+                if let Some(index) = self.selected().filter(|&idx| idx < self.list.len()) {
+                    if self.list[index].missing {
+                        notices.push(String::from("File is missing, press r to check again"));
+                        return None;
+                    }
+                    let task = self.remove_task(index);
+                    undo.push_delete_finished(index, task);
+                }
+                None
+            }
+            FinishListMessage::RefreshExistence => {
+                self.existence.refresh();
+                notices.push(String::from("Checking finished files..."));
+                None
+            }
+            FinishListMessage::PeekFile => {
+                if let Some(task) = self.selected().and_then(|idx| self.list.get(idx))
+                    && !self.peek.start(task.filepath.clone())
+                {
+                    notices.push(String::from("Background runtime is not ready"));
+                }
+                None
+            }
+            FinishListMessage::VerifyFile => {
+                let task = self.selected().and_then(|idx| self.list.get(idx))?;
+                if task.missing {
+                    notices.push(String::from("File is missing, press r to check again"));
+                    return None;
+                }
+                match task.verify_state() {
+                    Some(state) => downloading.verify_existing(state, notices),
+                    None => notices.push(String::from("No checksum to verify against")),
+                }
+                None
+            }
+            FinishListMessage::ScanLinks => {
+                let task = self.selected().and_then(|idx| self.list.get(idx))?;
+                if task.missing {
+                    notices.push(String::from("File is missing, press r to check again"));
+                    return None;
+                }
+                let Some(page) = task.url.clone() else {
+                    notices.push(String::from("No URL to resolve links against"));
+                    return None;
+                };
+                match task.content_type.as_deref() {
+                    Some(content_type) if !policy::is_html_mime(content_type) => {
+                        notices.push(format!("Not an HTML page ({})", content_type));
+                    }
+                    // 没有记录Content-Type时在后台检查文件内容
+                    content_type => {
+                        if !self
+                            .links
+                            .start(task.filepath.clone(), page, content_type.is_none())
+                        {
+                            notices.push(String::from("Background runtime is not ready"));
+                        }
+                    }
+                }
+                None
+            }
+            FinishListMessage::Export => {
+                if self.list.is_empty() {
+                    notices.push(String::from("No finished tasks to export"));
+                } else {
+                    widgets.push(WidgetType::new_export_dialog(self.list.len()));
+                }
+                None
+            }
+            FinishListMessage::ClearAll => {
+                if !self.list.is_empty() {
+                    undo.push_clear_finished(self.take_all());
+                }
+                None
+            }
+            FinishListMessage::ShowDetail => {
+                if let Some(task) = self.selected().and_then(|idx| self.list.get(idx)) {
+                    let dialog = DetailDialog::new(
+                        task.filepath.clone(),
+                        task.url.clone(),
+                        task.result.clone(),
+                    )
+                    .with_network(task.network.clone())
+                    .with_checksum(task.checksum.clone())
+                    .with_rate(task.peak_speed, task.ttfb)
+                    .with_finished_at(Some(timefmt::format_time(task.finished_time, &self.time)));
+                    widgets.push(WidgetType::DetailDialog(Box::new(dialog)));
+                }
+                None
+            }
+            FinishListMessage::CloneTask => {
+                if let Some(options) = self
+                    .selected()
+                    .and_then(|idx| self.list.get(idx))
+                    .and_then(FinishedTask::request_options)
+                {
+                    widgets.push(WidgetType::new_clone_input(&options));
+                }
+                None
+            }
+            FinishListMessage::SaveTemplate => {
+                if let Some(options) = self
+                    .selected()
+                    .and_then(|idx| self.list.get(idx))
+                    .and_then(FinishedTask::request_options)
+                {
+                    widgets.push(WidgetType::new_template_name_input(options));
+                }
+                None
+            }
+            FinishListMessage::JumpInput => {
+                widgets.push(WidgetType::new_jump_input(JumpTarget::Finished));
+                None
+            }
+            FinishListMessage::OpenWith { completed } => {
+                if let Some(task) = self
+                    .selected()
+                    .and_then(|idx| self.list.get(idx))
+                    .filter(|task| matches!(task.state, FinishState::Success))
+                {
+                    if task.missing {
+                        notices.push(String::from("File is missing, press r to check again"));
+                        return None;
+                    }
+                    // 自动解压过的压缩包打开解压的目录
+                    let path = match completed {
+                        false => task.extracted.as_deref().unwrap_or(&task.filepath),
+                        true => match task.completed.as_ref().and_then(CompletedCopy::path) {
+                            Some(path) => path,
+                            None => {
+                                notices.push(String::from("No copy in the completed folder"));
+                                return None;
+                            }
+                        },
+                    };
+                    let category = FileCategory::from_path(path);
+                    widgets.push(WidgetType::new_open_with_dialog(
+                        path.to_path_buf(),
+                        opener::commands_for(&self.open_with, category),
+                        self.last_open_with.get(&category).map(String::as_str),
+                    ));
+                }
+                None
+            }
             FinishListMessage::GoUp => {
                 self.select_previous();
                 None
@@ -306,6 +979,22 @@ impl FinishList {
                 self.select_next();
                 None
             }
+            FinishListMessage::PageUp => {
+                self.select_page_up();
+                None
+            }
+            FinishListMessage::PageDown => {
+                self.select_page_down();
+                None
+            }
+            FinishListMessage::GoTop => {
+                self.select_first();
+                None
+            }
+            FinishListMessage::GoBottom => {
+                self.select_last();
+                None
+            }
         }
     }
 
@@ -313,35 +1002,128 @@ impl FinishList {
         match key.code {
             KeyCode::Up | KeyCode::Char('k') => Some(FinishListMessage::GoUp),
             KeyCode::Down | KeyCode::Char('j') => Some(FinishListMessage::GoDown),
+            KeyCode::PageUp => Some(FinishListMessage::PageUp),
+            KeyCode::PageDown => Some(FinishListMessage::PageDown),
+            KeyCode::Home | KeyCode::Char('g') => Some(FinishListMessage::GoTop),
+            KeyCode::End | KeyCode::Char('G') => Some(FinishListMessage::GoBottom),
+            KeyCode::Enter => Some(FinishListMessage::ShowDetail),
+            KeyCode::Char('d') => Some(FinishListMessage::DeleteFile),
+            KeyCode::Char('C') => Some(FinishListMessage::ClearAll),
+            KeyCode::Char('A') => Some(FinishListMessage::CloneTask),
+            KeyCode::Char('o') => Some(FinishListMessage::OpenWith { completed: false }),
+            KeyCode::Char('O') => Some(FinishListMessage::OpenWith { completed: true }),
+            KeyCode::Char(':') => Some(FinishListMessage::JumpInput),
+            KeyCode::Char('b') => Some(FinishListMessage::SaveTemplate),
+            KeyCode::Char('r') => Some(FinishListMessage::RefreshExistence),
+            KeyCode::Char('P') => Some(FinishListMessage::PeekFile),
+            KeyCode::Char('X') => Some(FinishListMessage::Export),
+            KeyCode::Char('L') => Some(FinishListMessage::ScanLinks),
+            KeyCode::Char('V') => Some(FinishListMessage::VerifyFile),
             _ => None,
         }
     }
 
-    pub fn handle_key_event(&mut self, key: KeyEvent, widgets: &mut Vec<WidgetType>) {
+    pub fn handle_key_event(
+        &mut self,
+        key: KeyEvent,
+        widgets: &mut Vec<WidgetType>,
+        downloading: &mut DownloadList,
+        undo: &mut UndoBuffer,
+        notices: &mut NoticeBoard,
+    ) {
         let mut opt_message = self.get_key_message(key);
         while let Some(message) = opt_message {
-            opt_message = self.respond_to_message_inner(message, widgets);
+            opt_message =
+                self.respond_to_message_inner(message, widgets, downloading, undo, notices);
         }
     }
 
     // ------------------- HANDLE_ASYNC ----------------------
 
-    pub fn handle_async(&mut self) {
-        if self.selected.is_none() && !self.list.is_empty() {
-            self.selected = Some(0);
+    /// `visible`为完成页面是否正在显示，不显示时不开始新的检查，避免不必要的IO
+    pub fn handle_async(
+        &mut self,
+        visible: bool,
+        widgets: &mut Vec<WidgetType>,
+        notices: &mut NoticeBoard,
+    ) {
+        self.clear_expired(Instant::now());
+        self.view.select_first_if_none(self.list.len());
+        if let Some((filepath, result)) = self.peek.poll() {
+            match result {
+                Ok(bytes) => widgets.push(WidgetType::new_peek_dialog(filepath, bytes)),
+                Err(e) => notices.push(peek::error_notice(&filepath, &e)),
+            }
+        }
+        if let Some((page, result)) = self.links.poll() {
+            match result {
+                Ok(scanned) if scanned.links.is_empty() => {
+                    notices.push(String::from("No links found in the page"));
+                }
+                Ok(scanned) => {
+                    if scanned.total > scanned.links.len() {
+                        notices.push(format!(
+                            "Found {} links, showing the first {}",
+                            scanned.total,
+                            scanned.links.len()
+                        ));
+                    }
+                    widgets.push(WidgetType::new_link_review(&page, &scanned.links));
+                }
+                Err(e) => notices.push(format!("Cannot scan for links: {}", e)),
+            }
+        }
+        if let Some(notice) = self.export.poll() {
+            notices.push(notice);
+        }
+        if let Some(results) = self.existence.poll() {
+            self.apply_existence(&results);
+        }
+        if visible {
+            let list = &self.list;
+            self.existence
+                .start_if_due(Instant::now(), || Self::existence_paths(list));
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct FinishListRenderState {
+    pub focused: bool,
+    pub symbols: SymbolSet,
+}
+
+impl FinishListRenderState {
+    pub fn new(focused: bool, symbols: SymbolSet) -> Self {
+        FinishListRenderState { focused, symbols }
+    }
+}
+
 impl StatefulWidget for &mut FinishList {
-    type State = bool; // focused
+    type State = FinishListRenderState;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let empty_text_style = if *state {
+        let empty_text_style = if state.focused {
             Style::default().bg(Color::Gray).fg(Color::Black)
         } else {
             Style::default().fg(Color::White)
         };
 
+        // 开启了自动清除时在顶部说明，避免用户疑惑任务为什么消失了
+        let area = match self.auto_clear.is_off() {
+            true => area,
+            false => {
+                let [header, area] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+                Line::from(format!(
+                    " auto-clearing successes after {}",
+                    self.auto_clear
+                ))
+                .style(FinishList::HEADER_STYLE)
+                .render(header, buf);
+                area
+            }
+        };
+
         // 没有已经完成的任务时，显示EMPTY
         if self.list.is_empty() {
             let text = "NO TASKS";
@@ -353,39 +1135,158 @@ impl StatefulWidget for &mut FinishList {
             return;
         }
 
-        self.fit_to_screen(area.height);
-        match self.selected() {
-            None => {}
-            Some(idx) => {
-                let focused_distance = idx * (FinishList::RENDER_ITEM_HEIGHT + 1) as usize;
-                if focused_distance < self.scroll() {
-                    self.scroll_to(focused_distance);
-                }
-                if focused_distance + FinishList::RENDER_ITEM_HEIGHT as usize
-                    > self.scroll() + area.height as usize
-                {
-                    self.scroll_to(
-                        focused_distance + FinishList::RENDER_ITEM_HEIGHT as usize
-                            - area.height as usize,
-                    );
-                }
-            }
-        }
-
-        let items: Vec<_> = self
-            .list
-            .iter()
-            .map(|item| VerticalListItem::new(FinishList::RENDER_ITEM_HEIGHT, item))
-            .collect();
-        VerticalList::new(items, FinishedTaskRenderState::new(*state, false))
-            .with_selected_state(FinishedTaskRenderState::new(*state, true))
-            .with_selected(self.selected())
-            .with_scroll(self.scroll())
-            .render(area, buf);
+        self.view.render(
+            self.list.iter(),
+            FinishedTaskRenderState::new(state.focused, false, &self.time, state.symbols),
+            FinishedTaskRenderState::new(state.focused, true, &self.time, state.symbols),
+            area,
+            buf,
+        );
     }
 }
 
 pub enum FinishListMessage {
     GoUp,
     GoDown,
+    /// 翻页和跳到首尾时不会循环，停在第一项或者最后一项
+    PageUp,
+    PageDown,
+    GoTop,
+    GoBottom,
+    ShowDetail,
+    /// 删除该项以及对应的文件，可以撤销
+    DeleteFile,
+    /// 清空完成列表，可以撤销
+    ClearAll,
+    /// 使用相同的选项打开下载窗口，作为一个新的任务
+    CloneTask,
+    /// 输入名称，将该任务的URL和选项保存下来，之后在下载页面中重复添加
+    SaveTemplate,
+    /// 选择程序打开下载成功的文件，`completed`为`true`时打开`completed_dir`中的副本
+    OpenWith {
+        completed: bool,
+    },
+    /// 输入任务编号，跳转到该任务
+    JumpInput,
+    /// 立即检查下载成功的文件是否仍然存在
+    RefreshExistence,
+    /// 预览文件开头的一部分，失败的任务可能留下了部分下载的文件
+    PeekFile,
+    /// 输入路径，将整个列表导出为CSV或者Markdown表格
+    Export,
+    /// 从下载到的HTML页面中查找链接，选择其中的一些添加为新的任务
+    ScanLinks,
+    /// 重新读取下载成功的文件，与校验和比较，校验的进度显示在下载列表中
+    VerifyFile,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::checksum::{ChecksumSource, HashAlgorithm};
+
+    fn finished(state: FinishState, file: &str, url: &str) -> FinishedTask {
+        FinishedTask::new(
+            state,
+            PathBuf::from("/downloads").join(file),
+            Some(Url::parse(url).unwrap()),
+            None,
+            0,
+            None,
+            Duration::from_secs(1),
+        )
+    }
+
+    #[test]
+    fn only_successful_downloads_with_a_checksum_can_be_verified() {
+        let checksum = Checksum::new(HashAlgorithm::Sha256, &"ab".repeat(32)).unwrap();
+        let expected = |source| {
+            Some(ExpectedChecksum {
+                checksum: checksum.clone(),
+                source,
+            })
+        };
+        let url = "https://example.com/a.iso";
+        assert!(
+            finished(FinishState::Success, "a.iso", url)
+                .verify_state()
+                .is_none()
+        );
+        assert!(
+            finished(FinishState::Failure, "a.iso", url)
+                .with_checksum(expected(ChecksumSource::User))
+                .verify_state()
+                .is_none()
+        );
+
+        // 用户输入的校验和保存在选项中
+        let options =
+            RequestOptions::new(url.to_string(), None).with_checksum(Some(checksum.clone()));
+        let state = finished(FinishState::Success, "a.iso", url)
+            .with_options(Some(Arc::new(options)))
+            .with_checksum(expected(ChecksumSource::User))
+            .verify_state()
+            .unwrap();
+        assert_eq!(state.filepath(), Path::new("/downloads/a.iso"));
+        assert_eq!(state.expected_checksum(), expected(ChecksumSource::User));
+        assert_eq!(state.remote_checksum, None);
+
+        let state = finished(FinishState::Success, "a.iso", url)
+            .with_checksum(expected(ChecksumSource::Remote))
+            .verify_state()
+            .unwrap();
+        assert_eq!(state.expected_checksum(), expected(ChecksumSource::Remote));
+    }
+
+    #[test]
+    fn merge_policy_by_state_and_url() {
+        use FinishState::*;
+        assert_eq!(merge_policy(Failure, Success, true), MergePolicy::Replace);
+        assert_eq!(merge_policy(Failure, Success, false), MergePolicy::Append);
+        assert_eq!(merge_policy(Success, Success, true), MergePolicy::Supersede);
+        assert_eq!(
+            merge_policy(Success, Success, false),
+            MergePolicy::Supersede
+        );
+        assert_eq!(merge_policy(Failure, Failure, true), MergePolicy::Append);
+        assert_eq!(merge_policy(Success, Failure, true), MergePolicy::Append);
+    }
+
+    #[test]
+    fn successful_retry_replaces_the_failure_in_place() {
+        let url = "https://example.com/a.zip";
+        let mut list = FinishList::new();
+        list.push_task(finished(FinishState::Failure, "a.zip", url));
+        list.push_task(finished(FinishState::Success, "other.zip", url));
+        list.push_task(finished(FinishState::Success, "a.zip", url));
+
+        let tasks = list.list();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].state, FinishState::Success);
+        assert_eq!(tasks[0].retries(), 1);
+        assert!(!tasks[0].is_superseded());
+    }
+
+    #[test]
+    fn repeated_download_supersedes_the_latest_entry() {
+        let url = "https://example.com/a.zip";
+        let mut list = FinishList::new();
+        for _ in 0..3 {
+            list.push_task(finished(FinishState::Success, "a.zip", url));
+        }
+        // 不同URL失败的任务与原来的项无关
+        list.push_task(finished(
+            FinishState::Failure,
+            "a.zip",
+            "https://mirror.example.com/a.zip",
+        ));
+
+        let superseded: Vec<_> = list
+            .list()
+            .iter()
+            .map(FinishedTask::is_superseded)
+            .collect();
+        assert_eq!(superseded, [true, true, false, false]);
+        assert!(list.list().iter().all(|task| task.retries() == 0));
+    }
 }
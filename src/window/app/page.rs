@@ -10,9 +10,11 @@ use ratatui::{
 ///
 /// 0 -- 当前正在下载的任务列表
 /// 1 -- 已经完成的任务列表
+/// 2 -- 统计信息
 pub struct PageList {
     selected: ListState,
-    items: [ListItem<'static>; PageList::PAGE_COUNT],
+    // 显示在页面名称后的标记，例如下载页面的活动指示器
+    badges: [Option<String>; PageList::PAGE_COUNT],
     enter: bool,
 }
 
@@ -27,9 +29,9 @@ impl PageList {
 
     // FIXME: 目前暂时使用Ratatui自带的List，为此，需要使用换行符来保证一个项能够多行显示
     pub const PAGE_STR: [&'static str; PageList::PAGE_COUNT] =
-        ["\nDownloading\n\n", "\nFinished\n\n"];
+        ["\nDownloading\n\n", "\nFinished\n\n", "\nStats\n\n"];
 
-    pub const PAGE_COUNT: usize = 2;
+    pub const PAGE_COUNT: usize = 3;
 
     const FOCUSED_SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
     const UNFOCUSED_SELECTED_STYLE: Style = Style::new().bg(tailwind::GRAY.c500).fg(Color::Black);
@@ -41,14 +43,20 @@ impl PageList {
         selected.select(Some(0));
         PageList {
             selected,
-            items: [
-                ListItem::new(Text::from(Self::PAGE_STR[0]).centered()),
-                ListItem::new(Text::from(Self::PAGE_STR[1]).centered()),
-            ],
+            badges: Default::default(),
             enter: false,
         }
     }
 
+    /// 恢复上次退出时选中的页面，页面不存在时保持选中第一个页面
+    pub fn with_page(mut self, page: usize, entered: bool) -> Self {
+        if page < Self::PAGE_COUNT {
+            self.selected.select(Some(page));
+            self.enter = entered;
+        }
+        self
+    }
+
     // -------------------- MEMBER_ACCESS ---------------------
 
     pub fn selected(&self) -> Option<usize> {
@@ -61,6 +69,12 @@ impl PageList {
 
     // -------------------- MODIFIER -----------------------
 
+    pub fn set_badge(&mut self, index: usize, badge: Option<String>) {
+        if let Some(slot) = self.badges.get_mut(index) {
+            *slot = badge;
+        }
+    }
+
     pub fn set_selected(&mut self, index: Option<usize>) {
         self.selected.select(index);
     }
@@ -75,6 +89,21 @@ impl PageList {
 
     // -------------------- FUNCTION -----------------------
 
+    fn items(&self) -> Vec<ListItem<'static>> {
+        Self::PAGE_STR
+            .iter()
+            .zip(&self.badges)
+            .map(|(page, badge)| {
+                let text = match badge {
+                    // 标记放在名称所在的行
+                    Some(badge) => format!("\n{} {}\n\n", page.trim(), badge),
+                    None => page.to_string(),
+                };
+                ListItem::new(Text::from(text).centered())
+            })
+            .collect()
+    }
+
     pub fn select_next(&mut self) {
         match self.selected() {
             Some(i) => {
@@ -142,15 +171,18 @@ impl PageList {
         }
     }
 
-    pub fn handle_key_event(&mut self, key: KeyEvent) -> Option<KeyEvent> {
+    pub fn handle_key_event(&mut self, key: KeyEvent) -> KeyRoute {
         let mut opt_message = self.get_key_message(key);
-        if let Some(PageListMessage::Distribute(key)) = opt_message {
-            return Some(key);
+        match opt_message {
+            Some(PageListMessage::Distribute(key)) => return KeyRoute::Content(key),
+            // 进入页面之后所有按键都会交给页面，只有还没有进入时才会有不处理的按键
+            None => return KeyRoute::Unfocused(key),
+            _ => {}
         }
         while let Some(message) = opt_message {
             opt_message = self.respond_to_message(message);
         }
-        None
+        KeyRoute::Handled
     }
 }
 
@@ -165,7 +197,7 @@ impl Widget for &mut PageList {
             PageList::FOCUSED_SELECTED_STYLE
         };
 
-        let list = List::new(self.items.clone())
+        let list = List::new(self.items())
             .highlight_style(highlight_style)
             .highlight_spacing(HighlightSpacing::Always);
 
@@ -173,6 +205,17 @@ impl Widget for &mut PageList {
     }
 }
 
+/// [`PageList::handle_key_event`]处理按键的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRoute {
+    /// 页面列表自己处理了该按键
+    Handled,
+    /// 已经进入页面，该按键交给页面的内容处理
+    Content(KeyEvent),
+    /// 还没有进入页面，页面列表也不处理该按键
+    Unfocused(KeyEvent),
+}
+
 pub enum PageListMessage {
     Enter,
     Exit,
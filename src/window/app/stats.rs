@@ -0,0 +1,322 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Cell, Paragraph, Row, Table, Widget};
+
+use crate::app::config::Config;
+use crate::app::stats::{HostStatsMap, SessionClock};
+use crate::app::task::{ConnectionLimits, RuntimeStats, SlotOccupancy, SpeedDisplay};
+use crate::app::timefmt::{self, TimeConfig};
+use crate::window::app::DownloadList;
+use crate::window::common;
+
+/// 统计页面，显示任务数量以及后台运行时的状态
+///
+/// Session
+///   since:       <启动时间>
+///   uptime:      <duration>
+///   active:      <duration>
+///   transferred: <size>
+///   avg speed:   <speed>
+///
+/// Tasks
+///   downloading: <count>
+///   finished:    <count>
+///   speed:       <speed> (<instant|smoothed>)
+///
+/// Runtime (仅在配置中开启runtime_stats时显示)
+///   workers: <count> (<thread name>)
+///   active:  <count>
+///   queued:  <count>
+///   permits: <in use>/<max> (<waiting> waiting)
+///
+/// Hosts
+///   Host | Files | Bytes | Avg speed | Failures
+///
+/// 进入该页面后，按`r`键清空按主机统计的数据，按`s`键在瞬时速度和平滑后的速度之间切换，
+/// 下载页面中显示的速度同样会改变。
+pub struct StatsPage {
+    runtime: Arc<RuntimeStats>,
+    // 用于显示正在使用的名额，见[`ConnectionLimits::occupancy`]
+    limits: Arc<ConnectionLimits>,
+    show_runtime: bool,
+    thread_name: String,
+    time: TimeConfig,
+}
+
+impl StatsPage {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(runtime: Arc<RuntimeStats>, limits: Arc<ConnectionLimits>, config: &Config) -> Self {
+        StatsPage {
+            runtime,
+            limits,
+            show_runtime: config.runtime_stats,
+            thread_name: config.thread_name.clone(),
+            time: TimeConfig::default(),
+        }
+    }
+
+    pub fn with_time_config(mut self, time: TimeConfig) -> Self {
+        self.time = time;
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn runtime(&self) -> &RuntimeStats {
+        &self.runtime
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn section_title(title: &str) -> Line<'_> {
+        Line::from(title).bold()
+    }
+
+    /// 正在使用的名额以及等待名额的任务数量，不限制数量时只显示正在使用的名额
+    fn permits(slots: SlotOccupancy) -> String {
+        match slots.max {
+            0 => format!("{} in use, no limit ({} waiting)", slots.busy, slots.queued),
+            max => format!("{}/{} in use ({} waiting)", slots.busy, max, slots.queued),
+        }
+    }
+
+    fn entry(name: &str, value: String) -> Line<'static> {
+        Line::from(vec![
+            Span::from(format!("  {:<12}", name)).dim(),
+            Span::from(value),
+        ])
+    }
+
+    fn render_hosts(hosts: &HostStatsMap, area: Rect, buf: &mut Buffer) {
+        let [title_area, table_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        Paragraph::new(StatsPage::section_title("Hosts")).render(title_area, buf);
+
+        if hosts.is_empty() {
+            Paragraph::new(Line::from("  No data").dim()).render(table_area, buf);
+            return;
+        }
+
+        let header = Row::new([
+            "Host",
+            "Files",
+            "Bytes",
+            "Avg speed",
+            "Peak speed",
+            "Med TTFB",
+            "Failures",
+        ])
+        .dim();
+        let rows = hosts.sorted_by_bytes().into_iter().map(|(host, stats)| {
+            let speed = match stats.average_speed() {
+                Some(speed) => format!("{}/s", common::get_human_readable_size(speed)),
+                None => String::from("--"),
+            };
+            let peak = match stats.peak_speed {
+                0 => String::from("--"),
+                speed => format!("{}/s", common::get_human_readable_size(speed)),
+            };
+            let ttfb = match stats.median_ttfb() {
+                Some(ttfb) => format!("{} ms", ttfb.as_millis()),
+                None => String::from("--"),
+            };
+            Row::new([
+                Cell::from(host.to_string()),
+                Cell::from(stats.files.to_string()),
+                Cell::from(common::get_human_readable_size(stats.bytes)),
+                Cell::from(speed),
+                Cell::from(peak),
+                Cell::from(ttfb),
+                Cell::from(stats.failures.to_string()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(10),
+                Constraint::Length(6),
+                Constraint::Length(11),
+                Constraint::Length(13),
+                Constraint::Length(13),
+                Constraint::Length(9),
+                Constraint::Length(8),
+            ],
+        )
+        .header(header);
+        Widget::render(table, table_area, buf);
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    fn respond_to_message(
+        &mut self,
+        message: StatsPageMessage,
+        hosts: &mut HostStatsMap,
+        downloading: &mut DownloadList,
+    ) -> Option<StatsPageMessage> {
+        match message {
+            StatsPageMessage::ResetHosts => {
+                log::debug!(target:"App", "Reset host statistics");
+                hosts.clear();
+                None
+            }
+            StatsPageMessage::ToggleSpeedDisplay => {
+                downloading.toggle_speed_display();
+                None
+            }
+        }
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<StatsPageMessage> {
+        match key.code {
+            KeyCode::Char('r') => Some(StatsPageMessage::ResetHosts),
+            KeyCode::Char('s') => Some(StatsPageMessage::ToggleSpeedDisplay),
+            _ => None,
+        }
+    }
+
+    pub fn handle_key_event(
+        &mut self,
+        key: KeyEvent,
+        hosts: &mut HostStatsMap,
+        downloading: &mut DownloadList,
+    ) {
+        let mut opt_message = self.get_key_message(key);
+        while let Some(message) = opt_message {
+            opt_message = self.respond_to_message(message, hosts, downloading);
+        }
+    }
+}
+
+pub struct StatsPageRenderState<'a> {
+    pub page_focused: bool,
+    pub downloading: usize,
+    pub finished: usize,
+    pub hosts: &'a HostStatsMap,
+    pub session: &'a SessionClock,
+    pub speed_display: SpeedDisplay,
+    /// 正在运行的任务的速度之和（B/s），见[`DownloadList::display_speed`]
+    pub speed: u64,
+}
+
+impl<'a> StatsPageRenderState<'a> {
+    pub fn new(
+        page_focused: bool,
+        downloading: usize,
+        finished: usize,
+        hosts: &'a HostStatsMap,
+        session: &'a SessionClock,
+    ) -> Self {
+        StatsPageRenderState {
+            page_focused,
+            downloading,
+            finished,
+            hosts,
+            session,
+            speed_display: SpeedDisplay::default(),
+            speed: 0,
+        }
+    }
+
+    pub fn with_speed(mut self, speed_display: SpeedDisplay, speed: u64) -> Self {
+        self.speed_display = speed_display;
+        self.speed = speed;
+        self
+    }
+}
+
+impl<'a> StatefulWidget for &'a mut StatsPage {
+    type State = StatsPageRenderState<'a>;
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let session = state.session;
+        let average = match session.average_speed() {
+            Some(speed) => format!("{}/s", common::get_human_readable_size(speed)),
+            None => String::from("--"),
+        };
+        let mut lines = vec![
+            StatsPage::section_title("Session"),
+            StatsPage::entry("since", timefmt::format_time(session.started(), &self.time)),
+            StatsPage::entry(
+                "uptime",
+                common::format_duration(session.uptime(Instant::now())),
+            ),
+            StatsPage::entry("active", common::format_duration(session.active())),
+            StatsPage::entry(
+                "transferred",
+                common::get_human_readable_size(session.bytes()),
+            ),
+            StatsPage::entry("avg speed", average),
+            Line::default(),
+            StatsPage::section_title("Tasks"),
+            StatsPage::entry("downloading", state.downloading.to_string()),
+            StatsPage::entry("finished", state.finished.to_string()),
+            StatsPage::entry(
+                "speed",
+                format!(
+                    "{}/s ({}, s to switch)",
+                    common::get_human_readable_size(state.speed),
+                    state.speed_display
+                ),
+            ),
+        ];
+
+        if self.show_runtime {
+            lines.extend([
+                Line::default(),
+                StatsPage::section_title("Runtime"),
+                StatsPage::entry(
+                    "workers",
+                    format!("{} ({})", self.runtime.workers(), self.thread_name),
+                ),
+                StatsPage::entry("active", self.runtime.active().to_string()),
+                StatsPage::entry("queued", self.runtime.queued().to_string()),
+                StatsPage::entry("permits", StatsPage::permits(self.limits.occupancy())),
+            ]);
+        }
+
+        let style = if state.page_focused {
+            Style::new().fg(Color::White)
+        } else {
+            Style::new().fg(Color::Gray)
+        };
+
+        let [summary_area, _, hosts_area] = Layout::vertical([
+            Constraint::Length(lines.len() as u16),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        Paragraph::new(lines).style(style).render(summary_area, buf);
+        StatsPage::render_hosts(state.hosts, hosts_area, buf);
+    }
+}
+
+pub enum StatsPageMessage {
+    ResetHosts,
+    /// 切换显示瞬时速度还是平滑后的速度，见[`SpeedDisplay`]
+    ToggleSpeedDisplay,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permits_show_the_limit_when_there_is_one() {
+        let slots = SlotOccupancy {
+            busy: 3,
+            max: 4,
+            queued: 2,
+        };
+        assert_eq!(StatsPage::permits(slots), "3/4 in use (2 waiting)");
+        let unlimited = SlotOccupancy { max: 0, ..slots };
+        assert_eq!(
+            StatsPage::permits(unlimited),
+            "3 in use, no limit (2 waiting)"
+        );
+    }
+}
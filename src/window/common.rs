@@ -1,7 +1,17 @@
+mod footer;
+mod form;
+mod list;
 mod render;
+mod sanitize;
+mod symbols;
 mod util;
 mod widget;
 
+pub use footer::*;
+pub use form::*;
+pub use list::*;
 pub use render::*;
+pub use sanitize::*;
+pub use symbols::*;
 pub use util::*;
 pub use widget::*;
@@ -0,0 +1,233 @@
+use ratatui::prelude::*;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 任务最后一行右侧的各项指标，每一列有固定的宽度并且右对齐
+///
+/// ```text
+/// Downloading...   12.00 MB/1.00 GB │ ● ~1.23 MB/s │        13 min
+/// Downloading...  512.00 KB/3.00 MB │ ●  64.00 KB/s │           41s
+/// Connecting...            0 B / -- │       -- B/s │
+/// ```
+///
+/// 列的宽度按照最宽的常见值计算（例如`1023.99 MB/s`），因此同一个列表中宽度相同的行，
+/// 每一列都在相同的位置，上下扫视时容易比较。更宽的值在末尾截断。
+///
+/// 列之间以暗色的[`SymbolSet::separator`]分隔。宽度不够时从右往左整列去掉，
+/// 左侧至少保留[`FooterColumns::MIN_LEADING`]给状态或者错误摘要，见[`FooterColumns::split`]。
+///
+/// [`SymbolSet::separator`]: crate::window::common::SymbolSet::separator
+pub struct FooterColumns<'a> {
+    separator: &'a str,
+    columns: Vec<(Line<'a>, u16)>,
+}
+
+impl<'a> FooterColumns<'a> {
+    // ------------------- CONSTANT -----------------------
+
+    /// `1023.99 MB/1023.99 MB`
+    pub const SIZE_WIDTH: u16 = 21;
+    /// `1023.99 MB left`
+    pub const REMAINING_WIDTH: u16 = 15;
+    /// `~1023.99 MB/s`
+    pub const SPEED_WIDTH: u16 = 13;
+    /// `1h 05m–1h 40m`
+    pub const ETA_WIDTH: u16 = 13;
+    /// 左侧至少保留的宽度
+    pub const MIN_LEADING: u16 = 14;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(separator: &'a str) -> Self {
+        FooterColumns {
+            separator,
+            columns: Vec::new(),
+        }
+    }
+
+    /// 在右侧添加一列，`value`为空时该列只占位
+    pub fn with_column(mut self, value: impl Into<Line<'a>>, width: u16) -> Self {
+        self.columns.push((value.into(), width));
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    fn separator_width(&self) -> u16 {
+        self.separator.width() as u16 + 2
+    }
+
+    /// 宽度为`available`时从左往右能够放下的列数
+    pub fn fitted(&self, available: u16) -> usize {
+        let mut used = 0u16;
+        for (index, (_, width)) in self.columns.iter().enumerate() {
+            let gap = if index == 0 {
+                0
+            } else {
+                self.separator_width()
+            };
+            used = used.saturating_add(gap).saturating_add(*width);
+            if used > available {
+                return index;
+            }
+        }
+        self.columns.len()
+    }
+
+    /// 宽度为`available`时放得下的各列（以及分隔符）占据的总宽度
+    pub fn width(&self, available: u16) -> u16 {
+        let fitted = self.fitted(available);
+        let columns: u16 = self.columns[..fitted].iter().map(|(_, width)| width).sum();
+        columns + self.separator_width() * (fitted.saturating_sub(1) as u16)
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 将一行分为左侧的状态以及右侧的各列，两者之间隔开一格
+    ///
+    /// 同一个列表中所有行使用同样的列时，宽度相同的行得到的区域也相同。
+    pub fn split(&self, area: Rect) -> [Rect; 2] {
+        let width = self.width(area.width.saturating_sub(Self::MIN_LEADING + 1));
+        let [leading, _, columns] = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Length(u16::from(width > 0)),
+            Constraint::Length(width),
+        ])
+        .areas(area);
+        [leading, columns]
+    }
+
+    /// 放在`width`宽的区域中的各列，已经补齐了空格，从区域的左端开始渲染即可
+    pub fn into_line(self, width: u16) -> Line<'a> {
+        let fitted = self.fitted(width);
+        let used = self.width(width);
+        let separator = Span::raw(format!(" {} ", self.separator)).add_modifier(Modifier::DIM);
+        let mut spans = vec![Span::raw(" ".repeat(usize::from(width - used)))];
+        for (index, (value, column)) in self.columns.into_iter().take(fitted).enumerate() {
+            if index > 0 {
+                spans.push(separator.clone());
+            }
+            spans.extend(fit_column(value, column));
+        }
+        Line::from(spans)
+    }
+}
+
+/// 在左侧补齐空格使`value`右对齐到`width`，超出时截断末尾并且以`…`结尾
+fn fit_column(value: Line<'_>, width: u16) -> Vec<Span<'_>> {
+    let width = usize::from(width);
+    let value_width = value.width();
+    if value_width <= width {
+        let mut spans = vec![Span::raw(" ".repeat(width - value_width))];
+        spans.extend(value.spans);
+        return spans;
+    }
+    let mut spans = Vec::new();
+    // 留出省略号的位置
+    let mut budget = width.saturating_sub(1);
+    for span in value.spans {
+        let mut content = String::new();
+        for c in span.content.chars() {
+            let c_width = c.width().unwrap_or(0);
+            if c_width > budget {
+                budget = 0;
+                break;
+            }
+            budget -= c_width;
+            content.push(c);
+        }
+        let style = span.style;
+        spans.push(Span::styled(content, style));
+        if budget == 0 {
+            break;
+        }
+    }
+    let used: usize = spans.iter().map(Span::width).sum();
+    if width > 0 {
+        spans.push(Span::raw(format!(
+            "{}…",
+            " ".repeat(width.saturating_sub(used + 1))
+        )));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line<'_>) -> String {
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    fn columns() -> FooterColumns<'static> {
+        FooterColumns::new("│")
+            .with_column("12", 5)
+            .with_column("abc", 4)
+            .with_column("", 3)
+    }
+
+    #[test]
+    fn fitted_drops_columns_from_the_right() {
+        let columns = columns();
+        assert_eq!(columns.fitted(0), 0);
+        assert_eq!(columns.fitted(5), 1);
+        assert_eq!(columns.fitted(11), 1);
+        assert_eq!(columns.fitted(12), 2);
+        assert_eq!(columns.fitted(18), 3);
+        assert_eq!(columns.width(11), 5);
+        assert_eq!(columns.width(17), 12);
+        assert_eq!(columns.width(100), 18);
+    }
+
+    #[test]
+    fn into_line_right_aligns_each_column() {
+        let line = columns().into_line(14);
+        assert_eq!(text(&line), "     12 │  abc");
+        assert_eq!(line.width(), 14);
+        // 空的列只占位
+        assert_eq!(text(&columns().into_line(18)), "   12 │  abc │    ");
+        assert_eq!(text(&columns().into_line(4)), "    ");
+    }
+
+    #[test]
+    fn wide_values_are_truncated() {
+        let line = FooterColumns::new("|")
+            .with_column("abcdefgh", 5)
+            .into_line(5);
+        assert_eq!(text(&line), "abcd…");
+        // 宽字符放不下时以空格补齐
+        let line = FooterColumns::new("|")
+            .with_column("中文字", 4)
+            .into_line(4);
+        assert_eq!(text(&line), "中 …");
+        assert_eq!(line.width(), 4);
+    }
+
+    #[test]
+    fn truncation_keeps_span_styles() {
+        let value = Line::from(vec![Span::raw("● ").green(), Span::raw("123.45 MB/s")]);
+        let line = FooterColumns::new("|").with_column(value, 8).into_line(8);
+        assert_eq!(text(&line), "● 123.4…");
+        let dot = line.spans.iter().find(|span| span.content == "● ").unwrap();
+        assert_eq!(dot.style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn split_keeps_room_for_the_status() {
+        let columns = columns();
+        let [leading, right] = columns.split(Rect::new(0, 0, 40, 1));
+        assert_eq!(right, Rect::new(22, 0, 18, 1));
+        assert_eq!(leading.width, 21);
+
+        let [leading, right] = columns.split(Rect::new(0, 0, 20, 1));
+        assert_eq!(right, Rect::new(15, 0, 5, 1));
+        assert_eq!(leading.width, FooterColumns::MIN_LEADING);
+
+        let [leading, right] = columns.split(Rect::new(0, 0, 10, 1));
+        assert_eq!(right.width, 0);
+        assert_eq!(leading.width, 10);
+    }
+}
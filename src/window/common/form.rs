@@ -0,0 +1,517 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget};
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::window::common::{self, InputMode};
+
+/// 传给校验函数的字段值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValue<'a> {
+    Text(&'a [String]),
+    Toggle(bool),
+    Select(Option<&'a str>),
+}
+
+/// 字段的校验函数，返回的错误显示在该字段的下边框上
+pub type Validator = Box<dyn Fn(FieldValue<'_>) -> Result<(), String>>;
+
+enum FieldInput {
+    Text {
+        area: Box<TextArea<'static>>,
+        // 为None时只有一行，Enter不会换行
+        lines: Option<u16>,
+        // 占据表单中剩余的空间，至少显示`lines`行
+        fill: bool,
+    },
+    Toggle(bool),
+    Select {
+        options: Vec<String>,
+        selected: usize,
+    },
+}
+
+/// [`Form`]中的一个字段，使用`K`（通常是一个枚举）区分不同的字段
+///
+/// 文本字段在标签的下一行显示带边框的输入框，开关和选择字段只占据一行。
+pub struct FormField<K> {
+    key: K,
+    label: String,
+    input: FieldInput,
+    validator: Option<Validator>,
+    error: Option<String>,
+    // 显示在标签行右侧的提示
+    aside: Option<Line<'static>>,
+    // 显示在输入框下方一行的提示，为None时不占据空间
+    note: Option<Option<Line<'static>>>,
+}
+
+impl<K> FormField<K> {
+    // ------------------- CONSTANT -----------------------
+
+    const MASK_CHAR: char = '•';
+    const FOCUSED_STYLE: Style = Style::new().fg(Color::LightYellow);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 单行的文本输入框
+    pub fn text(key: K, label: impl Into<String>) -> Self {
+        FormField::new(
+            key,
+            label,
+            FieldInput::Text {
+                area: Box::default(),
+                lines: None,
+                fill: false,
+            },
+        )
+    }
+
+    /// 单行的文本输入框，输入的内容显示为`•`，用于密码等
+    pub fn masked(key: K, label: impl Into<String>) -> Self {
+        let mut field = FormField::text(key, label);
+        if let FieldInput::Text { area, .. } = &mut field.input {
+            area.set_mask_char(Self::MASK_CHAR);
+        }
+        field
+    }
+
+    pub fn toggle(key: K, label: impl Into<String>, value: bool) -> Self {
+        FormField::new(key, label, FieldInput::Toggle(value))
+    }
+
+    /// 在`options`中选择一项，使用左右方向键切换
+    pub fn select(key: K, label: impl Into<String>, options: Vec<String>, selected: usize) -> Self {
+        let selected = selected.min(options.len().saturating_sub(1));
+        FormField::new(key, label, FieldInput::Select { options, selected })
+    }
+
+    fn new(key: K, label: impl Into<String>, input: FieldInput) -> Self {
+        FormField {
+            key,
+            label: label.into(),
+            input,
+            validator: None,
+            error: None,
+            aside: None,
+            note: None,
+        }
+    }
+
+    /// 文本字段可以输入多行，输入框显示`lines`行
+    pub fn with_lines(mut self, lines: u16) -> Self {
+        if let FieldInput::Text { lines: l, .. } = &mut self.input {
+            *l = Some(lines.max(1));
+        }
+        self
+    }
+
+    /// 多行的文本字段占据表单中剩余的空间
+    pub fn with_fill(mut self) -> Self {
+        if let FieldInput::Text { fill, .. } = &mut self.input {
+            *fill = true;
+        }
+        self
+    }
+
+    pub fn with_validator(
+        mut self,
+        validator: impl Fn(FieldValue<'_>) -> Result<(), String> + 'static,
+    ) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    /// 在输入框下方保留一行，用于显示[`Form::set_note`]设置的提示
+    pub fn with_note_row(mut self) -> Self {
+        self.note = Some(None);
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn value(&self) -> FieldValue<'_> {
+        match &self.input {
+            FieldInput::Text { area, .. } => FieldValue::Text(area.lines()),
+            FieldInput::Toggle(value) => FieldValue::Toggle(*value),
+            FieldInput::Select { options, selected } => {
+                FieldValue::Select(options.get(*selected).map(String::as_str))
+            }
+        }
+    }
+
+    /// 渲染时占据的最少行数
+    fn height(&self) -> u16 {
+        let note = self.note.is_some() as u16;
+        match &self.input {
+            // 标签、输入框以及上下边框
+            FieldInput::Text { lines, .. } => 1 + lines.unwrap_or(1) + 2 + note,
+            FieldInput::Toggle(_) | FieldInput::Select { .. } => 1 + note,
+        }
+    }
+
+    fn is_fill(&self) -> bool {
+        matches!(self.input, FieldInput::Text { fill: true, .. })
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    fn set_text(&mut self, lines: Vec<String>) {
+        if let FieldInput::Text { area, .. } = &mut self.input {
+            let mask = area.mask_char();
+            **area = TextArea::new(lines);
+            if let Some(mask) = mask {
+                area.set_mask_char(mask);
+            }
+            area.move_cursor(CursorMove::Bottom);
+            area.move_cursor(CursorMove::End);
+        }
+    }
+
+    fn validate(&mut self) -> bool {
+        self.error = self
+            .validator
+            .as_ref()
+            .and_then(|validator| validator(self.value()).err());
+        self.error.is_none()
+    }
+
+    /// 编辑字段，返回按键是否被处理
+    fn input(&mut self, key: KeyEvent) -> bool {
+        let handled = match &mut self.input {
+            FieldInput::Text { lines: None, .. } if key.code == KeyCode::Enter => false,
+            FieldInput::Text { area, .. } => {
+                area.input(key);
+                true
+            }
+            FieldInput::Toggle(value) => match key.code {
+                KeyCode::Char(' ') | KeyCode::Enter => {
+                    *value = !*value;
+                    true
+                }
+                _ => false,
+            },
+            FieldInput::Select { options, selected } if !options.is_empty() => match key.code {
+                KeyCode::Left | KeyCode::Char('h') => {
+                    *selected = (*selected + options.len() - 1) % options.len();
+                    true
+                }
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => {
+                    *selected = (*selected + 1) % options.len();
+                    true
+                }
+                _ => false,
+            },
+            FieldInput::Select { .. } => false,
+        };
+        if handled {
+            self.error = None;
+        }
+        handled
+    }
+
+    fn render(&mut self, focused: bool, area: Rect, buf: &mut Buffer) {
+        let [main_area, note_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(self.note.is_some() as u16),
+        ])
+        .areas(area);
+        if let Some(Some(note)) = &self.note {
+            note.clone().render(note_area, buf);
+        }
+
+        let label_style = if focused {
+            Self::FOCUSED_STYLE
+        } else {
+            Style::new()
+        };
+        match &mut self.input {
+            FieldInput::Text { area, .. } => {
+                let [label_area, input_area] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(main_area);
+                Paragraph::new(self.label.as_str())
+                    .left_aligned()
+                    .bold()
+                    .render(label_area, buf);
+                if let Some(aside) = &self.aside {
+                    Paragraph::new(aside.clone())
+                        .right_aligned()
+                        .render(label_area, buf);
+                }
+
+                let border_text = Line::from("input");
+                let mut block = Block::new()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded);
+                block = if focused {
+                    block
+                        .title(border_text.italic())
+                        .border_style(Self::FOCUSED_STYLE)
+                } else {
+                    block.title(border_text.reset_style())
+                };
+                if let Some(error) = &self.error {
+                    block = block.title_bottom(Line::from(error.clone()).red());
+                }
+                area.set_block(block);
+                area.render(input_area, buf);
+            }
+            FieldInput::Toggle(value) => {
+                let mark = if *value { "[x]" } else { "[ ]" };
+                let mut line = Line::from(vec![
+                    Span::from(format!("{} ", mark)),
+                    Span::from(self.label.as_str()).style(label_style),
+                ]);
+                if let Some(error) = &self.error {
+                    line.push_span(Span::from(format!("  {}", error)).red());
+                }
+                line.render(main_area, buf);
+            }
+            FieldInput::Select { options, selected } => {
+                let option = options.get(*selected).map(String::as_str).unwrap_or("");
+                let mut line = Line::from(vec![
+                    Span::from(format!("{}: ", self.label)).style(label_style),
+                    Span::from(format!("< {} >", common::display_sanitize(option))).bold(),
+                ]);
+                if let Some(error) = &self.error {
+                    line.push_span(Span::from(format!("  {}", error)).red());
+                }
+                line.render(main_area, buf);
+            }
+        }
+    }
+}
+
+/// 由多个字段组成的表单，用于需要输入多项内容的弹窗
+///
+/// ```text
+/// ╭Download────────────────────────────╮
+/// │URL:                        warning │
+/// │╭input─────────────────────────────╮│
+/// ││https://example.com/a.iso         ││
+/// │╰──────────────────────────────────╯│
+/// │Save as:                            │
+/// │╭input─────────────────────────────╮│
+/// ││                                  ││
+/// │╰──────────────────────────────────╯│
+/// │[x] Extract archives                │
+/// ╰────────────────────────────────────╯
+/// ```
+///
+/// 与[`DownloadInput`]一样分为普通模式和编辑模式，只有编辑模式下获得焦点的字段会高亮显示。
+/// 表单只负责焦点、编辑、校验以及渲染，按键与操作的对应关系仍由使用表单的弹窗决定：
+/// 例如Tab调用[`Form::focus_next`]，编辑模式下的其他按键交给[`Form::input`]，
+/// 提交前调用[`Form::validate`]，全部通过后再读取字段的值。
+///
+/// [`DownloadInput`]: crate::window::download::DownloadInput
+pub struct Form<K> {
+    fields: Vec<FormField<K>>,
+    focus: usize,
+    mode: InputMode,
+    title: Line<'static>,
+    footer: Option<Line<'static>>,
+}
+
+impl<K: PartialEq> Form<K> {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(title: impl Into<Line<'static>>) -> Self {
+        Form {
+            fields: Vec::new(),
+            focus: 0,
+            mode: InputMode::Editing,
+            title: title.into(),
+            footer: None,
+        }
+    }
+
+    pub fn with_field(mut self, field: FormField<K>) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn mode(&self) -> &InputMode {
+        &self.mode
+    }
+
+    pub fn is_editing(&self) -> bool {
+        matches!(self.mode, InputMode::Editing)
+    }
+
+    /// 获得焦点的字段，表单为空时返回[`None`]
+    pub fn focused(&self) -> Option<&K> {
+        self.fields.get(self.focus).map(FormField::key)
+    }
+
+    pub fn field(&self, key: &K) -> Option<&FormField<K>> {
+        self.fields.iter().find(|field| field.key == *key)
+    }
+
+    /// 文本字段的所有行，其他字段返回空
+    pub fn lines(&self, key: &K) -> &[String] {
+        match self.field(key).map(FormField::value) {
+            Some(FieldValue::Text(lines)) => lines,
+            _ => &[],
+        }
+    }
+
+    /// 文本字段光标所在的行
+    pub fn cursor_line(&self, key: &K) -> Option<&str> {
+        match &self.field(key)?.input {
+            FieldInput::Text { area, .. } => {
+                let (row, _) = area.cursor();
+                area.lines().get(row).map(String::as_str)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn toggle(&self, key: &K) -> bool {
+        matches!(
+            self.field(key).map(FormField::value),
+            Some(FieldValue::Toggle(true))
+        )
+    }
+
+    pub fn selected(&self, key: &K) -> Option<&str> {
+        match self.field(key).map(FormField::value) {
+            Some(FieldValue::Select(selected)) => selected,
+            _ => None,
+        }
+    }
+
+    /// 渲染所有字段需要的最少行数，包括弹窗的边框
+    pub fn height(&self) -> u16 {
+        self.fields.iter().map(FormField::height).sum::<u16>() + 2
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    pub fn set_mode(&mut self, mode: InputMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_title(&mut self, title: impl Into<Line<'static>>) {
+        self.title = title.into();
+    }
+
+    /// 显示在弹窗下边框上的内容
+    pub fn set_footer(&mut self, footer: Option<Line<'static>>) {
+        self.footer = footer;
+    }
+
+    pub fn set_focus(&mut self, key: &K) {
+        if let Some(index) = self.fields.iter().position(|field| field.key == *key) {
+            self.focus = index;
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        if !self.fields.is_empty() {
+            self.focus = (self.focus + 1) % self.fields.len();
+        }
+    }
+
+    pub fn focus_prev(&mut self) {
+        if !self.fields.is_empty() {
+            self.focus = (self.focus + self.fields.len() - 1) % self.fields.len();
+        }
+    }
+
+    /// 替换文本字段的内容，光标移动到末尾
+    pub fn set_text(&mut self, key: &K, lines: Vec<String>) {
+        if let Some(field) = self.field_mut(key) {
+            field.set_text(lines);
+        }
+    }
+
+    pub fn set_toggle(&mut self, key: &K, value: bool) {
+        if let Some(FieldInput::Toggle(v)) = self.field_mut(key).map(|field| &mut field.input) {
+            *v = value;
+        }
+    }
+
+    pub fn set_aside(&mut self, key: &K, aside: Option<Line<'static>>) {
+        if let Some(field) = self.field_mut(key) {
+            field.aside = aside;
+        }
+    }
+
+    /// 只对使用了[`FormField::with_note_row`]的字段生效
+    pub fn set_note(&mut self, key: &K, note: Option<Line<'static>>) {
+        if let Some(field) = self.field_mut(key)
+            && let Some(row) = &mut field.note
+        {
+            *row = note;
+        }
+    }
+
+    /// 设置校验函数之外的错误，例如需要结合多个字段判断的错误
+    pub fn set_error(&mut self, key: &K, error: Option<String>) {
+        if let Some(field) = self.field_mut(key) {
+            field.error = error;
+        }
+    }
+
+    /// 校验所有字段，焦点移动到第一个出错的字段，全部通过时返回`true`
+    pub fn validate(&mut self) -> bool {
+        let mut first_invalid = None;
+        for (index, field) in self.fields.iter_mut().enumerate() {
+            if !field.validate() && first_invalid.is_none() {
+                first_invalid = Some(index);
+            }
+        }
+        if let Some(index) = first_invalid {
+            self.focus = index;
+        }
+        first_invalid.is_none()
+    }
+
+    /// 将按键交给获得焦点的字段，返回按键是否被处理
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        self.fields
+            .get_mut(self.focus)
+            .is_some_and(|field| field.input(key))
+    }
+
+    fn field_mut(&mut self, key: &K) -> Option<&mut FormField<K>> {
+        self.fields.iter_mut().find(|field| field.key == *key)
+    }
+}
+
+impl<K: PartialEq> Widget for &mut Form<K> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(self.title.clone()),
+            self.footer.clone(),
+            Style::new(),
+            area,
+            buf,
+        );
+
+        let constraints: Vec<_> = self
+            .fields
+            .iter()
+            .map(|field| match field.is_fill() {
+                true => Constraint::Min(field.height()),
+                false => Constraint::Length(field.height()),
+            })
+            .collect();
+        let areas = Layout::vertical(constraints).split(area);
+        let editing = self.is_editing();
+        for (index, (field, area)) in self.fields.iter_mut().zip(areas.iter()).enumerate() {
+            field.render(editing && index == self.focus, *area, buf);
+        }
+    }
+}
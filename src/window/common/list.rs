@@ -0,0 +1,209 @@
+use ratatui::prelude::*;
+
+use crate::window::common::{VerticalList, VerticalListItem};
+
+/// 下载页面和完成页面共用的列表状态，负责选中项、滚动距离以及渲染。
+///
+/// [`ItemList`]本身不保存列表的内容，调用者在渲染时提供每一项的组件，
+/// 每一项的高度相同，由`item_height`指定。项与项之间的分隔线以及空间不足时的
+/// 填充由[`VerticalList`]完成。
+///
+/// 选中项的下标总是相对于渲染时提供的所有项，调用者删除元素后需要调用
+/// [`ItemList::on_removed`]来修正选中项。
+#[derive(Debug, Clone)]
+pub struct ItemList {
+    selected: Option<usize>,
+    scroll: usize,
+    item_height: u16,
+    // 上一次渲染时区域的高度，用于计算翻页的距离
+    viewport_height: u16,
+}
+
+impl ItemList {
+    // ------------------- CONSTANT -----------------------
+
+    const DIVIDER_HEIGHT: usize = 1;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(item_height: u16) -> Self {
+        ItemList {
+            selected: None,
+            scroll: 0,
+            item_height,
+            viewport_height: 0,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    #[inline]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    #[inline]
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    #[inline]
+    pub fn item_height(&self) -> u16 {
+        self.item_height
+    }
+
+    /// 一页能够完整显示的项数，至少为1
+    pub fn page_size(&self) -> usize {
+        let stride = self.item_height as usize + Self::DIVIDER_HEIGHT;
+        ((self.viewport_height as usize + Self::DIVIDER_HEIGHT) / stride).max(1)
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    #[inline]
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+    }
+
+    #[inline]
+    pub fn scroll_to(&mut self, scroll: usize) {
+        self.scroll = scroll;
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 选中下一项，到达末尾时回到第一项
+    pub fn select_next(&mut self, len: usize) {
+        if len == 0 {
+            self.selected = None;
+            return;
+        }
+
+        self.selected = match self.selected {
+            Some(i) => Some((i + 1) % len),
+            None => Some(0),
+        };
+    }
+
+    /// 选中上一项，到达第一项时回到末尾
+    pub fn select_previous(&mut self, len: usize) {
+        if len == 0 {
+            self.selected = None;
+            return;
+        }
+
+        self.selected = match self.selected {
+            Some(i) if i == 0 || i >= len => Some(len - 1),
+            Some(i) => Some(i - 1),
+            None => Some(0),
+        };
+    }
+
+    /// 向下翻一页，与单步移动不同，到达末尾时停在最后一项
+    pub fn select_page_down(&mut self, len: usize) {
+        let target = match self.selected {
+            Some(i) => i.saturating_add(self.page_size()),
+            None => 0,
+        };
+        self.jump_to(target, len);
+    }
+
+    /// 向上翻一页，到达第一项时停在第一项
+    pub fn select_page_up(&mut self, len: usize) {
+        let target = match self.selected {
+            Some(i) => i
+                .min(len.saturating_sub(1))
+                .saturating_sub(self.page_size()),
+            None => 0,
+        };
+        self.jump_to(target, len);
+    }
+
+    pub fn select_first(&mut self, len: usize) {
+        self.jump_to(0, len);
+    }
+
+    pub fn select_last(&mut self, len: usize) {
+        self.jump_to(usize::MAX, len);
+    }
+
+    /// 选中第`index`项，超出范围时选中最后一项，同时调整滚动距离使其可见
+    pub fn jump_to(&mut self, index: usize, len: usize) {
+        if len == 0 {
+            self.selected = None;
+            return;
+        }
+        self.selected = Some(index.min(len - 1));
+        self.follow_selected(len, self.viewport_height);
+    }
+
+    /// 列表不为空但没有选中任何项时，选中第一项
+    pub fn select_first_if_none(&mut self, len: usize) {
+        if self.selected.is_none() && len > 0 {
+            self.selected = Some(0);
+        }
+    }
+
+    /// 第`index`项被删除之后调用，`len`为删除之后的长度
+    pub fn on_removed(&mut self, index: usize, len: usize) {
+        if let Some(selected) = self.selected {
+            if len == 0 {
+                self.selected = None;
+            } else if selected >= index && selected > 0 {
+                self.selected = Some(selected - 1);
+            }
+        }
+    }
+
+    fn total_height(&self, len: usize) -> usize {
+        (len * (self.item_height as usize + Self::DIVIDER_HEIGHT))
+            .saturating_sub(Self::DIVIDER_HEIGHT)
+    }
+
+    /// 调整滚动距离，使得内容尽可能填满区域，并且选中项完整显示
+    fn follow_selected(&mut self, len: usize, area_height: u16) {
+        let area_height = area_height as usize;
+        self.scroll = self
+            .scroll
+            .min(self.total_height(len).saturating_sub(area_height));
+
+        if let Some(idx) = self.selected {
+            let item_height = self.item_height as usize;
+            let focused_distance = idx * (item_height + Self::DIVIDER_HEIGHT);
+            if focused_distance < self.scroll {
+                self.scroll = focused_distance;
+            }
+            if focused_distance + item_height > self.scroll + area_height {
+                self.scroll = (focused_distance + item_height).saturating_sub(area_height);
+            }
+        }
+    }
+
+    /// 渲染列表，`items`为列表中的每一项，选中的项使用`selected_state`渲染，
+    /// 其余的项使用`unselected_state`渲染。
+    pub fn render<S, W, I>(
+        &mut self,
+        items: I,
+        unselected_state: S,
+        selected_state: S,
+        area: Rect,
+        buf: &mut Buffer,
+    ) where
+        S: Clone,
+        W: StatefulWidget<State = S> + Clone,
+        I: IntoIterator<Item = W>,
+    {
+        let items: Vec<_> = items
+            .into_iter()
+            .map(|item| VerticalListItem::new(self.item_height, item))
+            .collect();
+        self.viewport_height = area.height;
+        self.follow_selected(items.len(), area.height);
+
+        VerticalList::new(items, unselected_state)
+            .with_selected_state(selected_state)
+            .with_selected(self.selected)
+            .with_scroll(self.scroll)
+            .render(area, buf);
+    }
+}
@@ -1,10 +1,12 @@
 use ratatui::{
     layout::Flex,
     prelude::*,
-    widgets::{Block, BorderType},
+    widgets::{Block, BorderType, Clear, Paragraph},
 };
 use unicode_width::UnicodeWidthStr;
 
+use crate::app::task::TaskId;
+
 pub fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
         .flex(Flex::Center)
@@ -36,6 +38,46 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .areas::<3>(popup_layout[1])[1] // Return the middle chunk
 }
 
+/// 宽度占据`r`的`percent_x`，高度为`height`（不超过`r`）的居中区域，用于按内容决定高度的弹窗
+pub fn popup_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    center(
+        r,
+        Constraint::Percentage(percent_x),
+        Constraint::Length(height.min(r.height)),
+    )
+}
+
+/// 弹窗的区域`popup`小于`min_width`×`min_height`时，扩大到该大小并在`area`中居中，
+/// `area`本身不够大时返回[`None`]，此时应该使用[`render_too_small`]代替弹窗
+pub fn fit_popup(popup: Rect, min_width: u16, min_height: u16, area: Rect) -> Option<Rect> {
+    if area.width < min_width || area.height < min_height {
+        return None;
+    }
+    if popup.width >= min_width && popup.height >= min_height {
+        return Some(popup);
+    }
+    Some(center(
+        area,
+        Constraint::Length(popup.width.max(min_width)),
+        Constraint::Length(popup.height.max(min_height)),
+    ))
+}
+
+/// 终端太小，无法显示弹窗时，在`area`中间显示一行提示，超出宽度的部分被截断
+pub fn render_too_small(area: Rect, buf: &mut Buffer) {
+    if area.is_empty() {
+        return;
+    }
+    let [line] = Layout::vertical([Constraint::Length(1)])
+        .flex(Flex::Center)
+        .areas(area);
+    Clear.render(line, buf);
+    Paragraph::new("Window too small, Esc to close")
+        .centered()
+        .style(Style::new().reversed())
+        .render(line, buf);
+}
+
 /// 使文本在给定区域内居中
 ///
 /// `additional_x`和`additional_y`用于指定文本周围的额外空间。例如如果有边框的情况下，
@@ -87,3 +129,11 @@ pub fn render_border(
         .constraints([Constraint::Min(0)])
         .split(area)[0]
 }
+
+/// 列表中每一项的第一行，任务编号以暗色显示在内容之前，例如`#7 file.zip`
+pub fn numbered_line<'a>(id: Option<TaskId>, text: impl Into<Span<'a>>) -> Line<'a> {
+    match id {
+        Some(id) => Line::from(vec![Span::from(format!("{} ", id)).dim(), text.into()]),
+        None => Line::from(text.into()),
+    }
+}
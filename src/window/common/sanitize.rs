@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+/// 清理来自外部的字符串（文件名、URL、错误信息等），使其可以安全地显示在一行中。
+///
+/// 服务器可以在Content-Disposition的文件名中放入转义序列或者换行符，reqwest的错误信息
+/// 中也可能包含URL，直接渲染这些字符会破坏界面布局。
+///
+/// - 连续的换行符（`\r`、`\n`）合并为一个空格
+/// - 删除其他控制字符，包括ESC，因此`\x1b[31m`只会剩下`[31m`
+/// - 删除零宽字符和双向文本控制字符
+///
+/// 不需要修改时不会分配新的字符串。写入磁盘时的文件名由下载任务另外处理。
+pub fn display_sanitize(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(needs_sanitize) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut sanitized = String::with_capacity(s.len());
+    let mut in_newline = false;
+    for c in s.chars() {
+        if c == '\n' || c == '\r' {
+            if !in_newline {
+                sanitized.push(' ');
+            }
+            in_newline = true;
+            continue;
+        }
+        in_newline = false;
+        if !is_invisible(c) {
+            sanitized.push(c);
+        }
+    }
+    Cow::Owned(sanitized)
+}
+
+fn needs_sanitize(c: char) -> bool {
+    c == '\n' || c == '\r' || is_invisible(c)
+}
+
+/// 渲染时不应该出现的字符
+fn is_invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            // 零宽空格、零宽连接符、从左到右/从右到左标记
+            '\u{200B}'..='\u{200F}'
+            // 双向文本嵌入和覆盖
+            | '\u{202A}'..='\u{202E}'
+            // 单词连接符以及不可见的运算符
+            | '\u{2060}'..='\u{2064}'
+            // 双向文本隔离
+            | '\u{2066}'..='\u{2069}'
+            // 字节顺序标记
+            | '\u{FEFF}'
+        )
+}
@@ -0,0 +1,80 @@
+/// 界面中使用的非ASCII符号
+///
+/// 有些终端或者字体无法正确显示这些符号，此时可以在配置中设置`ascii_symbols = true`，
+/// 使用[`SymbolSet::ASCII`]。设置`reduced_motion = true`时，活动指示器只有一帧，
+/// 之后添加的动画也应当根据[`SymbolSet::animated`]决定是否播放。
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolSet {
+    /// 活动指示器的帧，为空时不显示
+    pub spinner: &'static [&'static str],
+    /// 所有任务都已暂停
+    pub paused: &'static str,
+    /// 下载速度受限于磁盘写入，显示在任务的状态之后
+    pub disk_bound: &'static str,
+    /// 任务正在加速，显示在文件名的右侧
+    pub boost: &'static str,
+    /// 任务健康状况的圆点，颜色见[`TaskHealth::color`]
+    ///
+    /// [`TaskHealth::color`]: crate::app::task::TaskHealth::color
+    pub health: &'static str,
+    /// 任务底部各列指标之间的分隔符，以暗色显示，见[`FooterColumns`]
+    pub separator: &'static str,
+    /// 是否播放动画
+    pub animated: bool,
+}
+
+impl SymbolSet {
+    pub const UNICODE: SymbolSet = SymbolSet {
+        spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+        paused: "⏸",
+        disk_bound: "💾!",
+        boost: "»",
+        health: "●",
+        separator: "│",
+        animated: true,
+    };
+
+    pub const ASCII: SymbolSet = SymbolSet {
+        spinner: &[],
+        paused: "||",
+        disk_bound: "[disk]",
+        boost: ">>",
+        health: "*",
+        separator: "|",
+        animated: true,
+    };
+
+    pub fn new(ascii_only: bool, reduced_motion: bool) -> Self {
+        let symbols = if ascii_only {
+            Self::ASCII
+        } else {
+            Self::UNICODE
+        };
+        if reduced_motion {
+            symbols.without_motion()
+        } else {
+            symbols
+        }
+    }
+
+    /// 关闭动画，活动指示器显示为静止的符号
+    fn without_motion(self) -> Self {
+        let spinner: &'static [&'static str] = match self.spinner {
+            [] => &[],
+            _ => &["●"],
+        };
+        SymbolSet {
+            spinner,
+            animated: false,
+            ..self
+        }
+    }
+
+    /// 第`frame`帧的活动指示器
+    pub fn spinner_frame(&self, frame: usize) -> Option<&'static str> {
+        if self.spinner.is_empty() {
+            return None;
+        }
+        Some(self.spinner[frame % self.spinner.len()])
+    }
+}
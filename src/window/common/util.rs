@@ -1,4 +1,7 @@
+use std::time::Duration;
+
 use ratatui::crossterm::event::KeyEvent;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::{app::App, window::WidgetType};
 pub enum InputMode {
@@ -45,7 +48,7 @@ pub trait WidgetExt: Sized {
             } = widget.respond_to_message(message, app);
             opt_message = response;
             self_widget = boxed_widget;
-            res.extend(new_widget.into_iter());
+            res.extend(new_widget);
         }
         if let Some(widget) = self_widget {
             res.insert(0, wrapper(widget));
@@ -94,6 +97,52 @@ impl<T: WidgetExt> MessageTransfer<T> {
             new_widget: None,
         }
     }
+
+    /// 关闭当前窗口，并打开`new_widget`
+    pub fn replace(new_widget: WidgetType) -> Self {
+        MessageTransfer {
+            response: None,
+            boxed_widget: None,
+            new_widget: Some(new_widget),
+        }
+    }
+}
+
+/// 以`1d 02h 03m`、`2h 03m 04s`、`3m 04s`或者`12s`的形式显示时长
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {:02}h {:02}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, secs)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// 按照显示宽度截断`text`，超出`width`时截断末尾并且以`…`结尾
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut budget = width - 1;
+    let mut truncated = String::new();
+    for c in text.chars() {
+        let c_width = c.width().unwrap_or(0);
+        if c_width > budget {
+            break;
+        }
+        budget -= c_width;
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
 }
 
 /// <size> Bytes -> B/KB/MB/GB
@@ -108,3 +157,21 @@ pub fn get_human_readable_size(size: u64) -> String {
         format!("{:.2} GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_by_display_width() {
+        assert_eq!(truncate_to_width("Queued", 6), "Queued");
+        assert_eq!(truncate_to_width("Queued", 5), "Queu…");
+        assert_eq!(truncate_to_width("Queued", 1), "…");
+        assert_eq!(truncate_to_width("Queued", 0), "");
+        // 中文每个字占两格
+        assert_eq!(truncate_to_width("下载失败", 8), "下载失败");
+        assert_eq!(truncate_to_width("下载失败", 7), "下载失…");
+        assert_eq!(truncate_to_width("下载失败", 6), "下载…");
+        assert!(truncate_to_width("下载失败了", 6).width() <= 6);
+    }
+}
@@ -3,7 +3,7 @@ use std::num::NonZeroU16;
 use ratatui::{
     prelude::*,
     symbols::scrollbar,
-    widgets::{Block, Borders, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
 };
 
 pub struct Fill {
@@ -315,3 +315,45 @@ where
             .for_each(|_| ());
     }
 }
+
+/// 显示在区域右下角的单行提示
+pub struct Toast {
+    message: String,
+}
+
+impl Toast {
+    const STYLE: Style = Style::new().bg(Color::DarkGray).fg(Color::White);
+
+    pub fn new(message: String) -> Self {
+        let message = super::display_sanitize(&message).into_owned();
+        Toast { message }
+    }
+}
+
+impl Widget for Toast {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = (self.message.chars().count() as u16 + 2).min(area.width);
+        let [_, line] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        let [_, line] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(width)]).areas(line);
+        Paragraph::new(format!(" {} ", self.message))
+            .style(Self::STYLE)
+            .render(line, buf);
+    }
+}
+
+/// 启动时在后台运行时就绪之前显示的画面
+pub struct Splash;
+
+impl Widget for Splash {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let text = Text::from(vec![
+            Line::from("request-tui").bold(),
+            Line::from("Loading...").fg(Color::Gray),
+        ]);
+        let [area] = Layout::vertical([Constraint::Length(text.height() as u16)])
+            .flex(layout::Flex::Center)
+            .areas(area);
+        Paragraph::new(text).centered().render(area, buf);
+    }
+}
@@ -0,0 +1,27 @@
+mod about;
+mod conflict;
+mod detail;
+mod export;
+mod instance;
+mod jump;
+mod migrate;
+mod open_with;
+mod peek;
+mod policy;
+mod saved;
+mod setup;
+mod when_done;
+
+pub use about::*;
+pub use conflict::*;
+pub use detail::*;
+pub use export::*;
+pub use instance::*;
+pub use jump::*;
+pub use migrate::*;
+pub use open_with::*;
+pub use peek::*;
+pub use policy::*;
+pub use saved::*;
+pub use setup::*;
+pub use when_done::*;
@@ -0,0 +1,122 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+
+use crate::app::App;
+use crate::app::about::BuildInfo;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 按`F1`打开的About弹窗，显示版本以及配置、日志等文件的位置
+///
+/// ```text
+/// ╭About request-tui───────────────────────────────╮
+/// │Version    0.1.0 (af79b7a)                      │
+/// │Config     ~/.config/request-tui/config.toml    │
+/// │Log        /tmp/request_tui-debug.log           │
+/// │Data       ~/.local/share/request-tui           │
+/// │Downloads  ~/Downloads                          │
+/// │Features   clipboard, control                   │
+/// ╰─────────────────────────────c: copy  Esc: close╯
+/// ```
+///
+/// 按`c`将所有内容复制到剪贴板，方便粘贴到问题报告中，需要启用`clipboard`特性。
+pub struct AboutDialog {
+    info: BuildInfo,
+}
+
+impl AboutDialog {
+    // ------------------- CONSTANT -----------------------
+
+    pub const MIN_WIDTH: u16 = 40;
+    /// 每项一行，加上边框
+    pub const MIN_HEIGHT: u16 = 8;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(info: BuildInfo) -> Self {
+        AboutDialog { info }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    #[cfg(feature = "clipboard")]
+    fn copy(&self, app: &mut App) {
+        match crate::app::clipboard::write_text(&self.info.to_text()) {
+            Ok(()) => app.notify(String::from("Copied build information")),
+            Err(e) => app.notify(format!("Failed to copy: {}", e)),
+        }
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    fn copy(&self, app: &mut App) {
+        app.notify(String::from(
+            "Clipboard support is not enabled in this build",
+        ));
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::AboutDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<AboutDialogMessage> {
+        match key.code {
+            KeyCode::Char('c') => Some(AboutDialogMessage::Copy),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::F(1) => Some(AboutDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut AboutDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("About request-tui")),
+            Some(Line::from("c: copy  Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+        let fields = self.info.fields();
+        let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        let lines: Vec<Line> = fields
+            .into_iter()
+            .map(|(name, value)| {
+                Line::from(vec![
+                    Span::from(format!("{:<width$}  ", name, width = width)).dim(),
+                    Span::raw(common::display_sanitize(&value).into_owned()),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .render(area, buf);
+    }
+}
+
+impl WidgetExt for AboutDialog {
+    type Message = AboutDialogMessage;
+
+    fn respond_to_message(
+        self: Box<Self>,
+        message: AboutDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            AboutDialogMessage::Copy => {
+                self.copy(app);
+                MessageTransfer::keep(self)
+            }
+            AboutDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum AboutDialogMessage {
+    /// 将所有内容复制到剪贴板
+    Copy,
+    Close,
+}
@@ -0,0 +1,210 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+use tokio::sync::mpsc;
+
+use crate::app::App;
+use crate::app::task::{ConflictResolution, FileConflict, TaskCommand};
+use crate::app::timefmt::{self, TimeConfig};
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 用户指定的保存路径已经存在文件时弹出的对话框
+///
+/// ```text
+/// ╭File exists──────────────────────────╮
+/// │<path>                               │
+/// │size: <size>  modified: <mtime>      │
+/// │                                     │
+/// │  Overwrite    Rename    [Cancel]    │
+/// ╰─────────────────────────L: later╯
+/// ```
+///
+/// 默认选中Cancel。按`L`关闭对话框时不发送指令，任务继续等待（不占用名额），
+/// 之后在下载列表中选中该任务按Enter重新打开；其他方式关闭时一定会向任务发送一个
+/// [`TaskCommand::ResolveConflict`]。
+pub struct ConflictDialog {
+    conflict: FileConflict,
+    command_sender: mpsc::UnboundedSender<TaskCommand>,
+    selected: ConflictResolution,
+    time: TimeConfig,
+}
+
+impl ConflictDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 显示文件路径以及三个选项需要的最小区域，包括边框
+    pub const MIN_WIDTH: u16 = 36;
+    pub const MIN_HEIGHT: u16 = 7;
+
+    const OPTIONS: [ConflictResolution; 3] = [
+        ConflictResolution::Overwrite,
+        ConflictResolution::Rename,
+        ConflictResolution::Cancel,
+    ];
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(conflict: FileConflict, command_sender: mpsc::UnboundedSender<TaskCommand>) -> Self {
+        ConflictDialog {
+            conflict,
+            command_sender,
+            selected: ConflictResolution::default(),
+            time: TimeConfig::default(),
+        }
+    }
+
+    pub fn with_time_config(mut self, time: TimeConfig) -> Self {
+        self.time = time;
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn conflict(&self) -> &FileConflict {
+        &self.conflict
+    }
+
+    pub fn selected(&self) -> ConflictResolution {
+        self.selected
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn option_name(option: ConflictResolution) -> &'static str {
+        match option {
+            ConflictResolution::Overwrite => "Overwrite",
+            ConflictResolution::Rename => "Rename",
+            ConflictResolution::Cancel => "Cancel",
+        }
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        let len = Self::OPTIONS.len() as isize;
+        let idx = Self::OPTIONS
+            .iter()
+            .position(|&o| o == self.selected)
+            .unwrap_or(0) as isize;
+        self.selected = Self::OPTIONS[(idx + offset).rem_euclid(len) as usize];
+    }
+
+    fn resolve(&self, resolution: ConflictResolution) {
+        log::debug!(target:"App", "Resolve conflict of {}: {:?}", self.conflict.path.display(), resolution);
+        let _ = self
+            .command_sender
+            .send(TaskCommand::ResolveConflict(resolution));
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::ConflictDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<ConflictDialogMessage> {
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => {
+                Some(ConflictDialogMessage::SelectPrevious)
+            }
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => {
+                Some(ConflictDialogMessage::SelectNext)
+            }
+            KeyCode::Enter => Some(ConflictDialogMessage::Resolve(self.selected)),
+            KeyCode::Char('o') => Some(ConflictDialogMessage::Resolve(
+                ConflictResolution::Overwrite,
+            )),
+            KeyCode::Char('r') => Some(ConflictDialogMessage::Resolve(ConflictResolution::Rename)),
+            KeyCode::Char('c') | KeyCode::Char('q') | KeyCode::Esc => {
+                Some(ConflictDialogMessage::Resolve(ConflictResolution::Cancel))
+            }
+            KeyCode::Char('L') => Some(ConflictDialogMessage::Later),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut ConflictDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("File exists")),
+            Some(Line::from("L: later").right_aligned()),
+            Style::new().fg(Color::LightYellow),
+            area,
+            buf,
+        );
+
+        let [info_area, option_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+        let modified = match self.conflict.modified {
+            Some(time) => timefmt::format_time(time, &self.time),
+            None => String::from("unknown"),
+        };
+        Paragraph::new(vec![
+            Line::from(
+                common::display_sanitize(&self.conflict.path.to_string_lossy()).into_owned(),
+            )
+            .bold(),
+            Line::from(format!(
+                "size: {}  modified: {}",
+                common::get_human_readable_size(self.conflict.size),
+                modified
+            )),
+        ])
+        .wrap(Wrap { trim: false })
+        .render(info_area, buf);
+
+        let spans: Vec<Span> = ConflictDialog::OPTIONS
+            .iter()
+            .flat_map(|&option| {
+                let name = format!(" {} ", ConflictDialog::option_name(option));
+                let span = if option == self.selected {
+                    Span::from(name).style(ConflictDialog::SELECTED_STYLE)
+                } else {
+                    Span::from(name)
+                };
+                [span, Span::from("  ")]
+            })
+            .collect();
+        Paragraph::new(Line::from(spans))
+            .centered()
+            .render(option_area, buf);
+    }
+}
+
+impl WidgetExt for ConflictDialog {
+    type Message = ConflictDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: ConflictDialogMessage,
+        _app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            ConflictDialogMessage::SelectPrevious => {
+                self.select_offset(-1);
+                MessageTransfer::keep(self)
+            }
+            ConflictDialogMessage::SelectNext => {
+                self.select_offset(1);
+                MessageTransfer::keep(self)
+            }
+            ConflictDialogMessage::Resolve(resolution) => {
+                self.resolve(resolution);
+                MessageTransfer::new()
+            }
+            ConflictDialogMessage::Later => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum ConflictDialogMessage {
+    SelectPrevious,
+    SelectNext,
+    Resolve(ConflictResolution),
+    /// 关闭对话框，稍后在下载列表中回答
+    Later,
+}
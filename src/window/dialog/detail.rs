@@ -0,0 +1,439 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+use url::Url;
+
+use crate::app::App;
+use crate::app::checksum::{ChecksumSource, ExpectedChecksum};
+use crate::app::network::NetworkOptions;
+use crate::app::task::{ConnectionSecurity, TaskResult, TaskState};
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 任务详情弹窗，显示任务的完整信息
+///
+/// ```text
+/// ╭Details──────────────────────────────╮
+/// │url:  <url>                          │
+/// │path: <path>                         │
+/// │done: 2025-03-01 14:05               │
+/// │map:  ████▓░                         │
+/// │disk: avg write 3ms, p99 480ms       │
+/// │rate: peak 48.2 MB/s, TTFB 840 ms    │
+/// │net:  IPv4, interface eth0           │
+/// │rule: extension .exe (warn)          │
+/// │sum:  sha256:9f86d0...               │
+/// │                                     │
+/// │Security                             │
+/// │  subject: example.com               │
+/// │  issuer:  R3                        │
+/// │  valid:   2025-01-01 – 2025-04-01   │
+/// │  names:   example.com, *.example.com│
+/// │  host:    matches                   │
+/// │                                     │
+/// │<stage>: <message>                   │
+/// │<message continued>                  │
+/// ╰─────────────────────────────Esc: close╯
+/// ```
+///
+/// 错误信息可能非常长（例如reqwest错误的完整source链），因此会自动换行，
+/// 超出弹窗高度时可以使用上下键滚动。
+///
+/// 正在进行的任务会显示文件中已经下载的部分（见[`PieceMap`]），每一格代表文件的一段，
+/// 使用`p`键切换是否显示。同时显示写入文件的耗时，用于判断磁盘是否拖慢了下载。
+///
+/// 收到服务器的响应之后显示Security部分，HTTPS连接显示服务器证书的摘要，
+/// 见[`ConnectionSecurity`]。
+///
+/// [`PieceMap`]: crate::app::task::PieceMap
+/// [`ConnectionSecurity`]: crate::app::task::ConnectionSecurity
+pub struct DetailDialog {
+    filepath: PathBuf,
+    url: Option<Url>,
+    result: Option<TaskResult>,
+    // 正在进行的任务的状态，用于实时显示下载的部分
+    state: Option<Arc<Mutex<TaskState>>>,
+    // 创建HTTP客户端时使用的网络设置，正在进行的任务从state中读取
+    network: Option<Arc<NetworkOptions>>,
+    // 下载完成后用于校验的校验和，正在进行的任务从state中读取
+    checksum: Option<ExpectedChecksum>,
+    // 下载期间的最高速度以及最近一次请求的TTFB，正在进行的任务从state中读取
+    peak_speed: Option<u64>,
+    ttfb: Option<Duration>,
+    // 已经完成的任务加入完成列表的时间，已经按照配置格式化
+    finished_at: Option<String>,
+    show_pieces: bool,
+    scroll: u16,
+}
+
+impl DetailDialog {
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(filepath: PathBuf, url: Option<Url>, result: Option<TaskResult>) -> Self {
+        DetailDialog {
+            filepath,
+            url,
+            result,
+            state: None,
+            network: None,
+            checksum: None,
+            peak_speed: None,
+            ttfb: None,
+            finished_at: None,
+            show_pieces: true,
+            scroll: 0,
+        }
+    }
+
+    pub fn with_state(mut self, state: Arc<Mutex<TaskState>>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_network(mut self, network: Option<Arc<NetworkOptions>>) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn with_checksum(mut self, checksum: Option<ExpectedChecksum>) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn with_rate(mut self, peak_speed: Option<u64>, ttfb: Option<Duration>) -> Self {
+        self.peak_speed = peak_speed;
+        self.ttfb = ttfb;
+        self
+    }
+
+    pub fn with_finished_at(mut self, finished_at: Option<String>) -> Self {
+        self.finished_at = finished_at;
+        self
+    }
+
+    // ------------------- CONSTANT -----------------------
+
+    /// 更小时各个字段挤在一起无法阅读
+    pub const MIN_WIDTH: u16 = 30;
+    pub const MIN_HEIGHT: u16 = 6;
+
+    const PIECE_STYLE: Style = Style::new().fg(Color::Green).bg(Color::DarkGray);
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn result(&self) -> Option<&TaskResult> {
+        self.result.as_ref()
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 已经下载的部分，每一格根据完成的比例使用不同的方块字符
+    fn piece_line(&self, width: usize) -> Option<Line<'static>> {
+        let state = self.state.as_ref()?.lock().unwrap();
+        let columns = state.pieces()?.columns(width);
+        let map: String = columns
+            .into_iter()
+            .map(|done| match done {
+                d if d >= 1.0 => '█',
+                d if d >= 0.5 => '▓',
+                d if d > 0.0 => '░',
+                _ => ' ',
+            })
+            .collect();
+        Some(Line::from(vec![
+            Span::from("map:  ").dim(),
+            Span::styled(map, Self::PIECE_STYLE),
+        ]))
+    }
+
+    /// 写入文件的平均耗时以及最近写入的p99耗时，还没有写入时为[`None`]
+    fn write_latency_line(&self) -> Option<Line<'static>> {
+        let state = self.state.as_ref()?.lock().unwrap();
+        let latency = state.write_latency();
+        let text = format!(
+            "avg write {}, p99 {}",
+            format_latency(latency.average()?),
+            format_latency(latency.percentile(99.0)?)
+        );
+        let text = if latency.is_disk_bound() {
+            Span::from(text).yellow()
+        } else {
+            Span::from(text)
+        };
+        Some(Line::from(vec![Span::from("disk: ").dim(), text]))
+    }
+
+    /// 下载期间的最高速度以及TTFB，两者都还没有时为[`None`]
+    fn rate_line(&self) -> Option<Line<'static>> {
+        let (peak_speed, ttfb) = match &self.state {
+            Some(state) => {
+                let state = state.lock().unwrap();
+                (state.peak_speed(), state.ttfb())
+            }
+            None => (self.peak_speed, self.ttfb),
+        };
+        let parts: Vec<String> = [
+            peak_speed.map(|speed| format!("peak {}/s", common::get_human_readable_size(speed))),
+            ttfb.map(|ttfb| format!("TTFB {} ms", ttfb.as_millis())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        Some(Line::from(vec![
+            Span::from("rate: ").dim(),
+            Span::from(parts.join(", ")),
+        ]))
+    }
+
+    /// 创建HTTP客户端时使用的网络设置，任务还没有开始连接时为[`None`]
+    ///
+    /// 正在进行的任务打开文件之后，同时显示缓冲区的大小。
+    fn network_line(&self) -> Option<Line<'static>> {
+        let buffers = self
+            .state
+            .as_ref()
+            .and_then(|state| state.lock().unwrap().buffers());
+        let network = self.network.clone().or_else(|| {
+            let state = self.state.as_ref()?.lock().unwrap();
+            state.network().cloned()
+        })?;
+        let mut spans = vec![Span::from("net:  ").dim(), Span::from(network.to_string())];
+        if let Some(buffers) = buffers {
+            spans.push(Span::from(" | buffers: ").dim());
+            spans.push(Span::from(buffers.to_string()));
+        }
+        Some(Line::from(spans))
+    }
+
+    /// 文件类型匹配到的规则，只有正在进行的任务会显示
+    fn policy_line(&self) -> Option<Line<'static>> {
+        let state = self.state.as_ref()?.lock().unwrap();
+        let policy_match = state.policy_match()?;
+        Some(Line::from(vec![
+            Span::from("rule: ").dim(),
+            Span::from(policy_match.to_string()).yellow(),
+        ]))
+    }
+
+    /// 用于校验的校验和，自动获取的校验和会注明来源
+    fn checksum_line(&self) -> Option<Line<'static>> {
+        let expected = self.checksum.clone().or_else(|| {
+            let state = self.state.as_ref()?.lock().unwrap();
+            state.expected_checksum()
+        })?;
+        let text = match expected.source {
+            ChecksumSource::User => Span::from(expected.to_string()),
+            ChecksumSource::Remote => Span::from(expected.to_string()).cyan(),
+        };
+        Some(Line::from(vec![Span::from("sum:  ").dim(), text]))
+    }
+
+    /// 连接是否加密以及证书的摘要，还没有收到响应时为空
+    fn security_lines(&self) -> Vec<Line<'static>> {
+        let Some(security) = self
+            .state
+            .as_ref()
+            .and_then(|state| state.lock().unwrap().security().cloned())
+        else {
+            return Vec::new();
+        };
+        let cert = match security {
+            ConnectionSecurity::Unencrypted => {
+                return vec![Line::from(vec![
+                    Span::from("Security: ").dim(),
+                    Span::from("unencrypted").yellow(),
+                ])];
+            }
+            ConnectionSecurity::Unavailable => {
+                return vec![Line::from(vec![
+                    Span::from("Security: ").dim(),
+                    Span::from("unavailable"),
+                ])];
+            }
+            ConnectionSecurity::Certificate(cert) => cert,
+        };
+        let row = |label: &'static str, value: Span<'static>| {
+            Line::from(vec![Span::from(label).dim(), value])
+        };
+        let date = |time: Option<DateTime<Utc>>| match time {
+            Some(time) => time.format("%Y-%m-%d").to_string(),
+            None => String::from("?"),
+        };
+        let validity = format!(
+            "{} \u{2013} {}",
+            date(cert.not_before),
+            date(cert.not_after)
+        );
+        let validity = if cert.is_expired(Utc::now()) {
+            Span::from(format!("{} (expired)", validity)).red()
+        } else {
+            Span::from(validity)
+        };
+        let host = if cert.host_matches {
+            Span::from("matches")
+        } else {
+            Span::from("does not match").red()
+        };
+        let sanitize = |text: &str| common::display_sanitize(text).into_owned();
+        vec![
+            Line::from("Security").bold(),
+            row("  subject: ", Span::from(sanitize(&cert.subject))),
+            row("  issuer:  ", Span::from(sanitize(&cert.issuer))),
+            row("  valid:   ", validity),
+            row("  names:   ", Span::from(sanitize(&cert.names.join(", ")))),
+            row("  host:    ", host),
+        ]
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::DetailDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<DetailDialogMessage> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(DetailDialogMessage::ScrollUp),
+            KeyCode::Down | KeyCode::Char('j') => Some(DetailDialogMessage::ScrollDown),
+            KeyCode::Char('p') => Some(DetailDialogMessage::TogglePieces),
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Some(DetailDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut DetailDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Details")),
+            Some(Line::from("Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+
+        let url = match &self.url {
+            Some(url) => common::display_sanitize(url.as_str()).into_owned(),
+            None => String::from("unknown"),
+        };
+        let status = match &self.result {
+            Some(result) => result.detail(),
+            None => String::from("Downloading..."),
+        };
+        let path = common::display_sanitize(&self.filepath.to_string_lossy()).into_owned();
+        let mut text = vec![
+            Line::from(vec![Span::from("url:  ").dim(), Span::from(url)]),
+            Line::from(vec![Span::from("path: ").dim(), Span::from(path)]),
+        ];
+        if let Some(finished_at) = &self.finished_at {
+            text.push(Line::from(vec![
+                Span::from("done: ").dim(),
+                Span::from(finished_at.clone()),
+            ]));
+        }
+        if self.show_pieces
+            && let Some(line) = self.piece_line((area.width as usize).saturating_sub(6))
+        {
+            text.push(line);
+        }
+        if let Some(line) = self.write_latency_line() {
+            text.push(line);
+        }
+        if let Some(line) = self.rate_line() {
+            text.push(line);
+        }
+        if let Some(line) = self.network_line() {
+            text.push(line);
+        }
+        if let Some(line) = self.policy_line() {
+            text.push(line);
+        }
+        if let Some(line) = self.checksum_line() {
+            text.push(line);
+        }
+        let security = self.security_lines();
+        if !security.is_empty() {
+            text.push(Line::default());
+            text.extend(security);
+        }
+        text.push(Line::default());
+        // 错误信息保留原本的分行，每一行单独清理
+        text.extend(
+            status
+                .lines()
+                .map(|line| Line::from(common::display_sanitize(line).into_owned())),
+        );
+        if let Some(hint) = self.result.as_ref().and_then(TaskResult::hint) {
+            text.push(Line::from(vec![
+                Span::from("hint: ").dim(),
+                Span::from(common::display_sanitize(&hint).into_owned()).yellow(),
+            ]));
+        }
+
+        // 估计换行后的行数，不允许滚动超出内容的范围
+        let width = area.width.max(1) as usize;
+        let line_count: usize = text
+            .iter()
+            .map(|line| line.width().div_ceil(width).max(1))
+            .sum();
+        let max_scroll = (line_count as u16).saturating_sub(area.height);
+        self.scroll = self.scroll.min(max_scroll);
+
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+impl WidgetExt for DetailDialog {
+    type Message = DetailDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: DetailDialogMessage,
+        _app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            DetailDialogMessage::ScrollUp => {
+                self.scroll = self.scroll.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            DetailDialogMessage::ScrollDown => {
+                self.scroll = self.scroll.saturating_add(1);
+                MessageTransfer::keep(self)
+            }
+            DetailDialogMessage::TogglePieces => {
+                self.show_pieces = !self.show_pieces;
+                MessageTransfer::keep(self)
+            }
+            DetailDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum DetailDialogMessage {
+    ScrollUp,
+    ScrollDown,
+    TogglePieces,
+    Close,
+}
+
+/// 写入耗时通常在毫秒级，不足1毫秒时显示微秒
+fn format_latency(latency: Duration) -> String {
+    if latency < Duration::from_millis(1) {
+        format!("{}us", latency.as_micros())
+    } else {
+        format!("{}ms", latency.as_millis())
+    }
+}
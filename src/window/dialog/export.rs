@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::export::ExportFormat;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+use crate::window::dialog::parse_directory;
+
+/// 导出完成列表之前输入路径以及选择格式，在完成页面中按`X`打开
+///
+/// ```text
+/// ╭Export 12 finished tasks────────────────────────╮
+/// │Path:   finished-2026-10-16.csv                 │
+/// │Format: CSV                                     │
+/// ╰───────Enter: export  Tab: format  Esc: cancel╯
+/// ```
+///
+/// 相对路径以下载目录为起点。切换格式时，如果路径仍然使用原来格式的扩展名，一起替换掉。
+pub struct ExportDialog {
+    count: usize,
+    input: String,
+    format: ExportFormat,
+    error: Option<String>,
+}
+
+impl ExportDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 渲染时占据的行数，包括边框
+    pub const RENDER_HEIGHT: u16 = 5;
+    pub const MIN_WIDTH: u16 = 48;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `count`为将要导出的任务数量
+    pub fn new(count: usize) -> Self {
+        let format = ExportFormat::default();
+        ExportDialog {
+            count,
+            input: format!(
+                "finished-{}.{}",
+                Local::now().format("%Y-%m-%d"),
+                format.extension()
+            ),
+            format,
+            error: None,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn switch_format(&mut self) {
+        let old = format!(".{}", self.format.extension());
+        self.format = self.format.toggle();
+        if let Some(stem) = self.input.strip_suffix(&old) {
+            self.input = format!("{}.{}", stem, self.format.extension());
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::ExportDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<ExportDialogMessage> {
+        match key.code {
+            KeyCode::Char(c) if !c.is_control() => Some(ExportDialogMessage::Input(c)),
+            KeyCode::Backspace => Some(ExportDialogMessage::Backspace),
+            KeyCode::Tab => Some(ExportDialogMessage::SwitchFormat),
+            KeyCode::Enter => Some(ExportDialogMessage::Submit),
+            KeyCode::Esc => Some(ExportDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+/// 导出文件的路径，`~`开头或者绝对路径保持不变，其他相对于`base`
+pub fn resolve_export_path(input: &str, base: &Path) -> Result<PathBuf, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(String::from("File path is required"));
+    }
+    if input.starts_with('~') || Path::new(input).is_absolute() {
+        parse_directory(input)
+    } else {
+        Ok(base.join(input))
+    }
+}
+
+impl Widget for &mut ExportDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from(format!("Export {} finished tasks", self.count))),
+            Some(Line::from("Enter: export  Tab: format  Esc: cancel").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+        let mut lines = vec![
+            Line::from(vec![
+                Span::from("Path:   ").dim(),
+                Span::raw(common::display_sanitize(&self.input)),
+                Span::raw(" ").reversed(),
+            ]),
+            Line::from(vec![
+                Span::from("Format: ").dim(),
+                Span::raw(self.format.to_string()),
+            ]),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(Line::from(common::display_sanitize(error)).red());
+        }
+        Paragraph::new(lines).render(area, buf);
+    }
+}
+
+impl WidgetExt for ExportDialog {
+    type Message = ExportDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: ExportDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            ExportDialogMessage::Input(c) => {
+                self.input.push(c);
+                self.error = None;
+                MessageTransfer::keep(self)
+            }
+            ExportDialogMessage::Backspace => {
+                self.input.pop();
+                self.error = None;
+                MessageTransfer::keep(self)
+            }
+            ExportDialogMessage::SwitchFormat => {
+                self.switch_format();
+                MessageTransfer::keep(self)
+            }
+            ExportDialogMessage::Submit => {
+                let base = app.download_list().download_dir().to_path_buf();
+                let path = match resolve_export_path(&self.input, &base) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return MessageTransfer::keep(self);
+                    }
+                };
+                if !app.finish_list_mut().start_export(path, self.format) {
+                    self.error = Some(String::from("Background runtime is not ready"));
+                    return MessageTransfer::keep(self);
+                }
+                MessageTransfer::new()
+            }
+            ExportDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum ExportDialogMessage {
+    Input(char),
+    Backspace,
+    SwitchFormat,
+    Submit,
+    Close,
+}
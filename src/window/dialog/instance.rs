@@ -0,0 +1,270 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+
+use crate::app::instance::InstanceConflict;
+use crate::app::{App, AppMessage};
+use crate::window::WidgetType;
+use crate::window::common::{self, FieldValue, Form, FormField, MessageTransfer, WidgetExt};
+use crate::window::dialog::parse_directory;
+
+/// 启动时发现另一个实例正在使用同一个下载目录，询问如何处理
+///
+/// ```text
+/// ╭Download directory in use────────────────────╮
+/// │Another instance (PID 4242) is using         │
+/// │/home/user/Downloads                         │
+/// │                                             │
+/// │ Continue read-only                          │
+/// │ Use another download directory              │
+/// │ Exit                                        │
+/// ╰──────────────Enter: select  Esc: read-only╯
+/// ```
+///
+/// 只读模式下可以浏览历史记录，但是不能添加任务，退出时也不保存配置和会话。
+/// 选择其他目录时显示只有一个字段的[`Form`]，获取该目录的锁之后退出只读模式，
+/// 只在本次运行中生效。
+pub struct InstanceDialog {
+    conflict: InstanceConflict,
+    selected: usize,
+    // 选择换用其他目录之后显示的表单
+    directory: Option<Form<InstanceField>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InstanceField {
+    Directory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InstanceChoice {
+    ReadOnly,
+    OtherDirectory,
+    Exit,
+}
+
+impl InstanceChoice {
+    const ALL: [InstanceChoice; 3] = [
+        InstanceChoice::ReadOnly,
+        InstanceChoice::OtherDirectory,
+        InstanceChoice::Exit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            InstanceChoice::ReadOnly => "Continue read-only",
+            InstanceChoice::OtherDirectory => "Use another download directory",
+            InstanceChoice::Exit => "Exit",
+        }
+    }
+}
+
+impl InstanceDialog {
+    // ------------------- CONSTANT -----------------------
+
+    pub const MIN_WIDTH: u16 = 40;
+    pub const MIN_HEIGHT: u16 = 10;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(conflict: InstanceConflict) -> Self {
+        InstanceDialog {
+            conflict,
+            selected: 0,
+            directory: None,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 以冲突的目录作为输入框的初始内容，通常只需要在末尾修改
+    fn open_input(&mut self) {
+        let mut form = Form::new("Download directory in use").with_field(
+            FormField::text(
+                InstanceField::Directory,
+                "Download directory for this session:",
+            )
+            .with_validator(|value| match value {
+                FieldValue::Text(lines) => {
+                    parse_directory(lines.first().map_or("", String::as_str)).map(|_| ())
+                }
+                _ => Ok(()),
+            }),
+        );
+        form.set_text(
+            &InstanceField::Directory,
+            vec![self.conflict.download_dir.display().to_string()],
+        );
+        form.set_footer(Some(Line::from("Enter: use  Esc: back").right_aligned()));
+        self.directory = Some(form);
+    }
+
+    /// 校验输入的目录并获取它的锁，失败时错误显示在输入框上
+    fn switch_directory(form: &mut Form<InstanceField>, app: &mut App) -> bool {
+        if !form.validate() {
+            return false;
+        }
+        let input = form
+            .lines(&InstanceField::Directory)
+            .first()
+            .map_or("", String::as_str);
+        match parse_directory(input).and_then(|dir| app.switch_download_dir(dir)) {
+            Ok(()) => true,
+            Err(e) => {
+                let error = common::display_sanitize(&e).into_owned();
+                form.set_error(&InstanceField::Directory, Some(error));
+                false
+            }
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::InstanceDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<InstanceDialogMessage> {
+        if self.directory.is_some() {
+            return match key.code {
+                KeyCode::Enter => Some(InstanceDialogMessage::Select),
+                KeyCode::Esc => Some(InstanceDialogMessage::Back),
+                _ => Some(InstanceDialogMessage::Input(key)),
+            };
+        }
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(InstanceDialogMessage::SelectPrevious),
+            KeyCode::Down | KeyCode::Char('j') => Some(InstanceDialogMessage::SelectNext),
+            KeyCode::Enter => Some(InstanceDialogMessage::Select),
+            KeyCode::Esc | KeyCode::Char('q') => Some(InstanceDialogMessage::Back),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut InstanceDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let Some(form) = &mut self.directory {
+            form.render(area, buf);
+            return;
+        }
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Download directory in use")),
+            Some(Line::from("Enter: select  Esc: read-only").right_aligned()),
+            Style::new().fg(Color::LightYellow),
+            area,
+            buf,
+        );
+
+        let [message_area, content_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+        Paragraph::new(common::display_sanitize(&self.conflict.to_string()))
+            .wrap(Wrap { trim: true })
+            .render(message_area, buf);
+
+        let lines: Vec<Line> = InstanceChoice::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| {
+                let line = Line::from(format!(" {}", choice.label()));
+                if i == self.selected {
+                    line.style(InstanceDialog::SELECTED_STYLE)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(content_area, buf);
+    }
+}
+
+impl WidgetExt for InstanceDialog {
+    type Message = InstanceDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: InstanceDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            InstanceDialogMessage::SelectPrevious => {
+                self.selected = self.selected.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            InstanceDialogMessage::SelectNext => {
+                if self.selected + 1 < InstanceChoice::ALL.len() {
+                    self.selected += 1;
+                }
+                MessageTransfer::keep(self)
+            }
+            InstanceDialogMessage::Input(key) => {
+                if let Some(directory) = &mut self.directory {
+                    directory.input(key);
+                }
+                MessageTransfer::keep(self)
+            }
+            InstanceDialogMessage::Select => {
+                if let Some(directory) = &mut self.directory {
+                    return match Self::switch_directory(directory, app) {
+                        true => MessageTransfer::new(),
+                        false => MessageTransfer::keep(self),
+                    };
+                }
+                match InstanceChoice::ALL[self.selected] {
+                    InstanceChoice::ReadOnly => MessageTransfer::new(),
+                    InstanceChoice::OtherDirectory => {
+                        self.open_input();
+                        MessageTransfer::keep(self)
+                    }
+                    InstanceChoice::Exit => {
+                        app.respond_to_message(AppMessage::Quit);
+                        MessageTransfer::new()
+                    }
+                }
+            }
+            InstanceDialogMessage::Back => {
+                if self.directory.take().is_some() {
+                    return MessageTransfer::keep(self);
+                }
+                MessageTransfer::new()
+            }
+        }
+    }
+}
+
+pub enum InstanceDialogMessage {
+    SelectPrevious,
+    SelectNext,
+    Input(KeyEvent),
+    Select,
+    Back,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn other_directory_starts_from_the_conflicting_one() {
+        let mut dialog = InstanceDialog::new(InstanceConflict {
+            pid: Some(4242),
+            download_dir: PathBuf::from("/srv/downloads"),
+        });
+        dialog.open_input();
+        let form = dialog.directory.as_mut().unwrap();
+        assert_eq!(form.lines(&InstanceField::Directory), ["/srv/downloads"]);
+        assert!(form.validate());
+
+        form.set_text(&InstanceField::Directory, vec![String::from("relative")]);
+        assert!(!form.validate());
+        let error = form
+            .field(&InstanceField::Directory)
+            .and_then(|field| field.error());
+        assert_eq!(error, Some("Use an absolute path"));
+    }
+}
@@ -0,0 +1,131 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::task::TaskId;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 跳转的目标列表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpTarget {
+    Downloading,
+    Finished,
+}
+
+/// 在列表页面输入`:`之后弹出的输入框，输入任务编号后按Enter选中该任务
+///
+/// ```text
+/// ╭Go to task──────────╮
+/// │:7                  │
+/// ╰────────────────────╯
+/// ```
+pub struct JumpInput {
+    target: JumpTarget,
+    input: String,
+}
+
+impl JumpInput {
+    // ------------------- CONSTANT -----------------------
+
+    /// 渲染时占据的行数，包括边框
+    pub const RENDER_HEIGHT: u16 = 3;
+    pub const RENDER_WIDTH: u16 = 24;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(target: JumpTarget) -> Self {
+        JumpInput {
+            target,
+            input: String::new(),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    /// 在`target`对应的列表中选中编号为`id`的任务，找不到时返回`false`
+    fn jump(&self, id: TaskId, app: &mut App) -> bool {
+        let (download_list, _, finish_list, _, _) = app.destruct_data();
+        match self.target {
+            JumpTarget::Downloading => download_list.select_task(id),
+            JumpTarget::Finished => finish_list.select_task(id),
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::JumpInput)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<JumpInputMessage> {
+        match key.code {
+            KeyCode::Char(c @ ('0'..='9' | '#')) => Some(JumpInputMessage::Input(c)),
+            KeyCode::Backspace => Some(JumpInputMessage::Backspace),
+            KeyCode::Enter => Some(JumpInputMessage::Submit),
+            KeyCode::Esc => Some(JumpInputMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut JumpInput {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Go to task")),
+            None,
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+        Paragraph::new(Line::from(vec![
+            Span::raw(":"),
+            Span::raw(self.input.as_str()),
+            Span::raw(" ").reversed(),
+        ]))
+        .render(area, buf);
+    }
+}
+
+impl WidgetExt for JumpInput {
+    type Message = JumpInputMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: JumpInputMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            JumpInputMessage::Input(c) => {
+                self.input.push(c);
+                MessageTransfer::keep(self)
+            }
+            JumpInputMessage::Backspace => {
+                // 输入为空时再按退格相当于取消
+                if self.input.pop().is_none() {
+                    return MessageTransfer::new();
+                }
+                MessageTransfer::keep(self)
+            }
+            JumpInputMessage::Submit => {
+                match TaskId::parse(&self.input) {
+                    Some(id) if self.jump(id, app) => {}
+                    Some(id) => app.notify(format!("No task {}", id)),
+                    None if self.input.is_empty() => {}
+                    None => app.notify(format!("Invalid task number: {}", self.input)),
+                }
+                MessageTransfer::new()
+            }
+            JumpInputMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum JumpInputMessage {
+    Input(char),
+    Backspace,
+    Submit,
+    Close,
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+
+use crate::app::App;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 下载目录修改之后，询问是否将暂停的任务已经下载的部分移动到新的目录
+///
+/// ```text
+/// ╭Download directory changed───────────╮
+/// │3 paused task(s) have partial files  │
+/// │in <old>                             │
+/// │Move them to <new>?                  │
+/// │                                     │
+/// │          Move    [Keep]             │
+/// ╰─────────────────────────────────────╯
+/// ```
+///
+/// 默认选中Keep，不移动时任务恢复后仍然保存在原来的目录中。
+pub struct MigrateDialog {
+    old_dir: PathBuf,
+    new_dir: PathBuf,
+    count: usize,
+    move_selected: bool,
+}
+
+impl MigrateDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 显示两个目录以及选项需要的最小区域，包括边框
+    pub const MIN_WIDTH: u16 = 36;
+    pub const MIN_HEIGHT: u16 = 7;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(old_dir: PathBuf, new_dir: PathBuf, count: usize) -> Self {
+        MigrateDialog {
+            old_dir,
+            new_dir,
+            count,
+            move_selected: false,
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::MigrateDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<MigrateDialogMessage> {
+        match key.code {
+            KeyCode::Left
+            | KeyCode::Char('h')
+            | KeyCode::BackTab
+            | KeyCode::Right
+            | KeyCode::Char('l')
+            | KeyCode::Tab => Some(MigrateDialogMessage::ToggleSelection),
+            KeyCode::Enter => Some(MigrateDialogMessage::Confirm(self.move_selected)),
+            KeyCode::Char('y') => Some(MigrateDialogMessage::Confirm(true)),
+            KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => {
+                Some(MigrateDialogMessage::Confirm(false))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut MigrateDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Download directory changed")),
+            None,
+            Style::new().fg(Color::LightYellow),
+            area,
+            buf,
+        );
+
+        let [info_area, option_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+        Paragraph::new(vec![
+            Line::from(format!(
+                "{} paused task(s) have partial files in",
+                self.count
+            )),
+            Line::from(common::display_sanitize(&self.old_dir.to_string_lossy()).into_owned())
+                .bold(),
+            Line::from("Move them to"),
+            Line::from(common::display_sanitize(&self.new_dir.to_string_lossy()).into_owned())
+                .bold(),
+        ])
+        .wrap(Wrap { trim: false })
+        .render(info_area, buf);
+
+        let spans: Vec<Span> = [(true, " Move "), (false, " Keep ")]
+            .into_iter()
+            .flat_map(|(confirm, name)| {
+                let span = if confirm == self.move_selected {
+                    Span::from(name).style(MigrateDialog::SELECTED_STYLE)
+                } else {
+                    Span::from(name)
+                };
+                [span, Span::from("  ")]
+            })
+            .collect();
+        Paragraph::new(Line::from(spans))
+            .centered()
+            .render(option_area, buf);
+    }
+}
+
+impl WidgetExt for MigrateDialog {
+    type Message = MigrateDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: MigrateDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            MigrateDialogMessage::ToggleSelection => {
+                self.move_selected = !self.move_selected;
+                MessageTransfer::keep(self)
+            }
+            MigrateDialogMessage::Confirm(confirmed) => {
+                if confirmed {
+                    let (download_list, _, _, _, notices) = app.destruct_data();
+                    download_list.migrate_partials(&self.old_dir, &self.new_dir, notices);
+                }
+                MessageTransfer::new()
+            }
+        }
+    }
+}
+
+pub enum MigrateDialogMessage {
+    ToggleSelection,
+    Confirm(bool),
+}
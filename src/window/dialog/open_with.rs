@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::opener::{self, FileCategory};
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 选择打开已完成文件的程序
+///
+/// ```text
+/// ╭Open with────────────────────╮
+/// │ mpv                         │
+/// │ vlc                         │
+/// │ xdg-open                    │
+/// ╰──────Enter: open  Esc: close╯
+/// ```
+///
+/// 可选的程序来自配置中的`open_with`，默认选中本次运行中该类别最近一次使用的程序。
+pub struct OpenWithDialog {
+    filepath: PathBuf,
+    category: FileCategory,
+    commands: Vec<String>,
+    selected: usize,
+}
+
+impl OpenWithDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 至少能显示文件名和一个程序
+    pub const MIN_WIDTH: u16 = 24;
+    pub const MIN_HEIGHT: u16 = 5;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(filepath: PathBuf, commands: Vec<String>, last_choice: Option<&str>) -> Self {
+        let category = FileCategory::from_path(&filepath);
+        let selected = last_choice
+            .and_then(|last| commands.iter().position(|c| c == last))
+            .unwrap_or(0);
+        OpenWithDialog {
+            filepath,
+            category,
+            commands,
+            selected,
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::OpenWithDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<OpenWithDialogMessage> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(OpenWithDialogMessage::SelectPrevious),
+            KeyCode::Down | KeyCode::Char('j') => Some(OpenWithDialogMessage::SelectNext),
+            KeyCode::Enter => Some(OpenWithDialogMessage::Open),
+            KeyCode::Esc | KeyCode::Char('q') => Some(OpenWithDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut OpenWithDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Open with")),
+            Some(Line::from("Enter: open  Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+
+        if self.commands.is_empty() {
+            Paragraph::new("No commands configured")
+                .centered()
+                .render(area, buf);
+            return;
+        }
+
+        let lines: Vec<Line> = self
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                let line = Line::from(format!(" {}", command));
+                if i == self.selected {
+                    line.style(OpenWithDialog::SELECTED_STYLE)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        // 选中的项总是可见
+        let scroll = (self.selected as u16).saturating_sub(area.height.saturating_sub(1));
+        Paragraph::new(lines).scroll((scroll, 0)).render(area, buf);
+    }
+}
+
+impl WidgetExt for OpenWithDialog {
+    type Message = OpenWithDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: OpenWithDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            OpenWithDialogMessage::SelectPrevious => {
+                self.selected = self.selected.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            OpenWithDialogMessage::SelectNext => {
+                if self.selected + 1 < self.commands.len() {
+                    self.selected += 1;
+                }
+                MessageTransfer::keep(self)
+            }
+            OpenWithDialogMessage::Open => {
+                let Some(command) = self.commands.get(self.selected) else {
+                    return MessageTransfer::new();
+                };
+                let (_, _, finish_list, _, notices) = app.destruct_data();
+                match opener::spawn_detached(command, &self.filepath) {
+                    Ok(()) => finish_list.remember_open_with(self.category, command.clone()),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        notices.push(format!("Command not found: {}", command))
+                    }
+                    Err(e) => notices.push(format!("Failed to run {}: {}", command, e)),
+                }
+                MessageTransfer::new()
+            }
+            OpenWithDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum OpenWithDialogMessage {
+    SelectPrevious,
+    SelectNext,
+    Open,
+    Close,
+}
@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+
+use crate::app::App;
+use crate::app::peek::{self, FileKind};
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 预览文件开头的一部分，按`P`打开，用于判断下载到的是否是HTML错误页面或者损坏的压缩包
+///
+/// ```text
+/// ╭Peek: Text | Hex────────────────────────────────────╮
+/// │file.zip: HTML page, first 4096 bytes               │
+/// │                                                    │
+/// │<!DOCTYPE html>                                     │
+/// │<html><head><title>404 Not Found</title></head>     │
+/// ╰────────────────────────────Tab: switch  Esc: close╯
+/// ```
+///
+/// 文本视图按UTF-8解码并且转义控制字符，十六进制视图在宽度足够时每行16个字节。
+pub struct PeekDialog {
+    filepath: PathBuf,
+    bytes: Vec<u8>,
+    kind: Option<FileKind>,
+    hex: bool,
+    scroll: u16,
+}
+
+impl PeekDialog {
+    // ------------------- CONSTANT -----------------------
+
+    pub const MIN_WIDTH: u16 = 40;
+    pub const MIN_HEIGHT: u16 = 6;
+
+    /// 每行16个字节的十六进制视图的宽度，更窄时每行8个字节
+    const WIDE_HEX_WIDTH: u16 = 78;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(filepath: PathBuf, bytes: Vec<u8>) -> Self {
+        PeekDialog {
+            kind: FileKind::detect(&bytes),
+            filepath,
+            bytes,
+            hex: false,
+            scroll: 0,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn summary_line(&self) -> Line<'static> {
+        let name = self
+            .filepath
+            .file_name()
+            .map(|name| common::display_sanitize(&name.to_string_lossy()).into_owned())
+            .unwrap_or_default();
+        let kind = match self.kind {
+            Some(kind) => Span::from(kind.to_string()).yellow(),
+            None => Span::from("unknown type"),
+        };
+        let size = match self.bytes.len() {
+            0 => String::from(", empty file"),
+            len => format!(", first {} bytes", len),
+        };
+        Line::from(vec![
+            Span::from(format!("{}: ", name)).dim(),
+            kind,
+            Span::from(size).dim(),
+        ])
+    }
+
+    fn body_lines(&self, width: u16) -> Vec<Line<'static>> {
+        if self.hex {
+            let per_line = if width >= Self::WIDE_HEX_WIDTH { 16 } else { 8 };
+            peek::hex_dump(&self.bytes, per_line)
+                .into_iter()
+                .map(Line::from)
+                .collect()
+        } else {
+            peek::printable_text(&self.bytes)
+                .lines()
+                .map(|line| Line::from(common::display_sanitize(line).into_owned()))
+                .collect()
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::PeekDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<PeekDialogMessage> {
+        match key.code {
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right | KeyCode::Char('h' | 'l') => {
+                Some(PeekDialogMessage::SwitchView)
+            }
+            KeyCode::Up | KeyCode::Char('k') => Some(PeekDialogMessage::ScrollUp),
+            KeyCode::Down | KeyCode::Char('j') => Some(PeekDialogMessage::ScrollDown),
+            KeyCode::Esc | KeyCode::Char('q' | 'P') => Some(PeekDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut PeekDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let (text, hex) = match self.hex {
+            false => (Span::from("Text").bold(), Span::from("Hex").dim()),
+            true => (Span::from("Text").dim(), Span::from("Hex").bold()),
+        };
+        let area = common::render_border(
+            Some(Line::from(vec![
+                Span::from("Peek: "),
+                text,
+                Span::from(" | "),
+                hex,
+            ])),
+            Some(Line::from("Tab: switch  Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+        let [summary, _, body] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        self.summary_line().render(summary, buf);
+
+        let lines = self.body_lines(body.width);
+        // 文本视图会自动换行，这里只是粗略地限制滚动范围
+        let max_scroll = (lines.len() as u16).saturating_sub(body.height);
+        self.scroll = self.scroll.min(max_scroll);
+        let paragraph = Paragraph::new(lines).scroll((self.scroll, 0));
+        match self.hex {
+            true => paragraph.render(body, buf),
+            false => paragraph.wrap(Wrap { trim: false }).render(body, buf),
+        }
+    }
+}
+
+impl WidgetExt for PeekDialog {
+    type Message = PeekDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: PeekDialogMessage,
+        _app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            PeekDialogMessage::SwitchView => {
+                self.hex = !self.hex;
+                self.scroll = 0;
+                MessageTransfer::keep(self)
+            }
+            PeekDialogMessage::ScrollUp => {
+                self.scroll = self.scroll.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            PeekDialogMessage::ScrollDown => {
+                self.scroll = self.scroll.saturating_add(1);
+                MessageTransfer::keep(self)
+            }
+            PeekDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum PeekDialogMessage {
+    /// 在文本和十六进制视图之间切换
+    SwitchView,
+    ScrollUp,
+    ScrollDown,
+    Close,
+}
@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget, Wrap};
+use tokio::sync::mpsc;
+
+use crate::app::App;
+use crate::app::policy::{PolicyMatch, PolicyRule};
+use crate::app::task::TaskCommand;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 下载的文件匹配文件类型策略中的提示规则时弹出的对话框
+///
+/// ```text
+/// ╭Flagged file type────────────────────╮
+/// │<path>                               │
+/// │matches extension .exe               │
+/// │                                     │
+/// │     Download anyway    [Cancel]     │
+/// ╰─────────────────────────L: later╯
+/// ```
+///
+/// 服务器返回的是HTML页面而不是文件时（见[`PolicyRule::HtmlPage`]），标题和说明不同：
+///
+/// ```text
+/// ╭Unexpected HTML page─────────────────╮
+/// │<path>                               │
+/// │Server returned an HTML page instead │
+/// │of ubuntu.iso                        │
+/// │     Download anyway    [Cancel]     │
+/// ╰─────────────────────────────────────╯
+/// ```
+///
+/// 默认选中Cancel。与[`ConflictDialog`]一样可以按`L`稍后回答，其他方式关闭时一定会向任务
+/// 发送一个[`TaskCommand::ConfirmDownload`]。
+///
+/// [`ConflictDialog`]: crate::window::dialog::ConflictDialog
+pub struct PolicyDialog {
+    filepath: PathBuf,
+    policy_match: PolicyMatch,
+    command_sender: mpsc::UnboundedSender<TaskCommand>,
+    confirm_selected: bool,
+}
+
+impl PolicyDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 显示匹配的规则以及选项需要的最小区域，包括边框
+    pub const MIN_WIDTH: u16 = 36;
+    pub const MIN_HEIGHT: u16 = 7;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(
+        filepath: PathBuf,
+        policy_match: PolicyMatch,
+        command_sender: mpsc::UnboundedSender<TaskCommand>,
+    ) -> Self {
+        PolicyDialog {
+            filepath,
+            policy_match,
+            command_sender,
+            confirm_selected: false,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn policy_match(&self) -> &PolicyMatch {
+        &self.policy_match
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn confirm(&self, confirmed: bool) {
+        log::debug!(target:"App", "Confirm download of {}: {}", self.filepath.display(), confirmed);
+        let _ = self
+            .command_sender
+            .send(TaskCommand::ConfirmDownload(confirmed));
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::PolicyDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<PolicyDialogMessage> {
+        match key.code {
+            KeyCode::Left
+            | KeyCode::Char('h')
+            | KeyCode::BackTab
+            | KeyCode::Right
+            | KeyCode::Char('l')
+            | KeyCode::Tab => Some(PolicyDialogMessage::ToggleSelection),
+            KeyCode::Enter => Some(PolicyDialogMessage::Confirm(self.confirm_selected)),
+            KeyCode::Char('y') => Some(PolicyDialogMessage::Confirm(true)),
+            KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => {
+                Some(PolicyDialogMessage::Confirm(false))
+            }
+            KeyCode::Char('L') => Some(PolicyDialogMessage::Later),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut PolicyDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let (title, reason) = match &self.policy_match.rule {
+            PolicyRule::HtmlPage => {
+                let filename = self
+                    .filepath
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+                (
+                    "Unexpected HTML page",
+                    format!(
+                        "Server returned an HTML page instead of {}",
+                        common::display_sanitize(&filename)
+                    ),
+                )
+            }
+            rule => ("Flagged file type", format!("matches {}", rule)),
+        };
+        let area = common::render_border(
+            Some(Line::from(title)),
+            Some(Line::from("L: later").right_aligned()),
+            Style::new().fg(Color::LightYellow),
+            area,
+            buf,
+        );
+
+        let [info_area, option_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+
+        Paragraph::new(vec![
+            Line::from(common::display_sanitize(&self.filepath.to_string_lossy()).into_owned())
+                .bold(),
+            Line::from(reason),
+        ])
+        .wrap(Wrap { trim: false })
+        .render(info_area, buf);
+
+        let spans: Vec<Span> = [(true, " Download anyway "), (false, " Cancel ")]
+            .into_iter()
+            .flat_map(|(confirm, name)| {
+                let span = if confirm == self.confirm_selected {
+                    Span::from(name).style(PolicyDialog::SELECTED_STYLE)
+                } else {
+                    Span::from(name)
+                };
+                [span, Span::from("  ")]
+            })
+            .collect();
+        Paragraph::new(Line::from(spans))
+            .centered()
+            .render(option_area, buf);
+    }
+}
+
+impl WidgetExt for PolicyDialog {
+    type Message = PolicyDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: PolicyDialogMessage,
+        _app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            PolicyDialogMessage::ToggleSelection => {
+                self.confirm_selected = !self.confirm_selected;
+                MessageTransfer::keep(self)
+            }
+            PolicyDialogMessage::Confirm(confirmed) => {
+                self.confirm(confirmed);
+                MessageTransfer::new()
+            }
+            PolicyDialogMessage::Later => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum PolicyDialogMessage {
+    ToggleSelection,
+    Confirm(bool),
+    Later,
+}
@@ -0,0 +1,297 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::saved::SavedDownload;
+use crate::app::sender::RequestOptions;
+use crate::window::WidgetType;
+use crate::window::app::DownloadList;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+use crate::window::download::DownloadInput;
+
+/// 保存任务之前输入名称的输入框，在下载页面或者完成页面中按`b`打开
+///
+/// ```text
+/// ╭Save download───────────────────╮
+/// │Name: nightly                   │
+/// ╰────────Enter: save  Esc: cancel╯
+/// ```
+///
+/// 已经存在同名的下载时替换它。
+pub struct TemplateNameInput {
+    options: RequestOptions,
+    input: String,
+}
+
+impl TemplateNameInput {
+    // ------------------- CONSTANT -----------------------
+
+    /// 渲染时占据的行数，包括边框
+    pub const RENDER_HEIGHT: u16 = 3;
+    pub const MIN_WIDTH: u16 = 34;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(options: RequestOptions) -> Self {
+        TemplateNameInput {
+            options,
+            input: String::new(),
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(
+            key,
+            app,
+            Self::get_key_message,
+            WidgetType::TemplateNameInput,
+        )
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<TemplateNameInputMessage> {
+        match key.code {
+            KeyCode::Char(c) if !c.is_control() => Some(TemplateNameInputMessage::Input(c)),
+            KeyCode::Backspace => Some(TemplateNameInputMessage::Backspace),
+            KeyCode::Enter => Some(TemplateNameInputMessage::Submit),
+            KeyCode::Esc => Some(TemplateNameInputMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut TemplateNameInput {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Save download")),
+            Some(Line::from("Enter: save  Esc: cancel").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+        Paragraph::new(Line::from(vec![
+            Span::from("Name: ").dim(),
+            Span::raw(self.input.as_str()),
+            Span::raw(" ").reversed(),
+        ]))
+        .render(area, buf);
+    }
+}
+
+impl WidgetExt for TemplateNameInput {
+    type Message = TemplateNameInputMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: TemplateNameInputMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            TemplateNameInputMessage::Input(c) => {
+                self.input.push(c);
+                MessageTransfer::keep(self)
+            }
+            TemplateNameInputMessage::Backspace => {
+                self.input.pop();
+                MessageTransfer::keep(self)
+            }
+            TemplateNameInputMessage::Submit => {
+                let name = self.input.trim().to_string();
+                if name.is_empty() {
+                    return MessageTransfer::keep(self);
+                }
+                let template = SavedDownload::new(name.clone(), self.options);
+                let message = match app.download_list_mut().save_template(template) {
+                    true => format!("Replaced saved download {}", name),
+                    false => format!("Saved download {}, press T to add it again", name),
+                };
+                app.notify(message);
+                MessageTransfer::new()
+            }
+            TemplateNameInputMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum TemplateNameInputMessage {
+    Input(char),
+    Backspace,
+    Submit,
+    Close,
+}
+
+/// 保存的下载的列表，在下载页面中按`T`打开
+///
+/// ```text
+/// ╭Saved downloads────────────────────────────────────╮
+/// │ nightly   https://example.com/{date}/build.tar.gz │
+/// │ iso       https://example.com/latest.iso          │
+/// ╰───────────Enter: add  e: edit  d: delete  Esc: close╯
+/// ```
+///
+/// 添加时URL中的`{date}`替换为当天的日期，见[`expand_url`]。修改时打开下载窗口，
+/// 提交之后替换保存的选项。
+///
+/// [`expand_url`]: crate::app::saved::expand_url
+pub struct TemplateDialog {
+    // 打开时的副本，修改之后同时更新下载页面中的列表
+    templates: Vec<SavedDownload>,
+    selected: usize,
+}
+
+impl TemplateDialog {
+    // ------------------- CONSTANT -----------------------
+
+    /// 至少能显示名称以及一部分URL
+    pub const MIN_WIDTH: u16 = 30;
+    pub const MIN_HEIGHT: u16 = 4;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    pub fn new(templates: Vec<SavedDownload>) -> Self {
+        TemplateDialog {
+            templates,
+            selected: 0,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    fn selected(&self) -> Option<&SavedDownload> {
+        self.templates.get(self.selected)
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::TemplateDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<TemplateDialogMessage> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(TemplateDialogMessage::SelectPrevious),
+            KeyCode::Down | KeyCode::Char('j') => Some(TemplateDialogMessage::SelectNext),
+            KeyCode::Enter => Some(TemplateDialogMessage::Queue),
+            KeyCode::Char('e') => Some(TemplateDialogMessage::Edit),
+            KeyCode::Char('d') | KeyCode::Delete => Some(TemplateDialogMessage::Delete),
+            KeyCode::Esc | KeyCode::Char('q') => Some(TemplateDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut TemplateDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("Saved downloads")),
+            Some(Line::from("Enter: add  e: edit  d: delete  Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+
+        if self.templates.is_empty() {
+            Paragraph::new("No saved downloads, press b on a task to save one")
+                .centered()
+                .render(area, buf);
+            return;
+        }
+
+        let name_width = self
+            .templates
+            .iter()
+            .map(|t| common::display_sanitize(&t.name).chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = self
+            .templates
+            .iter()
+            .enumerate()
+            .map(|(i, template)| {
+                let name = common::display_sanitize(&template.name);
+                let url = common::display_sanitize(&template.task.url);
+                let line = Line::from(vec![
+                    Span::from(format!(" {:<width$}  ", name, width = name_width)),
+                    Span::from(url.into_owned()).dim(),
+                ]);
+                if i == self.selected {
+                    line.style(TemplateDialog::SELECTED_STYLE)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        // 选中的项总是可见
+        let scroll = (self.selected as u16).saturating_sub(area.height.saturating_sub(1));
+        Paragraph::new(lines).scroll((scroll, 0)).render(area, buf);
+    }
+}
+
+impl WidgetExt for TemplateDialog {
+    type Message = TemplateDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: TemplateDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            TemplateDialogMessage::SelectPrevious => {
+                self.selected = self.selected.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            TemplateDialogMessage::SelectNext => {
+                if self.selected + 1 < self.templates.len() {
+                    self.selected += 1;
+                }
+                MessageTransfer::keep(self)
+            }
+            TemplateDialogMessage::Queue => {
+                let Some(template) = self.selected() else {
+                    return MessageTransfer::keep(self);
+                };
+                if let Some(id) = app.download_list_mut().queue_template(&template.name) {
+                    app.notify(format!("Added {} as task {}", template.name, id));
+                } else if app.download_list().is_read_only() {
+                    app.notify(String::from(DownloadList::READ_ONLY_NOTICE));
+                }
+                MessageTransfer::new()
+            }
+            TemplateDialogMessage::Edit => {
+                let Some(template) = self.selected() else {
+                    return MessageTransfer::keep(self);
+                };
+                let input = DownloadInput::from_options(&template.options())
+                    .editing_template(&template.name);
+                MessageTransfer::replace(WidgetType::DownloadInput(Box::new(input)))
+            }
+            TemplateDialogMessage::Delete => {
+                if self.selected < self.templates.len() {
+                    let template = self.templates.remove(self.selected);
+                    app.download_list_mut().remove_template(&template.name);
+                    app.notify(format!("Deleted saved download {}", template.name));
+                    self.selected = self.selected.min(self.templates.len().saturating_sub(1));
+                }
+                MessageTransfer::keep(self)
+            }
+            TemplateDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum TemplateDialogMessage {
+    SelectPrevious,
+    SelectNext,
+    /// 使用选中的下载添加一个新的任务
+    Queue,
+    /// 在下载窗口中修改选中的下载
+    Edit,
+    Delete,
+    Close,
+}
@@ -0,0 +1,442 @@
+use std::path::PathBuf;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::Widget;
+
+use crate::app::App;
+use crate::app::config::Config;
+use crate::app::task::resolve;
+use crate::window::WidgetType;
+use crate::window::common::{FieldValue, Form, FormField, MessageTransfer, WidgetExt};
+
+/// 首次运行（配置文件不存在）时显示的设置向导
+///
+/// ```text
+/// ╭Welcome (1/3)─────────────────────────────╮
+/// │Download directory:                       │
+/// │╭input───────────────────────────────────╮│
+/// ││/home/user/Downloads                    ││
+/// │╰────────────────────────────────────────╯│
+/// ╰──Enter: next  Shift+Tab: back  Esc: skip╯
+/// ```
+///
+/// 依次设置下载目录、同时进行的任务数量以及界面符号，完成时写入配置文件。
+/// 按Esc跳过时使用默认配置，同样写入配置文件，因此向导只会出现一次。
+///
+/// 每个步骤是只有一个字段的[`Form`]，字段以步骤区分，输入的校验以及错误的显示由表单负责。
+///
+/// 运行期间按F2可以再次打开，此时标题为Settings，按Esc关闭而不修改配置。
+pub struct SetupWizard {
+    config: Config,
+    // 首次运行时打开，跳过时同样保存配置
+    first_run: bool,
+    step: SetupStep,
+    directory: Form<SetupStep>,
+    concurrency: Form<SetupStep>,
+    appearance: Form<SetupStep>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupStep {
+    Directory,
+    Concurrency,
+    Appearance,
+}
+
+/// 界面使用的符号，对应配置中的`ascii_symbols`和`reduced_motion`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Appearance {
+    Unicode,
+    Ascii,
+    Static,
+}
+
+impl SetupStep {
+    fn index(self) -> usize {
+        match self {
+            SetupStep::Directory => 1,
+            SetupStep::Concurrency => 2,
+            SetupStep::Appearance => 3,
+        }
+    }
+}
+
+impl Appearance {
+    const OPTIONS: [Appearance; 3] = [Appearance::Unicode, Appearance::Ascii, Appearance::Static];
+
+    fn from_description(description: &str) -> Option<Self> {
+        Self::OPTIONS
+            .into_iter()
+            .find(|option| option.description() == description)
+    }
+
+    fn from_config(config: &Config) -> Self {
+        match (config.ascii_symbols, config.reduced_motion) {
+            (true, _) => Appearance::Ascii,
+            (false, true) => Appearance::Static,
+            (false, false) => Appearance::Unicode,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Appearance::Unicode => "Unicode symbols with animations",
+            Appearance::Ascii => "ASCII symbols only",
+            Appearance::Static => "Unicode symbols without animations",
+        }
+    }
+}
+
+impl SetupWizard {
+    // ------------------- CONSTANT -----------------------
+
+    /// 步骤标题、输入框以及底部的提示需要的最小区域
+    pub const MIN_WIDTH: u16 = 40;
+    pub const MIN_HEIGHT: u16 = 10;
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 以`config`为默认值，跳过向导时原样保存
+    pub fn new(config: Config) -> Self {
+        let mut directory = Form::new("").with_field(
+            FormField::text(SetupStep::Directory, "Download directory:").with_validator(|value| {
+                match value {
+                    FieldValue::Text(lines) => parse_directory(first_line(lines)).map(|_| ()),
+                    _ => Ok(()),
+                }
+            }),
+        );
+        directory.set_text(
+            &SetupStep::Directory,
+            vec![config.download_dir().display().to_string()],
+        );
+        let mut concurrency = Form::new("").with_field(
+            FormField::text(
+                SetupStep::Concurrency,
+                "Maximum concurrent downloads (0 for no limit):",
+            )
+            .with_validator(|value| match value {
+                FieldValue::Text(lines) => parse_concurrency(first_line(lines)).map(|_| ()),
+                _ => Ok(()),
+            }),
+        );
+        concurrency.set_text(
+            &SetupStep::Concurrency,
+            vec![config.max_concurrent_tasks.to_string()],
+        );
+        let selected = Appearance::from_config(&config);
+        let appearance = Form::new("").with_field(FormField::select(
+            SetupStep::Appearance,
+            "Symbols",
+            Appearance::OPTIONS
+                .iter()
+                .map(|option| option.description().to_string())
+                .collect(),
+            Appearance::OPTIONS
+                .iter()
+                .position(|&option| option == selected)
+                .unwrap_or(0),
+        ));
+        SetupWizard {
+            config,
+            first_run: true,
+            step: SetupStep::Directory,
+            directory,
+            concurrency,
+            appearance,
+        }
+    }
+
+    /// 运行期间从设置的快捷键打开时为`false`
+    pub fn with_first_run(mut self, first_run: bool) -> Self {
+        self.first_run = first_run;
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn step(&self) -> SetupStep {
+        self.step
+    }
+
+    fn form_mut(&mut self, step: SetupStep) -> &mut Form<SetupStep> {
+        match step {
+            SetupStep::Directory => &mut self.directory,
+            SetupStep::Concurrency => &mut self.concurrency,
+            SetupStep::Appearance => &mut self.appearance,
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn appearance(&self) -> Appearance {
+        self.appearance
+            .selected(&SetupStep::Appearance)
+            .and_then(Appearance::from_description)
+            .unwrap_or(Appearance::Unicode)
+    }
+
+    /// 根据所有步骤的输入生成配置，与默认下载目录相同时不写入路径
+    pub fn build_config(&self) -> Result<Config, String> {
+        let mut config = self.config.clone();
+        let directory = parse_directory(first_line(self.directory.lines(&SetupStep::Directory)))?;
+        config.download_dir = (directory != resolve::default_download_dir()).then_some(directory);
+        config.max_concurrent_tasks =
+            parse_concurrency(first_line(self.concurrency.lines(&SetupStep::Concurrency)))?;
+        let appearance = self.appearance();
+        config.ascii_symbols = appearance == Appearance::Ascii;
+        config.reduced_motion = appearance == Appearance::Static;
+        Ok(config)
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::SetupWizard)
+    }
+
+    pub fn get_key_message(&mut self, key: KeyEvent) -> Option<SetupWizardMessage> {
+        match key.code {
+            KeyCode::Esc if self.first_run => Some(SetupWizardMessage::Skip),
+            KeyCode::Esc => Some(SetupWizardMessage::Cancel),
+            KeyCode::Enter => Some(SetupWizardMessage::Next),
+            KeyCode::BackTab => Some(SetupWizardMessage::Back),
+            KeyCode::Tab => None,
+            _ => Some(SetupWizardMessage::Input(key)),
+        }
+    }
+
+    /// 处理除了完成和跳过以外的消息，完成或者跳过时返回需要保存的配置
+    pub fn update(&mut self, message: SetupWizardMessage) -> Option<Config> {
+        match message {
+            SetupWizardMessage::Input(key) => {
+                self.form_mut(self.step).input(key);
+            }
+            SetupWizardMessage::Back => {
+                self.step = match self.step {
+                    SetupStep::Directory | SetupStep::Concurrency => SetupStep::Directory,
+                    SetupStep::Appearance => SetupStep::Concurrency,
+                };
+            }
+            SetupWizardMessage::Next => {
+                if !self.form_mut(self.step).validate() {
+                    return None;
+                }
+                match self.step {
+                    SetupStep::Directory => self.step = SetupStep::Concurrency,
+                    SetupStep::Concurrency => self.step = SetupStep::Appearance,
+                    // 前面的步骤都已经校验过，这里不会出错
+                    SetupStep::Appearance => return self.build_config().ok(),
+                }
+            }
+            SetupWizardMessage::Skip => return Some(self.config.clone()),
+            SetupWizardMessage::Cancel => {}
+        }
+        None
+    }
+}
+
+impl Widget for &mut SetupWizard {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (title, esc) = match self.first_run {
+            true => ("Welcome", "skip"),
+            false => ("Settings", "cancel"),
+        };
+        let hint = match self.step {
+            SetupStep::Appearance => {
+                format!("←/→: change  Enter: finish  Shift+Tab: back  Esc: {}", esc)
+            }
+            _ => format!("Enter: next  Shift+Tab: back  Esc: {}", esc),
+        };
+        let step = self.step;
+        let form = self.form_mut(step);
+        form.set_title(format!("{} ({}/3)", title, step.index()));
+        form.set_footer(Some(Line::from(hint).right_aligned()));
+        form.render(area, buf);
+    }
+}
+
+impl WidgetExt for SetupWizard {
+    type Message = SetupWizardMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: SetupWizardMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        if let SetupWizardMessage::Cancel = message {
+            return MessageTransfer::new();
+        }
+        match self.update(message) {
+            Some(config) => {
+                app.finish_setup(config);
+                MessageTransfer::new()
+            }
+            None => MessageTransfer::keep(self),
+        }
+    }
+}
+
+fn first_line(lines: &[String]) -> &str {
+    lines.first().map_or("", String::as_str)
+}
+
+fn parse_concurrency(input: &str) -> Result<usize, String> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| String::from("Enter a number, 0 for no limit"))
+}
+
+/// 解析输入的下载目录，`~`开头时相对于用户目录，其他情况需要是绝对路径
+pub fn parse_directory(input: &str) -> Result<PathBuf, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(String::from("Download directory is required"));
+    }
+    let path = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            let home = directories::BaseDirs::new()
+                .ok_or_else(|| String::from("Cannot determine home directory"))?;
+            home.home_dir().join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(input),
+    };
+    if !path.is_absolute() {
+        return Err(String::from("Use an absolute path"));
+    }
+    Ok(path)
+}
+
+pub enum SetupWizardMessage {
+    Input(KeyEvent),
+    Back,
+    Next,
+    Skip,
+    /// 关闭运行期间打开的向导，不修改配置
+    Cancel,
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    /// 将按键交给向导，返回完成时生成的配置
+    fn press(wizard: &mut SetupWizard, code: KeyCode) -> Option<Config> {
+        let message = wizard.get_key_message(key(code))?;
+        wizard.update(message)
+    }
+
+    fn type_text(wizard: &mut SetupWizard, text: &str) {
+        for c in text.chars() {
+            press(wizard, KeyCode::Char(c));
+        }
+    }
+
+    fn clear(wizard: &mut SetupWizard) {
+        for _ in 0..200 {
+            press(wizard, KeyCode::Backspace);
+        }
+    }
+
+    #[test]
+    fn steps_through_every_field_and_builds_the_config() {
+        let mut wizard = SetupWizard::new(Config::default());
+        clear(&mut wizard);
+        type_text(&mut wizard, "/srv/downloads");
+        assert!(press(&mut wizard, KeyCode::Enter).is_none());
+        assert_eq!(wizard.step(), SetupStep::Concurrency);
+
+        clear(&mut wizard);
+        type_text(&mut wizard, "8");
+        assert!(press(&mut wizard, KeyCode::Enter).is_none());
+        assert_eq!(wizard.step(), SetupStep::Appearance);
+
+        press(&mut wizard, KeyCode::Right);
+        let config = press(&mut wizard, KeyCode::Enter).unwrap();
+        assert_eq!(config.download_dir, Some(PathBuf::from("/srv/downloads")));
+        assert_eq!(config.max_concurrent_tasks, 8);
+        assert!(config.ascii_symbols);
+        assert!(!config.reduced_motion);
+    }
+
+    #[test]
+    fn invalid_input_keeps_the_step_and_shows_the_error() {
+        let mut wizard = SetupWizard::new(Config::default());
+        clear(&mut wizard);
+        type_text(&mut wizard, "relative/dir");
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.step(), SetupStep::Directory);
+        let error = wizard
+            .directory
+            .field(&SetupStep::Directory)
+            .and_then(|field| field.error());
+        assert_eq!(error, Some("Use an absolute path"));
+
+        // 继续输入时清除错误
+        press(&mut wizard, KeyCode::Backspace);
+        assert!(
+            wizard
+                .directory
+                .field(&SetupStep::Directory)
+                .and_then(|field| field.error())
+                .is_none()
+        );
+
+        clear(&mut wizard);
+        type_text(&mut wizard, "/tmp");
+        press(&mut wizard, KeyCode::Enter);
+        clear(&mut wizard);
+        type_text(&mut wizard, "many");
+        press(&mut wizard, KeyCode::Enter);
+        assert_eq!(wizard.step(), SetupStep::Concurrency);
+    }
+
+    #[test]
+    fn back_keeps_the_entered_values() {
+        let mut wizard = SetupWizard::new(Config::default());
+        clear(&mut wizard);
+        type_text(&mut wizard, "/srv/a");
+        press(&mut wizard, KeyCode::Enter);
+        press(&mut wizard, KeyCode::BackTab);
+        assert_eq!(wizard.step(), SetupStep::Directory);
+        assert_eq!(wizard.directory.lines(&SetupStep::Directory), ["/srv/a"]);
+    }
+
+    #[test]
+    fn esc_skips_on_first_run_and_cancels_from_settings() {
+        let config = Config {
+            max_concurrent_tasks: 3,
+            ..Config::default()
+        };
+        let mut wizard = SetupWizard::new(config.clone());
+        clear(&mut wizard);
+        let skipped = press(&mut wizard, KeyCode::Esc).unwrap();
+        assert_eq!(skipped.max_concurrent_tasks, 3);
+        assert_eq!(skipped.download_dir, config.download_dir);
+
+        let mut wizard = SetupWizard::new(config).with_first_run(false);
+        assert!(matches!(
+            wizard.get_key_message(key(KeyCode::Esc)),
+            Some(SetupWizardMessage::Cancel)
+        ));
+        assert!(press(&mut wizard, KeyCode::Esc).is_none());
+    }
+
+    #[test]
+    fn appearance_starts_from_the_config() {
+        let config = Config {
+            reduced_motion: true,
+            ..Config::default()
+        };
+        let wizard = SetupWizard::new(config);
+        assert_eq!(wizard.appearance(), Appearance::Static);
+    }
+}
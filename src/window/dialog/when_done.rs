@@ -0,0 +1,146 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::when_done::WhenDone;
+use crate::window::WidgetType;
+use crate::window::common::{self, MessageTransfer, WidgetExt};
+
+/// 选择所有下载完成之后执行的操作，见[`WhenDone`]
+///
+/// ```text
+/// ╭When all downloads finish─────────╮
+/// │ do nothing                       │
+/// │ run command (systemctl suspend)  │
+/// │ exit                             │
+/// ╰─────────Enter: select  Esc: close╯
+/// ```
+///
+/// 没有配置`when_done_command`时，运行命令一项显示为不可用，不能选择。
+pub struct WhenDoneDialog {
+    // 配置中的命令，用空格连接，只用于显示
+    command: Option<String>,
+    selected: usize,
+}
+
+impl WhenDoneDialog {
+    // ------------------- CONSTANT -----------------------
+
+    pub const MIN_WIDTH: u16 = 30;
+    pub const MIN_HEIGHT: u16 = 5;
+
+    const SELECTED_STYLE: Style = Style::new().bg(Color::LightBlue).fg(Color::Black);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `current`为当前选择的操作，`command`为配置中的`when_done_command`
+    pub fn new(current: WhenDone, command: &[String]) -> Self {
+        WhenDoneDialog {
+            command: (!command.is_empty()).then(|| command.join(" ")),
+            selected: WhenDone::ALL
+                .iter()
+                .position(|&action| action == current)
+                .unwrap_or(0),
+        }
+    }
+
+    // -------------------- FUNCTION -----------------------
+
+    fn label(&self, action: WhenDone) -> String {
+        match (action, &self.command) {
+            (WhenDone::RunCommand, Some(command)) => {
+                format!("{} ({})", action, common::display_sanitize(command))
+            }
+            (WhenDone::RunCommand, None) => format!("{} (not configured)", action),
+            _ => action.to_string(),
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::WhenDoneDialog)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<WhenDoneDialogMessage> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(WhenDoneDialogMessage::SelectPrevious),
+            KeyCode::Down | KeyCode::Char('j') => Some(WhenDoneDialogMessage::SelectNext),
+            KeyCode::Enter => Some(WhenDoneDialogMessage::Select),
+            KeyCode::Esc | KeyCode::Char('q') => Some(WhenDoneDialogMessage::Close),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut WhenDoneDialog {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let area = common::render_border(
+            Some(Line::from("When all downloads finish")),
+            Some(Line::from("Enter: select  Esc: close").right_aligned()),
+            Style::new().fg(Color::LightBlue),
+            area,
+            buf,
+        );
+
+        let lines: Vec<Line> = WhenDone::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, &action)| {
+                let line = Line::from(format!(" {}", self.label(action)));
+                if i == self.selected {
+                    line.style(WhenDoneDialog::SELECTED_STYLE)
+                } else if action == WhenDone::RunCommand && self.command.is_none() {
+                    line.dim()
+                } else {
+                    line
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(area, buf);
+    }
+}
+
+impl WidgetExt for WhenDoneDialog {
+    type Message = WhenDoneDialogMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: WhenDoneDialogMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        match message {
+            WhenDoneDialogMessage::SelectPrevious => {
+                self.selected = self.selected.saturating_sub(1);
+                MessageTransfer::keep(self)
+            }
+            WhenDoneDialogMessage::SelectNext => {
+                if self.selected + 1 < WhenDone::ALL.len() {
+                    self.selected += 1;
+                }
+                MessageTransfer::keep(self)
+            }
+            WhenDoneDialogMessage::Select => {
+                let action = WhenDone::ALL[self.selected];
+                if action == WhenDone::RunCommand && self.command.is_none() {
+                    app.notify(String::from(
+                        "Set when_done_command in the config file to run a command",
+                    ));
+                    return MessageTransfer::keep(self);
+                }
+                app.set_when_done(action);
+                MessageTransfer::new()
+            }
+            WhenDoneDialogMessage::Close => MessageTransfer::new(),
+        }
+    }
+}
+
+pub enum WhenDoneDialogMessage {
+    SelectPrevious,
+    SelectNext,
+    Select,
+    Close,
+}
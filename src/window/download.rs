@@ -1,3 +1,5 @@
 mod input;
+mod review;
 
 pub use input::*;
+pub use review::*;
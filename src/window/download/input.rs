@@ -1,22 +1,76 @@
+use std::path::PathBuf;
+
 use ratatui::crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget};
-use tui_textarea::TextArea;
+use ratatui::widgets::Widget;
+use reqwest::header::{HeaderName, HeaderValue};
 
 use crate::app::App;
+use crate::app::address;
+use crate::app::checksum::{Checksum, ChecksumError};
+use crate::app::permissions::{FileMode, InvalidFileMode};
+use crate::app::sender::{RequestOptions, SaveAs};
+use crate::app::task::{ByteRange, InvalidByteRange, TaskId};
+use crate::app::template::NameTemplate;
 use crate::window::WidgetType;
 use crate::window::app::{DownloadList, DownloadListMessage};
-use crate::window::common::{self, InputMode, MessageTransfer, WidgetExt};
+use crate::window::common::{
+    self, FieldValue, Form, FormField, InputMode, MessageTransfer, WidgetExt,
+};
+use crate::window::download::BatchReview;
 
 /// 一个输入下载链接的窗口
 ///
-/// TODO: 未来再扩展更多功能。
+/// 包含五个输入框：URL（每行一个）、可选的保存路径（Save as）、请求头（每行一个
+/// `Name: value`）、下载完成的文件的权限（例如`0644`，覆盖配置中的`file_mode`）
+/// 以及只下载一部分时的字节范围（见[`ByteRange`]），使用Tab切换。
+/// URL后面可以用空格分隔跟随一个校验和，例如`https://example.com/a.iso sha256:<hex>`，
+/// 下载完成后会校验文件。
+/// 普通的保存路径只在输入了一个URL时生效；包含占位符时视为命名模板，
+/// 对同一批URL依次编号，见[`NameTemplate`]。输入无效时不会提交，并在输入框下方显示错误。
+///
+/// 一次输入的URL达到[`BatchReview::MIN_URLS`]个时，提交前先在[`BatchReview`]中确认。
+///
+/// 非编辑模式下按`x`切换下载完成后是否自动解压，见[`ArchiveKind`]；按`r`切换
+/// 已经存在相同的文件时是否仍然重新下载。
+///
+/// 也用于修改还没有开始的任务的选项，见[`DownloadInput::editing`]，以及修改保存的下载，
+/// 见[`DownloadInput::editing_template`]，此时只能输入一个URL。
+///
+/// 输入框的焦点切换、校验以及布局由[`Form`]负责，解析出错时错误显示在对应的输入框上。
 ///
 /// TODO: 现在先使用tui_input库的输入框，不过这个输入框对于自动换行的文本的支持较差，
 /// 具体表现为光标显示错误，因此未来使用自定义的输入框替代。
+///
+/// [`ArchiveKind`]: crate::app::task::ArchiveKind
 pub struct DownloadInput {
-    input: TextArea<'static>,
-    mode: InputMode,
+    form: Form<DownloadInputField>,
+    // 正在修改的任务，为None时添加新的任务
+    editing: Option<EditTarget>,
+    // 下载完成后自动解压
+    extract: bool,
+    // 已经存在相同的文件时仍然重新下载
+    redownload: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadInputField {
+    Url,
+    SaveAs,
+    Headers,
+    FileMode,
+    Range,
+}
+
+/// 正在修改的对象
+#[derive(Debug, Clone)]
+enum EditTarget {
+    /// 还没有开始的任务，以及该任务在命名模板中的序号
+    Task { id: TaskId, n: usize },
+    /// 保存的下载，见[`SavedDownload`]
+    ///
+    /// [`SavedDownload`]: crate::app::saved::SavedDownload
+    Template { name: String },
 }
 
 impl Default for DownloadInput {
@@ -28,47 +82,312 @@ impl Default for DownloadInput {
 impl DownloadInput {
     // ------------------- CONSTANT -----------------------
 
-    const INPUT_BOARDER_HIGHLIGHT_STYLE: Style = Style::new().fg(Color::LightYellow);
+    /// 正常显示需要的最小宽度，最小高度为[`DownloadInput::height`]，
+    /// 见[`WidgetType::desired_area`]
+    pub const MIN_WIDTH: u16 = 40;
 
     // -------------------- CONSTRUCT ---------------------
 
     pub fn new() -> Self {
+        let form = Form::new("Download")
+            .with_field(
+                FormField::text(DownloadInputField::Url, "URL:")
+                    .with_lines(1)
+                    .with_fill()
+                    .with_note_row()
+                    .with_validator(|value| match value {
+                        // URL较多时在提交前逐行确认，无效的行在确认窗口中标出
+                        FieldValue::Text(lines) if needs_review(lines) => Ok(()),
+                        FieldValue::Text(lines) => parse_urls(lines).map(|_| ()),
+                        _ => Ok(()),
+                    }),
+            )
+            .with_field(
+                FormField::text(
+                    DownloadInputField::SaveAs,
+                    "Save as (optional, {n:03} {name} {host} {date}):",
+                )
+                .with_validator(|value| match value {
+                    FieldValue::Text(lines) => parse_template(lines).map(|_| ()),
+                    _ => Ok(()),
+                }),
+            )
+            .with_field(
+                FormField::text(
+                    DownloadInputField::Headers,
+                    "Headers (optional, one \"Name: value\" per line):",
+                )
+                .with_lines(2)
+                .with_validator(|value| match value {
+                    FieldValue::Text(lines) => parse_headers(lines).map(|_| ()),
+                    _ => Ok(()),
+                }),
+            )
+            .with_field(
+                FormField::text(
+                    DownloadInputField::FileMode,
+                    "File mode (optional, e.g. 0644, Unix only):",
+                )
+                .with_validator(|value| match value {
+                    FieldValue::Text(lines) => parse_file_mode(lines).map(|_| ()),
+                    _ => Ok(()),
+                }),
+            )
+            .with_field(
+                FormField::text(
+                    DownloadInputField::Range,
+                    "Byte range (optional, e.g. 0-1048575, -1048576):",
+                )
+                .with_validator(|value| match value {
+                    FieldValue::Text(lines) => parse_range(lines).map(|_| ()),
+                    _ => Ok(()),
+                }),
+            );
         DownloadInput {
-            input: TextArea::default(),
-            mode: InputMode::Editing,
+            form,
+            editing: None,
+            extract: false,
+            redownload: false,
         }
     }
 
-    // ------------------ MEMBER_ACCESS --------------------
+    /// 预先填入已有任务的所有选项，用于复制或者修改任务
+    pub fn from_options(options: &RequestOptions) -> Self {
+        let url = match &options.checksum {
+            Some(checksum) => format!("{} {}", options.url, checksum),
+            None => options.url.clone(),
+        };
+        let save_as = options
+            .save_as
+            .as_ref()
+            .map(SaveAs::input_text)
+            .unwrap_or_default();
+        let mut input = DownloadInput::new()
+            .with_url(&url)
+            .with_save_as(&save_as)
+            .with_extract(options.extract)
+            .with_redownload(options.redownload);
+        if let Some(mode) = options.file_mode {
+            input
+                .form
+                .set_text(&DownloadInputField::FileMode, vec![mode.to_string()]);
+        }
+        if let Some(range) = options.range {
+            input
+                .form
+                .set_text(&DownloadInputField::Range, vec![range.to_string()]);
+        }
+        input.with_headers(&options.headers)
+    }
 
-    pub fn input(&self) -> &TextArea<'_> {
-        &self.input
+    pub fn with_warning(mut self, warning: Option<String>) -> Self {
+        let warning =
+            warning.map(|w| Line::from(common::display_sanitize(&w).into_owned()).yellow());
+        self.form.set_aside(&DownloadInputField::Url, warning);
+        self
     }
 
-    pub fn input_mut(&mut self) -> &mut TextArea<'static> {
-        &mut self.input
+    pub fn with_extract(mut self, extract: bool) -> Self {
+        self.extract = extract;
+        self
     }
 
-    pub fn mode(&self) -> &InputMode {
-        &self.mode
+    pub fn with_redownload(mut self, redownload: bool) -> Self {
+        self.redownload = redownload;
+        self
+    }
+
+    /// 修改编号为`id`的任务，而不是添加新的任务
+    pub fn editing(mut self, id: TaskId, options: &RequestOptions) -> Self {
+        let n = match &options.save_as {
+            Some(SaveAs::Template { n, .. }) => *n,
+            _ => 1,
+        };
+        self.editing = Some(EditTarget::Task { id, n });
+        self.form.set_title(format!("Edit task {}", id));
+        self
+    }
+
+    /// 修改名称为`name`的保存的下载，URL中可以使用`{date}`
+    pub fn editing_template(mut self, name: &str) -> Self {
+        self.form.set_title(format!(
+            "Edit saved download {}",
+            common::display_sanitize(name)
+        ));
+        self.editing = Some(EditTarget::Template {
+            name: name.to_string(),
+        });
+        self
     }
 
-    // -------------------- MODIFIER -----------------------
+    /// 预先填入URL
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.form
+            .set_text(&DownloadInputField::Url, vec![url.to_string()]);
+        self
+    }
+
+    /// 预先填入多个URL，每行一个
+    pub fn with_urls(mut self, urls: &[String]) -> Self {
+        self.form.set_text(&DownloadInputField::Url, urls.to_vec());
+        self
+    }
 
-    pub fn set_mode(&mut self, mode: InputMode) {
-        self.mode = mode;
+    /// 预先填入请求头，`headers`为空时保持不变
+    pub fn with_headers(mut self, headers: &[(String, String)]) -> Self {
+        if !headers.is_empty() {
+            self.form.set_text(
+                &DownloadInputField::Headers,
+                headers
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// 预先填入保存路径，同时将焦点移动到保存路径的输入框
+    pub fn with_save_as(mut self, save_as: &str) -> Self {
+        self.form
+            .set_text(&DownloadInputField::SaveAs, vec![save_as.to_string()]);
+        self.form.set_focus(&DownloadInputField::SaveAs);
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn focus(&self) -> Option<DownloadInputField> {
+        self.form.focused().copied()
+    }
+
+    pub fn mode(&self) -> &InputMode {
+        self.form.mode()
+    }
+
+    /// 显示所有输入框需要的最少行数
+    pub fn height(&self) -> u16 {
+        self.form.height()
     }
 
     // -------------------- HANDLE_MESSAGE --------------------
 
-    fn comfirm_inner(self: Box<Self>, app: &mut App) {
-        let lines = self.input.into_lines();
-        for line in lines {
-            let line = line.trim().to_string();
-            if line.is_empty() {
-                continue;
+    /// 添加新的任务并且URL较多时，需要先确认要添加哪些URL
+    fn needs_review(&self) -> bool {
+        self.editing.is_none() && needs_review(self.form.lines(&DownloadInputField::Url))
+    }
+
+    /// 添加在[`BatchReview`]中确认过的URL，其他选项与直接提交时相同
+    pub(super) fn submit_urls(&self, app: &mut App, urls: Vec<(String, Option<Checksum>)>) {
+        // 打开确认窗口之前已经校验过
+        let template = parse_template(self.form.lines(&DownloadInputField::SaveAs));
+        let headers = parse_headers(self.form.lines(&DownloadInputField::Headers));
+        let file_mode = parse_file_mode(self.form.lines(&DownloadInputField::FileMode));
+        let range = parse_range(self.form.lines(&DownloadInputField::Range));
+        if let (Ok(template), Ok(headers), Ok(file_mode), Ok(range)) =
+            (template, headers, file_mode, range)
+        {
+            self.comfirm_inner(app, urls, template, headers, file_mode, range);
+        }
+    }
+
+    /// 解析所有输入框，出错时错误显示在对应的输入框上
+    fn confirm(mut self: Box<Self>, app: &mut App) -> Result<(), Box<Self>> {
+        // URL较多时校验跳过URL，留给确认窗口；修改任务时不会打开确认窗口，因此在这里报告
+        let urls = match parse_urls(self.form.lines(&DownloadInputField::Url)) {
+            Ok(urls) => urls,
+            Err(e) => {
+                self.form.set_error(&DownloadInputField::Url, Some(e));
+                self.form.set_focus(&DownloadInputField::Url);
+                return Err(self);
+            }
+        };
+        let parsed = (
+            parse_template(self.form.lines(&DownloadInputField::SaveAs)),
+            parse_headers(self.form.lines(&DownloadInputField::Headers)),
+            parse_file_mode(self.form.lines(&DownloadInputField::FileMode)),
+            parse_range(self.form.lines(&DownloadInputField::Range)),
+        );
+        let (Ok(template), Ok(headers), Ok(file_mode), Ok(range)) = parsed else {
+            return Err(self);
+        };
+        if self.editing.is_some() && urls.len() != 1 {
+            self.form.set_error(
+                &DownloadInputField::Url,
+                Some(String::from("Exactly one URL is required when editing")),
+            );
+            self.form.set_focus(&DownloadInputField::Url);
+            return Err(self);
+        }
+        self.comfirm_inner(app, urls, template, headers, file_mode, range);
+        Ok(())
+    }
+
+    fn comfirm_inner(
+        &self,
+        app: &mut App,
+        urls: Vec<(String, Option<Checksum>)>,
+        template: Option<NameTemplate>,
+        headers: Vec<(String, String)>,
+        file_mode: Option<FileMode>,
+        range: Option<ByteRange>,
+    ) {
+        let save_as = self
+            .form
+            .lines(&DownloadInputField::SaveAs)
+            .first()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from);
+        let save_as = if template.is_none() && save_as.is_some() && urls.len() > 1 {
+            log::warn!(target:"App", "Save as is ignored when adding multiple URLs");
+            None
+        } else {
+            save_as
+        };
+
+        // 序号只在同一批URL中递增，修改任务时沿用原来的序号
+        let first_n = match self.editing {
+            Some(EditTarget::Task { n, .. }) => n,
+            _ => 1,
+        };
+        for (idx, (url, checksum)) in urls.into_iter().enumerate() {
+            let save_as = match &template {
+                Some(template) => Some(SaveAs::Template {
+                    template: template.clone(),
+                    n: first_n + idx,
+                }),
+                None => save_as.clone().map(SaveAs::Path),
+            };
+            let options = RequestOptions::new(url, save_as)
+                .with_checksum(checksum)
+                .with_headers(headers.clone())
+                .with_extract(self.extract)
+                .with_redownload(self.redownload)
+                .with_file_mode(file_mode)
+                .with_range(range);
+            match &self.editing {
+                Some(EditTarget::Task { id, .. }) => {
+                    let (download_list, _, _, _, notices) = app.destruct_data();
+                    if !download_list.update_task_options(*id, options) {
+                        notices.push(format!(
+                            "Task {} has already started, changes discarded",
+                            id
+                        ));
+                    }
+                }
+                Some(EditTarget::Template { name }) => {
+                    if !app.download_list_mut().update_template(name, options) {
+                        app.notify(format!("Saved download {} no longer exists", name));
+                    }
+                }
+                None => {
+                    DownloadList::respond_to_message(
+                        app,
+                        DownloadListMessage::AppendNewTask(options),
+                    );
+                }
             }
-            DownloadList::respond_to_message(app, DownloadListMessage::AppendNewTask(line));
         }
     }
 
@@ -77,17 +396,36 @@ impl DownloadInput {
     }
 
     fn get_key_message(&mut self, key: KeyEvent) -> Option<DownloadInputMessage> {
-        match self.mode {
+        match self.form.mode() {
             InputMode::Normal => match key.code {
                 KeyCode::Char('e') | KeyCode::Char('a') | KeyCode::Char('i') => {
                     Some(DownloadInputMessage::StartEditing)
                 }
                 KeyCode::Enter => Some(DownloadInputMessage::Confirm),
+                KeyCode::Char('x') => Some(DownloadInputMessage::ToggleExtract),
+                KeyCode::Char('r') => Some(DownloadInputMessage::ToggleRedownload),
+                KeyCode::Tab => Some(DownloadInputMessage::SwitchFocus),
+                KeyCode::BackTab => Some(DownloadInputMessage::SwitchFocusBack),
                 KeyCode::Char('q') => Some(DownloadInputMessage::Quit),
                 _ => None,
             },
             InputMode::Editing => match key.code {
                 KeyCode::Esc => Some(DownloadInputMessage::StopEditing),
+                KeyCode::Tab => Some(DownloadInputMessage::SwitchFocus),
+                KeyCode::BackTab => Some(DownloadInputMessage::SwitchFocusBack),
+                // 保存路径、权限以及范围只有一行
+                KeyCode::Enter
+                    if matches!(
+                        self.focus(),
+                        Some(
+                            DownloadInputField::SaveAs
+                                | DownloadInputField::FileMode
+                                | DownloadInputField::Range
+                        )
+                    ) =>
+                {
+                    None
+                }
                 _ => Some(DownloadInputMessage::Input(key)),
             },
         }
@@ -96,30 +434,47 @@ impl DownloadInput {
 
 impl Widget for &mut DownloadInput {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        Clear.render(area, buf);
-        let area =
-            common::render_border(Some(Line::from("Download")), None, Style::new(), area, buf);
-
-        let [hint_area, input_area] =
-            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
-        Paragraph::new("URL:")
-            .left_aligned()
-            .bold()
-            .render(hint_area, buf);
-
-        let border_text = Line::from("input");
-        let block = Block::new()
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded);
-        let block = match self.mode {
-            InputMode::Normal => block.title(border_text.reset_style()),
-            InputMode::Editing => block
-                .title(border_text.italic())
-                .border_style(DownloadInput::INPUT_BOARDER_HIGHLIGHT_STYLE),
-        };
+        let preview = self.url_preview();
+        self.form.set_note(&DownloadInputField::Url, preview);
+        let toggle = |on: bool| if on { "on" } else { "off" };
+        self.form.set_footer(Some(
+            Line::from(format!(
+                "r: re-download [{}]  x: extract archives [{}]",
+                toggle(self.redownload),
+                toggle(self.extract)
+            ))
+            .right_aligned(),
+        ));
+        self.form.render(area, buf);
+    }
+}
 
-        self.input.set_block(block);
-        self.input.render(input_area, buf);
+impl DownloadInput {
+    /// 编辑URL时，预览光标所在行实际会请求的地址，或者解析错误
+    fn url_preview(&self) -> Option<Line<'static>> {
+        if !self.form.is_editing() || self.focus() != Some(DownloadInputField::Url) {
+            return None;
+        }
+        let line = self.form.cursor_line(&DownloadInputField::Url)?;
+        if line.trim().is_empty() {
+            return None;
+        }
+        let (url, checksum) = match split_checksum(line) {
+            Ok(parsed) => parsed,
+            Err(e) => return Some(Line::from(e.to_string()).red()),
+        };
+        let mut preview = match address::normalize_url(url) {
+            Ok(url) if address::is_idn(&url) => Line::from(vec![
+                Span::from(format!("will request: {} ", url)).dim(),
+                Span::from("[IDN]").yellow(),
+            ]),
+            Ok(url) => Line::from(format!("will request: {}", url)).dim(),
+            Err(e) => return Some(Line::from(e.to_string()).red()),
+        };
+        if let Some(checksum) = checksum {
+            preview.push_span(Span::from(format!(" [{}]", checksum.algorithm())).green());
+        }
+        Some(preview)
     }
 }
 
@@ -133,19 +488,46 @@ impl WidgetExt for DownloadInput {
     ) -> MessageTransfer<Self> {
         match message {
             DownloadInputMessage::StartEditing => {
-                self.as_mut().set_mode(InputMode::Editing);
+                self.form.set_mode(InputMode::Editing);
                 MessageTransfer::keep(self)
             }
             DownloadInputMessage::StopEditing => {
-                self.set_mode(InputMode::Normal);
+                self.form.set_mode(InputMode::Normal);
                 MessageTransfer::keep(self)
             }
             DownloadInputMessage::Confirm => {
-                self.comfirm_inner(app);
-                MessageTransfer::new()
+                if !self.form.validate() {
+                    return MessageTransfer::keep(self);
+                }
+                if self.needs_review() {
+                    let lines = self.form.lines(&DownloadInputField::Url).to_vec();
+                    return MessageTransfer::replace(WidgetType::BatchReview(Box::new(
+                        BatchReview::new(self, &lines),
+                    )));
+                }
+                match self.confirm(app) {
+                    Ok(()) => MessageTransfer::new(),
+                    Err(this) => MessageTransfer::keep(this),
+                }
             }
             DownloadInputMessage::Input(key) => {
-                self.input.input(key);
+                self.form.input(key);
+                MessageTransfer::keep(self)
+            }
+            DownloadInputMessage::SwitchFocus => {
+                self.form.focus_next();
+                MessageTransfer::keep(self)
+            }
+            DownloadInputMessage::SwitchFocusBack => {
+                self.form.focus_prev();
+                MessageTransfer::keep(self)
+            }
+            DownloadInputMessage::ToggleExtract => {
+                self.extract = !self.extract;
+                MessageTransfer::keep(self)
+            }
+            DownloadInputMessage::ToggleRedownload => {
+                self.redownload = !self.redownload;
                 MessageTransfer::keep(self)
             }
             DownloadInputMessage::Quit => MessageTransfer::new(),
@@ -153,10 +535,170 @@ impl WidgetExt for DownloadInput {
     }
 }
 
+/// 非空的行数达到[`BatchReview::MIN_URLS`]时，提交之前需要确认
+fn needs_review(lines: &[String]) -> bool {
+    lines.iter().filter(|line| !line.trim().is_empty()).count() >= BatchReview::MIN_URLS
+}
+
+/// 解析每一行的URL以及可选的校验和，出错时返回带有行号的错误信息
+fn parse_urls(lines: &[String]) -> Result<Vec<(String, Option<Checksum>)>, String> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(row, line)| {
+            split_checksum(line)
+                .map(|(url, checksum)| (url.to_string(), checksum))
+                .map_err(|e| format!("Line {}: {}", row + 1, e))
+        })
+        .collect()
+}
+
+/// 解析"Save as"中的命名模板，不是模板时返回`Ok(None)`
+fn parse_template(lines: &[String]) -> Result<Option<NameTemplate>, String> {
+    let Some(line) = lines.first().map(|line| line.trim()) else {
+        return Ok(None);
+    };
+    if !NameTemplate::is_template(line) {
+        return Ok(None);
+    }
+    NameTemplate::parse(line)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// 解析请求头，每行一个`Name: value`
+fn parse_headers(lines: &[String]) -> Result<Vec<(String, String)>, String> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(row, line)| {
+            parse_header(line).map_err(|e| format!("Header line {}: {}", row + 1, e))
+        })
+        .collect()
+}
+
+/// 解析下载完成的文件的权限，为空时返回`Ok(None)`
+fn parse_file_mode(lines: &[String]) -> Result<Option<FileMode>, String> {
+    match lines.first().map(|line| line.trim()) {
+        None | Some("") => Ok(None),
+        Some(line) => line
+            .parse()
+            .map(Some)
+            .map_err(|e: InvalidFileMode| e.to_string()),
+    }
+}
+
+/// 解析部分下载的字节范围，为空时返回`Ok(None)`
+fn parse_range(lines: &[String]) -> Result<Option<ByteRange>, String> {
+    match lines.first().map(|line| line.trim()) {
+        None | Some("") => Ok(None),
+        Some(line) => line
+            .parse()
+            .map(Some)
+            .map_err(|e: InvalidByteRange| e.to_string()),
+    }
+}
+
+/// 将一行输入拆分为URL和可选的校验和，二者之间使用空白字符分隔
+///
+/// 校验和总是包含`:`，最后一段没有`:`时整行都是URL，例如路径中有未编码的空格。
+pub(super) fn split_checksum(line: &str) -> Result<(&str, Option<Checksum>), ChecksumError> {
+    let line = line.trim();
+    match line.rsplit_once(char::is_whitespace) {
+        Some((url, checksum)) if checksum.contains(':') => {
+            Ok((url.trim_end(), Some(checksum.parse()?)))
+        }
+        _ => Ok((line, None)),
+    }
+}
+
+/// 解析一行`Name: value`格式的请求头
+fn parse_header(line: &str) -> Result<(String, String), String> {
+    let (name, value) = line
+        .split_once(':')
+        .ok_or_else(|| String::from("expected \"Name: value\""))?;
+    let (name, value) = (name.trim(), value.trim());
+    HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid name {:?}", name))?;
+    HeaderValue::from_str(value).map_err(|_| format!("invalid value for {}", name))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
 pub enum DownloadInputMessage {
     StartEditing,
     StopEditing,
     Confirm,
     Input(KeyEvent),
+    SwitchFocus,
+    SwitchFocusBack,
+    ToggleExtract,
+    ToggleRedownload,
     Quit,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::app::about::BuildInfo;
+    use crate::app::config::{Config, LiveConfig};
+    use crate::app::session::Session;
+    use crate::app::task::{ConnectionLimits, RuntimeStats};
+    use crate::app::throttle::{SpeedLimit, Throttle};
+
+    fn app() -> App {
+        let config = Config::default();
+        let build_info = BuildInfo::collect(&config);
+        let (_, startup) = std::sync::mpsc::channel();
+        App::new(
+            startup,
+            Arc::new(RuntimeStats::new()),
+            Arc::new(Throttle::new(SpeedLimit::UNLIMITED, Vec::new())),
+            Arc::new(ConnectionLimits::unlimited()),
+            LiveConfig::new(config),
+            Session::default(),
+            build_info,
+        )
+    }
+
+    /// `BatchReview::MIN_URLS`行URL，其中第3行的校验和无效
+    fn batch_with_invalid_line() -> Vec<String> {
+        (0..BatchReview::MIN_URLS)
+            .map(|i| match i {
+                2 => String::from("https://example.com/2.bin sha256:nothex"),
+                _ => format!("https://example.com/{}.bin", i),
+            })
+            .collect()
+    }
+
+    fn url_error(transfer: &MessageTransfer<DownloadInput>) -> Option<&str> {
+        let input = transfer.boxed_widget.as_ref()?;
+        input.form.field(&DownloadInputField::Url)?.error()
+    }
+
+    #[test]
+    fn large_batch_is_reviewed_before_validation() {
+        let mut app = app();
+        let input = Box::new(DownloadInput::new().with_urls(&batch_with_invalid_line()));
+        let transfer = input.respond_to_message(DownloadInputMessage::Confirm, &mut app);
+        assert!(matches!(
+            transfer.new_widget,
+            Some(WidgetType::BatchReview(_))
+        ));
+    }
+
+    #[test]
+    fn large_batch_reports_invalid_lines_when_editing() {
+        let mut app = app();
+        let options = RequestOptions::new(String::from("https://example.com/a.bin"), None);
+        let input = DownloadInput::from_options(&options)
+            .editing(TaskId::new(1), &options)
+            .with_urls(&batch_with_invalid_line());
+        let transfer = Box::new(input).respond_to_message(DownloadInputMessage::Confirm, &mut app);
+        assert!(transfer.new_widget.is_none());
+        let error = url_error(&transfer).expect("the invalid line is not reported");
+        assert!(error.starts_with("Line 3:"), "{}", error);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashSet;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph, Widget};
+
+use crate::app::App;
+use crate::app::address;
+use crate::app::bulk::BulkSummary;
+use crate::app::checksum::Checksum;
+use crate::window::WidgetType;
+use crate::window::common::{self, ItemList, MessageTransfer, WidgetExt};
+use crate::window::download::DownloadInput;
+use crate::window::download::input::split_checksum;
+
+/// 一次添加多个URL时，提交之前确认要添加哪些URL
+///
+/// ```text
+/// ╭Review URLs (2/3 selected)──────────────────╮
+/// │[x] https://example.com/a.iso               │
+/// │────────────────────────────────────────────│
+/// │[ ] https://example.com/b.iso               │
+/// │────────────────────────────────────────────│
+/// │[!] htp:/junk  (unsupported scheme: htp)    │
+/// ╰Space: toggle  a: all  Enter: add  Esc: back╯
+/// ```
+///
+/// 无效的行默认不选中，也不能被选中。已经在下载列表中或者重复出现的URL在添加时跳过，
+/// 添加之后在状态栏中显示添加以及跳过的数量。按Esc回到下载窗口，已经输入的内容保持不变。
+/// URL超过[`BatchReview::PAGE_SIZE`]个时分页显示，使用`←`和`→`翻页。
+///
+/// 也用于选择从完成列表中HTML页面里找到的链接，见[`WidgetType::new_link_review`]。
+pub struct BatchReview {
+    title: String,
+    input: Box<DownloadInput>,
+    entries: Vec<ReviewEntry>,
+    page: usize,
+    list: ItemList,
+}
+
+/// 待确认的一行URL
+#[derive(Debug, Clone)]
+pub struct ReviewEntry {
+    pub line: String,
+    pub parsed: Result<(String, Option<Checksum>), String>,
+    pub checked: bool,
+}
+
+impl ReviewEntry {
+    // -------------------- CONSTRUCT ---------------------
+
+    /// 与下载窗口使用相同的规则检查URL以及校验和，有效时默认选中
+    pub fn parse(line: &str) -> Self {
+        let parsed = split_checksum(line)
+            .map_err(|e| e.to_string())
+            .and_then(|(url, checksum)| match address::normalize_url(url) {
+                Ok(_) => Ok((url.to_string(), checksum)),
+                Err(e) => Err(e.to_string()),
+            });
+        ReviewEntry {
+            line: line.trim().to_string(),
+            checked: parsed.is_ok(),
+            parsed,
+        }
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn is_valid(&self) -> bool {
+        self.parsed.is_ok()
+    }
+}
+
+impl BatchReview {
+    // ------------------- CONSTANT -----------------------
+
+    /// 至少显示一行URL以及边框上的标题和按键提示
+    pub const MIN_WIDTH: u16 = 30;
+    pub const MIN_HEIGHT: u16 = 5;
+
+    /// 输入的URL达到该数量时，提交之前需要确认
+    pub const MIN_URLS: usize = 10;
+    /// 每一页最多显示的URL数量，避免一次构建过多的组件
+    pub const PAGE_SIZE: usize = 1000;
+
+    const BORDER_STYLE: Style = Style::new().fg(Color::LightBlue);
+
+    // -------------------- CONSTRUCT ---------------------
+
+    /// `lines`为URL输入框中的所有行，空行会被忽略
+    pub fn new(input: Box<DownloadInput>, lines: &[String]) -> Self {
+        let entries: Vec<ReviewEntry> = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| ReviewEntry::parse(line))
+            .collect();
+        let mut list = ItemList::new(1);
+        list.select_first_if_none(entries.len().min(Self::PAGE_SIZE));
+        BatchReview {
+            title: String::from("Review URLs"),
+            input,
+            entries,
+            page: 0,
+            list,
+        }
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// 有效的行是否默认选中
+    pub fn with_checked(mut self, checked: bool) -> Self {
+        for entry in &mut self.entries {
+            entry.checked = checked && entry.is_valid();
+        }
+        self
+    }
+
+    // ------------------ MEMBER_ACCESS --------------------
+
+    pub fn entries(&self) -> &[ReviewEntry] {
+        &self.entries
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.entries.iter().filter(|e| e.checked).count()
+    }
+
+    fn page_count(&self) -> usize {
+        self.entries.len().div_ceil(Self::PAGE_SIZE).max(1)
+    }
+
+    fn page_entries(&self) -> &[ReviewEntry] {
+        let start = self.page * Self::PAGE_SIZE;
+        let end = (start + Self::PAGE_SIZE).min(self.entries.len());
+        &self.entries[start..end]
+    }
+
+    /// 选中项在所有URL中的下标
+    fn selected_entry(&self) -> Option<usize> {
+        self.list
+            .selected()
+            .map(|i| self.page * Self::PAGE_SIZE + i)
+            .filter(|&i| i < self.entries.len())
+    }
+
+    // -------------------- MODIFIER -----------------------
+
+    /// 切换选中项是否添加，无效的行不能被选中
+    pub fn toggle_selected(&mut self) {
+        if let Some(i) = self.selected_entry()
+            && self.entries[i].is_valid()
+        {
+            self.entries[i].checked = !self.entries[i].checked;
+        }
+    }
+
+    /// 有效的行中还有没选中的时选中所有有效的行，否则全部取消
+    pub fn toggle_all(&mut self) {
+        let check = self.entries.iter().any(|e| e.is_valid() && !e.checked);
+        for entry in &mut self.entries {
+            entry.checked = check && entry.is_valid();
+        }
+    }
+
+    fn turn_page(&mut self, forward: bool) {
+        let page = if forward {
+            (self.page + 1).min(self.page_count() - 1)
+        } else {
+            self.page.saturating_sub(1)
+        };
+        if page != self.page {
+            self.page = page;
+            self.list = ItemList::new(1);
+            self.list.select_first_if_none(self.page_entries().len());
+        }
+    }
+
+    // -------------------- HANDLE_MESSAGE --------------------
+
+    pub fn handle_key_event(self: Box<Self>, key: KeyEvent, app: &mut App) -> Vec<WidgetType> {
+        self.key_event_handler(key, app, Self::get_key_message, WidgetType::BatchReview)
+    }
+
+    fn get_key_message(&mut self, key: KeyEvent) -> Option<BatchReviewMessage> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(BatchReviewMessage::GoUp),
+            KeyCode::Down | KeyCode::Char('j') => Some(BatchReviewMessage::GoDown),
+            KeyCode::PageUp => Some(BatchReviewMessage::PageUp),
+            KeyCode::PageDown => Some(BatchReviewMessage::PageDown),
+            KeyCode::Left | KeyCode::Char('h') => Some(BatchReviewMessage::TurnPage(false)),
+            KeyCode::Right | KeyCode::Char('l') => Some(BatchReviewMessage::TurnPage(true)),
+            KeyCode::Char(' ') => Some(BatchReviewMessage::Toggle),
+            KeyCode::Char('a') => Some(BatchReviewMessage::ToggleAll),
+            KeyCode::Enter => Some(BatchReviewMessage::Confirm),
+            KeyCode::Esc | KeyCode::Char('q') => Some(BatchReviewMessage::Back),
+            _ => None,
+        }
+    }
+}
+
+impl Widget for &mut BatchReview {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let title = Line::from(format!(
+            "{} ({}/{} selected)",
+            common::display_sanitize(&self.title),
+            self.selected_count(),
+            self.entries.len()
+        ));
+        let mut footer = String::from("Space: toggle  a: all  Enter: add  Esc: back");
+        if self.page_count() > 1 {
+            footer = format!(
+                "←/→: page {}/{}  {}",
+                self.page + 1,
+                self.page_count(),
+                footer
+            );
+        }
+        let area = common::render_border(
+            Some(title),
+            Some(Line::from(footer).right_aligned()),
+            BatchReview::BORDER_STYLE,
+            area,
+            buf,
+        );
+
+        let start = self.page * BatchReview::PAGE_SIZE;
+        let end = (start + BatchReview::PAGE_SIZE).min(self.entries.len());
+        let rows = self.entries[start..end].iter().map(ReviewRow);
+        self.list.render(rows, false, true, area, buf);
+    }
+}
+
+/// 列表中的一行，状态为是否被选中
+#[derive(Clone, Copy)]
+struct ReviewRow<'a>(&'a ReviewEntry);
+
+impl StatefulWidget for ReviewRow<'_> {
+    type State = bool;
+
+    fn render(self, area: Rect, buf: &mut Buffer, selected: &mut bool) {
+        let entry = self.0;
+        let line = common::display_sanitize(&entry.line).into_owned();
+        let mut spans = match &entry.parsed {
+            Ok(_) => vec![
+                Span::from(if entry.checked { "[x] " } else { "[ ] " }),
+                Span::from(line),
+            ],
+            Err(e) => vec![
+                Span::from("[!] ").red(),
+                Span::from(line).red(),
+                Span::from(format!("  ({})", e)).dim(),
+            ],
+        };
+        if !entry.checked && entry.is_valid() {
+            spans[1] = spans[1].clone().dim();
+        }
+        let style = if *selected {
+            Style::new().bg(Color::LightBlue).fg(Color::Black)
+        } else {
+            Style::new()
+        };
+        Paragraph::new(Line::from(spans))
+            .style(style)
+            .render(area, buf);
+    }
+}
+
+impl WidgetExt for BatchReview {
+    type Message = BatchReviewMessage;
+
+    fn respond_to_message(
+        mut self: Box<Self>,
+        message: BatchReviewMessage,
+        app: &mut App,
+    ) -> MessageTransfer<Self> {
+        let len = self.page_entries().len();
+        match message {
+            BatchReviewMessage::GoUp => self.list.select_previous(len),
+            BatchReviewMessage::GoDown => self.list.select_next(len),
+            BatchReviewMessage::PageUp => self.list.select_page_up(len),
+            BatchReviewMessage::PageDown => self.list.select_page_down(len),
+            BatchReviewMessage::TurnPage(forward) => self.turn_page(forward),
+            BatchReviewMessage::Toggle => self.toggle_selected(),
+            BatchReviewMessage::ToggleAll => self.toggle_all(),
+            BatchReviewMessage::Confirm => {
+                let download_list = app.download_list();
+                let (urls, summary) = select_urls(self.entries, |url| download_list.is_queued(url));
+                log::info!(target:"App", "Adding {} reviewed URL(s)", urls.len());
+                self.input.submit_urls(app, urls);
+                app.notify(summary.to_string());
+                return MessageTransfer::new();
+            }
+            BatchReviewMessage::Back => {
+                return MessageTransfer::replace(WidgetType::DownloadInput(self.input));
+            }
+        }
+        MessageTransfer::keep(self)
+    }
+}
+
+/// 选出需要添加的URL，同时统计跳过的行：无效的、没有选中的，以及重复的URL，
+/// 即`is_queued`返回`true`（已经在下载列表中）或者在同一批中已经出现过的URL
+pub fn select_urls(
+    entries: Vec<ReviewEntry>,
+    is_queued: impl Fn(&str) -> bool,
+) -> (Vec<(String, Option<Checksum>)>, BulkSummary) {
+    let mut summary = BulkSummary::new("Queued");
+    let mut checked = Vec::new();
+    for entry in entries {
+        match entry.parsed {
+            Err(_) => summary.skip("invalid"),
+            Ok(_) if !entry.checked => summary.skip("deselected"),
+            Ok(parsed) => checked.push(parsed),
+        }
+    }
+    let mut seen = HashSet::new();
+    let mut urls = Vec::new();
+    for (url, checksum) in checked {
+        if is_queued(&url) || !seen.insert(url.clone()) {
+            log::debug!(target:"App", "Skipped duplicate URL {}", url);
+            summary.skip("duplicates");
+            continue;
+        }
+        summary.done();
+        urls.push((url, checksum));
+    }
+    (urls, summary)
+}
+
+pub enum BatchReviewMessage {
+    GoUp,
+    GoDown,
+    PageUp,
+    PageDown,
+    /// 翻到下一页（`true`）或者上一页
+    TurnPage(bool),
+    Toggle,
+    ToggleAll,
+    Confirm,
+    Back,
+}
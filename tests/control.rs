@@ -0,0 +1,122 @@
+//! 使用`examples/request-tui-ctl.rs`中的客户端连接真实的控制端口，需要开启`control`特性
+//!
+//! ```text
+//! cargo test --features control --test control
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use request_tui::app::control::{ControlCommand, ControlResponse, ControlServer};
+
+#[allow(dead_code)]
+#[path = "../examples/request-tui-ctl.rs"]
+mod ctl;
+
+/// 每个测试使用各自的数据目录
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "request-tui-control-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// 代替UI线程回复收到的`count`个命令，返回这些命令，结束时关闭控制端口
+fn answer(server: ControlServer, count: usize) -> thread::JoinHandle<Vec<ControlCommand>> {
+    thread::spawn(move || {
+        let mut received = Vec::new();
+        while received.len() < count {
+            let Some(request) = server.try_recv() else {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            };
+            let response = match &request.command {
+                ControlCommand::Stop { id } => ControlResponse::Stopped { id: *id },
+                ControlCommand::Status => ControlResponse::Status {
+                    tasks: 2,
+                    active: 1,
+                    pending: 0,
+                    finished: 3,
+                    speed: 1024,
+                },
+                command => ControlResponse::Error {
+                    message: format!("unexpected {:?}", command),
+                },
+            };
+            request.reply.send(response).unwrap();
+            received.push(request.command);
+        }
+        received
+    })
+}
+
+fn run_ctl(data_dir: &Path, args: &[&str]) -> ControlResponse {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let command = ctl::parse(&args).unwrap();
+    let stream = ControlServer::connect_in(data_dir).unwrap();
+    let response = ctl::request(stream, &command).unwrap();
+    serde_json::from_str(&response).unwrap()
+}
+
+#[test]
+fn ctl_commands_reach_the_running_instance() {
+    let dir = data_dir("roundtrip");
+    let ui = answer(ControlServer::bind_in(&dir).unwrap(), 2);
+
+    assert_eq!(
+        run_ctl(&dir, &["status"]),
+        ControlResponse::Status {
+            tasks: 2,
+            active: 1,
+            pending: 0,
+            finished: 3,
+            speed: 1024,
+        }
+    );
+    let ControlResponse::Stopped { id } = run_ctl(&dir, &["stop", "#7"]) else {
+        panic!("stop was not acknowledged");
+    };
+    assert_eq!(id.to_string(), "#7");
+
+    let received = ui.join().unwrap();
+    assert_eq!(received[0], ControlCommand::Status);
+    // 关闭之后删除控制端口文件
+    assert!(!ControlServer::path_in(&dir).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn second_instance_cannot_take_over_the_socket() {
+    let dir = data_dir("second");
+    let first = ControlServer::bind_in(&dir).unwrap();
+    let error = ControlServer::bind_in(&dir).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+    drop(first);
+    // 上一个实例退出之后可以重新监听
+    let again = ControlServer::bind_in(&dir).unwrap();
+    drop(again);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_is_only_accessible_by_the_current_user() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = data_dir("permissions");
+    // 目录已经存在并且权限过于宽松时同样收紧
+    let control_dir = ControlServer::path_in(&dir).parent().unwrap().to_path_buf();
+    std::fs::create_dir_all(&control_dir).unwrap();
+    std::fs::set_permissions(&control_dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+
+    let server = ControlServer::bind_in(&dir).unwrap();
+    let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&control_dir), 0o700);
+    assert_eq!(mode(&ControlServer::path_in(&dir)), 0o600);
+    drop(server);
+    std::fs::remove_dir_all(&dir).unwrap();
+}